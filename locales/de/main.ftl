@@ -0,0 +1,476 @@
+# Deutsch
+
+## Menu bar
+
+menu-about = Über
+menu-ai = KI
+menu-ai-audit = 🧾 Audit-Protokoll...
+menu-ai-diagnostics = 🩺 Diagnose...
+menu-broadcast = 📡 An Sitzungen senden...
+menu-change-theme = 🎨 Design ändern...
+menu-clear-blocks = 🗑️ Blöcke entfernen...
+menu-copy = Kopieren
+menu-data-archive = 🧳 Alle Daten exportieren / importieren...
+menu-debug-overlay = 🐞 Debug-Anzeige (F12)
+menu-documentation = Dokumentation
+menu-edit = Bearbeiten
+menu-export = Exportieren...
+menu-file = Datei
+menu-file-browser = 🗂 Dateibrowser
+menu-git-panel = 🌿 Git-Leiste
+menu-help = Hilfe
+menu-history = 🕘 Verlauf
+menu-import-session = Sitzung importieren...
+menu-insert-divider = ➖ Trennlinie einfügen...
+menu-keyboard-shortcuts = Tastenkürzel
+menu-load-models = 📥 Modelle laden
+menu-logs = 📜 Protokolle
+menu-mode-ai = 🤖 Nur KI-Eingabe
+menu-mode-ai-hint = Immer mit KI in Befehle umwandeln
+menu-mode-hybrid = 🔀 Hybrid (automatisch erkennen)
+menu-mode-hybrid-hint = Natürliche Sprache und Befehle automatisch unterscheiden
+menu-mode-terminal = 🖥️ Nur Terminal
+menu-mode-terminal-hint = Immer als Shell-Befehle ausführen
+menu-model = Modell:
+menu-new-session = Neue Sitzung
+menu-no-plugin-panels = Keine Plugin-Bereiche
+menu-no-recent-sessions = Noch keine anderen Sitzungen
+menu-offline = ✈ Offline
+menu-offline-hint = Nur KI-Anbieter auf diesem Rechner werden verwendet; Synchronisierung über das Netzwerk, Teilen und Webhook-Hooks sind aus
+menu-open-session = Sitzung öffnen...
+menu-operation-mode = Betriebsmodus:
+menu-paste = Einfügen
+menu-plugin-dir-hint = Jedes Plugin ist hier ein Ordner mit einer plugin.toml
+menu-plugins = Plugins
+menu-presentation = 🎬 Präsentationsmodus
+menu-provider = Anbieter:
+menu-quick-jump = 📍 Zu Verzeichnis springen...
+menu-quit = Beenden
+menu-recent-sessions = Letzte Sitzungen
+menu-redact-secrets = 🙈 Geheimnisse schwärzen
+menu-redact-secrets-hint = Token, Schlüssel und Passwörter in der Ausgabe maskieren, z. B. bei Bildschirmfreigabe
+menu-reload-plugins = ⟳ Plugins neu laden
+menu-reload-scripts = 📜 Skripte neu laden
+menu-restore-backup = Aus Sicherung wiederherstellen...
+menu-save-session = Sitzung speichern
+menu-scheduled-commands = ⏰ Geplante Befehle...
+menu-session-settings = Sitzungseinstellungen...
+menu-settings = Einstellungen
+menu-share-session = Sitzung teilen...
+menu-split-horizontal = Horizontal teilen
+menu-split-vertical = Vertikal teilen
+menu-ssh-hosts = SSH-Hosts...
+menu-sync-offline = Der Offline-Modus ist aktiv
+menu-sync-sessions = ⇅ Sitzungen synchronisieren
+menu-sync-unconfigured = Konfigurieren Sie ein [sync]-Backend in der Konfigurationsdatei
+menu-toggle-ai-panel = KI-Bereich ein/aus
+menu-view = Ansicht
+menu-workflows = Workflows...
+menu-zoom-in = Vergrößern
+menu-zoom-out = Verkleinern
+
+## Blocks
+
+block-actions = Blockaktionen
+block-add-to-ai-context = 🤖 Immer in den KI-Kontext aufnehmen
+block-always-in-ai-context = Wird immer als KI-Kontext gesendet
+block-cancel-suggestion = ✕ Abbrechen (Esc)
+block-collapse = Block einklappen
+block-copy-both = 📑 Beides kopieren
+block-copy-command = 📋 Befehl kopieren
+block-copy-image = 🖼 Als Bild kopieren
+block-copy-output = 📄 Ausgabe kopieren
+block-copy-result = 📋 Ergebnis kopieren
+block-copy-result-hint = Klicken, um das Ergebnis zu kopieren
+block-delete = 🗑️ Block löschen
+block-diff-environment = 🔀 Umgebung vergleichen...
+block-edit-rerun = ✏️ Bearbeiten und erneut ausführen
+block-edit-suggestion = ✎ Bearbeiten (E)
+block-execute-suggestion = ✓ Ausführen (Enter)
+block-execute-suggestion-hint = Vorgeschlagenen Befehl ausführen: { $command }
+block-expand = Block ausklappen
+block-fix-with-ai = 🔧 Mit KI korrigieren
+block-regenerate-suggestion = ↻ Neu erzeugen (R)
+block-remove-calculation = 🗑 Entfernen
+block-remove-divider = 🗑 Trennlinie entfernen
+block-remove-from-ai-context = 🤖 Nicht mehr in den KI-Kontext aufnehmen
+block-rerun-every = ⟳ Erneut ausführen alle
+block-retry = ↻ Wiederholen
+block-retry-backoff-hint = Bis zu { $count }-mal wiederholen, nach jedem Fehlschlag mit längerer Pause
+block-retry-hint = Der Fehler scheint vorübergehend zu sein; Befehl erneut ausführen
+block-retry-scheduled = ↻ Versuch { $attempt }/{ $max } in { $seconds } s
+block-share = 🔗 Teilen...
+block-stop-retrying = Wiederholen beenden
+block-stop-watching = ⏹ Beobachtung beenden
+block-stop-watching-hint = Beobachtung beenden
+
+## Calculator
+
+calc-error-division-by-zero = Division durch null
+calc-error-empty = Nichts zu berechnen
+calc-error-ends-early = Der Ausdruck endet zu früh
+calc-error-expected = „{ $expected }“ erwartet
+calc-error-expected-found = „{ $expected }“ erwartet, aber „{ $found }“ gefunden
+calc-error-invalid-number = Ungültige Zahl „{ $number }“
+calc-error-needs-arguments = { $name }() benötigt Argumente
+calc-error-not-finite = Das Ergebnis ist keine endliche Zahl
+calc-error-one-argument = { $name }() nimmt genau ein Argument
+calc-error-unexpected = Unerwartetes „{ $token }“
+calc-error-unknown-function = Unbekannte Funktion „{ $name }“
+calc-error-unknown-name = Unbekannter Name „{ $name }“
+
+## Session context menu
+
+context-change-theme = 🎨 Design ändern
+context-clear-blocks = 🗑️ Alle Blöcke entfernen...
+context-export-session = 📤 Sitzung exportieren
+context-open-session = 📂 Sitzung öffnen
+
+## Dialog titles
+
+dialog-ai-audit = 🧾 KI-Audit-Protokoll
+dialog-ai-diagnostics = 🩺 KI-Diagnose
+dialog-broadcast = 📡 An Sitzungen senden
+dialog-clear-blocks = 🗑️ Blöcke entfernen
+dialog-confirm-command = ⚠ Befehl bestätigen
+dialog-crash-report = ⚠ Absturzbericht
+dialog-data-archive = 🧳 Alle Daten exportieren / importieren
+dialog-environment-diff = 🔀 Umgebungsvergleich
+dialog-export-session = 📤 Sitzung exportieren
+dialog-history = 🕘 Verlauf
+dialog-import-session = 📥 Sitzung importieren
+dialog-insert-divider = ➖ Trennlinie einfügen
+dialog-insert-path = 📂 Pfad einfügen
+dialog-keyboard-shortcuts = ⌨ Tastenkürzel
+dialog-logs = 📜 Protokolle
+dialog-new-session = ✨ Neue Sitzung
+dialog-open-session = 📂 Sitzung öffnen
+dialog-quick-jump = 📍 Zu Verzeichnis springen
+dialog-restore-backup = 🗄 Aus Sicherung wiederherstellen
+dialog-scheduled-commands = ⏰ Geplante Befehle
+dialog-select-theme = 🎨 Design auswählen
+dialog-settings = ⚙ Einstellungen
+dialog-share = 🔗 Teilen
+dialog-ssh-hosts = 🖧 SSH-Hosts
+dialog-trust-workspace = 🛡 Diesem Ordner vertrauen?
+dialog-workflows = 📚 Workflows
+
+## Settings dialog
+
+settings-after-restart = Wirksam nach Speichern und Neustart
+settings-apply-now = 🧹 Jetzt anwenden
+settings-applying-retention = Aufbewahrungsregeln werden angewendet…
+settings-archive = Entfernte Blöcke im Datenverzeichnis archivieren
+settings-backup-disabled = 0 deaktiviert automatische Sicherungen
+settings-backup-interval = Datenbank sichern alle
+settings-backups-kept = Aufbewahrte Sicherungen
+settings-blocks-removed = { $count ->
+    [one] 1 Block entfernt
+   *[other] { $count } Blöcke entfernt
+}
+settings-compact = 🗜 Datenbank verdichten
+settings-compact-hint = VACUUM ausführen, um Platz gelöschter Blöcke freizugeben
+settings-compacted = Komprimiert: { $before } → { $after }
+settings-compacting = Datenbank wird komprimiert…
+settings-compaction-failed = Komprimierung fehlgeschlagen: { $error }
+settings-database-compacted = Datenbank komprimiert
+settings-database-size = Datenbankgröße: { $size }
+settings-days = {" "}Tage
+settings-encrypt = Befehle und Ausgaben verschlüsseln (Schlüssel im Schlüsselbund des Systems)
+settings-encrypt-hint = Vorhandene Blöcke werden beim nächsten Start ver- oder entschlüsselt. Archivierte Blöcke werden mit demselben Schlüssel versiegelt.
+settings-encrypted = 🔒 Gespeicherte Blöcke sind verschlüsselt
+settings-environment-snapshot = Umgebungsvariablen mit jedem Befehl speichern
+settings-environment-snapshot-hint = Speichert { $variables } (snapshot_variables in der Konfigurationsdatei). Unverschlüsselt gespeichert.
+settings-highlight-diagnostics = Fehler und Warnungen in der Ausgabe hervorheben
+settings-highlight-diagnostics-hint = Färbt Zeilen wie error: und warning: und markiert sie neben der Bildlaufleiste langer Ausgaben
+settings-highlight-output = Erkannte Ausgabe farbig hervorheben
+settings-highlight-output-hint = Färbt die Ausgabe von cat config.yaml, JSON-Antworten, SQL, Rust und Python-Tracebacks nach Sprache
+settings-hours = {" "}Stunden
+settings-language = Sprache
+settings-max-block-age = Blöcke entfernen, die älter sind als
+settings-max-block-age-hint = 0 behält Blöcke für immer
+settings-max-blocks = Blöcke pro Sitzung
+settings-no-limit = 0 für keine Begrenzung
+settings-output-sections = Ausgabe von Build-Werkzeugen in Abschnitte gliedern
+settings-output-sections-hint = Jedes von cargo kompilierte Crate, npm-Skript und jede pytest-Datei erhält eine einklappbare Überschrift
+settings-retention-failed = Aufbewahrung fehlgeschlagen: { $error }
+settings-retention-note = Die Bereinigung läuft beim Start; mit „Jetzt anwenden“ sofort ausführen.
+settings-save-failed = Einstellungen konnten nicht gespeichert werden: { $error }
+settings-saved = Einstellungen gespeichert
+settings-state-patterns = Blockstatus mit Mustern statt Farben anzeigen
+settings-state-patterns-hint = Akzentbalken erhalten ein Muster je Status und eine einheitliche neutrale Farbe
+settings-storage = Speicher
+settings-trusted-directories = Vertrauenswürdige Ordner
+settings-unknown = unbekannt
+settings-wait-for-command = Warten Sie, bis der laufende Befehl beendet ist
+settings-workspace-trust = Vor dem Ausführen der Einrichtung in nicht vertrauenswürdigen Ordnern fragen
+settings-workspace-trust-hint = Startbefehle, die .env-Datei und direnv warten, bis Sie einem Ordner vertrauen, wenn eine Sitzung dort zum ersten Mal geöffnet wird
+
+## AI panel
+
+ai-ask = KI fragen:
+ai-assistant = 🤖 KI-Assistent
+ai-clear = Leeren
+ai-context = Kontext
+ai-conversation = Unterhaltung:
+ai-drop-files = Dateien hier ablegen, um sie anzuhängen
+ai-include-history = Befehlsverlauf einbeziehen
+ai-load-models = Modelle laden
+ai-overflow = Wenn sie nicht passen:
+ai-overflow-chunk = In Teilen
+ai-overflow-chunk-hint = Die nicht passenden Blöcke stückweise in zusätzlichen Anfragen befragen und die Notizen mitsenden
+ai-overflow-drop = Älteste weglassen
+ai-overflow-drop-hint = Die neuesten passenden Blöcke senden und ältere weglassen
+ai-overflow-failures = Nur Fehlschläge
+ai-overflow-failures-hint = Wenn nicht alle Blöcke passen, nur die fehlgeschlagenen senden
+ai-overflow-summarize = Zusammenfassen
+ai-overflow-summarize-hint = Die nicht passenden Blöcke in einer zusätzlichen Anfrage von der KI zusammenfassen lassen
+ai-prompt-hint = Ihre Frage...
+ai-receiving = Antwort wird empfangen...
+ai-recent-blocks = Letzte Blöcke:
+ai-response = Antwort:
+ai-send = Senden
+ai-send-hint = Anfrage an die KI senden (Enter)
+ai-timing = Laden { $load } s · Prompt { $prompt } s · Erzeugung { $generation } s
+ai-timing-hint = Zeit für das Laden des Modells, das Lesen des Prompts und das Erzeugen der Antwort
+ai-waiting-rate-limit = Warten auf das Ratenlimit ({ $count } in der Warteschlange)...
+ai-waiting-rate-limit-hint = Anfragen werden zurückgehalten, um die Limits des Anbieters einzuhalten
+
+## Broadcast
+
+broadcast-exit = { $session } (Exit-Code { $code })
+broadcast-failed = 📡 { $count } fehlgeschlagen
+broadcast-failed-in = Letzte Übertragung fehlgeschlagen in:
+broadcast-hint = Hier eingegebene Befehle laufen auch in den markierten Sitzungen und fügen dort jeweils einen Block hinzu.
+broadcast-running = 📡 { $count } laufen
+broadcast-targets = Eingegebene Befehle laufen auch in { $count ->
+    [one] einer weiteren Sitzung
+   *[other] { $count } weiteren Sitzungen
+}
+
+## Confirm command
+
+confirm-matches = Dieser Befehl passt auf „{ $pattern }“ und lässt sich nicht leicht rückgängig machen:
+confirm-remember-hint = Genau diesen Befehl in der Konfiguration merken
+
+## Workspace trust
+
+trust-direnv = ▲ direnv { $path }
+trust-folder-hint = Diesen Ordner und seine Unterordner in der Konfiguration merken
+trust-load-env = 🔑 { $path } laden
+trust-only-known = Vertrauen Sie nur Ordnern, deren Inhalt Sie kennen.
+trust-runs-setup = Diese Sitzung führt beim Öffnen automatisch die Einrichtung aus in
+
+## Open Session dialog
+
+open-session-add-folder = ➕ Ordner
+open-session-current = ▶ { $name } (aktuell)
+open-session-hint = Wählen Sie eine Sitzung zum Öffnen (ziehen Sie ⠿ auf einen Ordner, um sie zu verschieben):
+open-session-list-failed = Die Sitzungsliste konnte nicht geladen werden: { $error }
+open-session-move-to = Verschieben nach
+open-session-new-folder = Neuer Ordner
+open-session-ungrouped = Ohne Ordner
+open-session-ungrouped-count = Ohne Ordner ({ $count })
+open-session-updated = Aktualisiert: { $time }
+
+## New Session dialog
+
+new-session-create = ✅ Erstellen
+new-session-name-hint = Sitzungsname...
+new-session-name-taken = Eine Sitzung namens „{ $name }“ gibt es bereits
+new-session-prompt = Geben Sie einen Namen für die neue Sitzung ein:
+new-session-use-name = „{ $name }“ verwenden
+
+## Export dialog
+
+export-choose-format = Exportformat wählen:
+export-encrypted = 🔒 Als verschlüsseltes Archiv exportieren
+export-encrypted-intro = Verschlüsseltes Archiv, für Protokolle mit sensibler Ausgabe:
+export-json = 📄 Als JSON exportieren
+export-markdown = 📝 Als Markdown exportieren
+export-passphrase = Passphrase
+export-passphrase-confirm = Bestätigen
+export-passphrase-mismatch = Die Passphrasen stimmen nicht überein
+export-session-name = Sitzung exportieren: { $name }
+export-text = 📋 Als Text exportieren
+
+## Import dialog
+
+import-button = 📥 Importieren
+import-failed = Import fehlgeschlagen: { $error }
+import-file = Datei
+import-intro = Als JSON oder als verschlüsseltes Archiv exportierte Sitzung:
+import-passphrase = Passphrase
+import-passphrase-hint = nur für verschlüsselte Archive
+
+## File browser
+
+files-attach = 📎 An den KI-Kontext anhängen
+files-attached = 📎 { $name } angehängt
+files-cd-here = 📂 Hierher wechseln (cd)
+files-file-hint = Klicken fügt den Pfad ein; Rechtsklick für mehr
+files-insert-path = ⌨ Pfad einfügen
+files-more = … { $count } weitere
+files-open = ↗ Öffnen
+files-show-hidden = Versteckte Dateien anzeigen
+files-title = Dateien
+path-picker-filter = Filtern...
+path-picker-hint = Dateipfad einfügen ({ $key })
+path-picker-insert = Einfügen
+path-picker-insert-directory = Dieses Verzeichnis einfügen
+path-picker-parent = Übergeordnetes Verzeichnis
+
+## Dividers and clearing
+
+clear-blocks-confirm = { $count ->
+    [one] Den Block
+   *[other] Alle { $count } Blöcke
+} in „{ $session }“ löschen? Gelöschte Blöcke werden auch aus der Datenbank entfernt und lassen sich nicht wiederherstellen.
+clear-blocks-delete = Blöcke löschen
+clear-blocks-running-kept = Der laufende Befehl bleibt erhalten.
+divider-heading-hint = Überschrift
+divider-insert = Einfügen
+divider-prompt = Überschrift für den nächsten Abschnitt, z. B. „Auf Staging ausrollen“
+
+## Session settings
+
+session-color = Farbe:
+session-color-blue = Blau
+session-color-gray = Grau
+session-color-green = Grün
+session-color-orange = Orange
+session-color-purple = Lila
+session-color-red = Rot
+session-color-yellow = Gelb
+session-description = Beschreibung
+session-description-hint = Wofür diese Sitzung gedacht ist
+session-env-file = .env-Datei
+session-env-file-load = Laden
+session-env-file-unload = Entladen
+session-environment = Sitzungsumgebung
+session-no-color = Keine
+session-show-values = Werte anzeigen
+
+## Output triggers
+
+trigger-add = ➕ Auslöser hinzufügen
+trigger-command-hint = Befehl
+trigger-highlight-line = Zeile hervorheben
+trigger-hint = Wenn ein regulärer Ausdruck auf eine Zeile der Befehlsausgabe passt, eine Aktion auslösen.
+trigger-mark-block = Block markieren
+trigger-notification = Auslöser: { $command }
+trigger-notify = Benachrichtigen
+trigger-pattern-hint = Regex
+trigger-run-command = Befehl ausführen
+trigger-title = Ausgabe-Auslöser
+
+## Scheduled commands
+
+schedule-add = ➕ Planen
+schedule-command = Befehl:
+schedule-empty = Nichts geplant
+schedule-next-run = Nächste geplante Ausführung um { $time }
+schedule-upcoming = Anstehend
+schedule-when = Wann:
+
+## Workflows
+
+workflows-empty = Keine Workflows. Importieren Sie unten Warp-Workflow-Dateien.
+workflows-import = Importieren
+workflows-import-failed = Import fehlgeschlagen: { $error }
+workflows-import-prompt = Warp-Workflows importieren (Datei oder Verzeichnis):
+workflows-imported = { $count ->
+    [one] 1 Workflow importiert
+   *[other] { $count } Workflows importiert
+}
+workflows-no-config-dir = Kein Konfigurationsverzeichnis verfügbar
+workflows-search-hint = Workflows durchsuchen...
+
+## Environment diff
+
+env-diff-after = Nachher
+env-diff-before = Vorher
+env-diff-failed = ✗ Exit-Code { $code }
+env-diff-no-differences = Keine Unterschiede im Arbeitsverzeichnis oder in den erfassten Variablen.
+env-diff-no-exit-code = kein Exit-Code
+env-diff-no-snapshot = Einer dieser Blöcke hat keine Umgebungsaufnahme. Schalten Sie „{ settings-environment-snapshot }“ in den Einstellungen ein, um sie für neue Befehle zu erfassen.
+env-diff-succeeded = ✓ Exit-Code 0
+env-diff-working-directory = Arbeitsverzeichnis
+
+## Sharing
+
+share-failed = Teilen fehlgeschlagen: { $error }
+share-link-copied = Link in die Zwischenablage kopiert: { $url }
+share-no-secrets = Keine Geheimnisse erkannt. Prüfen Sie den Text vor dem Teilen.
+share-not-configured = Legen Sie share.github_token oder share.paste_url in der Konfigurationsdatei fest, um zu teilen.
+share-offline = Der Offline-Modus ist aktiv; es wird nichts hochgeladen.
+share-secrets-replaced = { $count ->
+    [one] 1 mögliches Geheimnis durch { $marker } ersetzt.
+   *[other] { $count } mögliche Geheimnisse durch { $marker } ersetzt.
+} Prüfen Sie den Text vor dem Teilen.
+share-target-gist = Geheimer Gist
+share-target-paste = Paste
+share-uploading = Wird zu { $target } hochgeladen...
+
+## Presentation mode
+
+presentation-empty = Keine Blöcke in dieser Sitzung
+presentation-hint = ← → blättern · Esc beendet
+
+## Screen reader
+
+a11y-block = { $state }: { $command }
+a11y-divider = Trennlinie: { $title }
+a11y-exit-code = Exit-Code { $code }
+a11y-in-ai-context = im KI-Kontext
+a11y-pinned = angeheftet
+a11y-state-cancelled = Abgebrochen
+a11y-state-completed = Abgeschlossen
+a11y-state-editing = Wird bearbeitet
+a11y-state-failed = Fehlgeschlagen
+a11y-state-running = Läuft
+a11y-state-suggested = Vorgeschlagener Befehl
+a11y-tagged = markiert mit { $tags }
+a11y-took = dauerte { $duration }
+
+## Crash report
+
+crash-report-copy = 📋 Bericht kopieren
+crash-report-dismiss = Verwerfen
+crash-report-intro = Immaterium wurde beim letzten Mal unerwartet beendet. Dieser Bericht wurde gespeichert unter:
+crash-report-more = { $count } weitere
+
+## Buttons
+
+button-cancel = Abbrechen
+button-cancel-dialog = ❌ Abbrechen
+button-close = ❌ Schließen
+button-close-panel = Bereich schließen
+button-dont-run = Nicht ausführen
+button-refresh = Aktualisieren
+button-remove-attachment = Anhang entfernen
+button-remove-trigger = Auslöser entfernen
+button-run = Ausführen
+button-run-always = Ausführen, nicht mehr fragen
+button-run-once = Einmal ausführen
+button-save = 💾 Speichern
+button-select-all = Alle auswählen
+button-select-none = Keine auswählen
+button-stop-broadcasts = ⏹ Laufende Übertragungen stoppen
+button-trust-folder = 🛡 Ordner vertrauen
+button-unschedule = Planung entfernen
+button-upload = ⬆ Hochladen
+
+## Status bar
+
+status-blocks = { $count ->
+    [one] 1 Block
+   *[other] { $count } Blöcke
+}
+status-command-running = Ein Befehl läuft noch
+status-env-file-changed = ⟳ .env geändert
+status-env-file-reapply = Die geänderte .env-Datei erneut anwenden
+status-generating-command = 🤖 Befehl wird erzeugt...
+status-plugins-loaded = 🧩 { $count } Plugins geladen
+status-session = Sitzung: { $name }
+status-waiting-rate-limit = 🤖 Warten auf das Ratenlimit...
@@ -0,0 +1,476 @@
+# English (United States)
+
+## Menu bar
+
+menu-about = About
+menu-ai = AI
+menu-ai-audit = 🧾 Audit Log...
+menu-ai-diagnostics = 🩺 Diagnostics...
+menu-broadcast = 📡 Broadcast to Sessions...
+menu-change-theme = 🎨 Change Theme...
+menu-clear-blocks = 🗑️ Clear Blocks...
+menu-copy = Copy
+menu-data-archive = 🧳 Export / Import All Data...
+menu-debug-overlay = 🐞 Debug Overlay (F12)
+menu-documentation = Documentation
+menu-edit = Edit
+menu-export = Export...
+menu-file = File
+menu-file-browser = 🗂 File Browser
+menu-git-panel = 🌿 Git Panel
+menu-help = Help
+menu-history = 🕘 History
+menu-import-session = Import Session...
+menu-insert-divider = ➖ Insert Divider...
+menu-keyboard-shortcuts = Keyboard Shortcuts
+menu-load-models = 📥 Load models
+menu-logs = 📜 Logs
+menu-mode-ai = 🤖 AI Prompt Only
+menu-mode-ai-hint = Always convert to commands using AI
+menu-mode-hybrid = 🔀 Hybrid (Auto-detect)
+menu-mode-hybrid-hint = Automatically detect NL vs commands
+menu-mode-terminal = 🖥️ Terminal Only
+menu-mode-terminal-hint = Always execute as shell commands
+menu-model = Model:
+menu-new-session = New Session
+menu-no-plugin-panels = No plugin panels
+menu-no-recent-sessions = No other sessions yet
+menu-offline = ✈ Offline
+menu-offline-hint = Only AI providers on this machine are used; network sync, sharing and webhook hooks are off
+menu-open-session = Open Session...
+menu-operation-mode = Operation Mode:
+menu-paste = Paste
+menu-plugin-dir-hint = Each plugin is a folder here with a plugin.toml
+menu-plugins = Plugins
+menu-presentation = 🎬 Presentation Mode
+menu-provider = Provider:
+menu-quick-jump = 📍 Jump to Directory...
+menu-quit = Quit
+menu-recent-sessions = Recent Sessions
+menu-redact-secrets = 🙈 Redact Secrets
+menu-redact-secrets-hint = Mask tokens, keys and passwords in output, e.g. while screen sharing
+menu-reload-plugins = ⟳ Reload Plugins
+menu-reload-scripts = 📜 Reload Scripts
+menu-restore-backup = Restore from Backup...
+menu-save-session = Save Session
+menu-scheduled-commands = ⏰ Scheduled Commands...
+menu-session-settings = Session Settings...
+menu-settings = Settings
+menu-share-session = Share Session...
+menu-split-horizontal = Split Horizontal
+menu-split-vertical = Split Vertical
+menu-ssh-hosts = SSH Hosts...
+menu-sync-offline = Offline mode is on
+menu-sync-sessions = ⇅ Sync Sessions
+menu-sync-unconfigured = Configure a [sync] backend in the config file
+menu-toggle-ai-panel = Toggle AI Panel
+menu-view = View
+menu-workflows = Workflows...
+menu-zoom-in = Zoom In
+menu-zoom-out = Zoom Out
+
+## Blocks
+
+block-actions = Block actions
+block-add-to-ai-context = 🤖 Always Include in AI Context
+block-always-in-ai-context = Always sent as AI context
+block-cancel-suggestion = ✕ Cancel (Esc)
+block-collapse = Collapse block
+block-copy-both = 📑 Copy Both
+block-copy-command = 📋 Copy Command
+block-copy-image = 🖼 Copy as Image
+block-copy-output = 📄 Copy Output
+block-copy-result = 📋 Copy result
+block-copy-result-hint = Click to copy the result
+block-delete = 🗑️ Delete Block
+block-diff-environment = 🔀 Diff Environment...
+block-edit-rerun = ✏️ Edit & Re-run
+block-edit-suggestion = ✎ Edit (E)
+block-execute-suggestion = ✓ Execute (Enter)
+block-execute-suggestion-hint = Execute suggested command: { $command }
+block-expand = Expand block
+block-fix-with-ai = 🔧 Fix with AI
+block-regenerate-suggestion = ↻ Regenerate (R)
+block-remove-calculation = 🗑 Remove
+block-remove-divider = 🗑 Remove divider
+block-remove-from-ai-context = 🤖 Stop Including in AI Context
+block-rerun-every = ⟳ Re-run every
+block-retry = ↻ Retry
+block-retry-backoff-hint = Retry up to { $count } times, waiting longer after each failure
+block-retry-hint = The failure looks transient; run the command again
+block-retry-scheduled = ↻ retry { $attempt }/{ $max } in { $seconds }s
+block-share = 🔗 Share...
+block-stop-retrying = Stop retrying
+block-stop-watching = ⏹ Stop Watching
+block-stop-watching-hint = Stop watching
+
+## Calculator
+
+calc-error-division-by-zero = Division by zero
+calc-error-empty = Nothing to calculate
+calc-error-ends-early = Expression ends too early
+calc-error-expected = Expected '{ $expected }'
+calc-error-expected-found = Expected '{ $expected }' but found '{ $found }'
+calc-error-invalid-number = Invalid number '{ $number }'
+calc-error-needs-arguments = { $name }() needs arguments
+calc-error-not-finite = Result is not a finite number
+calc-error-one-argument = { $name }() takes one argument
+calc-error-unexpected = Unexpected '{ $token }'
+calc-error-unknown-function = Unknown function '{ $name }'
+calc-error-unknown-name = Unknown name '{ $name }'
+
+## Session context menu
+
+context-change-theme = 🎨 Change Theme
+context-clear-blocks = 🗑️ Clear All Blocks...
+context-export-session = 📤 Export Session
+context-open-session = 📂 Open Session
+
+## Dialog titles
+
+dialog-ai-audit = 🧾 AI Audit Log
+dialog-ai-diagnostics = 🩺 AI Diagnostics
+dialog-broadcast = 📡 Broadcast
+dialog-clear-blocks = 🗑️ Clear Blocks
+dialog-confirm-command = ⚠ Confirm Command
+dialog-crash-report = ⚠ Crash Report
+dialog-data-archive = 🧳 Export / Import All Data
+dialog-environment-diff = 🔀 Environment Diff
+dialog-export-session = 📤 Export Session
+dialog-history = 🕘 History
+dialog-import-session = 📥 Import Session
+dialog-insert-divider = ➖ Insert Divider
+dialog-insert-path = 📂 Insert Path
+dialog-keyboard-shortcuts = ⌨ Keyboard Shortcuts
+dialog-logs = 📜 Logs
+dialog-new-session = ✨ New Session
+dialog-open-session = 📂 Open Session
+dialog-quick-jump = 📍 Jump to Directory
+dialog-restore-backup = 🗄 Restore from Backup
+dialog-scheduled-commands = ⏰ Scheduled Commands
+dialog-select-theme = 🎨 Select Theme
+dialog-settings = ⚙ Settings
+dialog-share = 🔗 Share
+dialog-ssh-hosts = 🖧 SSH Hosts
+dialog-trust-workspace = 🛡 Trust This Folder?
+dialog-workflows = 📚 Workflows
+
+## Settings dialog
+
+settings-after-restart = Takes effect after saving and restarting
+settings-apply-now = 🧹 Apply Now
+settings-applying-retention = Applying retention…
+settings-archive = Archive removed blocks to the data directory
+settings-backup-disabled = 0 disables automatic backups
+settings-backup-interval = Back up the database every
+settings-backups-kept = Backups kept
+settings-blocks-removed = { $count ->
+    [one] Removed 1 block
+   *[other] Removed { $count } blocks
+}
+settings-compact = 🗜 Compact Database
+settings-compact-hint = Run VACUUM to reclaim space from deleted blocks
+settings-compacted = Compacted { $before } → { $after }
+settings-compacting = Compacting the database…
+settings-compaction-failed = Compaction failed: { $error }
+settings-database-compacted = Database compacted
+settings-database-size = Database size: { $size }
+settings-days = {" "}days
+settings-encrypt = Encrypt commands and output (key kept in the OS keyring)
+settings-encrypt-hint = Existing blocks are encrypted or decrypted on the next start. Archived blocks are sealed with the same key.
+settings-encrypted = 🔒 Stored blocks are encrypted
+settings-environment-snapshot = Record environment variables with each command
+settings-environment-snapshot-hint = Records { $variables } (set snapshot_variables in the config file). Stored unencrypted.
+settings-highlight-diagnostics = Highlight errors and warnings in output
+settings-highlight-diagnostics-hint = Colors lines like error: and warning: and marks them beside the scrollbar of long output
+settings-highlight-output = Syntax highlight recognized output
+settings-highlight-output-hint = Colors output of cat config.yaml, JSON responses, SQL, Rust and Python tracebacks by language
+settings-hours = {" "}hours
+settings-language = Language
+settings-max-block-age = Remove blocks older than
+settings-max-block-age-hint = 0 keeps blocks forever
+settings-max-blocks = Blocks kept per session
+settings-no-limit = 0 for no limit
+settings-output-sections = Fold build tool output into sections
+settings-output-sections-hint = Each crate cargo compiles, npm script and pytest file gets a heading that collapses it
+settings-retention-failed = Retention failed: { $error }
+settings-retention-note = Retention runs at startup; use Apply Now to run it immediately.
+settings-save-failed = Failed to save settings: { $error }
+settings-saved = Settings saved
+settings-state-patterns = Show block states with patterns instead of colors
+settings-state-patterns-hint = Accent bars get a pattern per state, and a single neutral color
+settings-storage = Storage
+settings-trusted-directories = Trusted folders
+settings-unknown = unknown
+settings-wait-for-command = Wait for the running command to finish
+settings-workspace-trust = Ask before running setup in untrusted folders
+settings-workspace-trust-hint = Startup commands, the .env file and direnv wait for you to trust a folder the first time a session opens there
+
+## AI panel
+
+ai-ask = Ask AI:
+ai-assistant = 🤖 AI Assistant
+ai-clear = Clear
+ai-context = Context
+ai-conversation = Conversation:
+ai-drop-files = Drop files here to attach them
+ai-include-history = Include command history
+ai-load-models = Load Models
+ai-overflow = When they don't fit:
+ai-overflow-chunk = Chunk
+ai-overflow-chunk-hint = Ask about the blocks that don't fit a piece at a time, in extra requests, and send the notes along
+ai-overflow-drop = Drop oldest
+ai-overflow-drop-hint = Send the most recent blocks that fit and leave out older ones
+ai-overflow-failures = Failures only
+ai-overflow-failures-hint = When the blocks don't all fit, send only the ones that failed
+ai-overflow-summarize = Summarize
+ai-overflow-summarize-hint = Have the AI summarize the blocks that don't fit, in an extra request
+ai-prompt-hint = Type your question...
+ai-receiving = Receiving response...
+ai-recent-blocks = Recent blocks:
+ai-response = Response:
+ai-send = Send
+ai-send-hint = Send prompt to AI (Enter)
+ai-timing = load { $load }s · prompt { $prompt }s · generation { $generation }s
+ai-timing-hint = Time spent loading the model, reading the prompt and generating the response
+ai-waiting-rate-limit = Waiting for rate limit ({ $count } queued)...
+ai-waiting-rate-limit-hint = Requests are held back to stay within the provider's limits
+
+## Broadcast
+
+broadcast-exit = { $session } (exit { $code })
+broadcast-failed = 📡 { $count } failed
+broadcast-failed-in = Last broadcast failed in:
+broadcast-hint = Commands typed here also run in the checked sessions, each adding a block there.
+broadcast-running = 📡 { $count } running
+broadcast-targets = Typed commands also run in { $count ->
+    [one] one other session
+   *[other] { $count } other sessions
+}
+
+## Confirm command
+
+confirm-matches = This command matches "{ $pattern }", which can't easily be undone:
+confirm-remember-hint = Remember this exact command in the config
+
+## Workspace trust
+
+trust-direnv = ▲ direnv { $path }
+trust-folder-hint = Remember this folder and its subfolders in the config
+trust-load-env = 🔑 Load { $path }
+trust-only-known = Only trust folders whose contents you know.
+trust-runs-setup = This session runs setup automatically when it opens in
+
+## Open Session dialog
+
+open-session-add-folder = ➕ Folder
+open-session-current = ▶ { $name } (current)
+open-session-hint = Select a session to open (drag ⠿ onto a folder to move it):
+open-session-list-failed = Couldn't load the session list: { $error }
+open-session-move-to = Move to
+open-session-new-folder = New folder
+open-session-ungrouped = Ungrouped
+open-session-ungrouped-count = Ungrouped ({ $count })
+open-session-updated = Updated: { $time }
+
+## New Session dialog
+
+new-session-create = ✅ Create
+new-session-name-hint = Session name...
+new-session-name-taken = A session named "{ $name }" already exists
+new-session-prompt = Enter a name for the new session:
+new-session-use-name = Use "{ $name }"
+
+## Export dialog
+
+export-choose-format = Choose export format:
+export-encrypted = 🔒 Export as Encrypted Archive
+export-encrypted-intro = Encrypted archive, for logs with sensitive output:
+export-json = 📄 Export as JSON
+export-markdown = 📝 Export as Markdown
+export-passphrase = Passphrase
+export-passphrase-confirm = Confirm
+export-passphrase-mismatch = Passphrases don't match
+export-session-name = Export session: { $name }
+export-text = 📋 Export as Text
+
+## Import dialog
+
+import-button = 📥 Import
+import-failed = Import failed: { $error }
+import-file = File
+import-intro = Session exported as JSON or as an encrypted archive:
+import-passphrase = Passphrase
+import-passphrase-hint = only for encrypted archives
+
+## File browser
+
+files-attach = 📎 Attach to AI context
+files-attached = 📎 Attached { $name }
+files-cd-here = 📂 cd here
+files-file-hint = Click to insert the path; right-click for more
+files-insert-path = ⌨ Insert path
+files-more = … { $count } more
+files-open = ↗ Open
+files-show-hidden = Show hidden files
+files-title = Files
+path-picker-filter = Filter...
+path-picker-hint = Insert a file path ({ $key })
+path-picker-insert = Insert
+path-picker-insert-directory = Insert this directory
+path-picker-parent = Parent directory
+
+## Dividers and clearing
+
+clear-blocks-confirm = Delete { $count ->
+    [one] the block
+   *[other] all { $count } blocks
+} in "{ $session }"? They are removed from the database as well and can't be brought back.
+clear-blocks-delete = Delete blocks
+clear-blocks-running-kept = The running command is kept.
+divider-heading-hint = Heading
+divider-insert = Insert
+divider-prompt = Heading for what comes next, e.g. "Deploy to staging"
+
+## Session settings
+
+session-color = Color:
+session-color-blue = Blue
+session-color-gray = Gray
+session-color-green = Green
+session-color-orange = Orange
+session-color-purple = Purple
+session-color-red = Red
+session-color-yellow = Yellow
+session-description = Description
+session-description-hint = What this session is for
+session-env-file = .env file
+session-env-file-load = Load
+session-env-file-unload = Unload
+session-environment = Session environment
+session-no-color = None
+session-show-values = Show values
+
+## Output triggers
+
+trigger-add = ➕ Add Trigger
+trigger-command-hint = command
+trigger-highlight-line = Highlight line
+trigger-hint = When a regex matches a line of command output, fire an action.
+trigger-mark-block = Mark block
+trigger-notification = Trigger: { $command }
+trigger-notify = Notify
+trigger-pattern-hint = regex
+trigger-run-command = Run command
+trigger-title = Output triggers
+
+## Scheduled commands
+
+schedule-add = ➕ Schedule
+schedule-command = Command:
+schedule-empty = Nothing scheduled
+schedule-next-run = Next scheduled run at { $time }
+schedule-upcoming = Upcoming
+schedule-when = When:
+
+## Workflows
+
+workflows-empty = No workflows. Import Warp workflow files below.
+workflows-import = Import
+workflows-import-failed = Import failed: { $error }
+workflows-import-prompt = Import Warp workflows (file or directory):
+workflows-imported = { $count ->
+    [one] Imported 1 workflow
+   *[other] Imported { $count } workflows
+}
+workflows-no-config-dir = No config directory available
+workflows-search-hint = Search workflows...
+
+## Environment diff
+
+env-diff-after = After
+env-diff-before = Before
+env-diff-failed = ✗ exit { $code }
+env-diff-no-differences = No differences in the working directory or the snapshot variables.
+env-diff-no-exit-code = no exit code
+env-diff-no-snapshot = One of these blocks has no environment snapshot. Turn on "{ settings-environment-snapshot }" in Settings to record one for new commands.
+env-diff-succeeded = ✓ exit 0
+env-diff-working-directory = Working directory
+
+## Sharing
+
+share-failed = Share failed: { $error }
+share-link-copied = Link copied to clipboard: { $url }
+share-no-secrets = No secrets detected. Review the text before sharing.
+share-not-configured = Set share.github_token or share.paste_url in the config file to share.
+share-offline = Offline mode is on; nothing is uploaded.
+share-secrets-replaced = { $count ->
+    [one] 1 possible secret replaced with { $marker }.
+   *[other] { $count } possible secrets replaced with { $marker }.
+} Review the text before sharing.
+share-target-gist = Secret Gist
+share-target-paste = Paste
+share-uploading = Uploading to { $target }...
+
+## Presentation mode
+
+presentation-empty = No blocks in this session
+presentation-hint = ← → step · Esc exits
+
+## Screen reader
+
+a11y-block = { $state }: { $command }
+a11y-divider = Divider: { $title }
+a11y-exit-code = exit code { $code }
+a11y-in-ai-context = in AI context
+a11y-pinned = pinned
+a11y-state-cancelled = Cancelled
+a11y-state-completed = Completed
+a11y-state-editing = Editing
+a11y-state-failed = Failed
+a11y-state-running = Running
+a11y-state-suggested = Suggested command
+a11y-tagged = tagged { $tags }
+a11y-took = took { $duration }
+
+## Crash report
+
+crash-report-copy = 📋 Copy Report
+crash-report-dismiss = Dismiss
+crash-report-intro = Immaterium closed unexpectedly last time. This report was saved to:
+crash-report-more = { $count } more
+
+## Buttons
+
+button-cancel = Cancel
+button-cancel-dialog = ❌ Cancel
+button-close = ❌ Close
+button-close-panel = Close panel
+button-dont-run = Don't Run
+button-refresh = Refresh
+button-remove-attachment = Remove attachment
+button-remove-trigger = Remove trigger
+button-run = Run
+button-run-always = Run, don't ask again
+button-run-once = Run Once
+button-save = 💾 Save
+button-select-all = Select All
+button-select-none = Select None
+button-stop-broadcasts = ⏹ Stop Running Broadcasts
+button-trust-folder = 🛡 Trust Folder
+button-unschedule = Unschedule
+button-upload = ⬆ Upload
+
+## Status bar
+
+status-blocks = { $count ->
+    [one] 1 block
+   *[other] { $count } blocks
+}
+status-command-running = A command is still running
+status-env-file-changed = ⟳ .env changed
+status-env-file-reapply = Re-apply the updated .env file
+status-generating-command = 🤖 Generating command...
+status-plugins-loaded = 🧩 { $count } plugins loaded
+status-session = Session: { $name }
+status-waiting-rate-limit = 🤖 Waiting for rate limit...
@@ -0,0 +1,3 @@
+-- Pinned blocks and the natural language request behind AI-generated commands
+ALTER TABLE blocks ADD COLUMN is_pinned BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE blocks ADD COLUMN original_input TEXT;
@@ -0,0 +1,3 @@
+-- Optional folder/project used to group sessions in the Open Session dialog
+ALTER TABLE sessions ADD COLUMN folder TEXT;
+CREATE INDEX IF NOT EXISTS idx_sessions_folder ON sessions(folder);
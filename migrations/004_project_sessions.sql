@@ -0,0 +1,3 @@
+-- Repository root a session belongs to, for per-project sessions
+ALTER TABLE sessions ADD COLUMN project_root TEXT;
+CREATE INDEX IF NOT EXISTS idx_sessions_project_root ON sessions(project_root);
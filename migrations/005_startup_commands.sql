@@ -0,0 +1,4 @@
+-- Commands run automatically when a session is opened (JSON array)
+ALTER TABLE sessions ADD COLUMN startup_commands TEXT;
+-- Blocks produced by those commands get a "startup" badge
+ALTER TABLE blocks ADD COLUMN is_startup BOOLEAN NOT NULL DEFAULT 0;
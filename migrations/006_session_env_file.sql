@@ -0,0 +1,3 @@
+-- .env file loaded into the session environment, and the keys it provided
+ALTER TABLE sessions ADD COLUMN env_file TEXT;
+ALTER TABLE sessions ADD COLUMN env_file_keys TEXT;
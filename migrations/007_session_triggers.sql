@@ -0,0 +1,2 @@
+-- Output triggers (regex -> action) configured per session (JSON array)
+ALTER TABLE sessions ADD COLUMN triggers TEXT;
@@ -0,0 +1,2 @@
+-- Large block outputs are stored zstd-compressed here, leaving output empty
+ALTER TABLE blocks ADD COLUMN output_zstd BLOB;
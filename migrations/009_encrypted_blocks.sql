@@ -0,0 +1,3 @@
+-- With encryption enabled, a block's command, output and original input are
+-- stored here encrypted and their own columns are left empty
+ALTER TABLE blocks ADD COLUMN sealed BLOB;
@@ -0,0 +1,2 @@
+-- Tags attached to a block (e.g. by scripting hooks), as a JSON array
+ALTER TABLE blocks ADD COLUMN tags TEXT;
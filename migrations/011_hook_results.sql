@@ -0,0 +1,2 @@
+-- Results of the configured pre/post command hooks, as a JSON array
+ALTER TABLE blocks ADD COLUMN hook_results TEXT;
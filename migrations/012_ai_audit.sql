@@ -0,0 +1,18 @@
+-- AI requests and their outcomes, for teams that must keep an audit trail
+CREATE TABLE IF NOT EXISTS ai_audit (
+    id TEXT PRIMARY KEY,
+    timestamp TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    prompt_hash TEXT NOT NULL,
+    prompt TEXT,
+    response TEXT,
+    error TEXT,
+    prompt_tokens INTEGER,
+    completion_tokens INTEGER,
+    latency_ms INTEGER NOT NULL,
+    executed_block_id TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_ai_audit_timestamp ON ai_audit(timestamp);
@@ -0,0 +1,2 @@
+-- Provider, model and prompt behind an AI-generated command, as JSON
+ALTER TABLE blocks ADD COLUMN ai_provenance TEXT;
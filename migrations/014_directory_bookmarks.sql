@@ -0,0 +1,8 @@
+-- Directories the shell has been in, for quick-jump; pinned ones are kept
+-- even when rarely visited
+CREATE TABLE IF NOT EXISTS directory_visits (
+    path TEXT PRIMARY KEY,
+    visits INTEGER NOT NULL DEFAULT 0,
+    last_visit TEXT NOT NULL,
+    pinned INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,2 @@
+-- Heading blocks that separate phases of work within a session
+ALTER TABLE blocks ADD COLUMN is_divider BOOLEAN NOT NULL DEFAULT 0;
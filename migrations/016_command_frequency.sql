@@ -0,0 +1,13 @@
+-- How often each command was run, across sessions, for ranking suggestions
+CREATE TABLE IF NOT EXISTS command_frequency (
+    command TEXT PRIMARY KEY,
+    count INTEGER NOT NULL DEFAULT 0,
+    last_used TEXT NOT NULL
+);
+
+-- Start from the commands already in history; encrypted ones stay out
+INSERT OR IGNORE INTO command_frequency (command, count, last_used)
+SELECT TRIM(command), COUNT(*), MAX(timestamp)
+FROM blocks
+WHERE sealed IS NULL AND NOT is_divider AND state != 'PendingApproval' AND TRIM(command) != ''
+GROUP BY TRIM(command);
@@ -0,0 +1,3 @@
+-- Optional description and color tag that help tell sessions apart
+ALTER TABLE sessions ADD COLUMN description TEXT;
+ALTER TABLE sessions ADD COLUMN color TEXT;
@@ -0,0 +1,2 @@
+-- Results of expressions typed after the calculator prefix, evaluated without a shell
+ALTER TABLE blocks ADD COLUMN is_calculation BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Blocks always sent as AI context, whatever their age
+ALTER TABLE blocks ADD COLUMN in_ai_context BOOLEAN NOT NULL DEFAULT 0;
@@ -1,6 +1,8 @@
 use crate::core::block::{Block, BlockState};
 use crate::core::session::Session;
+use crate::t;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
 /// Configuration for context building
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +19,57 @@ pub struct ContextConfig {
     pub max_output_chars: usize,
     /// How many recent blocks to prioritize
     pub recent_blocks_count: usize,
+    /// What happens to recent blocks that don't fit in the budget
+    #[serde(default)]
+    pub overflow: OverflowStrategy,
+}
+
+/// What to do with recent blocks when they don't all fit in the budget
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowStrategy {
+    /// Keep the most recent blocks that fit and leave out the rest
+    #[default]
+    Drop,
+    /// Keep only the blocks that failed
+    FailuresOnly,
+    /// Have the AI summarize the blocks that didn't fit, in extra requests
+    Summarize,
+    /// Ask the question about the blocks that didn't fit a piece at a time,
+    /// in extra requests, and send the notes along with the rest
+    Chunk,
+}
+
+impl OverflowStrategy {
+    pub const ALL: [OverflowStrategy; 4] = [
+        OverflowStrategy::Drop,
+        OverflowStrategy::FailuresOnly,
+        OverflowStrategy::Summarize,
+        OverflowStrategy::Chunk,
+    ];
+
+    pub fn label(&self) -> String {
+        match self {
+            OverflowStrategy::Drop => t!("ai-overflow-drop"),
+            OverflowStrategy::FailuresOnly => t!("ai-overflow-failures"),
+            OverflowStrategy::Summarize => t!("ai-overflow-summarize"),
+            OverflowStrategy::Chunk => t!("ai-overflow-chunk"),
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            OverflowStrategy::Drop => t!("ai-overflow-drop-hint"),
+            OverflowStrategy::FailuresOnly => t!("ai-overflow-failures-hint"),
+            OverflowStrategy::Summarize => t!("ai-overflow-summarize-hint"),
+            OverflowStrategy::Chunk => t!("ai-overflow-chunk-hint"),
+        }
+    }
+
+    /// Whether blocks that don't fit are kept for extra requests
+    fn keeps_overflow(&self) -> bool {
+        matches!(self, OverflowStrategy::Summarize | OverflowStrategy::Chunk)
+    }
 }
 
 impl Default for ContextConfig {
@@ -28,6 +81,7 @@ impl Default for ContextConfig {
             truncate_output: true,
             max_output_chars: 500,
             recent_blocks_count: 10,
+            overflow: OverflowStrategy::Drop,
         }
     }
 }
@@ -57,6 +111,86 @@ pub struct ContextBuilder {
     config: ContextConfig,
     parts: Vec<String>,
     token_count: usize,
+    // Blocks that didn't fit, formatted, oldest first
+    overflow: Vec<String>,
+}
+
+/// A context along with the blocks that didn't fit in it, split into pieces
+/// that each fit in the budget on their own
+#[derive(Debug, Clone)]
+pub struct BuiltContext {
+    pub context: String,
+    pub overflow: Vec<String>,
+    pub strategy: OverflowStrategy,
+    /// Budget the context left unused, which condensed history has to fit in
+    pub spare_tokens: usize,
+    tokens_per_char: f32,
+}
+
+impl BuiltContext {
+    /// A context with nothing left over
+    pub fn plain(context: String) -> Self {
+        Self {
+            context,
+            overflow: Vec::new(),
+            strategy: OverflowStrategy::Drop,
+            spare_tokens: 0,
+            tokens_per_char: ContextConfig::default().tokens_per_char,
+        }
+    }
+
+    /// Prompts for the extra requests the strategy makes about the blocks
+    /// that didn't fit, one per piece
+    pub fn overflow_requests(&self, question: &str) -> Vec<String> {
+        if !self.strategy.keeps_overflow() {
+            return Vec::new();
+        }
+        let parts = self.overflow.len();
+        self.overflow
+            .iter()
+            .enumerate()
+            .map(|(i, piece)| match self.strategy {
+                OverflowStrategy::Chunk => format!(
+                    "This is part {} of {} of earlier terminal history. Note briefly anything in it \
+                     relevant to the question below, or reply \"Nothing relevant\".\n\n\
+                     === Command History ===\n{}\n=== Question ===\n{}\n",
+                    i + 1,
+                    parts,
+                    piece,
+                    question
+                ),
+                _ => format!(
+                    "Summarize this terminal history in a few short lines: what was run, \
+                     what failed and any errors worth remembering.\n\n=== Command History ===\n{}",
+                    piece
+                ),
+            })
+            .collect()
+    }
+
+    /// The context with the answers to the overflow requests ahead of it,
+    /// as many of the newest as fit in the budget the context left over
+    pub fn with_condensed_history(&self, answers: &[String]) -> String {
+        let header = match self.strategy {
+            OverflowStrategy::Chunk => "=== Notes from Earlier Commands ===",
+            _ => "=== Earlier Commands (summarized) ===",
+        };
+        let estimate = |text: &str| (text.len() as f32 * self.tokens_per_char).ceil() as usize;
+        let mut used = estimate(header) + estimate("\n\n\n");
+        let mut kept = Vec::new();
+        for answer in answers.iter().rev() {
+            used += estimate(answer) + estimate("\n");
+            if used > self.spare_tokens {
+                break;
+            }
+            kept.push(answer.as_str());
+        }
+        if kept.is_empty() {
+            return self.context.clone();
+        }
+        kept.reverse();
+        format!("{}\n{}\n\n{}", header, kept.join("\n"), self.context)
+    }
 }
 
 impl ContextBuilder {
@@ -65,21 +199,30 @@ impl ContextBuilder {
             config,
             parts: Vec::new(),
             token_count: 0,
+            overflow: Vec::new(),
         }
     }
 
-    /// Add a section to the context if it fits
-    fn try_add_section(&mut self, section: String) -> bool {
-        let tokens = self.config.estimate_tokens(&section);
+    /// Count text against the budget if it fits
+    fn take_tokens(&mut self, text: &str) -> bool {
+        let tokens = self.config.estimate_tokens(text);
         if self.token_count + tokens <= self.config.max_tokens {
             self.token_count += tokens;
-            self.parts.push(section);
             true
         } else {
             false
         }
     }
 
+    /// Add a section to the context if it fits
+    fn try_add_section(&mut self, section: String) -> bool {
+        let fits = self.take_tokens(&section);
+        if fits {
+            self.parts.push(section);
+        }
+        fits
+    }
+
     /// Add system information
     pub fn add_system_info(&mut self) -> &mut Self {
         if !self.config.include_system_info {
@@ -88,7 +231,7 @@ impl ContextBuilder {
 
         let info = format!(
             "=== System Information ===\nOS: {}\nArchitecture: {}\nShell: bash\n",
-            std::env::consts::OS,
+            os_description(),
             std::env::consts::ARCH
         );
 
@@ -96,6 +239,30 @@ impl ContextBuilder {
         self
     }
 
+    /// Add what a generated command will run on: the OS (with the Linux
+    /// distribution, which decides between apt, dnf, pacman...), architecture,
+    /// shell and working directory
+    pub fn add_environment(&mut self, shell: &str, working_directory: &Path) -> &mut Self {
+        let shell = Path::new(shell).file_name().and_then(|n| n.to_str()).unwrap_or(shell);
+        let info = format!(
+            "=== Environment ===\nOS: {}\nArchitecture: {}\nShell: {}\nWorking Directory: {}\n",
+            os_description(),
+            std::env::consts::ARCH,
+            shell,
+            working_directory.display()
+        );
+
+        self.try_add_section(info);
+        self
+    }
+
+    /// Add a block that failed, for requests like "fix that"
+    pub fn add_failed_block(&mut self, block: &Block) -> &mut Self {
+        let section = format!("=== Last Failed Command ===\n{}", self.format_block(block));
+        self.try_add_section(section);
+        self
+    }
+
     /// Add session information
     pub fn add_session_info(&mut self, session: &Session) -> &mut Self {
         let info = format!(
@@ -110,6 +277,16 @@ impl ContextBuilder {
 
     /// Add a single block to context
     pub fn add_block(&mut self, block: &Block) -> bool {
+        let block_text = self.format_block(block);
+        self.try_add_section(block_text)
+    }
+
+    fn format_block(&self, block: &Block) -> String {
+        // Evaluated in place by the calculator, not run in the shell
+        if block.is_calculation {
+            return format!("[Calculation, not a command] {} = {}\n", block.command, block.output.to_string().trim());
+        }
+
         let mut block_text = String::new();
 
         // Add command
@@ -120,7 +297,7 @@ impl ContextBuilder {
             let output = if self.config.truncate_output
                 && block.output.len() > self.config.max_output_chars
             {
-                let truncated = &block.output[..self.config.max_output_chars];
+                let truncated = block.output.head(self.config.max_output_chars);
                 format!("{}...\n[Output truncated]\n", truncated)
             } else {
                 format!("{}\n", block.output)
@@ -142,39 +319,77 @@ impl ContextBuilder {
             BlockState::Editing => block_text.push_str("[Editing]\n"),
         }
 
-        self.try_add_section(block_text)
+        block_text
+    }
+
+    /// Add the blocks pinned to the AI context, however old, under a header
+    /// that is only added when at least one of them fits
+    pub fn add_pinned_blocks(&mut self, blocks: &[Block]) -> &mut Self {
+        let header = "=== Pinned Blocks ===\n";
+        let mut has_header = false;
+        for block in blocks.iter().filter(|block| block.in_ai_context && !block.is_divider) {
+            let text = self.format_block(block);
+            let mut needed = self.config.estimate_tokens(&text);
+            if !has_header {
+                needed += self.config.estimate_tokens(header);
+            }
+            if needed > self.remaining_tokens() {
+                continue;
+            }
+            if !has_header {
+                has_header = self.try_add_section(header.to_string());
+            }
+            self.try_add_section(text);
+        }
+        self
     }
 
-    /// Add blocks with smart selection
+    /// Add blocks with smart selection: blocks pinned to the AI context
+    /// first, however old, then the most recent of the rest
     pub fn add_blocks(&mut self, blocks: &[Block]) -> &mut Self {
         if blocks.is_empty() {
             return self;
         }
 
+        self.add_pinned_blocks(blocks);
+        // Dividers are headings, not commands that were run
+        let blocks: Vec<&Block> = blocks
+            .iter()
+            .filter(|block| !block.is_divider && !block.in_ai_context)
+            .collect();
+        if blocks.is_empty() {
+            return self;
+        }
+
         // Add header
         self.try_add_section("=== Command History ===\n".to_string());
 
         // Prioritize recent blocks
-        let start_idx = if blocks.len() > self.config.recent_blocks_count {
-            blocks.len() - self.config.recent_blocks_count
-        } else {
-            0
-        };
+        let start_idx = blocks.len().saturating_sub(self.config.recent_blocks_count);
+        let recent = &blocks[start_idx..];
+        let texts: Vec<String> = recent.iter().map(|block| self.format_block(block)).collect();
+        let needed: usize = texts.iter().map(|text| self.config.estimate_tokens(text)).sum();
+        let failures_only = needed > self.remaining_tokens() && self.config.overflow == OverflowStrategy::FailuresOnly;
 
         // Try to add blocks from most recent backwards
-        for block in blocks[start_idx..].iter().rev() {
-            if !self.add_block(block) {
+        let mut history = Vec::new();
+        for (block, text) in recent.iter().zip(texts).rev() {
+            if failures_only && !is_failure(block) {
+                continue;
+            }
+            if self.overflow.is_empty() && self.take_tokens(&text) {
+                history.push(text);
+            } else if self.config.overflow.keeps_overflow() {
+                self.overflow.push(text);
+            } else {
                 // If we can't fit more blocks, stop
                 break;
             }
         }
 
-        // Reverse the parts to get chronological order
-        let history_start = self.parts.iter().position(|p| p.contains("Command History"));
-        if let Some(start) = history_start {
-            let history_parts = self.parts.drain(start + 1..).collect::<Vec<_>>();
-            self.parts.extend(history_parts.into_iter().rev());
-        }
+        // Back to chronological order
+        self.parts.extend(history.into_iter().rev());
+        self.overflow.reverse();
 
         self
     }
@@ -197,6 +412,33 @@ impl ContextBuilder {
         self.parts.join("\n")
     }
 
+    /// Build the final context string, keeping the blocks that didn't fit
+    /// for the overflow strategy, in pieces that each fit in the budget
+    pub fn build_with_overflow(self) -> BuiltContext {
+        let mut pieces: Vec<String> = Vec::new();
+        let mut piece_tokens = 0;
+        for text in &self.overflow {
+            let tokens = self.config.estimate_tokens(text);
+            match pieces.last_mut() {
+                Some(piece) if piece_tokens + tokens <= self.config.max_tokens => {
+                    piece.push_str(text);
+                    piece_tokens += tokens;
+                }
+                _ => {
+                    pieces.push(text.clone());
+                    piece_tokens = tokens;
+                }
+            }
+        }
+        BuiltContext {
+            context: self.parts.join("\n"),
+            overflow: pieces,
+            strategy: self.config.overflow,
+            spare_tokens: self.config.max_tokens.saturating_sub(self.token_count),
+            tokens_per_char: self.config.tokens_per_char,
+        }
+    }
+
     /// Get current token count
     pub fn token_count(&self) -> usize {
         self.token_count
@@ -226,20 +468,84 @@ pub fn build_session_context(
     builder.build()
 }
 
+/// Helper to build the request for turning natural language into a command:
+/// the environment it will run in, the blocks pinned to the AI context, the
+/// last failure if given, then the request
+pub fn build_command_context(
+    request: &str,
+    shell: &str,
+    working_directory: &Path,
+    last_failure: Option<&Block>,
+    blocks: &[Block],
+) -> String {
+    let config = ContextConfig {
+        max_tokens: 2000,
+        truncate_output: true,
+        max_output_chars: 500,
+        ..Default::default()
+    };
+
+    let mut builder = ContextBuilder::new(config);
+    builder.add_environment(shell, working_directory);
+    builder.add_pinned_blocks(blocks);
+    if let Some(block) = last_failure {
+        builder.add_failed_block(block);
+    }
+    builder.add_prompt(request);
+    builder.build()
+}
+
+/// The OS, with the distribution name on Linux, e.g. "linux (Fedora Linux 40)"
+fn os_description() -> String {
+    let os = std::env::consts::OS;
+    match std::fs::read_to_string("/etc/os-release").ok().as_deref().and_then(os_release_name) {
+        Some(name) if os == "linux" => format!("{} ({})", os, name),
+        _ => os.to_string(),
+    }
+}
+
+/// PRETTY_NAME (or NAME) from the contents of /etc/os-release
+fn os_release_name(contents: &str) -> Option<String> {
+    let field = |key: &str| {
+        contents.lines().find_map(|line| {
+            let value = line.strip_prefix(key)?.strip_prefix('=')?;
+            Some(value.trim().trim_matches('"').to_string()).filter(|v| !v.is_empty())
+        })
+    };
+    field("PRETTY_NAME").or_else(|| field("NAME"))
+}
+
+/// Whether a block counts as a failure for `OverflowStrategy::FailuresOnly`
+fn is_failure(block: &Block) -> bool {
+    block.state == BlockState::Failed || block.exit_code.is_some_and(|code| code != 0)
+}
+
 /// Helper to build minimal context (just recent commands)
 pub fn build_minimal_context(blocks: &[Block], prompt: &str, max_blocks: usize) -> String {
+    build_history_context(blocks, prompt, max_blocks, OverflowStrategy::Drop).context
+}
+
+/// Like `build_minimal_context`, with the blocks that didn't fit handled by
+/// `overflow`
+pub fn build_history_context(
+    blocks: &[Block],
+    prompt: &str,
+    max_blocks: usize,
+    overflow: OverflowStrategy,
+) -> BuiltContext {
     let config = ContextConfig {
         max_tokens: 2000,
         truncate_output: true,
         max_output_chars: 200,
         recent_blocks_count: max_blocks,
         include_system_info: false,
+        overflow,
         ..Default::default()
     };
 
     let mut builder = ContextBuilder::new(config);
     builder.add_blocks(blocks).add_prompt(prompt);
-    builder.build()
+    builder.build_with_overflow()
 }
 
 #[cfg(test)]
@@ -254,7 +560,7 @@ mod tests {
             id: Uuid::new_v4(),
             timestamp: Utc::now(),
             command: command.to_string(),
-            output: output.to_string(),
+            output: output.into(),
             exit_code,
             state,
             metadata: crate::core::block::BlockMetadata {
@@ -263,10 +569,20 @@ mod tests {
                 environment: std::collections::HashMap::new(),
                 started_at: Some(Utc::now()),
                 completed_at: Some(Utc::now()),
+                hooks: Vec::new(),
+                ai_provenance: None,
             },
             is_collapsed: false,
             is_selected: false,
             original_input: None,
+            is_pinned: false,
+            is_startup: false,
+            highlighted_lines: Vec::new(),
+            tags: Vec::new(),
+            is_divider: false,
+            is_calculation: false,
+            in_ai_context: false,
+            pending_carriage_return: false,
         }
     }
 
@@ -279,6 +595,14 @@ mod tests {
             updated_at: Utc::now(),
             blocks: Vec::new(),
             environment: std::collections::HashMap::new(),
+            folder: None,
+            project_root: None,
+            startup_commands: Vec::new(),
+            env_file: None,
+            env_file_keys: Vec::new(),
+            triggers: Vec::new(),
+            description: None,
+            color: None,
         }
     }
 
@@ -412,6 +736,43 @@ mod tests {
         assert!(!context.contains("System Information")); // Minimal mode
     }
 
+    #[test]
+    fn test_build_command_context() {
+        let failed = create_test_block("apt install ripgrep", "apt: command not found", BlockState::Failed, Some(127));
+        let context = build_command_context(
+            "install ripgrep",
+            "/usr/bin/zsh",
+            Path::new("/home/user/project"),
+            Some(&failed),
+            &[],
+        );
+
+        assert!(context.contains(&format!("Architecture: {}", std::env::consts::ARCH)));
+        assert!(context.contains("Shell: zsh\n"));
+        assert!(context.contains("Working Directory: /home/user/project"));
+        assert!(context.contains("=== Last Failed Command ===\n$ apt install ripgrep"));
+        assert!(context.ends_with("install ripgrep\n"));
+
+        let context = build_command_context("list files", "bash", Path::new("/tmp"), None, &[]);
+        assert!(!context.contains("Last Failed Command"));
+        assert!(!context.contains("Pinned Blocks"));
+
+        let mut schema = create_test_block("cat schema.sql", "CREATE TABLE users", BlockState::Completed, Some(0));
+        schema.in_ai_context = true;
+        let blocks = [schema, create_test_block("ls", "a b", BlockState::Completed, Some(0))];
+        let context = build_command_context("count the users", "bash", Path::new("/tmp"), None, &blocks);
+        assert!(context.contains("=== Pinned Blocks ===\n\n$ cat schema.sql"));
+        assert!(!context.contains("$ ls"));
+    }
+
+    #[test]
+    fn test_os_release_name() {
+        let fedora = "NAME=\"Fedora Linux\"\nVERSION_ID=40\nPRETTY_NAME=\"Fedora Linux 40 (Workstation Edition)\"\n";
+        assert_eq!(os_release_name(fedora).as_deref(), Some("Fedora Linux 40 (Workstation Edition)"));
+        assert_eq!(os_release_name("NAME=Alpine\n").as_deref(), Some("Alpine"));
+        assert_eq!(os_release_name("ID=unknown\n"), None);
+    }
+
     #[test]
     fn test_recent_blocks_prioritization() {
         let config = ContextConfig {
@@ -436,6 +797,122 @@ mod tests {
         assert!(context.contains("$ recent2"));
     }
 
+    #[test]
+    fn test_overflow_strategies() {
+        let config = |overflow| ContextConfig {
+            max_tokens: 60,
+            truncate_output: false,
+            overflow,
+            ..Default::default()
+        };
+        let blocks = vec![
+            create_test_block("make", &"error: ".repeat(12), BlockState::Failed, Some(2)),
+            create_test_block("cat big.log", &"x".repeat(200), BlockState::Completed, Some(0)),
+            create_test_block("ls", "a b", BlockState::Completed, Some(0)),
+        ];
+
+        let mut builder = ContextBuilder::new(config(OverflowStrategy::Drop));
+        builder.add_blocks(&blocks);
+        let built = builder.build_with_overflow();
+        assert!(built.context.contains("$ ls") && !built.context.contains("$ make"));
+        assert!(built.overflow.is_empty());
+
+        let mut builder = ContextBuilder::new(config(OverflowStrategy::FailuresOnly));
+        builder.add_blocks(&blocks);
+        let context = builder.build();
+        assert!(context.contains("$ make"));
+        assert!(!context.contains("$ ls"));
+
+        let mut builder = ContextBuilder::new(config(OverflowStrategy::Summarize));
+        builder.add_blocks(&blocks);
+        let built = builder.build_with_overflow();
+        assert!(built.context.contains("$ ls"));
+        assert_eq!(built.overflow.len(), 2);
+        assert!(built.overflow[0].starts_with("$ make"));
+        let requests = built.overflow_requests("why did make fail?");
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].starts_with("Summarize"));
+        let condensed = built.with_condensed_history(&["make failed with errors".to_string()]);
+        assert!(condensed.starts_with("=== Earlier Commands (summarized) ===\nmake failed with errors\n"));
+        assert!(condensed.ends_with(&built.context));
+        // Answers that don't fit in what the context left over are dropped, oldest first
+        let long = "y".repeat(built.spare_tokens * 4);
+        let condensed = built.with_condensed_history(&[long.clone(), "make failed with errors".to_string()]);
+        assert!(!condensed.contains(&long));
+        assert!(condensed.contains("make failed with errors"));
+        assert_eq!(built.with_condensed_history(&[long]), built.context);
+
+        let mut builder = ContextBuilder::new(config(OverflowStrategy::Chunk));
+        builder.add_blocks(&blocks);
+        let requests = builder.build_with_overflow().overflow_requests("why did make fail?");
+        assert!(requests[1].starts_with("This is part 2 of 2"));
+        assert!(requests[1].contains("why did make fail?"));
+    }
+
+    #[test]
+    fn test_dividers_are_left_out() {
+        let config = ContextConfig::new(1000);
+        let mut builder = ContextBuilder::new(config);
+
+        let mut divider = create_test_block("Deploy", "", BlockState::Completed, None);
+        divider.is_divider = true;
+        let blocks = vec![divider, create_test_block("make deploy", "done", BlockState::Completed, Some(0))];
+
+        builder.add_blocks(&blocks);
+        let context = builder.build();
+
+        assert!(context.contains("$ make deploy"));
+        assert!(!context.contains("$ Deploy"));
+    }
+
+    #[test]
+    fn test_calculations_are_labelled() {
+        let config = ContextConfig::new(1000);
+        let mut builder = ContextBuilder::new(config);
+
+        let mut calculation = create_test_block("4096*3/1024", "12", BlockState::Completed, Some(0));
+        calculation.is_calculation = true;
+        assert!(builder.add_block(&calculation));
+
+        let context = builder.build();
+        assert!(context.contains("[Calculation, not a command] 4096*3/1024 = 12"));
+        assert!(!context.contains("$ 4096"));
+    }
+
+    #[test]
+    fn test_pinned_blocks_come_first() {
+        let config = ContextConfig {
+            recent_blocks_count: 1,
+            ..Default::default()
+        };
+        let mut builder = ContextBuilder::new(config);
+
+        let mut error_log = create_test_block("cat error.log", "panicked at src/main.rs:3", BlockState::Completed, Some(0));
+        error_log.in_ai_context = true;
+        let blocks = vec![
+            error_log,
+            create_test_block("vim src/main.rs", "", BlockState::Completed, Some(0)),
+            create_test_block("cargo run", "ok", BlockState::Completed, Some(0)),
+        ];
+
+        builder.add_blocks(&blocks);
+        let context = builder.build();
+
+        assert!(context.starts_with("=== Pinned Blocks ===\n\n$ cat error.log"));
+        assert!(context.contains("$ cargo run"));
+        assert!(!context.contains("$ vim"));
+    }
+
+    #[test]
+    fn test_pinned_header_needs_a_block() {
+        let mut builder = ContextBuilder::new(ContextConfig::new(10));
+        let mut big = create_test_block("cat big.log", &"x".repeat(200), BlockState::Completed, Some(0));
+        big.in_ai_context = true;
+
+        builder.add_pinned_blocks(&[big]);
+        assert!(!builder.build().contains("Pinned Blocks"));
+    }
+
     #[test]
     fn test_remaining_tokens() {
         let config = ContextConfig::new(1000);
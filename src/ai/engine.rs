@@ -1,10 +1,51 @@
 use super::provider::{AiError, ChatRequest, ChatResponse, LlmProvider, StreamResponse};
+use super::rate_limit::{estimate_tokens, RateLimit, RateLimiter};
+use super::requests::AiFeature;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a successful availability check is trusted before requests check again
+const HEALTH_TTL: Duration = Duration::from_secs(60);
+
+/// Last known reachability of a provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderHealth {
+    /// Not checked yet
+    Unknown,
+    Available,
+    Unavailable,
+}
+
+/// What is known about a provider, for the health dots and diagnostics
+#[derive(Debug, Clone)]
+pub struct ProviderStatus {
+    pub health: ProviderHealth,
+    pub checked_at: Option<Instant>,
+    /// Round trip of the last availability check
+    pub latency: Option<Duration>,
+    /// Most recent failed check or request
+    pub last_error: Option<String>,
+}
+
+impl Default for ProviderStatus {
+    fn default() -> Self {
+        Self {
+            health: ProviderHealth::Unknown,
+            checked_at: None,
+            latency: None,
+            last_error: None,
+        }
+    }
+}
 
 pub struct AiEngine {
     providers: HashMap<String, Arc<dyn LlmProvider>>,
     default_provider: Option<String>,
+    status: Mutex<HashMap<String, ProviderStatus>>,
+    limiters: HashMap<String, RateLimiter>,
+    /// Models pinned to particular features of a provider instead of the selected one
+    task_models: HashMap<(String, AiFeature), String>,
 }
 
 impl AiEngine {
@@ -12,6 +53,9 @@ impl AiEngine {
         Self {
             providers: HashMap::new(),
             default_provider: None,
+            status: Mutex::new(HashMap::new()),
+            limiters: HashMap::new(),
+            task_models: HashMap::new(),
         }
     }
 
@@ -28,6 +72,34 @@ impl AiEngine {
         self.providers.insert(name, provider);
     }
 
+    /// Hold requests to a provider back to stay within `limit`
+    pub fn set_rate_limit(&mut self, name: &str, limit: RateLimit) {
+        if limit.is_unlimited() {
+            self.limiters.remove(name);
+        } else {
+            self.limiters.insert(name.to_string(), RateLimiter::new(limit));
+        }
+    }
+
+    /// Always use `model` for `feature`'s requests to `provider`
+    pub fn set_task_model(&mut self, provider: &str, feature: AiFeature, model: impl Into<String>) {
+        self.task_models.insert((provider.to_string(), feature), model.into());
+    }
+
+    /// The model a request for `feature` to `provider` goes to: the one
+    /// pinned to it there, or the `selected` one
+    pub fn model_for(&self, provider: &str, feature: AiFeature, selected: &str) -> String {
+        self.task_models
+            .get(&(provider.to_string(), feature))
+            .map_or(selected, String::as_str)
+            .to_string()
+    }
+
+    /// Requests to a provider waiting for its rate limit
+    pub fn waiting(&self, name: &str) -> usize {
+        self.limiters.get(name).map_or(0, |limiter| limiter.waiting())
+    }
+
     /// Set the default provider
     pub fn set_default_provider(&mut self, name: &str) -> Result<(), AiError> {
         if !self.providers.contains_key(name) {
@@ -60,27 +132,108 @@ impl AiEngine {
     /// Check if any provider is available
     pub async fn has_available_provider(&self) -> bool {
         for provider in self.providers.values() {
-            if provider.is_available().await {
+            if self.check_available(provider).await {
                 return true;
             }
         }
         false
     }
 
+    /// Last recorded health of a provider
+    pub fn health(&self, name: &str) -> ProviderHealth {
+        self.status(name).health
+    }
+
+    /// Everything recorded about a provider
+    pub fn status(&self, name: &str) -> ProviderStatus {
+        self.status.lock().unwrap().get(name).cloned().unwrap_or_default()
+    }
+
+    /// Check every provider at once and record the results
+    pub async fn refresh_health(&self) {
+        let checks = self.providers.values().map(|provider| self.ping(provider));
+        futures::future::join_all(checks).await;
+    }
+
+    /// Check one provider now, whatever the cache says
+    pub async fn check_provider(&self, name: &str) {
+        if let Some(provider) = self.providers.get(name) {
+            self.ping(provider).await;
+        }
+    }
+
+    /// Time an availability check and record its outcome
+    async fn ping(&self, provider: &Arc<dyn LlmProvider>) -> bool {
+        let started = Instant::now();
+        let available = provider.is_available().await;
+        let mut status = self.status.lock().unwrap();
+        let status = status.entry(provider.name().to_string()).or_default();
+        status.checked_at = Some(Instant::now());
+        status.latency = Some(started.elapsed());
+        if available {
+            status.health = ProviderHealth::Available;
+        } else {
+            status.health = ProviderHealth::Unavailable;
+            status.last_error = Some("Availability check failed".to_string());
+        }
+        available
+    }
+
+    /// Remember a failed request, e.g. one made to a provider directly
+    pub fn record_error(&self, name: &str, error: &AiError) {
+        self.status
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_default()
+            .last_error = Some(error.to_string());
+    }
+
+    /// Whether a provider can take requests. A recent successful check is
+    /// trusted; anything else is checked again so a provider that comes back
+    /// up is picked up straight away.
+    async fn check_available(&self, provider: &Arc<dyn LlmProvider>) -> bool {
+        let status = self.status(provider.name());
+        if status.health == ProviderHealth::Available
+            && status.checked_at.is_some_and(|checked| checked.elapsed() < HEALTH_TTL)
+        {
+            return true;
+        }
+        self.ping(provider).await
+    }
+
+    /// Send a request once the provider's rate limit allows it
+    async fn send(&self, provider: &Arc<dyn LlmProvider>, request: ChatRequest) -> Result<ChatResponse, AiError> {
+        let limiter = self.limiters.get(provider.name());
+        let ticket = match limiter {
+            Some(limiter) => Some(limiter.acquire(estimate_tokens(&request)).await),
+            None => None,
+        };
+
+        let response = provider
+            .chat_completion(request)
+            .await
+            .inspect_err(|e| self.record_error(provider.name(), e))?;
+        if let (Some(limiter), Some(ticket), Some(usage)) = (limiter, ticket, &response.usage) {
+            limiter.settle(ticket, usage.total_tokens);
+        }
+        Ok(response)
+    }
+
     /// Send a chat completion request using the default provider
     pub async fn chat_completion(&self, request: ChatRequest) -> Result<ChatResponse, AiError> {
         let provider = self.get_default_provider().ok_or_else(|| {
             AiError::NotConfigured("No default provider set".to_string())
         })?;
 
-        if !provider.is_available().await {
+        if !self.check_available(provider).await {
             return Err(AiError::NotConfigured(format!(
                 "Provider '{}' is not available",
                 provider.name()
             )));
         }
 
-        provider.chat_completion(request).await
+        self.send(provider, request).await
     }
 
     /// Send a streaming chat completion request using the default provider
@@ -92,14 +245,22 @@ impl AiEngine {
             AiError::NotConfigured("No default provider set".to_string())
         })?;
 
-        if !provider.is_available().await {
+        if !self.check_available(provider).await {
             return Err(AiError::NotConfigured(format!(
                 "Provider '{}' is not available",
                 provider.name()
             )));
         }
 
-        provider.chat_completion_stream(request).await
+        if let Some(limiter) = self.limiters.get(provider.name()) {
+            // Streams don't report usage, so the estimate stands
+            limiter.acquire(estimate_tokens(&request)).await;
+        }
+
+        provider
+            .chat_completion_stream(request)
+            .await
+            .inspect_err(|e| self.record_error(provider.name(), e))
     }
 
     /// Send a chat completion request using a specific provider
@@ -112,14 +273,14 @@ impl AiEngine {
             AiError::NotConfigured(format!("Provider '{}' not found", provider_name))
         })?;
 
-        if !provider.is_available().await {
+        if !self.check_available(provider).await {
             return Err(AiError::NotConfigured(format!(
                 "Provider '{}' is not available",
                 provider_name
             )));
         }
 
-        provider.chat_completion(request).await
+        self.send(provider, request).await
     }
 }
 
@@ -155,6 +316,7 @@ mod tests {
                 model: "mock-model".to_string(),
                 finish_reason: Some("stop".to_string()),
                 usage: None,
+                timing: None,
             })
         }
 
@@ -190,6 +352,16 @@ mod tests {
         assert_eq!(engine.default_provider, Some("test".to_string()));
     }
 
+    #[test]
+    fn test_task_models() {
+        let mut engine = AiEngine::new();
+        engine.set_task_model("ollama", AiFeature::CommandGeneration, "qwen2.5-coder:7b");
+        assert_eq!(engine.model_for("ollama", AiFeature::CommandGeneration, "llama3.1"), "qwen2.5-coder:7b");
+        assert_eq!(engine.model_for("ollama", AiFeature::Chat, "llama3.1"), "llama3.1");
+        // Another provider doesn't get the Ollama model
+        assert_eq!(engine.model_for("groq", AiFeature::CommandGeneration, "mixtral-8x7b"), "mixtral-8x7b");
+    }
+
     #[tokio::test]
     async fn test_chat_completion() {
         let mut engine = AiEngine::new();
@@ -222,6 +394,80 @@ mod tests {
 
         let result = engine.chat_completion(request).await;
         assert!(result.is_err());
+        assert_eq!(engine.health("test"), ProviderHealth::Unavailable);
+    }
+
+    struct CountingProvider {
+        checks: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LlmProvider for CountingProvider {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        async fn is_available(&self) -> bool {
+            self.checks.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            true
+        }
+
+        async fn chat_completion(&self, _request: ChatRequest) -> Result<ChatResponse, AiError> {
+            Ok(ChatResponse {
+                content: "ok".to_string(),
+                model: "mock-model".to_string(),
+                finish_reason: None,
+                usage: None,
+                timing: None,
+            })
+        }
+
+        async fn chat_completion_stream(
+            &self,
+            _request: ChatRequest,
+        ) -> Result<StreamResponse, AiError> {
+            Err(AiError::Unknown("Not implemented".to_string()))
+        }
+
+        async fn list_models(&self) -> Result<Vec<String>, AiError> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health_is_cached() {
+        let provider = Arc::new(CountingProvider {
+            checks: Default::default(),
+        });
+        let mut engine = AiEngine::new();
+        engine.register_provider(provider.clone());
+        assert_eq!(engine.health("counting"), ProviderHealth::Unknown);
+
+        engine.refresh_health().await;
+        assert_eq!(engine.health("counting"), ProviderHealth::Available);
+
+        for _ in 0..3 {
+            let request = ChatRequest::new("mock-model".to_string())
+                .with_user_message("Hello".to_string());
+            engine.chat_completion(request).await.unwrap();
+        }
+        // Only the refresh reached the provider
+        assert_eq!(provider.checks.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(engine.status("counting").latency.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_status_records_errors() {
+        let mut engine = AiEngine::new();
+        engine.register_provider(Arc::new(MockProvider {
+            name: "test".to_string(),
+            available: true,
+        }));
+        assert!(engine.status("test").last_error.is_none());
+
+        let request = ChatRequest::new("mock-model".to_string());
+        assert!(engine.chat_completion_stream(request).await.is_err());
+        assert_eq!(engine.status("test").last_error.as_deref(), Some("Unknown error: Not implemented"));
     }
 }
 
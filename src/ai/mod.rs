@@ -5,8 +5,18 @@ pub mod context;
 pub mod engine;
 pub mod provider;
 pub mod providers;
+pub mod rate_limit;
+pub mod requests;
+pub mod stream;
+pub mod suggestion;
 
-pub use context::{build_minimal_context, build_session_context, ContextBuilder, ContextConfig};
-pub use engine::AiEngine;
-pub use provider::{AiError, ChatRequest, ChatResponse, LlmProvider, Message, MessageRole, StreamResponse, Usage};
+pub use context::{
+    build_command_context, build_history_context, build_minimal_context, build_session_context, BuiltContext, ContextBuilder,
+    ContextConfig, OverflowStrategy,
+};
+pub use engine::{AiEngine, ProviderHealth, ProviderStatus};
+pub use provider::{AiError, ChatRequest, ChatResponse, LlmProvider, Message, MessageRole, ResponseTiming, StreamResponse, Usage};
 pub use providers::OllamaProvider;
+pub use rate_limit::RateLimit;
+pub use requests::{AiFeature, AiOrigin, AiRequest, AiRequests, RequestId, RunningRequest};
+pub use suggestion::{parse_command_candidates, parse_command_explanation, CommandExplanation};
@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::pin::Pin;
+use std::time::Duration;
 use tokio_stream::Stream;
 
 pub type StreamResponse = Pin<Box<dyn Stream<Item = Result<String, AiError>> + Send>>;
@@ -22,6 +23,21 @@ pub trait LlmProvider: Send + Sync {
 
     /// List available models
     async fn list_models(&self) -> Result<Vec<String>, AiError>;
+
+    /// Load `model` ahead of the first request, for providers where that is slow
+    async fn warm_up(&self, _model: &str) -> Result<(), AiError> {
+        Ok(())
+    }
+
+    /// Where requests are sent, shown in diagnostics
+    fn base_url(&self) -> Option<&str> {
+        None
+    }
+
+    /// Model used when none is selected, shown in diagnostics
+    fn default_model(&self) -> Option<&str> {
+        None
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,6 +46,9 @@ pub struct ChatRequest {
     pub model: String,
     pub temperature: Option<f32>,
     pub max_tokens: Option<u32>,
+    /// Sampling seed, for providers that support repeatable sampling
+    #[serde(default)]
+    pub seed: Option<u64>,
     pub stream: bool,
 }
 
@@ -53,6 +72,33 @@ pub struct ChatResponse {
     pub model: String,
     pub finish_reason: Option<String>,
     pub usage: Option<Usage>,
+    /// Where the time went, for providers that report it
+    #[serde(default)]
+    pub timing: Option<ResponseTiming>,
+}
+
+/// Time spent on a response, split by phase
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResponseTiming {
+    /// Loading the model into memory; near zero when it was already loaded
+    pub load: Duration,
+    /// Reading the prompt
+    pub prompt: Duration,
+    /// Generating the response
+    pub generation: Duration,
+    pub total: Duration,
+}
+
+impl ResponseTiming {
+    /// For logs, e.g. "load 2.31s · prompt 0.12s · generation 1.05s"
+    pub fn summary(&self) -> String {
+        format!(
+            "load {:.2}s · prompt {:.2}s · generation {:.2}s",
+            self.load.as_secs_f64(),
+            self.prompt.as_secs_f64(),
+            self.generation.as_secs_f64()
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -96,6 +142,7 @@ impl ChatRequest {
             model,
             temperature: Some(0.7),
             max_tokens: Some(2048),
+            seed: None,
             stream: false,
         }
     }
@@ -134,6 +181,11 @@ impl ChatRequest {
         self
     }
 
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
     pub fn streaming(mut self, stream: bool) -> Self {
         self.stream = stream;
         self
@@ -150,7 +202,8 @@ mod tests {
             .with_system_message("You are a helpful assistant".to_string())
             .with_user_message("Hello".to_string())
             .with_temperature(0.8)
-            .with_max_tokens(1000);
+            .with_max_tokens(1000)
+            .with_seed(7);
 
         assert_eq!(request.model, "gpt-4");
         assert_eq!(request.messages.len(), 2);
@@ -158,6 +211,7 @@ mod tests {
         assert_eq!(request.messages[1].role, MessageRole::User);
         assert_eq!(request.temperature, Some(0.8));
         assert_eq!(request.max_tokens, Some(1000));
+        assert_eq!(request.seed, Some(7));
     }
 
     #[test]
@@ -1,4 +1,5 @@
 use crate::ai::provider::{AiError, ChatRequest, ChatResponse, LlmProvider, StreamResponse, Usage};
+use crate::ai::stream::sse_stream;
 use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
@@ -34,6 +35,14 @@ impl LlmProvider for GroqProvider {
         "groq"
     }
 
+    fn base_url(&self) -> Option<&str> {
+        Some("https://api.groq.com/openai/v1")
+    }
+
+    fn default_model(&self) -> Option<&str> {
+        Some(&self.default_model)
+    }
+
     async fn is_available(&self) -> bool {
         // Try to list models to check if API key is valid
         self.client
@@ -58,6 +67,7 @@ impl LlmProvider for GroqProvider {
                 .collect(),
             temperature: request.temperature,
             max_tokens: request.max_tokens,
+            seed: request.seed,
             stream: false,
         };
 
@@ -101,6 +111,7 @@ impl LlmProvider for GroqProvider {
                 completion_tokens: u.completion_tokens,
                 total_tokens: u.total_tokens,
             }),
+            timing: None,
         })
     }
 
@@ -117,6 +128,7 @@ impl LlmProvider for GroqProvider {
                 .collect(),
             temperature: request.temperature,
             max_tokens: request.max_tokens,
+            seed: request.seed,
             stream: true,
         };
 
@@ -139,29 +151,11 @@ impl LlmProvider for GroqProvider {
             )));
         }
 
-        let stream = response.bytes_stream();
+        let stream = sse_stream::<GroqStreamChunk, _, _, _>(response.bytes_stream());
         let mapped_stream = stream.filter_map(|result| async move {
             match result {
-                Ok(bytes) => {
-                    let text = String::from_utf8_lossy(&bytes).to_string();
-                    // Parse SSE format: "data: {...}\n\n"
-                    for line in text.lines() {
-                        if let Some(json_str) = line.strip_prefix("data: ") {
-                            if json_str == "[DONE]" {
-                                return None;
-                            }
-                            if let Ok(chunk) = serde_json::from_str::<GroqStreamChunk>(json_str) {
-                                if let Some(choice) = chunk.choices.first() {
-                                    if let Some(content) = &choice.delta.content {
-                                        return Some(Ok(content.clone()));
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    None
-                }
-                Err(e) => Some(Err(AiError::StreamError(e.to_string()))),
+                Ok(chunk) => chunk.choices.into_iter().next().and_then(|choice| choice.delta.content).map(Ok),
+                Err(e) => Some(Err(e)),
             }
         });
 
@@ -201,6 +195,8 @@ struct GroqChatRequest {
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
     stream: bool,
 }
 
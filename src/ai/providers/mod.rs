@@ -5,3 +5,30 @@ pub mod groq;
 pub use ollama::OllamaProvider;
 pub use openai::OpenAiProvider;
 pub use groq::GroqProvider;
+
+/// Whether a provider at `url` runs on this machine, and so is still usable
+/// in offline mode
+pub fn is_local_url(url: &str) -> bool {
+    let Some(host) = reqwest::Url::parse(url).ok().and_then(|url| url.host_str().map(str::to_string)) else {
+        return false;
+    };
+    match host.trim_matches(['[', ']']).parse::<std::net::IpAddr>() {
+        Ok(ip) => ip.is_loopback(),
+        Err(_) => host == "localhost" || host.ends_with(".localhost"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_local_url() {
+        assert!(is_local_url("http://localhost:11434"));
+        assert!(is_local_url("http://127.0.0.1:8080/v1"));
+        assert!(is_local_url("http://[::1]:11434"));
+        assert!(!is_local_url("https://api.groq.com/openai/v1"));
+        assert!(!is_local_url("http://192.168.1.20:11434"));
+        assert!(!is_local_url("not a url"));
+    }
+}
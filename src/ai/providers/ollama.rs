@@ -1,13 +1,19 @@
-use crate::ai::provider::{AiError, ChatRequest, ChatResponse, LlmProvider, Message, StreamResponse, Usage};
+use crate::ai::provider::{
+    AiError, ChatRequest, ChatResponse, LlmProvider, Message, ResponseTiming, StreamResponse, Usage,
+};
+use crate::ai::stream::ndjson_stream;
 use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use tokio_stream::StreamExt;
 
 pub struct OllamaProvider {
     client: Client,
     base_url: String,
     default_model: String,
+    /// How long Ollama keeps the model loaded after a request; its own default when unset
+    keep_alive: Option<String>,
 }
 
 impl OllamaProvider {
@@ -16,9 +22,15 @@ impl OllamaProvider {
             client: Client::new(),
             base_url,
             default_model,
+            keep_alive: None,
         }
     }
 
+    pub fn with_keep_alive(mut self, keep_alive: Option<String>) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
     fn chat_url(&self) -> String {
         format!("{}/api/chat", self.base_url)
     }
@@ -34,6 +46,14 @@ impl LlmProvider for OllamaProvider {
         "ollama"
     }
 
+    fn base_url(&self) -> Option<&str> {
+        Some(&self.base_url)
+    }
+
+    fn default_model(&self) -> Option<&str> {
+        Some(&self.default_model)
+    }
+
     async fn is_available(&self) -> bool {
         // Try to connect to Ollama
         self.client
@@ -55,9 +75,11 @@ impl LlmProvider for OllamaProvider {
                 })
                 .collect(),
             stream: false,
+            keep_alive: self.keep_alive.clone(),
             options: Some(OllamaOptions {
                 temperature: request.temperature,
                 num_predict: request.max_tokens.map(|t| t as i32),
+                seed: request.seed,
             }),
         };
 
@@ -83,6 +105,7 @@ impl LlmProvider for OllamaProvider {
             .await
             .map_err(|e| AiError::ApiError(e.to_string()))?;
 
+        let timing = ollama_response.timing();
         Ok(ChatResponse {
             content: ollama_response.message.content,
             model: ollama_response.model,
@@ -92,6 +115,7 @@ impl LlmProvider for OllamaProvider {
                 completion_tokens: ollama_response.eval_count.unwrap_or(0) as u32,
                 total_tokens: (prompt_tokens + ollama_response.eval_count.unwrap_or(0)) as u32,
             }),
+            timing,
         })
     }
 
@@ -107,9 +131,11 @@ impl LlmProvider for OllamaProvider {
                 })
                 .collect(),
             stream: true,
+            keep_alive: self.keep_alive.clone(),
             options: Some(OllamaOptions {
                 temperature: request.temperature,
                 num_predict: request.max_tokens.map(|t| t as i32),
+                seed: request.seed,
             }),
         };
 
@@ -130,21 +156,41 @@ impl LlmProvider for OllamaProvider {
             )));
         }
 
-        let stream = response.bytes_stream();
-        let mapped_stream = stream.map(|result| {
-            result
-                .map_err(|e| AiError::StreamError(e.to_string()))
-                .and_then(|bytes| {
-                    let text = String::from_utf8_lossy(&bytes).to_string();
-                    serde_json::from_str::<OllamaChatResponse>(&text)
-                        .map(|resp| resp.message.content)
-                        .map_err(|e| AiError::StreamError(e.to_string()))
-                })
-        });
+        let mapped_stream = ndjson_stream::<OllamaChatResponse, _, _, _>(response.bytes_stream())
+            .map(|result| result.map(|resp| resp.message.content));
 
         Ok(Box::pin(mapped_stream))
     }
 
+    /// A chat request without messages makes Ollama load the model and return
+    async fn warm_up(&self, model: &str) -> Result<(), AiError> {
+        let request = OllamaChatRequest {
+            model: model.to_string(),
+            messages: Vec::new(),
+            stream: false,
+            keep_alive: self.keep_alive.clone(),
+            options: None,
+        };
+
+        let response = self
+            .client
+            .post(self.chat_url())
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| AiError::NetworkError(e.to_string()))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(AiError::ApiError(format!(
+                "Ollama API error {}: {}",
+                status, error_text
+            )));
+        }
+        Ok(())
+    }
+
     async fn list_models(&self) -> Result<Vec<String>, AiError> {
         let response = self
             .client
@@ -175,6 +221,8 @@ struct OllamaChatRequest {
     messages: Vec<OllamaMessage>,
     stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     options: Option<OllamaOptions>,
 }
 
@@ -190,6 +238,8 @@ struct OllamaOptions {
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     num_predict: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -202,6 +252,28 @@ struct OllamaChatResponse {
     prompt_eval_count: Option<i64>,
     #[serde(default)]
     eval_count: Option<i64>,
+    // Durations are in nanoseconds
+    #[serde(default)]
+    total_duration: Option<u64>,
+    #[serde(default)]
+    load_duration: Option<u64>,
+    #[serde(default)]
+    prompt_eval_duration: Option<u64>,
+    #[serde(default)]
+    eval_duration: Option<u64>,
+}
+
+impl OllamaChatResponse {
+    fn timing(&self) -> Option<ResponseTiming> {
+        let total = self.total_duration?;
+        let nanos = |d: Option<u64>| Duration::from_nanos(d.unwrap_or(0));
+        Some(ResponseTiming {
+            load: nanos(self.load_duration),
+            prompt: nanos(self.prompt_eval_duration),
+            generation: nanos(self.eval_duration),
+            total: Duration::from_nanos(total),
+        })
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -237,4 +309,35 @@ mod tests {
         assert_eq!(provider.chat_url(), "http://localhost:11434/api/chat");
         assert_eq!(provider.models_url(), "http://localhost:11434/api/tags");
     }
+
+    #[test]
+    fn test_keep_alive_is_sent_when_set() {
+        let request = |keep_alive: Option<&str>| OllamaChatRequest {
+            model: "llama2".to_string(),
+            messages: Vec::new(),
+            stream: false,
+            keep_alive: keep_alive.map(str::to_string),
+            options: None,
+        };
+        let json = serde_json::to_value(request(Some("30m"))).unwrap();
+        assert_eq!(json["keep_alive"], "30m");
+        let json = serde_json::to_value(request(None)).unwrap();
+        assert!(json.get("keep_alive").is_none());
+    }
+
+    #[test]
+    fn test_response_timing() {
+        let response: OllamaChatResponse = serde_json::from_str(
+            r#"{"model":"llama2","message":{"role":"assistant","content":"ls"},"done":true,
+                "total_duration":3500000000,"load_duration":2000000000,
+                "prompt_eval_duration":500000000,"eval_duration":1000000000}"#,
+        )
+        .unwrap();
+        let timing = response.timing().unwrap();
+        assert_eq!(timing.load, Duration::from_secs(2));
+        assert_eq!(timing.prompt, Duration::from_millis(500));
+        assert_eq!(timing.generation, Duration::from_secs(1));
+        assert_eq!(timing.total, Duration::from_millis(3500));
+        assert_eq!(timing.summary(), "load 2.00s · prompt 0.50s · generation 1.00s");
+    }
 }
@@ -69,6 +69,14 @@ impl LlmProvider for OpenAiProvider {
         "openai"
     }
 
+    fn base_url(&self) -> Option<&str> {
+        Some(async_openai::config::OPENAI_API_BASE)
+    }
+
+    fn default_model(&self) -> Option<&str> {
+        Some(&self.default_model)
+    }
+
     async fn is_available(&self) -> bool {
         // Try to list models to check if API key is valid
         self.client.models().list().await.is_ok()
@@ -84,6 +92,10 @@ impl LlmProvider for OpenAiProvider {
             req.temperature(temp);
         }
 
+        if let Some(seed) = request.seed {
+            req.seed(openai_seed(seed)?);
+        }
+
         if let Some(max_tokens) = request.max_tokens {
             req.max_tokens(max_tokens as u16);
         }
@@ -121,6 +133,10 @@ impl LlmProvider for OpenAiProvider {
             req.temperature(temp);
         }
 
+        if let Some(seed) = request.seed {
+            req.seed(openai_seed(seed)?);
+        }
+
         if let Some(max_tokens) = request.max_tokens {
             req.max_tokens(max_tokens as u16);
         }
@@ -196,14 +212,29 @@ impl OpenAiProvider {
                 completion_tokens: u.completion_tokens,
                 total_tokens: u.total_tokens,
             }),
+            timing: None,
         })
     }
 }
 
+/// OpenAI takes a signed seed; larger ones are refused rather than wrapped
+/// around to a different seed
+fn openai_seed(seed: u64) -> Result<i64, AiError> {
+    i64::try_from(seed)
+        .map_err(|_| AiError::InvalidRequest(format!("seed {} is larger than OpenAI accepts ({})", seed, i64::MAX)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_openai_seed() {
+        assert_eq!(openai_seed(42).unwrap(), 42);
+        assert_eq!(openai_seed(i64::MAX as u64).unwrap(), i64::MAX);
+        assert!(matches!(openai_seed(u64::MAX), Err(AiError::InvalidRequest(_))));
+    }
+
     #[test]
     fn test_openai_provider_creation() {
         let provider = OpenAiProvider::new(
@@ -0,0 +1,208 @@
+use super::provider::ChatRequest;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Limits count requests sent in the last WINDOW
+const WINDOW: Duration = Duration::from_secs(60);
+/// Rough characters per token, for estimating a request before it is sent
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Client-side limits for one provider; `None` means unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimit {
+    pub requests_per_minute: Option<u32>,
+    pub tokens_per_minute: Option<u32>,
+}
+
+impl RateLimit {
+    pub fn is_unlimited(&self) -> bool {
+        self.requests_per_minute.is_none() && self.tokens_per_minute.is_none()
+    }
+}
+
+/// Handed out by `RateLimiter::acquire` so the request's token estimate can
+/// be replaced by the provider's count once it answers
+#[derive(Debug)]
+pub struct Ticket(u64);
+
+/// Holds requests back until they fit within a provider's limits, so a burst
+/// of them is spread out instead of being refused with 429s
+pub struct RateLimiter {
+    limit: RateLimit,
+    window: Mutex<Window>,
+    /// Requests currently held back
+    waiting: AtomicUsize,
+}
+
+#[derive(Default)]
+struct Window {
+    next_ticket: u64,
+    sent: VecDeque<Sent>,
+}
+
+struct Sent {
+    ticket: u64,
+    at: Instant,
+    tokens: u32,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            window: Mutex::new(Window::default()),
+            waiting: AtomicUsize::new(0),
+        }
+    }
+
+    /// Wait until a request of about `tokens` fits, then count it as sent
+    pub async fn acquire(&self, tokens: u32) -> Ticket {
+        let mut queued = None;
+        loop {
+            match self.try_acquire(tokens, Instant::now()) {
+                Ok(ticket) => return ticket,
+                Err(wait) => {
+                    if queued.is_none() {
+                        queued = Some(WaitingGuard::new(&self.waiting));
+                        tracing::debug!("Rate limit reached, waiting {:?}", wait);
+                    }
+                    tokio::time::sleep(wait).await;
+                }
+            }
+        }
+    }
+
+    /// Count a request as sent at `now` if it fits, or say how long until it might
+    fn try_acquire(&self, tokens: u32, now: Instant) -> Result<Ticket, Duration> {
+        let mut window = self.window.lock().unwrap();
+        while window.sent.front().is_some_and(|sent| now.duration_since(sent.at) >= WINDOW) {
+            window.sent.pop_front();
+        }
+
+        let until_expired = |sent: &Sent| (sent.at + WINDOW).saturating_duration_since(now);
+        let mut wait = Duration::ZERO;
+        if let Some(rpm) = self.limit.requests_per_minute {
+            let over = (window.sent.len() + 1).saturating_sub(rpm.max(1) as usize);
+            if over > 0 {
+                wait = wait.max(until_expired(&window.sent[over - 1]));
+            }
+        }
+        if let Some(tpm) = self.limit.tokens_per_minute {
+            // A request bigger than the whole budget still goes once nothing else is in flight
+            let mut used: u32 = window.sent.iter().map(|sent| sent.tokens).sum();
+            for sent in &window.sent {
+                if used.saturating_add(tokens) <= tpm {
+                    break;
+                }
+                used -= sent.tokens;
+                wait = wait.max(until_expired(sent));
+            }
+        }
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+
+        let ticket = window.next_ticket;
+        window.next_ticket += 1;
+        window.sent.push_back(Sent { ticket, at: now, tokens });
+        Ok(Ticket(ticket))
+    }
+
+    /// Replace a request's estimate with the tokens it actually used
+    pub fn settle(&self, ticket: Ticket, tokens: u32) {
+        let mut window = self.window.lock().unwrap();
+        if let Some(sent) = window.sent.iter_mut().find(|sent| sent.ticket == ticket.0) {
+            sent.tokens = tokens;
+        }
+    }
+
+    /// Requests currently held back by the limit
+    pub fn waiting(&self) -> usize {
+        self.waiting.load(Ordering::SeqCst)
+    }
+}
+
+/// Counts a request as waiting for as long as it is held back, including
+/// when the caller gives up on it
+struct WaitingGuard<'a>(&'a AtomicUsize);
+
+impl<'a> WaitingGuard<'a> {
+    fn new(waiting: &'a AtomicUsize) -> Self {
+        waiting.fetch_add(1, Ordering::SeqCst);
+        Self(waiting)
+    }
+}
+
+impl Drop for WaitingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Tokens a request is likely to use: its messages plus the most it may generate
+pub fn estimate_tokens(request: &ChatRequest) -> u32 {
+    let chars: usize = request.messages.iter().map(|m| m.content.len()).sum();
+    (chars / CHARS_PER_TOKEN) as u32 + request.max_tokens.unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(requests_per_minute: Option<u32>, tokens_per_minute: Option<u32>) -> RateLimiter {
+        RateLimiter::new(RateLimit {
+            requests_per_minute,
+            tokens_per_minute,
+        })
+    }
+
+    #[test]
+    fn test_requests_per_minute() {
+        let limiter = limiter(Some(2), None);
+        let start = Instant::now();
+        assert!(limiter.try_acquire(0, start).is_ok());
+        assert!(limiter.try_acquire(0, start + Duration::from_secs(10)).is_ok());
+
+        // The third waits for the first to leave the window
+        let wait = limiter.try_acquire(0, start + Duration::from_secs(20)).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(40));
+        assert!(limiter.try_acquire(0, start + WINDOW).is_ok());
+    }
+
+    #[test]
+    fn test_tokens_per_minute() {
+        let limiter = limiter(None, Some(1000));
+        let start = Instant::now();
+        let first = limiter.try_acquire(600, start).unwrap();
+        assert!(limiter.try_acquire(300, start + Duration::from_secs(1)).is_ok());
+        assert!(limiter.try_acquire(200, start + Duration::from_secs(2)).is_err());
+
+        // The first request used fewer tokens than estimated
+        limiter.settle(first, 100);
+        assert!(limiter.try_acquire(200, start + Duration::from_secs(2)).is_ok());
+
+        // Too big for the budget, but allowed through once the window is empty
+        assert!(limiter.try_acquire(5000, start + Duration::from_secs(3)).is_err());
+        assert!(limiter.try_acquire(5000, start + Duration::from_secs(62)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_waiting_requests_are_counted() {
+        let limiter = std::sync::Arc::new(limiter(Some(1), None));
+        limiter.acquire(0).await;
+
+        let queued = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire(0).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(limiter.waiting(), 1);
+
+        // Giving up on a queued request takes it out of the count
+        queued.abort();
+        let _ = queued.await;
+        assert_eq!(limiter.waiting(), 0);
+    }
+}
@@ -0,0 +1,238 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
+use uuid::Uuid;
+
+/// Identifies one AI request for as long as it's in flight
+pub type RequestId = u64;
+
+/// The feature an AI request was made for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AiFeature {
+    /// A prompt from the AI panel
+    Chat,
+    /// A shell command from natural language
+    CommandGeneration,
+    /// What a suggested command and its flags do
+    Explanation,
+    /// Why a command failed and how to fix it
+    Diagnosis,
+    /// A corrected command for one that failed
+    FixIt,
+    /// The provider's models, for the model picker
+    ModelList,
+}
+
+impl AiFeature {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AiFeature::Chat => "Chat",
+            AiFeature::CommandGeneration => "Command generation",
+            AiFeature::Explanation => "Explanation",
+            AiFeature::Diagnosis => "Diagnosis",
+            AiFeature::FixIt => "Fix-it",
+            AiFeature::ModelList => "Model list",
+        }
+    }
+}
+
+/// Where a request came from, handed back with its answer so the answer
+/// lands in the right place however many requests are in flight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AiOrigin {
+    pub feature: AiFeature,
+    /// The block the request is about, if any
+    pub block_id: Option<Uuid>,
+}
+
+impl AiOrigin {
+    pub fn new(feature: AiFeature) -> Self {
+        Self { feature, block_id: None }
+    }
+
+    pub fn for_block(feature: AiFeature, block_id: Uuid) -> Self {
+        Self { feature, block_id: Some(block_id) }
+    }
+}
+
+/// An AI request that was started and hasn't answered yet
+#[derive(Debug)]
+pub struct RunningRequest {
+    pub id: RequestId,
+    pub origin: AiOrigin,
+    pub provider: String,
+    pub started: Instant,
+    /// False while it waits for a free slot under the concurrency limit
+    active: Arc<AtomicBool>,
+}
+
+impl RunningRequest {
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+}
+
+/// The AI requests in flight, whatever provider they went to. All of them
+/// answer on one channel tagged with their id, so starting a request never
+/// drops another's answer, and at most `max_concurrent` are sent at once.
+pub struct AiRequests<M> {
+    sender: mpsc::UnboundedSender<(RequestId, Option<M>)>,
+    receiver: mpsc::UnboundedReceiver<(RequestId, Option<M>)>,
+    running: Vec<RunningRequest>,
+    next_id: RequestId,
+    slots: Arc<Semaphore>,
+}
+
+impl<M> AiRequests<M> {
+    pub fn new(max_concurrent: usize) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        Self {
+            sender,
+            receiver,
+            running: Vec::new(),
+            next_id: 1,
+            slots: Arc::new(Semaphore::new(max_concurrent.max(1))),
+        }
+    }
+
+    /// Register a request; its task answers through the returned handle
+    pub fn start(&mut self, origin: AiOrigin, provider: impl Into<String>) -> AiRequest<M> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let active = Arc::new(AtomicBool::new(false));
+        self.running.push(RunningRequest {
+            id,
+            origin,
+            provider: provider.into(),
+            started: Instant::now(),
+            active: active.clone(),
+        });
+        AiRequest {
+            id,
+            sender: self.sender.clone(),
+            slots: self.slots.clone(),
+            active,
+            answered: false,
+        }
+    }
+
+    /// Next answer from any request, with where the request came from; the
+    /// request is no longer listed as running
+    pub fn try_recv(&mut self) -> Option<(AiOrigin, M)> {
+        while let Ok((id, message)) = self.receiver.try_recv() {
+            let Some(index) = self.running.iter().position(|request| request.id == id) else {
+                continue;
+            };
+            let request = self.running.remove(index);
+            if let Some(message) = message {
+                return Some((request.origin, message));
+            }
+        }
+        None
+    }
+
+    /// Whether a request for `feature` is in flight
+    pub fn is_running(&self, feature: AiFeature) -> bool {
+        self.running.iter().any(|request| request.origin.feature == feature)
+    }
+
+    /// Requests started and not answered yet, oldest first
+    pub fn running(&self) -> &[RunningRequest] {
+        &self.running
+    }
+
+    /// Answers received and not yet taken
+    pub fn backlog(&self) -> usize {
+        self.receiver.len()
+    }
+}
+
+/// Held by the task doing one request
+pub struct AiRequest<M> {
+    id: RequestId,
+    sender: mpsc::UnboundedSender<(RequestId, Option<M>)>,
+    slots: Arc<Semaphore>,
+    active: Arc<AtomicBool>,
+    answered: bool,
+}
+
+impl<M> AiRequest<M> {
+    pub fn id(&self) -> RequestId {
+        self.id
+    }
+
+    /// Wait for a free slot under the concurrency limit; the request counts
+    /// against it until the permit is dropped
+    pub async fn slot(&self) -> OwnedSemaphorePermit {
+        let permit = self.slots.clone().acquire_owned().await.expect("request slots are never closed");
+        self.active.store(true, Ordering::Relaxed);
+        permit
+    }
+
+    /// Deliver the request's answer
+    pub fn answer(mut self, message: M) {
+        self.answered = true;
+        let _ = self.sender.send((self.id, Some(message)));
+    }
+}
+
+/// A task that ends without answering still stops being listed as running
+impl<M> Drop for AiRequest<M> {
+    fn drop(&mut self) {
+        if !self.answered {
+            let _ = self.sender.send((self.id, None));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_answers_are_kept_apart() {
+        let block_id = Uuid::new_v4();
+        let mut requests = AiRequests::new(2);
+        let models = requests.start(AiOrigin::new(AiFeature::ModelList), "ollama");
+        let explanation = requests.start(AiOrigin::for_block(AiFeature::Explanation, block_id), "ollama");
+        assert_eq!(requests.running().len(), 2);
+        assert!(requests.is_running(AiFeature::Explanation));
+
+        explanation.answer("lists files");
+        models.answer("qwen2.5-coder:3b");
+        assert_eq!(
+            requests.try_recv(),
+            Some((AiOrigin::for_block(AiFeature::Explanation, block_id), "lists files"))
+        );
+        assert_eq!(requests.try_recv(), Some((AiOrigin::new(AiFeature::ModelList), "qwen2.5-coder:3b")));
+        assert!(requests.running().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit() {
+        let mut requests = AiRequests::<()>::new(1);
+        let first = requests.start(AiOrigin::new(AiFeature::Chat), "ollama");
+        let second = requests.start(AiOrigin::new(AiFeature::Chat), "ollama");
+        let held = first.slot().await;
+        assert!(requests.running()[0].is_active());
+
+        let waiting = tokio::spawn(async move {
+            let _slot = second.slot().await;
+        });
+        tokio::task::yield_now().await;
+        assert!(!requests.running()[1].is_active());
+
+        drop(held);
+        waiting.await.unwrap();
+        assert!(requests.running()[1].is_active());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_request_stops_running() {
+        let mut requests = AiRequests::<()>::new(1);
+        drop(requests.start(AiOrigin::new(AiFeature::Chat), "ollama"));
+        assert!(requests.try_recv().is_none());
+        assert!(requests.running().is_empty());
+    }
+}
@@ -0,0 +1,227 @@
+use crate::ai::provider::AiError;
+use serde::de::DeserializeOwned;
+use std::fmt::Display;
+use tokio_stream::{Stream, StreamExt};
+
+/// Payload that ends an OpenAI-style event stream
+const SSE_DONE: &str = "[DONE]";
+
+/// Splits bytes into lines however the network chunked them: a line may
+/// arrive across several chunks, or several lines in one
+#[derive(Debug, Default)]
+pub struct LineDecoder {
+    buffer: Vec<u8>,
+}
+
+impl LineDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a chunk, returning the lines it completed without their `\n` or `\r\n`
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+        let mut lines = Vec::new();
+        let mut start = 0;
+        while let Some(end) = self.buffer[start..].iter().position(|&b| b == b'\n') {
+            lines.push(decode_line(&self.buffer[start..start + end]));
+            start += end + 1;
+        }
+        self.buffer.drain(..start);
+        lines
+    }
+
+    /// The last line, when the stream ended without a trailing newline
+    pub fn finish(&mut self) -> Option<String> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+        let line = decode_line(&self.buffer);
+        self.buffer.clear();
+        Some(line)
+    }
+}
+
+fn decode_line(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Collects server-sent events from lines, yielding the `data` of each event
+/// once the blank line ending it arrives. Comments and other fields are skipped.
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    lines: LineDecoder,
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a chunk, returning the data of the events it completed
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        let lines = self.lines.push(bytes);
+        lines.into_iter().filter_map(|line| self.line(line)).collect()
+    }
+
+    /// The data of an event cut off by the end of the stream
+    pub fn finish(&mut self) -> Option<String> {
+        if let Some(line) = self.lines.finish() {
+            self.line(line);
+        }
+        self.dispatch()
+    }
+
+    fn line(&mut self, line: String) -> Option<String> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if let Some(value) = line.strip_prefix("data") {
+            if value.is_empty() || value.starts_with(':') {
+                let value = value.strip_prefix(':').unwrap_or(value);
+                self.data.push(value.strip_prefix(' ').unwrap_or(value).to_string());
+            }
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<String> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let data = self.data.join("\n");
+        self.data.clear();
+        Some(data)
+    }
+}
+
+/// Parse a newline-delimited JSON response body (Ollama) into one value per line
+pub fn ndjson_stream<T, S, B, E>(bytes: S) -> impl Stream<Item = Result<T, AiError>> + Send
+where
+    T: DeserializeOwned + Send,
+    S: Stream<Item = Result<B, E>> + Send,
+    B: AsRef<[u8]> + Send,
+    E: Display + Send,
+{
+    async_stream::stream! {
+        let mut decoder = LineDecoder::new();
+        tokio::pin!(bytes);
+        while let Some(chunk) = bytes.next().await {
+            let lines = match chunk {
+                Ok(chunk) => decoder.push(chunk.as_ref()),
+                Err(e) => {
+                    yield Err(AiError::StreamError(e.to_string()));
+                    return;
+                }
+            };
+            for line in lines.into_iter().filter(|line| !line.trim().is_empty()) {
+                yield parse_json(&line);
+            }
+        }
+        if let Some(line) = decoder.finish().filter(|line| !line.trim().is_empty()) {
+            yield parse_json(&line);
+        }
+    }
+}
+
+/// Parse a server-sent event body (OpenAI-compatible APIs) into the JSON
+/// `data` of each event, ending at `[DONE]`
+pub fn sse_stream<T, S, B, E>(bytes: S) -> impl Stream<Item = Result<T, AiError>> + Send
+where
+    T: DeserializeOwned + Send,
+    S: Stream<Item = Result<B, E>> + Send,
+    B: AsRef<[u8]> + Send,
+    E: Display + Send,
+{
+    async_stream::stream! {
+        let mut decoder = SseDecoder::new();
+        tokio::pin!(bytes);
+        while let Some(chunk) = bytes.next().await {
+            let events = match chunk {
+                Ok(chunk) => decoder.push(chunk.as_ref()),
+                Err(e) => {
+                    yield Err(AiError::StreamError(e.to_string()));
+                    return;
+                }
+            };
+            for data in events {
+                if data == SSE_DONE {
+                    return;
+                }
+                yield parse_json(&data);
+            }
+        }
+        if let Some(data) = decoder.finish().filter(|data| data != SSE_DONE) {
+            yield parse_json(&data);
+        }
+    }
+}
+
+fn parse_json<T: DeserializeOwned>(text: &str) -> Result<T, AiError> {
+    serde_json::from_str(text).map_err(|e| AiError::StreamError(format!("{}: {}", e, text)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Chunk {
+        text: String,
+    }
+
+    fn chunks(parts: &[&'static str]) -> impl Stream<Item = Result<&'static [u8], String>> {
+        tokio_stream::iter(parts.iter().map(|part| Ok(part.as_bytes())).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_lines_split_across_chunks() {
+        let mut decoder = LineDecoder::new();
+        assert!(decoder.push(b"{\"a\":").is_empty());
+        assert_eq!(decoder.push(b"1}\r\n{\"b\":2}\n{\"c\""), ["{\"a\":1}", "{\"b\":2}"]);
+        assert_eq!(decoder.finish().as_deref(), Some("{\"c\""));
+        assert_eq!(decoder.finish(), None);
+
+        // A character split between chunks survives
+        let bytes = "ü\n".as_bytes();
+        assert!(decoder.push(&bytes[..1]).is_empty());
+        assert_eq!(decoder.push(&bytes[1..]), ["ü"]);
+    }
+
+    #[test]
+    fn test_sse_events() {
+        let mut decoder = SseDecoder::new();
+        assert!(decoder.push(b": keep-alive\n\nevent: message\ndata: {\"a\"").is_empty());
+        assert_eq!(decoder.push(b":1}\n\ndata: one\ndata:two\n\n"), ["{\"a\":1}", "one\ntwo"]);
+        assert!(decoder.push(b"data: [DONE]").is_empty());
+        assert_eq!(decoder.finish().as_deref(), Some("[DONE]"));
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_stream() {
+        let stream = ndjson_stream::<Chunk, _, _, _>(chunks(&["{\"text\":\"a\"}\n{\"te", "xt\":\"b\"}\n\n{\"text\":\"c\"}"]));
+        let texts: Vec<_> = stream.map(|chunk| chunk.unwrap().text).collect().await;
+        assert_eq!(texts, ["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn test_sse_stream_stops_at_done() {
+        let stream = sse_stream::<Chunk, _, _, _>(chunks(&[
+            "data: {\"text\":\"a\"}\n\nda",
+            "ta: {\"text\":\"b\"}\n\ndata: [DONE]\n\n",
+            "data: {\"text\":\"ignored\"}\n\n",
+        ]));
+        let texts: Vec<_> = stream.map(|chunk| chunk.unwrap().text).collect().await;
+        assert_eq!(texts, ["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_bad_json_is_an_error() {
+        let stream = ndjson_stream::<Chunk, _, _, _>(chunks(&["not json\n"]));
+        let results: Vec<_> = stream.collect().await;
+        assert!(matches!(&results[..], [Err(AiError::StreamError(_))]));
+    }
+}
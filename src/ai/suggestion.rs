@@ -0,0 +1,219 @@
+/// Split a command generation reply into its candidate commands, best first.
+/// Models tend to number or bullet alternatives and wrap them in code
+/// fences despite being asked not to, so those are stripped. A fenced block
+/// without list markers is one command, however many lines it has, and so
+/// are lines continued with `\` and here-documents. With a single candidate
+/// asked for, the whole reply is the command.
+pub fn parse_command_candidates(reply: &str, max: usize) -> Vec<String> {
+    if max <= 1 {
+        return vec![reply.trim().to_string()];
+    }
+    let fences = fenced_blocks(reply);
+    let mut found = Vec::new();
+    if fences.is_empty() {
+        found = split_commands(reply);
+    }
+    for body in fences {
+        if body.lines().any(|line| is_list_item(line.trim())) {
+            found.extend(split_commands(&body));
+        } else if !body.trim().is_empty() {
+            found.push(body.trim().to_string());
+        }
+    }
+
+    let mut candidates: Vec<String> = Vec::new();
+    for command in found {
+        if !candidates.contains(&command) {
+            candidates.push(command);
+        }
+        if candidates.len() == max {
+            break;
+        }
+    }
+    if candidates.is_empty() {
+        candidates.push(reply.trim().to_string());
+    }
+    candidates
+}
+
+/// Bodies of the ``` fenced blocks in a reply; an unclosed fence runs to the end
+fn fenced_blocks(reply: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut body: Option<Vec<&str>> = None;
+    for line in reply.lines() {
+        if line.trim_start().starts_with("```") {
+            match body.take() {
+                Some(lines) => blocks.push(lines.join("\n")),
+                None => body = Some(Vec::new()),
+            }
+        } else if let Some(lines) = &mut body {
+            lines.push(line);
+        }
+    }
+    if let Some(lines) = body {
+        blocks.push(lines.join("\n"));
+    }
+    blocks
+}
+
+/// One command per line, or per item when the text is a list. Lines
+/// continued with a trailing `\`, here-document bodies and, in a list,
+/// unmarked lines under an item stay with their command.
+fn split_commands(text: &str) -> Vec<String> {
+    let listed = text.lines().any(|line| is_list_item(line.trim()));
+    let mut commands: Vec<String> = Vec::new();
+    let mut continued = false;
+    let mut heredoc: Option<String> = None;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if let Some(terminator) = &heredoc {
+            if let Some(command) = commands.last_mut() {
+                command.push('\n');
+                command.push_str(line);
+            }
+            if trimmed == terminator {
+                heredoc = None;
+            }
+            continue;
+        }
+        if trimmed.is_empty() {
+            continue;
+        }
+        match commands.last_mut() {
+            Some(command) if continued || (listed && !is_list_item(trimmed)) => {
+                command.push('\n');
+                command.push_str(trimmed);
+            }
+            _ => commands.push(strip_list_marker(trimmed).trim().trim_matches('`').trim().to_string()),
+        }
+        continued = trimmed.ends_with('\\');
+        heredoc = heredoc_terminator(trimmed);
+    }
+    commands.retain(|command| !command.is_empty());
+    commands
+}
+
+/// The word ending a here-document started on `line`, e.g. `EOF` for `cat <<'EOF'`
+fn heredoc_terminator(line: &str) -> Option<String> {
+    let (_, rest) = line.split_once("<<")?;
+    // `<<<` is a here-string, all on one line
+    if rest.starts_with('<') {
+        return None;
+    }
+    let rest = rest.strip_prefix(['-', '~']).unwrap_or(rest).trim_start();
+    let word: String = rest
+        .chars()
+        .take_while(|c| !c.is_whitespace() && !matches!(c, ';' | '|' | '&' | '>' | ')'))
+        .collect();
+    let word = word.trim_matches(|c| c == '\'' || c == '"');
+    (!word.is_empty()).then(|| word.to_string())
+}
+
+/// What a suggested command does, for people who don't know it by heart
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandExplanation {
+    /// One sentence on the command as a whole
+    pub summary: String,
+    /// Each flag or option it uses, with what it means
+    pub flags: Vec<(String, String)>,
+}
+
+/// Read an explanation reply: a sentence, then one `flag: meaning` line per flag
+pub fn parse_command_explanation(reply: &str) -> CommandExplanation {
+    let mut explanation = CommandExplanation::default();
+    let lines = reply
+        .lines()
+        .map(|line| strip_list_marker(line.trim()).trim())
+        .filter(|line| !line.is_empty() && !line.starts_with("```"));
+    for line in lines {
+        if explanation.summary.is_empty() {
+            explanation.summary = line.to_string();
+            continue;
+        }
+        if let Some((flag, meaning)) = line.split_once(": ") {
+            let flag = flag.trim().trim_matches('`');
+            // Other lines with a colon are prose, not flags
+            if flag.starts_with('-') || (!flag.is_empty() && !flag.contains(char::is_whitespace)) {
+                explanation.flags.push((flag.to_string(), meaning.trim().to_string()));
+            }
+        }
+    }
+    explanation
+}
+
+/// Whether `line` starts with a list marker
+fn is_list_item(line: &str) -> bool {
+    strip_list_marker(line).len() != line.len()
+}
+
+/// `1. ls`, `2) ls`, `- ls` and `* ls` all become `ls`
+fn strip_list_marker(line: &str) -> &str {
+    if let Some(rest) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+        return rest;
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 {
+        if let Some(rest) = line[digits..].strip_prefix(". ").or_else(|| line[digits..].strip_prefix(") ")) {
+            return rest;
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command_candidates() {
+        let reply = "```sh\n1. find . -name '*.rs'\n2) `fd -e rs`\n- find . -name '*.rs'\n\n* ls **/*.rs\n```";
+        assert_eq!(
+            parse_command_candidates(reply, 3),
+            ["find . -name '*.rs'", "fd -e rs", "ls **/*.rs"]
+        );
+        assert_eq!(parse_command_candidates(reply, 2).len(), 2);
+
+        // A single candidate keeps the reply as it was, line breaks included
+        assert_eq!(parse_command_candidates(" for f in *; do\n  echo $f\ndone\n", 1), ["for f in *; do\n  echo $f\ndone"]);
+        assert_eq!(parse_command_candidates("2>/dev/null ls", 3), ["2>/dev/null ls"]);
+    }
+
+    #[test]
+    fn test_multi_line_candidates() {
+        let reply = "```bash\ncat > app.conf <<'EOF'\nport = 8080\n\nEOF\n```\n\n```sh\ndocker run \\\n  -p 8080:8080 app\n```";
+        assert_eq!(
+            parse_command_candidates(reply, 3),
+            ["cat > app.conf <<'EOF'\nport = 8080\n\nEOF", "docker run \\\n  -p 8080:8080 app"]
+        );
+
+        let reply = "tar czf backup.tgz \\\n  src docs\nzip -r backup.zip src docs";
+        assert_eq!(
+            parse_command_candidates(reply, 3),
+            ["tar czf backup.tgz \\\nsrc docs", "zip -r backup.zip src docs"]
+        );
+
+        let reply = "1. cat <<EOF > notes.txt\n- first\nEOF\n2. grep -c . notes.txt <<< \"x\"\n3. wc -l notes.txt";
+        assert_eq!(
+            parse_command_candidates(reply, 3),
+            ["cat <<EOF > notes.txt\n- first\nEOF", "grep -c . notes.txt <<< \"x\"", "wc -l notes.txt"]
+        );
+    }
+
+    #[test]
+    fn test_parse_command_explanation() {
+        let reply = "Lists files larger than 100 MB under the current directory.\n\n\
+                     - `-type f`: only regular files\n\
+                     -size +100M: bigger than 100 MB\n\
+                     Note that this may take a while: it walks every directory";
+        let explanation = parse_command_explanation(reply);
+        assert_eq!(explanation.summary, "Lists files larger than 100 MB under the current directory.");
+        assert_eq!(
+            explanation.flags,
+            [
+                ("-type f".to_string(), "only regular files".to_string()),
+                ("-size +100M".to_string(), "bigger than 100 MB".to_string()),
+            ]
+        );
+        assert_eq!(parse_command_explanation("").summary, "");
+    }
+}
@@ -1,4 +1,4 @@
-use super::Config;
+use super::{CommandHook, Config, SyncBackendConfig};
 use anyhow::{Context, Result};
 use directories::ProjectDirs;
 use std::fs;
@@ -83,6 +83,29 @@ impl Config {
             }
         }
         
+        // Expand sync credentials
+        if let Some(passphrase) = &self.sync.passphrase {
+            self.sync.passphrase = Some(Self::expand_env_var(passphrase));
+        }
+        match &mut self.sync.backend {
+            Some(SyncBackendConfig::WebDav { password: Some(password), .. }) => {
+                *password = Self::expand_env_var(password);
+            }
+            Some(SyncBackendConfig::S3 { access_key, secret_key, .. }) => {
+                *access_key = Self::expand_env_var(access_key);
+                *secret_key = Self::expand_env_var(secret_key);
+            }
+            _ => {}
+        }
+        if let Some(token) = &self.share.github_token {
+            self.share.github_token = Some(Self::expand_env_var(token));
+        }
+        for hook in self.hooks.pre.iter_mut().chain(self.hooks.post.iter_mut()) {
+            if let CommandHook::Webhook { url } = hook {
+                *url = Self::expand_env_var(url);
+            }
+        }
+
         // Expand MCP server environment variables
         for server in &mut self.mcp.servers {
             for (_, value) in server.env.iter_mut() {
@@ -126,4 +149,92 @@ mod tests {
         let not_expanded = Config::expand_env_var("normal_value");
         assert_eq!(not_expanded, "normal_value");
     }
+
+    #[test]
+    fn test_prompt_segments_from_toml() {
+        let mut config = Config::default();
+        config.prompt.segments = vec![crate::config::PromptSegment::Time];
+        let toml = toml::to_string_pretty(&config).unwrap();
+        assert!(toml.contains("segments = [\"time\"]"));
+
+        let parsed: Config = toml::from_str(&toml).unwrap();
+        assert_eq!(parsed.prompt.segments, vec![crate::config::PromptSegment::Time]);
+    }
+
+    #[test]
+    fn test_command_examples_from_toml() {
+        let mut config = Config::default();
+        assert!(config.ai.command_examples.is_empty());
+        config.ai.command_examples.push(crate::config::CommandExample {
+            request: "deploy to staging".to_string(),
+            command: "terraform -chdir=envs/staging apply".to_string(),
+        });
+        let toml = toml::to_string_pretty(&config).unwrap();
+        assert!(toml.contains("[[ai.command_examples]]"));
+
+        let parsed: Config = toml::from_str(&toml).unwrap();
+        assert_eq!(parsed.ai.command_examples, config.ai.command_examples);
+    }
+
+    #[test]
+    fn test_command_temperature_is_clamped() {
+        let mut config = Config::default();
+        assert_eq!(config.ai.command_sampling_temperature(), 0.0);
+        config.ai.command_temperature = 0.7;
+        assert_eq!(config.ai.command_sampling_temperature(), 0.7);
+        config.ai.command_temperature = 5.0;
+        assert_eq!(config.ai.command_sampling_temperature(), 2.0);
+        config.ai.command_temperature = -1.0;
+        assert_eq!(config.ai.command_sampling_temperature(), 0.0);
+        config.ai.command_temperature = f32::NAN;
+        assert_eq!(config.ai.command_sampling_temperature(), 0.0);
+    }
+
+    #[test]
+    fn test_context_overflow_from_toml() {
+        let mut config = Config::default();
+        assert_eq!(config.ai.context_overflow, crate::ai::OverflowStrategy::Drop);
+        config.ai.context_overflow = crate::ai::OverflowStrategy::FailuresOnly;
+        let toml = toml::to_string_pretty(&config).unwrap();
+        assert!(toml.contains("context_overflow = \"failures_only\""));
+
+        let parsed: Config = toml::from_str(&toml).unwrap();
+        assert_eq!(parsed.ai.context_overflow, crate::ai::OverflowStrategy::FailuresOnly);
+    }
+
+    #[test]
+    fn test_workspace_trust() {
+        let root = tempfile::tempdir().unwrap();
+        let trusted = root.path().join("trusted");
+        let evil = root.path().join("evil");
+        std::fs::create_dir_all(trusted.join("sub")).unwrap();
+        std::fs::create_dir(&evil).unwrap();
+
+        let mut general = Config::default().general;
+        general.workspace_trust = true;
+        general.trusted_directories = vec![trusted.clone()];
+
+        assert!(general.trusts(&trusted));
+        assert!(general.trusts(&trusted.join("sub")));
+        assert!(!general.trusts(&evil));
+        // Lexically below the trusted directory, but resolves outside it
+        assert!(!general.trusts(&trusted.join("..").join("evil")));
+        // Paths that don't exist can't be resolved and aren't trusted
+        assert!(!general.trusts(&trusted.join("missing")));
+
+        general.workspace_trust = false;
+        assert!(general.trusts(&evil));
+    }
+
+    #[test]
+    fn test_window_layout_round_trip() {
+        let mut config = Config::default();
+        config.window.size = Some([1400.0, 900.0]);
+        config.window.position = Some([-1920.0, 40.0]);
+        config.window.panel_widths.insert("plugin_panel".to_string(), 280.0);
+        let toml = toml::to_string_pretty(&config).unwrap();
+
+        let parsed: Config = toml::from_str(&toml).unwrap();
+        assert_eq!(parsed.window, config.window);
+    }
 }
@@ -1,5 +1,7 @@
+use crate::ai::OverflowStrategy;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -8,6 +10,34 @@ pub struct Config {
     pub ai: AiConfig,
     pub mcp: McpConfig,
     pub keybindings: KeybindingsConfig,
+    #[serde(default)]
+    pub prompt: PromptConfig,
+    #[serde(default)]
+    pub kubernetes: KubernetesConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    #[serde(default)]
+    pub sync: SyncConfig,
+    #[serde(default)]
+    pub share: ShareConfig,
+    #[serde(default)]
+    pub scripting: ScriptingConfig,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    #[serde(default)]
+    pub validation: ValidationConfig,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub dangerous_commands: DangerousCommandsConfig,
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    #[serde(default)]
+    pub block_actions: Vec<BlockActionConfig>,
+    #[serde(default)]
+    pub global_hotkey: GlobalHotkeyConfig,
+    #[serde(default)]
+    pub window: WindowConfig,
 }
 
 impl Default for Config {
@@ -18,6 +48,20 @@ impl Default for Config {
             ai: AiConfig::default(),
             mcp: McpConfig::default(),
             keybindings: KeybindingsConfig::default(),
+            prompt: PromptConfig::default(),
+            kubernetes: KubernetesConfig::default(),
+            storage: StorageConfig::default(),
+            sync: SyncConfig::default(),
+            share: ShareConfig::default(),
+            scripting: ScriptingConfig::default(),
+            hooks: HooksConfig::default(),
+            validation: ValidationConfig::default(),
+            retry: RetryConfig::default(),
+            dangerous_commands: DangerousCommandsConfig::default(),
+            redaction: RedactionConfig::default(),
+            block_actions: Vec::new(),
+            global_hotkey: GlobalHotkeyConfig::default(),
+            window: WindowConfig::default(),
         }
     }
 }
@@ -28,6 +72,98 @@ pub struct GeneralConfig {
     pub save_history: bool,
     pub max_history_size: usize,
     pub auto_save_interval: u64, // seconds
+    /// Recall commands from all sessions with Up/Down, not just the current one
+    #[serde(default)]
+    pub global_history: bool,
+    /// Modal (normal/insert) vim-style editing in the command input
+    #[serde(default)]
+    pub vim_mode: bool,
+    /// Launching inside a git repository opens (or creates) that repository's session
+    #[serde(default)]
+    pub project_sessions: bool,
+    /// Apply direnv's environment for the working directory (once the .envrc is allowed)
+    #[serde(default = "default_true")]
+    pub direnv: bool,
+    /// Interface language, e.g. `en-US` or `de`
+    #[serde(default = "default_language")]
+    pub language: String,
+    /// Leave a running command alone on exit instead of hanging it up. It still
+    /// loses its terminal, so only commands that ignore SIGHUP (e.g. under nohup) survive.
+    #[serde(default)]
+    pub detach_on_exit: bool,
+    /// Suggest the most used command starting with what has been typed
+    #[serde(default = "default_true")]
+    pub command_suggestions: bool,
+    /// Input starting with this is evaluated as arithmetic instead of run;
+    /// empty turns the calculator off
+    #[serde(default = "default_calc_prefix")]
+    pub calc_prefix: String,
+    /// Where text selected in output is copied when the mouse is released
+    #[serde(default)]
+    pub copy_on_select: CopyOnSelect,
+    /// Middle-click pastes the primary selection (or clipboard) into the command input
+    #[serde(default = "default_true")]
+    pub middle_click_paste: bool,
+    /// Ask before a session opening in an untrusted directory runs its
+    /// startup commands, loads its .env file or applies direnv
+    #[serde(default = "default_true")]
+    pub workspace_trust: bool,
+    /// Directories, and everything below them, trusted to do that unasked
+    #[serde(default)]
+    pub trusted_directories: Vec<PathBuf>,
+    /// Nothing leaves the machine: only AI providers on localhost are used,
+    /// and network sync, sharing and webhook hooks are off
+    #[serde(default)]
+    pub offline: bool,
+    /// Stop a broadcast command still running in another session after this
+    /// many seconds (0 never stops it)
+    #[serde(default = "default_broadcast_timeout")]
+    pub broadcast_timeout_secs: u64,
+}
+
+impl GeneralConfig {
+    /// Whether a session in `dir` may run its automatic setup without asking.
+    /// Both sides are canonicalized so `..` and symlinks can't escape a
+    /// trusted directory; a path that can't be resolved is not trusted.
+    pub fn trusts(&self, dir: &Path) -> bool {
+        if !self.workspace_trust {
+            return true;
+        }
+        let Ok(dir) = dir.canonicalize() else {
+            return false;
+        };
+        self.trusted_directories
+            .iter()
+            .filter_map(|trusted| trusted.canonicalize().ok())
+            .any(|trusted| dir.starts_with(trusted))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CopyOnSelect {
+    Off,
+    /// The X11 primary selection, which Wayland sessions reach through
+    /// XWayland; nothing on other platforms
+    #[default]
+    Primary,
+    Clipboard,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_calc_prefix() -> String {
+    "=".to_string()
+}
+
+fn default_broadcast_timeout() -> u64 {
+    600
+}
+
+fn default_language() -> String {
+    crate::i18n::DEFAULT_LANGUAGE.to_string()
 }
 
 impl Default for GeneralConfig {
@@ -37,6 +173,20 @@ impl Default for GeneralConfig {
             save_history: true,
             max_history_size: 10000,
             auto_save_interval: 30,
+            global_history: false,
+            vim_mode: false,
+            project_sessions: false,
+            direnv: true,
+            language: default_language(),
+            detach_on_exit: false,
+            command_suggestions: true,
+            calc_prefix: default_calc_prefix(),
+            copy_on_select: CopyOnSelect::default(),
+            middle_click_paste: true,
+            workspace_trust: true,
+            trusted_directories: Vec::new(),
+            offline: false,
+            broadcast_timeout_secs: default_broadcast_timeout(),
         }
     }
 }
@@ -48,6 +198,28 @@ pub struct AppearanceConfig {
     pub font_size: f32,
     pub show_line_numbers: bool,
     pub block_spacing: f32,
+    /// Render each block as a single log row that expands on click
+    #[serde(default)]
+    pub log_view: bool,
+    /// Show block state with patterns instead of colors
+    #[serde(default)]
+    pub state_patterns: bool,
+    /// Show the git sidebar with the repository's changes
+    #[serde(default)]
+    pub git_panel: bool,
+    /// Show the file tree of the working directory
+    #[serde(default)]
+    pub file_browser: bool,
+    /// Color error and warning lines in output and mark them by the scrollbar
+    #[serde(default = "default_true")]
+    pub highlight_diagnostics: bool,
+    /// Fold the phases of recognized build tools into collapsible sections
+    #[serde(default = "default_true")]
+    pub output_sections: bool,
+    /// Syntax highlight output recognized as JSON, YAML, TOML, SQL, Rust or
+    /// a Python traceback
+    #[serde(default = "default_true")]
+    pub highlight_output: bool,
 }
 
 impl Default for AppearanceConfig {
@@ -58,10 +230,404 @@ impl Default for AppearanceConfig {
             font_size: 14.0,
             show_line_numbers: true,
             block_spacing: 8.0,
+            log_view: false,
+            state_patterns: false,
+            git_panel: false,
+            file_browser: false,
+            highlight_diagnostics: true,
+            output_sections: true,
+            highlight_output: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptConfig {
+    /// Segments shown in the prompt row above the command input, in order
+    pub segments: Vec<PromptSegment>,
+}
+
+impl Default for PromptConfig {
+    fn default() -> Self {
+        Self {
+            segments: vec![
+                PromptSegment::Cwd,
+                PromptSegment::GitBranch,
+                PromptSegment::Venv,
+                PromptSegment::Node,
+                PromptSegment::ExitCode,
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptSegment {
+    Cwd,
+    GitBranch,
+    ExitCode,
+    Time,
+    Venv,
+    /// Node version from nvm/asdf, or the one the project pins
+    Node,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KubernetesConfig {
+    /// Show the current kube context/namespace in the status bar
+    pub enabled: bool,
+    /// Contexts or clusters containing any of these (case-insensitive) get a warning badge
+    pub production_patterns: Vec<String>,
+}
+
+impl Default for KubernetesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            production_patterns: vec!["prod".to_string()],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageConfig {
+    /// Remove blocks older than this many days at startup (0 keeps them forever)
+    pub max_block_age_days: u32,
+    /// Keep at most this many of the newest blocks per session (0 for no limit)
+    pub max_blocks_per_session: u32,
+    /// Append removed blocks to an archive file in the data directory instead of discarding them
+    pub archive_removed_blocks: bool,
+    /// Encrypt commands and output in the database with a key kept in the OS keyring
+    #[serde(default)]
+    pub encrypt_history: bool,
+    /// Snapshot the database into the data directory this often (0 disables backups)
+    #[serde(default = "default_backup_interval_hours")]
+    pub backup_interval_hours: u32,
+    /// Number of database snapshots kept before the oldest are deleted
+    #[serde(default = "default_backups_to_keep")]
+    pub backups_to_keep: u32,
+    /// Record the values of `snapshot_variables` with each command. They are
+    /// stored unencrypted, even with `encrypt_history`.
+    #[serde(default)]
+    pub snapshot_environment: bool,
+    /// Environment variables recorded with each command
+    #[serde(default = "default_snapshot_variables")]
+    pub snapshot_variables: Vec<String>,
+}
+
+fn default_snapshot_variables() -> Vec<String> {
+    ["PATH", "VIRTUAL_ENV", "CONDA_DEFAULT_ENV", "KUBECONFIG", "AWS_PROFILE", "NODE_ENV"]
+        .iter()
+        .map(|name| name.to_string())
+        .collect()
+}
+
+fn default_backup_interval_hours() -> u32 {
+    24
+}
+
+fn default_backups_to_keep() -> u32 {
+    7
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            max_block_age_days: 0,
+            max_blocks_per_session: 0,
+            archive_removed_blocks: true,
+            encrypt_history: false,
+            backup_interval_hours: default_backup_interval_hours(),
+            backups_to_keep: default_backups_to_keep(),
+            snapshot_environment: false,
+            snapshot_variables: default_snapshot_variables(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncConfig {
+    /// Where sessions are synced; unset disables sync
+    #[serde(default)]
+    pub backend: Option<SyncBackendConfig>,
+    /// Encrypt synced sessions with this passphrase (same format as encrypted exports)
+    #[serde(default)]
+    pub passphrase: Option<String>,
+    /// Sync automatically every this many minutes (0 syncs only on request)
+    #[serde(default)]
+    pub interval_minutes: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShareConfig {
+    /// GitHub token with the `gist` scope, for sharing as secret gists
+    #[serde(default)]
+    pub github_token: Option<String>,
+    /// Paste service that accepts the text as a POST body and returns its URL
+    #[serde(default)]
+    pub paste_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptingConfig {
+    /// Run the hooks defined in the Rhai script
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Script to load instead of `init.rhai` in the config directory
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+impl Default for ScriptingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: None,
+        }
+    }
+}
+
+/// Hooks run, in order, before and after every command
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HooksConfig {
+    #[serde(default)]
+    pub pre: Vec<CommandHook>,
+    #[serde(default)]
+    pub post: Vec<CommandHook>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CommandHook {
+    /// Refuse commands matching a regular expression (pre hooks only)
+    Refuse {
+        pattern: String,
+        #[serde(default)]
+        message: Option<String>,
+    },
+    /// POST a JSON description of the command to a URL
+    Webhook { url: String },
+    /// Append one line per command to a file
+    AuditLog { path: String },
+    /// Run a shell command with `IMMATERIUM_*` variables describing the
+    /// command; a failing pre hook refuses the command
+    Run { command: String },
+}
+
+/// Checks run on commands before they are run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationConfig {
+    /// Check suggested commands with `bash -n` and show problems on the suggestion
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Also run shellcheck, when it is installed
+    #[serde(default = "default_true")]
+    pub shellcheck: bool,
+    /// Check typed commands too, holding back those with errors for a second look
+    #[serde(default)]
+    pub typed_commands: bool,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            shellcheck: true,
+            typed_commands: false,
+        }
+    }
+}
+
+/// Re-running commands that failed with a transient error
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryConfig {
+    /// Attempts "Retry with backoff" makes before giving up
+    #[serde(default = "default_retry_attempts")]
+    pub max_attempts: u32,
+    /// Wait before the first automatic retry, doubled after each one
+    #[serde(default = "default_retry_delay_secs")]
+    pub initial_delay_secs: u64,
+}
+
+fn default_retry_attempts() -> u32 {
+    3
+}
+
+fn default_retry_delay_secs() -> u64 {
+    2
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_retry_attempts(),
+            initial_delay_secs: default_retry_delay_secs(),
+        }
+    }
+}
+
+/// Asking before typed commands that are hard to undo are run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DangerousCommandsConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Commands to confirm, e.g. `rm -rf`: the command and subcommand words
+    /// plus flags that must all be present
+    #[serde(default = "default_dangerous_patterns")]
+    pub patterns: Vec<String>,
+    /// Exact commands confirmed with "Don't ask again"
+    #[serde(default)]
+    pub allowed: Vec<String>,
+}
+
+fn default_dangerous_patterns() -> Vec<String> {
+    ["rm -rf", "git push --force", "git push -f", "git reset --hard", "terraform destroy", "kubectl delete"]
+        .iter()
+        .map(|p| p.to_string())
+        .collect()
+}
+
+impl Default for DangerousCommandsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            patterns: default_dangerous_patterns(),
+            allowed: Vec::new(),
+        }
+    }
+}
+
+/// Masking secrets in output as it's shown and copied, e.g. while screen
+/// sharing; what's stored is left as it was
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// The rules used when sharing: tokens, API keys, passwords, private keys
+    #[serde(default = "default_true")]
+    pub builtin: bool,
+    /// More regexes to mask; the first capture group, or else the whole match
+    #[serde(default)]
+    pub patterns: Vec<String>,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            builtin: true,
+            patterns: Vec::new(),
+        }
+    }
+}
+
+/// Window layout restored at startup, updated as the window changes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WindowConfig {
+    /// Inner size in points, when not maximized
+    #[serde(default)]
+    pub size: Option<[f32; 2]>,
+    /// Outer position in desktop coordinates (may be on any monitor)
+    #[serde(default)]
+    pub position: Option<[f32; 2]>,
+    #[serde(default)]
+    pub maximized: bool,
+    #[serde(default)]
+    pub fullscreen: bool,
+    /// Width of each resizable side panel, by panel id
+    #[serde(default)]
+    pub panel_widths: HashMap<String, f32>,
+}
+
+/// System-wide shortcut that shows or hides the window from any application
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlobalHotkeyConfig {
+    /// e.g. `ctrl+backquote`; unset disables the hotkey
+    #[serde(default)]
+    pub shortcut: Option<String>,
+    /// Slide down from the top of the screen, quake-style
+    #[serde(default)]
+    pub slide_down: bool,
+    /// Height of the slide-down window as a fraction of the monitor
+    #[serde(default = "default_slide_height")]
+    pub height_fraction: f32,
+}
+
+fn default_slide_height() -> f32 {
+    0.5
+}
+
+impl Default for GlobalHotkeyConfig {
+    fn default() -> Self {
+        Self {
+            shortcut: None,
+            slide_down: false,
+            height_fraction: default_slide_height(),
         }
     }
 }
 
+/// Custom entry in the block context menu
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockActionConfig {
+    pub label: String,
+    /// Shell command run with `IMMATERIUM_*` variables describing the block
+    pub command: String,
+    /// Write the block output to the command's stdin
+    #[serde(default)]
+    pub stdin: bool,
+    /// What to do with the command's stdout
+    #[serde(default)]
+    pub result: BlockActionResult,
+    /// Stop the command if it is still running after this many seconds
+    /// (0 never stops it)
+    #[serde(default = "default_action_timeout")]
+    pub timeout_secs: u64,
+}
+
+fn default_action_timeout() -> u64 {
+    60
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockActionResult {
+    /// Show the first line in the status bar
+    #[default]
+    Status,
+    Clipboard,
+    /// Place it in the command input
+    Insert,
+    /// Add it to the session as a new block
+    Block,
+    None,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SyncBackendConfig {
+    /// A local or mounted directory (e.g. a Syncthing or Dropbox folder)
+    Directory { path: String },
+    WebDav {
+        url: String,
+        #[serde(default)]
+        username: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+    },
+    /// An S3-compatible bucket, addressed path-style (`endpoint/bucket/key`)
+    S3 {
+        endpoint: String,
+        bucket: String,
+        region: String,
+        access_key: String,
+        secret_key: String,
+        #[serde(default)]
+        prefix: Option<String>,
+    },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AiConfig {
     pub default_provider: String,
@@ -70,6 +636,96 @@ pub struct AiConfig {
     pub providers: HashMap<String, AiProviderConfig>,
     #[serde(default)]
     pub selected_model: Option<String>, // Last selected model
+    #[serde(default)]
+    pub audit: AiAuditConfig,
+    /// Send the last command along with natural-language requests when it failed
+    #[serde(default = "default_true")]
+    pub include_last_failure: bool,
+    /// Requests paired with the commands expected for them, sent ahead of
+    /// each natural-language request so suggestions follow local conventions
+    #[serde(default)]
+    pub command_examples: Vec<CommandExample>,
+    /// Alternative commands to ask for per natural-language request, offered
+    /// as options on the suggestion
+    #[serde(default = "default_command_candidates")]
+    pub command_candidates: usize,
+    /// Ask for a short explanation of each suggested command and its flags
+    #[serde(default = "default_true")]
+    pub explain_commands: bool,
+    /// Sampling temperature for natural-language-to-command requests; 0
+    /// gives the same command for the same request instead of a creative one.
+    /// Read it through [`AiConfig::command_sampling_temperature`].
+    #[serde(default)]
+    pub command_temperature: f32,
+    /// Sampling seed for natural-language-to-command requests, for providers
+    /// that support one
+    #[serde(default)]
+    pub command_seed: Option<u64>,
+    /// Input starting with this is always sent to the AI, whatever the operation mode (empty disables)
+    #[serde(default = "default_ai_prefix")]
+    pub ai_prefix: String,
+    /// Input starting with this always runs in the shell, whatever the operation mode (empty disables)
+    #[serde(default = "default_shell_prefix")]
+    pub shell_prefix: String,
+    /// Most AI requests in flight at once; the rest wait for one to finish
+    #[serde(default = "default_max_concurrent_requests")]
+    pub max_concurrent_requests: usize,
+    /// What happens to AI panel context blocks that don't fit in the budget
+    #[serde(default)]
+    pub context_overflow: OverflowStrategy,
+}
+
+/// Models pinned to particular tasks on one provider; a task without one
+/// uses the model selected in the AI panel
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaskModelsConfig {
+    /// Shell commands generated from natural language
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Prompts from the AI panel
+    #[serde(default)]
+    pub chat: Option<String>,
+    /// Explanations of suggested commands
+    #[serde(default)]
+    pub explanation: Option<String>,
+    /// Why a command failed and how to fix it
+    #[serde(default)]
+    pub diagnosis: Option<String>,
+}
+
+fn default_max_concurrent_requests() -> usize {
+    2
+}
+
+fn default_command_candidates() -> usize {
+    3
+}
+
+fn default_ai_prefix() -> String {
+    "#".to_string()
+}
+
+fn default_shell_prefix() -> String {
+    "!".to_string()
+}
+
+/// A natural-language request and the command it should become
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandExample {
+    pub request: String,
+    pub command: String,
+}
+
+/// Audit log of AI requests, kept in the database
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AiAuditConfig {
+    /// Record every AI request: provider, model, prompt hash, tokens, latency
+    /// and whether a generated command was run
+    #[serde(default)]
+    pub enabled: bool,
+    /// Keep the full prompt and response text, not just a hash of the prompt
+    #[serde(default)]
+    pub full_text: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -82,6 +738,38 @@ pub enum OperationMode {
     Hybrid,            // Mode 3: Auto-detect (default)
 }
 
+impl OperationMode {
+    /// The mode after this one when cycling with the keyboard
+    pub fn next(&self) -> Self {
+        match self {
+            OperationMode::TerminalOnly => OperationMode::AiPromptOnly,
+            OperationMode::AiPromptOnly => OperationMode::Hybrid,
+            OperationMode::Hybrid => OperationMode::TerminalOnly,
+        }
+    }
+
+    /// Short name for the badge next to the command input
+    pub fn label(&self) -> &'static str {
+        match self {
+            OperationMode::TerminalOnly => "Terminal",
+            OperationMode::AiPromptOnly => "AI",
+            OperationMode::Hybrid => "Hybrid",
+        }
+    }
+}
+
+impl AiConfig {
+    /// Command temperature within the 0 to 2 that providers accept; a value
+    /// that isn't a number counts as 0
+    pub fn command_sampling_temperature(&self) -> f32 {
+        if self.command_temperature.is_finite() {
+            self.command_temperature.clamp(0.0, 2.0)
+        } else {
+            0.0
+        }
+    }
+}
+
 impl Default for AiConfig {
     fn default() -> Self {
         let mut providers = HashMap::new();
@@ -93,6 +781,11 @@ impl Default for AiConfig {
                 api_key: None,
                 model: "codellama".to_string(),
                 enabled: true,
+                keep_alive: Some("30m".to_string()),
+                warm_up: true,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                task_models: TaskModelsConfig::default(),
             },
         );
         
@@ -103,6 +796,11 @@ impl Default for AiConfig {
                 api_key: Some("${OPENAI_API_KEY}".to_string()),
                 model: "gpt-4".to_string(),
                 enabled: false,
+                keep_alive: None,
+                warm_up: false,
+                requests_per_minute: None,
+                tokens_per_minute: None,
+                task_models: TaskModelsConfig::default(),
             },
         );
         
@@ -113,6 +811,11 @@ impl Default for AiConfig {
                 api_key: Some("${GROQ_API_KEY}".to_string()),
                 model: "mixtral-8x7b-32768".to_string(),
                 enabled: false,
+                keep_alive: None,
+                warm_up: false,
+                requests_per_minute: Some(30),
+                tokens_per_minute: None,
+                task_models: TaskModelsConfig::default(),
             },
         );
 
@@ -122,6 +825,17 @@ impl Default for AiConfig {
             operation_mode: OperationMode::Hybrid,
             providers,
             selected_model: None,
+            audit: AiAuditConfig::default(),
+            include_last_failure: true,
+            command_examples: Vec::new(),
+            command_candidates: default_command_candidates(),
+            explain_commands: true,
+            command_temperature: 0.0,
+            command_seed: None,
+            ai_prefix: default_ai_prefix(),
+            shell_prefix: default_shell_prefix(),
+            max_concurrent_requests: default_max_concurrent_requests(),
+            context_overflow: OverflowStrategy::default(),
         }
     }
 }
@@ -132,6 +846,21 @@ pub struct AiProviderConfig {
     pub api_key: Option<String>,
     pub model: String,
     pub enabled: bool,
+    /// How long Ollama keeps the model loaded after a request, e.g. `30m`, or `-1` for always
+    #[serde(default)]
+    pub keep_alive: Option<String>,
+    /// Load the model as soon as it is selected so the first request isn't slowed by it
+    #[serde(default)]
+    pub warm_up: bool,
+    /// Most requests sent to the provider per minute; unlimited when unset
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+    /// Most tokens sent to and generated by the provider per minute; unlimited when unset
+    #[serde(default)]
+    pub tokens_per_minute: Option<u32>,
+    /// Models of this provider to use for particular tasks
+    #[serde(default)]
+    pub task_models: TaskModelsConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -175,6 +904,41 @@ pub struct KeybindingsConfig {
     pub split_vertical: String,
     pub close_pane: String,
     pub settings: String,
+    /// Cycle Terminal → AI → Hybrid operation modes
+    #[serde(default = "default_cycle_operation_mode")]
+    pub cycle_operation_mode: String,
+    /// Open the directory quick-jump popup
+    #[serde(default = "default_quick_jump")]
+    pub quick_jump: String,
+    /// Open the keyboard shortcut reference
+    #[serde(default = "default_shortcuts")]
+    pub shortcuts: String,
+    /// Pick a file and insert its shell-escaped path at the input cursor
+    #[serde(default = "default_insert_path")]
+    pub insert_path: String,
+    /// Enter or leave presentation mode
+    #[serde(default = "default_presentation")]
+    pub presentation: String,
+}
+
+fn default_cycle_operation_mode() -> String {
+    "Ctrl+Shift+M".to_string()
+}
+
+fn default_quick_jump() -> String {
+    "Ctrl+G".to_string()
+}
+
+fn default_shortcuts() -> String {
+    "F1".to_string()
+}
+
+fn default_insert_path() -> String {
+    "Ctrl+Shift+O".to_string()
+}
+
+fn default_presentation() -> String {
+    "F5".to_string()
 }
 
 impl Default for KeybindingsConfig {
@@ -188,6 +952,11 @@ impl Default for KeybindingsConfig {
             split_vertical: "Ctrl+Shift+V".to_string(),
             close_pane: "Ctrl+Shift+W".to_string(),
             settings: "Ctrl+,".to_string(),
+            cycle_operation_mode: default_cycle_operation_mode(),
+            quick_jump: default_quick_jump(),
+            shortcuts: default_shortcuts(),
+            insert_path: default_insert_path(),
+            presentation: default_presentation(),
         }
     }
 }
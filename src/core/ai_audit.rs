@@ -0,0 +1,207 @@
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Hex digits of the prompt's SHA-256 kept in the log
+const PROMPT_HASH_LEN: usize = 16;
+
+/// What an AI request was made for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AiRequestKind {
+    /// A prompt from the AI panel
+    Chat,
+    /// A shell command generated from natural language
+    Command,
+    /// An explanation of a suggested command
+    Explanation,
+    /// A summary or notes on history that didn't fit in a chat's context
+    Condense,
+}
+
+impl AiRequestKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AiRequestKind::Chat => "chat",
+            AiRequestKind::Command => "command",
+            AiRequestKind::Explanation => "explanation",
+            AiRequestKind::Condense => "condense",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "command" => AiRequestKind::Command,
+            "explanation" => AiRequestKind::Explanation,
+            "condense" => AiRequestKind::Condense,
+            _ => AiRequestKind::Chat,
+        }
+    }
+}
+
+/// One AI request and its outcome, as kept in the audit log. Prompt and
+/// response text are only kept when `ai.audit.full_text` is set; the prompt
+/// hash is always kept so requests can be matched up without it.
+#[derive(Debug, Clone)]
+pub struct AiAuditEntry {
+    pub id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub kind: AiRequestKind,
+    pub provider: String,
+    pub model: String,
+    pub prompt_hash: String,
+    pub prompt: Option<String>,
+    pub response: Option<String>,
+    pub error: Option<String>,
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>,
+    pub latency: Duration,
+    /// Block that ran the generated command, if it was run
+    pub executed_block: Option<Uuid>,
+}
+
+impl AiAuditEntry {
+    /// Start an entry for a request about to be sent
+    pub fn new(kind: AiRequestKind, provider: &str, model: &str, prompt: &str, full_text: bool) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            kind,
+            provider: provider.to_string(),
+            model: model.to_string(),
+            prompt_hash: prompt_hash(prompt),
+            prompt: full_text.then(|| prompt.to_string()),
+            response: None,
+            error: None,
+            prompt_tokens: None,
+            completion_tokens: None,
+            latency: Duration::ZERO,
+            executed_block: None,
+        }
+    }
+
+    /// Replace the prompt with the one finally sent, for requests whose text
+    /// is only complete once earlier requests have answered
+    pub fn with_prompt(mut self, prompt: &str) -> Self {
+        self.prompt_hash = prompt_hash(prompt);
+        if self.prompt.is_some() {
+            self.prompt = Some(prompt.to_string());
+        }
+        self
+    }
+
+    /// Record the provider's answer; the text is kept only if the prompt was
+    pub fn succeeded(mut self, response: &str, tokens: Option<(u32, u32)>, latency: Duration) -> Self {
+        if self.prompt.is_some() {
+            self.response = Some(response.to_string());
+        }
+        if let Some((prompt_tokens, completion_tokens)) = tokens {
+            self.prompt_tokens = Some(prompt_tokens);
+            self.completion_tokens = Some(completion_tokens);
+        }
+        self.latency = latency;
+        self
+    }
+
+    pub fn failed(mut self, error: String, latency: Duration) -> Self {
+        self.error = Some(error);
+        self.latency = latency;
+        self
+    }
+
+    pub fn executed(&self) -> bool {
+        self.executed_block.is_some()
+    }
+
+    pub(crate) fn from_row(row: &SqliteRow) -> Self {
+        let uuid = |column: &str| row.get::<Option<String>, _>(column).and_then(|s| Uuid::parse_str(&s).ok());
+        Self {
+            id: uuid("id").unwrap_or_default(),
+            timestamp: DateTime::parse_from_rfc3339(row.get("timestamp"))
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_default(),
+            kind: AiRequestKind::parse(row.get("kind")),
+            provider: row.get("provider"),
+            model: row.get("model"),
+            prompt_hash: row.get("prompt_hash"),
+            prompt: row.get("prompt"),
+            response: row.get("response"),
+            error: row.get("error"),
+            prompt_tokens: row.get::<Option<i64>, _>("prompt_tokens").map(|t| t as u32),
+            completion_tokens: row.get::<Option<i64>, _>("completion_tokens").map(|t| t as u32),
+            latency: Duration::from_millis(row.get::<i64, _>("latency_ms") as u64),
+            executed_block: uuid("executed_block_id"),
+        }
+    }
+}
+
+/// Shortened SHA-256 of a prompt, enough to tell requests apart
+pub fn prompt_hash(prompt: &str) -> String {
+    let mut hash = format!("{:x}", Sha256::digest(prompt.as_bytes()));
+    hash.truncate(PROMPT_HASH_LEN);
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Database, SessionManager};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_text_is_kept_only_when_asked() {
+        let entry = AiAuditEntry::new(AiRequestKind::Chat, "ollama", "llama2", "list files", false)
+            .succeeded("ls", Some((12, 3)), Duration::from_millis(800));
+        assert_eq!(entry.prompt_hash, prompt_hash("list files"));
+        assert_eq!(entry.prompt_hash.len(), PROMPT_HASH_LEN);
+        assert!(entry.prompt.is_none() && entry.response.is_none());
+        assert_eq!(entry.completion_tokens, Some(3));
+
+        let entry = AiAuditEntry::new(AiRequestKind::Chat, "ollama", "llama2", "list files", true)
+            .succeeded("ls", None, Duration::from_millis(800));
+        assert_eq!(entry.prompt.as_deref(), Some("list files"));
+        assert_eq!(entry.response.as_deref(), Some("ls"));
+    }
+
+    #[test]
+    fn test_final_prompt_replaces_draft() {
+        let entry = AiAuditEntry::new(AiRequestKind::Chat, "ollama", "llama2", "why?", true)
+            .with_prompt("=== Earlier Commands (summarized) ===\nmake failed\n\nwhy?");
+        assert_eq!(entry.prompt_hash, prompt_hash("=== Earlier Commands (summarized) ===\nmake failed\n\nwhy?"));
+        assert!(entry.prompt.unwrap().contains("make failed"));
+
+        let hashed = AiAuditEntry::new(AiRequestKind::Condense, "ollama", "llama2", "why?", false).with_prompt("why not?");
+        assert!(hashed.prompt.is_none());
+        assert_eq!(AiRequestKind::parse(hashed.kind.as_str()), AiRequestKind::Condense);
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_round_trip() {
+        let dir = tempdir().unwrap();
+        let db = Database::new(dir.path().join("test.db")).await.unwrap();
+        let manager = SessionManager::new(db).await.unwrap();
+
+        let failed = AiAuditEntry::new(AiRequestKind::Chat, "groq", "mixtral", "why?", true)
+            .failed("Rate limited".to_string(), Duration::from_millis(50));
+        manager.record_ai_request(&failed).await.unwrap();
+        let command = AiAuditEntry::new(AiRequestKind::Command, "ollama", "llama2", "show disk usage", false)
+            .succeeded("df -h", Some((20, 4)), Duration::from_millis(1200));
+        manager.record_ai_request(&command).await.unwrap();
+
+        let block_id = Uuid::new_v4();
+        manager.mark_ai_command_executed(&command.id, &block_id).await.unwrap();
+
+        let entries = manager.ai_audit_log(10).await.unwrap();
+        assert_eq!(entries.len(), 2);
+        let command = entries.iter().find(|e| e.kind == AiRequestKind::Command).unwrap();
+        assert_eq!(command.executed_block, Some(block_id));
+        assert_eq!(command.latency, Duration::from_millis(1200));
+        assert_eq!(command.prompt_tokens, Some(20));
+        assert!(command.prompt.is_none());
+        let failed = entries.iter().find(|e| e.kind == AiRequestKind::Chat).unwrap();
+        assert_eq!(failed.error.as_deref(), Some("Rate limited"));
+        assert!(!failed.executed());
+    }
+}
@@ -0,0 +1,234 @@
+use super::export::{open_with_passphrase, seal_with_passphrase};
+use super::{ExportedSession, Session, SessionManager};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+/// Bumped when the archive layout changes in a way older versions can't read
+const ARCHIVE_VERSION: u32 = 1;
+
+/// Every session with its blocks, and optionally the settings, in one file
+/// for moving to another machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataArchive {
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+    pub sessions: Vec<Session>,
+    /// config.toml as it was on the exporting machine
+    #[serde(default)]
+    pub settings: Option<String>,
+    /// Some of the history was encrypted in the database
+    #[serde(skip)]
+    pub sealed_history: bool,
+}
+
+/// What to do with sessions already in the database when restoring
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestoreMode {
+    /// Keep local sessions; add new ones, and replace a local copy only if
+    /// the archived one was changed later
+    Merge,
+    /// Delete every local session, leaving only those in the archive
+    Replace,
+}
+
+/// Sessions touched by a restore
+#[derive(Debug, Default, PartialEq)]
+pub struct RestoreReport {
+    pub added: usize,
+    pub updated: usize,
+    pub kept: usize,
+    pub removed: usize,
+}
+
+impl RestoreReport {
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!("{} added", self.added), format!("{} updated", self.updated)];
+        if self.kept > 0 {
+            parts.push(format!("{} kept", self.kept));
+        }
+        if self.removed > 0 {
+            parts.push(format!("{} removed", self.removed));
+        }
+        parts.join(", ")
+    }
+}
+
+impl DataArchive {
+    /// Read every session out of the database
+    pub async fn collect(manager: &SessionManager, settings: Option<String>) -> Result<Self> {
+        let mut sessions = Vec::new();
+        for info in manager.list_sessions().await? {
+            let session = manager
+                .load_session(&info.id)
+                .await
+                .with_context(|| format!("Failed to read session '{}'", info.name))?;
+            sessions.push(session);
+        }
+        Ok(Self {
+            version: ARCHIVE_VERSION,
+            created_at: Utc::now(),
+            sessions,
+            settings,
+            sealed_history: manager.encrypts() || manager.has_encrypted_blocks().await?,
+        })
+    }
+
+    /// Whether the archive may only be written encrypted: the settings can
+    /// hold API keys, and encrypted history shouldn't come out in the clear
+    pub fn needs_passphrase(&self) -> bool {
+        self.settings.is_some() || self.sealed_history
+    }
+
+    /// Write the archive as JSON, or sealed with `passphrase` if one is
+    /// given, readable only by the current user
+    pub fn to_file<P: AsRef<Path>>(&self, path: P, passphrase: Option<&str>) -> Result<()> {
+        if passphrase.is_none() && self.needs_passphrase() {
+            bail!("A passphrase is required for archives with settings or encrypted history");
+        }
+        let json = serde_json::to_vec_pretty(&self).context("Failed to serialize archive")?;
+        let data = match passphrase {
+            Some(passphrase) => seal_with_passphrase(&json, passphrase)?,
+            None => json,
+        };
+        write_private(path.as_ref(), &data).with_context(|| format!("Failed to write {}", path.as_ref().display()))
+    }
+
+    /// Read an archive written by `to_file`. The passphrase is only used
+    /// for encrypted archives.
+    pub fn from_file<P: AsRef<Path>>(path: P, passphrase: &str) -> Result<Self> {
+        let data = std::fs::read(path.as_ref())
+            .with_context(|| format!("Failed to read {}", path.as_ref().display()))?;
+        let json = if ExportedSession::is_encrypted(&data) {
+            open_with_passphrase(&data, passphrase)?
+        } else {
+            data
+        };
+        let archive: Self = serde_json::from_slice(&json).context("Not a data archive")?;
+        if archive.version > ARCHIVE_VERSION {
+            bail!("Archive was written by a newer version (format {})", archive.version);
+        }
+        Ok(archive)
+    }
+
+    /// Write the archived sessions into the database
+    pub async fn restore(&self, manager: &SessionManager, mode: RestoreMode) -> Result<RestoreReport> {
+        let local: HashMap<_, _> = manager
+            .list_sessions()
+            .await?
+            .into_iter()
+            .map(|info| (info.id, info.updated_at))
+            .collect();
+        let mut report = RestoreReport::default();
+
+        let mut removed = Vec::new();
+        if mode == RestoreMode::Replace {
+            removed.extend(local.keys().filter(|id| !self.sessions.iter().any(|s| s.id == **id)).copied());
+            report.removed = removed.len();
+        }
+
+        let mut restored = Vec::new();
+        for session in &self.sessions {
+            match local.get(&session.id) {
+                None => report.added += 1,
+                Some(updated_at) if mode == RestoreMode::Merge && *updated_at >= session.updated_at => {
+                    report.kept += 1;
+                    continue;
+                }
+                Some(_) => report.updated += 1,
+            }
+            restored.push(session);
+        }
+
+        // All or nothing, so a failed restore doesn't leave local sessions deleted
+        manager.replace_sessions(&removed, &restored).await.context("Failed to restore archive")?;
+
+        tracing::info!("Restored data archive: {}", report.summary());
+        Ok(report)
+    }
+}
+
+/// Write `data` to a file only its owner can read
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    // The mode only applies to new files
+    #[cfg(unix)]
+    file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    file.write_all(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Block, Database};
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    async fn manager(path: &Path) -> SessionManager {
+        SessionManager::new(Database::new(path.to_path_buf()).await.unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_archive_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let source = manager(&temp_dir.path().join("source.db")).await;
+
+        let mut kept = Session::new("kept".to_string(), PathBuf::from("/tmp"));
+        kept.add_block(Block::new("ls".to_string(), PathBuf::from("/tmp")));
+        source.create_session(&kept).await.unwrap();
+        source.save_blocks(&kept.id, &kept.blocks).await.unwrap();
+        let archive = DataArchive::collect(&source, Some("[general]\n".to_string())).await.unwrap();
+
+        let path = temp_dir.path().join("all.enc");
+        archive.to_file(&path, Some("hunter2")).unwrap();
+        assert!(DataArchive::from_file(&path, "wrong").is_err());
+        let archive = DataArchive::from_file(&path, "hunter2").unwrap();
+        assert_eq!(archive.settings.as_deref(), Some("[general]\n"));
+
+        let target = manager(&temp_dir.path().join("target.db")).await;
+        let local = Session::new("local".to_string(), PathBuf::from("/tmp"));
+        target.create_session(&local).await.unwrap();
+
+        let report = archive.restore(&target, RestoreMode::Merge).await.unwrap();
+        assert_eq!(report, RestoreReport { added: 1, ..Default::default() });
+        assert_eq!(target.list_sessions().await.unwrap().len(), 2);
+        assert_eq!(target.load_session(&kept.id).await.unwrap().blocks[0].command, "ls");
+
+        // Merging again changes nothing; replacing drops the local-only session
+        let report = archive.restore(&target, RestoreMode::Merge).await.unwrap();
+        assert_eq!(report, RestoreReport { kept: 1, ..Default::default() });
+        let report = archive.restore(&target, RestoreMode::Replace).await.unwrap();
+        assert_eq!(report, RestoreReport { updated: 1, removed: 1, ..Default::default() });
+        let sessions = target.list_sessions().await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "kept");
+    }
+
+    #[tokio::test]
+    async fn test_archive_needs_passphrase_for_settings() {
+        let temp_dir = tempdir().unwrap();
+        let source = manager(&temp_dir.path().join("source.db")).await;
+        let path = temp_dir.path().join("all.archive");
+
+        let archive = DataArchive::collect(&source, Some("api_key = \"secret\"\n".to_string())).await.unwrap();
+        assert!(archive.to_file(&path, None).is_err());
+        assert!(!path.exists());
+
+        let archive = DataArchive::collect(&source, None).await.unwrap();
+        archive.to_file(&path, None).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use super::Database;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const BACKUP_PREFIX: &str = "immaterium-";
+const BACKUP_EXTENSION: &str = "db";
+
+/// A database snapshot in the backup directory
+#[derive(Debug, Clone)]
+pub struct BackupInfo {
+    pub path: PathBuf,
+    pub created: DateTime<Local>,
+    pub size: u64,
+}
+
+/// Snapshot the database into `dir`, then delete all but the `keep` newest
+/// snapshots. Returns the path of the new snapshot.
+pub async fn create_backup(db: &Database, dir: &Path, keep: usize) -> Result<PathBuf> {
+    tokio::fs::create_dir_all(dir)
+        .await
+        .with_context(|| format!("Failed to create backup directory {}", dir.display()))?;
+
+    let name = format!(
+        "{}{}.{}",
+        BACKUP_PREFIX,
+        Local::now().format("%Y%m%d-%H%M%S"),
+        BACKUP_EXTENSION
+    );
+    let path = dir.join(&name);
+    // Snapshot under a temporary name so an interrupted backup is never listed
+    let partial = dir.join(format!(".{}.partial", name));
+    let _ = tokio::fs::remove_file(&partial).await;
+
+    db.backup_to(&partial).await?;
+    tokio::fs::rename(&partial, &path)
+        .await
+        .with_context(|| format!("Failed to write backup {}", path.display()))?;
+    tracing::info!("Backed up database to {}", path.display());
+
+    for old in list_backups(dir)?.into_iter().skip(keep.max(1)) {
+        if let Err(e) = std::fs::remove_file(&old.path) {
+            tracing::warn!("Failed to remove old backup {}: {}", old.path.display(), e);
+        }
+    }
+
+    Ok(path)
+}
+
+/// Snapshots in `dir`, newest first
+pub fn list_backups(dir: &Path) -> Result<Vec<BackupInfo>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    };
+
+    let mut backups = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_backup = path.file_name().and_then(|n| n.to_str()).is_some_and(|name| {
+            name.starts_with(BACKUP_PREFIX) && name.ends_with(&format!(".{}", BACKUP_EXTENSION))
+        });
+        if !is_backup {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        backups.push(BackupInfo {
+            created: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH).into(),
+            size: metadata.len(),
+            path,
+        });
+    }
+
+    // Names embed the time, and sort correctly even if mtimes were lost in a copy
+    backups.sort_by(|a, b| b.path.cmp(&a.path));
+    Ok(backups)
+}
+
+/// Check that a snapshot is an intact SQLite database
+pub async fn verify_backup(path: &Path) -> Result<()> {
+    let mut conn = SqliteConnectOptions::new()
+        .filename(path)
+        .read_only(true)
+        .connect()
+        .await
+        .with_context(|| format!("Failed to open backup {}", path.display()))?;
+
+    let result: String = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_one(&mut conn)
+        .await
+        .context("Backup is not a readable database")?;
+    conn.close().await?;
+
+    if result != "ok" {
+        bail!("Backup failed the integrity check: {}", result);
+    }
+    Ok(())
+}
+
+/// Replace the database at `db_path` with a snapshot. The database must be
+/// closed; the replaced file is kept next to it with a `.pre-restore` suffix.
+pub async fn restore_backup(backup: &Path, db_path: &Path) -> Result<()> {
+    verify_backup(backup).await?;
+
+    let with_suffix = |suffix: &str| {
+        let mut name = db_path.as_os_str().to_owned();
+        name.push(suffix);
+        PathBuf::from(name)
+    };
+
+    if db_path.exists() {
+        std::fs::rename(db_path, with_suffix(".pre-restore"))
+            .context("Failed to move the current database aside")?;
+    }
+    // A leftover WAL would be replayed on top of the restored file
+    for suffix in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(with_suffix(suffix));
+    }
+
+    std::fs::copy(backup, db_path)
+        .with_context(|| format!("Failed to restore {}", backup.display()))?;
+    tracing::info!("Restored database from {}", backup.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Session, SessionManager};
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_backup_rotation_and_restore() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("immaterium.db");
+        let backup_dir = temp_dir.path().join("backups");
+
+        let manager = SessionManager::new(Database::new(db_path.clone()).await.unwrap())
+            .await
+            .unwrap();
+        let session = Session::new("kept".to_string(), PathBuf::from("/tmp"));
+        manager.create_session(&session).await.unwrap();
+
+        let db = Database::new(db_path.clone()).await.unwrap();
+        let backup = create_backup(&db, &backup_dir, 2).await.unwrap();
+        assert!(backup.exists());
+
+        // Fake two older snapshots; only the newest two survive rotation
+        for name in ["immaterium-20200101-000000.db", "immaterium-20200102-000000.db"] {
+            std::fs::copy(&backup, backup_dir.join(name)).unwrap();
+        }
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        create_backup(&db, &backup_dir, 2).await.unwrap();
+        let backups = list_backups(&backup_dir).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert!(backups.iter().any(|b| b.path == backup));
+
+        manager.delete_session(&session.id).await.unwrap();
+        db.close().await.unwrap();
+        manager.close().await;
+
+        restore_backup(&backup, &db_path).await.unwrap();
+        assert!(temp_dir.path().join("immaterium.db.pre-restore").exists());
+
+        let manager = SessionManager::new(Database::new(db_path).await.unwrap())
+            .await
+            .unwrap();
+        assert_eq!(manager.load_session(&session.id).await.unwrap().name, "kept");
+    }
+
+    #[tokio::test]
+    async fn test_verify_rejects_non_database() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("immaterium-20240101-000000.db");
+        std::fs::write(&path, b"not a database at all, just some text").unwrap();
+        assert!(verify_backup(&path).await.is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use super::output::OutputBuffer;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -10,13 +11,38 @@ pub struct Block {
     pub id: Uuid,
     pub timestamp: DateTime<Utc>,
     pub command: String,
-    pub output: String,
+    pub output: OutputBuffer,
     pub exit_code: Option<i32>,
     pub state: BlockState,
     pub metadata: BlockMetadata,
     pub is_collapsed: bool,
     pub is_selected: bool,
     pub original_input: Option<String>, // For AI-generated commands, stores the original NL input
+    #[serde(default)]
+    pub is_pinned: bool,
+    /// Produced by one of the session's startup commands
+    #[serde(default)]
+    pub is_startup: bool,
+    /// Output line indices highlighted by triggers
+    #[serde(default)]
+    pub highlighted_lines: Vec<usize>,
+    /// Labels shown next to the command, e.g. added by scripting hooks
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// A heading separating phases of work rather than a command; its text
+    /// is kept in `command`
+    #[serde(default)]
+    pub is_divider: bool,
+    /// An expression evaluated by the built-in calculator; the expression is
+    /// kept in `command` and the result in `output`
+    #[serde(default)]
+    pub is_calculation: bool,
+    /// Always sent as AI context, however long ago it ran
+    #[serde(default)]
+    pub in_ai_context: bool,
+    /// Output ended with a `\r` whose meaning depends on the next chunk
+    #[serde(skip)]
+    pub pending_carriage_return: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -29,6 +55,42 @@ pub enum BlockState {
     Cancelled,
 }
 
+/// Quick filters for the block list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockFilter {
+    Failed,
+    Running,
+    AiGenerated,
+    Pinned,
+}
+
+impl BlockFilter {
+    pub const ALL: [BlockFilter; 4] = [
+        BlockFilter::Failed,
+        BlockFilter::Running,
+        BlockFilter::AiGenerated,
+        BlockFilter::Pinned,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BlockFilter::Failed => "Failed",
+            BlockFilter::Running => "Running",
+            BlockFilter::AiGenerated => "AI-generated",
+            BlockFilter::Pinned => "Pinned",
+        }
+    }
+
+    pub fn matches(&self, block: &Block) -> bool {
+        match self {
+            BlockFilter::Failed => block.state == BlockState::Failed,
+            BlockFilter::Running => block.is_running(),
+            BlockFilter::AiGenerated => block.is_ai_generated(),
+            BlockFilter::Pinned => block.is_pinned,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlockMetadata {
     pub duration: Option<Duration>,
@@ -36,6 +98,39 @@ pub struct BlockMetadata {
     pub environment: HashMap<String, String>,
     pub started_at: Option<DateTime<Utc>>,
     pub completed_at: Option<DateTime<Utc>>,
+    /// Outcome of the configured pre/post command hooks
+    #[serde(default)]
+    pub hooks: Vec<HookResult>,
+    /// Provider, model and prompt behind an AI-generated command
+    #[serde(default)]
+    pub ai_provenance: Option<AiProvenance>,
+}
+
+/// Where an AI-generated command came from, kept for auditing what ran and why
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AiProvenance {
+    pub provider: String,
+    pub model: String,
+    /// The request as sent, including any context added to the user's input
+    pub prompt: String,
+}
+
+/// When a command hook ran
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HookPhase {
+    Pre,
+    Post,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HookResult {
+    pub phase: HookPhase,
+    /// Short description of the hook, e.g. `webhook https://...`
+    pub hook: String,
+    pub ok: bool,
+    #[serde(default)]
+    pub message: Option<String>,
 }
 
 impl Block {
@@ -44,7 +139,7 @@ impl Block {
             id: Uuid::new_v4(),
             timestamp: Utc::now(),
             command,
-            output: String::new(),
+            output: OutputBuffer::new(),
             exit_code: None,
             state: BlockState::Editing,
             metadata: BlockMetadata {
@@ -53,10 +148,20 @@ impl Block {
                 environment: HashMap::new(),
                 started_at: None,
                 completed_at: None,
+                hooks: Vec::new(),
+                ai_provenance: None,
             },
             is_collapsed: false,
             is_selected: false,
             original_input: None,
+            is_pinned: false,
+            is_startup: false,
+            highlighted_lines: Vec::new(),
+            tags: Vec::new(),
+            is_divider: false,
+            is_calculation: false,
+            in_ai_context: false,
+            pending_carriage_return: false,
         }
     }
 
@@ -65,7 +170,7 @@ impl Block {
             id: Uuid::new_v4(),
             timestamp: Utc::now(),
             command: suggested_command,
-            output: String::new(),
+            output: OutputBuffer::new(),
             exit_code: None,
             state: BlockState::PendingApproval,
             metadata: BlockMetadata {
@@ -74,13 +179,47 @@ impl Block {
                 environment: HashMap::new(),
                 started_at: None,
                 completed_at: None,
+                hooks: Vec::new(),
+                ai_provenance: None,
             },
             is_collapsed: false,
             is_selected: false,
             original_input: Some(nl_input),
+            is_pinned: false,
+            is_startup: false,
+            highlighted_lines: Vec::new(),
+            tags: Vec::new(),
+            is_divider: false,
+            is_calculation: false,
+            in_ai_context: false,
+            pending_carriage_return: false,
         }
     }
 
+    /// A heading block, e.g. "Deploy to staging", that never runs
+    pub fn divider(title: String, working_directory: PathBuf) -> Self {
+        let mut block = Self::new(title, working_directory);
+        block.state = BlockState::Completed;
+        block.is_divider = true;
+        block
+    }
+
+    /// The result of a calculator expression, finished as soon as it's made
+    pub fn calculation(expression: String, result: String, working_directory: PathBuf) -> Self {
+        let mut block = Self::new(expression, working_directory);
+        block.append_output(result);
+        block.state = BlockState::Completed;
+        block.exit_code = Some(0);
+        block.is_calculation = true;
+        block
+    }
+
+    /// Record which provider, model and prompt produced the command
+    pub fn with_provenance(mut self, provenance: AiProvenance) -> Self {
+        self.metadata.ai_provenance = Some(provenance);
+        self
+    }
+
     pub fn start_execution(&mut self) {
         self.state = BlockState::Running;
         self.metadata.started_at = Some(Utc::now());
@@ -104,14 +243,94 @@ impl Block {
         }
     }
 
+    /// End a block that never ran, e.g. because a hook refused it
+    pub fn cancel_execution(&mut self) {
+        self.state = BlockState::Cancelled;
+        self.metadata.completed_at = Some(Utc::now());
+    }
+
+    /// Run the block again in place, discarding the previous output and result
+    pub fn restart_execution(&mut self) {
+        self.output.clear();
+        self.highlighted_lines.clear();
+        self.metadata.hooks.clear();
+        self.pending_carriage_return = false;
+        self.exit_code = None;
+        self.metadata.duration = None;
+        self.metadata.completed_at = None;
+        self.start_execution();
+    }
+
+    /// Append streamed output. `\r\n` is kept as a line ending, while a lone
+    /// `\r` returns to the start of the line so the next write replaces it,
+    /// as a terminal would for progress bars.
     pub fn append_output(&mut self, text: String) {
-        self.output.push_str(&text);
+        if !self.pending_carriage_return && !text.contains('\r') {
+            self.output.push_str(&text);
+            return;
+        }
+
+        let mut text = text.as_str();
+        if std::mem::take(&mut self.pending_carriage_return) {
+            if text.starts_with('\n') {
+                self.output.push('\r');
+            } else if !text.is_empty() {
+                self.clear_current_line();
+            }
+        }
+
+        if let Some(rest) = text.strip_suffix('\r') {
+            self.pending_carriage_return = true;
+            text = rest;
+        }
+
+        let mut segments = text.split('\r');
+        if let Some(first) = segments.next() {
+            self.output.push_str(first);
+        }
+        for segment in segments {
+            if segment.starts_with('\n') {
+                self.output.push('\r');
+            } else {
+                self.clear_current_line();
+            }
+            self.output.push_str(segment);
+        }
+    }
+
+    fn clear_current_line(&mut self) {
+        self.output.clear_current_line();
+    }
+
+    /// Percentage shown on the line a running command is currently rewriting
+    /// (curl, pip, docker pull...), as a fraction between 0 and 1
+    pub fn progress(&self) -> Option<f32> {
+        if !self.is_running() {
+            return None;
+        }
+
+        let line = self.output.current_line();
+        line.match_indices('%').rev().find_map(|(pos, _)| {
+            let number_start = line[..pos]
+                .rfind(|c: char| !c.is_ascii_digit() && c != '.')
+                .map_or(0, |i| i + 1);
+            let percent: f32 = line[number_start..pos].parse().ok()?;
+            (0.0..=100.0).contains(&percent).then_some(percent / 100.0)
+        })
     }
 
     pub fn toggle_collapsed(&mut self) {
         self.is_collapsed = !self.is_collapsed;
     }
 
+    pub fn toggle_pinned(&mut self) {
+        self.is_pinned = !self.is_pinned;
+    }
+
+    pub fn toggle_ai_context(&mut self) {
+        self.in_ai_context = !self.in_ai_context;
+    }
+
     pub fn set_selected(&mut self, selected: bool) {
         self.is_selected = selected;
     }
@@ -124,6 +343,11 @@ impl Block {
         matches!(self.state, BlockState::Completed | BlockState::Failed | BlockState::Cancelled)
     }
 
+    /// Whether the command was suggested by the AI from a natural language request
+    pub fn is_ai_generated(&self) -> bool {
+        self.original_input.is_some()
+    }
+
     pub fn get_display_command(&self) -> String {
         if self.command.len() > 100 {
             format!("{}...", &self.command[..97])
@@ -136,23 +360,132 @@ impl Block {
         if self.is_collapsed {
             String::new()
         } else {
-            self.output.clone()
+            self.output.to_string()
         }
     }
 
     pub fn format_duration(&self) -> String {
-        match self.metadata.duration {
-            Some(d) if d.as_secs() > 0 => format!("{}s", d.as_secs()),
-            Some(d) => format!("{}ms", d.as_millis()),
-            None => "".to_string(),
-        }
+        self.metadata.duration.map(format_duration).unwrap_or_default()
+    }
+}
+
+/// `850ms`, `42s`, `3m 05s` or `1h 02m`
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs == 0 {
+        format!("{}ms", duration.as_millis())
+    } else if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
     }
 }
 
+/// The values of the `names` variables a command starts with: from
+/// `overrides` (the environment the app adds) if set there, otherwise from
+/// `lookup`. Unset variables are left out.
+pub fn environment_snapshot(
+    names: &[String],
+    overrides: &HashMap<String, String>,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> HashMap<String, String> {
+    names
+        .iter()
+        .filter_map(|name| {
+            let value = overrides.get(name).cloned().or_else(|| lookup(name))?;
+            Some((name.clone(), value))
+        })
+        .collect()
+}
+
+/// A variable whose value differs between two blocks' environment snapshots;
+/// `None` means it was unset
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvironmentChange {
+    pub name: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// What changed from `before` to `after`: the working directory if it moved,
+/// and the snapshot variables that were added, removed or changed, by name
+pub fn environment_diff(before: &Block, after: &Block) -> (Option<(PathBuf, PathBuf)>, Vec<EnvironmentChange>) {
+    let (old_dir, new_dir) = (&before.metadata.working_directory, &after.metadata.working_directory);
+    let directory = (old_dir != new_dir).then(|| (old_dir.clone(), new_dir.clone()));
+
+    let (old_env, new_env) = (&before.metadata.environment, &after.metadata.environment);
+    let mut names: Vec<&String> = old_env.keys().chain(new_env.keys()).collect();
+    names.sort();
+    names.dedup();
+    let changes = names
+        .into_iter()
+        .filter(|name| old_env.get(*name) != new_env.get(*name))
+        .map(|name| EnvironmentChange {
+            name: name.clone(),
+            before: old_env.get(name).cloned(),
+            after: new_env.get(name).cloned(),
+        })
+        .collect();
+    (directory, changes)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_environment_snapshot() {
+        let names = vec!["PATH".to_string(), "VIRTUAL_ENV".to_string(), "KUBECONFIG".to_string()];
+        let overrides = HashMap::from([("KUBECONFIG".to_string(), "/tmp/kube".to_string())]);
+        let lookup = |name: &str| match name {
+            "PATH" => Some("/usr/bin".to_string()),
+            "KUBECONFIG" => Some("~/.kube/config".to_string()),
+            _ => None,
+        };
+        let snapshot = environment_snapshot(&names, &overrides, lookup);
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot["PATH"], "/usr/bin");
+        assert_eq!(snapshot["KUBECONFIG"], "/tmp/kube");
+    }
+
+    #[test]
+    fn test_environment_diff() {
+        let mut before = Block::new("make".to_string(), PathBuf::from("/src/app"));
+        before.metadata.environment = HashMap::from([
+            ("PATH".to_string(), "/usr/bin".to_string()),
+            ("VIRTUAL_ENV".to_string(), "/src/app/.venv".to_string()),
+            ("LANG".to_string(), "C".to_string()),
+        ]);
+        let mut after = Block::new("make".to_string(), PathBuf::from("/src/app/sub"));
+        after.metadata.environment = HashMap::from([
+            ("PATH".to_string(), "/opt/bin:/usr/bin".to_string()),
+            ("KUBECONFIG".to_string(), "/tmp/kube".to_string()),
+            ("LANG".to_string(), "C".to_string()),
+        ]);
+
+        let (directory, changes) = environment_diff(&before, &after);
+        assert_eq!(directory, Some((PathBuf::from("/src/app"), PathBuf::from("/src/app/sub"))));
+        let names: Vec<&str> = changes.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["KUBECONFIG", "PATH", "VIRTUAL_ENV"]);
+        assert_eq!(changes[0].before, None);
+        assert_eq!(changes[1].after.as_deref(), Some("/opt/bin:/usr/bin"));
+        assert_eq!(changes[2].after, None);
+
+        let (directory, changes) = environment_diff(&before, &before);
+        assert!(directory.is_none() && changes.is_empty());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(850)), "850ms");
+        assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(format_duration(Duration::from_secs(185)), "3m 05s");
+        assert_eq!(format_duration(Duration::from_secs(3720)), "1h 02m");
+        assert_eq!(Block::new("ls".to_string(), PathBuf::from("/tmp")).format_duration(), "");
+    }
+
     #[test]
     fn test_block_creation() {
         let block = Block::new("echo test".to_string(), PathBuf::from("/tmp"));
@@ -179,6 +512,39 @@ mod tests {
         assert!(block.metadata.duration.is_some());
     }
 
+    #[test]
+    fn test_block_restart_execution() {
+        let mut block = Block::new("date".to_string(), PathBuf::from("/tmp"));
+        block.start_execution();
+        block.append_output("first\n".to_string());
+        block.complete_execution(1);
+
+        block.restart_execution();
+        assert_eq!(block.state, BlockState::Running);
+        assert!(block.output.is_empty());
+        assert!(block.exit_code.is_none());
+        assert!(block.metadata.duration.is_none());
+    }
+
+    #[test]
+    fn test_carriage_return_progress() {
+        let mut block = Block::new("curl -O file".to_string(), PathBuf::from("/tmp"));
+        block.start_execution();
+        block.append_output("Downloading\r\n".to_string());
+        block.append_output(" 10% [=>   ]\r".to_string());
+        block.append_output(" 55% [===> ]\r".to_string());
+        assert_eq!(block.output, "Downloading\r\n 55% [===> ]");
+        assert_eq!(block.progress(), Some(0.55));
+
+        block.append_output(" 99.5%\r100% done\r".to_string());
+        block.append_output("\ncomplete\r\n".to_string());
+        assert_eq!(block.output, "Downloading\r\n100% done\r\ncomplete\r\n");
+        assert_eq!(block.progress(), None);
+
+        block.complete_execution(0);
+        assert_eq!(block.progress(), None);
+    }
+
     #[test]
     fn test_block_failed_execution() {
         let mut block = Block::new("false".to_string(), PathBuf::from("/tmp"));
@@ -192,7 +558,7 @@ mod tests {
     #[test]
     fn test_block_collapse() {
         let mut block = Block::new("echo test".to_string(), PathBuf::from("/tmp"));
-        block.output = "test output".to_string();
+        block.output = "test output".into();
         
         assert!(!block.is_collapsed);
         assert_eq!(block.get_display_output(), "test output");
@@ -205,4 +571,21 @@ mod tests {
         assert!(!block.is_collapsed);
         assert_eq!(block.get_display_output(), "test output");
     }
+
+    #[test]
+    fn test_block_filters() {
+        let mut failed = Block::new("false".to_string(), PathBuf::from("/tmp"));
+        failed.start_execution();
+        failed.complete_execution(1);
+
+        let mut ai = Block::new_pending_approval("list files".to_string(), "ls".to_string(), PathBuf::from("/tmp"));
+        ai.toggle_pinned();
+
+        assert!(BlockFilter::Failed.matches(&failed));
+        assert!(!BlockFilter::Failed.matches(&ai));
+        assert!(BlockFilter::AiGenerated.matches(&ai));
+        assert!(BlockFilter::Pinned.matches(&ai));
+        assert!(!BlockFilter::Pinned.matches(&failed));
+        assert!(!BlockFilter::Running.matches(&failed));
+    }
 }
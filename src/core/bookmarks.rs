@@ -0,0 +1,122 @@
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::path::{Path, PathBuf};
+
+/// A directory the shell has been in, or one pinned by hand
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectoryBookmark {
+    pub path: PathBuf,
+    pub visits: u32,
+    pub last_visit: DateTime<Utc>,
+    pub pinned: bool,
+}
+
+impl DirectoryBookmark {
+    /// How often and how recently the directory was visited
+    pub fn frecency(&self, now: DateTime<Utc>) -> f64 {
+        super::frequency::frecency(self.visits, self.last_visit, now)
+    }
+
+    pub(crate) fn from_row(row: &SqliteRow) -> Self {
+        Self {
+            path: PathBuf::from(row.get::<String, _>("path")),
+            visits: row.get::<i64, _>("visits") as u32,
+            last_visit: DateTime::parse_from_rfc3339(row.get("last_visit"))
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_default(),
+            pinned: row.get::<i64, _>("pinned") != 0,
+        }
+    }
+}
+
+/// Score `query` as a fuzzy match against `path`: every query character has
+/// to appear in order, ignoring case. Runs of consecutive characters and
+/// matches in the last path component score higher, so `imm` finds
+/// `~/src/immaterium` before `~/images/mm`. `None` if it doesn't match.
+pub fn fuzzy_score(query: &str, path: &Path) -> Option<i64> {
+    let text = path.to_string_lossy().to_lowercase();
+    let name_start = text.trim_end_matches('/').rfind('/').map_or(0, |i| i + 1);
+    let mut score = 0;
+    let mut run = 0;
+    let mut chars = text.char_indices().peekable();
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        loop {
+            let (i, c) = chars.next()?;
+            if c == q {
+                run += 1;
+                score += run;
+                if i >= name_start {
+                    score += 2;
+                }
+                break;
+            }
+            run = 0;
+        }
+    }
+    Some(score)
+}
+
+/// Order bookmarks for the quick-jump list. With no query, pinned
+/// directories come first, then the rest by frecency; with one, only
+/// matching directories are kept, best match first.
+pub fn rank<'a>(bookmarks: &'a [DirectoryBookmark], query: &str, now: DateTime<Utc>) -> Vec<&'a DirectoryBookmark> {
+    let mut ranked: Vec<(&DirectoryBookmark, i64)> = bookmarks
+        .iter()
+        .filter_map(|b| {
+            let score = if query.trim().is_empty() { 0 } else { fuzzy_score(query, &b.path)? };
+            Some((b, score))
+        })
+        .collect();
+    ranked.sort_by(|(a, a_score), (b, b_score)| {
+        b_score
+            .cmp(a_score)
+            .then_with(|| b.pinned.cmp(&a.pinned))
+            .then_with(|| b.frecency(now).total_cmp(&a.frecency(now)))
+    });
+    ranked.into_iter().map(|(b, _)| b).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn bookmark(path: &str, visits: u32, age: Duration, pinned: bool) -> DirectoryBookmark {
+        DirectoryBookmark {
+            path: PathBuf::from(path),
+            visits,
+            last_visit: Utc::now() - age,
+            pinned,
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("imm", Path::new("/home/me/src/immaterium")).is_some());
+        assert!(fuzzy_score("IMM", Path::new("/home/me/src/immaterium")).is_some());
+        assert!(fuzzy_score("xyz", Path::new("/home/me/src/immaterium")).is_none());
+        assert!(
+            fuzzy_score("imm", Path::new("/home/me/src/immaterium")) > fuzzy_score("imm", Path::new("/home/me/images/mm"))
+        );
+    }
+
+    #[test]
+    fn test_rank() {
+        let bookmarks = vec![
+            bookmark("/srv/logs", 50, Duration::days(30), false),
+            bookmark("/home/me/src/api", 10, Duration::minutes(5), false),
+            bookmark("/etc/nginx", 1, Duration::days(60), true),
+        ];
+        let now = Utc::now();
+        let paths = |ranked: Vec<&DirectoryBookmark>| ranked.iter().map(|b| b.path.clone()).collect::<Vec<_>>();
+
+        // Pinned first, then recent visits beat many old ones
+        assert_eq!(
+            paths(rank(&bookmarks, "", now)),
+            [PathBuf::from("/etc/nginx"), PathBuf::from("/home/me/src/api"), PathBuf::from("/srv/logs")]
+        );
+        assert_eq!(paths(rank(&bookmarks, "ngx", now)), [PathBuf::from("/etc/nginx")]);
+        assert!(rank(&bookmarks, "zzz", now).is_empty());
+    }
+}
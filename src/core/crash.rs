@@ -0,0 +1,184 @@
+use super::Block;
+use anyhow::{Context, Result};
+use chrono::Local;
+use std::backtrace::Backtrace;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+const REPORT_PREFIX: &str = "crash-";
+const REPORT_EXTENSION: &str = "txt";
+/// Reports already shown to the user are moved here
+const SEEN_DIR: &str = "seen";
+
+/// Report written by the most recent panic, for notes added while unwinding
+static LAST_REPORT: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// A crash report left by an earlier run
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+/// Write a crash report with a backtrace into `dir` when the main thread
+/// panics, then run the default hook. Panics elsewhere (tokio tasks, PTY
+/// threads) don't bring the app down, so they are only logged as usual.
+pub fn install_panic_hook(dir: PathBuf) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if std::thread::current().name() == Some("main") {
+            let report = format_report(&panic_message(info), &location(info), &Backtrace::force_capture().to_string());
+            match write_report(&dir, &report) {
+                Ok(path) => eprintln!("Crash report written to {}", path.display()),
+                Err(e) => eprintln!("Failed to write crash report: {:#}", e),
+            }
+        }
+        default_hook(info);
+    }));
+}
+
+fn panic_message(info: &PanicHookInfo) -> String {
+    if let Some(message) = info.payload().downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = info.payload().downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "(no message)".to_string()
+    }
+}
+
+fn location(info: &PanicHookInfo) -> String {
+    info.location()
+        .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn format_report(message: &str, location: &str, backtrace: &str) -> String {
+    format!(
+        "Immaterium {} crashed at {}\n\nMessage: {}\nLocation: {}\nOS: {} {}\n\nBacktrace:\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+        message,
+        location,
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        backtrace
+    )
+}
+
+/// Save a report into `dir`, returning its path
+pub fn write_report(dir: &Path, report: &str) -> Result<PathBuf> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(format!(
+        "{}{}.{}",
+        REPORT_PREFIX,
+        Local::now().format("%Y%m%d-%H%M%S%.3f"),
+        REPORT_EXTENSION
+    ));
+    fs::write(&path, report).with_context(|| format!("Failed to write {}", path.display()))?;
+    if let Ok(mut last) = LAST_REPORT.lock() {
+        *last = Some(path.clone());
+    }
+    Ok(path)
+}
+
+/// Append a line to the report of the panic being handled, e.g. where the
+/// session went
+pub fn add_to_report(note: &str) {
+    let Some(path) = LAST_REPORT.lock().ok().and_then(|last| last.clone()) else {
+        return;
+    };
+    let written = OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| writeln!(file, "\n{}", note));
+    if let Err(e) = written {
+        eprintln!("Failed to update crash report: {}", e);
+    }
+}
+
+/// Write a session's blocks to a JSON file in `dir` when they can't go to the database
+pub fn dump_blocks(dir: &Path, session_id: &Uuid, blocks: &[Block]) -> Result<PathBuf> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(format!("session-{}-{}.json", session_id, Local::now().format("%Y%m%d-%H%M%S")));
+    let json = serde_json::to_string_pretty(blocks)?;
+    fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Reports in `dir` not yet shown to the user, oldest first
+pub fn unseen_reports(dir: &Path) -> Vec<CrashReport> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut reports: Vec<_> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension().and_then(|e| e.to_str()) == Some(REPORT_EXTENSION)
+                && path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with(REPORT_PREFIX))
+        })
+        .filter_map(|path| {
+            let contents = fs::read_to_string(&path).ok()?;
+            Some(CrashReport { path, contents })
+        })
+        .collect();
+    reports.sort_by(|a, b| a.path.cmp(&b.path));
+    reports
+}
+
+/// Move a report out of the way once the user has seen it
+pub fn mark_seen(report: &CrashReport) -> Result<()> {
+    let dir = report.path.parent().context("Crash report has no directory")?;
+    let seen = dir.join(SEEN_DIR);
+    fs::create_dir_all(&seen).with_context(|| format!("Failed to create {}", seen.display()))?;
+    let name = report.path.file_name().context("Crash report has no file name")?;
+    fs::rename(&report.path, seen.join(name))
+        .with_context(|| format!("Failed to move {}", report.path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_reports_are_shown_once() {
+        let dir = tempdir().unwrap();
+        assert!(unseen_reports(dir.path()).is_empty());
+
+        let report = format_report("index out of bounds", "src/ui/app.rs:10:5", "0: main");
+        let path = write_report(dir.path(), &report).unwrap();
+        add_to_report("Session saved to the database");
+
+        let reports = unseen_reports(dir.path());
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].path, path);
+        assert!(reports[0].contents.contains("Message: index out of bounds"));
+        assert!(reports[0].contents.contains("Backtrace:\n0: main"));
+        assert!(reports[0].contents.ends_with("Session saved to the database\n"));
+
+        mark_seen(&reports[0]).unwrap();
+        assert!(unseen_reports(dir.path()).is_empty());
+        assert!(dir.path().join(SEEN_DIR).join(path.file_name().unwrap()).exists());
+    }
+
+    #[test]
+    fn test_dump_blocks() {
+        let dir = tempdir().unwrap();
+        let block = Block::new("cargo build".to_string(), PathBuf::from("/src"));
+        let path = dump_blocks(dir.path(), &Uuid::new_v4(), std::slice::from_ref(&block)).unwrap();
+        let blocks: Vec<Block> = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(blocks[0].id, block.id);
+        // Not mistaken for a crash report
+        assert!(unseen_reports(dir.path()).is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, bail, Context, Result};
+
+const KEYRING_SERVICE: &str = "immaterium";
+const KEYRING_USER: &str = "database-key";
+const NONCE_LEN: usize = 12;
+
+/// AES-256-GCM encryption for values stored in the database. Sealed values
+/// are the random nonce followed by the ciphertext.
+pub struct FieldCipher {
+    cipher: Aes256Gcm,
+}
+
+impl FieldCipher {
+    pub fn new(key: &[u8; 32]) -> Self {
+        Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+        }
+    }
+
+    pub fn generate_key() -> [u8; 32] {
+        Aes256Gcm::generate_key(OsRng).into()
+    }
+
+    pub fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let nonce = Aes256Gcm::generate_nonce(OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| anyhow!("Failed to encrypt value"))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    pub fn open(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            bail!("Encrypted value is truncated");
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("Failed to decrypt value (wrong key?)"))
+    }
+
+    /// The database key from the OS keyring. With `create`, a new key is
+    /// generated and stored if there is none yet.
+    pub fn from_keyring(create: bool) -> Result<Option<Self>> {
+        // The secret service client can deadlock on a thread driving a tokio
+        // runtime, so talk to the keyring from a thread of its own
+        std::thread::spawn(move || Self::read_keyring(create))
+            .join()
+            .map_err(|_| anyhow!("OS keyring access panicked"))?
+    }
+
+    fn read_keyring(create: bool) -> Result<Option<Self>> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)
+            .context("Failed to open OS keyring")?;
+
+        let encoded = match entry.get_password() {
+            Ok(encoded) => encoded,
+            Err(keyring::Error::NoEntry) if create => {
+                let encoded = encode_hex(&Self::generate_key());
+                entry
+                    .set_password(&encoded)
+                    .context("Failed to store database key in OS keyring")?;
+                tracing::info!("Created database encryption key in the OS keyring");
+                encoded
+            }
+            Err(keyring::Error::NoEntry) => return Ok(None),
+            Err(e) => return Err(e).context("Failed to read database key from OS keyring"),
+        };
+
+        let key: [u8; 32] = decode_hex(&encoded)
+            .and_then(|bytes| bytes.try_into().ok())
+            .context("Database key in OS keyring is malformed")?;
+        Ok(Some(Self::new(&key)))
+    }
+}
+
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let cipher = FieldCipher::new(&FieldCipher::generate_key());
+        let sealed = cipher.seal(b"export AWS_SECRET_ACCESS_KEY=hunter2").unwrap();
+        assert!(!sealed.windows(7).any(|w| w == b"hunter2"));
+        assert_eq!(cipher.open(&sealed).unwrap(), b"export AWS_SECRET_ACCESS_KEY=hunter2");
+
+        // Same plaintext, fresh nonce
+        assert_ne!(cipher.seal(b"ls").unwrap(), cipher.seal(b"ls").unwrap());
+
+        let other = FieldCipher::new(&FieldCipher::generate_key());
+        assert!(other.open(&sealed).is_err());
+        assert!(cipher.open(&sealed[..8]).is_err());
+    }
+
+    #[test]
+    fn test_hex_roundtrip() {
+        let key = FieldCipher::generate_key();
+        assert_eq!(decode_hex(&encode_hex(&key)).unwrap(), key.to_vec());
+        assert!(decode_hex("abc").is_none());
+        assert!(decode_hex("zz").is_none());
+    }
+}
@@ -1,8 +1,51 @@
 use anyhow::{Context, Result};
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
+/// Schema changes applied once, in order, on top of the initial schema
+const VERSIONED_MIGRATIONS: &[(i64, &str)] = &[
+    (2, include_str!("../../migrations/002_block_pinning.sql")),
+    (3, include_str!("../../migrations/003_session_folders.sql")),
+    (4, include_str!("../../migrations/004_project_sessions.sql")),
+    (5, include_str!("../../migrations/005_startup_commands.sql")),
+    (6, include_str!("../../migrations/006_session_env_file.sql")),
+    (7, include_str!("../../migrations/007_session_triggers.sql")),
+    (8, include_str!("../../migrations/008_compressed_output.sql")),
+    (9, include_str!("../../migrations/009_encrypted_blocks.sql")),
+    (10, include_str!("../../migrations/010_block_tags.sql")),
+    (11, include_str!("../../migrations/011_hook_results.sql")),
+    (12, include_str!("../../migrations/012_ai_audit.sql")),
+    (13, include_str!("../../migrations/013_ai_provenance.sql")),
+    (14, include_str!("../../migrations/014_directory_bookmarks.sql")),
+    (15, include_str!("../../migrations/015_block_dividers.sql")),
+    (16, include_str!("../../migrations/016_command_frequency.sql")),
+    (17, include_str!("../../migrations/017_session_metadata.sql")),
+    (18, include_str!("../../migrations/018_calculation_blocks.sql")),
+    (19, include_str!("../../migrations/019_ai_context_blocks.sql")),
+];
+
+/// Block outputs at least this many bytes are stored compressed
+pub const COMPRESSION_THRESHOLD: usize = 4 * 1024;
+
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Compress output for the `output_zstd` column, or None if it is small
+/// enough to store as plain text
+pub fn compress_output(output: &str) -> Result<Option<Vec<u8>>> {
+    if output.len() < COMPRESSION_THRESHOLD {
+        return Ok(None);
+    }
+    zstd::encode_all(output.as_bytes(), COMPRESSION_LEVEL)
+        .map(Some)
+        .context("Failed to compress block output")
+}
+
+pub fn decompress_output(data: &[u8]) -> Result<String> {
+    let bytes = zstd::decode_all(data).context("Failed to decompress block output")?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
 pub struct Database {
     pool: SqlitePool,
 }
@@ -46,10 +89,102 @@ impl Database {
             .await
             .context("Failed to run migrations")?;
 
+        // Later migrations are not idempotent; track them with user_version
+        let version: i64 = sqlx::query_scalar("PRAGMA user_version")
+            .fetch_one(&self.pool)
+            .await
+            .context("Failed to read schema version")?;
+
+        for (target, sql) in VERSIONED_MIGRATIONS.iter().copied() {
+            if version < target {
+                self.run_migration(target, sql).await?;
+            }
+        }
+
+        if version < 8 {
+            self.compress_existing_outputs().await?;
+        }
+
         tracing::info!("Database migrations completed");
         Ok(())
     }
 
+    /// Run one versioned migration and record its version in a single
+    /// transaction, so a failure part way leaves neither behind
+    async fn run_migration(&self, target: i64, sql: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await.context("Failed to start transaction")?;
+        // Executor::execute rather than raw_sql, whose future on a borrowed
+        // connection isn't Send, and opening the database happens in spawned tasks
+        sqlx::Executor::execute(&mut *tx, sql)
+            .await
+            .with_context(|| format!("Failed to run migration {}", target))?;
+        sqlx::query(&format!("PRAGMA user_version = {}", target))
+            .execute(&mut *tx)
+            .await
+            .context("Failed to update schema version")?;
+        tx.commit()
+            .await
+            .with_context(|| format!("Failed to commit migration {}", target))
+    }
+
+    /// Move large outputs saved before compression into the compressed column
+    async fn compress_existing_outputs(&self) -> Result<()> {
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            "SELECT id, output FROM blocks WHERE output_zstd IS NULL AND length(CAST(output AS BLOB)) >= ?",
+        )
+        .bind(COMPRESSION_THRESHOLD as i64)
+        .fetch_all(&self.pool)
+        .await
+        .context("Failed to read block outputs")?;
+
+        for (id, output) in &rows {
+            sqlx::query("UPDATE blocks SET output = '', output_zstd = ? WHERE id = ?")
+                .bind(compress_output(output)?)
+                .bind(id)
+                .execute(&self.pool)
+                .await
+                .context("Failed to compress block output")?;
+        }
+
+        if !rows.is_empty() {
+            tracing::info!("Compressed output of {} stored blocks", rows.len());
+        }
+        Ok(())
+    }
+
+    /// Size of the database in bytes (pages in use plus free pages)
+    pub async fn size_bytes(&self) -> Result<u64> {
+        let page_count: i64 = sqlx::query_scalar("PRAGMA page_count")
+            .fetch_one(&self.pool)
+            .await
+            .context("Failed to read page count")?;
+        let page_size: i64 = sqlx::query_scalar("PRAGMA page_size")
+            .fetch_one(&self.pool)
+            .await
+            .context("Failed to read page size")?;
+        Ok((page_count * page_size) as u64)
+    }
+
+    /// Rebuild the database file to reclaim space left by deleted rows
+    pub async fn vacuum(&self) -> Result<()> {
+        sqlx::raw_sql("VACUUM; PRAGMA wal_checkpoint(TRUNCATE);")
+            .execute(&self.pool)
+            .await
+            .context("Failed to compact database")?;
+        Ok(())
+    }
+
+    /// Write a consistent snapshot of the database to `path`, which must not
+    /// exist yet. Unlike copying the file, this includes pages still in the WAL.
+    pub async fn backup_to(&self, path: &Path) -> Result<()> {
+        sqlx::query("VACUUM INTO ?")
+            .bind(path.to_string_lossy().to_string())
+            .execute(&self.pool)
+            .await
+            .with_context(|| format!("Failed to back up database to {}", path.display()))?;
+        Ok(())
+    }
+
     pub fn pool(&self) -> &SqlitePool {
         &self.pool
     }
@@ -75,4 +210,53 @@ mod tests {
         
         db.close().await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_migrations_run_once() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+
+        let db = Database::new(db_path.clone()).await.unwrap();
+        db.close().await.unwrap();
+
+        // Reopening must not re-apply the ALTER TABLE migrations
+        let db = Database::new(db_path).await.unwrap();
+        let version: i64 = sqlx::query_scalar("PRAGMA user_version")
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(version, VERSIONED_MIGRATIONS.last().unwrap().0);
+        db.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_rolls_back() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+
+        let sql = "CREATE TABLE half_done (id TEXT); ALTER TABLE no_such_table ADD COLUMN x TEXT;";
+        assert!(db.run_migration(99, sql).await.is_err());
+
+        let version: i64 = sqlx::query_scalar("PRAGMA user_version")
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(version, VERSIONED_MIGRATIONS.last().unwrap().0);
+        let tables: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master WHERE name = 'half_done'")
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(tables, 0);
+        db.close().await.unwrap();
+    }
+
+    #[test]
+    fn test_output_compression_threshold() {
+        assert!(compress_output("short").unwrap().is_none());
+
+        let long = "line of build output\n".repeat(500);
+        let compressed = compress_output(&long).unwrap().unwrap();
+        assert!(compressed.len() < long.len() / 10);
+        assert_eq!(decompress_output(&compressed).unwrap(), long);
+    }
 }
@@ -0,0 +1,127 @@
+use super::OutputBuffer;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// How bad a line of output looks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineSeverity {
+    Error,
+    Warning,
+}
+
+/// A line of output that reports an error or warning
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputDiagnostic {
+    /// Zero-based line index in the block output
+    pub line: usize,
+    pub severity: LineSeverity,
+}
+
+fn error_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(concat!(
+            r"(?i)^\s*(error|fatal)(\[\w+\])?:|\b(error|fatal|failed|failure):|\berror [a-z]*\d+:",
+            r"|^traceback \(most recent call last\)|^\w+(error|exception): |panicked at|^panic: ",
+            r"|^npm err!|^\s*\[error\]|^make(\[\d+\])?: \*\*\*|(?-i:^E {3})",
+        ))
+        .expect("valid error pattern")
+    })
+}
+
+fn warning_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?i)^\s*warn(ing)?(\[\w+\])?:|\bwarning:|^npm warn|^\s*\[warn(ing)?\]|\w+warning: ")
+            .expect("valid warning pattern")
+    })
+}
+
+/// Whether a line reports an error or a warning, e.g. `error[E0425]:`,
+/// `warning:`, a Python traceback or a Rust/Go panic
+pub fn line_severity(line: &str) -> Option<LineSeverity> {
+    if error_pattern().is_match(line) {
+        Some(LineSeverity::Error)
+    } else if warning_pattern().is_match(line) {
+        Some(LineSeverity::Warning)
+    } else {
+        None
+    }
+}
+
+/// Error and warning lines of one block's output, kept up to date as the
+/// output grows without rescanning lines already seen
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticScan {
+    diagnostics: Vec<OutputDiagnostic>,
+    /// Lines ending in a newline that have been scanned; the line after them
+    /// may still be written to
+    complete_lines: usize,
+    scanned_len: usize,
+}
+
+impl DiagnosticScan {
+    pub fn diagnostics(&self) -> &[OutputDiagnostic] {
+        &self.diagnostics
+    }
+
+    /// Scan what was added to `output` since the last update
+    pub fn update(&mut self, output: &OutputBuffer) -> &[OutputDiagnostic] {
+        if output.len() < self.scanned_len {
+            // Rewritten, e.g. a carriage return or the block ran again
+            *self = Self::default();
+        }
+        if output.len() == self.scanned_len {
+            return &self.diagnostics;
+        }
+
+        let complete = self.complete_lines;
+        self.diagnostics.retain(|d| d.line < complete);
+        for (index, line) in output.lines().enumerate().skip(complete) {
+            if let Some(severity) = line_severity(line.trim_end_matches(['\n', '\r'])) {
+                self.diagnostics.push(OutputDiagnostic { line: index, severity });
+            }
+            if line.ends_with('\n') {
+                self.complete_lines = index + 1;
+            }
+        }
+        self.scanned_len = output.len();
+        &self.diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_severity() {
+        assert_eq!(line_severity("error[E0425]: cannot find value `x` in this scope"), Some(LineSeverity::Error));
+        assert_eq!(line_severity("Traceback (most recent call last):"), Some(LineSeverity::Error));
+        assert_eq!(line_severity("ValueError: invalid literal for int()"), Some(LineSeverity::Error));
+        assert_eq!(line_severity("thread 'main' panicked at src/main.rs:2:5:"), Some(LineSeverity::Error));
+        assert_eq!(line_severity("src/app.ts(3,1): error TS2304: Cannot find name 'x'."), Some(LineSeverity::Error));
+        assert_eq!(line_severity("warning: unused variable: `y`"), Some(LineSeverity::Warning));
+        assert_eq!(line_severity("npm WARN deprecated request@2.88.2"), Some(LineSeverity::Warning));
+        assert_eq!(line_severity("   Compiling immaterium v0.1.0"), None);
+        assert_eq!(line_severity("test tests::error_handling ... ok"), None);
+    }
+
+    #[test]
+    fn test_scan_follows_streaming_output() {
+        let mut output = OutputBuffer::new();
+        let mut scan = DiagnosticScan::default();
+        output.push_str("Compiling\nwarning: unused");
+        assert_eq!(scan.update(&output), &[OutputDiagnostic { line: 1, severity: LineSeverity::Warning }]);
+
+        output.push_str(" import\nerror: aborting\n");
+        assert_eq!(
+            scan.update(&output).iter().map(|d| (d.line, d.severity)).collect::<Vec<_>>(),
+            vec![(1, LineSeverity::Warning), (2, LineSeverity::Error)]
+        );
+
+        output.clear();
+        output.push_str("ok\n");
+        assert!(scan.update(&output).is_empty());
+    }
+}
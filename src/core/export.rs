@@ -1,8 +1,17 @@
+use super::crypto::FieldCipher;
 use super::Session;
-use anyhow::{Context, Result};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use anyhow::{anyhow, bail, Context, Result};
+use argon2::Argon2;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 
+/// Header of an encrypted session archive, followed by the salt and the
+/// sealed, zstd-compressed JSON export
+const ENCRYPTED_MAGIC: &[u8] = b"IMMATERIUM-ENC1\n";
+const SALT_LEN: usize = 16;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportedSession {
     pub session: Session,
@@ -40,6 +49,45 @@ impl ExportedSession {
         Self::from_json(&json)
     }
 
+    /// Export session as an encrypted archive, readable only with `passphrase`.
+    /// The key is derived with Argon2id and the archive sealed with AES-256-GCM.
+    pub fn to_encrypted(&self, passphrase: &str) -> Result<Vec<u8>> {
+        let json = serde_json::to_vec(&self).context("Failed to serialize session")?;
+        seal_with_passphrase(&json, passphrase)
+    }
+
+    /// Export session to an encrypted archive file
+    pub fn to_encrypted_file<P: AsRef<Path>>(&self, path: P, passphrase: &str) -> Result<()> {
+        let archive = self.to_encrypted(passphrase)?;
+        std::fs::write(path.as_ref(), archive)
+            .context("Failed to write encrypted archive")?;
+        Ok(())
+    }
+
+    /// Whether `data` is an encrypted archive rather than plain JSON
+    pub fn is_encrypted(data: &[u8]) -> bool {
+        data.starts_with(ENCRYPTED_MAGIC)
+    }
+
+    /// Import session from an encrypted archive
+    pub fn from_encrypted(data: &[u8], passphrase: &str) -> Result<Self> {
+        let json = open_with_passphrase(data, passphrase)?;
+        serde_json::from_slice(&json)
+            .context("Failed to deserialize session from archive")
+    }
+
+    /// Import session from a JSON file or an encrypted archive file.
+    /// The passphrase is only used for encrypted archives.
+    pub fn from_file<P: AsRef<Path>>(path: P, passphrase: &str) -> Result<Self> {
+        let data = std::fs::read(path.as_ref())
+            .context("Failed to read session file")?;
+        if Self::is_encrypted(&data) {
+            Self::from_encrypted(&data, passphrase)
+        } else {
+            Self::from_json(std::str::from_utf8(&data).context("Session file is not valid UTF-8")?)
+        }
+    }
+
     /// Export session to Markdown format
     pub fn to_markdown(&self) -> String {
         let mut md = String::new();
@@ -51,8 +99,23 @@ impl ExportedSession {
         if !self.session.blocks.is_empty() {
             md.push_str("## Commands\n\n");
             
-            for (i, block) in self.session.blocks.iter().enumerate() {
-                md.push_str(&format!("### Block {} - {}\n\n", i + 1, block.timestamp.format("%H:%M:%S")));
+            let mut number = 0;
+            for block in &self.session.blocks {
+                if block.is_divider {
+                    md.push_str(&format!("## {}\n\n", block.command));
+                    continue;
+                }
+                // Calculations were evaluated in place, not run in the shell
+                if block.is_calculation {
+                    md.push_str(&format!(
+                        "**Calculation:** `{}` = `{}`\n\n",
+                        block.command,
+                        block.output.to_string().trim()
+                    ));
+                    continue;
+                }
+                number += 1;
+                md.push_str(&format!("### Block {} - {}\n\n", number, block.timestamp.format("%H:%M:%S")));
                 
                 // Command
                 md.push_str("**Command:**\n```bash\n");
@@ -62,7 +125,7 @@ impl ExportedSession {
                 // Output
                 if !block.output.is_empty() {
                     md.push_str("**Output:**\n```\n");
-                    md.push_str(&block.output);
+                    md.push_str(&block.output.to_string());
                     md.push_str("\n```\n\n");
                 }
                 
@@ -102,12 +165,26 @@ impl ExportedSession {
         text.push_str(&format!("Working Directory: {}\n\n", self.session.working_directory.display()));
         
         if !self.session.blocks.is_empty() {
-            for (i, block) in self.session.blocks.iter().enumerate() {
-                text.push_str(&format!("[Block {}] {}\n", i + 1, block.timestamp.format("%H:%M:%S")));
+            let mut number = 0;
+            for block in &self.session.blocks {
+                if block.is_divider {
+                    text.push_str(&format!("==== {} ====\n\n", block.command));
+                    continue;
+                }
+                if block.is_calculation {
+                    text.push_str(&format!(
+                        "Calculation: {} = {}\n\n",
+                        block.command,
+                        block.output.to_string().trim()
+                    ));
+                    continue;
+                }
+                number += 1;
+                text.push_str(&format!("[Block {}] {}\n", number, block.timestamp.format("%H:%M:%S")));
                 text.push_str(&format!("$ {}\n", block.command));
                 
                 if !block.output.is_empty() {
-                    text.push_str(&block.output);
+                    text.push_str(&block.output.to_string());
                     if !block.output.ends_with('\n') {
                         text.push('\n');
                     }
@@ -133,6 +210,49 @@ impl ExportedSession {
     }
 }
 
+/// Compress `payload` and seal it with a key derived from `passphrase`,
+/// behind the encrypted archive header
+pub(super) fn seal_with_passphrase(payload: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    if passphrase.is_empty() {
+        bail!("Passphrase is empty");
+    }
+
+    let compressed = zstd::encode_all(payload, 3).context("Failed to compress archive")?;
+
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let sealed = passphrase_cipher(passphrase, &salt)?.seal(&compressed)?;
+
+    let mut archive = ENCRYPTED_MAGIC.to_vec();
+    archive.extend_from_slice(&salt);
+    archive.extend_from_slice(&sealed);
+    Ok(archive)
+}
+
+/// Reverse of `seal_with_passphrase`
+pub(super) fn open_with_passphrase(data: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let rest = data
+        .strip_prefix(ENCRYPTED_MAGIC)
+        .context("Not an encrypted archive")?;
+    if rest.len() < SALT_LEN {
+        bail!("Encrypted archive is truncated");
+    }
+    let (salt, sealed) = rest.split_at(SALT_LEN);
+
+    let compressed = passphrase_cipher(passphrase, salt)?
+        .open(sealed)
+        .map_err(|_| anyhow!("Wrong passphrase or damaged archive"))?;
+    zstd::decode_all(compressed.as_slice()).context("Failed to decompress archive")
+}
+
+fn passphrase_cipher(passphrase: &str, salt: &[u8]) -> Result<FieldCipher> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Failed to derive key from passphrase: {}", e))?;
+    Ok(FieldCipher::new(&key))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -167,6 +287,31 @@ mod tests {
         assert_eq!(imported.session.id, session.id);
     }
 
+    #[test]
+    fn test_encrypted_export_import() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("session.enc");
+
+        let mut session = Session::new("prod".to_string(), PathBuf::from("/tmp"));
+        let mut block = Block::new("cat .env".to_string(), PathBuf::from("/tmp"));
+        block.append_output("API_KEY=hunter2\n".to_string());
+        session.blocks.push(block);
+
+        let exported = ExportedSession::new(session.clone());
+        exported.to_encrypted_file(&file_path, "correct horse").unwrap();
+
+        let data = std::fs::read(&file_path).unwrap();
+        assert!(ExportedSession::is_encrypted(&data));
+        assert!(!data.windows(7).any(|w| w == b"hunter2"));
+
+        let imported = ExportedSession::from_file(&file_path, "correct horse").unwrap();
+        assert_eq!(imported.session.id, session.id);
+        assert!(imported.session.blocks[0].output == "API_KEY=hunter2\n");
+
+        assert!(ExportedSession::from_file(&file_path, "wrong").is_err());
+        assert!(exported.to_encrypted("").is_err());
+    }
+
     #[test]
     fn test_markdown_export() {
         let mut session = Session::new("test".to_string(), PathBuf::from("/tmp"));
@@ -184,6 +329,38 @@ mod tests {
         assert!(markdown.contains("hello"));
     }
 
+    #[test]
+    fn test_dividers_in_exports() {
+        let mut session = Session::new("test".to_string(), PathBuf::from("/tmp"));
+        session.blocks.push(Block::divider("Build".to_string(), PathBuf::from("/tmp")));
+        session.blocks.push(Block::new("make".to_string(), PathBuf::from("/tmp")));
+        let exported = ExportedSession::new(session);
+
+        let markdown = exported.to_markdown();
+        assert!(markdown.contains("## Build\n"));
+        // Dividers aren't counted as blocks
+        assert!(markdown.contains("### Block 1 - "));
+        assert!(!markdown.contains("### Block 2"));
+        assert!(exported.to_text().contains("==== Build ====\n"));
+
+        let imported = ExportedSession::from_json(&exported.to_json().unwrap()).unwrap();
+        assert!(imported.session.blocks[0].is_divider);
+    }
+
+    #[test]
+    fn test_calculations_in_exports() {
+        let mut session = Session::new("test".to_string(), PathBuf::from("/tmp"));
+        session.blocks.push(Block::calculation("4096*3/1024".to_string(), "12".to_string(), PathBuf::from("/tmp")));
+        let exported = ExportedSession::new(session);
+
+        let markdown = exported.to_markdown();
+        assert!(markdown.contains("**Calculation:** `4096*3/1024` = `12`\n"));
+        assert!(!markdown.contains("```bash"));
+        let text = exported.to_text();
+        assert!(text.contains("Calculation: 4096*3/1024 = 12\n"));
+        assert!(!text.contains("$ 4096"));
+    }
+
     #[test]
     fn test_text_export() {
         let mut session = Session::new("test".to_string(), PathBuf::from("/tmp"));
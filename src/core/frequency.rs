@@ -0,0 +1,124 @@
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+
+/// How often and how recently something was used, with uses counting for
+/// less as they age, so last week's habits give way to today's
+pub fn frecency(count: u32, last_used: DateTime<Utc>, now: DateTime<Utc>) -> f64 {
+    let age = now.signed_duration_since(last_used);
+    let weight = if age.num_hours() < 1 {
+        4.0
+    } else if age.num_days() < 1 {
+        2.0
+    } else if age.num_weeks() < 1 {
+        1.0
+    } else {
+        0.25
+    };
+    count as f64 * weight
+}
+
+/// How often a command was run, across all sessions
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandUsage {
+    pub command: String,
+    pub count: u32,
+    pub last_used: DateTime<Utc>,
+}
+
+impl CommandUsage {
+    pub fn frecency(&self, now: DateTime<Utc>) -> f64 {
+        frecency(self.count, self.last_used, now)
+    }
+
+    pub(crate) fn from_row(row: &SqliteRow) -> Self {
+        Self {
+            command: row.get("command"),
+            count: row.get::<i64, _>("count") as u32,
+            last_used: DateTime::parse_from_rfc3339(row.get("last_used"))
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// The form a command is counted under: trimmed, with runs of whitespace
+/// outside quotes collapsed, so `ls  -la` and `ls -la ` count as one.
+/// Commands starting with a space aren't counted, like bash's
+/// `HISTCONTROL=ignorespace`.
+pub fn normalize_command(command: &str) -> Option<String> {
+    if command.starts_with(' ') || command.trim().is_empty() {
+        return None;
+    }
+    let mut normalized = String::with_capacity(command.len());
+    let mut quote = None;
+    let mut escaped = false;
+    let mut separated = false;
+    for c in command.trim().chars() {
+        match quote {
+            _ if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '\\' => escaped = true,
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c.is_whitespace() => {
+                if !separated {
+                    normalized.push(' ');
+                    separated = true;
+                }
+                continue;
+            }
+            None => {}
+        }
+        separated = false;
+        normalized.push(c);
+    }
+    Some(normalized)
+}
+
+/// Commands starting with `prefix`, most used first. The prefix itself is
+/// left out, as there is nothing to complete.
+pub fn rank_commands<'a>(usage: &'a [CommandUsage], prefix: &str, now: DateTime<Utc>) -> Vec<&'a CommandUsage> {
+    let mut matches: Vec<&CommandUsage> = usage
+        .iter()
+        .filter(|u| u.command.starts_with(prefix) && u.command != prefix)
+        .collect();
+    matches.sort_by(|a, b| b.frecency(now).total_cmp(&a.frecency(now)));
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_normalize_command() {
+        assert_eq!(normalize_command("ls   -la  ").as_deref(), Some("ls -la"));
+        assert_eq!(normalize_command("echo 'a   b'  c").as_deref(), Some("echo 'a   b' c"));
+        assert_eq!(normalize_command("echo a\\  b").as_deref(), Some("echo a\\  b"));
+        assert_eq!(normalize_command("echo \"a \\\"  b\"  c").as_deref(), Some("echo \"a \\\"  b\" c"));
+        assert_eq!(normalize_command(" secret --token x"), None);
+        assert_eq!(normalize_command(""), None);
+    }
+
+    #[test]
+    fn test_rank_commands() {
+        let now = Utc::now();
+        let usage = |command: &str, count: u32, age: Duration| CommandUsage {
+            command: command.to_string(),
+            count,
+            last_used: now - age,
+        };
+        let usage = vec![
+            usage("cargo build", 40, Duration::days(30)),
+            usage("cargo test", 5, Duration::minutes(2)),
+            usage("cargo", 100, Duration::minutes(1)),
+            usage("git status", 80, Duration::minutes(1)),
+        ];
+        let commands: Vec<_> = rank_commands(&usage, "cargo", now).iter().map(|u| u.command.as_str()).collect();
+        // Five uses just now beat forty a month ago
+        assert_eq!(commands, ["cargo test", "cargo build"]);
+    }
+}
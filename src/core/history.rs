@@ -0,0 +1,139 @@
+/// Shell-style command history with Up/Down navigation
+#[derive(Debug, Clone)]
+pub struct CommandHistory {
+    entries: Vec<String>,
+    max_size: usize,
+    index: Option<usize>,
+    saved_input: String, // Input that was being typed before navigation started
+}
+
+impl CommandHistory {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            entries: Vec::new(),
+            max_size: max_size.max(1),
+            index: None,
+            saved_input: String::new(),
+        }
+    }
+
+    /// Replace the history with the given commands (oldest first)
+    pub fn load<I: IntoIterator<Item = String>>(&mut self, commands: I) {
+        self.entries.clear();
+        self.reset_navigation();
+        for command in commands {
+            self.push(command);
+        }
+    }
+
+    /// Add a command, skipping consecutive duplicates
+    pub fn push(&mut self, command: String) {
+        if command.trim().is_empty() || self.entries.last() == Some(&command) {
+            return;
+        }
+        self.entries.push(command);
+        if self.entries.len() > self.max_size {
+            let excess = self.entries.len() - self.max_size;
+            self.entries.drain(..excess);
+        }
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether the user is currently stepping through history
+    pub fn is_navigating(&self) -> bool {
+        self.index.is_some()
+    }
+
+    /// Step back in history (Up arrow). Returns the text to put in the input.
+    pub fn older(&mut self, current_input: &str) -> Option<String> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        let idx = match self.index {
+            None => {
+                self.saved_input = current_input.to_string();
+                self.entries.len() - 1
+            }
+            Some(0) => 0,
+            Some(idx) => idx - 1,
+        };
+        self.index = Some(idx);
+        Some(self.entries[idx].clone())
+    }
+
+    /// Step forward in history (Down arrow). Returns the text to put in the input;
+    /// past the newest entry the originally typed input is restored.
+    pub fn newer(&mut self) -> Option<String> {
+        let idx = self.index?;
+        if idx + 1 < self.entries.len() {
+            self.index = Some(idx + 1);
+            Some(self.entries[idx + 1].clone())
+        } else {
+            self.index = None;
+            Some(std::mem::take(&mut self.saved_input))
+        }
+    }
+
+    pub fn reset_navigation(&mut self) {
+        self.index = None;
+        self.saved_input.clear();
+    }
+}
+
+impl Default for CommandHistory {
+    fn default() -> Self {
+        Self::new(10000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_navigation_restores_typed_input() {
+        let mut history = CommandHistory::new(100);
+        history.push("ls".to_string());
+        history.push("pwd".to_string());
+
+        assert_eq!(history.older("ech"), Some("pwd".to_string()));
+        assert_eq!(history.older("pwd"), Some("ls".to_string()));
+        // Stays on the oldest entry
+        assert_eq!(history.older("ls"), Some("ls".to_string()));
+
+        assert_eq!(history.newer(), Some("pwd".to_string()));
+        assert_eq!(history.newer(), Some("ech".to_string()));
+        assert!(!history.is_navigating());
+        assert_eq!(history.newer(), None);
+    }
+
+    #[test]
+    fn test_consecutive_duplicates_and_max_size() {
+        let mut history = CommandHistory::new(2);
+        history.push("a".to_string());
+        history.push("a".to_string());
+        history.push("b".to_string());
+        history.push("c".to_string());
+
+        assert_eq!(history.entries(), &["b".to_string(), "c".to_string()]);
+    }
+
+    #[test]
+    fn test_empty_history() {
+        let mut history = CommandHistory::default();
+        assert_eq!(history.older(""), None);
+        assert_eq!(history.newer(), None);
+    }
+}
@@ -0,0 +1,305 @@
+use super::{Block, BlockState, SessionManager};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+const SEGMENT_EXTENSION: &str = "jsonl";
+
+/// A change to a session's blocks, recorded as it happens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JournalEntry {
+    /// Full copy of a block, replacing any earlier copy
+    Block { session_id: Uuid, block: Box<Block> },
+    /// Output appended to a block
+    Output { session_id: Uuid, block_id: Uuid, text: String },
+}
+
+/// Append-only log of block changes made since the last save, so a crash
+/// between auto-saves loses nothing. It is split into numbered segments: each
+/// save starts a new one and deletes the older ones once it is written.
+pub struct Journal {
+    dir: PathBuf,
+    segment: u64,
+    writer: Option<BufWriter<File>>,
+}
+
+impl Journal {
+    /// Start a new segment in `dir`, after any left over from earlier runs
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create journal directory {}", dir.display()))?;
+        let last = segments(dir)?.last().map(|(segment, _)| *segment).unwrap_or(0);
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            segment: last + 1,
+            writer: None,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn record_block(&mut self, session_id: Uuid, block: &Block) {
+        self.record(JournalEntry::Block {
+            session_id,
+            block: Box::new(block.clone()),
+        });
+    }
+
+    pub fn record_output(&mut self, session_id: Uuid, block_id: Uuid, text: &str) {
+        self.record(JournalEntry::Output {
+            session_id,
+            block_id,
+            text: text.to_string(),
+        });
+    }
+
+    /// Journaling is best effort: a failed write is logged, not fatal
+    fn record(&mut self, entry: JournalEntry) {
+        if let Err(e) = self.append(&entry) {
+            tracing::warn!("Failed to write journal: {:#}", e);
+        }
+    }
+
+    fn append(&mut self, entry: &JournalEntry) -> Result<()> {
+        if self.writer.is_none() {
+            let path = segment_path(&self.dir, self.segment);
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            self.writer = Some(BufWriter::new(file));
+        }
+        let writer = self.writer.as_mut().expect("journal writer was just opened");
+        serde_json::to_writer(&mut *writer, entry)?;
+        writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Write buffered entries to disk; called once per frame
+    pub fn flush(&mut self) {
+        if let Some(writer) = &mut self.writer {
+            if let Err(e) = writer.flush() {
+                tracing::warn!("Failed to flush journal: {}", e);
+            }
+        }
+    }
+
+    /// Close the current segment for a save that covers everything in it,
+    /// returning its number for `discard_through` once the save is written
+    pub fn checkpoint(&mut self) -> u64 {
+        self.flush();
+        self.writer = None;
+        self.segment += 1;
+        self.segment - 1
+    }
+}
+
+/// Delete segments up to and including `segment`
+pub fn discard_through(dir: &Path, segment: u64) -> Result<()> {
+    for (number, path) in segments(dir)? {
+        if number <= segment {
+            fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Entries left in `dir`, oldest first. A line cut short by a crash ends
+/// its segment.
+pub fn pending_entries(dir: &Path) -> Result<Vec<JournalEntry>> {
+    let mut entries = Vec::new();
+    for (_, path) in segments(dir)? {
+        let file = File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+        for line in BufReader::new(file).lines() {
+            match line.ok().and_then(|line| serde_json::from_str(&line).ok()) {
+                Some(entry) => entries.push(entry),
+                None => {
+                    tracing::warn!("Journal {} ends with an incomplete entry", path.display());
+                    break;
+                }
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Apply entries left by a run that didn't save them to the database, then
+/// delete them. Blocks still running were cut off and are marked cancelled.
+/// Returns the number of blocks recovered.
+pub async fn replay(dir: &Path, sm: &SessionManager) -> Result<usize> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let entries = pending_entries(dir)?;
+    let mut by_session: Vec<(Uuid, Vec<JournalEntry>)> = Vec::new();
+    for entry in entries {
+        let session_id = match &entry {
+            JournalEntry::Block { session_id, .. } | JournalEntry::Output { session_id, .. } => *session_id,
+        };
+        match by_session.iter_mut().find(|(id, _)| *id == session_id) {
+            Some((_, session_entries)) => session_entries.push(entry),
+            None => by_session.push((session_id, vec![entry])),
+        }
+    }
+
+    let mut recovered = 0;
+    for (session_id, entries) in by_session {
+        let mut session = match sm.load_session(&session_id).await {
+            Ok(session) => session,
+            Err(e) => {
+                tracing::warn!("Skipping journal for session {}: {:#}", session_id, e);
+                continue;
+            }
+        };
+        let touched = apply(&mut session.blocks, entries);
+        sm.save_blocks(&session_id, &session.blocks).await?;
+        tracing::info!("Recovered {} blocks in session {} from the journal", touched, session.name);
+        recovered += touched;
+    }
+
+    for (_, path) in segments(dir)? {
+        fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    }
+    Ok(recovered)
+}
+
+/// Apply entries to a session's blocks in order, returning how many blocks changed
+fn apply(blocks: &mut Vec<Block>, entries: Vec<JournalEntry>) -> usize {
+    let mut touched = HashSet::new();
+    for entry in entries {
+        match entry {
+            JournalEntry::Block { block, .. } => {
+                touched.insert(block.id);
+                match blocks.iter_mut().find(|b| b.id == block.id) {
+                    Some(existing) => *existing = *block,
+                    None => blocks.push(*block),
+                }
+            }
+            JournalEntry::Output { block_id, text, .. } => {
+                if let Some(block) = blocks.iter_mut().find(|b| b.id == block_id) {
+                    touched.insert(block_id);
+                    block.append_output(text);
+                }
+            }
+        }
+    }
+    for block in blocks.iter_mut() {
+        if touched.contains(&block.id) && block.state == BlockState::Running {
+            block.append_output("\n[Interrupted: Immaterium closed unexpectedly]\n".to_string());
+            block.cancel_execution();
+        }
+    }
+    touched.len()
+}
+
+fn segment_path(dir: &Path, segment: u64) -> PathBuf {
+    dir.join(format!("{:020}.{}", segment, SEGMENT_EXTENSION))
+}
+
+/// Segment files in `dir`, in the order they were written
+fn segments(dir: &Path) -> Result<Vec<(u64, PathBuf)>> {
+    let mut segments = Vec::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(segments),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_EXTENSION) {
+            continue;
+        }
+        if let Some(number) = path.file_stem().and_then(|s| s.to_str()).and_then(|s| s.parse().ok()) {
+            segments.push((number, path));
+        }
+    }
+    segments.sort_by_key(|(number, _)| *number);
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Database, Session};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_checkpoint_and_discard() {
+        let dir = tempdir().unwrap();
+        let session_id = Uuid::new_v4();
+        let block = Block::new("make".to_string(), PathBuf::from("/src"));
+
+        let mut journal = Journal::open(dir.path()).unwrap();
+        journal.record_block(session_id, &block);
+        let saved = journal.checkpoint();
+        journal.record_output(session_id, block.id, "building\n");
+        journal.flush();
+        assert_eq!(pending_entries(dir.path()).unwrap().len(), 2);
+
+        // The save covering the first segment finished
+        discard_through(dir.path(), saved).unwrap();
+        let entries = pending_entries(dir.path()).unwrap();
+        assert!(matches!(&entries[..], [JournalEntry::Output { text, .. }] if text == "building\n"));
+
+        // A later run continues after the leftover segment
+        assert!(Journal::open(dir.path()).unwrap().segment > saved + 1);
+    }
+
+    #[test]
+    fn test_incomplete_entry_is_skipped() {
+        let dir = tempdir().unwrap();
+        let session_id = Uuid::new_v4();
+        let mut journal = Journal::open(dir.path()).unwrap();
+        journal.record_output(session_id, Uuid::new_v4(), "kept");
+        journal.flush();
+        drop(journal);
+
+        let (_, path) = segments(dir.path()).unwrap().pop().unwrap();
+        let mut file = OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(b"{\"kind\":\"output\",\"sess").unwrap();
+
+        assert_eq!(pending_entries(dir.path()).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let dir = tempdir().unwrap();
+        let journal_dir = dir.path().join("journal");
+        let db = Database::new(dir.path().join("test.db")).await.unwrap();
+        let manager = SessionManager::new(db).await.unwrap();
+
+        let session = Session::new("work".to_string(), PathBuf::from("/src"));
+        manager.create_session(&session).await.unwrap();
+        let mut saved = Block::new("ls".to_string(), PathBuf::from("/src"));
+        saved.complete_execution(0);
+        manager.save_blocks(&session.id, std::slice::from_ref(&saved)).await.unwrap();
+
+        // Crash while a second command was still printing
+        let mut journal = Journal::open(&journal_dir).unwrap();
+        let mut running = Block::new("make".to_string(), PathBuf::from("/src"));
+        running.start_execution();
+        journal.record_block(session.id, &running);
+        journal.record_output(session.id, running.id, "compiling\n");
+        journal.flush();
+        drop(journal);
+
+        assert_eq!(replay(&journal_dir, &manager).await.unwrap(), 1);
+        let blocks = manager.load_session(&session.id).await.unwrap().blocks;
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].id, saved.id);
+        assert_eq!(blocks[1].state, BlockState::Cancelled);
+        assert!(blocks[1].output.to_string().starts_with("compiling\n"));
+        assert!(pending_entries(&journal_dir).unwrap().is_empty());
+
+        // Nothing left to replay
+        assert_eq!(replay(&journal_dir, &manager).await.unwrap(), 0);
+    }
+}
@@ -111,7 +111,7 @@ impl BlockManager {
     }
 
     pub fn copy_block_output(&self, id: &Uuid) -> Option<String> {
-        self.get_block(id).map(|b| b.output.clone())
+        self.get_block(id).map(|b| b.output.to_string())
     }
 
     pub fn copy_block_full(&self, id: &Uuid) -> Option<String> {
@@ -212,7 +212,7 @@ mod tests {
     fn test_copy_operations() {
         let mut manager = BlockManager::new();
         let mut block = Block::new("echo test".to_string(), PathBuf::from("/tmp"));
-        block.output = "test output".to_string();
+        block.output = "test output".into();
         block.exit_code = Some(0);
         let id = block.id;
         
@@ -1,16 +1,47 @@
 // Core data structures module
 // Contains Block, Session, BlockManager, and database implementations
 
+pub mod ai_audit;
+pub mod archive;
+pub mod backup;
+pub mod bookmarks;
 pub mod block;
+pub mod crash;
+pub mod crypto;
 pub mod database;
+pub mod diagnostics;
 pub mod export;
+pub mod frequency;
+pub mod history;
+pub mod journal;
 pub mod manager;
+pub mod output;
+pub mod retry;
+pub mod scheduler;
+pub mod sections;
 pub mod session;
 pub mod session_manager;
+pub mod trigger;
 
-pub use block::{Block, BlockMetadata, BlockState};
+pub use ai_audit::{AiAuditEntry, AiRequestKind};
+pub use archive::{DataArchive, RestoreMode, RestoreReport};
+pub use backup::BackupInfo;
+pub use bookmarks::DirectoryBookmark;
+pub use block::{AiProvenance, Block, BlockFilter, BlockMetadata, BlockState, HookPhase, HookResult};
+pub use crash::CrashReport;
+pub use crypto::FieldCipher;
 pub use database::Database;
+pub use diagnostics::{DiagnosticScan, LineSeverity, OutputDiagnostic};
 pub use export::ExportedSession;
+pub use frequency::CommandUsage;
+pub use history::CommandHistory;
+pub use journal::{Journal, JournalEntry};
 pub use manager::BlockManager;
-pub use session::Session;
-pub use session_manager::{SessionInfo, SessionManager};
+pub use output::OutputBuffer;
+pub use scheduler::{ScheduledCommand, Scheduler};
+pub use sections::{OutputSection, SectionScan};
+pub use session::{Session, SessionColor};
+pub use session_manager::{
+    group_sessions_by_folder, session_name_taken, session_subtitle, unique_session_name, SessionInfo, SessionManager,
+};
+pub use trigger::{Trigger, TriggerAction, TriggerHit, TriggerMatcher};
@@ -0,0 +1,198 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::sync::Arc;
+
+/// Size at which the open tail is sealed into a shared chunk
+const CHUNK_BYTES: usize = 16 * 1024;
+
+/// Command output stored as immutable line-aligned chunks plus an open tail.
+/// Appends only touch the tail and cloning shares the sealed chunks, so both
+/// stay cheap however much a command prints.
+#[derive(Debug, Clone, Default)]
+pub struct OutputBuffer {
+    /// Sealed chunks; each ends with a newline so no line spans two chunks
+    chunks: Vec<Arc<str>>,
+    tail: String,
+    len: usize,
+}
+
+impl OutputBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_str(&mut self, text: &str) {
+        self.tail.push_str(text);
+        self.len += text.len();
+        self.seal();
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.tail.push(c);
+        self.len += c.len_utf8();
+    }
+
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.tail.clear();
+        self.len = 0;
+    }
+
+    /// Length in bytes
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn ends_with(&self, c: char) -> bool {
+        match self.tail.chars().last() {
+            Some(last) => last == c,
+            None => self.chunks.last().is_some_and(|chunk| chunk.ends_with(c)),
+        }
+    }
+
+    /// The text in storage order; concatenated it is the full output
+    pub fn chunks(&self) -> impl Iterator<Item = &str> {
+        self.chunks.iter().map(|chunk| chunk.as_ref()).chain(std::iter::once(self.tail.as_str()))
+    }
+
+    /// Lines including their line endings, like `str::split_inclusive('\n')`
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.chunks().flat_map(|chunk| chunk.split_inclusive('\n'))
+    }
+
+    /// Text after the last newline (the line still being written)
+    pub fn current_line(&self) -> &str {
+        self.tail.rsplit('\n').next().unwrap_or_default()
+    }
+
+    /// Drop the line still being written, as a carriage return would
+    pub fn clear_current_line(&mut self) {
+        let removed = self.current_line().len();
+        self.tail.truncate(self.tail.len() - removed);
+        self.len -= removed;
+    }
+
+    /// The first `max_bytes` bytes, cut back to a character boundary
+    pub fn head(&self, max_bytes: usize) -> String {
+        let mut head = String::with_capacity(max_bytes.min(self.len));
+        for chunk in self.chunks() {
+            let remaining = max_bytes - head.len();
+            if chunk.len() <= remaining {
+                head.push_str(chunk);
+                continue;
+            }
+            let mut end = remaining;
+            while !chunk.is_char_boundary(end) {
+                end -= 1;
+            }
+            head.push_str(&chunk[..end]);
+            break;
+        }
+        head
+    }
+
+    /// Move complete lines out of the tail once it grows past the chunk size
+    fn seal(&mut self) {
+        if self.tail.len() < CHUNK_BYTES {
+            return;
+        }
+        if let Some(pos) = self.tail.rfind('\n') {
+            let rest = self.tail.split_off(pos + 1);
+            let sealed = std::mem::replace(&mut self.tail, rest);
+            self.chunks.push(Arc::from(sealed));
+        }
+    }
+}
+
+impl fmt::Display for OutputBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.chunks().try_for_each(|chunk| f.write_str(chunk))
+    }
+}
+
+impl From<&str> for OutputBuffer {
+    fn from(text: &str) -> Self {
+        let mut buffer = Self::new();
+        buffer.push_str(text);
+        buffer
+    }
+}
+
+impl From<String> for OutputBuffer {
+    fn from(text: String) -> Self {
+        Self::from(text.as_str())
+    }
+}
+
+impl PartialEq<&str> for OutputBuffer {
+    fn eq(&self, other: &&str) -> bool {
+        self.len == other.len() && {
+            let mut rest = *other;
+            self.chunks().all(|chunk| match rest.strip_prefix(chunk) {
+                Some(remaining) => {
+                    rest = remaining;
+                    true
+                }
+                None => false,
+            })
+        }
+    }
+}
+
+// Stored and exported as one string so sessions saved before chunking still load
+impl Serialize for OutputBuffer {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for OutputBuffer {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_seal_on_line_boundaries() {
+        let mut output = OutputBuffer::new();
+        let line = "x".repeat(99) + "\n";
+        for _ in 0..500 {
+            output.push_str(&line[..40]);
+            output.push_str(&line[40..]);
+        }
+        output.push_str("partial");
+
+        assert!(output.chunks.len() > 1);
+        assert!(output.chunks.iter().all(|chunk| chunk.ends_with('\n')));
+        assert_eq!(output.len(), 500 * 100 + 7);
+        assert_eq!(output.lines().count(), 501);
+        assert_eq!(output.current_line(), "partial");
+        assert_eq!(output.to_string().len(), output.len());
+
+        output.clear_current_line();
+        assert!(output.ends_with('\n'));
+        assert_eq!(output.len(), 500 * 100);
+    }
+
+    #[test]
+    fn test_head_and_equality() {
+        let output = OutputBuffer::from("héllo\nworld\n");
+        assert_eq!(output.head(2), "h");
+        assert_eq!(output.head(100), "héllo\nworld\n");
+        assert!(output == "héllo\nworld\n");
+        assert!(output != "héllo\n");
+
+        let json = serde_json::to_string(&output).unwrap();
+        assert_eq!(json, "\"héllo\\nworld\\n\"");
+        let parsed: OutputBuffer = serde_json::from_str(&json).unwrap();
+        assert!(parsed == "héllo\nworld\n");
+    }
+}
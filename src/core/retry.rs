@@ -0,0 +1,80 @@
+use super::OutputBuffer;
+use regex::Regex;
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Lines at the end of the output searched for transient errors; the cause
+/// of a failure is almost always printed last
+const TAIL_LINES: usize = 40;
+
+/// Longest wait between automatic retries, however many attempts were made
+const MAX_DELAY: Duration = Duration::from_secs(300);
+
+fn transient_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(concat!(
+            r"(?i)timed? ?out|timeout|connection (reset|refused|closed)|broken pipe",
+            r"|temporary failure in name resolution|could not resolve host|name or service not known",
+            r"|network is unreachable|no route to host|tls handshake|rate limit|too many requests",
+            r"|econnreset|econnrefused|etimedout|eai_again",
+            r"|\b(429|50[0-4])\b.*\b(error|status|gateway|unavailable|server|http)",
+            r"|\b(error|status|http/[\d.]+)\b\D{0,20}\b(429|50[0-4])\b",
+            r"|bad gateway|service unavailable|internal server error",
+        ))
+        .expect("valid transient error pattern")
+    })
+}
+
+/// Whether a failed command's output looks like it hit a passing problem
+/// (network errors, timeouts, 5xx responses) that running it again may fix
+pub fn is_transient_failure(output: &OutputBuffer) -> bool {
+    let mut tail = VecDeque::with_capacity(TAIL_LINES);
+    for line in output.lines() {
+        if tail.len() == TAIL_LINES {
+            tail.pop_front();
+        }
+        tail.push_back(line);
+    }
+    tail.iter().any(|line| transient_pattern().is_match(line))
+}
+
+/// Wait before automatic retry number `attempt` (starting at 1): `initial`,
+/// doubled for each attempt after the first
+pub fn backoff_delay(initial: Duration, attempt: u32) -> Duration {
+    let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+    initial.saturating_mul(factor).min(MAX_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(text: &str) -> OutputBuffer {
+        let mut output = OutputBuffer::new();
+        output.push_str(text);
+        output
+    }
+
+    #[test]
+    fn test_is_transient_failure() {
+        assert!(is_transient_failure(&output("Fetching...\ncurl: (28) Operation timed out after 30001 ms\n")));
+        assert!(is_transient_failure(&output("error: RPC failed; curl 56 Recv failure: Connection reset by peer\n")));
+        assert!(is_transient_failure(&output("npm ERR! 503 Service Unavailable - GET https://registry.npmjs.org/x\n")));
+        assert!(is_transient_failure(&output("fatal: unable to access: Could not resolve host: github.com\n")));
+        assert!(is_transient_failure(&output("HTTP/1.1 502 Bad Gateway\n")));
+
+        assert!(!is_transient_failure(&output("error[E0425]: cannot find value `x` in this scope\n")));
+        assert!(!is_transient_failure(&output("ls: cannot access 'missing': No such file or directory\n")));
+        assert!(!is_transient_failure(&output("test result: FAILED. 500 passed; 3 failed\n")));
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let initial = Duration::from_secs(2);
+        assert_eq!(backoff_delay(initial, 1), Duration::from_secs(2));
+        assert_eq!(backoff_delay(initial, 3), Duration::from_secs(8));
+        assert_eq!(backoff_delay(initial, 40), MAX_DELAY);
+    }
+}
@@ -0,0 +1,342 @@
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use uuid::Uuid;
+
+/// When a scheduled command runs. Times are local wall-clock times.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schedule {
+    /// Once, at a fixed time (`at 14:30` or `at 2026-01-31 09:00`)
+    At(NaiveDateTime),
+    /// Once at the next occurrence of a time of day (`at 14:30`)
+    DailyOnce(NaiveTime),
+    /// Repeatedly with a fixed interval (`every 30s`, `every 5m`, `every 2h`)
+    Every(Duration),
+    /// Five-field cron expression (`*/15 9-17 * * 1-5`)
+    Cron(CronExpr),
+}
+
+impl Schedule {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+
+        if let Some(rest) = spec.strip_prefix("at ") {
+            let rest = rest.trim();
+            if let Ok(time) = NaiveTime::parse_from_str(rest, "%H:%M") {
+                return Ok(Schedule::DailyOnce(time));
+            }
+            let at = NaiveDateTime::parse_from_str(rest, "%Y-%m-%d %H:%M")
+                .with_context(|| format!("Expected HH:MM or YYYY-MM-DD HH:MM, got '{}'", rest))?;
+            return Ok(Schedule::At(at));
+        }
+
+        if let Some(rest) = spec.strip_prefix("every ") {
+            return parse_interval(rest.trim()).map(Schedule::Every);
+        }
+
+        CronExpr::parse(spec).map(Schedule::Cron)
+    }
+
+    /// Whether the command stays scheduled after running
+    pub fn is_recurring(&self) -> bool {
+        matches!(self, Schedule::Every(_) | Schedule::Cron(_))
+    }
+
+    /// First run strictly after `after`, if any
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self {
+            Schedule::At(at) => (*at > after).then_some(*at),
+            Schedule::DailyOnce(time) => {
+                let today = after.date().and_time(*time);
+                Some(if today > after { today } else { today + Duration::days(1) })
+            }
+            Schedule::Every(interval) => Some(after + *interval),
+            Schedule::Cron(cron) => cron.next_after(after),
+        }
+    }
+}
+
+fn parse_interval(text: &str) -> Result<Duration> {
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .context("Interval needs a unit: s, m, h or d")?;
+    let (number, unit) = text.split_at(split);
+    let amount: i64 = number.parse().context("Interval must start with a number")?;
+    if amount == 0 {
+        bail!("Interval must be greater than zero");
+    }
+
+    Ok(match unit.trim() {
+        "s" | "sec" | "secs" => Duration::seconds(amount),
+        "m" | "min" | "mins" => Duration::minutes(amount),
+        "h" | "hour" | "hours" => Duration::hours(amount),
+        "d" | "day" | "days" => Duration::days(amount),
+        other => bail!("Unknown interval unit '{}'", other),
+    })
+}
+
+/// Standard five-field cron expression: minute hour day-of-month month day-of-week.
+/// Each field accepts `*`, numbers, ranges (`1-5`), steps (`*/10`, `0-30/5`) and lists.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronExpr {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days_of_month: Vec<bool>,
+    months: Vec<bool>,
+    days_of_week: Vec<bool>,
+    // Cron ORs day-of-month and day-of-week when both are restricted
+    dom_restricted: bool,
+    dow_restricted: bool,
+}
+
+impl CronExpr {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            bail!("Expected 'at ...', 'every ...' or a five-field cron expression");
+        }
+
+        let mut days_of_week = parse_field(fields[4], 0, 7).context("Invalid day-of-week")?;
+        // 7 is an alias for Sunday
+        if days_of_week[7] {
+            days_of_week[0] = true;
+        }
+
+        Ok(Self {
+            minutes: parse_field(fields[0], 0, 59).context("Invalid minute")?,
+            hours: parse_field(fields[1], 0, 23).context("Invalid hour")?,
+            days_of_month: parse_field(fields[2], 1, 31).context("Invalid day-of-month")?,
+            months: parse_field(fields[3], 1, 12).context("Invalid month")?,
+            days_of_week,
+            dom_restricted: fields[2] != "*",
+            dow_restricted: fields[4] != "*",
+        })
+    }
+
+    fn matches(&self, time: NaiveDateTime) -> bool {
+        let dom = self.days_of_month[time.day() as usize];
+        let dow = self.days_of_week[time.weekday().num_days_from_sunday() as usize];
+        let day = match (self.dom_restricted, self.dow_restricted) {
+            (true, true) => dom || dow,
+            _ => dom && dow,
+        };
+
+        day && self.minutes[time.minute() as usize]
+            && self.hours[time.hour() as usize]
+            && self.months[time.month() as usize]
+    }
+
+    /// Next matching minute after `after`, searching up to a few years ahead
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut time = after.date().and_hms_opt(after.hour(), after.minute(), 0)? + Duration::minutes(1);
+        let limit = after + Duration::days(366 * 4);
+
+        while time <= limit {
+            if !self.months[time.month() as usize] {
+                // Skip to the first minute of the next month
+                let (year, month) = if time.month() == 12 {
+                    (time.year() + 1, 1)
+                } else {
+                    (time.year(), time.month() + 1)
+                };
+                time = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if self.matches(time) {
+                return Some(time);
+            }
+            time += Duration::minutes(1);
+        }
+        None
+    }
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<Vec<bool>> {
+    let mut allowed = vec![false; max as usize + 1];
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().context("Invalid step")?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            bail!("Step must be greater than zero");
+        }
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (a.parse()?, b.parse()?)
+        } else {
+            let value: u32 = range.parse()?;
+            // `5/10` means starting at 5 through the maximum
+            if part.contains('/') { (value, max) } else { (value, value) }
+        };
+
+        if start < min || end > max || start > end {
+            bail!("'{}' is out of range {}-{}", part, min, max);
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            allowed[value as usize] = true;
+        }
+    }
+
+    Ok(allowed)
+}
+
+/// A command waiting to run on a schedule
+#[derive(Debug, Clone)]
+pub struct ScheduledCommand {
+    pub id: Uuid,
+    pub command: String,
+    /// The schedule as entered, for display
+    pub spec: String,
+    pub schedule: Schedule,
+    pub next_run: NaiveDateTime,
+}
+
+/// Commands scheduled within a session
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    jobs: Vec<ScheduledCommand>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, command: &str, spec: &str, now: NaiveDateTime) -> Result<Uuid> {
+        let command = command.trim();
+        if command.is_empty() {
+            bail!("Command is empty");
+        }
+
+        let schedule = Schedule::parse(spec)?;
+        let next_run = schedule
+            .next_after(now)
+            .context("Schedule never runs (is the time in the past?)")?;
+
+        let id = Uuid::new_v4();
+        self.jobs.push(ScheduledCommand {
+            id,
+            command: command.to_string(),
+            spec: spec.trim().to_string(),
+            schedule,
+            next_run,
+        });
+        Ok(id)
+    }
+
+    pub fn remove(&mut self, id: &Uuid) {
+        self.jobs.retain(|job| &job.id != id);
+    }
+
+    pub fn clear(&mut self) {
+        self.jobs.clear();
+    }
+
+    /// Scheduled commands ordered by next run
+    pub fn upcoming(&self) -> Vec<&ScheduledCommand> {
+        let mut jobs: Vec<&ScheduledCommand> = self.jobs.iter().collect();
+        jobs.sort_by_key(|job| job.next_run);
+        jobs
+    }
+
+    /// Time until the earliest scheduled run
+    pub fn next_due_in(&self, now: NaiveDateTime) -> Option<std::time::Duration> {
+        self.jobs
+            .iter()
+            .map(|job| (job.next_run - now).to_std().unwrap_or_default())
+            .min()
+    }
+
+    /// Commands due at `now`. Recurring jobs are advanced to their next run
+    /// (missed runs are not replayed); one-off jobs are removed.
+    pub fn take_due(&mut self, now: NaiveDateTime) -> Vec<String> {
+        let mut due = Vec::new();
+
+        self.jobs.retain_mut(|job| {
+            if job.next_run > now {
+                return true;
+            }
+            due.push(job.command.clone());
+            match job.schedule.next_after(now).filter(|_| job.schedule.is_recurring()) {
+                Some(next) => {
+                    job.next_run = next;
+                    true
+                }
+                None => false,
+            }
+        });
+
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_parse_schedules() {
+        assert_eq!(Schedule::parse("every 5m").unwrap(), Schedule::Every(Duration::minutes(5)));
+        assert_eq!(
+            Schedule::parse("at 2026-03-01 09:30").unwrap(),
+            Schedule::At(time("2026-03-01 09:30"))
+        );
+        assert!(matches!(Schedule::parse("at 23:15").unwrap(), Schedule::DailyOnce(_)));
+        assert!(Schedule::parse("every 0s").is_err());
+        assert!(Schedule::parse("every 5 fortnights").is_err());
+        assert!(Schedule::parse("61 * * * *").is_err());
+        assert!(Schedule::parse("hourly").is_err());
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        let weekday_quarters = CronExpr::parse("*/15 9-17 * * 1-5").unwrap();
+        // Friday 17:50 -> Monday 09:00
+        assert_eq!(
+            weekday_quarters.next_after(time("2026-01-02 17:50")),
+            Some(time("2026-01-05 09:00"))
+        );
+        assert_eq!(
+            weekday_quarters.next_after(time("2026-01-05 09:00")),
+            Some(time("2026-01-05 09:15"))
+        );
+
+        let new_year = CronExpr::parse("0 0 1 1 *").unwrap();
+        assert_eq!(new_year.next_after(time("2026-06-15 12:00")), Some(time("2027-01-01 00:00")));
+    }
+
+    #[test]
+    fn test_daily_once_rolls_over() {
+        let schedule = Schedule::parse("at 08:00").unwrap();
+        assert_eq!(schedule.next_after(time("2026-01-01 07:59")), Some(time("2026-01-01 08:00")));
+        assert_eq!(schedule.next_after(time("2026-01-01 08:00")), Some(time("2026-01-02 08:00")));
+    }
+
+    #[test]
+    fn test_scheduler_take_due() {
+        let now = time("2026-01-01 12:00");
+        let mut scheduler = Scheduler::new();
+        scheduler.add("uptime", "every 10m", now).unwrap();
+        scheduler.add("backup.sh", "at 2026-01-01 12:05", now).unwrap();
+        assert!(scheduler.add("late", "at 2025-12-31 12:00", now).is_err());
+
+        assert_eq!(scheduler.upcoming()[0].command, "backup.sh");
+        assert!(scheduler.take_due(time("2026-01-01 12:04")).is_empty());
+
+        let due = scheduler.take_due(time("2026-01-01 12:30"));
+        assert_eq!(due, vec!["uptime".to_string(), "backup.sh".to_string()]);
+
+        // The one-off job is gone; the recurring one is rescheduled from now
+        let upcoming = scheduler.upcoming();
+        assert_eq!(upcoming.len(), 1);
+        assert_eq!(upcoming[0].next_run, time("2026-01-01 12:40"));
+    }
+}
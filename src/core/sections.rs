@@ -0,0 +1,170 @@
+use super::OutputBuffer;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// A phase of a build tool's output, e.g. one crate being compiled or one
+/// npm lifecycle script, shown as a collapsible part of the block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputSection {
+    pub title: String,
+    /// Line index of the section's heading
+    pub start: usize,
+    /// Line after the last one in the section; `None` while it runs to the
+    /// end of the output
+    pub end: Option<usize>,
+}
+
+/// What a line means for the sections around it
+#[derive(Debug, PartialEq)]
+enum Boundary {
+    /// Heading of a new section
+    Start(String),
+    /// Continues the section with this title if it is the open one, e.g.
+    /// another test of the same pytest file; otherwise starts it
+    Group(String),
+    /// The tool's summary after its last section
+    End,
+}
+
+fn boundary(line: &str) -> Option<Boundary> {
+    static CARGO: OnceLock<Regex> = OnceLock::new();
+    static CARGO_END: OnceLock<Regex> = OnceLock::new();
+    static NPM: OnceLock<Regex> = OnceLock::new();
+    static PYTEST_RULE: OnceLock<Regex> = OnceLock::new();
+    static PYTEST_FILE: OnceLock<Regex> = OnceLock::new();
+
+    let cargo = CARGO.get_or_init(|| {
+        Regex::new(r"^\s+(Compiling|Checking|Documenting|Running|Doc-tests) \S").expect("valid cargo pattern")
+    });
+    let cargo_end = CARGO_END.get_or_init(|| Regex::new(r"^\s+Finished [`\w]").expect("valid cargo pattern"));
+    // npm announces each lifecycle script as `> package@version script`
+    let npm = NPM.get_or_init(|| Regex::new(r"^> (\S+@\S+ \S+)\s*$").expect("valid npm pattern"));
+    let pytest_rule = PYTEST_RULE.get_or_init(|| {
+        Regex::new(r"^([=_]{3,}) (.+?) [=_]{3,}$").expect("valid pytest pattern")
+    });
+    let pytest_file = PYTEST_FILE.get_or_init(|| Regex::new(r"^(\S+\.py)(::\S+)? ").expect("valid pytest pattern"));
+
+    if cargo.is_match(line) {
+        return Some(Boundary::Start(line.trim().to_string()));
+    }
+    if cargo_end.is_match(line) {
+        return Some(Boundary::End);
+    }
+    if let Some(caps) = npm.captures(line) {
+        return Some(Boundary::Start(caps[1].to_string()));
+    }
+    if let Some(caps) = pytest_rule.captures(line) {
+        return Some(Boundary::Start(caps[2].to_string()));
+    }
+    pytest_file
+        .captures(line)
+        .map(|caps| Boundary::Group(caps[1].to_string()))
+}
+
+/// Sections of one block's output, kept up to date as the output grows.
+/// Only complete lines are scanned; the line still being written belongs to
+/// whatever section is open.
+#[derive(Debug, Clone, Default)]
+pub struct SectionScan {
+    sections: Vec<OutputSection>,
+    complete_lines: usize,
+    scanned_len: usize,
+}
+
+impl SectionScan {
+    pub fn sections(&self) -> &[OutputSection] {
+        &self.sections
+    }
+
+    /// Scan the lines completed in `output` since the last update
+    pub fn update(&mut self, output: &OutputBuffer) -> &[OutputSection] {
+        if output.len() < self.scanned_len {
+            *self = Self::default();
+        }
+        if output.len() == self.scanned_len {
+            return &self.sections;
+        }
+
+        for (index, line) in output.lines().enumerate().skip(self.complete_lines) {
+            if !line.ends_with('\n') {
+                break;
+            }
+            self.complete_lines = index + 1;
+            let open = self.sections.last_mut().filter(|s| s.end.is_none());
+            match boundary(line.trim_end_matches(['\n', '\r'])) {
+                Some(Boundary::Group(title)) if open.as_ref().is_some_and(|s| s.title == title) => {}
+                Some(Boundary::Start(title)) | Some(Boundary::Group(title)) => {
+                    if let Some(open) = open {
+                        open.end = Some(index);
+                    }
+                    self.sections.push(OutputSection { title, start: index, end: None });
+                }
+                Some(Boundary::End) => {
+                    if let Some(open) = open {
+                        open.end = Some(index);
+                    }
+                }
+                None => {}
+            }
+        }
+        self.scanned_len = output.len();
+        &self.sections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(text: &str) -> Vec<(String, usize, Option<usize>)> {
+        let mut scan = SectionScan::default();
+        scan.update(&OutputBuffer::from(text))
+            .iter()
+            .map(|s| (s.title.clone(), s.start, s.end))
+            .collect()
+    }
+
+    #[test]
+    fn test_cargo_sections() {
+        let output = "   Compiling libc v0.2.150\n   Compiling app v0.1.0 (/work/app)\nwarning: unused variable: `x`\n --> src/main.rs:2:9\n    Finished `dev` profile in 3.1s\n";
+        assert_eq!(
+            scan(output),
+            vec![
+                ("Compiling libc v0.2.150".to_string(), 0, Some(1)),
+                ("Compiling app v0.1.0 (/work/app)".to_string(), 1, Some(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_npm_and_pytest_sections() {
+        let output = "\n> app@1.0.0 prebuild\n> rimraf dist\n\n> app@1.0.0 build\n> tsc\n";
+        assert_eq!(
+            scan(output),
+            vec![
+                ("app@1.0.0 prebuild".to_string(), 1, Some(4)),
+                ("app@1.0.0 build".to_string(), 4, None),
+            ]
+        );
+
+        let output = "tests/test_a.py::test_one PASSED\ntests/test_a.py::test_two FAILED\ntests/test_b.py::test_three PASSED\n=== FAILURES ===\n";
+        let titles: Vec<_> = scan(output).into_iter().map(|(title, start, _)| (title, start)).collect();
+        assert_eq!(
+            titles,
+            vec![
+                ("tests/test_a.py".to_string(), 0),
+                ("tests/test_b.py".to_string(), 2),
+                ("FAILURES".to_string(), 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_partial_line_waits() {
+        let mut output = OutputBuffer::from("   Compiling a v1\n   Compil");
+        let mut scan = SectionScan::default();
+        assert_eq!(scan.update(&output).len(), 1);
+        output.push_str("ing b v1\n");
+        assert_eq!(scan.update(&output).len(), 2);
+    }
+}
@@ -1,4 +1,4 @@
-use super::Block;
+use super::{Block, Trigger};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -14,6 +14,84 @@ pub struct Session {
     pub blocks: Vec<Block>,
     pub environment: HashMap<String, String>,
     pub working_directory: PathBuf,
+    /// Folder/project the session is grouped under, if any
+    #[serde(default)]
+    pub folder: Option<String>,
+    /// Git repository root for per-project sessions
+    #[serde(default)]
+    pub project_root: Option<PathBuf>,
+    /// Commands run as blocks whenever the session is opened
+    #[serde(default)]
+    pub startup_commands: Vec<String>,
+    /// .env file whose variables are loaded into `environment`
+    #[serde(default)]
+    pub env_file: Option<PathBuf>,
+    /// Keys that came from `env_file`, replaced when it is re-applied
+    #[serde(default)]
+    pub env_file_keys: Vec<String>,
+    /// Regex triggers matched against command output
+    #[serde(default)]
+    pub triggers: Vec<Trigger>,
+    /// What the session is for, shown under its name in the session list
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Color tag for telling sessions apart at a glance
+    #[serde(default)]
+    pub color: Option<SessionColor>,
+}
+
+/// Color tag shown next to a session's name in the session list and status bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionColor {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+    Gray,
+}
+
+impl SessionColor {
+    pub const ALL: [SessionColor; 7] = [
+        SessionColor::Red,
+        SessionColor::Orange,
+        SessionColor::Yellow,
+        SessionColor::Green,
+        SessionColor::Blue,
+        SessionColor::Purple,
+        SessionColor::Gray,
+    ];
+
+    /// Name stored in the database
+    pub fn name(self) -> &'static str {
+        match self {
+            SessionColor::Red => "red",
+            SessionColor::Orange => "orange",
+            SessionColor::Yellow => "yellow",
+            SessionColor::Green => "green",
+            SessionColor::Blue => "blue",
+            SessionColor::Purple => "purple",
+            SessionColor::Gray => "gray",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|color| color.name() == name)
+    }
+
+    pub fn rgb(self) -> [u8; 3] {
+        match self {
+            SessionColor::Red => [220, 70, 80],
+            SessionColor::Orange => [240, 140, 50],
+            SessionColor::Yellow => [230, 200, 60],
+            SessionColor::Green => [90, 190, 100],
+            SessionColor::Blue => [80, 150, 230],
+            SessionColor::Purple => [170, 110, 220],
+            SessionColor::Gray => [140, 140, 140],
+        }
+    }
 }
 
 impl Session {
@@ -26,6 +104,14 @@ impl Session {
             blocks: Vec::new(),
             environment: HashMap::new(),
             working_directory,
+            folder: None,
+            project_root: None,
+            startup_commands: Vec::new(),
+            env_file: None,
+            env_file_keys: Vec::new(),
+            triggers: Vec::new(),
+            description: None,
+            color: None,
         }
     }
 
@@ -33,4 +119,46 @@ impl Session {
         self.blocks.push(block);
         self.updated_at = Utc::now();
     }
+
+    /// Load variables from a .env file, dropping any keys a previous load added
+    pub fn apply_env_file(&mut self, path: PathBuf, vars: Vec<(String, String)>) {
+        self.clear_env_file();
+        self.env_file_keys = vars.iter().map(|(key, _)| key.clone()).collect();
+        self.environment.extend(vars);
+        self.env_file = Some(path);
+    }
+
+    /// Stop using the .env file and remove the variables it provided
+    pub fn clear_env_file(&mut self) {
+        for key in self.env_file_keys.drain(..) {
+            self.environment.remove(&key);
+        }
+        self.env_file = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reapply_env_file_drops_removed_keys() {
+        let mut session = Session::new("test".to_string(), PathBuf::from("/tmp"));
+        session.environment.insert("MANUAL".to_string(), "1".to_string());
+
+        let path = PathBuf::from("/tmp/.env");
+        session.apply_env_file(path.clone(), vec![
+            ("A".to_string(), "1".to_string()),
+            ("B".to_string(), "2".to_string()),
+        ]);
+        session.apply_env_file(path.clone(), vec![("A".to_string(), "3".to_string())]);
+
+        assert_eq!(session.environment.get("A").map(String::as_str), Some("3"));
+        assert!(!session.environment.contains_key("B"));
+        assert_eq!(session.env_file.as_ref(), Some(&path));
+
+        session.clear_env_file();
+        assert!(!session.environment.contains_key("A"));
+        assert_eq!(session.environment.get("MANUAL").map(String::as_str), Some("1"));
+    }
 }
@@ -1,31 +1,70 @@
-use super::{Block, BlockState, Database, Session};
+use super::frequency::normalize_command;
+use super::{
+    AiAuditEntry, AiProvenance, Block, BlockState, CommandUsage, Database, DirectoryBookmark, Session, SessionColor, Trigger,
+};
+use super::crypto::FieldCipher;
+use super::database::{compress_output, decompress_output};
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqliteRow;
 use sqlx::Row;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use uuid::Uuid;
 
+/// Columns read by `block_from_row`
+const BLOCK_COLUMNS: &str = "id, timestamp, command, output, exit_code, state, working_directory, \
+     environment, started_at, completed_at, duration_ms, is_collapsed, \
+     is_pinned, original_input, is_startup, output_zstd, sealed, tags, hook_results, ai_provenance, is_divider, is_calculation, \
+     in_ai_context";
+
 #[derive(Clone)]
 pub struct SessionManager {
     db: Arc<Database>,
+    /// Key for encrypted block contents, if one is available
+    cipher: Option<Arc<FieldCipher>>,
+    /// Encrypt block contents on save (requires `cipher`)
+    encrypt: bool,
 }
 
 impl SessionManager {
     pub async fn new(db: Database) -> Result<Self> {
-        Ok(Self { db: Arc::new(db) })
+        Ok(Self {
+            db: Arc::new(db),
+            cipher: None,
+            encrypt: false,
+        })
+    }
+
+    /// Use `cipher` to read encrypted blocks, and to encrypt saved blocks if
+    /// `encrypt` is set
+    pub fn with_cipher(mut self, cipher: FieldCipher, encrypt: bool) -> Self {
+        self.cipher = Some(Arc::new(cipher));
+        self.encrypt = encrypt;
+        self
     }
 
     /// Create a new session and save it to the database
     pub async fn create_session(&self, session: &Session) -> Result<()> {
+        self.insert_session(self.db.pool(), session).await?;
+
+        tracing::info!("Created session: {} ({})", session.name, session.id);
+        Ok(())
+    }
+
+    async fn insert_session<'e>(&self, executor: impl sqlx::SqliteExecutor<'e>, session: &Session) -> Result<()> {
         let env_json = serde_json::to_string(&session.environment)?;
+        let startup_json = serde_json::to_string(&session.startup_commands)?;
         
         sqlx::query(
             r#"
-            INSERT INTO sessions (id, name, created_at, updated_at, working_directory, environment, is_active)
-            VALUES (?, ?, ?, ?, ?, ?, 1)
+            INSERT INTO sessions (id, name, created_at, updated_at, working_directory, environment, folder,
+                                  project_root, startup_commands, env_file, env_file_keys, triggers, description,
+                                  color, is_active)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 1)
             "#
         )
         .bind(session.id.to_string())
@@ -34,18 +73,29 @@ impl SessionManager {
         .bind(session.updated_at.to_rfc3339())
         .bind(session.working_directory.to_string_lossy().to_string())
         .bind(env_json)
-        .execute(self.db.pool())
+        .bind(&session.folder)
+        .bind(session.project_root.as_ref().map(|p| p.to_string_lossy().to_string()))
+        .bind(startup_json)
+        .bind(session.env_file.as_ref().map(|p| p.to_string_lossy().to_string()))
+        .bind(serde_json::to_string(&session.env_file_keys)?)
+        .bind(serde_json::to_string(&session.triggers)?)
+        .bind(&session.description)
+        .bind(session.color.map(SessionColor::name))
+        .execute(executor)
         .await
         .context("Failed to create session")?;
 
-        tracing::info!("Created session: {} ({})", session.name, session.id);
         Ok(())
     }
 
     /// Load a session by ID
     pub async fn load_session(&self, session_id: &Uuid) -> Result<Session> {
         let row = sqlx::query(
-            "SELECT id, name, created_at, updated_at, working_directory, environment FROM sessions WHERE id = ?"
+            r#"
+            SELECT id, name, created_at, updated_at, working_directory, environment, folder, project_root,
+                   startup_commands, env_file, env_file_keys, triggers, description, color
+            FROM sessions WHERE id = ?
+            "#
         )
         .bind(session_id.to_string())
         .fetch_one(self.db.pool())
@@ -58,10 +108,20 @@ impl SessionManager {
         let updated_at: String = row.get("updated_at");
         let working_directory: String = row.get("working_directory");
         let environment_json: Option<String> = row.get("environment");
+        let startup_json: Option<String> = row.get("startup_commands");
 
         let environment: HashMap<String, String> = environment_json
             .and_then(|json| serde_json::from_str(&json).ok())
             .unwrap_or_default();
+        let startup_commands: Vec<String> = startup_json
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        let env_file_keys: Vec<String> = row.get::<Option<String>, _>("env_file_keys")
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        let triggers: Vec<Trigger> = row.get::<Option<String>, _>("triggers")
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
 
         let mut session = Session {
             id: Uuid::parse_str(&id)?,
@@ -71,6 +131,14 @@ impl SessionManager {
             working_directory: PathBuf::from(working_directory),
             environment,
             blocks: Vec::new(),
+            folder: row.get("folder"),
+            project_root: row.get::<Option<String>, _>("project_root").map(PathBuf::from),
+            startup_commands,
+            env_file: row.get::<Option<String>, _>("env_file").map(PathBuf::from),
+            env_file_keys,
+            triggers,
+            description: row.get("description"),
+            color: row.get::<Option<String>, _>("color").as_deref().and_then(SessionColor::from_name),
         };
 
         // Load blocks for this session
@@ -79,10 +147,31 @@ impl SessionManager {
         Ok(session)
     }
 
+    /// Most recently used session associated with a repository root
+    pub async fn find_project_session(&self, project_root: &Path) -> Result<Option<Session>> {
+        let row = sqlx::query(
+            "SELECT id FROM sessions WHERE project_root = ? ORDER BY updated_at DESC LIMIT 1"
+        )
+        .bind(project_root.to_string_lossy().to_string())
+        .fetch_optional(self.db.pool())
+        .await?;
+
+        match row {
+            Some(row) => {
+                let id = Uuid::parse_str(&row.get::<String, _>("id"))?;
+                Ok(Some(self.load_session(&id).await?))
+            }
+            None => Ok(None),
+        }
+    }
+
     /// Get all sessions (without loading blocks)
     pub async fn list_sessions(&self) -> Result<Vec<SessionInfo>> {
         let rows = sqlx::query(
-            "SELECT id, name, created_at, updated_at, is_active FROM sessions ORDER BY updated_at DESC"
+            r#"
+            SELECT id, name, created_at, updated_at, is_active, folder, working_directory, description, color
+            FROM sessions ORDER BY updated_at DESC
+            "#
         )
         .fetch_all(self.db.pool())
         .await?;
@@ -95,6 +184,10 @@ impl SessionManager {
                 created_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("created_at"))?.with_timezone(&Utc),
                 updated_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("updated_at"))?.with_timezone(&Utc),
                 is_active: row.get("is_active"),
+                folder: row.get("folder"),
+                working_directory: PathBuf::from(row.get::<String, _>("working_directory")),
+                description: row.get("description"),
+                color: row.get::<Option<String>, _>("color").as_deref().and_then(SessionColor::from_name),
             });
         }
 
@@ -103,21 +196,69 @@ impl SessionManager {
 
     /// Save a block to the database
     pub async fn save_block(&self, session_id: &Uuid, block: &Block, order: i32) -> Result<()> {
+        self.write_block(self.db.pool(), session_id, block, order).await
+    }
+
+    /// Save a session's blocks, in list order, and bump its timestamp in a
+    /// single transaction: one commit instead of one per block, and a crash
+    /// mid-save leaves the previous copy intact
+    pub async fn save_blocks(&self, session_id: &Uuid, blocks: &[Block]) -> Result<()> {
+        let mut tx = self.db.pool().begin().await.context("Failed to start transaction")?;
+        for (order, block) in blocks.iter().enumerate() {
+            self.write_block(&mut *tx, session_id, block, order as i32).await?;
+        }
+        sqlx::query("UPDATE sessions SET updated_at = ? WHERE id = ?")
+            .bind(Utc::now().to_rfc3339())
+            .bind(session_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await.context("Failed to commit blocks")?;
+        Ok(())
+    }
+
+    /// Add a block after a session's existing ones and bump its timestamp,
+    /// for sessions that aren't open (their blocks aren't in memory to save)
+    pub async fn append_block(&self, session_id: &Uuid, block: &Block) -> Result<()> {
+        let mut tx = self.db.pool().begin().await.context("Failed to start transaction")?;
+        let order: i32 = sqlx::query_scalar("SELECT COALESCE(MAX(block_order) + 1, 0) FROM blocks WHERE session_id = ?")
+            .bind(session_id.to_string())
+            .fetch_one(&mut *tx)
+            .await?;
+        self.write_block(&mut *tx, session_id, block, order).await?;
+        sqlx::query("UPDATE sessions SET updated_at = ? WHERE id = ?")
+            .bind(Utc::now().to_rfc3339())
+            .bind(session_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await.context("Failed to commit block")?;
+        Ok(())
+    }
+
+    async fn write_block<'e>(
+        &self,
+        executor: impl sqlx::SqliteExecutor<'e>,
+        session_id: &Uuid,
+        block: &Block,
+        order: i32,
+    ) -> Result<()> {
         let env_json = serde_json::to_string(&block.metadata.environment)?;
-        
+        let content = StoredContent::new(block, self.sealing_cipher())?;
+
         sqlx::query(
             r#"
             INSERT OR REPLACE INTO blocks 
             (id, session_id, timestamp, command, output, exit_code, state, working_directory, 
-             environment, started_at, completed_at, duration_ms, is_collapsed, block_order)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             environment, started_at, completed_at, duration_ms, is_collapsed, block_order,
+             is_pinned, original_input, is_startup, output_zstd, sealed, tags, hook_results, ai_provenance, is_divider,
+             is_calculation, in_ai_context)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#
         )
         .bind(block.id.to_string())
         .bind(session_id.to_string())
         .bind(block.timestamp.to_rfc3339())
-        .bind(&block.command)
-        .bind(&block.output)
+        .bind(content.command)
+        .bind(content.output)
         .bind(block.exit_code)
         .bind(format!("{:?}", block.state))
         .bind(block.metadata.working_directory.to_string_lossy().to_string())
@@ -127,7 +268,18 @@ impl SessionManager {
         .bind(block.metadata.duration.map(|d| d.as_millis() as i64))
         .bind(block.is_collapsed)
         .bind(order)
-        .execute(self.db.pool())
+        .bind(block.is_pinned)
+        .bind(content.original_input)
+        .bind(block.is_startup)
+        .bind(content.output_zstd)
+        .bind(content.sealed)
+        .bind(serde_json::to_string(&block.tags)?)
+        .bind(serde_json::to_string(&block.metadata.hooks)?)
+        .bind(content.ai_provenance)
+        .bind(block.is_divider)
+        .bind(block.is_calculation)
+        .bind(block.in_ai_context)
+        .execute(executor)
         .await
         .context("Failed to save block")?;
 
@@ -136,65 +288,210 @@ impl SessionManager {
 
     /// Load all blocks for a session
     async fn load_blocks(&self, session_id: &Uuid) -> Result<Vec<Block>> {
-        let rows = sqlx::query(
+        let rows = sqlx::query(&format!(
             r#"
-            SELECT id, timestamp, command, output, exit_code, state, working_directory,
-                   environment, started_at, completed_at, duration_ms, is_collapsed
+            SELECT {BLOCK_COLUMNS}
             FROM blocks
             WHERE session_id = ?
             ORDER BY block_order ASC
             "#
-        )
+        ))
         .bind(session_id.to_string())
         .fetch_all(self.db.pool())
         .await?;
 
-        let mut blocks = Vec::new();
-        for row in rows {
-            let id: String = row.get("id");
-            let timestamp: String = row.get("timestamp");
-            let working_directory: String = row.get("working_directory");
-            let environment_json: Option<String> = row.get("environment");
-            let state_str: String = row.get("state");
+        rows.iter().map(|row| block_from_row(row, self.cipher.as_deref())).collect()
+    }
 
-            let environment: HashMap<String, String> = environment_json
-                .and_then(|json| serde_json::from_str(&json).ok())
-                .unwrap_or_default();
-
-            let started_at: Option<String> = row.get("started_at");
-            let completed_at: Option<String> = row.get("completed_at");
-            let duration_ms: Option<i64> = row.get("duration_ms");
-
-            let state = match state_str.as_str() {
-                "Editing" => BlockState::Editing,
-                "Running" => BlockState::Running,
-                "Completed" => BlockState::Completed,
-                "Failed" => BlockState::Failed,
-                "Cancelled" => BlockState::Cancelled,
-                _ => BlockState::Completed,
-            };
+    /// Delete blocks that were removed from a session, which saving alone
+    /// would leave behind
+    pub async fn delete_blocks(&self, block_ids: &[Uuid]) -> Result<()> {
+        let mut tx = self.db.pool().begin().await.context("Failed to start transaction")?;
+        for id in block_ids {
+            uncount_commands(&mut tx, "id = ?", &id.to_string()).await?;
+            sqlx::query("DELETE FROM blocks WHERE id = ?")
+                .bind(id.to_string())
+                .execute(&mut *tx)
+                .await
+                .context("Failed to delete block")?;
+        }
+        tx.commit().await.context("Failed to commit block deletion")?;
+        Ok(())
+    }
 
-            blocks.push(Block {
-                id: Uuid::parse_str(&id)?,
-                timestamp: DateTime::parse_from_rfc3339(&timestamp)?.with_timezone(&Utc),
-                command: row.get("command"),
-                output: row.get("output"),
-                exit_code: row.get("exit_code"),
-                state,
-                metadata: super::BlockMetadata {
-                    duration: duration_ms.map(|ms| Duration::from_millis(ms as u64)),
-                    working_directory: PathBuf::from(working_directory),
-                    environment,
-                    started_at: started_at.and_then(|s| DateTime::parse_from_rfc3339(&s).ok().map(|dt| dt.with_timezone(&Utc))),
-                    completed_at: completed_at.and_then(|s| DateTime::parse_from_rfc3339(&s).ok().map(|dt| dt.with_timezone(&Utc))),
+    /// Whether saved blocks are encrypted
+    pub fn encrypts(&self) -> bool {
+        self.sealing_cipher().is_some()
+    }
+
+    fn sealing_cipher(&self) -> Option<&FieldCipher> {
+        self.cipher.as_deref().filter(|_| self.encrypt)
+    }
+
+    pub async fn has_encrypted_blocks(&self) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM blocks WHERE sealed IS NOT NULL")
+            .fetch_one(self.db.pool())
+            .await?;
+        Ok(count > 0)
+    }
+
+    /// Rewrite stored blocks to match the encryption setting: encrypt plaintext
+    /// blocks when it is on, decrypt them when it is off. Turning it on also
+    /// empties the command frequency table, which isn't encrypted. The
+    /// database is then compacted so no copies remain in free pages. Returns
+    /// the number of blocks rewritten.
+    pub async fn migrate_encryption(&self) -> Result<usize> {
+        let Some(cipher) = self.cipher.as_deref() else {
+            return Ok(0);
+        };
+        let condition = if self.encrypt { "sealed IS NULL" } else { "sealed IS NOT NULL" };
+        let rows = sqlx::query(&format!("SELECT {BLOCK_COLUMNS} FROM blocks WHERE {condition}"))
+            .fetch_all(self.db.pool())
+            .await
+            .context("Failed to read blocks for encryption")?;
+
+        for row in &rows {
+            let block = block_from_row(row, Some(cipher))?;
+            let content = StoredContent::new(&block, self.sealing_cipher())?;
+            sqlx::query(
+                "UPDATE blocks SET command = ?, output = ?, output_zstd = ?, original_input = ?, sealed = ?, \
+                 ai_provenance = ? WHERE id = ?"
+            )
+            .bind(content.command)
+            .bind(content.output)
+            .bind(content.output_zstd)
+            .bind(content.original_input)
+            .bind(content.sealed)
+            .bind(content.ai_provenance)
+            .bind(block.id.to_string())
+            .execute(self.db.pool())
+            .await
+            .context("Failed to rewrite block")?;
+        }
+
+        let forgotten = if self.encrypt {
+            sqlx::query("DELETE FROM command_frequency")
+                .execute(self.db.pool())
+                .await
+                .context("Failed to clear command frequency")?
+                .rows_affected()
+        } else {
+            0
+        };
+
+        if !rows.is_empty() || forgotten > 0 {
+            self.db.vacuum().await?;
+            tracing::info!(
+                "{} {} stored blocks",
+                if self.encrypt { "Encrypted" } else { "Decrypted" },
+                rows.len()
+            );
+        }
+        Ok(rows.len())
+    }
+
+    /// Most recent commands across all sessions (oldest first)
+    pub async fn recent_commands(&self, limit: i64) -> Result<Vec<String>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT command, sealed FROM blocks
+            WHERE state != 'PendingApproval' AND NOT is_divider AND NOT is_calculation
+            ORDER BY timestamp DESC LIMIT ?
+            "#
+        )
+        .bind(limit)
+        .fetch_all(self.db.pool())
+        .await?;
+
+        let mut commands = Vec::new();
+        for row in rows.iter().rev() {
+            match row.get::<Option<Vec<u8>>, _>("sealed") {
+                Some(sealed) => match self.cipher.as_deref() {
+                    Some(cipher) => commands.push(BlockContent::open(&sealed, cipher)?.command),
+                    None => continue,
                 },
-                is_collapsed: row.get("is_collapsed"),
-                is_selected: false,
-                original_input: None, // Not stored in DB yet
-            });
+                None => commands.push(row.get("command")),
+            }
         }
+        Ok(commands)
+    }
 
-        Ok(blocks)
+    /// Count a run of `command` for ranking suggestions. With encrypted
+    /// history on, nothing is counted, since the table isn't encrypted.
+    pub async fn record_command_use(&self, command: &str) -> Result<()> {
+        let Some(command) = normalize_command(command).filter(|_| !self.encrypt) else {
+            return Ok(());
+        };
+        sqlx::query(
+            "INSERT INTO command_frequency (command, count, last_used) VALUES (?, 1, ?)
+             ON CONFLICT(command) DO UPDATE SET count = count + 1, last_used = excluded.last_used",
+        )
+        .bind(command)
+        .bind(Utc::now().to_rfc3339())
+        .execute(self.db.pool())
+        .await
+        .context("Failed to count command")?;
+        Ok(())
+    }
+
+    /// The most used commands, for ranking by frecency
+    pub async fn command_usage(&self, limit: i64) -> Result<Vec<CommandUsage>> {
+        let rows = sqlx::query("SELECT command, count, last_used FROM command_frequency ORDER BY count DESC LIMIT ?")
+            .bind(limit)
+            .fetch_all(self.db.pool())
+            .await?;
+        Ok(rows.iter().map(CommandUsage::from_row).collect())
+    }
+
+    /// Add a request to the AI audit log. With encrypted history on, prompt
+    /// and response text stay out of it, since it isn't encrypted.
+    pub async fn record_ai_request(&self, entry: &AiAuditEntry) -> Result<()> {
+        let (prompt, response) = if self.encrypt {
+            (None, None)
+        } else {
+            (entry.prompt.as_deref(), entry.response.as_deref())
+        };
+        sqlx::query(
+            "INSERT INTO ai_audit (id, timestamp, kind, provider, model, prompt_hash, prompt, response, error,
+             prompt_tokens, completion_tokens, latency_ms, executed_block_id)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(entry.id.to_string())
+        .bind(entry.timestamp.to_rfc3339())
+        .bind(entry.kind.as_str())
+        .bind(&entry.provider)
+        .bind(&entry.model)
+        .bind(&entry.prompt_hash)
+        .bind(prompt)
+        .bind(response)
+        .bind(&entry.error)
+        .bind(entry.prompt_tokens.map(|t| t as i64))
+        .bind(entry.completion_tokens.map(|t| t as i64))
+        .bind(entry.latency.as_millis() as i64)
+        .bind(entry.executed_block.map(|id| id.to_string()))
+        .execute(self.db.pool())
+        .await
+        .context("Failed to record AI request")?;
+        Ok(())
+    }
+
+    /// Note that the command generated by an audited request was run in `block_id`
+    pub async fn mark_ai_command_executed(&self, entry_id: &Uuid, block_id: &Uuid) -> Result<()> {
+        sqlx::query("UPDATE ai_audit SET executed_block_id = ? WHERE id = ?")
+            .bind(block_id.to_string())
+            .bind(entry_id.to_string())
+            .execute(self.db.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Most recent entries of the AI audit log, newest first
+    pub async fn ai_audit_log(&self, limit: i64) -> Result<Vec<AiAuditEntry>> {
+        let rows = sqlx::query("SELECT * FROM ai_audit ORDER BY timestamp DESC LIMIT ?")
+            .bind(limit)
+            .fetch_all(self.db.pool())
+            .await?;
+        Ok(rows.iter().map(AiAuditEntry::from_row).collect())
     }
 
     /// Update session's updated_at timestamp
@@ -238,16 +535,490 @@ impl SessionManager {
         }
     }
 
+    /// Move a session into a folder, or out of any folder with None
+    pub async fn set_session_folder(&self, session_id: &Uuid, folder: Option<&str>) -> Result<()> {
+        let folder = folder.map(str::trim).filter(|f| !f.is_empty());
+
+        sqlx::query("UPDATE sessions SET folder = ? WHERE id = ?")
+            .bind(folder)
+            .bind(session_id.to_string())
+            .execute(self.db.pool())
+            .await
+            .context("Failed to set session folder")?;
+
+        Ok(())
+    }
+
+    /// Replace the commands run when the session is opened
+    pub async fn set_startup_commands(&self, session_id: &Uuid, commands: &[String]) -> Result<()> {
+        sqlx::query("UPDATE sessions SET startup_commands = ? WHERE id = ?")
+            .bind(serde_json::to_string(commands)?)
+            .bind(session_id.to_string())
+            .execute(self.db.pool())
+            .await
+            .context("Failed to save startup commands")?;
+
+        Ok(())
+    }
+
+    /// Record the directory the session's shell is in
+    pub async fn set_working_directory(&self, session_id: &Uuid, path: &Path) -> Result<()> {
+        sqlx::query("UPDATE sessions SET working_directory = ? WHERE id = ?")
+            .bind(path.to_string_lossy().to_string())
+            .bind(session_id.to_string())
+            .execute(self.db.pool())
+            .await
+            .context("Failed to save working directory")?;
+
+        Ok(())
+    }
+
+    /// Count a visit to a directory, for quick-jump
+    pub async fn record_directory_visit(&self, path: &Path) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO directory_visits (path, visits, last_visit) VALUES (?, 1, ?)
+             ON CONFLICT(path) DO UPDATE SET visits = visits + 1, last_visit = excluded.last_visit",
+        )
+        .bind(path.to_string_lossy().to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(self.db.pool())
+        .await
+        .context("Failed to record directory visit")?;
+        Ok(())
+    }
+
+    /// Pin a directory to the top of quick-jump, or unpin it
+    pub async fn set_directory_pinned(&self, path: &Path, pinned: bool) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO directory_visits (path, visits, last_visit, pinned) VALUES (?, 0, ?, ?)
+             ON CONFLICT(path) DO UPDATE SET pinned = excluded.pinned",
+        )
+        .bind(path.to_string_lossy().to_string())
+        .bind(Utc::now().to_rfc3339())
+        .bind(pinned)
+        .execute(self.db.pool())
+        .await
+        .context("Failed to pin directory")?;
+        Ok(())
+    }
+
+    /// Drop a directory from quick-jump, e.g. after it was deleted
+    pub async fn forget_directory(&self, path: &Path) -> Result<()> {
+        sqlx::query("DELETE FROM directory_visits WHERE path = ?")
+            .bind(path.to_string_lossy().to_string())
+            .execute(self.db.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Pinned directories and the most recently visited others
+    pub async fn directory_bookmarks(&self, limit: i64) -> Result<Vec<DirectoryBookmark>> {
+        let rows = sqlx::query("SELECT * FROM directory_visits ORDER BY pinned DESC, last_visit DESC LIMIT ?")
+            .bind(limit)
+            .fetch_all(self.db.pool())
+            .await?;
+        Ok(rows.iter().map(DirectoryBookmark::from_row).collect())
+    }
+
+    /// Set the description and color tag; a blank description clears it
+    pub async fn set_session_details(
+        &self,
+        session_id: &Uuid,
+        description: Option<&str>,
+        color: Option<SessionColor>,
+    ) -> Result<()> {
+        let description = description.map(str::trim).filter(|d| !d.is_empty());
+        sqlx::query("UPDATE sessions SET description = ?, color = ? WHERE id = ?")
+            .bind(description)
+            .bind(color.map(SessionColor::name))
+            .bind(session_id.to_string())
+            .execute(self.db.pool())
+            .await
+            .context("Failed to save session details")?;
+
+        Ok(())
+    }
+
+    /// Replace the session's output triggers
+    pub async fn set_triggers(&self, session_id: &Uuid, triggers: &[Trigger]) -> Result<()> {
+        sqlx::query("UPDATE sessions SET triggers = ? WHERE id = ?")
+            .bind(serde_json::to_string(triggers)?)
+            .bind(session_id.to_string())
+            .execute(self.db.pool())
+            .await
+            .context("Failed to save triggers")?;
+
+        Ok(())
+    }
+
+    /// Persist the session's environment along with its .env file tracking
+    pub async fn update_environment(&self, session: &Session) -> Result<()> {
+        sqlx::query("UPDATE sessions SET environment = ?, env_file = ?, env_file_keys = ? WHERE id = ?")
+            .bind(serde_json::to_string(&session.environment)?)
+            .bind(session.env_file.as_ref().map(|p| p.to_string_lossy().to_string()))
+            .bind(serde_json::to_string(&session.env_file_keys)?)
+            .bind(session.id.to_string())
+            .execute(self.db.pool())
+            .await
+            .context("Failed to save session environment")?;
+
+        Ok(())
+    }
+
+    /// Delete blocks older than `max_age_days` or beyond the newest
+    /// `max_blocks_per_session` of their session (0 disables either limit).
+    /// With `archive`, removed blocks are first appended to that file as JSON
+    /// lines, which only the user can read. With encryption on, each block
+    /// is sealed with the database key and stored as hex under `sealed`.
+    /// Returns the number of blocks removed.
+    pub async fn apply_retention(
+        &self,
+        max_age_days: u32,
+        max_blocks_per_session: u32,
+        archive: Option<&Path>,
+    ) -> Result<usize> {
+        let cutoff = if max_age_days > 0 {
+            (Utc::now() - chrono::Duration::days(max_age_days as i64)).to_rfc3339()
+        } else {
+            String::new()
+        };
+        let max_blocks = if max_blocks_per_session > 0 { max_blocks_per_session as i64 } else { i64::MAX };
+
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT session_id, {BLOCK_COLUMNS}
+            FROM blocks
+            WHERE timestamp < ?
+               OR id IN (
+                   SELECT id FROM (
+                       SELECT id, ROW_NUMBER() OVER (PARTITION BY session_id ORDER BY block_order DESC) AS position
+                       FROM blocks
+                   )
+                   WHERE position > ?
+               )
+            "#
+        ))
+        .bind(cutoff)
+        .bind(max_blocks)
+        .fetch_all(self.db.pool())
+        .await
+        .context("Failed to find expired blocks")?;
+
+        if rows.is_empty() {
+            return Ok(0);
+        }
+
+        if let Some(path) = archive {
+            let mut lines = String::new();
+            let sealing = self.cipher.as_deref().filter(|_| self.encrypt);
+            for row in &rows {
+                let session_id = row.get::<String, _>("session_id");
+                let block = block_from_row(row, self.cipher.as_deref())?;
+                let entry = match sealing {
+                    Some(cipher) => serde_json::json!({
+                        "session_id": session_id,
+                        "sealed": crate::core::crypto::encode_hex(&cipher.seal(&serde_json::to_vec(&block)?)?),
+                    }),
+                    None => serde_json::json!({ "session_id": session_id, "block": block }),
+                };
+                lines.push_str(&entry.to_string());
+                lines.push('\n');
+            }
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut options = std::fs::OpenOptions::new();
+            options.create(true).append(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            let mut file = options
+                .open(path)
+                .with_context(|| format!("Failed to open archive {}", path.display()))?;
+            // An archive from before this was private
+            #[cfg(unix)]
+            std::fs::set_permissions(path, std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+            std::io::Write::write_all(&mut file, lines.as_bytes())
+                .context("Failed to write block archive")?;
+        }
+
+        let mut tx = self.db.pool().begin().await.context("Failed to start transaction")?;
+        for row in &rows {
+            let id: String = row.get("id");
+            uncount_commands(&mut tx, "id = ?", &id).await?;
+            sqlx::query("DELETE FROM blocks WHERE id = ?")
+                .bind(id)
+                .execute(&mut *tx)
+                .await
+                .context("Failed to delete expired block")?;
+        }
+        tx.commit().await.context("Failed to commit retention")?;
+
+        tracing::info!("Retention removed {} blocks", rows.len());
+        Ok(rows.len())
+    }
+
+    pub async fn database_size(&self) -> Result<u64> {
+        self.db.size_bytes().await
+    }
+
+    pub async fn compact_database(&self) -> Result<()> {
+        self.db.vacuum().await
+    }
+
+    /// Snapshot the database into `dir`, keeping the `keep` newest snapshots
+    pub async fn backup_database(&self, dir: &Path, keep: usize) -> Result<PathBuf> {
+        super::backup::create_backup(&self.db, dir, keep).await
+    }
+
+    /// Close the database for every clone of this manager
+    pub async fn close(&self) {
+        self.db.pool().close().await;
+    }
+
     /// Delete a session and all its blocks
     pub async fn delete_session(&self, session_id: &Uuid) -> Result<()> {
+        let mut tx = self.db.pool().begin().await.context("Failed to start transaction")?;
+        uncount_commands(&mut tx, "session_id = ?", &session_id.to_string()).await?;
         sqlx::query("DELETE FROM sessions WHERE id = ?")
             .bind(session_id.to_string())
-            .execute(self.db.pool())
+            .execute(&mut *tx)
             .await?;
+        tx.commit().await.context("Failed to commit session deletion")?;
 
         tracing::info!("Deleted session: {}", session_id);
         Ok(())
     }
+
+    /// Replace a session and its blocks with a copy from elsewhere, keeping
+    /// its `updated_at` so sync sees both sides as up to date
+    pub async fn upsert_session(&self, session: &Session) -> Result<()> {
+        self.replace_sessions(&[], &[session]).await
+    }
+
+    /// Delete the sessions in `removed` and replace or add each of
+    /// `sessions`, in a single transaction: either all of it happens or none
+    pub async fn replace_sessions(&self, removed: &[Uuid], sessions: &[&Session]) -> Result<()> {
+        let mut tx = self.db.pool().begin().await.context("Failed to start transaction")?;
+        for session_id in removed {
+            uncount_commands(&mut tx, "session_id = ?", &session_id.to_string()).await?;
+            sqlx::query("DELETE FROM blocks WHERE session_id = ?")
+                .bind(session_id.to_string())
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM sessions WHERE id = ?")
+                .bind(session_id.to_string())
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        for session in sessions {
+            let was_active: bool = sqlx::query("SELECT is_active FROM sessions WHERE id = ?")
+                .bind(session.id.to_string())
+                .fetch_optional(&mut *tx)
+                .await?
+                .map(|row| row.get("is_active"))
+                .unwrap_or(false);
+
+            sqlx::query("DELETE FROM blocks WHERE session_id = ?")
+                .bind(session.id.to_string())
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM sessions WHERE id = ?")
+                .bind(session.id.to_string())
+                .execute(&mut *tx)
+                .await?;
+
+            self.insert_session(&mut *tx, session)
+                .await
+                .with_context(|| format!("Failed to restore session '{}'", session.name))?;
+            for (order, block) in session.blocks.iter().enumerate() {
+                self.write_block(&mut *tx, &session.id, block, order as i32).await?;
+            }
+
+            sqlx::query("UPDATE sessions SET is_active = ? WHERE id = ?")
+                .bind(was_active)
+                .bind(session.id.to_string())
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await.context("Failed to commit sessions")?;
+        Ok(())
+    }
+}
+
+/// The parts of a block that may hold secrets. With encryption they are
+/// stored together in the `sealed` column instead of their own columns.
+#[derive(Serialize, Deserialize)]
+struct BlockContent {
+    command: String,
+    output: String,
+    original_input: Option<String>,
+    /// Prompt of the block's `AiProvenance`, which is otherwise stored unsealed
+    #[serde(default)]
+    ai_prompt: Option<String>,
+}
+
+impl BlockContent {
+    fn seal(&self, cipher: &FieldCipher) -> Result<Vec<u8>> {
+        let json = serde_json::to_vec(self)?;
+        let compressed = zstd::encode_all(json.as_slice(), 3).context("Failed to compress block")?;
+        cipher.seal(&compressed)
+    }
+
+    fn open(sealed: &[u8], cipher: &FieldCipher) -> Result<Self> {
+        let compressed = cipher.open(sealed)?;
+        let json = zstd::decode_all(compressed.as_slice()).context("Failed to decompress block")?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+}
+
+/// Column values for a block's content
+struct StoredContent {
+    command: String,
+    output: String,
+    output_zstd: Option<Vec<u8>>,
+    original_input: Option<String>,
+    sealed: Option<Vec<u8>>,
+    ai_provenance: Option<String>,
+}
+
+impl StoredContent {
+    fn new(block: &Block, cipher: Option<&FieldCipher>) -> Result<Self> {
+        if let Some(cipher) = cipher {
+            let content = BlockContent {
+                command: block.command.clone(),
+                output: block.output.to_string(),
+                original_input: block.original_input.clone(),
+                ai_prompt: block.metadata.ai_provenance.as_ref().map(|p| p.prompt.clone()),
+            };
+            let provenance = block.metadata.ai_provenance.clone().map(|p| AiProvenance { prompt: String::new(), ..p });
+            return Ok(Self {
+                command: String::new(),
+                output: String::new(),
+                output_zstd: None,
+                original_input: None,
+                sealed: Some(content.seal(cipher)?),
+                ai_provenance: provenance.as_ref().map(serde_json::to_string).transpose()?,
+            });
+        }
+
+        let output = block.output.to_string();
+        let output_zstd = compress_output(&output)?;
+        Ok(Self {
+            command: block.command.clone(),
+            output: if output_zstd.is_some() { String::new() } else { output },
+            output_zstd,
+            original_input: block.original_input.clone(),
+            sealed: None,
+            ai_provenance: block.metadata.ai_provenance.as_ref().map(serde_json::to_string).transpose()?,
+        })
+    }
+}
+
+/// Take the runs of the blocks matching `filter` (bound to `value`) off the
+/// command frequency table before they are deleted, so deleted history
+/// doesn't live on there
+async fn uncount_commands(conn: &mut sqlx::SqliteConnection, filter: &str, value: &str) -> Result<()> {
+    let commands: Vec<String> = sqlx::query_scalar(&format!(
+        "SELECT command FROM blocks WHERE sealed IS NULL AND NOT is_divider AND NOT is_calculation \
+         AND state != 'PendingApproval' AND {filter}"
+    ))
+    .bind(value)
+    .fetch_all(&mut *conn)
+    .await?;
+    for command in commands.iter().filter_map(|command| normalize_command(command)) {
+        sqlx::query("UPDATE command_frequency SET count = count - 1 WHERE command = ?")
+            .bind(command)
+            .execute(&mut *conn)
+            .await?;
+    }
+    sqlx::query("DELETE FROM command_frequency WHERE count <= 0")
+        .execute(&mut *conn)
+        .await
+        .context("Failed to update command frequency")?;
+    Ok(())
+}
+
+/// Build a block from a `blocks` row selected with `BLOCK_COLUMNS`
+fn block_from_row(row: &SqliteRow, cipher: Option<&FieldCipher>) -> Result<Block> {
+    let id: String = row.get("id");
+    let timestamp: String = row.get("timestamp");
+    let working_directory: String = row.get("working_directory");
+    let environment_json: Option<String> = row.get("environment");
+    let state_str: String = row.get("state");
+
+    let environment: HashMap<String, String> = environment_json
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+
+    let started_at: Option<String> = row.get("started_at");
+    let completed_at: Option<String> = row.get("completed_at");
+    let duration_ms: Option<i64> = row.get("duration_ms");
+    let content = match row.get::<Option<Vec<u8>>, _>("sealed") {
+        Some(sealed) => {
+            let cipher = cipher.context("Block is encrypted but the database key is unavailable")?;
+            BlockContent::open(&sealed, cipher)?
+        }
+        None => BlockContent {
+            command: row.get("command"),
+            output: match row.get::<Option<Vec<u8>>, _>("output_zstd") {
+                Some(compressed) => decompress_output(&compressed)?,
+                None => row.get("output"),
+            },
+            original_input: row.get("original_input"),
+            ai_prompt: None,
+        },
+    };
+    let ai_provenance = row
+        .get::<Option<String>, _>("ai_provenance")
+        .and_then(|json| serde_json::from_str::<AiProvenance>(&json).ok())
+        .map(|provenance| match &content.ai_prompt {
+            Some(prompt) => AiProvenance { prompt: prompt.clone(), ..provenance },
+            None => provenance,
+        });
+
+    let state = match state_str.as_str() {
+        "Editing" => BlockState::Editing,
+        "Running" => BlockState::Running,
+        "Completed" => BlockState::Completed,
+        "Failed" => BlockState::Failed,
+        "Cancelled" => BlockState::Cancelled,
+        _ => BlockState::Completed,
+    };
+
+    Ok(Block {
+        id: Uuid::parse_str(&id)?,
+        timestamp: DateTime::parse_from_rfc3339(&timestamp)?.with_timezone(&Utc),
+        command: content.command,
+        output: content.output.into(),
+        exit_code: row.get("exit_code"),
+        state,
+        metadata: super::BlockMetadata {
+            duration: duration_ms.map(|ms| Duration::from_millis(ms as u64)),
+            working_directory: PathBuf::from(working_directory),
+            environment,
+            started_at: started_at.and_then(|s| DateTime::parse_from_rfc3339(&s).ok().map(|dt| dt.with_timezone(&Utc))),
+            completed_at: completed_at.and_then(|s| DateTime::parse_from_rfc3339(&s).ok().map(|dt| dt.with_timezone(&Utc))),
+            hooks: row.get::<Option<String>, _>("hook_results")
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default(),
+            ai_provenance,
+        },
+        is_collapsed: row.get("is_collapsed"),
+        is_selected: false,
+        original_input: content.original_input,
+        is_pinned: row.get("is_pinned"),
+        is_startup: row.get("is_startup"),
+        highlighted_lines: Vec::new(),
+        tags: row.get::<Option<String>, _>("tags")
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+        is_divider: row.get("is_divider"),
+        is_calculation: row.get("is_calculation"),
+        in_ai_context: row.get("in_ai_context"),
+        pending_carriage_return: false,
+    })
 }
 
 #[derive(Debug, Clone)]
@@ -257,4 +1028,486 @@ pub struct SessionInfo {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub is_active: bool,
+    pub folder: Option<String>,
+    pub working_directory: PathBuf,
+    pub description: Option<String>,
+    pub color: Option<SessionColor>,
+}
+
+/// Group sessions by folder for display: named folders sorted alphabetically,
+/// followed by ungrouped sessions. Order within a group is preserved.
+pub fn group_sessions_by_folder(sessions: &[SessionInfo]) -> Vec<(Option<String>, Vec<SessionInfo>)> {
+    let mut folders: Vec<(Option<String>, Vec<SessionInfo>)> = Vec::new();
+
+    for session in sessions {
+        match folders.iter_mut().find(|(folder, _)| *folder == session.folder) {
+            Some((_, group)) => group.push(session.clone()),
+            None => folders.push((session.folder.clone(), vec![session.clone()])),
+        }
+    }
+
+    folders.sort_by(|(a, _), (b, _)| match (a, b) {
+        (Some(a), Some(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+
+    folders
+}
+
+/// Whether a session other than `except` already has this name, ignoring
+/// case and surrounding whitespace
+pub fn session_name_taken(name: &str, sessions: &[SessionInfo], except: Option<Uuid>) -> bool {
+    let name = name.trim();
+    sessions
+        .iter()
+        .filter(|s| Some(s.id) != except)
+        .any(|s| s.name.trim().eq_ignore_ascii_case(name))
+}
+
+/// `name` if no session has it yet, otherwise the first free "name (2)",
+/// "name (3)"...
+pub fn unique_session_name(name: &str, sessions: &[SessionInfo]) -> String {
+    let name = name.trim();
+    if !session_name_taken(name, sessions, None) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|n| format!("{} ({})", name, n))
+        .find(|candidate| !session_name_taken(candidate, sessions, None))
+        .expect("some numbered name is free")
+}
+
+/// Tells apart sessions that share a name (from before names had to be
+/// unique, or restored from an archive): their directory and creation date
+pub fn session_subtitle(session: &SessionInfo, sessions: &[SessionInfo]) -> Option<String> {
+    session_name_taken(&session.name, sessions, Some(session.id)).then(|| {
+        format!(
+            "{} · created {}",
+            session.working_directory.display(),
+            session.created_at.format("%Y-%m-%d")
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_session_settings_roundtrip() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let manager = SessionManager::new(db).await.unwrap();
+
+        let session = Session::new("api".to_string(), PathBuf::from("/tmp"));
+        manager.create_session(&session).await.unwrap();
+        manager.set_session_folder(&session.id, Some("work")).await.unwrap();
+
+        let sessions = manager.list_sessions().await.unwrap();
+        assert_eq!(sessions[0].folder.as_deref(), Some("work"));
+        let loaded = manager.load_session(&session.id).await.unwrap();
+        assert_eq!(loaded.folder.as_deref(), Some("work"));
+
+        let commands = vec!["source .venv/bin/activate".to_string()];
+        manager.set_startup_commands(&session.id, &commands).await.unwrap();
+        let loaded = manager.load_session(&session.id).await.unwrap();
+        assert_eq!(loaded.startup_commands, commands);
+
+        let mut with_env = loaded.clone();
+        with_env.apply_env_file(PathBuf::from("/tmp/.env"), vec![("TOKEN".to_string(), "x".to_string())]);
+        manager.update_environment(&with_env).await.unwrap();
+        let loaded = manager.load_session(&session.id).await.unwrap();
+        assert_eq!(loaded.environment.get("TOKEN").map(String::as_str), Some("x"));
+
+        let triggers = vec![Trigger::new("FAIL", crate::core::TriggerAction::Notify)];
+        manager.set_triggers(&session.id, &triggers).await.unwrap();
+        let loaded = manager.load_session(&session.id).await.unwrap();
+        assert_eq!(loaded.triggers, triggers);
+        assert_eq!(loaded.env_file_keys, vec!["TOKEN".to_string()]);
+
+        manager
+            .set_session_details(&session.id, Some("staging cluster"), Some(SessionColor::Orange))
+            .await
+            .unwrap();
+        let loaded = manager.load_session(&session.id).await.unwrap();
+        assert_eq!(loaded.description.as_deref(), Some("staging cluster"));
+        assert_eq!(loaded.color, Some(SessionColor::Orange));
+        assert_eq!(manager.list_sessions().await.unwrap()[0].color, Some(SessionColor::Orange));
+
+        // Blank folder names ungroup the session
+        manager.set_session_folder(&session.id, Some("  ")).await.unwrap();
+        assert_eq!(manager.list_sessions().await.unwrap()[0].folder, None);
+    }
+
+    #[tokio::test]
+    async fn test_large_output_roundtrip() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let manager = SessionManager::new(db).await.unwrap();
+
+        let session = Session::new("logs".to_string(), PathBuf::from("/tmp"));
+        manager.create_session(&session).await.unwrap();
+
+        let mut small = Block::new("echo hi".to_string(), PathBuf::from("/tmp"));
+        small.output = "hi\n".into();
+        let mut large = Block::new("cat build.log".to_string(), PathBuf::from("/tmp"));
+        let log = "warning: unused variable\n".repeat(1000);
+        large.output = log.as_str().into();
+        manager.save_block(&session.id, &small, 0).await.unwrap();
+        manager.save_block(&session.id, &large, 1).await.unwrap();
+
+        let loaded = manager.load_session(&session.id).await.unwrap();
+        assert!(loaded.blocks[0].output == "hi\n");
+        assert!(loaded.blocks[1].output == log.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_save_blocks_in_one_transaction() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let manager = SessionManager::new(db).await.unwrap();
+
+        let session = Session::new("batch".to_string(), PathBuf::from("/tmp"));
+        manager.create_session(&session).await.unwrap();
+        let blocks: Vec<Block> = (0..3)
+            .map(|i| Block::new(format!("echo {}", i), PathBuf::from("/tmp")))
+            .collect();
+        manager.save_blocks(&session.id, &blocks).await.unwrap();
+        // Saving again replaces the rows rather than duplicating them
+        manager.save_blocks(&session.id, &blocks).await.unwrap();
+
+        let loaded = manager.load_session(&session.id).await.unwrap();
+        let commands: Vec<_> = loaded.blocks.iter().map(|b| b.command.as_str()).collect();
+        assert_eq!(commands, ["echo 0", "echo 1", "echo 2"]);
+        assert!(loaded.updated_at > session.updated_at);
+    }
+
+    #[tokio::test]
+    async fn test_append_block() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let manager = SessionManager::new(db).await.unwrap();
+
+        let session = Session::new("fleet".to_string(), PathBuf::from("/tmp"));
+        manager.create_session(&session).await.unwrap();
+        let blocks: Vec<Block> = (0..2)
+            .map(|i| Block::new(format!("echo {}", i), PathBuf::from("/tmp")))
+            .collect();
+        manager.save_blocks(&session.id, &blocks).await.unwrap();
+        manager.append_block(&session.id, &Block::new("df -h".to_string(), PathBuf::from("/tmp"))).await.unwrap();
+
+        let loaded = manager.load_session(&session.id).await.unwrap();
+        let commands: Vec<_> = loaded.blocks.iter().map(|b| b.command.as_str()).collect();
+        assert_eq!(commands, ["echo 0", "echo 1", "df -h"]);
+        assert!(loaded.updated_at > session.updated_at);
+    }
+
+    #[tokio::test]
+    async fn test_apply_retention() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let manager = SessionManager::new(db).await.unwrap();
+
+        let session = Session::new("old".to_string(), PathBuf::from("/tmp"));
+        manager.create_session(&session).await.unwrap();
+        for (order, age_days) in [40, 3, 2, 1].into_iter().enumerate() {
+            let mut block = Block::new(format!("echo {}", order), PathBuf::from("/tmp"));
+            block.timestamp = Utc::now() - chrono::Duration::days(age_days);
+            manager.save_block(&session.id, &block, order as i32).await.unwrap();
+        }
+
+        // Disabled limits remove nothing
+        assert_eq!(manager.apply_retention(0, 0, None).await.unwrap(), 0);
+
+        let archive = temp_dir.path().join("archive").join("blocks.jsonl");
+        assert_eq!(manager.apply_retention(30, 2, Some(&archive)).await.unwrap(), 2);
+        let loaded = manager.load_session(&session.id).await.unwrap();
+        let commands: Vec<_> = loaded.blocks.iter().map(|b| b.command.as_str()).collect();
+        assert_eq!(commands, vec!["echo 2", "echo 3"]);
+        assert_eq!(std::fs::read_to_string(&archive).unwrap().lines().count(), 2);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&archive).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        manager.compact_database().await.unwrap();
+        assert!(manager.database_size().await.unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_retention_archive() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let key = FieldCipher::generate_key();
+        let manager = SessionManager::new(db).await.unwrap().with_cipher(FieldCipher::new(&key), true);
+
+        let session = Session::new("secrets".to_string(), PathBuf::from("/tmp"));
+        manager.create_session(&session).await.unwrap();
+        let mut block = Block::new("export TOKEN=hunter2".to_string(), PathBuf::from("/tmp"));
+        block.timestamp = Utc::now() - chrono::Duration::days(40);
+        manager.save_block(&session.id, &block, 0).await.unwrap();
+
+        let archive = temp_dir.path().join("blocks.jsonl");
+        assert_eq!(manager.apply_retention(30, 0, Some(&archive)).await.unwrap(), 1);
+        let line = std::fs::read_to_string(&archive).unwrap();
+        assert!(!line.contains("hunter2"));
+
+        let entry: serde_json::Value = serde_json::from_str(line.trim()).unwrap();
+        let sealed = crate::core::crypto::decode_hex(entry["sealed"].as_str().unwrap()).unwrap();
+        let opened: Block = serde_json::from_slice(&FieldCipher::new(&key).open(&sealed).unwrap()).unwrap();
+        assert_eq!(opened.command, "export TOKEN=hunter2");
+    }
+
+    #[tokio::test]
+    async fn test_encryption_migration() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let key = FieldCipher::generate_key();
+
+        let plain = SessionManager::new(Database::new(db_path.clone()).await.unwrap()).await.unwrap();
+        let session = Session::new("secrets".to_string(), PathBuf::from("/tmp"));
+        plain.create_session(&session).await.unwrap();
+        let mut block = Block::new("export TOKEN=hunter2".to_string(), PathBuf::from("/tmp"))
+            .with_provenance(AiProvenance {
+                provider: "ollama".to_string(),
+                model: "llama2".to_string(),
+                prompt: "set TOKEN to hunter2".to_string(),
+            });
+        block.output = "hunter2\n".into();
+        plain.save_block(&session.id, &block, 0).await.unwrap();
+        let loaded = plain.load_session(&session.id).await.unwrap();
+        assert_eq!(loaded.blocks[0].metadata.ai_provenance, block.metadata.ai_provenance);
+
+        // Existing plaintext blocks are encrypted in place
+        let encrypted = plain.clone().with_cipher(FieldCipher::new(&key), true);
+        assert_eq!(encrypted.migrate_encryption().await.unwrap(), 1);
+        assert!(encrypted.has_encrypted_blocks().await.unwrap());
+        let command: String = sqlx::query_scalar("SELECT command FROM blocks")
+            .fetch_one(encrypted.db.pool())
+            .await
+            .unwrap();
+        assert!(command.is_empty());
+        let provenance: String = sqlx::query_scalar("SELECT ai_provenance FROM blocks")
+            .fetch_one(encrypted.db.pool())
+            .await
+            .unwrap();
+        assert!(provenance.contains("llama2") && !provenance.contains("hunter2"));
+
+        let loaded = encrypted.load_session(&session.id).await.unwrap();
+        assert_eq!(loaded.blocks[0].command, "export TOKEN=hunter2");
+        assert_eq!(loaded.blocks[0].metadata.ai_provenance, block.metadata.ai_provenance);
+        assert!(loaded.blocks[0].output == "hunter2\n");
+        assert_eq!(encrypted.recent_commands(10).await.unwrap(), vec!["export TOKEN=hunter2".to_string()]);
+
+        // Without the key the session can't be read; turning encryption off decrypts
+        assert!(plain.load_session(&session.id).await.is_err());
+        let decrypted = plain.with_cipher(FieldCipher::new(&key), false);
+        assert_eq!(decrypted.migrate_encryption().await.unwrap(), 1);
+        assert!(!decrypted.has_encrypted_blocks().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_find_project_session() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let manager = SessionManager::new(db).await.unwrap();
+
+        let mut session = Session::new("immaterium".to_string(), PathBuf::from("/src/immaterium/src"));
+        session.project_root = Some(PathBuf::from("/src/immaterium"));
+        manager.create_session(&session).await.unwrap();
+
+        let found = manager.find_project_session(Path::new("/src/immaterium")).await.unwrap();
+        assert_eq!(found.map(|s| s.id), Some(session.id));
+        assert!(manager.find_project_session(Path::new("/src/other")).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dividers_and_deleted_blocks() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let manager = SessionManager::new(db).await.unwrap();
+
+        let session = Session::new("phases".to_string(), PathBuf::from("/tmp"));
+        manager.create_session(&session).await.unwrap();
+        let divider = Block::divider("Deploy".to_string(), PathBuf::from("/tmp"));
+        let mut block = Block::new("make deploy".to_string(), PathBuf::from("/tmp"));
+        block.complete_execution(0);
+        manager.save_blocks(&session.id, &[divider.clone(), block.clone()]).await.unwrap();
+
+        let loaded = manager.load_session(&session.id).await.unwrap();
+        assert!(loaded.blocks[0].is_divider && !loaded.blocks[1].is_divider);
+        // Headings aren't commands to recall
+        assert_eq!(manager.recent_commands(10).await.unwrap(), vec!["make deploy".to_string()]);
+
+        manager.delete_blocks(&[divider.id, block.id]).await.unwrap();
+        assert!(manager.load_session(&session.id).await.unwrap().blocks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_calculation_blocks() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let manager = SessionManager::new(db).await.unwrap();
+
+        let session = Session::new("calc".to_string(), PathBuf::from("/tmp"));
+        manager.create_session(&session).await.unwrap();
+        let calculation = Block::calculation("4096*3/1024".to_string(), "12".to_string(), PathBuf::from("/tmp"));
+        manager.save_blocks(&session.id, &[calculation]).await.unwrap();
+
+        let loaded = manager.load_session(&session.id).await.unwrap();
+        assert!(loaded.blocks[0].is_calculation);
+        assert_eq!(loaded.blocks[0].output.to_string(), "12");
+        // Expressions aren't shell commands to recall
+        assert!(manager.recent_commands(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ai_context_blocks() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let manager = SessionManager::new(db).await.unwrap();
+
+        let session = Session::new("pinned".to_string(), PathBuf::from("/tmp"));
+        manager.create_session(&session).await.unwrap();
+        let mut block = Block::new("cargo build".to_string(), PathBuf::from("/tmp"));
+        block.toggle_ai_context();
+        manager.save_blocks(&session.id, &[block]).await.unwrap();
+
+        let loaded = manager.load_session(&session.id).await.unwrap();
+        assert!(loaded.blocks[0].in_ai_context);
+    }
+
+    #[tokio::test]
+    async fn test_command_usage() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let manager = SessionManager::new(db).await.unwrap();
+
+        manager.record_command_use("ls  -la").await.unwrap();
+        manager.record_command_use("ls -la ").await.unwrap();
+        manager.record_command_use("make").await.unwrap();
+        manager.record_command_use(" export TOKEN=x").await.unwrap();
+
+        let usage = manager.command_usage(10).await.unwrap();
+        let counts: Vec<_> = usage.iter().map(|u| (u.command.as_str(), u.count)).collect();
+        assert_eq!(counts, [("ls -la", 2), ("make", 1)]);
+
+        // Nothing is counted in the clear with encrypted history
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let manager = SessionManager::new(db).await.unwrap().with_cipher(FieldCipher::new(&[7u8; 32]), true);
+        manager.record_command_use("make").await.unwrap();
+        assert!(manager.command_usage(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_command_usage_forgets_deleted_history() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let manager = SessionManager::new(db).await.unwrap();
+
+        let session = Session::new("usage".to_string(), PathBuf::from("/tmp"));
+        manager.create_session(&session).await.unwrap();
+        let blocks: Vec<Block> = ["make", "make", "export TOKEN=x", "ls"]
+            .iter()
+            .map(|command| Block::new(command.to_string(), PathBuf::from("/tmp")))
+            .collect();
+        for block in &blocks {
+            manager.record_command_use(&block.command).await.unwrap();
+        }
+        manager.save_blocks(&session.id, &blocks).await.unwrap();
+
+        manager.delete_blocks(&[blocks[1].id, blocks[2].id]).await.unwrap();
+        let usage = manager.command_usage(10).await.unwrap();
+        let mut counts: Vec<_> = usage.iter().map(|u| (u.command.as_str(), u.count)).collect();
+        counts.sort();
+        assert_eq!(counts, [("ls", 1), ("make", 1)]);
+
+        manager.delete_session(&session.id).await.unwrap();
+        assert!(manager.command_usage(10).await.unwrap().is_empty());
+
+        // Turning encryption on clears what was counted in the clear
+        manager.record_command_use("ssh prod").await.unwrap();
+        let manager = manager.with_cipher(FieldCipher::new(&[7u8; 32]), true);
+        manager.migrate_encryption().await.unwrap();
+        assert!(manager.command_usage(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_directory_bookmarks() {
+        let temp_dir = tempdir().unwrap();
+        let db = Database::new(temp_dir.path().join("test.db")).await.unwrap();
+        let manager = SessionManager::new(db).await.unwrap();
+
+        manager.record_directory_visit(Path::new("/src/api")).await.unwrap();
+        manager.record_directory_visit(Path::new("/src/api")).await.unwrap();
+        manager.set_directory_pinned(Path::new("/etc/nginx"), true).await.unwrap();
+
+        let bookmarks = manager.directory_bookmarks(10).await.unwrap();
+        assert_eq!(bookmarks.len(), 2);
+        assert_eq!(bookmarks[0].path, PathBuf::from("/etc/nginx"));
+        assert!(bookmarks[0].pinned && bookmarks[0].visits == 0);
+        assert_eq!(bookmarks[1].visits, 2);
+
+        // Visiting a pinned directory keeps the pin
+        manager.record_directory_visit(Path::new("/etc/nginx")).await.unwrap();
+        manager.forget_directory(Path::new("/src/api")).await.unwrap();
+        let bookmarks = manager.directory_bookmarks(10).await.unwrap();
+        assert_eq!(bookmarks.len(), 1);
+        assert!(bookmarks[0].pinned && bookmarks[0].visits == 1);
+    }
+
+    #[test]
+    fn test_group_sessions_by_folder() {
+        let info = |name: &str, folder: Option<&str>| SessionInfo {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            is_active: false,
+            folder: folder.map(String::from),
+            working_directory: PathBuf::from("/tmp"),
+            description: None,
+            color: None,
+        };
+        let sessions = vec![
+            info("scratch", None),
+            info("infra", Some("work")),
+            info("blog", Some("Personal")),
+            info("api", Some("work")),
+        ];
+
+        let groups = group_sessions_by_folder(&sessions);
+        let folders: Vec<_> = groups.iter().map(|(f, s)| (f.as_deref(), s.len())).collect();
+        assert_eq!(folders, vec![(Some("Personal"), 1), (Some("work"), 2), (None, 1)]);
+        assert_eq!(groups[1].1[0].name, "infra");
+    }
+
+    #[test]
+    fn test_unique_session_names() {
+        let info = |name: &str| SessionInfo {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            is_active: false,
+            folder: None,
+            working_directory: PathBuf::from("/work"),
+            description: None,
+            color: None,
+        };
+        let sessions = vec![info("api"), info("API (2)"), info("blog"), info("blog")];
+
+        assert!(session_name_taken(" Api ", &sessions, None));
+        assert!(!session_name_taken("api", &sessions, Some(sessions[0].id)));
+        assert_eq!(unique_session_name("infra", &sessions), "infra");
+        assert_eq!(unique_session_name("api", &sessions), "api (3)");
+
+        assert_eq!(session_subtitle(&sessions[0], &sessions), None);
+        assert!(session_subtitle(&sessions[2], &sessions).unwrap().starts_with("/work · created "));
+    }
 }
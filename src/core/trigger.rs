@@ -0,0 +1,183 @@
+use crate::t;
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// What happens when a trigger's pattern matches a line of output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "command")]
+pub enum TriggerAction {
+    /// Show a desktop notification with the matching line
+    Notify,
+    /// Highlight the matching line in the block output
+    HighlightLine,
+    /// Queue another command to run after the current one
+    RunCommand(String),
+    /// Pin the block so it stands out and shows under the Pinned filter
+    MarkBlock,
+}
+
+impl TriggerAction {
+    pub fn label(&self) -> String {
+        match self {
+            TriggerAction::Notify => t!("trigger-notify"),
+            TriggerAction::HighlightLine => t!("trigger-highlight-line"),
+            TriggerAction::RunCommand(_) => t!("trigger-run-command"),
+            TriggerAction::MarkBlock => t!("trigger-mark-block"),
+        }
+    }
+}
+
+/// A per-session rule: when `pattern` matches streaming output, fire `action`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Trigger {
+    pub pattern: String,
+    pub action: TriggerAction,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Trigger {
+    pub fn new(pattern: impl Into<String>, action: TriggerAction) -> Self {
+        Self {
+            pattern: pattern.into(),
+            action,
+            enabled: true,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        Regex::new(&self.pattern)
+            .map(|_| ())
+            .with_context(|| format!("Invalid pattern '{}'", self.pattern))
+    }
+}
+
+/// A trigger that fired on a line of a block's output
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriggerHit {
+    pub action: TriggerAction,
+    /// Zero-based line index in the block output
+    pub line_index: usize,
+    pub line: String,
+}
+
+/// Matches triggers against one command's output as it streams in. Chunks are
+/// split into lines; a partial trailing line is held until it completes.
+/// Apart from highlighting, each trigger fires at most once per command so a
+/// noisy match cannot flood notifications or re-run a command repeatedly.
+pub struct TriggerMatcher {
+    triggers: Vec<(Regex, TriggerAction, bool)>,
+    partial: String,
+    line_index: usize,
+}
+
+impl TriggerMatcher {
+    /// Compile the enabled triggers; invalid patterns are skipped with a warning
+    pub fn new(triggers: &[Trigger]) -> Self {
+        let triggers = triggers
+            .iter()
+            .filter(|t| t.enabled)
+            .filter_map(|t| match Regex::new(&t.pattern) {
+                Ok(regex) => Some((regex, t.action.clone(), false)),
+                Err(e) => {
+                    tracing::warn!("Skipping trigger '{}': {}", t.pattern, e);
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            triggers,
+            partial: String::new(),
+            line_index: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triggers.is_empty()
+    }
+
+    /// Feed a chunk of output, returning the triggers fired by completed lines
+    pub fn feed(&mut self, chunk: &str) -> Vec<TriggerHit> {
+        let mut hits = Vec::new();
+        if self.triggers.is_empty() {
+            return hits;
+        }
+
+        self.partial.push_str(chunk);
+        while let Some(pos) = self.partial.find('\n') {
+            let line: String = self.partial.drain(..=pos).collect();
+            self.match_line(line.trim_end_matches(['\n', '\r']), &mut hits);
+            self.line_index += 1;
+        }
+        hits
+    }
+
+    /// Match whatever partial line remains once the command has exited
+    pub fn finish(&mut self) -> Vec<TriggerHit> {
+        let mut hits = Vec::new();
+        if !self.partial.is_empty() {
+            let line = std::mem::take(&mut self.partial);
+            self.match_line(&line, &mut hits);
+        }
+        hits
+    }
+
+    fn match_line(&mut self, line: &str, hits: &mut Vec<TriggerHit>) {
+        for (regex, action, fired) in &mut self.triggers {
+            let once = *action != TriggerAction::HighlightLine;
+            if (once && *fired) || !regex.is_match(line) {
+                continue;
+            }
+            *fired = true;
+            hits.push(TriggerHit {
+                action: action.clone(),
+                line_index: self.line_index,
+                line: line.to_string(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matcher_handles_split_lines() {
+        let triggers = vec![
+            Trigger::new("ERROR", TriggerAction::HighlightLine),
+            Trigger::new("(?i)build failed", TriggerAction::Notify),
+            Trigger::new("[", TriggerAction::MarkBlock),
+        ];
+        let mut matcher = TriggerMatcher::new(&triggers);
+
+        assert!(matcher.feed("starting\nERR").is_empty());
+        let hits = matcher.feed("OR: disk full\r\nok\nERROR again\n");
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].line_index, 1);
+        assert_eq!(hits[0].line, "ERROR: disk full");
+        assert_eq!(hits[1].line_index, 3);
+
+        let hits = matcher.feed("Build FAILED\nbuild failed\n");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].action, TriggerAction::Notify);
+
+        assert!(matcher.feed("trailing ERROR").is_empty());
+        assert_eq!(matcher.finish().len(), 1);
+    }
+
+    #[test]
+    fn test_trigger_serialization() {
+        let trigger = Trigger::new("done", TriggerAction::RunCommand("make deploy".to_string()));
+        let json = serde_json::to_string(&trigger).unwrap();
+        let parsed: Trigger = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, trigger);
+        assert!(Trigger::new("(", TriggerAction::Notify).validate().is_err());
+    }
+}
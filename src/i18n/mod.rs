@@ -0,0 +1,192 @@
+// Localization of user-facing strings with Fluent
+// Message files live in locales/<language>/main.ftl and are embedded at build
+// time; the active language can be switched while the app is running
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::FluentResource;
+use std::sync::{Arc, OnceLock, RwLock};
+use unic_langid::LanguageIdentifier;
+
+pub use fluent_bundle::{FluentArgs, FluentValue};
+
+/// Supported languages: (code, native name, message file)
+pub const LANGUAGES: &[(&str, &str, &str)] = &[
+    ("en-US", "English", include_str!("../../locales/en-US/main.ftl")),
+    ("de", "Deutsch", include_str!("../../locales/de/main.ftl")),
+];
+
+pub const DEFAULT_LANGUAGE: &str = "en-US";
+
+/// Look up a message in the active language: `t!("menu-file")` or
+/// `t!("status-plugins-loaded", count = 3)`
+#[macro_export]
+macro_rules! t {
+    ($id:expr) => {
+        $crate::i18n::tr($id)
+    };
+    ($id:expr, $($name:ident = $value:expr),+ $(,)?) => {{
+        let mut args = $crate::i18n::FluentArgs::new();
+        $(args.set(stringify!($name), $value);)+
+        $crate::i18n::tr_args($id, &args)
+    }};
+}
+
+struct Localizer {
+    language: &'static str,
+    bundle: FluentBundle<FluentResource>,
+}
+
+impl Localizer {
+    /// Localizer for `code`, or the default language if it is not supported
+    fn new(code: &str) -> Self {
+        let (language, _, source) = LANGUAGES
+            .iter()
+            .find(|(c, _, _)| c.eq_ignore_ascii_case(code))
+            .unwrap_or(&LANGUAGES[0]);
+        let id: LanguageIdentifier = language.parse().unwrap_or_default();
+        let mut bundle = FluentBundle::new_concurrent(vec![id]);
+        // Unicode isolation marks around arguments render as boxes in egui
+        bundle.set_use_isolating(false);
+        let resource = FluentResource::try_new(source.to_string()).unwrap_or_else(|(resource, errors)| {
+            tracing::warn!("Errors in {} messages: {:?}", language, errors);
+            resource
+        });
+        if let Err(errors) = bundle.add_resource(resource) {
+            tracing::warn!("Duplicate {} messages: {:?}", language, errors);
+        }
+        Self { language, bundle }
+    }
+
+    fn format(&self, id: &str, args: Option<&FluentArgs>) -> Option<String> {
+        let pattern = self.bundle.get_message(id)?.value()?;
+        let mut errors = Vec::new();
+        let text = self.bundle.format_pattern(pattern, args, &mut errors);
+        if !errors.is_empty() {
+            tracing::debug!("Errors formatting message {}: {:?}", id, errors);
+        }
+        Some(text.into_owned())
+    }
+}
+
+fn current() -> &'static RwLock<Arc<Localizer>> {
+    static CURRENT: OnceLock<RwLock<Arc<Localizer>>> = OnceLock::new();
+    CURRENT.get_or_init(|| RwLock::new(Arc::new(Localizer::new(DEFAULT_LANGUAGE))))
+}
+
+fn fallback() -> &'static Localizer {
+    static FALLBACK: OnceLock<Localizer> = OnceLock::new();
+    FALLBACK.get_or_init(|| Localizer::new(DEFAULT_LANGUAGE))
+}
+
+/// Switch the active language; unsupported codes select the default
+pub fn set_language(code: &str) {
+    let localizer = Arc::new(Localizer::new(code));
+    if let Ok(mut current) = current().write() {
+        *current = localizer;
+    }
+}
+
+/// Code of the active language
+pub fn language() -> &'static str {
+    current().read().map(|l| l.language).unwrap_or(DEFAULT_LANGUAGE)
+}
+
+pub fn tr(id: &str) -> String {
+    format(id, None)
+}
+
+pub fn tr_args(id: &str, args: &FluentArgs) -> String {
+    format(id, Some(args))
+}
+
+/// Messages missing from the active language fall back to English, and
+/// unknown ids are returned as they are
+fn format(id: &str, args: Option<&FluentArgs>) -> String {
+    let localizer = current().read().map(|l| l.clone()).ok();
+    localizer
+        .and_then(|l| l.format(id, args))
+        .or_else(|| fallback().format(id, args))
+        .unwrap_or_else(|| id.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// Ids of the messages defined in a message file (`id = ...` lines)
+    fn message_ids(source: &str) -> HashSet<&str> {
+        assert!(FluentResource::try_new(source.to_string()).is_ok(), "message file parses");
+        source
+            .lines()
+            .filter(|line| line.starts_with(|c: char| c.is_ascii_alphabetic()))
+            .filter_map(|line| line.split_once(" =").map(|(id, _)| id))
+            .collect()
+    }
+
+    #[test]
+    fn test_languages_define_the_same_messages() {
+        let english = message_ids(LANGUAGES[0].2);
+        for (code, _, source) in &LANGUAGES[1..] {
+            let ids = message_ids(source);
+            let missing: Vec<_> = english.difference(&ids).collect();
+            let extra: Vec<_> = ids.difference(&english).collect();
+            assert!(missing.is_empty() && extra.is_empty(), "{}: missing {:?}, extra {:?}", code, missing, extra);
+        }
+    }
+
+    /// Source files under `dir`, recursively
+    fn source_files(dir: &std::path::Path, files: &mut Vec<std::path::PathBuf>) {
+        for entry in std::fs::read_dir(dir).unwrap().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                source_files(&path, files);
+            } else if path.extension().is_some_and(|ext| ext == "rs") {
+                files.push(path);
+            }
+        }
+    }
+
+    #[test]
+    fn test_used_messages_are_defined() {
+        let english = message_ids(LANGUAGES[0].2);
+        let usage = regex::Regex::new(r#"t!\("([a-z0-9-]+)""#).unwrap();
+        let mut files = Vec::new();
+        source_files(&std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src"), &mut files);
+        let mut undefined = Vec::new();
+        for file in files {
+            let source = std::fs::read_to_string(&file).unwrap();
+            for id in usage.captures_iter(&source).map(|c| c.get(1).unwrap().as_str()) {
+                if !english.contains(id) {
+                    undefined.push(format!("{}: {}", file.display(), id));
+                }
+            }
+        }
+        assert!(undefined.is_empty(), "undefined messages: {:?}", undefined);
+    }
+
+    #[test]
+    fn test_format_and_fallback() {
+        let german = Localizer::new("de");
+        assert_eq!(german.format("menu-file", None).as_deref(), Some("Datei"));
+        let mut args = FluentArgs::new();
+        args.set("count", 3);
+        assert_eq!(german.format("status-plugins-loaded", Some(&args)).as_deref(), Some("🧩 3 Plugins geladen"));
+        assert_eq!(
+            german.format("broadcast-targets", Some(&args)).as_deref(),
+            Some("Eingegebene Befehle laufen auch in 3 weiteren Sitzungen")
+        );
+        args.set("count", 1);
+        assert_eq!(
+            german.format("broadcast-targets", Some(&args)).as_deref(),
+            Some("Eingegebene Befehle laufen auch in einer weiteren Sitzung")
+        );
+        // References to other messages are resolved in the same language
+        assert!(german
+            .format("env-diff-no-snapshot", None)
+            .is_some_and(|text| text.contains("„Umgebungsvariablen mit jedem Befehl speichern“")));
+
+        assert_eq!(Localizer::new("xx").language, DEFAULT_LANGUAGE);
+        assert_eq!(tr("no-such-message"), "no-such-message");
+    }
+}
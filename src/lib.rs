@@ -7,6 +7,12 @@ pub mod mcp;
 pub mod syntax;
 pub mod theme;
 pub mod utils;
+pub mod workflows;
+pub mod sync;
+pub mod share;
+pub mod plugins;
+pub mod scripting;
+pub mod i18n;
 
 pub use config::Config;
 pub use ui::ImmateriumApp;
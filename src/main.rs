@@ -1,33 +1,57 @@
 use anyhow::Result;
+use immaterium::utils::logging;
 use immaterium::{Config, ImmateriumApp};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 fn main() -> Result<()> {
-    // Initialize logging
+    // Initialize logging: stderr, plus a file in the data directory for
+    // anyone who launched from a desktop icon (shown in Help > Logs)
+    let log_file = Config::data_dir()
+        .and_then(|dir| logging::init(&dir.join("logs")))
+        .map_err(|e| eprintln!("Logging to stderr only: {:#}", e))
+        .ok();
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "immaterium=debug,warn".into()),
         )
         .with(tracing_subscriber::fmt::layer())
+        .with(log_file.map(|log_file| {
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(move || log_file.clone())
+        }))
         .init();
 
     tracing::info!("Starting Immaterium Terminal");
 
+    // Crash reports go to the data directory and are shown on the next launch
+    if let Ok(data_dir) = Config::data_dir() {
+        immaterium::core::crash::install_panic_hook(data_dir.join("crashes"));
+    }
+
     // Load configuration
     let config = Config::load()?;
     tracing::info!("Configuration loaded successfully");
 
     // Set up eframe options
+    let window = &config.window;
+    let mut viewport = egui::ViewportBuilder::default()
+        .with_inner_size(window.size.unwrap_or([1200.0, 800.0]))
+        .with_min_inner_size([800.0, 600.0])
+        .with_maximized(window.maximized)
+        .with_fullscreen(window.fullscreen)
+        .with_icon(
+            eframe::icon_data::from_png_bytes(include_bytes!("../assets/icon.png"))
+                .unwrap_or_default(),
+        );
+    if let Some(position) = window.position {
+        viewport = viewport.with_position(position);
+    }
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([1200.0, 800.0])
-            .with_min_inner_size([800.0, 600.0])
-            .with_icon(
-                eframe::icon_data::from_png_bytes(include_bytes!("../assets/icon.png"))
-                    .unwrap_or_default(),
-            ),
-        persist_window: true,
+        viewport,
+        // eframe's own window persistence would override the layout from config
+        persist_window: window.size.is_none(),
         ..Default::default()
     };
 
@@ -0,0 +1,168 @@
+use super::{Plugin, PluginAction, PluginManifest, PluginPanel, PluginRenderer, PluginRequest, PluginResponse};
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+const MANIFEST_FILE: &str = "plugin.toml";
+
+/// How long a plugin may take to answer one request
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Plugins loaded from one subdirectory each of a plugins directory
+pub struct PluginHost {
+    dir: PathBuf,
+    plugins: Vec<Plugin>,
+}
+
+impl PluginHost {
+    /// Plugins in the config directory (`<config>/plugins`)
+    pub fn open_default() -> Option<Self> {
+        let dirs = directories::ProjectDirs::from("com", "immaterium", "immaterium")?;
+        Some(Self::open(dirs.config_dir().join("plugins")))
+    }
+
+    pub fn open(dir: PathBuf) -> Self {
+        let mut host = Self {
+            dir,
+            plugins: Vec::new(),
+        };
+        host.reload();
+        host
+    }
+
+    pub fn plugins(&self) -> &[Plugin] {
+        &self.plugins
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Re-read every `<dir>/<plugin>/plugin.toml`, sorted by plugin name
+    pub fn reload(&mut self) {
+        self.plugins.clear();
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return;
+        };
+
+        for dir in entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()) {
+            let path = dir.join(MANIFEST_FILE);
+            if !path.exists() {
+                continue;
+            }
+            let manifest = std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|content| toml::from_str::<PluginManifest>(&content).map_err(Into::into));
+            match manifest {
+                Ok(manifest) => self.plugins.push(Plugin { manifest, dir }),
+                Err(e) => tracing::warn!("Failed to load plugin {:?}: {}", path, e),
+            }
+        }
+        self.plugins.sort_by_key(|p| p.manifest.name.to_lowercase());
+        tracing::info!("Loaded {} plugins", self.plugins.len());
+    }
+
+    pub fn actions(&self) -> impl Iterator<Item = (&Plugin, &PluginAction)> {
+        self.plugins
+            .iter()
+            .flat_map(|p| p.manifest.actions.iter().map(move |a| (p, a)))
+    }
+
+    pub fn panels(&self) -> impl Iterator<Item = (&Plugin, &PluginPanel)> {
+        self.plugins
+            .iter()
+            .flat_map(|p| p.manifest.panels.iter().map(move |panel| (p, panel)))
+    }
+
+    /// The first renderer that handles blocks running `command`
+    pub fn renderer_for(&self, command: &str) -> Option<(&Plugin, &PluginRenderer)> {
+        self.plugins
+            .iter()
+            .flat_map(|p| p.manifest.renderers.iter().map(move |r| (p, r)))
+            .find(|(_, renderer)| renderer.matches(command))
+    }
+}
+
+/// Run the plugin with `request` on stdin and parse its reply from stdout
+pub async fn invoke(plugin: &Plugin, request: &PluginRequest) -> Result<PluginResponse> {
+    let manifest = &plugin.manifest;
+    let local = plugin.dir.join(&manifest.command);
+    let program = if manifest.command.contains('/') && local.exists() {
+        local
+    } else {
+        PathBuf::from(&manifest.command)
+    };
+
+    let mut child = tokio::process::Command::new(&program)
+        .args(&manifest.args)
+        .current_dir(&plugin.dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to start plugin '{}'", manifest.name))?;
+
+    let input = serde_json::to_vec(request)?;
+    let mut stdin = child.stdin.take().context("Plugin stdin unavailable")?;
+    let writer = async move {
+        stdin.write_all(&input).await?;
+        stdin.shutdown().await
+    };
+
+    let (written, output) = tokio::time::timeout(TIMEOUT, async {
+        tokio::join!(writer, child.wait_with_output())
+    })
+    .await
+    .with_context(|| format!("Plugin '{}' timed out", manifest.name))?;
+    let output = output.with_context(|| format!("Plugin '{}' failed", manifest.name))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("Plugin '{}' exited with {}: {}", manifest.name, output.status, stderr.trim());
+    }
+    // A plugin may exit without reading its input; only its reply matters
+    if let Err(e) = written {
+        tracing::debug!("Plugin '{}' did not read its request: {}", manifest.name, e);
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.trim().is_empty() {
+        return Ok(PluginResponse::default());
+    }
+    serde_json::from_str(stdout.trim())
+        .with_context(|| format!("Plugin '{}' replied with invalid JSON", manifest.name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Block;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_load_and_invoke() {
+        let dir = tempfile::tempdir().unwrap();
+        let plugin_dir = dir.path().join("echo");
+        std::fs::create_dir_all(&plugin_dir).unwrap();
+        std::fs::write(
+            plugin_dir.join(MANIFEST_FILE),
+            "name = \"echo\"\ncommand = \"sh\"\nargs = [\"-c\", \"cat >/dev/null; echo '{\\\"copy\\\": \\\"hi\\\"}'\"]\n\
+             [[actions]]\nid = \"copy\"\nlabel = \"Copy hi\"\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(dir.path().join("broken")).unwrap();
+        std::fs::write(dir.path().join("broken").join(MANIFEST_FILE), "name = ").unwrap();
+
+        let host = PluginHost::open(dir.path().to_path_buf());
+        assert_eq!(host.plugins().len(), 1);
+        let (plugin, action) = host.actions().next().unwrap();
+
+        let block = Block::new("ls".to_string(), PathBuf::from("/tmp"));
+        let request = PluginRequest::Action { action: action.id.clone(), block: (&block).into() };
+        let response = invoke(plugin, &request).await.unwrap();
+        assert_eq!(response.copy.as_deref(), Some("hi"));
+    }
+}
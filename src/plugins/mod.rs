@@ -0,0 +1,103 @@
+// Third-party plugins: a TOML manifest plus an external process
+// Plugins add block context-menu actions, side panels and output renderers;
+// each request runs the plugin's command with one JSON message on stdin
+
+pub mod host;
+pub mod protocol;
+
+pub use host::{invoke, PluginHost};
+pub use protocol::{BlockPayload, PluginRequest, PluginResponse, SessionPayload};
+
+use regex::Regex;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// Contents of a plugin's `plugin.toml`
+#[derive(Debug, Clone, Deserialize)]
+pub struct PluginManifest {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Program to run, relative to the plugin directory or on PATH
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub actions: Vec<PluginAction>,
+    #[serde(default)]
+    pub panels: Vec<PluginPanel>,
+    #[serde(default)]
+    pub renderers: Vec<PluginRenderer>,
+}
+
+/// Entry in the block context menu
+#[derive(Debug, Clone, Deserialize)]
+pub struct PluginAction {
+    pub id: String,
+    pub label: String,
+}
+
+/// Side panel whose text the plugin provides for the current session
+#[derive(Debug, Clone, Deserialize)]
+pub struct PluginPanel {
+    pub id: String,
+    pub title: String,
+}
+
+/// Replaces the output of finished blocks whose command matches one of the patterns
+#[derive(Debug, Clone, Deserialize)]
+pub struct PluginRenderer {
+    pub id: String,
+    /// Regular expressions matched against the block command
+    pub commands: Vec<String>,
+}
+
+impl PluginRenderer {
+    pub fn matches(&self, command: &str) -> bool {
+        self.commands
+            .iter()
+            .any(|pattern| Regex::new(pattern).is_ok_and(|re| re.is_match(command)))
+    }
+}
+
+/// A loaded plugin and the directory it was loaded from
+#[derive(Debug, Clone)]
+pub struct Plugin {
+    pub manifest: PluginManifest,
+    pub dir: PathBuf,
+}
+
+impl Plugin {
+    pub fn name(&self) -> &str {
+        &self.manifest.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest: PluginManifest = toml::from_str(
+            r#"
+            name = "kubectl helper"
+            command = "./plugin.py"
+
+            [[actions]]
+            id = "describe"
+            label = "Describe pod"
+
+            [[renderers]]
+            id = "table"
+            commands = ["^kubectl get "]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.actions[0].label, "Describe pod");
+        assert!(manifest.panels.is_empty());
+        assert!(manifest.renderers[0].matches("kubectl get pods -A"));
+        assert!(!manifest.renderers[0].matches("kubectl logs web"));
+    }
+}
@@ -0,0 +1,101 @@
+use crate::core::{Block, Session};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Largest block output sent to a plugin; longer output keeps its end
+const MAX_OUTPUT_BYTES: usize = 256 * 1024;
+
+/// Most recent blocks sent with a panel request
+const PANEL_BLOCKS: usize = 50;
+
+/// Message written to the plugin's stdin
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PluginRequest {
+    Action { action: String, block: BlockPayload },
+    Panel { panel: String, session: SessionPayload },
+    Render { renderer: String, block: BlockPayload },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockPayload {
+    pub id: Uuid,
+    pub command: String,
+    pub output: String,
+    pub exit_code: Option<i32>,
+    pub working_directory: PathBuf,
+}
+
+impl From<&Block> for BlockPayload {
+    fn from(block: &Block) -> Self {
+        let output = block.output.to_string();
+        let output = match output.len().checked_sub(MAX_OUTPUT_BYTES) {
+            Some(excess) => {
+                let start = (excess..output.len()).find(|&i| output.is_char_boundary(i)).unwrap_or(excess);
+                output[start..].to_string()
+            }
+            None => output,
+        };
+        Self {
+            id: block.id,
+            command: block.command.clone(),
+            output,
+            exit_code: block.exit_code,
+            working_directory: block.metadata.working_directory.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionPayload {
+    pub id: Uuid,
+    pub name: String,
+    pub working_directory: PathBuf,
+    /// The most recent blocks, oldest first
+    pub blocks: Vec<BlockPayload>,
+}
+
+impl SessionPayload {
+    pub fn new(session: &Session, blocks: &[Block]) -> Self {
+        let recent = &blocks[blocks.len().saturating_sub(PANEL_BLOCKS)..];
+        Self {
+            id: session.id,
+            name: session.name.clone(),
+            working_directory: session.working_directory.clone(),
+            blocks: recent.iter().map(BlockPayload::from).collect(),
+        }
+    }
+}
+
+/// Reply read from the plugin's stdout. Every field is optional.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct PluginResponse {
+    /// Panel contents, or the rendered output for a renderer
+    pub text: Option<String>,
+    /// Put this on the clipboard
+    pub copy: Option<String>,
+    /// Place this in the command input for the user to run
+    pub insert_command: Option<String>,
+    /// Short status to show the user
+    pub message: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_and_response_format() {
+        let block = Block::new("kubectl get pods".to_string(), PathBuf::from("/srv"));
+        let request = PluginRequest::Action { action: "describe".to_string(), block: (&block).into() };
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["type"], "action");
+        assert_eq!(json["block"]["command"], "kubectl get pods");
+
+        let response: PluginResponse = serde_json::from_str(r#"{"insert_command": "kubectl describe pod web"}"#).unwrap();
+        assert_eq!(response.insert_command.as_deref(), Some("kubectl describe pod web"));
+        assert!(response.text.is_none());
+    }
+}
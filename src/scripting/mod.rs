@@ -0,0 +1,274 @@
+// User scripting hooks in Rhai, loaded from `<config>/init.rhai`
+// A script defines any of these functions; missing hooks are skipped and
+// script errors are logged without affecting the command:
+//
+//   fn on_command_submit(command, cwd)
+//       Return a string to run instead, `false` to cancel, or nothing.
+//   fn on_block_complete(block)
+//       `block` is a map with command, output, exit_code, cwd, duration_ms
+//       and tags. Return a tag, an array of tags, or a map with any of
+//       `tags`, `pinned` and `collapsed`.
+//   fn transform_output(command, output)
+//       Called once when a block finishes; return a string to replace the
+//       stored output, or nothing to keep it.
+//
+// Example: tag failed kubectl commands
+//
+//   fn on_block_complete(block) {
+//       if block.exit_code != 0 && block.command.starts_with("kubectl") { "k8s-failure" }
+//   }
+
+use crate::core::Block;
+use anyhow::{anyhow, Context, Result};
+use rhai::{Array, Dynamic, Engine, FuncArgs, Map, Scope, AST};
+use std::path::{Path, PathBuf};
+
+const SCRIPT_FILE: &str = "init.rhai";
+
+/// Operations a single hook call may run before it is aborted
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Largest output passed to `on_block_complete` and `transform_output`
+const MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+
+/// Changes requested by `on_block_complete`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlockUpdate {
+    pub tags: Vec<String>,
+    pub pinned: Option<bool>,
+    pub collapsed: Option<bool>,
+}
+
+impl BlockUpdate {
+    pub fn apply(self, block: &mut Block) {
+        for tag in self.tags {
+            if !block.tags.contains(&tag) {
+                block.tags.push(tag);
+            }
+        }
+        if let Some(pinned) = self.pinned {
+            block.is_pinned = pinned;
+        }
+        if let Some(collapsed) = self.collapsed {
+            block.is_collapsed = collapsed;
+        }
+    }
+}
+
+pub struct ScriptEngine {
+    engine: Engine,
+    ast: Option<AST>,
+    path: PathBuf,
+}
+
+impl ScriptEngine {
+    /// `<config>/init.rhai`
+    pub fn default_path() -> Option<PathBuf> {
+        let dirs = directories::ProjectDirs::from("com", "immaterium", "immaterium")?;
+        Some(dirs.config_dir().join(SCRIPT_FILE))
+    }
+
+    /// Load the script at `path`; a missing file loads no hooks
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let mut engine = Self {
+            engine: new_engine(),
+            ast: None,
+            path,
+        };
+        engine.reload()?;
+        Ok(engine)
+    }
+
+    #[cfg(test)]
+    fn from_source(source: &str) -> Result<Self> {
+        let engine = new_engine();
+        let ast = engine.compile(source).map_err(|e| anyhow!("{}", e))?;
+        Ok(Self { engine, ast: Some(ast), path: PathBuf::new() })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Re-read the script. On error the previously loaded hooks are kept.
+    pub fn reload(&mut self) -> Result<()> {
+        if !self.path.exists() {
+            self.ast = None;
+            return Ok(());
+        }
+        let source = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        let ast = self
+            .engine
+            .compile(&source)
+            .map_err(|e| anyhow!("{}: {}", self.path.display(), e))?;
+        self.ast = Some(ast);
+        Ok(())
+    }
+
+    /// Names of the hooks the script defines
+    pub fn hooks(&self) -> Vec<String> {
+        self.ast
+            .iter()
+            .flat_map(|ast| ast.iter_functions())
+            .map(|f| f.name.to_string())
+            .filter(|name| ["on_command_submit", "on_block_complete", "transform_output"].contains(&name.as_str()))
+            .collect()
+    }
+
+    /// The command to run after `on_command_submit`, or None if the script cancelled it
+    pub fn on_command_submit(&self, command: &str, cwd: &Path) -> Option<String> {
+        let args = (command.to_string(), cwd.to_string_lossy().to_string());
+        let Some(result) = self.call("on_command_submit", args) else {
+            return Some(command.to_string());
+        };
+        if result.as_bool() == Ok(false) {
+            tracing::info!("Script cancelled command: {}", command);
+            return None;
+        }
+        match result.into_string() {
+            Ok(replacement) if !replacement.trim().is_empty() => Some(replacement),
+            _ => Some(command.to_string()),
+        }
+    }
+
+    pub fn on_block_complete(&self, block: &Block) -> BlockUpdate {
+        let Some(result) = self.call("on_block_complete", (block_map(block),)) else {
+            return BlockUpdate::default();
+        };
+        if result.is_map() {
+            let map = result.cast::<Map>();
+            BlockUpdate {
+                tags: map.get("tags").cloned().map(into_tags).unwrap_or_default(),
+                pinned: map.get("pinned").and_then(|v| v.as_bool().ok()),
+                collapsed: map.get("collapsed").and_then(|v| v.as_bool().ok()),
+            }
+        } else {
+            BlockUpdate { tags: into_tags(result), ..Default::default() }
+        }
+    }
+
+    /// Replacement output for a finished block, if the script provides one
+    pub fn transform_output(&self, command: &str, output: &str) -> Option<String> {
+        if output.len() > MAX_OUTPUT_BYTES {
+            return None;
+        }
+        let args = (command.to_string(), output.to_string());
+        self.call("transform_output", args)?.into_string().ok()
+    }
+
+    /// Call `name` if the script defines it; errors are logged and return None
+    fn call(&self, name: &str, args: impl FuncArgs) -> Option<Dynamic> {
+        let ast = self.ast.as_ref()?;
+        if !ast.iter_functions().any(|f| f.name == name) {
+            return None;
+        }
+        match self.engine.call_fn::<Dynamic>(&mut Scope::new(), ast, name, args) {
+            Ok(result) => Some(result),
+            Err(e) => {
+                tracing::warn!("Script hook {} failed: {}", name, e);
+                None
+            }
+        }
+    }
+}
+
+fn new_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(64);
+    engine.on_print(|text| tracing::info!("[script] {}", text));
+    engine.on_debug(|text, _, pos| tracing::debug!("[script] {:?} {}", pos, text));
+    engine
+}
+
+fn block_map(block: &Block) -> Map {
+    let output = block.output.to_string();
+    let mut map = Map::new();
+    map.insert("command".into(), block.command.clone().into());
+    map.insert(
+        "output".into(),
+        if output.len() > MAX_OUTPUT_BYTES { Dynamic::UNIT } else { output.into() },
+    );
+    map.insert("exit_code".into(), block.exit_code.map_or(Dynamic::UNIT, |c| (c as i64).into()));
+    map.insert("cwd".into(), block.metadata.working_directory.to_string_lossy().to_string().into());
+    map.insert(
+        "duration_ms".into(),
+        block.metadata.duration.map_or(Dynamic::UNIT, |d| (d.as_millis() as i64).into()),
+    );
+    let tags: Array = block.tags.iter().cloned().map(Dynamic::from).collect();
+    map.insert("tags".into(), tags.into());
+    map
+}
+
+/// A single tag or an array of tags; anything else is ignored
+fn into_tags(value: Dynamic) -> Vec<String> {
+    if value.is_array() {
+        value
+            .cast::<Array>()
+            .into_iter()
+            .filter_map(|v| v.into_string().ok())
+            .filter(|tag| !tag.trim().is_empty())
+            .collect()
+    } else {
+        value.into_string().ok().filter(|tag| !tag.trim().is_empty()).into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_submit_hook() {
+        let scripts = ScriptEngine::from_source(
+            r#"
+            fn on_command_submit(command, cwd) {
+                if command == "rm -rf /" { return false; }
+                if command.starts_with("k ") { return "kubectl " + command.sub_string(2); }
+            }
+            "#,
+        )
+        .unwrap();
+        let cwd = Path::new("/tmp");
+        assert_eq!(scripts.on_command_submit("rm -rf /", cwd), None);
+        assert_eq!(scripts.on_command_submit("k get pods", cwd).as_deref(), Some("kubectl get pods"));
+        assert_eq!(scripts.on_command_submit("ls", cwd).as_deref(), Some("ls"));
+        assert_eq!(scripts.transform_output("ls", "a\nb\n"), None);
+    }
+
+    #[test]
+    fn test_block_complete_tags_failed_kubectl() {
+        let scripts = ScriptEngine::from_source(
+            r#"
+            fn on_block_complete(block) {
+                if block.exit_code != 0 && block.command.starts_with("kubectl") {
+                    #{ tags: ["k8s", "failed"], pinned: true }
+                }
+            }
+            fn transform_output(command, output) { output.replace("secret", "***"); output }
+            "#,
+        )
+        .unwrap();
+
+        let mut block = Block::new("kubectl apply -f app.yaml".to_string(), PathBuf::from("/srv"));
+        block.start_execution();
+        block.complete_execution(1);
+        let update = scripts.on_block_complete(&block);
+        assert_eq!(update.tags, vec!["k8s", "failed"]);
+        update.apply(&mut block);
+        assert!(block.is_pinned);
+
+        let mut ok = Block::new("kubectl get pods".to_string(), PathBuf::from("/srv"));
+        ok.complete_execution(0);
+        assert_eq!(scripts.on_block_complete(&ok), BlockUpdate::default());
+
+        assert_eq!(scripts.transform_output("env", "token=secret").as_deref(), Some("token=***"));
+    }
+
+    #[test]
+    fn test_runaway_script_is_stopped() {
+        let scripts = ScriptEngine::from_source("fn on_command_submit(command, cwd) { loop {} }").unwrap();
+        assert_eq!(scripts.on_command_submit("ls", Path::new("/")).as_deref(), Some("ls"));
+    }
+}
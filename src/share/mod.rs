@@ -0,0 +1,135 @@
+// Sharing blocks and sessions as secret gists or pastes
+// Exports are redacted before upload; the user reviews the result first
+
+pub mod redact;
+
+pub use redact::{redact, Redaction, Redactor};
+
+use crate::config::ShareConfig;
+use crate::t;
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+
+const GIST_API: &str = "https://api.github.com/gists";
+
+/// Where a share is uploaded
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShareTarget {
+    /// A secret (unlisted) GitHub Gist
+    Gist { token: String },
+    /// An endpoint that takes the raw text as a POST body and answers with its URL
+    Paste { url: String },
+}
+
+impl ShareTarget {
+    /// Targets with the configuration they need, Gist first
+    pub fn available(config: &ShareConfig) -> Vec<Self> {
+        let mut targets = Vec::new();
+        if let Some(token) = config.github_token.as_ref().filter(|t| !t.is_empty()) {
+            targets.push(Self::Gist { token: token.clone() });
+        }
+        if let Some(url) = config.paste_url.as_ref().filter(|u| !u.is_empty()) {
+            targets.push(Self::Paste { url: url.clone() });
+        }
+        targets
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Self::Gist { .. } => t!("share-target-gist"),
+            Self::Paste { .. } => t!("share-target-paste"),
+        }
+    }
+}
+
+/// Upload `content` as `file_name` and return its URL
+pub async fn upload(target: &ShareTarget, file_name: &str, description: &str, content: &str) -> Result<String> {
+    let client = reqwest::Client::new();
+    match target {
+        ShareTarget::Gist { token } => {
+            let body = json!({
+                "description": description,
+                "public": false,
+                "files": { file_name: { "content": content } },
+            });
+            let response = client
+                .post(GIST_API)
+                .bearer_auth(token)
+                .header("Accept", "application/vnd.github+json")
+                .header("User-Agent", concat!("immaterium/", env!("CARGO_PKG_VERSION")))
+                .json(&body)
+                .send()
+                .await
+                .context("Failed to reach GitHub")?;
+
+            let status = response.status();
+            let value: Value = response.json().await.context("Unexpected response from GitHub")?;
+            if !status.is_success() {
+                let message = value["message"].as_str().unwrap_or("unknown error");
+                bail!("GitHub rejected the gist ({}): {}", status, message);
+            }
+            value["html_url"]
+                .as_str()
+                .map(str::to_string)
+                .context("GitHub did not return a gist URL")
+        }
+        ShareTarget::Paste { url } => {
+            let response = client
+                .post(url)
+                .header("Content-Type", "text/plain; charset=utf-8")
+                .body(content.to_string())
+                .send()
+                .await
+                .context("Failed to reach the paste service")?;
+
+            let status = response.status();
+            let text = response.text().await?;
+            if !status.is_success() {
+                bail!("Paste service returned {}", status);
+            }
+            paste_url(&text).context("Paste service did not return a URL")
+        }
+    }
+}
+
+/// The URL in a paste service response: the body itself, or a `url`/`link`
+/// field when it answers with JSON
+fn paste_url(response: &str) -> Option<String> {
+    let response = response.trim();
+    if let Ok(value) = serde_json::from_str::<Value>(response) {
+        return ["url", "link"]
+            .iter()
+            .find_map(|key| value[key].as_str())
+            .map(str::to_string);
+    }
+    response
+        .lines()
+        .next()
+        .filter(|line| line.starts_with("http://") || line.starts_with("https://"))
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paste_url() {
+        assert_eq!(paste_url("https://0x0.st/abc.md\n").as_deref(), Some("https://0x0.st/abc.md"));
+        assert_eq!(
+            paste_url(r#"{"url": "https://paste.example.com/x"}"#).as_deref(),
+            Some("https://paste.example.com/x")
+        );
+        assert!(paste_url("error: too large").is_none());
+    }
+
+    #[test]
+    fn test_available_targets() {
+        let config = ShareConfig {
+            github_token: Some("ghp_x".to_string()),
+            paste_url: Some(String::new()),
+        };
+        assert_eq!(ShareTarget::available(&config), vec![ShareTarget::Gist { token: "ghp_x".to_string() }]);
+        assert!(ShareTarget::available(&ShareConfig::default()).is_empty());
+    }
+}
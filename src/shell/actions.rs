@@ -0,0 +1,158 @@
+use crate::config::BlockActionConfig;
+use crate::core::Block;
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// What a block action needs from the block, copied so the action can run
+/// in the background
+#[derive(Debug, Clone)]
+pub struct ActionInput {
+    pub block_id: uuid::Uuid,
+    pub command: String,
+    pub output: String,
+    pub exit_code: Option<i32>,
+    pub cwd: PathBuf,
+}
+
+impl From<&Block> for ActionInput {
+    fn from(block: &Block) -> Self {
+        Self {
+            block_id: block.id,
+            command: block.command.clone(),
+            output: block.output.to_string(),
+            exit_code: block.exit_code,
+            cwd: block.metadata.working_directory.clone(),
+        }
+    }
+}
+
+/// Run a user-defined block action and return its stdout.
+///
+/// The command sees `IMMATERIUM_COMMAND`, `IMMATERIUM_EXIT_CODE`,
+/// `IMMATERIUM_CWD` and `IMMATERIUM_OUTPUT_FILE`, a file holding the block
+/// output. Only the user can read the file, and it is removed once the
+/// command returns or is stopped after the action's timeout.
+pub async fn run_block_action(action: &BlockActionConfig, input: &ActionInput) -> Result<String> {
+    let output_file = tempfile::Builder::new()
+        .prefix(&format!("immaterium-block-{}-", input.block_id))
+        .suffix(".txt")
+        .tempfile()
+        .context("Failed to create the output file")?;
+    tokio::fs::write(output_file.path(), &input.output)
+        .await
+        .with_context(|| format!("Failed to write {}", output_file.path().display()))?;
+
+    let mut process = tokio::process::Command::new("sh");
+    process
+        .arg("-c")
+        .arg(&action.command)
+        .env("IMMATERIUM_COMMAND", &input.command)
+        .env("IMMATERIUM_CWD", &input.cwd)
+        .env("IMMATERIUM_OUTPUT_FILE", output_file.path())
+        .stdin(if action.stdin { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(code) = input.exit_code {
+        process.env("IMMATERIUM_EXIT_CODE", code.to_string());
+    }
+    if input.cwd.is_dir() {
+        process.current_dir(&input.cwd);
+    }
+
+    let mut child = process
+        .spawn()
+        .with_context(|| format!("Failed to run '{}'", action.label))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let output = input.output.clone();
+        // A command that exits without reading its input is not an error
+        tokio::spawn(async move {
+            let _ = stdin.write_all(output.as_bytes()).await;
+        });
+    }
+
+    let output = if action.timeout_secs > 0 {
+        let timeout = Duration::from_secs(action.timeout_secs);
+        match tokio::time::timeout(timeout, child.wait_with_output()).await {
+            Ok(output) => output?,
+            Err(_) => bail!("'{}' was stopped after {}s", action.label, action.timeout_secs),
+        }
+    } else {
+        child.wait_with_output().await?
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("'{}' exited with {}: {}", action.label, output.status, stderr.trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BlockActionResult;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_action_receives_block() {
+        let mut block = Block::new("printf".to_string(), std::env::temp_dir());
+        block.append_output("b\na\n".to_string());
+        block.complete_execution(0);
+        let input = ActionInput::from(&block);
+
+        let sort = BlockActionConfig {
+            label: "Sort".to_string(),
+            command: "sort".to_string(),
+            stdin: true,
+            result: BlockActionResult::Block,
+            timeout_secs: 60,
+        };
+        assert_eq!(run_block_action(&sort, &input).await.unwrap(), "a\nb\n");
+
+        let env = BlockActionConfig {
+            label: "Describe".to_string(),
+            command: r#"echo "$IMMATERIUM_COMMAND $IMMATERIUM_EXIT_CODE $(wc -l < "$IMMATERIUM_OUTPUT_FILE")""#.to_string(),
+            stdin: false,
+            result: BlockActionResult::Status,
+            timeout_secs: 60,
+        };
+        assert_eq!(run_block_action(&env, &input).await.unwrap().trim(), "printf 0 2");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_output_file_is_private_and_removed() {
+        let input = ActionInput::from(&Block::new("true".to_string(), std::env::temp_dir()));
+        let action = BlockActionConfig {
+            label: "Inspect".to_string(),
+            command: r#"ls -l "$IMMATERIUM_OUTPUT_FILE" | cut -c1-10; echo "$IMMATERIUM_OUTPUT_FILE""#.to_string(),
+            stdin: false,
+            result: BlockActionResult::Status,
+            timeout_secs: 60,
+        };
+        let output = run_block_action(&action, &input).await.unwrap();
+        let (mode, path) = output.trim().split_once('\n').unwrap();
+        assert_eq!(mode, "-rw-------");
+        assert!(!std::path::Path::new(path).exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_action_timeout() {
+        let input = ActionInput::from(&Block::new("true".to_string(), std::env::temp_dir()));
+        let action = BlockActionConfig {
+            label: "Hang".to_string(),
+            command: "sleep 30".to_string(),
+            stdin: false,
+            result: BlockActionResult::None,
+            timeout_secs: 1,
+        };
+        let started = std::time::Instant::now();
+        let error = run_block_action(&action, &input).await.unwrap_err();
+        assert!(error.to_string().contains("stopped after 1s"));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
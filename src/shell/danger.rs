@@ -0,0 +1,104 @@
+/// Words that run the command after them, skipped when matching
+const WRAPPERS: &[&str] = &["sudo", "env", "command", "exec", "time", "nohup", "doas"];
+
+/// The first of `patterns` that `command` runs, e.g. `rm -rf` for
+/// `cd /tmp && sudo rm -fr build`. Each step of the command line is checked
+/// on its own, after `sudo`, `env` and `VAR=value`. Short flags match in any
+/// order or grouping, long flags exactly. Quoting isn't parsed, so this is a
+/// safety net rather than a guarantee.
+pub fn matching_pattern<'a>(command: &str, patterns: &'a [String]) -> Option<&'a str> {
+    let steps = split_steps(command);
+    patterns
+        .iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .find(|pattern| steps.iter().any(|step| step_matches(step, pattern)))
+}
+
+/// Words of each command separated by `;`, `&&`, `||`, `|` or a newline
+fn split_steps(command: &str) -> Vec<Vec<&str>> {
+    command
+        .split(['\n', ';', '|', '&'])
+        .map(|step| {
+            let words: Vec<&str> = step.split_whitespace().collect();
+            let start = words
+                .iter()
+                .position(|w| !WRAPPERS.contains(w) && !is_assignment(w))
+                .unwrap_or(words.len());
+            words[start..].to_vec()
+        })
+        .filter(|words| !words.is_empty())
+        .collect()
+}
+
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=')
+        .is_some_and(|(name, _)| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+}
+
+/// The step runs the pattern's command, its other words follow in order
+/// (so `kubectl -n prod delete` is `kubectl delete`), and it has every flag
+fn step_matches(step: &[&str], pattern: &str) -> bool {
+    let (flags, words): (Vec<&str>, Vec<&str>) = pattern.split_whitespace().partition(|w| w.starts_with('-'));
+    let Some((program, subcommands)) = words.split_first() else {
+        return false;
+    };
+    if command_name(step[0]) != *program {
+        return false;
+    }
+    let mut rest = step[1..].iter().filter(|w| !w.starts_with('-'));
+    subcommands.iter().all(|expected| rest.any(|word| word == expected))
+        && flags.iter().all(|flag| has_flag(step, flag))
+}
+
+/// `/bin/rm` runs `rm`
+fn command_name(word: &str) -> &str {
+    word.rsplit('/').next().unwrap_or(word)
+}
+
+fn has_flag(step: &[&str], flag: &str) -> bool {
+    if flag.starts_with("--") {
+        return step.iter().any(|w| *w == flag || w.strip_prefix(flag).is_some_and(|rest| rest.starts_with('=')));
+    }
+    // Short flags may be grouped (-rf, -fr) or given apart (-r -f); -R is -r
+    let short: String = step
+        .iter()
+        .filter(|w| w.starts_with('-') && !w.starts_with("--"))
+        .flat_map(|w| w.chars().skip(1))
+        .collect::<String>()
+        .to_lowercase();
+    flag.chars().skip(1).all(|c| short.contains(c.to_ascii_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns() -> Vec<String> {
+        ["rm -rf", "git push --force", "terraform destroy", "kubectl delete"]
+            .iter()
+            .map(|p| p.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_matching_pattern() {
+        let patterns = patterns();
+        assert_eq!(matching_pattern("rm -rf build", &patterns), Some("rm -rf"));
+        assert_eq!(matching_pattern("rm -r -f build", &patterns), Some("rm -rf"));
+        assert_eq!(matching_pattern("sudo /bin/rm -Rf /var/cache", &patterns), Some("rm -rf"));
+        assert_eq!(matching_pattern("cd infra && TF_LOG=1 terraform destroy", &patterns), Some("terraform destroy"));
+        assert_eq!(matching_pattern("git push origin main --force", &patterns), Some("git push --force"));
+        assert_eq!(matching_pattern("kubectl -n prod delete pod api", &patterns), Some("kubectl delete"));
+    }
+
+    #[test]
+    fn test_safe_commands() {
+        let patterns = patterns();
+        assert_eq!(matching_pattern("rm -r build", &patterns), None);
+        assert_eq!(matching_pattern("git push --force-with-lease", &patterns), None);
+        assert_eq!(matching_pattern("echo rm -rf", &patterns), None);
+        assert_eq!(matching_pattern("terraform plan -destroy", &patterns), None);
+        assert_eq!(matching_pattern("rm -rf x", &[" ".to_string()]), None);
+    }
+}
@@ -1,7 +1,10 @@
+use super::osc::{self, OscParser, OscSegment, ShellEvent};
 use anyhow::{Context, Result};
-use portable_pty::{CommandBuilder, NativePtySystem, PtySize, PtySystem};
-use std::io::Read;
+use portable_pty::{ChildKiller, CommandBuilder, MasterPty, NativePtySystem, PtySize, PtySystem};
+use std::collections::HashMap;
+use std::io::{Read, Write};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc;
 use tokio::task;
 
@@ -9,12 +12,75 @@ use tokio::task;
 pub enum OutputLine {
     Stdout(String),
     Stderr(String),
+    /// Directory the shell was in when the command finished (OSC 7)
+    WorkingDirectory(PathBuf),
     Exit(i32),
 }
 
+/// Input for a running command's terminal
+#[derive(Debug, Clone, PartialEq)]
+pub enum PtyInput {
+    /// Bytes typed into the terminal (keys, pasted text)
+    Bytes(Vec<u8>),
+    Resize { rows: u16, cols: u16 },
+    /// Hang up the command and everything it started
+    Terminate,
+}
+
+/// Routes PTY output through the OSC parser. Text before the shell's
+/// output-start marker (e.g. anything ~/.bashrc prints) is held back and
+/// dropped once the marker arrives; shell events become OutputLine values.
+#[derive(Default)]
+struct OutputRouter {
+    parser: OscParser,
+    output_started: bool,
+    preamble: String,
+    reported_exit: Option<i32>,
+}
+
+impl OutputRouter {
+    /// Returns false once the receiver has been dropped
+    fn route(&mut self, text: &str, tx: &mpsc::UnboundedSender<OutputLine>) -> bool {
+        for segment in self.parser.feed(text) {
+            let line = match segment {
+                OscSegment::Text(text) if self.output_started => OutputLine::Stdout(text),
+                OscSegment::Text(text) => {
+                    self.preamble.push_str(&text);
+                    continue;
+                }
+                OscSegment::Event(ShellEvent::OutputStart) => {
+                    self.output_started = true;
+                    self.preamble.clear();
+                    continue;
+                }
+                OscSegment::Event(ShellEvent::CommandFinished(code)) => {
+                    self.reported_exit = code;
+                    continue;
+                }
+                OscSegment::Event(ShellEvent::WorkingDirectory(path)) => OutputLine::WorkingDirectory(path),
+                OscSegment::Event(_) => continue,
+            };
+            if tx.send(line).is_err() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Flush held-back text. If the marker never arrived, nothing is dropped.
+    fn finish(&mut self, tx: &mpsc::UnboundedSender<OutputLine>) {
+        let mut rest = std::mem::take(&mut self.preamble);
+        rest.push_str(&self.parser.finish());
+        if !rest.is_empty() {
+            let _ = tx.send(OutputLine::Stdout(rest));
+        }
+    }
+}
+
 pub struct ShellExecutor {
     shell_path: String,
     working_directory: PathBuf,
+    environment: HashMap<String, String>,
 }
 
 impl ShellExecutor {
@@ -25,6 +91,7 @@ impl ShellExecutor {
         Ok(Self {
             shell_path,
             working_directory,
+            environment: HashMap::new(),
         })
     }
 
@@ -36,31 +103,50 @@ impl ShellExecutor {
         &self.working_directory
     }
 
+    /// Extra environment variables injected into every command (e.g. from the session)
+    pub fn set_environment(&mut self, environment: HashMap<String, String>) {
+        self.environment = environment;
+    }
+
     /// Execute a command and return a channel for streaming output
     pub async fn execute(
         &self,
         command: String,
     ) -> Result<mpsc::UnboundedReceiver<OutputLine>> {
+        let (rx, _input) = self.execute_with_input(command).await?;
+        Ok(rx)
+    }
+
+    /// Execute a command, also returning a sender for writing to its terminal
+    /// and resizing it (used for full-screen programs)
+    pub async fn execute_with_input(
+        &self,
+        command: String,
+    ) -> Result<(mpsc::UnboundedReceiver<OutputLine>, std::sync::mpsc::Sender<PtyInput>)> {
         let (tx, rx) = mpsc::unbounded_channel();
+        let (input_tx, input_rx) = std::sync::mpsc::channel();
         let shell_path = self.shell_path.clone();
         let working_dir = self.working_directory.clone();
+        let environment = self.environment.clone();
 
         // Spawn blocking task for PTY operations
         task::spawn_blocking(move || {
-            if let Err(e) = Self::execute_blocking(shell_path, working_dir, command, tx.clone()) {
+            if let Err(e) = Self::execute_blocking(shell_path, working_dir, environment, command, tx.clone(), input_rx) {
                 tracing::error!("Command execution error: {}", e);
                 let _ = tx.send(OutputLine::Exit(-1));
             }
         });
 
-        Ok(rx)
+        Ok((rx, input_tx))
     }
 
     fn execute_blocking(
         shell_path: String,
         working_dir: PathBuf,
+        environment: HashMap<String, String>,
         command: String,
         tx: mpsc::UnboundedSender<OutputLine>,
+        input_rx: std::sync::mpsc::Receiver<PtyInput>,
     ) -> Result<()> {
         let pty_system = NativePtySystem::default();
 
@@ -79,13 +165,20 @@ impl ShellExecutor {
         cmd.arg("-c");
         
         // Source .bashrc (if it exists) before executing the command
-        // Suppress errors from .bashrc to avoid polluting output
+        // Suppress errors from .bashrc to avoid polluting output. The shell
+        // marks where the command's output starts and reports its exit status
+        // and final directory (OSC 133 / OSC 7).
         let full_command = format!(
-            "[ -f ~/.bashrc ] && source ~/.bashrc 2>/dev/null; {}",
-            command
+            "[ -f ~/.bashrc ] && source ~/.bashrc 2>/dev/null; {}\n{}\n{}",
+            osc::OUTPUT_START_MARKER,
+            command,
+            osc::COMMAND_FINISHED_HOOK
         );
         cmd.arg(&full_command);
         cmd.cwd(&working_dir);
+        for (key, value) in &environment {
+            cmd.env(key, value);
+        }
 
         // Spawn the child process
         let mut child = pair
@@ -98,6 +191,11 @@ impl ShellExecutor {
 
         // Read output from the master
         let mut reader = pair.master.try_clone_reader()?;
+        let writer = pair.master.take_writer()?;
+        let master = Arc::new(Mutex::new(pair.master));
+        let killer = child.clone_killer();
+        Self::spawn_input_thread(writer, master.clone(), killer, child.process_id(), input_rx);
+        let mut router = OutputRouter::default();
         let mut buffer = Vec::new();
         let mut temp_buf = [0u8; 8192];
 
@@ -111,18 +209,24 @@ impl ShellExecutor {
                     while let Some(newline_pos) = buffer.iter().position(|&b| b == b'\n') {
                         let line_bytes = buffer.drain(..=newline_pos).collect::<Vec<_>>();
                         if let Ok(line) = String::from_utf8(line_bytes) {
-                            if tx.send(OutputLine::Stdout(line)).is_err() {
+                            if !router.route(&line, &tx) {
                                 return Ok(()); // Receiver dropped
                             }
                         }
                     }
 
-                    // Send partial line if buffer is getting large
-                    if buffer.len() > 4096 {
-                        if let Ok(line) = String::from_utf8(buffer.drain(..).collect()) {
-                            let _ = tx.send(OutputLine::Stdout(line));
-                        } else {
-                            buffer.clear();
+                    // Send the rest without waiting for the line to end:
+                    // progress bars, prompts and full-screen programs redraw
+                    // without newlines. Only an incomplete UTF-8 sequence at
+                    // the end is held back for the next read.
+                    let complete = match std::str::from_utf8(&buffer) {
+                        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                        _ => buffer.len(),
+                    };
+                    if complete > 0 {
+                        let partial = buffer.drain(..complete).collect::<Vec<_>>();
+                        if !router.route(&String::from_utf8_lossy(&partial), &tx) {
+                            return Ok(()); // Receiver dropped
                         }
                     }
                 }
@@ -140,22 +244,82 @@ impl ShellExecutor {
         // Send any remaining buffer
         if !buffer.is_empty() {
             if let Ok(line) = String::from_utf8(buffer) {
-                let _ = tx.send(OutputLine::Stdout(line));
+                router.route(&line, &tx);
             }
         }
+        router.finish(&tx);
 
         // Wait for child to exit
         let exit_status = child
             .wait()
             .context("Failed to wait for child process")?;
 
-        let exit_code = exit_status.exit_code() as i32;
+        // Prefer the status the shell reported for the command itself
+        let exit_code = router.reported_exit.unwrap_or(exit_status.exit_code() as i32);
         tracing::debug!("Command exited with code: {}", exit_code);
         let _ = tx.send(OutputLine::Exit(exit_code));
+        drop(master);
 
         Ok(())
     }
 
+    /// Forward terminal input until every sender is dropped
+    fn spawn_input_thread(
+        mut writer: Box<dyn Write + Send>,
+        master: Arc<Mutex<Box<dyn MasterPty + Send>>>,
+        mut killer: Box<dyn ChildKiller + Send + Sync>,
+        pid: Option<u32>,
+        input_rx: std::sync::mpsc::Receiver<PtyInput>,
+    ) {
+        std::thread::spawn(move || {
+            while let Ok(input) = input_rx.recv() {
+                let result = match input {
+                    PtyInput::Bytes(bytes) => writer.write_all(&bytes).and_then(|_| writer.flush()),
+                    PtyInput::Resize { rows, cols } => master
+                        .lock()
+                        .map_err(|_| std::io::Error::other("PTY lock poisoned"))
+                        .and_then(|m| {
+                            m.resize(PtySize {
+                                rows,
+                                cols,
+                                pixel_width: 0,
+                                pixel_height: 0,
+                            })
+                            .map_err(std::io::Error::other)
+                        }),
+                    PtyInput::Terminate => {
+                        Self::hang_up(killer.as_mut(), pid);
+                        break;
+                    }
+                };
+                if let Err(e) = result {
+                    tracing::debug!("Terminal input stopped: {}", e);
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Send SIGHUP to the shell's process group, so programs it started go
+    /// too; elsewhere (or without a pid) terminate the shell itself
+    fn hang_up(killer: &mut (dyn ChildKiller + Send + Sync), pid: Option<u32>) {
+        #[cfg(unix)]
+        if let Some(pid) = pid {
+            use nix::sys::signal::{killpg, Signal};
+            use nix::unistd::Pid;
+            // The PTY makes the shell a session leader, so its pid is the group id
+            match killpg(Pid::from_raw(pid as i32), Signal::SIGHUP) {
+                Ok(()) => return,
+                Err(e) => tracing::debug!("Failed to signal process group {}: {}", pid, e),
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = pid;
+        if let Err(e) = killer.kill() {
+            tracing::warn!("Failed to terminate command: {}", e);
+        }
+    }
+
     /// Execute a simple command synchronously (for testing)
     pub fn execute_sync(&self, command: String) -> Result<(String, i32)> {
         // Source .bashrc before executing the command
@@ -168,6 +332,7 @@ impl ShellExecutor {
             .arg("-c")
             .arg(&full_command)
             .current_dir(&self.working_directory)
+            .envs(&self.environment)
             .output()
             .context("Failed to execute command")?;
 
@@ -231,6 +396,88 @@ mod tests {
         assert_eq!(exit_code, Some(0));
     }
 
+    #[tokio::test]
+    async fn test_shell_reports_exit_code_and_cwd() {
+        let dir = tempfile::tempdir().unwrap();
+        let executor = ShellExecutor::default();
+        let command = format!("echo before; cd {}; exit 3", dir.path().display());
+        let mut rx = executor.execute(command).await.unwrap();
+
+        let mut output = String::new();
+        let mut cwd = None;
+        let mut exit_code = None;
+        while let Some(line) = rx.recv().await {
+            match line {
+                OutputLine::Stdout(s) => output.push_str(&s),
+                OutputLine::WorkingDirectory(path) => cwd = Some(path),
+                OutputLine::Exit(code) => exit_code = Some(code),
+                _ => {}
+            }
+        }
+
+        assert!(output.contains("before"));
+        assert!(!output.contains("\x1b]133"));
+        assert_eq!(exit_code, Some(3));
+        // `exit` skips the finish hook, so no directory is reported
+        assert_eq!(cwd, None);
+
+        let command = format!("cd {} && pwd", dir.path().display());
+        let mut rx = executor.execute(command).await.unwrap();
+        let mut cwd = None;
+        while let Some(line) = rx.recv().await {
+            if let OutputLine::WorkingDirectory(path) = line {
+                cwd = Some(path);
+            }
+        }
+        assert_eq!(cwd.map(|p| p.canonicalize().unwrap()), Some(dir.path().canonicalize().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_terminal_input() {
+        let executor = ShellExecutor::default();
+        let (mut rx, input) = executor
+            .execute_with_input("read -r name; echo \"hi $name\"".to_string())
+            .await
+            .unwrap();
+        input.send(PtyInput::Resize { rows: 40, cols: 120 }).unwrap();
+        input.send(PtyInput::Bytes(b"immaterium\r".to_vec())).unwrap();
+
+        let mut output = String::new();
+        while let Some(line) = rx.recv().await {
+            match line {
+                OutputLine::Stdout(s) => output.push_str(&s),
+                OutputLine::Exit(code) => {
+                    assert_eq!(code, 0);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        assert!(output.contains("hi immaterium"), "got: {:?}", output);
+    }
+
+    #[tokio::test]
+    async fn test_terminate() {
+        let executor = ShellExecutor::default();
+        let (mut rx, input) = executor
+            .execute_with_input("sleep 30".to_string())
+            .await
+            .unwrap();
+        input.send(PtyInput::Terminate).unwrap();
+
+        let exit = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            while let Some(line) = rx.recv().await {
+                if let OutputLine::Exit(code) = line {
+                    return Some(code);
+                }
+            }
+            None
+        })
+        .await
+        .expect("command was not terminated");
+        assert_ne!(exit, Some(0));
+    }
+
     #[test]
     fn test_bashrc_sourcing_sync() {
         // Create a temporary test alias in a temp bashrc file
@@ -0,0 +1,62 @@
+/// Exit statuses above this mean the shell saw the command killed by signal
+/// `status - 128`
+const SIGNAL_BASE: i32 = 128;
+
+/// Short explanation of what an exit status usually means, for the tooltip
+/// on a failed block. `None` for success and statuses with no common meaning.
+pub fn explain_exit_code(code: i32) -> Option<String> {
+    let explanation = match code {
+        0 => return None,
+        -1 => "The command could not be started, or ended without a status",
+        1 => "General error; the command's output usually says what went wrong",
+        2 => "Misused shell builtin, or invalid arguments (many tools exit 2 on usage errors)",
+        124 => "Timed out (the status `timeout` exits with)",
+        125 => "A wrapper such as `timeout`, `env` or `docker run` failed before running the command",
+        126 => "Found but not executable: check its permissions, or that it isn't a directory",
+        127 => "Command not found: check the spelling, PATH, or that the tool is installed",
+        255 => "Exit status out of range, or an ssh connection error",
+        code if code > SIGNAL_BASE && code < SIGNAL_BASE + 65 => {
+            let signal = code - SIGNAL_BASE;
+            return Some(match signal_name(signal) {
+                Some((name, meaning)) => format!("Killed by {} (signal {}): {}", name, signal, meaning),
+                None => format!("Killed by signal {}", signal),
+            });
+        }
+        _ => return None,
+    };
+    Some(explanation.to_string())
+}
+
+fn signal_name(signal: i32) -> Option<(&'static str, &'static str)> {
+    Some(match signal {
+        1 => ("SIGHUP", "its terminal hung up"),
+        2 => ("SIGINT", "interrupted, e.g. with Ctrl+C"),
+        3 => ("SIGQUIT", "quit, e.g. with Ctrl+\\"),
+        4 => ("SIGILL", "illegal instruction"),
+        6 => ("SIGABRT", "aborted, often by a failed assertion or panic"),
+        8 => ("SIGFPE", "arithmetic error such as division by zero"),
+        9 => ("SIGKILL", "killed outright, often by the out-of-memory killer"),
+        11 => ("SIGSEGV", "segmentation fault, an invalid memory access"),
+        13 => ("SIGPIPE", "wrote to a pipe nobody was reading"),
+        14 => ("SIGALRM", "a timer ran out"),
+        15 => ("SIGTERM", "asked to terminate"),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_exit_code() {
+        assert_eq!(explain_exit_code(0), None);
+        assert!(explain_exit_code(127).unwrap().starts_with("Command not found"));
+        assert_eq!(
+            explain_exit_code(130).as_deref(),
+            Some("Killed by SIGINT (signal 2): interrupted, e.g. with Ctrl+C")
+        );
+        assert_eq!(explain_exit_code(128 + 40).as_deref(), Some("Killed by signal 40"));
+        assert_eq!(explain_exit_code(42), None);
+    }
+}
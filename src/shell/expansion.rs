@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::process::Command;
+
+/// Load the aliases visible to commands run by ShellExecutor (after sourcing ~/.bashrc)
+pub fn load_aliases(shell_path: &str) -> HashMap<String, String> {
+    let output = Command::new(shell_path)
+        .arg("-c")
+        .arg("[ -f ~/.bashrc ] && source ~/.bashrc >/dev/null 2>&1; alias")
+        .output();
+
+    match output {
+        Ok(output) => parse_alias_output(&String::from_utf8_lossy(&output.stdout)),
+        Err(e) => {
+            tracing::warn!("Failed to load shell aliases: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+/// Parse the output of bash's `alias` builtin (`alias ll='ls -alF'`)
+pub fn parse_alias_output(output: &str) -> HashMap<String, String> {
+    let mut aliases = HashMap::new();
+
+    for line in output.lines() {
+        let Some(definition) = line.strip_prefix("alias ") else {
+            continue;
+        };
+        let Some((name, value)) = definition.split_once('=') else {
+            continue;
+        };
+
+        let value = if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+            value[1..value.len() - 1].replace("'\\''", "'")
+        } else {
+            value.to_string()
+        };
+
+        aliases.insert(name.to_string(), value);
+    }
+
+    aliases
+}
+
+/// Expand aliases in command position and `$VAR` / `${VAR}` references
+/// (outside single quotes) to preview what will actually execute.
+/// Unknown variables are left untouched.
+pub fn expand_preview<F>(command: &str, aliases: &HashMap<String, String>, lookup: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let chars: Vec<char> = command.chars().collect();
+    let len = chars.len();
+    let mut out = String::with_capacity(command.len());
+    let mut i = 0;
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut at_command_start = true;
+
+    while i < len {
+        let c = chars[i];
+
+        if at_command_start && !in_single && !in_double {
+            if c.is_whitespace() {
+                out.push(c);
+                i += 1;
+                continue;
+            }
+
+            let start = i;
+            while i < len && !chars[i].is_whitespace() && !"|;&".contains(chars[i]) {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            at_command_start = false;
+
+            if let Some(expansion) = aliases.get(&word) {
+                out.push_str(expansion);
+            } else {
+                // Not an alias: process the word normally (it may contain $VARS)
+                i = start;
+            }
+            continue;
+        }
+
+        match c {
+            '\'' if !in_double => {
+                in_single = !in_single;
+                out.push(c);
+            }
+            '"' if !in_single => {
+                in_double = !in_double;
+                out.push(c);
+            }
+            '\\' if !in_single && i + 1 < len => {
+                out.push(c);
+                out.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            '$' if !in_single => {
+                let (name, consumed) = if chars.get(i + 1) == Some(&'{') {
+                    match chars[i + 2..].iter().position(|&ch| ch == '}') {
+                        Some(end) => (chars[i + 2..i + 2 + end].iter().collect::<String>(), end + 3),
+                        None => (String::new(), 1),
+                    }
+                } else {
+                    let name: String = chars[i + 1..]
+                        .iter()
+                        .take_while(|ch| ch.is_ascii_alphanumeric() || **ch == '_')
+                        .collect();
+                    let consumed = name.len() + 1;
+                    (name, consumed)
+                };
+
+                let is_identifier = name
+                    .chars()
+                    .next()
+                    .map(|ch| ch.is_ascii_alphabetic() || ch == '_')
+                    .unwrap_or(false);
+
+                match lookup(&name).filter(|_| is_identifier) {
+                    Some(value) => out.push_str(&value),
+                    None => out.extend(&chars[i..i + consumed]),
+                }
+                i += consumed;
+                continue;
+            }
+            '|' | ';' | '&' if !in_single && !in_double => {
+                out.push(c);
+                at_command_start = true;
+            }
+            _ => out.push(c),
+        }
+
+        i += 1;
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(name: &str) -> Option<String> {
+        match name {
+            "HOME" => Some("/home/user".to_string()),
+            "EDITOR" => Some("vim".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_parse_alias_output() {
+        let output = "alias ll='ls -alF'\nalias grep='grep --color=auto'\nalias say='echo '\\''hi'\\'''\n";
+        let aliases = parse_alias_output(output);
+        assert_eq!(aliases.get("ll").map(String::as_str), Some("ls -alF"));
+        assert_eq!(aliases.get("grep").map(String::as_str), Some("grep --color=auto"));
+        assert_eq!(aliases.get("say").map(String::as_str), Some("echo 'hi'"));
+    }
+
+    #[test]
+    fn test_expand_alias_in_command_position() {
+        let mut aliases = HashMap::new();
+        aliases.insert("ls".to_string(), "ls --color=auto".to_string());
+
+        assert_eq!(expand_preview("ls -la /tmp", &aliases, env), "ls --color=auto -la /tmp");
+        assert_eq!(expand_preview("echo ls | ls", &aliases, env), "echo ls | ls --color=auto");
+        assert_eq!(expand_preview("cd /tmp && ls", &aliases, env), "cd /tmp && ls --color=auto");
+    }
+
+    #[test]
+    fn test_expand_variables() {
+        let aliases = HashMap::new();
+        assert_eq!(expand_preview("cd $HOME/dev", &aliases, env), "cd /home/user/dev");
+        assert_eq!(expand_preview("${EDITOR} file", &aliases, env), "vim file");
+        assert_eq!(expand_preview("echo \"$HOME\"", &aliases, env), "echo \"/home/user\"");
+        // Single quotes, unknown vars and special parameters are left alone
+        assert_eq!(expand_preview("echo '$HOME'", &aliases, env), "echo '$HOME'");
+        assert_eq!(expand_preview("echo $UNKNOWN $1 $(pwd)", &aliases, env), "echo $UNKNOWN $1 $(pwd)");
+    }
+}
@@ -0,0 +1,276 @@
+use crate::config::{CommandHook, HooksConfig};
+use crate::core::{HookPhase, HookResult};
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// How long a single webhook or `run` hook may take
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The command a hook is running for
+#[derive(Debug, Clone)]
+pub struct HookEvent<'a> {
+    pub phase: HookPhase,
+    pub command: &'a str,
+    pub cwd: &'a Path,
+    /// Set for post hooks
+    pub exit_code: Option<i32>,
+    pub duration: Option<Duration>,
+}
+
+/// Runs the configured pre/post command hooks
+#[derive(Debug, Clone)]
+pub struct HookRunner {
+    pre: Vec<CommandHook>,
+    post: Vec<CommandHook>,
+    client: reqwest::Client,
+    /// Skip webhooks, for offline mode
+    offline: bool,
+}
+
+impl HookRunner {
+    pub fn new(config: &HooksConfig) -> Self {
+        Self {
+            pre: config.pre.clone(),
+            post: config.post.clone(),
+            client: reqwest::Client::new(),
+            offline: false,
+        }
+    }
+
+    /// Skip webhooks while `offline`, recording that they were skipped
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = offline;
+    }
+
+    /// Run the pre hooks in order. Returns their results and, if a hook refused
+    /// the command, the reason; hooks after a refusing one are skipped.
+    pub async fn run_pre(&self, command: &str, cwd: &Path) -> (Vec<HookResult>, Option<String>) {
+        let event = HookEvent { phase: HookPhase::Pre, command, cwd, exit_code: None, duration: None };
+        let mut results = Vec::new();
+        for hook in &self.pre {
+            let result = self.run(hook, &event).await;
+            let refused = match (hook, &result) {
+                (CommandHook::Refuse { .. } | CommandHook::Run { .. }, Err(e)) => Some(e.to_string()),
+                _ => None,
+            };
+            results.push(hook_result(hook, HookPhase::Pre, result));
+            if refused.is_some() {
+                return (results, refused);
+            }
+        }
+        (results, None)
+    }
+
+    pub async fn run_post(&self, command: &str, cwd: &Path, exit_code: i32, duration: Duration) -> Vec<HookResult> {
+        let event = HookEvent {
+            phase: HookPhase::Post,
+            command,
+            cwd,
+            exit_code: Some(exit_code),
+            duration: Some(duration),
+        };
+        let mut results = Vec::new();
+        for hook in self.post.iter().filter(|hook| !matches!(hook, CommandHook::Refuse { .. })) {
+            let result = self.run(hook, &event).await;
+            results.push(hook_result(hook, HookPhase::Post, result));
+        }
+        results
+    }
+
+    /// Run one hook. An error from a `refuse` or `run` pre hook refuses the command.
+    async fn run(&self, hook: &CommandHook, event: &HookEvent<'_>) -> Result<Option<String>> {
+        match hook {
+            CommandHook::Refuse { pattern, message } => {
+                let re = Regex::new(pattern).with_context(|| format!("Invalid pattern '{}'", pattern))?;
+                if re.is_match(event.command) {
+                    bail!("{}", message.clone().unwrap_or_else(|| format!("Matches '{}'", pattern)));
+                }
+                Ok(None)
+            }
+            CommandHook::Webhook { .. } if self.offline => Ok(Some("skipped in offline mode".to_string())),
+            CommandHook::Webhook { url } => {
+                let response = self
+                    .client
+                    .post(url)
+                    .timeout(TIMEOUT)
+                    .json(&event_json(event))
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                Ok(Some(response.status().to_string()))
+            }
+            CommandHook::AuditLog { path } => {
+                append_audit_line(&crate::utils::expand_tilde(path), event).await?;
+                Ok(None)
+            }
+            CommandHook::Run { command } => run_hook_command(command, event).await,
+        }
+    }
+}
+
+fn hook_result(hook: &CommandHook, phase: HookPhase, result: Result<Option<String>>) -> HookResult {
+    let (ok, message) = match result {
+        Ok(message) => (true, message),
+        Err(e) => (false, Some(e.to_string())),
+    };
+    HookResult { phase, hook: describe(hook), ok, message }
+}
+
+/// Short label for a hook, shown with its result
+pub fn describe(hook: &CommandHook) -> String {
+    match hook {
+        CommandHook::Refuse { pattern, .. } => format!("refuse {}", pattern),
+        CommandHook::Webhook { url } => format!("webhook {}", url),
+        CommandHook::AuditLog { path } => format!("audit log {}", path),
+        CommandHook::Run { command } => format!("run {}", command),
+    }
+}
+
+fn phase_name(phase: HookPhase) -> &'static str {
+    match phase {
+        HookPhase::Pre => "pre",
+        HookPhase::Post => "post",
+    }
+}
+
+fn event_json(event: &HookEvent<'_>) -> serde_json::Value {
+    serde_json::json!({
+        "phase": event.phase,
+        "command": event.command,
+        "cwd": event.cwd,
+        "exit_code": event.exit_code,
+        "duration_ms": event.duration.map(|d| d.as_millis() as u64),
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+/// `<timestamp>\t<phase>\t<exit code or ->\t<cwd>\t<command>`, with newlines
+/// in the command escaped so each entry stays on one line
+async fn append_audit_line(path: &Path, event: &HookEvent<'_>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let line = format!(
+        "{}\t{}\t{}\t{}\t{}\n",
+        chrono::Utc::now().to_rfc3339(),
+        phase_name(event.phase),
+        event.exit_code.map_or("-".to_string(), |code| code.to_string()),
+        event.cwd.display(),
+        event.command.replace('\n', "\\n"),
+    );
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    file.write_all(line.as_bytes()).await?;
+    // A tokio file finishes writing in the background; wait so the next
+    // line can't overtake this one or be lost when the handle drops
+    file.flush().await?;
+    Ok(())
+}
+
+async fn run_hook_command(command: &str, event: &HookEvent<'_>) -> Result<Option<String>> {
+    let mut process = tokio::process::Command::new("sh");
+    process
+        .arg("-c")
+        .arg(command)
+        .env("IMMATERIUM_HOOK_PHASE", phase_name(event.phase))
+        .env("IMMATERIUM_COMMAND", event.command)
+        .env("IMMATERIUM_CWD", event.cwd)
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    if let Some(code) = event.exit_code {
+        process.env("IMMATERIUM_EXIT_CODE", code.to_string());
+    }
+    if let Some(duration) = event.duration {
+        process.env("IMMATERIUM_DURATION_MS", duration.as_millis().to_string());
+    }
+    if event.cwd.is_dir() {
+        process.current_dir(event.cwd);
+    }
+
+    let output = tokio::time::timeout(TIMEOUT, process.output())
+        .await
+        .context("Hook timed out")?
+        .context("Failed to run hook")?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let reason = [stderr, stdout].into_iter().find(|s| !s.is_empty());
+        bail!("{}", reason.unwrap_or_else(|| format!("Hook exited with {}", output.status)));
+    }
+    Ok(Some(stdout).filter(|s| !s.is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_refuse_stops_pre_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("audit.log");
+        let config = HooksConfig {
+            pre: vec![
+                CommandHook::Refuse { pattern: r"^rm -rf /\s*$".to_string(), message: Some("Not here".to_string()) },
+                CommandHook::AuditLog { path: log.to_string_lossy().to_string() },
+            ],
+            post: vec![CommandHook::AuditLog { path: log.to_string_lossy().to_string() }],
+        };
+        let runner = HookRunner::new(&config);
+
+        let (results, refused) = runner.run_pre("rm -rf /", dir.path()).await;
+        assert_eq!(refused.as_deref(), Some("Not here"));
+        assert_eq!(results.len(), 1);
+        assert!(!log.exists());
+
+        let (results, refused) = runner.run_pre("ls", dir.path()).await;
+        assert!(refused.is_none());
+        assert!(results.iter().all(|r| r.ok));
+        let results = runner.run_post("ls", dir.path(), 2, Duration::from_millis(5)).await;
+        assert_eq!(results.len(), 1);
+
+        let lines: Vec<String> = std::fs::read_to_string(&log).unwrap().lines().map(String::from).collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("\tpre\t-\t"));
+        assert!(lines[1].contains("\tpost\t2\t") && lines[1].ends_with("\tls"));
+    }
+
+    #[tokio::test]
+    async fn test_offline_skips_webhooks() {
+        let config = HooksConfig {
+            pre: vec![CommandHook::Webhook { url: "http://192.0.2.1/hook".to_string() }],
+            post: Vec::new(),
+        };
+        let mut runner = HookRunner::new(&config);
+        runner.set_offline(true);
+
+        let (results, refused) = runner.run_pre("ls", Path::new("/tmp")).await;
+        assert!(refused.is_none());
+        assert_eq!(results.len(), 1);
+        assert!(results[0].ok);
+        assert_eq!(results[0].message.as_deref(), Some("skipped in offline mode"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failing_run_hook_refuses() {
+        let config = HooksConfig {
+            pre: vec![CommandHook::Run {
+                command: r#"case "$IMMATERIUM_COMMAND" in *prod*) echo "no prod" >&2; exit 1;; esac"#.to_string(),
+            }],
+            post: Vec::new(),
+        };
+        let runner = HookRunner::new(&config);
+        let (_, refused) = runner.run_pre("deploy prod", Path::new("/")).await;
+        assert_eq!(refused.as_deref(), Some("no prod"));
+        let (results, refused) = runner.run_pre("deploy staging", Path::new("/")).await;
+        assert!(refused.is_none() && results[0].ok);
+    }
+}
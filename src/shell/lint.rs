@@ -0,0 +1,181 @@
+use serde::Deserialize;
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+
+/// How serious a finding is, in shellcheck's terms
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+    Style,
+}
+
+/// A problem found in a command before running it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub line: usize,
+    pub column: Option<usize>,
+    /// shellcheck's SC code, e.g. 2086
+    pub code: Option<u32>,
+    pub message: String,
+}
+
+impl Finding {
+    /// `1:5 SC2086` style position and code
+    pub fn location(&self) -> String {
+        let mut location = match self.column {
+            Some(column) => format!("{}:{}", self.line, column),
+            None => format!("line {}", self.line),
+        };
+        if let Some(code) = self.code {
+            location.push_str(&format!(" SC{}", code));
+        }
+        location
+    }
+}
+
+/// Whether any finding should stop a command from running without a second look
+pub fn has_errors(findings: &[Finding]) -> bool {
+    findings.iter().any(|f| f.severity == Severity::Error)
+}
+
+/// Check a command with `bash -n` and, when asked and installed, shellcheck.
+/// Findings come sorted by severity, errors first.
+pub fn check_command(command: &str, shellcheck: bool) -> Vec<Finding> {
+    let mut findings = check_syntax(command);
+    // shellcheck repeats syntax errors less clearly than bash
+    if shellcheck && findings.is_empty() {
+        findings.extend(run_shellcheck(command));
+    }
+    findings.sort_by_key(|f| (f.severity, f.line, f.column));
+    findings
+}
+
+fn check_syntax(command: &str) -> Vec<Finding> {
+    match Command::new("bash").args(["-n", "-c", command]).output() {
+        Ok(output) if !output.status.success() => parse_bash_errors(&String::from_utf8_lossy(&output.stderr)),
+        Ok(_) => Vec::new(),
+        Err(e) => {
+            tracing::warn!("Failed to run bash -n: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Parse `bash: -c: line 2: syntax error: unexpected end of file` lines
+fn parse_bash_errors(stderr: &str) -> Vec<Finding> {
+    stderr
+        .lines()
+        .filter_map(|line| {
+            let (_, rest) = line.split_once("line ")?;
+            let (number, message) = rest.split_once(": ")?;
+            Some(Finding {
+                severity: Severity::Error,
+                line: number.parse().ok()?,
+                column: None,
+                code: None,
+                message: message.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+fn run_shellcheck(command: &str) -> Vec<Finding> {
+    let child = Command::new("shellcheck")
+        .args(["--shell=bash", "--format=json1", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Vec::new(),
+        Err(e) => {
+            tracing::warn!("Failed to run shellcheck: {}", e);
+            return Vec::new();
+        }
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(command.as_bytes());
+    }
+    match child.wait_with_output() {
+        // shellcheck exits with 1 when it has something to say
+        Ok(output) => parse_shellcheck(&String::from_utf8_lossy(&output.stdout)),
+        Err(e) => {
+            tracing::warn!("Failed to run shellcheck: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ShellcheckReport {
+    comments: Vec<ShellcheckComment>,
+}
+
+#[derive(Deserialize)]
+struct ShellcheckComment {
+    line: usize,
+    column: usize,
+    level: Severity,
+    code: u32,
+    message: String,
+}
+
+/// Parse shellcheck's `json1` output
+fn parse_shellcheck(json: &str) -> Vec<Finding> {
+    match serde_json::from_str::<ShellcheckReport>(json) {
+        Ok(report) => report
+            .comments
+            .into_iter()
+            .map(|c| Finding {
+                severity: c.level,
+                line: c.line,
+                column: Some(c.column),
+                code: Some(c.code),
+                message: c.message,
+            })
+            .collect(),
+        Err(e) => {
+            tracing::warn!("Unexpected shellcheck output: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bash_errors() {
+        let findings = parse_bash_errors(
+            "bash: -c: line 2: unexpected EOF while looking for matching `)'\nbash: -c: line 2: syntax error: unexpected end of file\n",
+        );
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[1].line, 2);
+        assert_eq!(findings[1].message, "syntax error: unexpected end of file");
+        assert_eq!(findings[1].location(), "line 2");
+    }
+
+    #[test]
+    fn test_parse_shellcheck() {
+        let json = r#"{"comments":[{"file":"-","line":1,"endLine":1,"column":6,"endColumn":8,"level":"info","code":2086,"message":"Double quote to prevent globbing and word splitting.","fix":null}]}"#;
+        let findings = parse_shellcheck(json);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Info);
+        assert_eq!(findings[0].location(), "1:6 SC2086");
+        assert!(!has_errors(&findings));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_command() {
+        assert!(check_command("ls -la | grep foo", false).is_empty());
+        let findings = check_command("echo $(ls", false);
+        assert!(has_errors(&findings));
+    }
+}
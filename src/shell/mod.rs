@@ -1,8 +1,19 @@
 // Shell executor module
 // Handles command execution through bash
 
+pub mod actions;
+pub mod danger;
 pub mod executor;
+pub mod exit_code;
+pub mod expansion;
+pub mod hooks;
+pub mod lint;
+pub mod osc;
 pub mod process;
 
-pub use executor::{OutputLine, ShellExecutor};
+pub use actions::{run_block_action, ActionInput};
+pub use executor::{OutputLine, PtyInput, ShellExecutor};
+pub use hooks::HookRunner;
+pub use lint::{Finding, Severity};
+pub use osc::{OscParser, OscSegment, ShellEvent};
 pub use process::{ProcessHandle, ProcessStatus};
@@ -0,0 +1,260 @@
+// Operating system command (OSC) escape sequences: OSC 133 semantic prompt
+// markers and OSC 7 working directory reports from the shell, and OSC 8
+// hyperlinks emitted by programs
+
+use std::path::PathBuf;
+
+const ESC: char = '\x1b';
+const BEL: char = '\x07';
+
+/// Something the shell reported through an escape sequence
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShellEvent {
+    /// OSC 133;A - a prompt is about to be drawn
+    PromptStart,
+    /// OSC 133;B - the prompt ended and command input begins
+    CommandStart,
+    /// OSC 133;C - the command is running; what follows is its output
+    OutputStart,
+    /// OSC 133;D[;exit] - the command finished
+    CommandFinished(Option<i32>),
+    /// OSC 7 - the shell's current directory
+    WorkingDirectory(PathBuf),
+}
+
+/// A piece of parsed output: plain text or a shell event, in stream order
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscSegment {
+    Text(String),
+    Event(ShellEvent),
+}
+
+/// Bash snippet emitted after a command: reports its exit status (OSC 133;D)
+/// and the directory the shell ended up in (OSC 7)
+pub const COMMAND_FINISHED_HOOK: &str = r#"__immaterium_status=$?; printf '\033]133;D;%s\007\033]7;file://%s%s\007' "$__immaterium_status" "${HOSTNAME:-localhost}" "$PWD"; exit $__immaterium_status"#;
+
+/// Bash snippet marking the start of command output (OSC 133;C)
+pub const OUTPUT_START_MARKER: &str = r#"printf '\033]133;C\007'"#;
+
+/// Extracts OSC 133 and OSC 7 sequences from streamed output. Sequences may be
+/// split across chunks; other escape sequences pass through unchanged.
+#[derive(Debug, Default)]
+pub struct OscParser {
+    pending: String,
+}
+
+impl OscParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a chunk, splitting it into text (with shell integration sequences
+    /// removed) and the events those sequences carried
+    pub fn feed(&mut self, chunk: &str) -> Vec<OscSegment> {
+        let mut input = std::mem::take(&mut self.pending);
+        input.push_str(chunk);
+
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut rest = input.as_str();
+
+        while let Some(start) = rest.find("\x1b]") {
+            text.push_str(&rest[..start]);
+            let sequence = &rest[start..];
+
+            let Some((body, len)) = osc_body(sequence) else {
+                // Incomplete sequence: wait for the rest of it
+                self.pending = sequence.to_string();
+                rest = "";
+                break;
+            };
+
+            match parse_event(body) {
+                Some(event) => {
+                    if !text.is_empty() {
+                        segments.push(OscSegment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(OscSegment::Event(event));
+                }
+                None => text.push_str(&sequence[..len]),
+            }
+            rest = &sequence[len..];
+        }
+
+        // Hold back a trailing ESC that may start a sequence in the next chunk
+        if let Some(stripped) = rest.strip_suffix(ESC) {
+            text.push_str(stripped);
+            self.pending.push(ESC);
+        } else {
+            text.push_str(rest);
+        }
+
+        if !text.is_empty() {
+            segments.push(OscSegment::Text(text));
+        }
+        segments
+    }
+
+    /// Return anything held back waiting for a sequence to complete
+    pub fn finish(&mut self) -> String {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+/// Split `ESC ] body terminator` into the body and the total length,
+/// or None if the terminator (BEL or ESC \) has not arrived yet
+fn osc_body(sequence: &str) -> Option<(&str, usize)> {
+    let content = &sequence[2..];
+    let end = content.find([BEL, ESC])?;
+    if content[end..].starts_with(BEL) {
+        return Some((&content[..end], 2 + end + 1));
+    }
+    match content[end + 1..].chars().next() {
+        Some('\\') => Some((&content[..end], 2 + end + 2)),
+        // An ESC that doesn't form ST ends the sequence without consuming it
+        Some(_) => Some((&content[..end], 2 + end)),
+        None => None,
+    }
+}
+
+fn parse_event(body: &str) -> Option<ShellEvent> {
+    if let Some(mark) = body.strip_prefix("133;") {
+        let mut parts = mark.split(';');
+        return match parts.next()? {
+            "A" => Some(ShellEvent::PromptStart),
+            "B" => Some(ShellEvent::CommandStart),
+            "C" => Some(ShellEvent::OutputStart),
+            "D" => Some(ShellEvent::CommandFinished(parts.next().and_then(|c| c.parse().ok()))),
+            _ => None,
+        };
+    }
+
+    let url = body.strip_prefix("7;")?;
+    let after_scheme = url.strip_prefix("file://")?;
+    // Skip the hostname; the path starts at the next slash
+    let path = &after_scheme[after_scheme.find('/')?..];
+    Some(ShellEvent::WorkingDirectory(PathBuf::from(percent_decode(path))))
+}
+
+/// A run of output text, linked if it sits inside an OSC 8 hyperlink
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkSpan<'a> {
+    pub text: &'a str,
+    pub url: Option<&'a str>,
+}
+
+pub fn has_hyperlink(text: &str) -> bool {
+    text.contains("\x1b]8;")
+}
+
+/// Split text on OSC 8 hyperlinks (`ESC ]8;params;URI ST text ESC ]8;; ST`),
+/// removing the escape sequences. An unterminated sequence is kept as text.
+pub fn split_hyperlinks(text: &str) -> Vec<LinkSpan<'_>> {
+    let mut spans = Vec::new();
+    let mut url: Option<&str> = None;
+    let mut rest = text;
+
+    while let Some(start) = rest.find("\x1b]8;") {
+        let sequence = &rest[start..];
+        let Some((body, len)) = osc_body(sequence) else {
+            break;
+        };
+
+        if start > 0 {
+            spans.push(LinkSpan { text: &rest[..start], url });
+        }
+        // body is "8;params;URI"; an empty URI closes the link
+        url = body.splitn(3, ';').nth(2).filter(|uri| !uri.is_empty());
+        rest = &sequence[len..];
+    }
+
+    if !rest.is_empty() {
+        spans.push(LinkSpan { text: rest, url });
+    }
+    spans
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(byte) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> OscSegment {
+        OscSegment::Text(s.to_string())
+    }
+
+    #[test]
+    fn test_extracts_markers_and_cwd() {
+        let mut parser = OscParser::new();
+        let segments = parser.feed(
+            "rc noise\x1b]133;C\x07hello\r\n\x1b]133;D;2\x07\x1b]7;file://box/home/me/my%20dir\x1b\\",
+        );
+        assert_eq!(
+            segments,
+            vec![
+                text("rc noise"),
+                OscSegment::Event(ShellEvent::OutputStart),
+                text("hello\r\n"),
+                OscSegment::Event(ShellEvent::CommandFinished(Some(2))),
+                OscSegment::Event(ShellEvent::WorkingDirectory(PathBuf::from("/home/me/my dir"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sequences_split_across_chunks() {
+        let mut parser = OscParser::new();
+        assert_eq!(parser.feed("out\x1b"), vec![text("out")]);
+        assert!(parser.feed("]133;D;").is_empty());
+        assert_eq!(
+            parser.feed("0\x07done"),
+            vec![OscSegment::Event(ShellEvent::CommandFinished(Some(0))), text("done")]
+        );
+
+        parser.feed("\x1b]7;file://host/tm");
+        assert_eq!(parser.finish(), "\x1b]7;file://host/tm");
+    }
+
+    #[test]
+    fn test_split_hyperlinks() {
+        let line = "see \x1b]8;id=1;https://example.com/docs\x1b\\the docs\x1b]8;;\x1b\\ or \x1b]8;;file:///tmp/a\x07a\x1b]8;;\x07";
+        assert!(has_hyperlink(line));
+        assert_eq!(
+            split_hyperlinks(line),
+            vec![
+                LinkSpan { text: "see ", url: None },
+                LinkSpan { text: "the docs", url: Some("https://example.com/docs") },
+                LinkSpan { text: " or ", url: None },
+                LinkSpan { text: "a", url: Some("file:///tmp/a") },
+            ]
+        );
+
+        let broken = "text \x1b]8;;https://unterminated";
+        assert_eq!(split_hyperlinks(broken), vec![LinkSpan { text: broken, url: None }]);
+    }
+
+    #[test]
+    fn test_other_sequences_pass_through() {
+        let mut parser = OscParser::new();
+        let input = "\x1b]0;window title\x07\x1b[31mred\x1b[0m";
+        assert_eq!(parser.feed(input), vec![text(input)]);
+    }
+}
@@ -0,0 +1,43 @@
+use super::SyncBackend;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::path::PathBuf;
+
+/// Syncs through a directory, which may itself be shared by another tool
+pub struct DirectoryBackend {
+    dir: PathBuf,
+}
+
+impl DirectoryBackend {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+}
+
+#[async_trait]
+impl SyncBackend for DirectoryBackend {
+    async fn get(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        match tokio::fs::read(self.dir.join(name)).await {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", name)),
+        }
+    }
+
+    async fn put(&self, name: &str, data: Vec<u8>) -> Result<()> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+
+        // Write then rename so a reader never sees a partial file
+        let path = self.dir.join(name);
+        let partial = self.dir.join(format!(".{}.partial", name));
+        tokio::fs::write(&partial, data)
+            .await
+            .with_context(|| format!("Failed to write {}", name))?;
+        tokio::fs::rename(&partial, &path)
+            .await
+            .with_context(|| format!("Failed to write {}", name))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,256 @@
+// Session sync between machines through a shared backend
+// Each session is stored as one object next to a manifest of update times;
+// the newer side wins when both have changed
+
+pub mod directory;
+pub mod s3;
+pub mod webdav;
+
+pub use directory::DirectoryBackend;
+pub use s3::S3Backend;
+pub use webdav::WebDavBackend;
+
+use crate::config::SyncBackendConfig;
+use crate::core::{ExportedSession, SessionInfo, SessionManager};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+const MANIFEST: &str = "manifest.json";
+
+/// Object storage that sessions are synced through
+#[async_trait]
+pub trait SyncBackend: Send + Sync {
+    /// Read an object, or None if it does not exist
+    async fn get(&self, name: &str) -> Result<Option<Vec<u8>>>;
+
+    async fn put(&self, name: &str, data: Vec<u8>) -> Result<()>;
+}
+
+pub fn backend_from_config(config: &SyncBackendConfig) -> Box<dyn SyncBackend> {
+    match config {
+        SyncBackendConfig::Directory { path } => {
+            Box::new(DirectoryBackend::new(crate::utils::expand_tilde(path)))
+        }
+        SyncBackendConfig::WebDav { url, username, password } => {
+            Box::new(WebDavBackend::new(url, username.clone(), password.clone()))
+        }
+        SyncBackendConfig::S3 { endpoint, bucket, region, access_key, secret_key, prefix } => {
+            Box::new(S3Backend::new(
+                endpoint,
+                bucket,
+                region,
+                access_key,
+                secret_key,
+                prefix.as_deref().unwrap_or_default(),
+            ))
+        }
+    }
+}
+
+/// Remote record of each synced session
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub sessions: BTreeMap<Uuid, RemoteSession>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteSession {
+    pub name: String,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub encrypted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncAction {
+    Push(Uuid),
+    Pull(Uuid),
+}
+
+/// Decide what to transfer: sessions missing on one side are copied over,
+/// and a session on both sides is copied from whichever was updated last
+pub fn plan(local: &[SessionInfo], remote: &Manifest) -> Vec<SyncAction> {
+    let mut actions = Vec::new();
+
+    for session in local {
+        match remote.sessions.get(&session.id) {
+            Some(remote) if remote.updated_at >= session.updated_at => {}
+            _ => actions.push(SyncAction::Push(session.id)),
+        }
+    }
+    for (id, remote) in &remote.sessions {
+        match local.iter().find(|s| &s.id == id) {
+            Some(local) if local.updated_at >= remote.updated_at => {}
+            _ => actions.push(SyncAction::Pull(*id)),
+        }
+    }
+
+    actions
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    pub pushed: usize,
+    /// Sessions replaced or added locally
+    pub pulled: Vec<Uuid>,
+}
+
+impl SyncReport {
+    pub fn summary(&self) -> String {
+        format!("{}↑ {}↓", self.pushed, self.pulled.len())
+    }
+}
+
+fn object_name(id: &Uuid, encrypted: bool) -> String {
+    if encrypted {
+        format!("session-{}.enc", id)
+    } else {
+        format!("session-{}.json", id)
+    }
+}
+
+/// Push and pull sessions so the local database and the backend agree.
+/// With a passphrase, pushed sessions are encrypted archives.
+pub async fn sync_sessions(
+    manager: &SessionManager,
+    backend: &dyn SyncBackend,
+    passphrase: Option<&str>,
+) -> Result<SyncReport> {
+    let mut manifest: Manifest = match backend.get(MANIFEST).await? {
+        Some(data) => serde_json::from_slice(&data).context("Sync manifest is malformed")?,
+        None => Manifest::default(),
+    };
+    let local = manager.list_sessions().await?;
+    let mut report = SyncReport::default();
+
+    for action in plan(&local, &manifest) {
+        match action {
+            SyncAction::Push(id) => {
+                let session = manager.load_session(&id).await?;
+                let exported = ExportedSession::new(session.clone());
+                let data = match passphrase {
+                    Some(passphrase) => exported.to_encrypted(passphrase)?,
+                    None => exported.to_json()?.into_bytes(),
+                };
+                let encrypted = passphrase.is_some();
+                backend.put(&object_name(&id, encrypted), data).await?;
+                manifest.sessions.insert(
+                    id,
+                    RemoteSession {
+                        name: session.name,
+                        updated_at: session.updated_at,
+                        encrypted,
+                    },
+                );
+                report.pushed += 1;
+            }
+            SyncAction::Pull(id) => {
+                let remote = &manifest.sessions[&id];
+                let data = backend
+                    .get(&object_name(&id, remote.encrypted))
+                    .await?
+                    .with_context(|| format!("Session '{}' is in the manifest but missing", remote.name))?;
+                let exported = if remote.encrypted {
+                    let passphrase = passphrase
+                        .context("Synced sessions are encrypted; set sync.passphrase")?;
+                    ExportedSession::from_encrypted(&data, passphrase)?
+                } else {
+                    ExportedSession::from_json(std::str::from_utf8(&data)?)?
+                };
+                manager.upsert_session(&exported.session).await?;
+                report.pulled.push(id);
+            }
+        }
+    }
+
+    if report.pushed > 0 {
+        backend
+            .put(MANIFEST, serde_json::to_vec_pretty(&manifest)?)
+            .await?;
+    }
+
+    tracing::info!("Session sync finished: {}", report.summary());
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Block, Database, Session};
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    fn info(id: Uuid, updated_at: DateTime<Utc>) -> SessionInfo {
+        SessionInfo {
+            id,
+            name: "s".to_string(),
+            created_at: updated_at,
+            updated_at,
+            is_active: false,
+            folder: None,
+            working_directory: PathBuf::from("/tmp"),
+            description: None,
+            color: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_newer_side_wins() {
+        let now = Utc::now();
+        let earlier = now - chrono::Duration::hours(1);
+        let (same, local_newer, remote_newer, local_only, remote_only) =
+            (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        let local = vec![
+            info(same, now),
+            info(local_newer, now),
+            info(remote_newer, earlier),
+            info(local_only, now),
+        ];
+        let mut remote = Manifest::default();
+        for (id, updated_at) in [(same, now), (local_newer, earlier), (remote_newer, now), (remote_only, now)] {
+            remote.sessions.insert(id, RemoteSession { name: "s".to_string(), updated_at, encrypted: false });
+        }
+
+        let actions = plan(&local, &remote);
+        assert_eq!(actions.len(), 4);
+        assert!(actions.contains(&SyncAction::Push(local_newer)));
+        assert!(actions.contains(&SyncAction::Push(local_only)));
+        assert!(actions.contains(&SyncAction::Pull(remote_newer)));
+        assert!(actions.contains(&SyncAction::Pull(remote_only)));
+    }
+
+    #[tokio::test]
+    async fn test_sync_between_databases() {
+        let temp_dir = tempdir().unwrap();
+        let backend = DirectoryBackend::new(temp_dir.path().join("remote"));
+        let work = SessionManager::new(Database::new(temp_dir.path().join("work.db")).await.unwrap())
+            .await
+            .unwrap();
+        let home = SessionManager::new(Database::new(temp_dir.path().join("home.db")).await.unwrap())
+            .await
+            .unwrap();
+
+        let session = Session::new("deploys".to_string(), PathBuf::from("/tmp"));
+        work.create_session(&session).await.unwrap();
+        let block = Block::new("make deploy".to_string(), PathBuf::from("/tmp"));
+        work.save_block(&session.id, &block, 0).await.unwrap();
+
+        let report = sync_sessions(&work, &backend, Some("secret")).await.unwrap();
+        assert_eq!(report.pushed, 1);
+
+        assert!(sync_sessions(&home, &backend, None).await.is_err());
+        let report = sync_sessions(&home, &backend, Some("secret")).await.unwrap();
+        assert_eq!(report.pulled, vec![session.id]);
+        let pulled = home.load_session(&session.id).await.unwrap();
+        assert_eq!(pulled.blocks[0].command, "make deploy");
+
+        // Nothing changed since, so nothing moves
+        let report = sync_sessions(&home, &backend, Some("secret")).await.unwrap();
+        assert_eq!((report.pushed, report.pulled.len()), (0, 0));
+    }
+}
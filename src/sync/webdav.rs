@@ -0,0 +1,88 @@
+use super::SyncBackend;
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use reqwest::StatusCode;
+
+/// Syncs through a WebDAV collection (Nextcloud, ownCloud, Apache mod_dav...)
+pub struct WebDavBackend {
+    client: reqwest::Client,
+    base_url: String,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl WebDavBackend {
+    pub fn new(url: &str, username: Option<String>, password: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: url.trim_end_matches('/').to_string(),
+            username,
+            password,
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, url: String) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, url);
+        match &self.username {
+            Some(username) => request.basic_auth(username, self.password.as_deref()),
+            None => request,
+        }
+    }
+
+    /// Create the collection itself, which PUT requires to exist
+    async fn create_collection(&self) -> Result<()> {
+        let method = reqwest::Method::from_bytes(b"MKCOL").expect("valid method");
+        let response = self
+            .request(method, format!("{}/", self.base_url))
+            .send()
+            .await
+            .context("WebDAV MKCOL failed")?;
+        // 405: the collection already exists
+        if !response.status().is_success() && response.status() != StatusCode::METHOD_NOT_ALLOWED {
+            bail!("WebDAV MKCOL failed: {}", response.status());
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SyncBackend for WebDavBackend {
+    async fn get(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        let response = self
+            .request(reqwest::Method::GET, format!("{}/{}", self.base_url, name))
+            .send()
+            .await
+            .with_context(|| format!("Failed to fetch {}", name))?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(response.bytes().await?.to_vec())),
+            status => bail!("Failed to fetch {}: {}", name, status),
+        }
+    }
+
+    async fn put(&self, name: &str, data: Vec<u8>) -> Result<()> {
+        let url = format!("{}/{}", self.base_url, name);
+        let mut response = self
+            .request(reqwest::Method::PUT, url.clone())
+            .body(data.clone())
+            .send()
+            .await
+            .with_context(|| format!("Failed to upload {}", name))?;
+
+        if response.status() == StatusCode::CONFLICT {
+            self.create_collection().await?;
+            response = self
+                .request(reqwest::Method::PUT, url)
+                .body(data)
+                .send()
+                .await
+                .with_context(|| format!("Failed to upload {}", name))?;
+        }
+
+        if !response.status().is_success() {
+            bail!("Failed to upload {}: {}", name, response.status());
+        }
+        Ok(())
+    }
+}
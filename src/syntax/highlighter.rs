@@ -1,24 +1,84 @@
-use syntect::highlighting::{ThemeSet, HighlightIterator, Highlighter, Style};
+use syntect::highlighting::{
+    Color as SyntectColor, ScopeSelectors, Style, StyleModifier, Theme as SyntectTheme, ThemeItem, ThemeSettings,
+};
 use syntect::parsing::{SyntaxSet, SyntaxReference};
 use syntect::easy::HighlightLines;
 use lazy_static::lazy_static;
+use crate::core::OutputBuffer;
+use crate::theme::{Color, Theme};
+use std::ops::Range;
+use std::sync::{Arc, RwLock};
+
+/// Longest output that gets syntax highlighted, in lines
+const MAX_HIGHLIGHT_LINES: usize = 5000;
+
+/// Commands whose output is the content of the files they're given
+const FILE_PRINTERS: &[&str] = &["cat", "bat", "batcat", "less", "more", "head", "tail", "jq", "yq"];
+
+/// Colored byte ranges of one output line
+pub type LineColors = Vec<(Range<usize>, egui::Color32)>;
 
 lazy_static! {
     /// Global syntax set with common languages
     pub static ref SYNTAX_SET: SyntaxSet = SyntaxSet::load_defaults_newlines();
-    
-    /// Default highlighting theme (we'll use our own theme colors)
-    pub static ref THEME_SET: ThemeSet = ThemeSet::load_defaults();
+
+    /// Highlighting theme built from the active app theme's syntax colors
+    static ref CURRENT_THEME: RwLock<Arc<SyntectTheme>> = RwLock::new(Arc::new(syntect_theme(&Theme::dark())));
+}
+
+/// Highlight with `theme`'s syntax colors from now on
+pub fn set_theme(theme: &Theme) {
+    if let Ok(mut current) = CURRENT_THEME.write() {
+        *current = Arc::new(syntect_theme(theme));
+    }
+}
+
+/// A syntect theme coloring each kind of token with the matching one of
+/// `theme`'s syntax colors, and everything else with its primary text color
+pub fn syntect_theme(theme: &Theme) -> SyntectTheme {
+    let color = |c: &Color| SyntectColor { r: c.r, g: c.g, b: c.b, a: c.a };
+    let syntax = &theme.syntax;
+    let item = |scopes: &str, c: &Color| ThemeItem {
+        scope: scopes.parse::<ScopeSelectors>().unwrap_or_default(),
+        style: StyleModifier { foreground: Some(color(c)), background: None, font_style: None },
+    };
+    SyntectTheme {
+        name: Some(theme.name.clone()),
+        author: None,
+        settings: ThemeSettings {
+            foreground: Some(color(&theme.colors.text_primary)),
+            background: Some(color(&theme.colors.background)),
+            ..Default::default()
+        },
+        scopes: vec![
+            item("comment, punctuation.definition.comment", &syntax.comment),
+            item("string, punctuation.definition.string", &syntax.string),
+            item("constant.numeric, constant.language, constant.character, constant.other", &syntax.number),
+            // `fn`, `def`, `class` and the like are storage types to syntect
+            item(
+                "keyword, storage.modifier, storage.type.function, storage.type.class, storage.type.struct, \
+                 storage.type.enum, storage.type.impl, storage.type.trait, storage.type.module",
+                &syntax.keyword,
+            ),
+            item("keyword.operator, punctuation.separator, punctuation.accessor", &syntax.operator),
+            item("entity.name.function, support.function, variable.function", &syntax.function),
+            item("entity.name.type, entity.name.class, storage.type, support.type, support.class", &syntax.type_name),
+            item("entity.name.tag, meta.mapping.key, support.type.property-name", &syntax.function),
+            item("variable, variable.parameter, variable.other", &syntax.variable),
+        ],
+    }
 }
 
 pub struct SyntaxHighlighter {
     syntax_set: &'static SyntaxSet,
+    theme: Arc<SyntectTheme>,
 }
 
 impl SyntaxHighlighter {
     pub fn new() -> Self {
         Self {
             syntax_set: &SYNTAX_SET,
+            theme: CURRENT_THEME.read().map(|theme| theme.clone()).unwrap_or_else(|_| Arc::new(syntect_theme(&Theme::dark()))),
         }
     }
 
@@ -39,14 +99,54 @@ impl SyntaxHighlighter {
         self.syntax_set.find_syntax_by_name("Bourne Again Shell (bash)")
     }
 
+    /// Detect the language of a command's output: from the extension of the
+    /// file it prints (`cat config.yaml`) or asks for (`-o json`), otherwise
+    /// from what the output looks like. `None` leaves it plain.
+    pub fn detect_output_syntax(&self, command: &str, output: &str) -> Option<&SyntaxReference> {
+        let plain = |syntax: &&SyntaxReference| syntax.name != "Plain Text";
+        printed_extension(command)
+            .and_then(|ext| self.find_syntax(&ext))
+            .filter(plain)
+            .or_else(|| sniff_language(output).and_then(|ext| self.find_syntax(ext)))
+    }
+
+    /// TOML isn't in syntect's default set; Java properties files share its
+    /// `key = value` lines and `#` comments
+    fn find_syntax(&self, extension: &str) -> Option<&SyntaxReference> {
+        self.syntax_set.find_syntax_by_extension(extension).or_else(|| match extension {
+            "toml" => self.syntax_set.find_syntax_by_extension("properties"),
+            "yml" => self.syntax_set.find_syntax_by_extension("yaml"),
+            _ => None,
+        })
+    }
+
+    /// Colors of each line of `output` highlighted as `syntax`
+    pub fn highlight_lines<'t>(&self, syntax: &SyntaxReference, lines: impl Iterator<Item = &'t str>) -> Vec<LineColors> {
+        let mut highlighter = HighlightLines::new(syntax, &self.theme);
+        lines
+            .map(|line| {
+                let mut start = 0;
+                highlighter
+                    .highlight_line(line, self.syntax_set)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(style, text)| {
+                        let range = start..start + text.len();
+                        start = range.end;
+                        (range, egui::Color32::from_rgb(style.foreground.r, style.foreground.g, style.foreground.b))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
     /// Highlight a shell command
     pub fn highlight_command(&self, command: &str) -> Vec<(Style, String)> {
         let syntax = self.detect_syntax(command)
             .or_else(|| self.syntax_set.find_syntax_by_name("Bourne Again Shell (bash)"))
             .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text());
 
-        let theme = &THEME_SET.themes["base16-ocean.dark"];
-        let mut highlighter = HighlightLines::new(syntax, theme);
+        let mut highlighter = HighlightLines::new(syntax, &self.theme);
         
         let mut result = Vec::new();
         for line in command.lines() {
@@ -60,6 +160,83 @@ impl SyntaxHighlighter {
         result
     }
 
+    /// Build an egui layout job for a command, colorized like the block header.
+    /// Falls back to a single plain section if highlighting doesn't round-trip the text.
+    pub fn command_layout_job(&self, command: &str, font_id: egui::FontId, default_color: egui::Color32) -> egui::text::LayoutJob {
+        let mut job = egui::text::LayoutJob::default();
+        let mut sections = self.highlight_command(command);
+
+        // highlight_command terminates every line with '\n'
+        if !command.ends_with('\n') && sections.last().map(|(_, t)| t == "\n").unwrap_or(false) {
+            sections.pop();
+        }
+
+        let rebuilt: String = sections.iter().map(|(_, t)| t.as_str()).collect();
+        if rebuilt != command {
+            job.append(command, 0.0, egui::TextFormat::simple(font_id, default_color));
+            return job;
+        }
+
+        for (style, text) in sections {
+            let color = if style == Style::default() {
+                default_color
+            } else {
+                egui::Color32::from_rgb(style.foreground.r, style.foreground.g, style.foreground.b)
+            };
+            job.append(&text, 0.0, egui::TextFormat::simple(font_id.clone(), color));
+        }
+
+        job
+    }
+
+    /// Build a layout job for a unified diff of `path`: added and removed
+    /// lines get a tinted background and their code is highlighted for the
+    /// file's language; headers and hunk markers are dimmed
+    pub fn diff_layout_job(&self, diff: &str, path: &str, font_id: egui::FontId, default_color: egui::Color32) -> egui::text::LayoutJob {
+        let extension = std::path::Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or_default();
+        let syntax = self
+            .syntax_set
+            .find_syntax_by_extension(extension)
+            .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text());
+        let mut highlighter = HighlightLines::new(syntax, &self.theme);
+        let mut job = egui::text::LayoutJob::default();
+
+        for line in diff.split_inclusive('\n') {
+            let header = ["diff ", "index ", "--- ", "+++ ", "new file", "deleted file", "similarity", "rename "]
+                .iter()
+                .any(|prefix| line.starts_with(prefix));
+            if header || line.starts_with("@@") || line.starts_with('\\') {
+                let color = if line.starts_with("@@") {
+                    egui::Color32::from_rgb(100, 180, 255)
+                } else {
+                    egui::Color32::from_rgb(120, 120, 120)
+                };
+                job.append(line, 0.0, egui::TextFormat::simple(font_id.clone(), color));
+                continue;
+            }
+
+            let (marker, code) = line.split_at(line.chars().next().map_or(0, char::len_utf8));
+            let background = match marker {
+                "+" => egui::Color32::from_rgba_unmultiplied(60, 160, 80, 40),
+                "-" => egui::Color32::from_rgba_unmultiplied(200, 60, 70, 40),
+                _ => egui::Color32::TRANSPARENT,
+            };
+            let format = |color| egui::TextFormat { background, ..egui::TextFormat::simple(font_id.clone(), color) };
+            job.append(marker, 0.0, format(default_color));
+            match highlighter.highlight_line(code, self.syntax_set) {
+                Ok(ranges) => {
+                    for (style, text) in ranges {
+                        let color = egui::Color32::from_rgb(style.foreground.r, style.foreground.g, style.foreground.b);
+                        job.append(text, 0.0, format(color));
+                    }
+                }
+                Err(_) => job.append(code, 0.0, format(default_color)),
+            }
+        }
+
+        job
+    }
+
     /// Detect if output contains code blocks (markdown-style)
     pub fn detect_code_blocks(&self, output: &str) -> Vec<CodeBlock> {
         let mut blocks = Vec::new();
@@ -102,6 +279,121 @@ impl SyntaxHighlighter {
     }
 }
 
+/// Extension of the file a command prints, or of the format it asks for
+/// with `-o`/`--output` (`kubectl get pod -o yaml`)
+fn printed_extension(command: &str) -> Option<String> {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    let format = words.windows(2).find_map(|pair| match pair {
+        ["-o" | "--output", format] => Some(*format),
+        _ => None,
+    });
+    let format = format.or_else(|| words.iter().find_map(|w| w.strip_prefix("--output=").or_else(|| w.strip_prefix("-o"))));
+    if let Some(format @ ("json" | "yaml" | "yml" | "toml")) = format {
+        return Some(format.to_string());
+    }
+
+    let program = words.first()?.rsplit('/').next()?;
+    if !FILE_PRINTERS.contains(&program) {
+        return None;
+    }
+    words[1..]
+        .iter()
+        .rev()
+        .filter(|w| !w.starts_with('-'))
+        .find_map(|w| std::path::Path::new(w.trim_matches(['\'', '"'])).extension()?.to_str())
+        .map(str::to_lowercase)
+}
+
+/// Extension of the language `output` looks like: JSON, YAML, TOML, SQL, a
+/// Python traceback or Rust source
+fn sniff_language(output: &str) -> Option<&'static str> {
+    let trimmed = output.trim_start();
+    if output.contains("Traceback (most recent call last):") {
+        return Some("py");
+    }
+    let body = trimmed.trim_end();
+    let json_start = body.starts_with('{') || (body.starts_with('[') && body[1..].trim_start().starts_with(['{', '[', '"', ']']));
+    if json_start && body.ends_with(['}', ']']) {
+        return Some("json");
+    }
+    let first_word = trimmed.split_whitespace().next().unwrap_or_default().to_uppercase();
+    if ["SELECT", "INSERT", "UPDATE", "DELETE", "CREATE", "ALTER", "DROP", "WITH"].contains(&first_word.as_str())
+        && body.ends_with(';')
+    {
+        return Some("sql");
+    }
+
+    let lines: Vec<&str> = trimmed
+        .lines()
+        .take(50)
+        .map(str::trim_end)
+        .filter(|l| !l.trim().is_empty() && !l.trim_start().starts_with('#'))
+        .collect();
+    if lines.len() < 2 {
+        return None;
+    }
+    let rust_items = ["fn ", "pub ", "use ", "impl ", "mod ", "struct ", "enum ", "#[", "//"];
+    if lines.iter().filter(|l| rust_items.iter().any(|item| l.starts_with(item))).count() >= 2
+        && lines.iter().any(|l| l.ends_with('{') || l.ends_with(';'))
+    {
+        return Some("rs");
+    }
+    let is_table = |l: &&str| l.starts_with('[') && l.ends_with(']');
+    let is_key_value = |l: &&str| {
+        l.split_once(" = ").is_some_and(|(key, _)| {
+            !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || "_-.\"".contains(c))
+        })
+    };
+    if lines.iter().any(is_table) && lines.iter().all(|l| is_table(l) || is_key_value(l) || l.starts_with(' ')) {
+        return Some("toml");
+    }
+    let is_yaml = |l: &&str| {
+        let item = l.trim_start().trim_start_matches("- ");
+        l.trim_start().starts_with("- ")
+            || item.split_once(':').is_some_and(|(key, rest)| {
+                !key.is_empty()
+                    && !key.contains(char::is_whitespace)
+                    && (rest.is_empty() || rest.starts_with(' '))
+            })
+            || l.starts_with(' ')
+    };
+    if lines[0] == "---" || (lines.iter().all(is_yaml) && lines.iter().any(|l| l.ends_with(':'))) {
+        return Some("yaml");
+    }
+    None
+}
+
+/// Syntax colors of one block's output, recomputed when the output changes
+#[derive(Debug, Clone, Default)]
+pub struct OutputHighlight {
+    lines: Vec<LineColors>,
+    highlighted_len: Option<usize>,
+}
+
+impl OutputHighlight {
+    pub fn lines(&self) -> &[LineColors] {
+        &self.lines
+    }
+
+    /// Highlight `output` if it changed since the last update
+    pub fn update(&mut self, command: &str, output: &OutputBuffer) {
+        if self.highlighted_len == Some(output.len()) {
+            return;
+        }
+        self.highlighted_len = Some(output.len());
+        self.lines.clear();
+
+        let highlighter = SyntaxHighlighter::new();
+        if output.lines().nth(MAX_HIGHLIGHT_LINES).is_some() {
+            return;
+        }
+        let sample: String = output.lines().take(50).collect();
+        if let Some(syntax) = highlighter.detect_output_syntax(command, &sample) {
+            self.lines = highlighter.highlight_lines(syntax, output.lines());
+        }
+    }
+}
+
 impl Default for SyntaxHighlighter {
     fn default() -> Self {
         Self::new()
@@ -149,6 +441,78 @@ mod tests {
         assert!(!result.is_empty());
     }
 
+    #[test]
+    fn test_command_layout_job_preserves_text() {
+        let highlighter = SyntaxHighlighter::new();
+        let command = "ls -la | grep \"foo bar\" ";
+        let job = highlighter.command_layout_job(command, egui::FontId::monospace(14.0), egui::Color32::WHITE);
+        assert_eq!(job.text, command);
+        assert!(job.sections.len() > 1);
+
+        let empty = highlighter.command_layout_job("", egui::FontId::monospace(14.0), egui::Color32::WHITE);
+        assert_eq!(empty.text, "");
+    }
+
+    #[test]
+    fn test_diff_layout_job_preserves_text() {
+        let highlighter = SyntaxHighlighter::new();
+        let diff = "diff --git a/src/main.rs b/src/main.rs\n@@ -1,2 +1,2 @@\n fn main() {\n-    println!(\"hi\");\n+    println!(\"hello\");\n }\n";
+        let job = highlighter.diff_layout_job(diff, "src/main.rs", egui::FontId::monospace(14.0), egui::Color32::WHITE);
+        assert_eq!(job.text, diff);
+    }
+
+    #[test]
+    fn test_detect_output_syntax() {
+        let highlighter = SyntaxHighlighter::new();
+        let name = |command: &str, output: &str| highlighter.detect_output_syntax(command, output).map(|s| s.name.clone());
+
+        assert_eq!(name("cat config.yaml", "a b c\n").as_deref(), Some("YAML"));
+        assert_eq!(name("kubectl get pod api -o json", "").as_deref(), Some("JSON"));
+        assert_eq!(name("head -n 20 src/main.rs", "").as_deref(), Some("Rust"));
+        assert!(name("cat Cargo.toml", "").is_some());
+        assert_eq!(name("curl localhost", "{\"ok\": true}\n").as_deref(), Some("JSON"));
+        assert_eq!(name("psql", "SELECT id\nFROM users;\n").as_deref(), Some("SQL"));
+        assert_eq!(
+            name("python app.py", "Traceback (most recent call last):\n  File \"app.py\", line 1\n").as_deref(),
+            Some("Python")
+        );
+        assert_eq!(name("./gen", "name: api\nspec:\n  replicas: 2\n").as_deref(), Some("YAML"));
+        assert_eq!(name("./gen", "use std::io;\n\nfn main() {\n}\n").as_deref(), Some("Rust"));
+        assert!(name("./gen", "[package]\nname = \"app\"\nversion = \"0.1.0\"\n").is_some());
+
+        assert_eq!(name("ls -la", "total 0\ndrwxr-xr-x 2 root root 40 .\n"), None);
+        assert_eq!(name("echo hi", "hi\n"), None);
+        assert_eq!(name("cat notes", "[1] first\n[2] second\n"), None);
+    }
+
+    #[test]
+    fn test_output_highlight_covers_lines() {
+        let mut output = OutputBuffer::new();
+        output.push_str("{\n  \"name\": \"api\"\n}\n");
+        let mut highlight = OutputHighlight::default();
+        highlight.update("cat package.json", &output);
+        assert_eq!(highlight.lines().len(), 3);
+        assert_eq!(highlight.lines()[1].last().map(|(range, _)| range.end), Some("  \"name\": \"api\"\n".len()));
+
+        let mut plain = OutputBuffer::new();
+        plain.push_str("hello\n");
+        highlight.update("echo hello", &plain);
+        assert!(highlight.lines().is_empty());
+    }
+
+    #[test]
+    fn test_highlighting_uses_theme_colors() {
+        let theme = Theme::light();
+        let highlighter = SyntaxHighlighter { syntax_set: &SYNTAX_SET, theme: Arc::new(syntect_theme(&theme)) };
+        let rust = highlighter.syntax_set.find_syntax_by_extension("rs").unwrap();
+        let lines = highlighter.highlight_lines(rust, ["// note\n", "fn main() {}\n"].into_iter());
+
+        let comment = theme.syntax.comment.to_egui();
+        assert!(lines[0].iter().any(|(_, color)| *color == comment));
+        let keyword = theme.syntax.keyword.to_egui();
+        assert_eq!(lines[1][0], (0..2, keyword));
+    }
+
     #[test]
     fn test_detect_code_blocks() {
         let highlighter = SyntaxHighlighter::new();
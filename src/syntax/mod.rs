@@ -1,3 +1,3 @@
 pub mod highlighter;
 
-pub use highlighter::{CodeBlock, SyntaxHighlighter};
+pub use highlighter::{set_theme, syntect_theme, CodeBlock, LineColors, OutputHighlight, SyntaxHighlighter};
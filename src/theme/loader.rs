@@ -101,7 +101,7 @@ impl ThemeLoader {
         Ok(())
     }
 
-    /// Apply theme to egui context
+    /// Apply theme to egui context and syntax highlighting
     pub fn apply_to_egui(&self, ctx: &egui::Context) {
         let theme = self.current();
         let mut visuals = egui::Visuals::dark();
@@ -130,6 +130,7 @@ impl ThemeLoader {
         visuals.hyperlink_color = theme.colors.block_running.to_egui();
         
         ctx.set_visuals(visuals);
+        crate::syntax::set_theme(theme);
         
         // Update text styles
         let mut style = (*ctx.style()).clone();
@@ -0,0 +1,127 @@
+use crate::core::{Block, BlockState};
+use crate::t;
+use egui::{Color32, Context, Id, Key, LayerId, Order, Response, Stroke, Ui, WidgetInfo, WidgetType};
+
+/// Outline around the keyboard-focused widget; bright enough to read on any theme
+const FOCUS_RING_COLOR: Color32 = Color32::from_rgb(255, 200, 0);
+const FOCUS_RING_WIDTH: f32 = 2.0;
+
+/// Small button showing only an icon, named for screen readers and tooltips
+pub fn icon_button(ui: &mut Ui, icon: &str, label: &str) -> Response {
+    let response = ui.small_button(icon);
+    let enabled = response.enabled();
+    response.widget_info(|| WidgetInfo::labeled(WidgetType::Button, enabled, label));
+    response.on_hover_text(label)
+}
+
+/// Glyph for a block state, so states can be told apart without color.
+/// Running blocks show a spinner where one can be drawn and a clock otherwise.
+pub fn state_icon(state: &BlockState) -> &'static str {
+    match state {
+        BlockState::PendingApproval => "⌛",
+        BlockState::Running => "⏱",
+        BlockState::Completed => "✓",
+        BlockState::Failed => "✗",
+        BlockState::Editing => "✎",
+        BlockState::Cancelled => "⊘",
+    }
+}
+
+/// What a screen reader announces for a block: its state, command and result
+pub fn block_description(block: &Block) -> String {
+    let state = match block.state {
+        BlockState::PendingApproval => t!("a11y-state-suggested"),
+        BlockState::Running => t!("a11y-state-running"),
+        BlockState::Completed => t!("a11y-state-completed"),
+        BlockState::Failed => t!("a11y-state-failed"),
+        BlockState::Editing => t!("a11y-state-editing"),
+        BlockState::Cancelled => t!("a11y-state-cancelled"),
+    };
+    let mut parts = vec![t!("a11y-block", state = state, command = block.command.clone())];
+    if let Some(code) = block.exit_code.filter(|code| *code != 0) {
+        parts.push(t!("a11y-exit-code", code = code.to_string()));
+    }
+    let duration = block.format_duration();
+    if !duration.is_empty() {
+        parts.push(t!("a11y-took", duration = duration));
+    }
+    if block.is_pinned {
+        parts.push(t!("a11y-pinned"));
+    }
+    if block.in_ai_context {
+        parts.push(t!("a11y-in-ai-context"));
+    }
+    if !block.tags.is_empty() {
+        parts.push(t!("a11y-tagged", tags = block.tags.join(", ")));
+    }
+    parts.join(", ")
+}
+
+/// Draw a ring around the focused widget once the keyboard has been used to
+/// move focus. Clicking hides it again, so mouse users only see it while tabbing.
+pub fn paint_focus_ring(ctx: &Context) {
+    let id = Id::new("focus_ring_visible");
+    let (navigated, clicked) = ctx.input(|i| {
+        let navigated = [Key::Tab, Key::ArrowUp, Key::ArrowDown, Key::ArrowLeft, Key::ArrowRight]
+            .iter()
+            .any(|key| i.key_pressed(*key));
+        (navigated, i.pointer.any_pressed())
+    });
+    let mut visible = ctx.data(|d| d.get_temp::<bool>(id)).unwrap_or(false);
+    if clicked {
+        visible = false;
+    } else if navigated {
+        visible = true;
+    }
+    ctx.data_mut(|d| d.insert_temp(id, visible));
+
+    if !visible {
+        return;
+    }
+    let Some(focused) = ctx.memory(|m| m.focused()) else {
+        return;
+    };
+    if let Some(response) = ctx.read_response(focused) {
+        ctx.layer_painter(LayerId::new(Order::Tooltip, Id::new("focus_ring"))).rect_stroke(
+            response.interact_rect.expand(FOCUS_RING_WIDTH),
+            3.0,
+            Stroke::new(FOCUS_RING_WIDTH, FOCUS_RING_COLOR),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    #[test]
+    fn test_block_description() {
+        let mut block = Block::new("make test".to_string(), PathBuf::from("/src"));
+        block.start_execution();
+        assert_eq!(block_description(&block), "Running: make test");
+
+        block.complete_execution(2);
+        block.metadata.duration = Some(Duration::from_secs(3));
+        block.tags.push("ci".to_string());
+        assert_eq!(block_description(&block), "Failed: make test, exit code 2, took 3s, tagged ci");
+
+        let suggested = Block::new_pending_approval("list files".to_string(), "ls".to_string(), PathBuf::from("/"));
+        assert_eq!(block_description(&suggested), "Suggested command: ls");
+    }
+
+    #[test]
+    fn test_state_icons_are_distinct() {
+        let states = [
+            BlockState::Editing,
+            BlockState::PendingApproval,
+            BlockState::Running,
+            BlockState::Completed,
+            BlockState::Failed,
+            BlockState::Cancelled,
+        ];
+        let icons: std::collections::HashSet<_> = states.iter().map(state_icon).collect();
+        assert_eq!(icons.len(), states.len());
+    }
+}
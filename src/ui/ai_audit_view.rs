@@ -0,0 +1,180 @@
+use crate::core::{AiAuditEntry, AiRequestKind};
+use crate::t;
+use chrono::Local;
+use egui::{Color32, Context, RichText, ScrollArea, TextEdit};
+use uuid::Uuid;
+
+/// AI > Audit Log: recorded AI requests, newest first, with the details of
+/// the selected one
+#[derive(Default)]
+pub struct AiAuditView {
+    pub open: bool,
+    entries: Vec<AiAuditEntry>,
+    loading: bool,
+    filter: String,
+    selected: Option<Uuid>,
+}
+
+impl AiAuditView {
+    /// Show the log with entries about to be loaded
+    pub fn open(&mut self) {
+        self.open = true;
+        self.loading = true;
+    }
+
+    pub fn set_entries(&mut self, entries: Vec<AiAuditEntry>) {
+        self.entries = entries;
+        self.loading = false;
+    }
+
+    /// Returns true when the user asks to reload the entries
+    pub fn show(&mut self, ctx: &Context, recording: bool) -> bool {
+        if !self.open {
+            return false;
+        }
+        let mut refresh = false;
+        let mut open = true;
+        egui::Window::new(t!("dialog-ai-audit"))
+            .id(egui::Id::new("ai_audit_window"))
+            .open(&mut open)
+            .default_size([820.0, 460.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(TextEdit::singleline(&mut self.filter).hint_text("Filter").desired_width(200.0));
+                    if ui.button("⟳ Refresh").clicked() {
+                        self.loading = true;
+                        refresh = true;
+                    }
+                    if self.loading {
+                        ui.spinner();
+                    }
+                });
+                if !recording {
+                    ui.label(
+                        RichText::new("Recording is off. Set enabled = true under [ai.audit] in the config file.")
+                            .weak()
+                            .small(),
+                    );
+                }
+                ui.separator();
+
+                let entries: Vec<&AiAuditEntry> = self.entries.iter().filter(|e| self.matches(e)).collect();
+                ScrollArea::vertical()
+                    .id_source("ai_audit_rows")
+                    .max_height(260.0)
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        egui::Grid::new("ai_audit").num_columns(7).striped(true).spacing([12.0, 4.0]).show(ui, |ui| {
+                            for heading in ["Time", "Kind", "Provider / model", "Tokens", "Latency", "Run", "Prompt"] {
+                                ui.label(RichText::new(heading).strong());
+                            }
+                            ui.end_row();
+
+                            for entry in &entries {
+                                let time = entry.timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string();
+                                if ui.selectable_label(self.selected == Some(entry.id), time).clicked() {
+                                    self.selected = Some(entry.id);
+                                }
+                                ui.label(entry.kind.as_str());
+                                ui.label(format!("{}/{}", entry.provider, entry.model));
+                                ui.label(format_tokens(entry));
+                                ui.label(format!("{} ms", entry.latency.as_millis()));
+                                ui.label(match (entry.kind, entry.executed()) {
+                                    (AiRequestKind::Command, true) => "yes",
+                                    (AiRequestKind::Command, false) => "no",
+                                    (AiRequestKind::Chat | AiRequestKind::Explanation | AiRequestKind::Condense, _) => "—",
+                                });
+                                match &entry.error {
+                                    Some(error) => ui.label(RichText::new(error).color(Color32::from_rgb(230, 80, 80))),
+                                    None => ui.label(RichText::new(prompt_preview(entry)).monospace()),
+                                };
+                                ui.end_row();
+                            }
+                        });
+                    });
+
+                if let Some(entry) = self.selected.and_then(|id| self.entries.iter().find(|e| e.id == id)) {
+                    ui.separator();
+                    show_details(ui, entry);
+                }
+            });
+        self.open = open;
+        refresh
+    }
+
+    fn matches(&self, entry: &AiAuditEntry) -> bool {
+        if self.filter.is_empty() {
+            return true;
+        }
+        let filter = self.filter.to_lowercase();
+        [Some(&entry.provider), Some(&entry.model), Some(&entry.prompt_hash), entry.prompt.as_ref(), entry.response.as_ref()]
+            .into_iter()
+            .flatten()
+            .any(|text| text.to_lowercase().contains(&filter))
+    }
+}
+
+fn show_details(ui: &mut egui::Ui, entry: &AiAuditEntry) {
+    ui.label(RichText::new(format!("Prompt hash {}", entry.prompt_hash)).monospace().weak());
+    if let Some(block) = entry.executed_block {
+        ui.label(RichText::new(format!("Run in block {}", block)).weak());
+    }
+    ScrollArea::vertical().id_source("ai_audit_details").max_height(140.0).show(ui, |ui| {
+        match &entry.prompt {
+            Some(prompt) => {
+                ui.label(RichText::new("Prompt").strong());
+                ui.label(RichText::new(prompt).monospace());
+            }
+            None => {
+                ui.label(RichText::new("Full text was not recorded for this request").weak());
+            }
+        }
+        if let Some(response) = &entry.response {
+            ui.label(RichText::new("Response").strong());
+            ui.label(RichText::new(response).monospace());
+        }
+        if let Some(error) = &entry.error {
+            ui.label(RichText::new(error).color(Color32::from_rgb(230, 80, 80)));
+        }
+    });
+}
+
+fn format_tokens(entry: &AiAuditEntry) -> String {
+    match (entry.prompt_tokens, entry.completion_tokens) {
+        (Some(prompt), Some(completion)) => format!("{} + {}", prompt, completion),
+        _ => "—".to_string(),
+    }
+}
+
+/// Last line of the prompt, which follows any context sent with it, or its
+/// hash when the text wasn't recorded
+fn prompt_preview(entry: &AiAuditEntry) -> String {
+    match &entry.prompt {
+        Some(prompt) => {
+            let line = prompt.lines().last().unwrap_or_default();
+            match line.char_indices().nth(60) {
+                Some((cut, _)) => format!("{}…", &line[..cut]),
+                None => line.to_string(),
+            }
+        }
+        None => format!("#{}", entry.prompt_hash),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_prompt_preview() {
+        let hashed = AiAuditEntry::new(AiRequestKind::Command, "ollama", "llama2", "list files", false);
+        assert_eq!(prompt_preview(&hashed), format!("#{}", hashed.prompt_hash));
+
+        let long = format!("Recent commands: ls\n{}", "x".repeat(80));
+        let entry = AiAuditEntry::new(AiRequestKind::Chat, "ollama", "llama2", &long, true)
+            .succeeded("ok", Some((10, 2)), Duration::from_millis(5));
+        assert_eq!(prompt_preview(&entry), format!("{}…", "x".repeat(60)));
+        assert_eq!(format_tokens(&entry), "10 + 2");
+    }
+}
@@ -0,0 +1,100 @@
+use crate::ai::{AiEngine, ProviderHealth, ProviderStatus};
+use crate::t;
+use egui::{Color32, Context, RichText};
+use std::time::Duration;
+
+/// Checks asked for from the diagnostics window
+pub enum DiagnosticsAction {
+    Check(String),
+    CheckAll,
+}
+
+/// AI > Diagnostics: for each registered provider, whether it answers, how
+/// long the last check took, where requests go and the last error
+pub fn show(ctx: &Context, open: &mut bool, engine: Option<&AiEngine>) -> Option<DiagnosticsAction> {
+    let mut action = None;
+    egui::Window::new(t!("dialog-ai-diagnostics"))
+        .id(egui::Id::new("ai_diagnostics_window"))
+        .open(open)
+        .collapsible(false)
+        .default_width(640.0)
+        .show(ctx, |ui| {
+            let Some(engine) = engine else {
+                ui.label(RichText::new("No AI providers are set up. Enable one under [ai.providers] in the config file.").weak());
+                return;
+            };
+            let mut providers = engine.list_providers();
+            providers.sort();
+
+            egui::Grid::new("ai_diagnostics").num_columns(6).striped(true).spacing([12.0, 6.0]).show(ui, |ui| {
+                for heading in ["Provider", "Status", "Ping", "Base URL", "Model", ""] {
+                    ui.label(RichText::new(heading).strong());
+                }
+                ui.end_row();
+
+                for name in &providers {
+                    let status = engine.status(name);
+                    let provider = engine.get_provider(name);
+                    ui.label(name);
+                    let (text, color) = health_label(&status);
+                    ui.label(RichText::new(text).color(color));
+                    ui.label(status.latency.map(format_latency).unwrap_or_else(|| "—".to_string()));
+                    ui.label(RichText::new(provider.and_then(|p| p.base_url()).unwrap_or("—")).monospace());
+                    ui.label(provider.and_then(|p| p.default_model()).unwrap_or("—"));
+                    if ui.small_button("Ping").on_hover_text("Check this provider now").clicked() {
+                        action = Some(DiagnosticsAction::Check(name.clone()));
+                    }
+                    ui.end_row();
+                }
+            });
+
+            let errors: Vec<_> = providers
+                .iter()
+                .filter_map(|name| engine.status(name).last_error.map(|error| (name, error)))
+                .collect();
+            if !errors.is_empty() {
+                ui.separator();
+                ui.label(RichText::new("Last errors").strong());
+                for (name, error) in errors {
+                    ui.label(RichText::new(format!("{}: {}", name, error)).color(Color32::from_rgb(230, 80, 80)));
+                }
+            }
+
+            ui.separator();
+            if ui.button("⟳ Check All").clicked() {
+                action = Some(DiagnosticsAction::CheckAll);
+            }
+        });
+    action
+}
+
+fn health_label(status: &ProviderStatus) -> (String, Color32) {
+    let checked = status
+        .checked_at
+        .map(|at| format!(" ({}s ago)", at.elapsed().as_secs()))
+        .unwrap_or_default();
+    match status.health {
+        ProviderHealth::Available => (format!("● Available{}", checked), Color32::from_rgb(80, 200, 120)),
+        ProviderHealth::Unavailable => (format!("● Not reachable{}", checked), Color32::from_rgb(230, 80, 80)),
+        ProviderHealth::Unknown => ("● Not checked".to_string(), Color32::GRAY),
+    }
+}
+
+fn format_latency(latency: Duration) -> String {
+    if latency < Duration::from_secs(1) {
+        format!("{} ms", latency.as_millis())
+    } else {
+        format!("{:.1} s", latency.as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_latency() {
+        assert_eq!(format_latency(Duration::from_millis(42)), "42 ms");
+        assert_eq!(format_latency(Duration::from_millis(2500)), "2.5 s");
+    }
+}
@@ -1,6 +1,12 @@
-use crate::ai::{build_minimal_context, AiEngine, ChatRequest, ContextConfig, LlmProvider};
+use crate::ai::{
+    build_minimal_context, AiEngine, AiFeature, ChatRequest, ContextConfig, LlmProvider, OverflowStrategy, ProviderHealth,
+    ResponseTiming,
+};
 use crate::core::Block;
-use egui::{ScrollArea, TextEdit, Ui};
+use crate::t;
+use crate::ui::accessibility::icon_button;
+use egui::{Color32, ScrollArea, Sense, TextEdit, Ui};
+use std::collections::HashMap;
 use std::sync::Arc;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -14,14 +20,33 @@ pub struct AiPanel {
     mode: AiPanelMode,
     prompt: String,
     response: String,
+    // Where the time for the last response went, when the provider reports it
+    response_timing: Option<ResponseTiming>,
     is_streaming: bool,
     selected_provider: String,
     selected_model: String,
     available_models: Vec<String>,
     pub include_context: bool,
     pub context_blocks: usize,
+    // What happens to context blocks that don't fit in the budget
+    pub context_overflow: OverflowStrategy,
     // Conversation history
     conversation: Vec<ConversationMessage>,
+    // Files dropped on the panel, sent along with prompts
+    attachments: Vec<Attachment>,
+    // Last background availability check of each provider
+    provider_health: HashMap<String, ProviderHealth>,
+    // Requests to the selected provider held back by its rate limit
+    waiting: usize,
+}
+
+/// Largest file that can be attached as context
+const MAX_ATTACHMENT_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub file_name: String,
+    pub content: String,
 }
 
 #[derive(Debug, Clone)]
@@ -29,6 +54,8 @@ pub struct ConversationMessage {
     pub role: MessageRole,
     pub content: String,
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// What the message was for; anything but Chat is labelled with it
+    pub feature: AiFeature,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -44,13 +71,18 @@ impl Default for AiPanel {
             mode: AiPanelMode::Closed,
             prompt: String::new(),
             response: String::new(),
+            response_timing: None,
             is_streaming: false,
             selected_provider: "ollama".to_string(),
             selected_model: String::new(),
             available_models: Vec::new(),
             include_context: true,
             context_blocks: 5,
+            context_overflow: OverflowStrategy::Drop,
             conversation: Vec::new(),
+            attachments: Vec::new(),
+            provider_health: HashMap::new(),
+            waiting: 0,
         }
     }
 }
@@ -60,6 +92,46 @@ impl AiPanel {
         Self::default()
     }
 
+    pub fn set_provider_health(&mut self, health: HashMap<String, ProviderHealth>) {
+        self.provider_health = health;
+    }
+
+    /// Colored dot for a provider: green when reachable, red when not, gray before the first check
+    fn health_dot(&self, ui: &mut Ui, provider: &str) {
+        let health = self
+            .provider_health
+            .get(provider)
+            .copied()
+            .unwrap_or(ProviderHealth::Unknown);
+        let (color, text) = match health {
+            ProviderHealth::Available => (Color32::from_rgb(80, 200, 120), "available"),
+            ProviderHealth::Unavailable => (Color32::from_rgb(230, 80, 80), "not reachable"),
+            ProviderHealth::Unknown => (Color32::GRAY, "not checked yet"),
+        };
+        let (rect, response) = ui.allocate_exact_size(egui::vec2(8.0, 8.0), Sense::hover());
+        ui.painter().circle_filled(rect.center(), 4.0, color);
+        let label = format!("{}: {}", provider, text);
+        response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Label, true, &label));
+        response.on_hover_text(label);
+    }
+
+    /// Provider choices in a combo box, each with its health dot
+    fn provider_options(&self, ui: &mut Ui, providers: &[String]) -> Option<AiAction> {
+        let mut action = None;
+        for provider in providers {
+            ui.horizontal(|ui| {
+                self.health_dot(ui, provider);
+                if ui
+                    .selectable_label(provider == &self.selected_provider, provider)
+                    .clicked()
+                {
+                    action = Some(AiAction::ProviderChanged(provider.clone()));
+                }
+            });
+        }
+        action
+    }
+
     pub fn mode(&self) -> &AiPanelMode {
         &self.mode
     }
@@ -96,25 +168,97 @@ impl AiPanel {
         }
     }
 
-    pub fn add_user_message(&mut self, content: String) {
+    pub fn add_user_message(&mut self, content: String, feature: AiFeature) {
         self.conversation.push(ConversationMessage {
             role: MessageRole::User,
             content,
             timestamp: chrono::Utc::now(),
+            feature,
         });
     }
 
-    pub fn add_assistant_message(&mut self, content: String) {
+    pub fn add_assistant_message(&mut self, content: String, feature: AiFeature) {
         self.conversation.push(ConversationMessage {
             role: MessageRole::Assistant,
             content,
             timestamp: chrono::Utc::now(),
+            feature,
+        });
+    }
+
+    /// Attach a text file as context for the following prompts
+    pub fn attach_file(&mut self, name: String, bytes: &[u8]) -> Result<(), String> {
+        if bytes.len() > MAX_ATTACHMENT_BYTES {
+            return Err(format!("{} is larger than {} KiB", name, MAX_ATTACHMENT_BYTES / 1024));
+        }
+        if bytes.contains(&0) {
+            return Err(format!("{} is not a text file", name));
+        }
+        let content = String::from_utf8_lossy(bytes).into_owned();
+        self.attachments.retain(|a| a.file_name != name);
+        self.attachments.push(Attachment { file_name: name, content });
+        Ok(())
+    }
+
+    pub fn attachments(&self) -> &[Attachment] {
+        &self.attachments
+    }
+
+    /// Attached files formatted for a prompt, or None if nothing is attached
+    pub fn attachment_context(&self) -> Option<String> {
+        if self.attachments.is_empty() {
+            return None;
+        }
+        let files: Vec<String> = self
+            .attachments
+            .iter()
+            .map(|a| format!("File `{}`:\n```\n{}\n```", a.file_name, a.content.trim_end()))
+            .collect();
+        Some(files.join("\n\n"))
+    }
+
+    /// Choice of what happens to context blocks that don't fit
+    fn overflow_picker(&mut self, ui: &mut Ui, id: &str) -> Option<AiAction> {
+        let mut action = None;
+        egui::ComboBox::from_id_source(id)
+            .selected_text(self.context_overflow.label())
+            .show_ui(ui, |ui| {
+                for strategy in OverflowStrategy::ALL {
+                    if ui
+                        .selectable_value(&mut self.context_overflow, strategy, strategy.label())
+                        .on_hover_text(strategy.description())
+                        .clicked()
+                    {
+                        action = Some(AiAction::OverflowChanged(strategy));
+                    }
+                }
+            })
+            .response
+            .on_hover_text(self.context_overflow.description());
+        action
+    }
+
+    /// Chips for attached files, each with a button to remove it
+    fn show_attachments(&mut self, ui: &mut Ui) {
+        let mut remove = None;
+        ui.horizontal_wrapped(|ui| {
+            for (i, attachment) in self.attachments.iter().enumerate() {
+                ui.label(format!("📎 {}", attachment.file_name))
+                    .on_hover_text(format!("{} bytes", attachment.content.len()));
+                if icon_button(ui, "✕", &t!("button-remove-attachment")).clicked() {
+                    remove = Some(i);
+                }
+            }
         });
+        if let Some(i) = remove {
+            self.attachments.remove(i);
+        }
     }
 
     pub fn clear_conversation(&mut self) {
         self.conversation.clear();
         self.response.clear();
+        self.response_timing = None;
     }
 
     /// Draw a compact AI panel (for bottom of screen)
@@ -126,19 +270,16 @@ impl AiPanel {
                 ui.label("🤖");
                 
                 // Provider selection
-                egui::ComboBox::from_id_source("ai_provider_compact")
+                self.health_dot(ui, &self.selected_provider);
+                if let Some(chosen) = egui::ComboBox::from_id_source("ai_provider_compact")
                     .selected_text(&self.selected_provider)
                     .width(100.0)
-                    .show_ui(ui, |ui| {
-                        for provider in providers {
-                            if ui
-                                .selectable_label(provider == &self.selected_provider, provider)
-                                .clicked()
-                            {
-                                action = Some(AiAction::ProviderChanged(provider.clone()));
-                            }
-                        }
-                    });
+                    .show_ui(ui, |ui| self.provider_options(ui, providers))
+                    .inner
+                    .flatten()
+                {
+                    action = Some(chosen);
+                }
 
                 ui.separator();
 
@@ -153,18 +294,28 @@ impl AiPanel {
                         .width(150.0)
                         .show_ui(ui, |ui| {
                             for model in &self.available_models {
-                                ui.selectable_value(&mut self.selected_model, model.clone(), model);
+                                if ui.selectable_value(&mut self.selected_model, model.clone(), model).clicked() {
+                                    action = Some(AiAction::ModelChanged(model.clone()));
+                                }
                             }
                         });
                 }
                 
                 ui.separator();
                 
-                ui.checkbox(&mut self.include_context, "Context");
+                ui.checkbox(&mut self.include_context, t!("ai-context"));
                 if self.include_context {
                     ui.add(egui::Slider::new(&mut self.context_blocks, 1..=20).text("blocks"));
+                    if let Some(changed) = self.overflow_picker(ui, "ai_overflow_compact") {
+                        action = Some(changed);
+                    }
                 }
             });
+            if !self.attachments.is_empty() {
+                self.show_attachments(ui);
+            } else if ui.ctx().input(|i| !i.raw.hovered_files.is_empty()) {
+                ui.label(egui::RichText::new(t!("ai-drop-files")).weak().small());
+            }
         });
 
         action
@@ -174,31 +325,28 @@ impl AiPanel {
     pub fn show_sidebar(&mut self, ui: &mut Ui, providers: &[String]) -> Option<AiAction> {
         let mut action = None;
 
-        ui.heading("🤖 AI Assistant");
+        ui.heading(t!("ai-assistant"));
         ui.separator();
 
         // Provider selection
         ui.horizontal(|ui| {
-            ui.label("Provider:");
-            egui::ComboBox::from_id_source("ai_provider")
+            ui.label(t!("menu-provider"));
+            self.health_dot(ui, &self.selected_provider);
+            if let Some(chosen) = egui::ComboBox::from_id_source("ai_provider")
                 .selected_text(&self.selected_provider)
-                .show_ui(ui, |ui| {
-                    for provider in providers {
-                        if ui
-                            .selectable_label(provider == &self.selected_provider, provider)
-                            .clicked()
-                        {
-                            action = Some(AiAction::ProviderChanged(provider.clone()));
-                        }
-                    }
-                });
+                .show_ui(ui, |ui| self.provider_options(ui, providers))
+                .inner
+                .flatten()
+            {
+                action = Some(chosen);
+            }
         });
 
         // Model selection
         ui.horizontal(|ui| {
-            ui.label("Model:");
+            ui.label(t!("menu-model"));
             if self.available_models.is_empty() {
-                if ui.button("Load Models").clicked() {
+                if ui.button(t!("ai-load-models")).clicked() {
                     action = Some(AiAction::LoadModels);
                 }
             } else {
@@ -206,7 +354,9 @@ impl AiPanel {
                     .selected_text(&self.selected_model)
                     .show_ui(ui, |ui| {
                         for model in &self.available_models {
-                            ui.selectable_value(&mut self.selected_model, model.clone(), model);
+                            if ui.selectable_value(&mut self.selected_model, model.clone(), model).clicked() {
+                                action = Some(AiAction::ModelChanged(model.clone()));
+                            }
                         }
                     });
             }
@@ -215,18 +365,27 @@ impl AiPanel {
         ui.separator();
 
         // Context options
-        ui.checkbox(&mut self.include_context, "Include command history");
+        ui.checkbox(&mut self.include_context, t!("ai-include-history"));
         if self.include_context {
             ui.horizontal(|ui| {
-                ui.label("Recent blocks:");
+                ui.label(t!("ai-recent-blocks"));
                 ui.add(egui::Slider::new(&mut self.context_blocks, 1..=20));
             });
+            ui.horizontal(|ui| {
+                ui.label(t!("ai-overflow"));
+                if let Some(changed) = self.overflow_picker(ui, "ai_overflow") {
+                    action = Some(changed);
+                }
+            });
+        }
+        if !self.attachments.is_empty() {
+            self.show_attachments(ui);
         }
 
         ui.separator();
 
         // Conversation history
-        ui.label("Conversation:");
+        ui.label(t!("ai-conversation"));
         ScrollArea::vertical()
             .id_source("conversation_scroll")
             .max_height(200.0)
@@ -240,6 +399,9 @@ impl AiPanel {
 
                     ui.horizontal(|ui| {
                         ui.label(egui::RichText::new(icon).color(color));
+                        if msg.feature != AiFeature::Chat {
+                            ui.label(egui::RichText::new(msg.feature.label()).weak().small());
+                        }
                         ui.label(
                             egui::RichText::new(&msg.content)
                                 .color(color)
@@ -253,12 +415,12 @@ impl AiPanel {
         ui.separator();
 
         // Prompt input
-        ui.label("Ask AI:");
+        ui.label(t!("ai-ask"));
         let response = ui.add(
             TextEdit::multiline(&mut self.prompt)
                 .desired_rows(3)
                 .desired_width(f32::INFINITY)
-                .hint_text("Type your question..."),
+                .hint_text(t!("ai-prompt-hint")),
         );
 
         if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
@@ -270,8 +432,8 @@ impl AiPanel {
 
         ui.horizontal(|ui| {
             if ui
-                .button("Send")
-                .on_hover_text("Send prompt to AI (Enter)")
+                .button(t!("ai-send"))
+                .on_hover_text(t!("ai-send-hint"))
                 .clicked()
                 && !self.prompt.trim().is_empty()
             {
@@ -279,7 +441,7 @@ impl AiPanel {
                 self.prompt.clear();
             }
 
-            if ui.button("Clear").clicked() {
+            if ui.button(t!("ai-clear")).clicked() {
                 self.clear_conversation();
             }
         });
@@ -287,7 +449,7 @@ impl AiPanel {
         // Response area
         if !self.response.is_empty() {
             ui.separator();
-            ui.label("Response:");
+            ui.label(t!("ai-response"));
             ScrollArea::vertical()
                 .id_source("response_scroll")
                 .max_height(150.0)
@@ -295,11 +457,25 @@ impl AiPanel {
                 .show(ui, |ui| {
                     ui.label(&self.response);
                 });
+            if let Some(timing) = &self.response_timing {
+                let text = t!(
+                    "ai-timing",
+                    load = format!("{:.2}", timing.load.as_secs_f64()),
+                    prompt = format!("{:.2}", timing.prompt.as_secs_f64()),
+                    generation = format!("{:.2}", timing.generation.as_secs_f64())
+                );
+                ui.label(egui::RichText::new(text).weak().small()).on_hover_text(t!("ai-timing-hint"));
+            }
         }
 
         if self.is_streaming {
             ui.spinner();
-            ui.label("Receiving response...");
+            if self.waiting > 0 {
+                ui.label(t!("ai-waiting-rate-limit", count = self.waiting))
+                    .on_hover_text(t!("ai-waiting-rate-limit-hint"));
+            } else {
+                ui.label(t!("ai-receiving"));
+            }
         }
 
         action
@@ -310,6 +486,18 @@ impl AiPanel {
         self.is_streaming = false;
     }
 
+    pub fn set_waiting(&mut self, waiting: usize) {
+        self.waiting = waiting;
+    }
+
+    pub fn waiting(&self) -> usize {
+        self.waiting
+    }
+
+    pub fn set_response_timing(&mut self, timing: Option<ResponseTiming>) {
+        self.response_timing = timing;
+    }
+
     pub fn append_response(&mut self, chunk: String) {
         self.response.push_str(&chunk);
     }
@@ -317,6 +505,7 @@ impl AiPanel {
     pub fn start_streaming(&mut self) {
         self.is_streaming = true;
         self.response.clear();
+        self.response_timing = None;
     }
 
     pub fn stop_streaming(&mut self) {
@@ -330,11 +519,18 @@ impl AiPanel {
     pub fn selected_model(&self) -> &str {
         &self.selected_model
     }
+
+    pub fn available_models(&self) -> &[String] {
+        &self.available_models
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum AiAction {
     ProviderChanged(String),
+    ModelChanged(String),
+    /// The context overflow strategy was picked, to be saved
+    OverflowChanged(OverflowStrategy),
     LoadModels,
     SendPrompt(String),
 }
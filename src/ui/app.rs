@@ -1,17 +1,75 @@
-use crate::ai::{build_minimal_context, AiEngine, ChatRequest, ContextConfig};
-use crate::ai::providers::{GroqProvider, OllamaProvider, OpenAiProvider};
-use crate::config::Config;
-use crate::core::{Block, BlockManager, Database, ExportedSession, Session, SessionManager};
-use crate::shell::{OutputLine, ShellExecutor};
+use crate::ai::{build_command_context, build_history_context, BuiltContext, parse_command_candidates, parse_command_explanation, CommandExplanation, AiEngine, AiError, ChatRequest, ChatResponse, ContextConfig, AiFeature, AiOrigin, AiRequests, RateLimit, ResponseTiming};
+use crate::ai::providers::{is_local_url, GroqProvider, OllamaProvider, OpenAiProvider};
+use crate::config::{AiConfig, AiProviderConfig, BlockActionConfig, BlockActionResult, Config, CopyOnSelect, ScriptingConfig, StorageConfig, SyncBackendConfig, WindowConfig};
+use crate::core::{
+    backup, block::{environment_diff, environment_snapshot}, bookmarks, crash, retry, frequency, group_sessions_by_folder, journal, session_name_taken, session_subtitle, unique_session_name, AiAuditEntry, AiProvenance, AiRequestKind, BackupInfo, Block, BlockFilter, BlockManager, CommandHistory, CommandUsage, CrashReport, DataArchive, Database, DiagnosticScan, DirectoryBookmark, ExportedSession,
+    FieldCipher, HookResult, Journal, OutputBuffer, RestoreMode, RestoreReport, Scheduler, SectionScan, Session, SessionColor, SessionInfo, SessionManager, Trigger, TriggerAction, TriggerHit, TriggerMatcher,
+};
+use crate::shell::{self, danger, expansion, lint, ActionInput, Finding, HookRunner, OutputLine, PtyInput, ShellExecutor};
+use crate::plugins::{self, Plugin, PluginHost, PluginPanel, PluginRequest, PluginResponse, SessionPayload};
+use crate::scripting::ScriptEngine;
+use crate::share::{self, Redactor, ShareTarget};
+use crate::sync::{self, SyncReport};
+use crate::syntax::{OutputHighlight, SyntaxHighlighter};
 use crate::theme::ThemeLoader;
-use crate::ui::{AiAction, AiPanel, BlockWidget};
+use crate::ui::accessibility::icon_button;
+use crate::ui::block_image::BlockCapture;
+use crate::ui::block_widget::RetryStatus;
+use crate::ui::ai_audit_view::AiAuditView;
+use crate::ui::ai_diagnostics::{self, DiagnosticsAction};
+use crate::ui::debug_overlay::{DebugStats, SaveTimings};
+use crate::ui::file_browser::{FileBrowser, FileBrowserAction};
+use crate::ui::git_panel::{GitPanel, GitPanelAction};
+use crate::ui::history_view::HistoryView;
+use crate::ui::hotkey::{GlobalHotkey, SlideDown};
+use crate::ui::log_viewer::LogViewer;
+use crate::ui::presentation::Presentation;
+use crate::ui::selection::{self, SelectionCopy};
+use crate::ui::path_picker::PathPicker;
+use crate::ui::terminal_view::{self, TerminalView};
+use crate::ui::toast::Toasts;
+use crate::ui::{AiAction, AiPanel, BlockWidget, PromptBar, VimMode, VimState};
+use crate::utils::direnv::{self, DirenvStatus};
+use crate::utils::ssh_config::{self, SshHost};
+use crate::utils::keybindings::{self, parse_shortcut, KeyAction};
+use crate::utils::git::{self, FileChange};
+use crate::utils::calc;
+use crate::utils::launch;
+use crate::utils::kube::KubeConfig;
+use crate::utils::runtime_env;
+use crate::utils::{abbreviate_home, insertable_path, shell_escape, GitStatus, ProjectRuntimes};
+use crate::workflows::{self, WorkflowStore};
+use crate::t;
 use egui::{CentralPanel, Color32, Context, RichText, ScrollArea, TopBottomPanel, ViewportCommand};
-use std::path::PathBuf;
-use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
+/// Longest output streaming into a running block may go unsaved
+const OUTPUT_SAVE_DELAY: Duration = Duration::from_secs(60);
+
+/// Longest the final save waits for auto-saves still in flight on exit
+const SHUTDOWN_SAVE_WAIT: Duration = Duration::from_secs(5);
+
+/// Most distinct commands loaded for ranking suggestions
+const COMMAND_USAGE_LIMIT: i64 = 2000;
+
+/// Sessions listed under File > Recent Sessions
+const RECENT_SESSIONS: usize = 8;
+
+/// Output lines sent along when asking the AI about a failed command
+const EXIT_EXPLANATION_LINES: usize = 30;
+
+/// How often AI providers are checked in the background for the health dots
+const AI_HEALTH_INTERVAL: Duration = Duration::from_secs(60);
+/// Newest AI audit entries loaded into the audit view
+const AI_AUDIT_ENTRIES_SHOWN: i64 = 500;
+
 pub struct ImmateriumApp {
     config: Config,
     command_input: String,
@@ -21,35 +79,265 @@ pub struct ImmateriumApp {
     session_manager: Option<SessionManager>,
     current_block_id: Option<Uuid>,
     output_receiver: Option<mpsc::UnboundedReceiver<OutputMessage>>,
-    ai_receiver: Option<mpsc::UnboundedReceiver<AiMessage>>,
+    // Keyboard input and resizes for the running command's terminal
+    pty_input: Option<std::sync::mpsc::Sender<PtyInput>>,
+    // Full-screen program (vim, htop, ...) currently on the alternate screen
+    terminal_view: Option<TerminalView>,
+    // AI requests in flight, each answering under its own id
+    ai_requests: AiRequests<AiMessage>,
     context_menu_block: Option<Uuid>,
     context_menu_pos: Option<egui::Pos2>,
     context_menu_opened_at: Option<Instant>,
+    /// Move keyboard focus into the context menu on its first frame
+    focus_context_menu: bool,
+    /// Suggested command whose Execute button should take keyboard focus
+    focus_approval: Option<Uuid>,
     last_save: Instant,
+    /// Blocks handed to the database by auto-save that are not written yet
+    pending_writes: Arc<AtomicUsize>,
+    show_debug_overlay: bool,
+    log_viewer: LogViewer,
+    debug_stats: DebugStats,
     save_needed: bool,
+    /// When output first arrived that auto-save has not written yet
+    output_unsaved_since: Option<Instant>,
+    save_timings: Arc<Mutex<SaveTimings>>,
+    /// Block changes since the last save, replayed after a crash
+    journal: Option<Journal>,
+    /// Reports from earlier crashes not yet shown
+    crash_reports: Vec<CrashReport>,
     // Session UI state
     show_session_list: bool,
     show_new_session_dialog: bool,
     new_session_name: String,
     available_sessions: Vec<crate::core::SessionInfo>,
     show_export_dialog: bool,
+    export_passphrase: String,
+    export_passphrase_confirm: String,
+    show_import_dialog: bool,
+    import_path: String,
+    import_passphrase: String,
+    import_status: Option<String>,
+    /// Imported session being saved in the background
+    importing_session: Option<Uuid>,
     // Theme
     theme_loader: ThemeLoader,
     show_theme_selector: bool,
     // AI
     ai_panel: AiPanel,
     ai_engine: Option<Arc<AiEngine>>,
-    // Natural language command generation state
-    original_nl_input: String,
-    is_generating_command: bool,
+    // Providers are set up off the UI thread; the engine arrives here
+    ai_engine_receiver: Option<mpsc::UnboundedReceiver<Option<AiEngine>>>,
+    last_ai_health_check: Option<Instant>,
+    show_ai_diagnostics: bool,
+    ai_audit_view: AiAuditView,
+    ai_audit_receiver: Option<mpsc::UnboundedReceiver<Vec<AiAuditEntry>>>,
+    /// Audit entries of generated commands waiting for approval, by block
+    ai_audit_commands: HashMap<Uuid, Uuid>,
+    /// Alternatives offered on a suggested command, by block
+    command_candidates: HashMap<Uuid, Vec<String>>,
+    /// Explanations of suggested commands by block and command; `None` while requested
+    command_explanations: HashMap<(Uuid, String), Option<CommandExplanation>>,
+    /// Problems found checking suggested commands, by block and command; `None` while checking
+    command_findings: HashMap<(Uuid, String), Option<Vec<Finding>>>,
+    findings_sender: mpsc::UnboundedSender<(Uuid, String, Vec<Finding>)>,
+    findings_receiver: mpsc::UnboundedReceiver<(Uuid, String, Vec<Finding>)>,
+    /// Typed commands back from being checked, to run or hold
+    typed_lint_sender: mpsc::UnboundedSender<(String, Vec<Finding>)>,
+    typed_lint_receiver: mpsc::UnboundedReceiver<(String, Vec<Finding>)>,
     // Command history
-    command_history: Vec<String>,
-    history_index: Option<usize>,
-    current_input_buffer: String, // Saves the current input when navigating history
+    command_history: CommandHistory,
+    // Vim editing mode state for the command input
+    vim: VimState,
+    // Git status of the session's working directory
+    git_status: Option<GitStatus>,
+    git_receiver: Option<mpsc::UnboundedReceiver<Option<GitStatus>>>,
+    // Virtualenv / Node version the project in the working directory expects
+    project_runtimes: ProjectRuntimes,
+    // Git sidebar: changes and diffs arrive on one channel
+    git_panel: GitPanel,
+    git_panel_sender: mpsc::UnboundedSender<GitPanelUpdate>,
+    git_panel_receiver: mpsc::UnboundedReceiver<GitPanelUpdate>,
+    file_browser: FileBrowser,
+    path_picker: PathPicker,
+    // Aliases defined by ~/.bashrc, used for the expansion preview
+    shell_aliases: HashMap<String, String>,
+    alias_receiver: Option<mpsc::UnboundedReceiver<HashMap<String, String>>>,
+    // Active quick filters for the block list (empty shows everything)
+    block_filters: HashSet<BlockFilter>,
+    // Blocks expanded from their single row in log view
+    expanded_log_rows: HashSet<Uuid>,
+    // Session folders created in the Open Session dialog but still empty
+    pending_session_folders: Vec<String>,
+    new_folder_name: String,
+    // Session settings dialog
+    show_session_settings: bool,
+    description_draft: String,
+    color_draft: Option<SessionColor>,
+    startup_commands_draft: String,
+    triggers_draft: Vec<Trigger>,
+    triggers_error: Option<String>,
+    // Startup commands waiting to run, one at a time
+    startup_queue: VecDeque<String>,
+    // .env file tracking for the current session
+    env_file_draft: String,
+    env_file_error: Option<String>,
+    env_file_modified: Option<SystemTime>,
+    env_file_changed: bool,
+    last_env_file_check: Instant,
+    reveal_env_values: bool,
+    // direnv: environment exported by an allowed .envrc for the working directory
+    direnv_environment: HashMap<String, String>,
+    direnv_blocked: Option<PathBuf>,
+    direnv_dir: Option<PathBuf>,
+    direnv_receiver: Option<mpsc::UnboundedReceiver<DirenvStatus>>,
+    // Directory whose startup commands, .env and direnv wait on being trusted
+    untrusted_workspace: Option<PathBuf>,
+    // Other sessions that typed commands are also run in, and how the last
+    // broadcast went
+    show_broadcast: bool,
+    broadcast_targets: HashSet<Uuid>,
+    broadcast_pending: usize,
+    broadcast_failed: Vec<String>,
+    // Typed command to broadcast once it has passed this session's script and hooks
+    broadcast_after_start: Option<Uuid>,
+    // Bumped to stop the broadcast commands still running
+    broadcast_cancel: tokio::sync::watch::Sender<u64>,
+    // SSH host picker
+    show_ssh_picker: bool,
+    ssh_hosts: Vec<SshHost>,
+    ssh_query: String,
+    // Directory quick-jump: visited and pinned directories
+    show_quick_jump: bool,
+    quick_jump_query: String,
+    quick_jump_selected: usize,
+    directory_bookmarks: Vec<DirectoryBookmark>,
+    bookmark_receiver: Option<mpsc::UnboundedReceiver<Vec<DirectoryBookmark>>>,
+    // Commands run across all sessions, for frequency-ranked suggestions
+    command_usage: Vec<CommandUsage>,
+    // Sortable table of the session's commands, and the block it asked to show
+    history_view: HistoryView,
+    scroll_to_block: Option<Uuid>,
+    // Divider insertion and clearing the session's blocks
+    show_divider_dialog: bool,
+    divider_title: String,
+    confirm_clear_blocks: bool,
+    // Typed command matching a [dangerous_commands] pattern, and that pattern
+    confirm_dangerous: Option<(String, String)>,
+    // Blocks compared in the environment diff window, before and after
+    environment_diff: Option<(Uuid, Uuid)>,
+    // Keyboard shortcut reference
+    show_shortcuts: bool,
+    shortcuts_query: String,
+    // Kubernetes context from kubeconfig
+    kube_config: Option<KubeConfig>,
+    // Workflow library
+    show_workflows: bool,
+    // Application settings dialog
+    show_settings: bool,
+    database_size: Option<u64>,
+    storage_status: Option<String>,
+    /// Retention or compaction running in the background
+    storage_busy: bool,
+    // Session sync with the configured backend
+    sync_receiver: Option<mpsc::UnboundedReceiver<Result<SyncReport, String>>>,
+    sync_status: Option<String>,
+    last_sync: Option<Instant>,
+    // Database backups and the restore dialog
+    last_backup: Option<SystemTime>,
+    show_restore_dialog: bool,
+    backups: Vec<BackupInfo>,
+    restore_candidate: Option<PathBuf>,
+    backup_status: Option<String>,
+    /// A backup or restore running in the background
+    backup_busy: bool,
+    // Export/import of all sessions and settings, for moving machines
+    show_data_archive: bool,
+    archive_path: String,
+    archive_passphrase: String,
+    archive_passphrase_confirm: String,
+    archive_include_settings: bool,
+    archive_restore_mode: RestoreMode,
+    archive_status: Option<String>,
+    /// An export or import running in the background
+    archive_busy: bool,
+    // Share dialog: redacted export under review, and the upload in flight
+    share_draft: Option<ShareDraft>,
+    share_receiver: Option<mpsc::UnboundedReceiver<Result<String, String>>>,
+    // Plugins: replies from plugin processes arrive on one channel
+    plugin_host: Option<PluginHost>,
+    plugin_sender: mpsc::UnboundedSender<PluginReply>,
+    plugin_receiver: mpsc::UnboundedReceiver<PluginReply>,
+    plugin_panel: Option<PluginPanelView>,
+    plugin_renders: HashMap<Uuid, String>,
+    plugin_status: Option<String>,
+    // Window layout last written to the config file
+    saved_window: WindowConfig,
+    // System-wide hotkey that summons the window, and its slide-down animation
+    global_hotkey: Option<GlobalHotkey>,
+    slide_down: Option<SlideDown>,
+    // Where the AI panel was drawn, so files dropped on it attach instead
+    ai_panel_rect: Option<egui::Rect>,
+    // Pre/post command hooks from config, run with every command
+    hook_runner: HookRunner,
+    // User-defined block actions from config, finished on one channel
+    action_sender: mpsc::UnboundedSender<BlockActionReply>,
+    action_receiver: mpsc::UnboundedReceiver<BlockActionReply>,
+    // Files read in the background to attach to the AI context
+    attach_sender: mpsc::UnboundedSender<(String, Result<Vec<u8>, String>)>,
+    attach_receiver: mpsc::UnboundedReceiver<(String, Result<Vec<u8>, String>)>,
+    session_sender: mpsc::UnboundedSender<SessionMessage>,
+    session_receiver: mpsc::UnboundedReceiver<SessionMessage>,
+    /// Session being loaded in the background; the latest request wins
+    loading_session: Option<Uuid>,
+    session_list: SessionListState,
+    /// Outcome of the last user-defined block action
+    action_status: Option<String>,
+    toasts: Toasts,
+    // Rhai hooks from init.rhai
+    scripts: Option<ScriptEngine>,
+    script_status: Option<String>,
+    workflow_store: Option<WorkflowStore>,
+    workflow_query: String,
+    workflow_import_path: String,
+    workflow_import_status: Option<String>,
+    // Blocks re-run on an interval (watch mode)
+    watched_blocks: HashMap<Uuid, Duration>,
+    watch_interval_secs: u64,
+    // Blocks whose last run failed with a transient error, and those being
+    // retried automatically with backoff
+    transient_failures: HashSet<Uuid>,
+    retries: HashMap<Uuid, ScheduledRetry>,
+    // Error and warning lines found in each block's output so far
+    diagnostic_scans: HashMap<Uuid, DiagnosticScan>,
+    // Build tool phases found in each block's output so far
+    section_scans: HashMap<Uuid, SectionScan>,
+    // Syntax colors of finished blocks' output
+    output_highlights: HashMap<Uuid, OutputHighlight>,
+    // Copy-on-select for text selected in output
+    selection_copy: SelectionCopy,
+    // Masks secrets in shown and copied output when [redaction] is enabled,
+    // and each block's masked output
+    redactor: Option<Redactor>,
+    redacted_outputs: HashMap<Uuid, (usize, OutputBuffer)>,
+    // Read-only, full-window walk through the blocks
+    presentation: Presentation,
+    // Block being painted off to the side for "Copy as image"
+    block_capture: Option<BlockCapture>,
+    // Commands scheduled within the session; due ones (and those queued by
+    // triggers) wait in command_queue until nothing else is running
+    scheduler: Scheduler,
+    command_queue: VecDeque<String>,
+    show_scheduler: bool,
+    schedule_command_draft: String,
+    schedule_spec_draft: String,
+    schedule_error: Option<String>,
 }
 
 impl ImmateriumApp {
     pub fn new(cc: &eframe::CreationContext<'_>, config: Config) -> Self {
+        crate::i18n::set_language(&config.general.language);
+
         // Initialize theme loader
         let mut theme_loader = ThemeLoader::new();
         
@@ -63,6 +351,7 @@ impl ImmateriumApp {
         
         // Apply theme to egui context
         theme_loader.apply_to_egui(&cc.egui_ctx);
+        SelectionCopy::install(&cc.egui_ctx);
         
         // Customize egui style
         let mut style = (*cc.egui_ctx.style()).clone();
@@ -78,35 +367,68 @@ impl ImmateriumApp {
         let working_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
         let mut session = Session::new("default".to_string(), working_dir.clone());
         
+        let project_root = if config.general.project_sessions {
+            GitStatus::repo_root(&working_dir)
+        } else {
+            None
+        };
+
         let runtime = tokio::runtime::Runtime::new()
             .expect("Failed to create tokio runtime");
 
         // Initialize database and session manager
         let session_manager = runtime.block_on(async {
-            let db_path = PathBuf::from("immaterium.db");
-            match Database::new(db_path).await {
+            match Database::new(database_path()).await {
                 Ok(db) => {
                     tracing::info!("Database initialized successfully");
                     
                     match SessionManager::new(db).await {
                         Ok(sm) => {
-                            // Try to load active session
-                            match sm.get_active_session().await {
-                                Ok(Some(loaded_session)) => {
-                                    tracing::info!("Loaded active session: {}", loaded_session.name);
-                                    session = loaded_session;
+                            let sm = Self::configure_encryption(sm, &config.storage).await;
+                            if let Some(dir) = journal_dir() {
+                                match journal::replay(&dir, &sm).await {
+                                    Ok(0) => {}
+                                    Ok(n) => tracing::info!("Recovered {} blocks left unsaved by the last run", n),
+                                    Err(e) => tracing::error!("Failed to replay journal: {:#}", e),
                                 }
-                                Ok(None) => {
-                                    tracing::info!("No active session found, creating new one");
-                                    // Create new session in database
-                                    if let Err(e) = sm.create_session(&session).await {
-                                        tracing::error!("Failed to create session: {}", e);
-                                    } else if let Err(e) = sm.set_active_session(&session.id).await {
-                                        tracing::error!("Failed to set active session: {}", e);
-                                    }
+                            }
+                            let storage = &config.storage;
+                            if storage.max_block_age_days > 0 || storage.max_blocks_per_session > 0 {
+                                if let Err(e) = sm.apply_retention(
+                                    storage.max_block_age_days,
+                                    storage.max_blocks_per_session,
+                                    retention_archive(storage).as_deref(),
+                                ).await {
+                                    tracing::error!("Failed to apply retention: {}", e);
                                 }
-                                Err(e) => {
-                                    tracing::error!("Failed to load active session: {}", e);
+                            }
+
+                            let project_session = match &project_root {
+                                Some(root) => Self::open_project_session(&sm, root, &working_dir).await,
+                                None => None,
+                            };
+
+                            if let Some(project_session) = project_session {
+                                session = project_session;
+                            } else {
+                                // Try to load active session
+                                match sm.get_active_session().await {
+                                    Ok(Some(loaded_session)) => {
+                                        tracing::info!("Loaded active session: {}", loaded_session.name);
+                                        session = loaded_session;
+                                    }
+                                    Ok(None) => {
+                                        tracing::info!("No active session found, creating new one");
+                                        // Create new session in database
+                                        if let Err(e) = sm.create_session(&session).await {
+                                            tracing::error!("Failed to create session: {}", e);
+                                        } else if let Err(e) = sm.set_active_session(&session.id).await {
+                                            tracing::error!("Failed to set active session: {}", e);
+                                        }
+                                    }
+                                    Err(e) => {
+                                        tracing::error!("Failed to load active session: {}", e);
+                                    }
                                 }
                             }
                             Some(sm)
@@ -130,17 +452,34 @@ impl ImmateriumApp {
             block_manager.add_block(block.clone());
         }
 
-        // Initialize AI engine before moving config
-        let ai_engine = Self::initialize_ai_engine(&config).map(Arc::new);
-        
         // Initialize AI panel with saved model
         let mut ai_panel = AiPanel::new();
         if let Some(ref model) = config.ai.selected_model {
             ai_panel.set_selected_model(model.clone());
         }
         ai_panel.set_selected_provider(config.ai.default_provider.clone());
+        ai_panel.context_overflow = config.ai.context_overflow;
 
-        Self {
+        let config_max_history = config.general.max_history_size;
+        let ai_requests = AiRequests::new(config.ai.max_concurrent_requests);
+        let (plugin_sender, plugin_receiver) = mpsc::unbounded_channel();
+        let (findings_sender, findings_receiver) = mpsc::unbounded_channel();
+        let (typed_lint_sender, typed_lint_receiver) = mpsc::unbounded_channel();
+        let (git_panel_sender, git_panel_receiver) = mpsc::unbounded_channel();
+        let (scripts, script_status) = load_scripts(&config.scripting);
+        let mut hook_runner = HookRunner::new(&config.hooks);
+        hook_runner.set_offline(config.general.offline);
+        let config_window = config.window.clone();
+        let journal = open_journal(&config.storage);
+        let global_hotkey = config.global_hotkey.shortcut.as_deref().and_then(|shortcut| {
+            GlobalHotkey::register(shortcut, &cc.egui_ctx)
+                .map_err(|e| tracing::warn!("{}", e))
+                .ok()
+        });
+        let (action_sender, action_receiver) = mpsc::unbounded_channel();
+        let (attach_sender, attach_receiver) = mpsc::unbounded_channel();
+        let (session_sender, session_receiver) = mpsc::unbounded_channel();
+        let mut app = Self {
             config,
             command_input: String::new(),
             session,
@@ -148,28 +487,428 @@ impl ImmateriumApp {
             runtime,
             session_manager,
             current_block_id: None,
+            pty_input: None,
+            terminal_view: None,
             output_receiver: None,
             context_menu_block: None,
             context_menu_pos: None,
             context_menu_opened_at: None,
+            focus_context_menu: false,
+            focus_approval: None,
             last_save: Instant::now(),
+            pending_writes: Arc::new(AtomicUsize::new(0)),
+            show_debug_overlay: false,
+            log_viewer: LogViewer::default(),
+            debug_stats: DebugStats::default(),
             save_needed: false,
+            output_unsaved_since: None,
+            save_timings: Arc::new(Mutex::new(SaveTimings::default())),
+            journal,
+            crash_reports: crash_dir().map(|dir| crash::unseen_reports(&dir)).unwrap_or_default(),
             show_session_list: false,
             show_new_session_dialog: false,
             new_session_name: String::new(),
             available_sessions: Vec::new(),
             show_export_dialog: false,
+            export_passphrase: String::new(),
+            export_passphrase_confirm: String::new(),
+            show_import_dialog: false,
+            import_path: String::new(),
+            import_passphrase: String::new(),
+            import_status: None,
+            importing_session: None,
             theme_loader,
             show_theme_selector: false,
             ai_panel,
-            ai_engine,
-            ai_receiver: None,
-            original_nl_input: String::new(),
-            is_generating_command: false,
-            command_history: Vec::new(),
-            history_index: None,
-            current_input_buffer: String::new(),
+            ai_engine: None,
+            ai_engine_receiver: None,
+            last_ai_health_check: None,
+            show_ai_diagnostics: false,
+            ai_audit_view: AiAuditView::default(),
+            ai_audit_receiver: None,
+            ai_audit_commands: HashMap::new(),
+            command_candidates: HashMap::new(),
+            command_explanations: HashMap::new(),
+            command_findings: HashMap::new(),
+            findings_sender,
+            findings_receiver,
+            typed_lint_sender,
+            typed_lint_receiver,
+            ai_requests,
+            command_history: CommandHistory::new(config_max_history),
+            vim: VimState::new(),
+            git_status: None,
+            git_receiver: None,
+            project_runtimes: ProjectRuntimes::default(),
+            git_panel: GitPanel::default(),
+            git_panel_sender,
+            git_panel_receiver,
+            file_browser: FileBrowser::default(),
+            path_picker: PathPicker::default(),
+            shell_aliases: HashMap::new(),
+            alias_receiver: None,
+            block_filters: HashSet::new(),
+            expanded_log_rows: HashSet::new(),
+            pending_session_folders: Vec::new(),
+            new_folder_name: String::new(),
+            show_session_settings: false,
+            description_draft: String::new(),
+            color_draft: None,
+            startup_commands_draft: String::new(),
+            triggers_draft: Vec::new(),
+            triggers_error: None,
+            startup_queue: VecDeque::new(),
+            env_file_draft: String::new(),
+            env_file_error: None,
+            env_file_modified: None,
+            env_file_changed: false,
+            last_env_file_check: Instant::now(),
+            reveal_env_values: false,
+            direnv_environment: HashMap::new(),
+            direnv_blocked: None,
+            direnv_dir: None,
+            direnv_receiver: None,
+            untrusted_workspace: None,
+            show_broadcast: false,
+            broadcast_targets: HashSet::new(),
+            broadcast_pending: 0,
+            broadcast_failed: Vec::new(),
+            broadcast_after_start: None,
+            broadcast_cancel: tokio::sync::watch::channel(0).0,
+            show_ssh_picker: false,
+            ssh_hosts: Vec::new(),
+            ssh_query: String::new(),
+            show_quick_jump: false,
+            quick_jump_query: String::new(),
+            quick_jump_selected: 0,
+            directory_bookmarks: Vec::new(),
+            bookmark_receiver: None,
+            command_usage: Vec::new(),
+            history_view: HistoryView::default(),
+            scroll_to_block: None,
+            show_divider_dialog: false,
+            divider_title: String::new(),
+            confirm_clear_blocks: false,
+            confirm_dangerous: None,
+            environment_diff: None,
+            show_shortcuts: false,
+            shortcuts_query: String::new(),
+            kube_config: None,
+            show_workflows: false,
+            show_settings: false,
+            database_size: None,
+            storage_status: None,
+            storage_busy: false,
+            sync_receiver: None,
+            sync_status: None,
+            last_sync: None,
+            last_backup: backup_dir()
+                .and_then(|dir| backup::list_backups(&dir).ok())
+                .and_then(|backups| backups.first().map(|b| b.created.into())),
+            show_restore_dialog: false,
+            backups: Vec::new(),
+            restore_candidate: None,
+            backup_status: None,
+            backup_busy: false,
+            show_data_archive: false,
+            archive_path: String::new(),
+            archive_passphrase: String::new(),
+            archive_passphrase_confirm: String::new(),
+            archive_include_settings: false,
+            archive_restore_mode: RestoreMode::Merge,
+            archive_status: None,
+            archive_busy: false,
+            share_draft: None,
+            share_receiver: None,
+            plugin_host: PluginHost::open_default(),
+            plugin_sender,
+            plugin_receiver,
+            plugin_panel: None,
+            plugin_renders: HashMap::new(),
+            plugin_status: None,
+            saved_window: config_window,
+            global_hotkey,
+            slide_down: None,
+            ai_panel_rect: None,
+            hook_runner,
+            action_sender,
+            action_receiver,
+            attach_sender,
+            attach_receiver,
+            session_sender,
+            session_receiver,
+            loading_session: None,
+            session_list: SessionListState::default(),
+            action_status: None,
+            toasts: Toasts::default(),
+            scripts,
+            script_status,
+            workflow_store: None,
+            workflow_query: String::new(),
+            workflow_import_path: String::new(),
+            workflow_import_status: None,
+            watched_blocks: HashMap::new(),
+            transient_failures: HashSet::new(),
+            retries: HashMap::new(),
+            diagnostic_scans: HashMap::new(),
+            section_scans: HashMap::new(),
+            output_highlights: HashMap::new(),
+            selection_copy: SelectionCopy::default(),
+            redactor: None,
+            redacted_outputs: HashMap::new(),
+            presentation: Presentation::default(),
+            block_capture: None,
+            watch_interval_secs: 2,
+            scheduler: Scheduler::new(),
+            command_queue: VecDeque::new(),
+            show_scheduler: false,
+            schedule_command_draft: String::new(),
+            schedule_spec_draft: String::new(),
+            schedule_error: None,
+        };
+
+        app.update_redactor();
+        app.load_command_history();
+        app.load_command_usage();
+        app.start_ai_engine(&cc.egui_ctx);
+        app.refresh_git_status(&cc.egui_ctx);
+        app.refresh_kube_context();
+        app.refresh_project_runtimes();
+        app.load_shell_aliases(&cc.egui_ctx);
+        app.open_workspace(&cc.egui_ctx);
+        app.load_available_sessions(&cc.egui_ctx);
+        app
+    }
+
+    /// Open the session tied to a repository root, creating it on first launch there
+    async fn open_project_session(sm: &SessionManager, root: &Path, working_dir: &Path) -> Option<Session> {
+        let session = match sm.find_project_session(root).await {
+            Ok(Some(session)) => {
+                tracing::info!("Loaded project session: {}", session.name);
+                session
+            }
+            Ok(None) => {
+                let name = root
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or("project")
+                    .to_string();
+                let mut session = Session::new(name, working_dir.to_path_buf());
+                session.project_root = Some(root.to_path_buf());
+                if let Err(e) = sm.create_session(&session).await {
+                    tracing::error!("Failed to create project session: {}", e);
+                    return None;
+                }
+                tracing::info!("Created project session for {}", root.display());
+                session
+            }
+            Err(e) => {
+                tracing::error!("Failed to look up project session: {}", e);
+                return None;
+            }
+        };
+
+        if let Err(e) = sm.set_active_session(&session.id).await {
+            tracing::error!("Failed to set active session: {}", e);
+        }
+        Some(session)
+    }
+
+    /// Set up the AI providers in the background so startup doesn't wait on them
+    fn start_ai_engine(&mut self, ctx: &Context) {
+        let config = self.config.clone();
+        let ctx_clone = ctx.clone();
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.ai_engine_receiver = Some(rx);
+
+        self.runtime.spawn(async move {
+            let engine = tokio::task::spawn_blocking(move || Self::initialize_ai_engine(&config))
+                .await
+                .unwrap_or(None);
+            let _ = tx.send(engine);
+            ctx_clone.request_repaint();
+        });
+    }
+
+    /// Enabled AI providers, less those offline mode leaves out, by name
+    fn usable_providers(&self) -> Vec<String> {
+        let mut providers: Vec<String> = self.config.ai.providers
+            .iter()
+            .filter(|(name, p)| p.enabled && (!self.config.general.offline || provider_is_local(name, p)))
+            .map(|(name, _)| name.clone())
+            .collect();
+        providers.sort();
+        providers
+    }
+
+    /// Rebuild the AI engine with or without the network providers, moving
+    /// off a provider offline mode just left out
+    fn apply_offline_mode(&mut self, ctx: &Context) {
+        tracing::info!("Offline mode {}", if self.config.general.offline { "on" } else { "off" });
+        self.hook_runner.set_offline(self.config.general.offline);
+        self.ai_engine = None;
+        self.start_ai_engine(ctx);
+        let providers = self.usable_providers();
+        if !providers.iter().any(|name| name == self.ai_panel.selected_provider()) {
+            if let Some(name) = providers.into_iter().next() {
+                self.handle_ai_action(AiAction::ProviderChanged(name), ctx);
+            }
+        }
+    }
+
+    /// Pick up the AI engine once it is set up. With `wait`, block until it
+    /// is, for requests made in the moment after startup.
+    fn poll_ai_engine(&mut self, wait: bool) {
+        let Some(rx) = &mut self.ai_engine_receiver else {
+            return;
+        };
+        let engine = if wait {
+            self.runtime.block_on(rx.recv()).flatten()
+        } else {
+            match rx.try_recv() {
+                Ok(engine) => engine,
+                Err(mpsc::error::TryRecvError::Empty) => return,
+                Err(mpsc::error::TryRecvError::Disconnected) => None,
+            }
+        };
+        self.ai_engine = engine.map(Arc::new);
+        self.ai_engine_receiver = None;
+        self.warm_up_model();
+    }
+
+    /// Switch how command input is interpreted and remember it for next time
+    fn set_operation_mode(&mut self, mode: crate::config::OperationMode) {
+        if self.config.ai.operation_mode == mode {
+            return;
+        }
+        self.config.ai.operation_mode = mode;
+        if let Err(e) = self.config.save() {
+            tracing::error!("Failed to save config: {}", e);
+        }
+    }
+
+    /// Badge showing the operation mode; clicking it moves to the next one
+    fn show_mode_badge(&mut self, ui: &mut egui::Ui) {
+        use crate::config::OperationMode;
+        let mode = self.config.ai.operation_mode.clone();
+        let (color, hint) = match mode {
+            OperationMode::TerminalOnly => (Color32::from_rgb(150, 150, 150), t!("menu-mode-terminal-hint")),
+            OperationMode::AiPromptOnly => (Color32::from_rgb(180, 130, 255), t!("menu-mode-ai-hint")),
+            OperationMode::Hybrid => (Color32::from_rgb(100, 180, 255), t!("menu-mode-hybrid-hint")),
+        };
+        let badge = egui::Button::new(
+            RichText::new(mode.label())
+                .color(color)
+                .size(self.config.appearance.font_size - 3.0)
+        )
+        .small()
+        .stroke(egui::Stroke::new(1.0, color));
+        let hint = format!("{} ({})", hint, self.config.keybindings.cycle_operation_mode);
+        if ui.add(badge).on_hover_text(hint).clicked() {
+            self.set_operation_mode(mode.next());
+        }
+    }
+
+    /// Have the selected provider load the selected model now, if its config
+    /// asks for it, so the first request doesn't wait for the load
+    fn warm_up_model(&self) {
+        let Some(engine) = &self.ai_engine else {
+            return;
+        };
+        let provider_name = self.ai_panel.selected_provider().to_string();
+        let model = self.ai_panel.selected_model().to_string();
+        let wanted = self.config.ai.providers.get(&provider_name).is_some_and(|p| p.warm_up);
+        let Some(provider) = engine.get_provider(&provider_name).filter(|_| wanted && !model.is_empty()) else {
+            return;
+        };
+        let provider = provider.clone();
+        let engine = engine.clone();
+        self.runtime.spawn(async move {
+            let started = Instant::now();
+            match provider.warm_up(&model).await {
+                Ok(()) => tracing::info!("Warmed up {}/{} in {:?}", provider_name, model, started.elapsed()),
+                Err(e) => {
+                    tracing::warn!("Failed to warm up {}/{}: {}", provider_name, model, e);
+                    engine.record_error(&provider_name, &e);
+                }
+            }
+        });
+    }
+
+    /// An audit entry for a request about to be sent, with where to record
+    /// it, if the AI audit log is on
+    fn start_ai_audit(
+        &self,
+        kind: AiRequestKind,
+        provider: &str,
+        model: &str,
+        prompt: &str,
+    ) -> Option<(SessionManager, AiAuditEntry)> {
+        let audit = &self.config.ai.audit;
+        let session_manager = self.session_manager.clone().filter(|_| audit.enabled)?;
+        Some((session_manager, AiAuditEntry::new(kind, provider, model, prompt, audit.full_text)))
+    }
+
+    /// Note in the audit log that the generated command of `pending_block` was run
+    fn audit_command_executed(&mut self, pending_block: Uuid, executed_block: Uuid) {
+        let (Some(audit_id), Some(session_manager)) =
+            (self.ai_audit_commands.remove(&pending_block), self.session_manager.clone())
+        else {
+            return;
+        };
+        self.runtime.spawn(async move {
+            if let Err(e) = session_manager.mark_ai_command_executed(&audit_id, &executed_block).await {
+                tracing::warn!("Failed to update AI audit log: {:#}", e);
+            }
+        });
+    }
+
+    /// Load the newest audit entries for the audit view in the background
+    fn load_ai_audit(&mut self, ctx: &Context) {
+        let Some(session_manager) = self.session_manager.clone() else {
+            self.ai_audit_view.set_entries(Vec::new());
+            return;
+        };
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.ai_audit_receiver = Some(rx);
+        let ctx = ctx.clone();
+        self.runtime.spawn(async move {
+            let entries = session_manager.ai_audit_log(AI_AUDIT_ENTRIES_SHOWN).await.unwrap_or_else(|e| {
+                tracing::error!("Failed to load AI audit log: {:#}", e);
+                Vec::new()
+            });
+            let _ = tx.send(entries);
+            ctx.request_repaint();
+        });
+    }
+
+    /// Check provider availability in the background every AI_HEALTH_INTERVAL
+    /// and hand the latest results, and any requests held back by a rate
+    /// limit, to the AI panel
+    fn refresh_ai_health(&mut self, ctx: &Context) {
+        let Some(engine) = &self.ai_engine else {
+            return;
+        };
+        if self.last_ai_health_check.is_none_or(|t| t.elapsed() >= AI_HEALTH_INTERVAL) {
+            self.last_ai_health_check = Some(Instant::now());
+            let engine = engine.clone();
+            let ctx_clone = ctx.clone();
+            self.runtime.spawn(async move {
+                engine.refresh_health().await;
+                ctx_clone.request_repaint();
+            });
         }
+        let health = engine
+            .list_providers()
+            .into_iter()
+            .map(|name| {
+                let health = engine.health(&name);
+                (name, health)
+            })
+            .collect();
+        self.ai_panel.set_provider_health(health);
+        let waiting = engine.waiting(self.ai_panel.selected_provider());
+        self.ai_panel.set_waiting(waiting);
     }
 
     /// Initialize AI engine with configured providers
@@ -177,13 +916,23 @@ impl ImmateriumApp {
         let mut engine = AiEngine::new();
         let mut providers_registered = 0;
 
+        let offline = config.general.offline;
+        let skip_offline = |name: &str, provider: &AiProviderConfig| {
+            let skip = offline && !provider_is_local(name, provider);
+            if skip {
+                tracing::info!("Offline: not using the {} provider", name);
+            }
+            skip
+        };
+
         // Initialize Ollama provider
         if let Some(ollama_config) = config.ai.providers.get("ollama") {
-            if ollama_config.enabled {
+            if ollama_config.enabled && !skip_offline("ollama", ollama_config) {
                 let base_url = ollama_config.base_url.clone()
                     .unwrap_or_else(|| "http://localhost:11434".to_string());
                 
-                let provider = OllamaProvider::new(base_url, ollama_config.model.clone());
+                let provider = OllamaProvider::new(base_url, ollama_config.model.clone())
+                    .with_keep_alive(ollama_config.keep_alive.clone());
                 engine.register_provider(Arc::new(provider));
                 providers_registered += 1;
                 tracing::info!("Registered Ollama provider");
@@ -192,7 +941,7 @@ impl ImmateriumApp {
 
         // Initialize OpenAI provider
         if let Some(openai_config) = config.ai.providers.get("openai") {
-            if openai_config.enabled {
+            if openai_config.enabled && !skip_offline("openai", openai_config) {
                 if let Some(api_key) = &openai_config.api_key {
                     // Expand environment variables
                     let api_key = shellexpand::env(api_key)
@@ -215,7 +964,7 @@ impl ImmateriumApp {
 
         // Initialize Groq provider
         if let Some(groq_config) = config.ai.providers.get("groq") {
-            if groq_config.enabled {
+            if groq_config.enabled && !skip_offline("groq", groq_config) {
                 if let Some(api_key) = &groq_config.api_key {
                     // Expand environment variables
                     let api_key = shellexpand::env(api_key)
@@ -239,6 +988,28 @@ impl ImmateriumApp {
             }
         }
 
+        for (name, provider_config) in &config.ai.providers {
+            let pins = &provider_config.task_models;
+            for (feature, model) in [
+                (AiFeature::CommandGeneration, &pins.command),
+                (AiFeature::FixIt, &pins.command),
+                (AiFeature::Chat, &pins.chat),
+                (AiFeature::Explanation, &pins.explanation),
+                (AiFeature::Diagnosis, &pins.diagnosis),
+            ] {
+                if let Some(model) = model.as_ref().filter(|m| !m.is_empty()) {
+                    engine.set_task_model(name, feature, model.clone());
+                }
+            }
+            engine.set_rate_limit(
+                name,
+                RateLimit {
+                    requests_per_minute: provider_config.requests_per_minute,
+                    tokens_per_minute: provider_config.tokens_per_minute,
+                },
+            );
+        }
+
         // Set default provider
         if providers_registered > 0 {
             if let Err(e) = engine.set_default_provider(&config.ai.default_provider) {
@@ -256,6 +1027,16 @@ impl ImmateriumApp {
             return;
         }
 
+        // Arithmetic after the calculator prefix never reaches the shell or AI
+        let calc_prefix = self.config.general.calc_prefix.clone();
+        if !calc_prefix.is_empty() {
+            if let Some(expression) = self.command_input.trim().strip_prefix(&calc_prefix) {
+                let expression = expression.trim().to_string();
+                self.calculate(&expression);
+                return;
+            }
+        }
+
         // Check if there's already a running command
         if self.current_block_id.is_some() {
             tracing::warn!("Command already running, ignoring new command");
@@ -264,24 +1045,37 @@ impl ImmateriumApp {
 
         let input = self.command_input.trim().to_string();
         
-        // Add to history (avoid duplicates of the most recent command)
-        if self.command_history.last() != Some(&input) {
-            self.command_history.push(input.clone());
-            // Limit history size
-            if self.command_history.len() > 1000 {
-                self.command_history.remove(0);
+        // Add to history and reset navigation
+        self.command_history.push(input.clone());
+        self.command_history.reset_navigation();
+        // A fresh prompt starts in insert mode, like `set -o vi` in bash
+        self.vim.set_mode(VimMode::Insert);
+        
+        // A prefix overrides the operation mode
+        match forced_input(&input, &self.config.ai.ai_prefix, &self.config.ai.shell_prefix) {
+            Some(ForcedInput::Ai(request)) => {
+                if !request.is_empty() {
+                    self.convert_natural_language_to_command(request.to_string(), ctx);
+                }
+                self.command_input.clear();
+                return;
+            }
+            Some(ForcedInput::Shell(command)) => {
+                if !command.is_empty() {
+                    self.execute_typed_command(command.to_string(), ctx);
+                }
+                self.command_input.clear();
+                return;
             }
+            None => {}
         }
-        // Reset history navigation
-        self.history_index = None;
-        self.current_input_buffer.clear();
-        
+
         // Check operation mode and handle accordingly
         use crate::config::OperationMode;
         match self.config.ai.operation_mode {
             OperationMode::TerminalOnly => {
                 // Mode 1: Always execute as shell command
-                self.execute_shell_command(input, ctx);
+                self.execute_typed_command(input, ctx);
                 self.command_input.clear();
             }
             OperationMode::AiPromptOnly => {
@@ -296,48 +1090,435 @@ impl ImmateriumApp {
                     tracing::info!("Detected natural language input, converting to command: {}", input);
                     self.convert_natural_language_to_command(input, ctx);
                 } else {
-                    self.execute_shell_command(input, ctx);
+                    self.execute_typed_command(input, ctx);
                 }
                 self.command_input.clear();
             }
         }
     }
 
+    /// Evaluate an expression into a calculation block. A mistake is shown
+    /// in the status bar and the input is kept for fixing.
+    fn calculate(&mut self, expression: &str) {
+        match calc::evaluate(expression) {
+            Ok(value) => {
+                self.command_history.push(self.command_input.trim().to_string());
+                self.command_history.reset_navigation();
+                let result = calc::format_number(value);
+                self.block_manager.add_block(Block::calculation(
+                    expression.to_string(),
+                    result,
+                    self.session.working_directory.clone(),
+                ));
+                self.save_needed = true;
+                self.command_input.clear();
+            }
+            Err(e) => self.toasts.push(format!("🧮 {}", calc_error_message(&e))),
+        }
+    }
+
+    /// Populate command history from the current session, or from all sessions
+    /// when global history is enabled
+    fn load_command_history(&mut self) {
+        let limit = self.config.general.max_history_size;
+
+        if self.config.general.global_history {
+            if let Some(ref session_manager) = self.session_manager {
+                let session_manager = session_manager.clone();
+                match self.runtime.block_on(async {
+                    session_manager.recent_commands(limit as i64).await
+                }) {
+                    Ok(commands) => {
+                        self.command_history.load(commands);
+                        return;
+                    }
+                    Err(e) => tracing::error!("Failed to load global history: {}", e),
+                }
+            }
+        }
+
+        let calc_prefix = &self.config.general.calc_prefix;
+        let commands = self.block_manager.get_blocks()
+            .iter()
+            .filter(|b| b.state != crate::core::BlockState::PendingApproval)
+            .filter(|b| !b.is_calculation || !calc_prefix.is_empty())
+            .map(|b| if b.is_calculation {
+                format!("{} {}", calc_prefix, b.command)
+            } else {
+                b.command.clone()
+            });
+        self.command_history.load(commands);
+    }
+
+    /// Load how often commands were run, for ranking suggestions
+    fn load_command_usage(&mut self) {
+        if let Some(ref session_manager) = self.session_manager {
+            let session_manager = session_manager.clone();
+            match self.runtime.block_on(async { session_manager.command_usage(COMMAND_USAGE_LIMIT).await }) {
+                Ok(usage) => self.command_usage = usage,
+                Err(e) => tracing::error!("Failed to load command usage: {}", e),
+            }
+        }
+    }
+
+    /// Count a command the user chose to run, here and in the database
+    fn record_command_use(&mut self, command: &str) {
+        let Some(normalized) = frequency::normalize_command(command) else {
+            return;
+        };
+        let Some(ref session_manager) = self.session_manager else {
+            return;
+        };
+        if session_manager.encrypts() {
+            return;
+        }
+        match self.command_usage.iter_mut().find(|u| u.command == normalized) {
+            Some(usage) => {
+                usage.count += 1;
+                usage.last_used = chrono::Utc::now();
+            }
+            None => self.command_usage.push(CommandUsage {
+                command: normalized,
+                count: 1,
+                last_used: chrono::Utc::now(),
+            }),
+        }
+        let session_manager = session_manager.clone();
+        let command = command.to_string();
+        self.runtime.spawn(async move {
+            if let Err(e) = session_manager.record_command_use(&command).await {
+                tracing::error!("Failed to count command: {}", e);
+            }
+        });
+    }
+
+    /// The most used command starting with what has been typed so far
+    fn command_suggestion(&self) -> Option<CommandUsage> {
+        if !self.config.general.command_suggestions
+            || self.command_input.trim().is_empty()
+            || self.command_history.is_navigating()
+            || self.config.ai.operation_mode == crate::config::OperationMode::AiPromptOnly
+        {
+            return None;
+        }
+        frequency::rank_commands(&self.command_usage, &self.command_input, chrono::Utc::now())
+            .first()
+            .map(|usage| (*usage).clone())
+    }
+
     /// Navigate to previous command in history (Up arrow)
     fn history_previous(&mut self) {
-        if self.command_history.is_empty() {
+        if let Some(command) = self.command_history.older(&self.command_input) {
+            self.command_input = command;
+        }
+    }
+
+    /// Navigate to next command in history (Down arrow)
+    fn history_next(&mut self) {
+        if let Some(command) = self.command_history.newer() {
+            self.command_input = command;
+        }
+    }
+
+    /// Route key events for the focused command input through the vim state machine.
+    /// Must run before the TextEdit is drawn so consumed events never reach it.
+    fn handle_vim_input(&mut self, ctx: &Context, input_id: egui::Id) {
+        let mut state = egui::TextEdit::load_state(ctx, input_id).unwrap_or_default();
+        let mut cursor = state.cursor.char_range()
+            .map(|r| r.primary.index)
+            .unwrap_or_else(|| self.command_input.chars().count());
+        let mut changed = false;
+
+        let vim = &mut self.vim;
+        let text = &mut self.command_input;
+        ctx.input_mut(|i| {
+            i.events.retain(|event| match (vim.mode(), event) {
+                (VimMode::Insert, egui::Event::Key { key: egui::Key::Escape, pressed: true, .. }) => {
+                    vim.enter_normal(&mut cursor);
+                    changed = true;
+                    false
+                }
+                (VimMode::Insert, _) => true,
+                (VimMode::Normal, egui::Event::Text(typed)) => {
+                    for c in typed.chars() {
+                        vim.handle_normal_key(c, text, &mut cursor);
+                    }
+                    changed = true;
+                    false
+                }
+                // Enter still submits and Up/Down still recall history in normal mode
+                (VimMode::Normal, egui::Event::Key { key, .. }) => matches!(
+                    key,
+                    egui::Key::Enter | egui::Key::ArrowUp | egui::Key::ArrowDown
+                ),
+                (VimMode::Normal, egui::Event::Paste(_)) => false,
+                (VimMode::Normal, _) => true,
+            });
+        });
+
+        if changed {
+            let cursor = egui::text::CCursor::new(cursor.min(self.command_input.chars().count()));
+            state.cursor.set_char_range(Some(egui::text::CCursorRange::one(cursor)));
+            state.store(ctx, input_id);
+        }
+    }
+
+    /// Snapshot the numbers for the debug overlay. Runs before the channels
+    /// are drained so their backlogs show what piled up since the last frame.
+    fn collect_debug_stats(&mut self) {
+        let unsaved = self.has_unsaved_changes();
+        let stats = &mut self.debug_stats;
+        stats.blocks_total = self.block_manager.count();
+        stats.output_bytes = self.block_manager.get_blocks().iter().map(|b| b.output.len()).sum();
+        stats.pending_writes = self.pending_writes.load(Ordering::Relaxed);
+        stats.save_needed = unsaved;
+        stats.save_timings = self.save_timings.lock().map(|t| t.clone()).unwrap_or_default();
+        stats.backlogs = vec![
+            ("output", self.output_receiver.as_ref().map_or(0, |rx| rx.len())),
+            ("ai", self.ai_requests.backlog()),
+            ("plugins", self.plugin_receiver.len()),
+            ("findings", self.findings_receiver.len()),
+            ("typed lint", self.typed_lint_receiver.len()),
+            ("git panel", self.git_panel_receiver.len()),
+            ("actions", self.action_receiver.len()),
+            ("attachments", self.attach_receiver.len()),
+            ("sessions", self.session_receiver.len()),
+        ];
+    }
+
+    /// Saved width of a resizable side panel
+    fn panel_width(&self, id: &str, default: f32) -> f32 {
+        self.config.window.panel_widths.get(id).copied().unwrap_or(default)
+    }
+
+    /// Record the window's current size, position and state in the config.
+    /// Size and position are kept from before the window was maximized, and
+    /// not tracked at all when the hotkey places the window by sliding it down.
+    fn track_window_layout(&mut self, ctx: &Context) {
+        let (inner, outer, maximized, fullscreen) = ctx.input(|i| {
+            let viewport = i.viewport();
+            (viewport.inner_rect, viewport.outer_rect, viewport.maximized, viewport.fullscreen)
+        });
+        let window = &mut self.config.window;
+        window.maximized = maximized.unwrap_or(window.maximized);
+        window.fullscreen = fullscreen.unwrap_or(window.fullscreen);
+        let slides_down = self.global_hotkey.is_some() && self.config.global_hotkey.slide_down;
+        if window.maximized || window.fullscreen || slides_down {
             return;
         }
+        if let Some(inner) = inner {
+            window.size = Some([inner.width().round(), inner.height().round()]);
+        }
+        if let Some(outer) = outer {
+            window.position = Some([outer.min.x.round(), outer.min.y.round()]);
+        }
+    }
 
-        match self.history_index {
-            None => {
-                // First time pressing up - save current input and go to last command
-                self.current_input_buffer = self.command_input.clone();
-                self.history_index = Some(self.command_history.len() - 1);
-                self.command_input = self.command_history[self.command_history.len() - 1].clone();
+    /// Show and focus the window when the global hotkey is pressed, or
+    /// minimize it if it already has focus
+    fn handle_global_hotkey(&mut self, ctx: &Context) {
+        if self.global_hotkey.as_ref().is_some_and(|hotkey| hotkey.take_pressed()) {
+            let (focused, minimized) = ctx.input(|i| {
+                (i.viewport().focused.unwrap_or(false), i.viewport().minimized.unwrap_or(false))
+            });
+            if focused && !minimized {
+                self.slide_down = None;
+                ctx.send_viewport_cmd(ViewportCommand::Minimized(true));
+            } else {
+                ctx.send_viewport_cmd(ViewportCommand::Visible(true));
+                ctx.send_viewport_cmd(ViewportCommand::Minimized(false));
+                if self.config.global_hotkey.slide_down {
+                    self.slide_down = SlideDown::start(ctx, self.config.global_hotkey.height_fraction);
+                    if let Some(slide) = &self.slide_down {
+                        ctx.send_viewport_cmd(ViewportCommand::InnerSize(slide.size()));
+                    }
+                }
+                ctx.send_viewport_cmd(ViewportCommand::Focus);
+                ctx.memory_mut(|m| m.request_focus(egui::Id::new("command_input")));
+            }
+        }
+
+        if let Some(slide) = &self.slide_down {
+            let (pos, done) = slide.position();
+            ctx.send_viewport_cmd(ViewportCommand::OuterPosition(pos));
+            if done {
+                self.slide_down = None;
+            } else {
+                ctx.request_repaint();
             }
-            Some(idx) => {
-                // Navigate further back if possible
-                if idx > 0 {
-                    self.history_index = Some(idx - 1);
-                    self.command_input = self.command_history[idx - 1].clone();
+        }
+    }
+
+    /// Files dropped on the AI panel are attached as context; anywhere else
+    /// their paths are inserted at the command input cursor
+    fn handle_dropped_files(&mut self, ctx: &Context) {
+        let (files, pos) = ctx.input(|i| (i.raw.dropped_files.clone(), i.pointer.latest_pos()));
+        if files.is_empty() {
+            return;
+        }
+
+        let on_ai_panel = matches!((self.ai_panel_rect, pos), (Some(rect), Some(pos)) if rect.contains(pos));
+        if on_ai_panel {
+            for file in files {
+                let name = file.path.as_ref()
+                    .and_then(|p| p.file_name())
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or(file.name.clone());
+                match (&file.bytes, file.path) {
+                    (Some(bytes), _) => self.finish_attachment(name, Ok(bytes.to_vec())),
+                    (None, Some(path)) => self.attach_file(name, path, ctx),
+                    (None, None) => self.toasts.push(format!("📎 {}: no contents", name)),
                 }
             }
+            return;
+        }
+
+        let paths: Vec<String> = files
+            .iter()
+            .filter_map(|f| f.path.as_ref())
+            .map(|p| shell_escape(&p.to_string_lossy()))
+            .collect();
+        if !paths.is_empty() {
+            self.insert_at_cursor(ctx, &paths.join(" "));
         }
     }
 
-    /// Navigate to next command in history (Down arrow)
-    fn history_next(&mut self) {
-        if let Some(idx) = self.history_index {
-            if idx < self.command_history.len() - 1 {
-                // Navigate forward
-                self.history_index = Some(idx + 1);
-                self.command_input = self.command_history[idx + 1].clone();
-            } else {
-                // Reached the end - restore original input
-                self.history_index = None;
-                self.command_input = self.current_input_buffer.clone();
+    /// Read a file in the background and attach it to the AI context
+    fn attach_file(&mut self, name: String, path: PathBuf, ctx: &Context) {
+        let tx = self.attach_sender.clone();
+        let ctx = ctx.clone();
+        self.runtime.spawn(async move {
+            let bytes = tokio::fs::read(&path).await.map_err(|e| format!("{}: {}", name, e));
+            let _ = tx.send((name, bytes));
+            ctx.request_repaint();
+        });
+    }
+
+    fn finish_attachment(&mut self, name: String, bytes: Result<Vec<u8>, String>) {
+        match bytes.and_then(|bytes| self.ai_panel.attach_file(name.clone(), &bytes)) {
+            Ok(()) => self.toasts.push(t!("files-attached", name = name)),
+            Err(e) => self.toasts.push(format!("📎 {}", e)),
+        }
+    }
+
+    fn handle_file_browser_action(&mut self, action: FileBrowserAction, ctx: &Context) {
+        match action {
+            FileBrowserAction::Cd(dir) => self.change_directory(&dir, ctx),
+            FileBrowserAction::Insert(path) => {
+                self.insert_at_cursor(ctx, &insertable_path(&path, &self.session.working_directory));
             }
+            FileBrowserAction::Open(path) => launch::open_with_default(&path),
+            FileBrowserAction::Attach(path) => {
+                let name = path.file_name().map_or_else(String::new, |n| n.to_string_lossy().to_string());
+                self.attach_file(name, path, ctx);
+            }
+            FileBrowserAction::Close => {
+                self.config.appearance.file_browser = false;
+                if let Err(e) = self.config.save() {
+                    tracing::error!("Failed to save config: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Insert text at the command input cursor, spaced from the words around it
+    fn insert_at_cursor(&mut self, ctx: &Context, text: &str) {
+        let input_id = egui::Id::new("command_input");
+        let mut state = egui::TextEdit::load_state(ctx, input_id).unwrap_or_default();
+        let len = self.command_input.chars().count();
+        let cursor = state.cursor.char_range().map_or(len, |r| r.primary.index.min(len));
+        let byte = self.command_input.char_indices().nth(cursor).map_or(self.command_input.len(), |(i, _)| i);
+
+        let mut insert = text.to_string();
+        if self.command_input[..byte].chars().last().is_some_and(|c| !c.is_whitespace()) {
+            insert.insert(0, ' ');
+        }
+        if self.command_input[byte..].chars().next().is_some_and(|c| !c.is_whitespace()) {
+            insert.push(' ');
+        }
+        self.command_input.insert_str(byte, &insert);
+
+        let end = egui::text::CCursor::new(cursor + insert.chars().count());
+        state.cursor.set_char_range(Some(egui::text::CCursorRange::one(end)));
+        state.store(ctx, input_id);
+        ctx.memory_mut(|m| m.request_focus(input_id));
+    }
+
+    /// Compile the [redaction] rules, or drop them when it's turned off
+    fn update_redactor(&mut self) {
+        let config = &self.config.redaction;
+        self.redactor = config
+            .enabled
+            .then(|| Redactor::new(config.builtin, &config.patterns))
+            .filter(|redactor| !redactor.is_empty());
+        self.redacted_outputs.clear();
+    }
+
+    /// Text about to be copied, with secrets masked if redaction is on
+    fn redact_copy(&self, text: String) -> String {
+        match &self.redactor {
+            Some(redactor) => redactor.mask(&text).into_owned(),
+            None => text,
+        }
+    }
+
+    /// The block as it should be shown: with secrets in its output (and its
+    /// command, unless it's waiting for approval) masked if redaction is on
+    fn redact_for_display<'b>(&mut self, block: &'b Block) -> std::borrow::Cow<'b, Block> {
+        let Some(redactor) = &self.redactor else {
+            return std::borrow::Cow::Borrowed(block);
+        };
+        let mut shown = block.clone();
+        if block.state != crate::core::BlockState::PendingApproval {
+            shown.command = redactor.mask(&block.command).into_owned();
+        }
+        let (len, output) = self.redacted_outputs.entry(block.id).or_default();
+        if *len != block.output.len() || output.is_empty() {
+            let text: String = block.output.chunks().collect();
+            *output = OutputBuffer::new();
+            output.push_str(&redactor.mask(&text));
+            *len = block.output.len();
+        }
+        shown.output = output.clone();
+        std::borrow::Cow::Owned(shown)
+    }
+
+    /// Blocks shown when presenting: finished and running ones, not
+    /// suggestions waiting for approval
+    fn presentation_blocks(&self) -> Vec<Block> {
+        self.block_manager
+            .get_blocks()
+            .iter()
+            .filter(|b| b.state != crate::core::BlockState::PendingApproval)
+            .cloned()
+            .collect()
+    }
+
+    /// Present from the selected block, or the first one
+    fn start_presentation(&mut self) {
+        let blocks = self.presentation_blocks();
+        let selected = self.block_manager.get_selected_block().map(|b| b.id);
+        let index = blocks.iter().position(|b| Some(b.id) == selected).unwrap_or(0);
+        self.context_menu_pos = None;
+        self.context_menu_block = None;
+        self.presentation.start(index);
+    }
+
+    /// Middle-click pastes into the command input, unless another text field
+    /// has focus
+    fn handle_middle_click_paste(&mut self, ctx: &Context) {
+        if !self.config.general.middle_click_paste
+            || self.presentation.active
+            || !ctx.input(|i| i.pointer.button_pressed(egui::PointerButton::Middle))
+        {
+            return;
+        }
+        let input_id = egui::Id::new("command_input");
+        if ctx.memory(|m| m.focused().is_some_and(|id| id != input_id)) {
+            return;
+        }
+        if let Some(text) = selection::paste_text().filter(|t| !t.is_empty()) {
+            self.insert_at_cursor(ctx, text.trim_end_matches('\n'));
         }
     }
 
@@ -378,89 +1559,372 @@ impl ImmateriumApp {
 
     /// Convert natural language to shell command using AI
     fn convert_natural_language_to_command(&mut self, nl_input: String, ctx: &Context) {
+        self.generate_command(nl_input, None, ctx);
+    }
+
+    /// Ask the AI for a corrected version of a failed command; it comes back
+    /// as a suggestion to approve, like a generated command
+    fn fix_failed_command(&mut self, block_id: Uuid, ctx: &Context) {
+        let Some(block) = self.block_manager.get_block(&block_id) else {
+            return;
+        };
+        let request = format!("Fix this command so it succeeds: {}", block.command);
+        self.generate_command(request, Some(block_id), ctx);
+    }
+
+    /// Generate a shell command for `nl_input`; `fixing` is the failed block
+    /// a fix-it request is about, which is sent along as context
+    fn generate_command(&mut self, nl_input: String, fixing: Option<Uuid>, ctx: &Context) {
+        self.poll_ai_engine(true);
         if self.ai_engine.is_none() {
+            if fixing.is_some() {
+                self.toasts.push("🤖 AI engine not available");
+                return;
+            }
             tracing::warn!("AI engine not available, executing as regular command");
             self.execute_shell_command(nl_input, ctx);
             return;
         }
 
-        self.original_nl_input = nl_input.clone();
-        self.is_generating_command = true;
-        
         let engine = self.ai_engine.as_ref().unwrap().clone();
         let ctx_clone = ctx.clone();
-        let (tx, rx) = mpsc::unbounded_channel();
-        self.ai_receiver = Some(rx);
+        let origin = match fixing {
+            Some(block_id) => AiOrigin::for_block(AiFeature::FixIt, block_id),
+            None => AiOrigin::new(AiFeature::CommandGeneration),
+        };
         
         // Get current provider and model
         let provider_name = self.ai_panel.selected_provider().to_string();
-        let model = self.ai_panel.selected_model().to_string();
+        let model = engine.model_for(&provider_name, origin.feature, self.ai_panel.selected_model());
         
         if model.is_empty() {
             tracing::error!("No AI model selected");
-            self.is_generating_command = false;
             return;
         }
         
         // Build context for command generation
-        let system_prompt = "You are a helpful shell command generator. Convert natural language requests into valid bash commands. \
-                            Reply ONLY with the shell command, no explanations, no markdown, no code blocks. \
-                            If the request is ambiguous, choose the most common interpretation.";
+        let candidates = self.config.ai.command_candidates.max(1);
+        let system_prompt = command_system_prompt(candidates);
         
-        let user_prompt = format!("Convert this request to a bash command: {}", nl_input);
+        let last_failure = match fixing {
+            Some(block_id) => self.block_manager.get_block(&block_id),
+            None => self
+                .block_manager
+                .get_blocks()
+                .iter()
+                .rev()
+                .find(|b| b.state != crate::core::BlockState::PendingApproval)
+                .filter(|b| self.config.ai.include_last_failure && b.state == crate::core::BlockState::Failed),
+        };
+        let mut user_prompt = build_command_context(
+            &command_request(&nl_input),
+            &self.config.general.default_shell,
+            &self.session.working_directory,
+            last_failure,
+            self.block_manager.get_blocks(),
+        );
+        if let Some(files) = self.ai_panel.attachment_context() {
+            user_prompt = format!("{}\n\n{}", files, user_prompt);
+        }
         
-        let request = ChatRequest::new(model)
-            .with_system_message(system_prompt.to_string())
-            .with_user_message(user_prompt);
+        let audit = self.start_ai_audit(AiRequestKind::Command, &provider_name, &model, &user_prompt);
+        let request = command_chat_request(&self.config.ai, model, system_prompt);
+        let provenance = AiProvenance {
+            provider: provider_name.clone(),
+            model: request.model.clone(),
+            prompt: user_prompt.clone(),
+        };
+        let request = request.with_user_message(user_prompt);
+        let ai_request = self.ai_requests.start(origin, &provider_name);
         
         self.runtime.spawn(async move {
-            match engine.chat_completion_with_provider(&provider_name, request).await {
+            let _slot = ai_request.slot().await;
+            let started = Instant::now();
+            let result = engine.chat_completion_with_provider(&provider_name, request).await;
+            let audit_id = record_ai_audit(audit, &result, started.elapsed()).await;
+            match result {
                 Ok(response) => {
-                    let command = response.content.trim().to_string();
-                    tracing::info!("AI generated command: {}", command);
-                    let _ = tx.send(AiMessage::CommandGenerated(command));
+                    let commands = parse_command_candidates(&response.content, candidates);
+                    tracing::info!("AI generated command: {}", commands.join(" | "));
+                    if let Some(timing) = &response.timing {
+                        tracing::info!("Command generation took {:?}: {}", timing.total, timing.summary());
+                    }
+                    ai_request.answer(AiMessage::CommandGenerated(nl_input, commands, provenance, audit_id));
                     ctx_clone.request_repaint();
                 }
                 Err(e) => {
                     tracing::error!("Failed to generate command: {}", e);
-                    let _ = tx.send(AiMessage::Error(format!("Failed to generate command: {}", e)));
+                    ai_request.answer(AiMessage::Error(format!("Failed to generate command: {}", e)));
                     ctx_clone.request_repaint();
                 }
             }
         });
     }
 
-    fn execute_shell_command(&mut self, command: String, ctx: &Context) {
-        tracing::info!("Executing command: {}", command);
-
-        // Create a new block
-        let mut block = Block::new(command.clone(), self.session.working_directory.clone());
-        block.start_execution();
-        let block_id = block.id;
-        self.block_manager.add_block(block);
-        self.current_block_id = Some(block_id);
-        self.save_needed = true; // Mark that we need to save
+    /// Ask the selected model what a suggested command and its flags do, in
+    /// the background, unless that was already asked for this block
+    fn explain_command(&mut self, block_id: Uuid, command: String, ctx: &Context) {
+        if !self.config.ai.explain_commands || command.is_empty() {
+            return;
+        }
+        let Some(engine) = self.ai_engine.clone() else {
+            return;
+        };
+        let key = (block_id, command.clone());
+        if self.command_explanations.contains_key(&key) {
+            return;
+        }
+        self.command_explanations.insert(key, None);
+
+        let provider_name = self.ai_panel.selected_provider().to_string();
+        let model = engine.model_for(&provider_name, AiFeature::Explanation, self.ai_panel.selected_model());
+        let prompt = format!("Shell: {}\nCommand: {}", self.config.general.default_shell, command);
+        let audit = self.start_ai_audit(AiRequestKind::Explanation, &provider_name, &model, &prompt);
+        let request = ChatRequest::new(model)
+            .with_system_message(
+                "You explain shell commands to people who may not know them. Reply with one sentence saying \
+                 what the command does, then one line per flag or option it uses, as `flag: meaning`. \
+                 No markdown, no other text."
+                    .to_string(),
+            )
+            .with_user_message(prompt);
+        let ai_request = self
+            .ai_requests
+            .start(AiOrigin::for_block(AiFeature::Explanation, block_id), &provider_name);
+        let ctx = ctx.clone();
+        self.runtime.spawn(async move {
+            let _slot = ai_request.slot().await;
+            let started = Instant::now();
+            let result = engine.chat_completion_with_provider(&provider_name, request).await;
+            record_ai_audit(audit, &result, started.elapsed()).await;
+            match result {
+                Ok(response) => {
+                    let explanation = parse_command_explanation(&response.content);
+                    ai_request.answer(AiMessage::Explanation(block_id, command, explanation));
+                    ctx.request_repaint();
+                }
+                Err(e) => tracing::warn!("Failed to explain command: {}", e),
+            }
+        });
+    }
+
+    /// Check a suggested command with bash -n and shellcheck in the background,
+    /// unless that was already done for this block
+    fn validate_command(&mut self, block_id: Uuid, command: String, ctx: &Context) {
+        let validation = &self.config.validation;
+        if !validation.enabled || command.is_empty() {
+            return;
+        }
+        let key = (block_id, command.clone());
+        if self.command_findings.contains_key(&key) {
+            return;
+        }
+        self.command_findings.insert(key, None);
+
+        let shellcheck = validation.shellcheck;
+        let tx = self.findings_sender.clone();
+        let ctx = ctx.clone();
+        self.runtime.spawn_blocking(move || {
+            let findings = lint::check_command(&command, shellcheck);
+            let _ = tx.send((block_id, command, findings));
+            ctx.request_repaint();
+        });
+    }
+
+    /// Run a command typed at the prompt. One matching a dangerous pattern
+    /// waits for confirmation first. When typed commands are checked and this
+    /// one has errors, it waits as a suggestion to be run anyway or edited.
+    fn execute_typed_command(&mut self, command: String, ctx: &Context) {
+        let dangerous = &self.config.dangerous_commands;
+        if dangerous.enabled && !dangerous.allowed.iter().any(|c| c.trim() == command.trim()) {
+            if let Some(pattern) = danger::matching_pattern(&command, &dangerous.patterns) {
+                self.confirm_dangerous = Some((command, pattern.to_string()));
+                return;
+            }
+        }
+        self.run_typed_command(command, ctx);
+    }
+
+    fn run_typed_command(&mut self, command: String, ctx: &Context) {
+        let validation = &self.config.validation;
+        if validation.enabled && validation.typed_commands {
+            // Checked in the background like suggestions; it runs or is held
+            // once the findings are back
+            let shellcheck = validation.shellcheck;
+            let tx = self.typed_lint_sender.clone();
+            let ctx = ctx.clone();
+            self.runtime.spawn_blocking(move || {
+                let findings = lint::check_command(&command, shellcheck);
+                let _ = tx.send((command, findings));
+                ctx.request_repaint();
+            });
+            return;
+        }
+        self.start_typed_command(command, ctx);
+    }
+
+    /// Run a typed command that was checked, or hold it as a suggestion to
+    /// run anyway or edit when the check found errors
+    fn finish_typed_lint(&mut self, command: String, findings: Vec<Finding>, ctx: &Context) {
+        if lint::has_errors(&findings) {
+            let mut block = Block::new(command.clone(), self.session.working_directory.clone());
+            block.state = crate::core::BlockState::PendingApproval;
+            self.command_findings.insert((block.id, command), Some(findings));
+            self.focus_approval = Some(block.id);
+            self.block_manager.add_block(block);
+            return;
+        }
+        self.start_typed_command(command, ctx);
+    }
+
+    /// Count and run a typed command; it is broadcast once it has started
+    fn start_typed_command(&mut self, command: String, ctx: &Context) {
+        self.record_command_use(&command);
+        self.broadcast_after_start = self.execute_shell_command(command, ctx);
+    }
+
+    /// Run a typed command in every broadcast target as well, each in its
+    /// own session's directory and environment, once it has started here
+    /// (past the script and the pre-command hooks). Targets run the same
+    /// hooks and their own triggers, and are journaled; the finished blocks
+    /// are saved straight into those sessions. Results arrive as
+    /// `SessionMessage::Broadcast`.
+    fn broadcast_command(&mut self, command: &str, ctx: &Context) {
+        let Some(session_manager) = self.session_manager.clone() else {
+            return;
+        };
+        let targets: Vec<Uuid> = self.broadcast_targets
+            .iter()
+            .copied()
+            .filter(|id| *id != self.session.id)
+            .collect();
+        if targets.is_empty() {
+            return;
+        }
+        if self.broadcast_pending == 0 {
+            self.broadcast_failed.clear();
+        }
+        self.broadcast_pending += targets.len();
+
+        let timeout = self.config.general.broadcast_timeout_secs;
+        let job = BroadcastJob {
+            shell: self.config.general.default_shell.clone(),
+            command: command.to_string(),
+            hooks: self.hook_runner.clone(),
+            timeout: (timeout > 0).then(|| Duration::from_secs(timeout)),
+        };
+        for session_id in targets {
+            let session_manager = session_manager.clone();
+            let job = job.clone();
+            let cancel = self.broadcast_cancel.subscribe();
+            let tx = self.session_sender.clone();
+            let ctx = ctx.clone();
+            self.runtime.spawn(async move {
+                let result = run_in_session(&session_manager, session_id, job, cancel, &tx).await;
+                let _ = tx.send(SessionMessage::Broadcast(session_id, result.map_err(|e| format!("{:#}", e))));
+                ctx.request_repaint();
+            });
+        }
+    }
+
+    /// Run a command in a new block, returning the block's id unless a
+    /// script cancelled it
+    fn execute_shell_command(&mut self, command: String, ctx: &Context) -> Option<Uuid> {
+        let command = match &self.scripts {
+            Some(scripts) => match scripts.on_command_submit(&command, &self.session.working_directory) {
+                Some(command) => command,
+                None => {
+                    self.script_status = Some(format!("📜 Script cancelled: {}", command));
+                    return None;
+                }
+            },
+            None => command,
+        };
+        tracing::info!("Executing command: {}", command);
+
+        // Create a new block
+        let mut block = Block::new(command.clone(), self.session.working_directory.clone());
+        block.start_execution();
+        let block_id = block.id;
+        self.block_manager.add_block(block);
+        self.spawn_command(block_id, command, ctx);
+        Some(block_id)
+    }
+
+    /// Run `command` in the background, streaming its output into `block_id`
+    fn spawn_command(&mut self, block_id: Uuid, command: String, ctx: &Context) {
+        self.current_block_id = Some(block_id);
+        self.save_needed = true; // Mark that we need to save
+        if let (Some(journal), Some(block)) = (&mut self.journal, self.block_manager.get_block(&block_id)) {
+            journal.record_block(self.session.id, block);
+        }
 
         let (output_tx, output_rx) = mpsc::unbounded_channel();
         self.output_receiver = Some(output_rx);
 
         let ctx_clone = ctx.clone();
+        let environment = self.command_environment();
+        if self.config.storage.snapshot_environment {
+            if let Some(block) = self.block_manager.get_block_mut(&block_id) {
+                block.metadata.environment = environment_snapshot(
+                    &self.config.storage.snapshot_variables,
+                    &environment,
+                    |name| std::env::var(name).ok(),
+                );
+            }
+        }
         
         // Create executor for this command
-        let executor = ShellExecutor::new(self.config.general.default_shell.clone())
+        let mut executor = ShellExecutor::new(self.config.general.default_shell.clone())
             .expect("Failed to create shell executor");
+        executor.set_environment(environment);
+        if self.session.working_directory.is_dir() {
+            executor.set_working_directory(self.session.working_directory.clone());
+        }
+
+        // Triggers are matched here in the output pump so they fire on live output
+        let mut matcher = TriggerMatcher::new(&self.session.triggers);
+        let hooks = self.hook_runner.clone();
+        let cwd = self.session.working_directory.clone();
 
         self.runtime.spawn(async move {
-            match executor.execute(command.clone()).await {
-                Ok(mut rx) => {
+            let (results, refused) = hooks.run_pre(&command, &cwd).await;
+            if !results.is_empty() {
+                let _ = output_tx.send(OutputMessage::Hooks(results));
+            }
+            if let Some(reason) = refused {
+                tracing::info!("Hook refused command: {}", reason);
+                let _ = output_tx.send(OutputMessage::Refused(reason));
+                ctx_clone.request_repaint();
+                return;
+            }
+
+            let started = Instant::now();
+            match executor.execute_with_input(command.clone()).await {
+                Ok((mut rx, input)) => {
+                    let _ = output_tx.send(OutputMessage::Input(input));
                     while let Some(line) = rx.recv().await {
                         match line {
                             OutputLine::Stdout(s) | OutputLine::Stderr(s) => {
+                                let hits = matcher.feed(&s);
                                 let _ = output_tx.send(OutputMessage::Output(s));
+                                for hit in hits {
+                                    let _ = output_tx.send(OutputMessage::Trigger(hit));
+                                }
                                 ctx_clone.request_repaint();
                             }
+                            OutputLine::WorkingDirectory(path) => {
+                                let _ = output_tx.send(OutputMessage::WorkingDirectory(path));
+                            }
                             OutputLine::Exit(code) => {
                                 tracing::info!("Command exited with code: {}", code);
+                                for hit in matcher.finish() {
+                                    let _ = output_tx.send(OutputMessage::Trigger(hit));
+                                }
+                                let results = hooks.run_post(&command, &cwd, code, started.elapsed()).await;
+                                if !results.is_empty() {
+                                    let _ = output_tx.send(OutputMessage::Hooks(results));
+                                }
                                 let _ = output_tx.send(OutputMessage::Exit(code));
                                 ctx_clone.request_repaint();
                                 break;
@@ -478,440 +1942,3309 @@ impl ImmateriumApp {
         });
     }
 
-    fn auto_save(&mut self) {
-        // Check if enough time has elapsed since last save
-        let save_interval = Duration::from_secs(self.config.general.auto_save_interval);
-        if self.last_save.elapsed() < save_interval || !self.save_needed {
+    /// Run the actions whose `[keybindings]` shortcut was pressed this frame.
+    /// A full-screen program in the terminal view gets every key instead, and
+    /// the command input's own actions (run, AI suggest) only fire while it
+    /// has focus, so Ctrl+Enter in a dialog's text field stays there.
+    fn dispatch_shortcuts(&mut self, ctx: &Context) {
+        if self.terminal_view.is_some() {
+            return;
+        }
+        // Presenting is read-only; only its own shortcut still works
+        let presenting = self.presentation.active;
+        let input_focused = ctx.memory(|m| m.has_focus(egui::Id::new("command_input")));
+        let actions = KeyAction::ALL
+            .into_iter()
+            .filter(KeyAction::is_available)
+            .filter(|action| !presenting || *action == KeyAction::Presentation)
+            .filter(|action| input_focused || !action.needs_input_focus());
+        for action in actions {
+            let Some(shortcut) = parse_shortcut(action.binding(&self.config.keybindings)) else {
+                continue;
+            };
+            if ctx.input_mut(|i| i.consume_shortcut(&shortcut)) {
+                self.run_key_action(action, ctx);
+            }
+        }
+    }
+
+    fn run_key_action(&mut self, action: KeyAction, ctx: &Context) {
+        match action {
+            KeyAction::NewBlock => self.execute_command(ctx),
+            KeyAction::AiSuggest => {
+                let input = self.command_input.trim().to_string();
+                self.poll_ai_engine(true);
+                if input.is_empty() || self.current_block_id.is_some() {
+                    return;
+                }
+                if self.ai_engine.is_none() {
+                    self.toasts.push("AI is not available");
+                    return;
+                }
+                self.command_input.clear();
+                self.convert_natural_language_to_command(input, ctx);
+            }
+            KeyAction::Settings => {
+                self.storage_status = None;
+                self.refresh_database_size(ctx);
+                self.show_settings = true;
+            }
+            KeyAction::CycleOperationMode => self.set_operation_mode(self.config.ai.operation_mode.next()),
+            KeyAction::QuickJump => {
+                if self.show_quick_jump {
+                    self.show_quick_jump = false;
+                } else {
+                    self.open_quick_jump(ctx);
+                }
+            }
+            KeyAction::InsertPath => self.path_picker.open_at(&self.session.working_directory),
+            KeyAction::Presentation => {
+                if self.presentation.active {
+                    self.presentation.active = false;
+                } else {
+                    self.start_presentation();
+                }
+            }
+            KeyAction::Shortcuts => {
+                self.shortcuts_query.clear();
+                self.show_shortcuts = !self.show_shortcuts;
+            }
+            KeyAction::History => self.history_view.open = !self.history_view.open,
+            KeyAction::Search | KeyAction::SplitHorizontal | KeyAction::SplitVertical | KeyAction::ClosePane => {}
+        }
+    }
+
+    /// Select a block and scroll it into view, expanding it and dropping any
+    /// filter that hides it
+    fn show_block(&mut self, block_id: Uuid) {
+        let Some(block) = self.block_manager.get_block(&block_id) else {
             return;
+        };
+        if !self.block_filters.is_empty() && !self.block_filters.iter().any(|f| f.matches(block)) {
+            self.block_filters.clear();
         }
+        if block.is_collapsed {
+            self.block_manager.toggle_block_collapsed(&block_id);
+        }
+        if self.config.appearance.log_view {
+            self.expanded_log_rows.insert(block_id);
+        }
+        self.block_manager.select_block(block_id);
+        self.scroll_to_block = Some(block_id);
+    }
+
+    /// Add a heading block separating what comes next from what came before
+    fn insert_divider(&mut self, title: &str) {
+        let title = title.trim();
+        if title.is_empty() {
+            return;
+        }
+        let divider = Block::divider(title.to_string(), self.session.working_directory.clone());
+        self.block_manager.add_block(divider);
+        self.save_needed = true;
+    }
 
+    /// Remove blocks from the session and the database. A running block is
+    /// left alone.
+    fn delete_blocks(&mut self, block_ids: Vec<Uuid>) {
+        let block_ids: Vec<Uuid> = block_ids.into_iter().filter(|id| Some(*id) != self.current_block_id).collect();
+        for id in &block_ids {
+            self.block_manager.remove_block(id);
+            self.watched_blocks.remove(id);
+        }
+        if let Some(ref session_manager) = self.session_manager {
+            let session_manager = session_manager.clone();
+            let ids = block_ids.clone();
+            if let Err(e) = self.runtime.block_on(async { session_manager.delete_blocks(&ids).await }) {
+                tracing::error!("Failed to delete blocks: {}", e);
+            }
+        }
+        self.save_needed = true;
+    }
+
+    /// Run `cd` in the session's shell, unless it is busy
+    fn change_directory(&mut self, dir: &Path, ctx: &Context) {
+        if self.current_block_id.is_some() {
+            self.toasts.push(t!("status-command-running"));
+        } else {
+            self.execute_shell_command(format!("cd {}", shell_escape(&dir.to_string_lossy())), ctx);
+        }
+    }
+
+    /// Load visited and pinned directories and show the quick-jump popup.
+    /// Directories that no longer exist are left out but not forgotten, as
+    /// they may be on a drive that isn't mounted.
+    fn open_quick_jump(&mut self, ctx: &Context) {
+        self.directory_bookmarks.clear();
         if let Some(ref session_manager) = self.session_manager {
-            let session_id = self.session.id;
-            let blocks: Vec<_> = self.block_manager.get_blocks().iter().cloned().collect();
-            
             let session_manager = session_manager.clone();
+            let (tx, rx) = mpsc::unbounded_channel();
+            let ctx = ctx.clone();
+            self.bookmark_receiver = Some(rx);
             self.runtime.spawn(async move {
-                // Save all blocks
-                for (index, block) in blocks.iter().enumerate() {
-                    if let Err(e) = session_manager.save_block(&session_id, block, index as i32).await {
-                        tracing::error!("Failed to save block: {}", e);
+                let bookmarks = match session_manager.directory_bookmarks(500).await {
+                    Ok(bookmarks) => bookmarks,
+                    Err(e) => {
+                        tracing::error!("Failed to load directory bookmarks: {}", e);
+                        Vec::new()
                     }
-                }
-                
-                // Update session timestamp
-                if let Err(e) = session_manager.touch_session(&session_id).await {
-                    tracing::error!("Failed to update session timestamp: {}", e);
-                } else {
-                    tracing::debug!("Auto-saved session {}", session_id);
-                }
+                };
+                let existing = tokio::task::spawn_blocking(move || {
+                    bookmarks.into_iter().filter(|b| b.path.is_dir()).collect()
+                });
+                let _ = tx.send(existing.await.unwrap_or_default());
+                ctx.request_repaint();
             });
-            
-            self.last_save = Instant::now();
-            self.save_needed = false;
         }
+        self.quick_jump_query.clear();
+        self.quick_jump_selected = 0;
+        self.show_quick_jump = true;
     }
 
-    fn load_available_sessions(&mut self) {
+    /// Pin or unpin a directory in quick-jump
+    fn set_directory_pinned(&mut self, path: &Path, pinned: bool) {
         if let Some(ref session_manager) = self.session_manager {
             let session_manager = session_manager.clone();
-            let runtime = &self.runtime;
-            
-            if let Ok(sessions) = runtime.block_on(async {
-                session_manager.list_sessions().await
-            }) {
-                self.available_sessions = sessions;
+            if let Err(e) = self.runtime.block_on(async { session_manager.set_directory_pinned(path, pinned).await }) {
+                tracing::error!("Failed to pin directory: {}", e);
+                return;
             }
         }
+        match self.directory_bookmarks.iter_mut().find(|b| b.path == path) {
+            Some(bookmark) => bookmark.pinned = pinned,
+            None => self.directory_bookmarks.push(DirectoryBookmark {
+                path: path.to_path_buf(),
+                visits: 0,
+                last_visit: chrono::Utc::now(),
+                pinned,
+            }),
+        }
     }
 
-    fn switch_to_session(&mut self, session_id: Uuid) {
+    fn forget_directory(&mut self, path: &Path) {
         if let Some(ref session_manager) = self.session_manager {
             let session_manager = session_manager.clone();
-            
-            match self.runtime.block_on(async {
-                session_manager.load_session(&session_id).await
-            }) {
-                Ok(loaded_session) => {
-                    // Save current session first
-                    self.auto_save();
-                    
-                    // Switch to new session
-                    self.session = loaded_session;
-                    self.block_manager = BlockManager::new();
-                    for block in &self.session.blocks {
-                        self.block_manager.add_block(block.clone());
-                    }
-                    
-                    // Set as active
-                    let _ = self.runtime.block_on(async {
-                        session_manager.set_active_session(&session_id).await
-                    });
-                    
-                    tracing::info!("Switched to session: {}", self.session.name);
-                }
-                Err(e) => {
-                    tracing::error!("Failed to load session: {}", e);
-                }
+            if let Err(e) = self.runtime.block_on(async { session_manager.forget_directory(path).await }) {
+                tracing::error!("Failed to forget directory: {}", e);
+                return;
             }
         }
+        self.directory_bookmarks.retain(|b| b.path != path);
     }
 
-    fn create_new_session(&mut self, name: String) {
-        let working_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
-        let new_session = Session::new(name, working_dir);
-        
+    /// Follow a directory change reported by the shell (e.g. after `cd`) so the
+    /// next command starts there
+    fn set_working_directory(&mut self, path: PathBuf) {
+        if path == self.session.working_directory || !path.is_dir() {
+            return;
+        }
+        tracing::info!("Working directory changed to {}", path.display());
+        self.session.working_directory = path;
+
         if let Some(ref session_manager) = self.session_manager {
             let session_manager = session_manager.clone();
-            let session_clone = new_session.clone();
-            
-            match self.runtime.block_on(async {
-                session_manager.create_session(&session_clone).await
+            let session_id = self.session.id;
+            let path = self.session.working_directory.clone();
+            if let Err(e) = self.runtime.block_on(async {
+                session_manager.set_working_directory(&session_id, &path).await?;
+                session_manager.record_directory_visit(&path).await
             }) {
-                Ok(_) => {
-                    // Switch to the new session
-                    self.switch_to_session(new_session.id);
-                }
-                Err(e) => {
-                    tracing::error!("Failed to create session: {}", e);
-                }
+                tracing::error!("Failed to save working directory: {}", e);
             }
         }
     }
 
-    fn handle_ai_action(&mut self, action: AiAction, ctx: &Context) {
-        match action {
-            AiAction::ProviderChanged(provider) => {
-                tracing::info!("AI provider changed to: {}", provider);
-                self.ai_panel.set_selected_provider(provider.clone());
-                // Request model list load
-                self.handle_ai_action(AiAction::LoadModels, ctx);
+    /// Carry out the action of a trigger that matched a block's output
+    fn handle_trigger_hit(&mut self, block_id: Uuid, hit: TriggerHit) {
+        tracing::info!("Trigger fired ({:?}): {}", hit.action, hit.line);
+        let Some(block) = self.block_manager.get_block_mut(&block_id) else {
+            return;
+        };
+
+        match hit.action {
+            TriggerAction::Notify => {
+                crate::utils::notify::send(&t!("trigger-notification", command = block.command.clone()), &hit.line);
             }
-            AiAction::LoadModels => {
-                tracing::info!("Loading AI models...");
-                
-                if let Some(engine) = &self.ai_engine {
-                    let provider_name = self.ai_panel.selected_provider().to_string();
-                    
-                    if let Some(provider) = engine.get_provider(&provider_name) {
-                        let provider_clone = provider.clone();
-                        let ctx_clone = ctx.clone();
-                        
-                        // Create channel for receiving models
-                        let (tx, rx) = mpsc::unbounded_channel();
-                        self.ai_receiver = Some(rx);
-                        
-                        self.runtime.spawn(async move {
-                            match provider_clone.list_models().await {
-                                Ok(models) => {
-                                    tracing::info!("Loaded {} models from {}", models.len(), provider_name);
-                                    let _ = tx.send(AiMessage::ModelsLoaded(models));
-                                    ctx_clone.request_repaint();
-                                }
-                                Err(e) => {
-                                    tracing::error!("Failed to load models: {}", e);
-                                    let _ = tx.send(AiMessage::Error(format!("Failed to load models: {}", e)));
-                                    ctx_clone.request_repaint();
-                                }
-                            }
-                        });
-                    }
-                } else {
-                    tracing::warn!("No AI engine available");
-                    // Set some default models for offline use
-                    let models = vec![
-                        "qwen2.5-coder:3b".to_string(),
-                        "qwen2.5-coder:7b".to_string(),
-                    ];
-                    self.ai_panel.set_available_models(models);
-                }
+            TriggerAction::HighlightLine => block.highlighted_lines.push(hit.line_index),
+            TriggerAction::RunCommand(command) => self.command_queue.push_back(command),
+            TriggerAction::MarkBlock => {
+                block.is_pinned = true;
+                self.save_needed = true;
             }
-            AiAction::SendPrompt(prompt) => {
-                tracing::info!("Sending prompt to AI: {}", prompt);
-                
-                // Add to conversation
-                self.ai_panel.add_user_message(prompt.clone());
-                self.ai_panel.start_streaming();
-                
-                // Build context from recent blocks if enabled
-                let blocks: Vec<Block> = self.block_manager.get_blocks()
-                    .iter()
-                    .cloned()
-                    .collect();
-                
-                let context = if self.ai_panel.include_context {
-                    build_minimal_context(&blocks, &prompt, self.ai_panel.context_blocks)
-                } else {
-                    prompt.clone()
-                };
-                
-                // Send to AI engine
-                if let Some(engine) = &self.ai_engine {
-                    let provider_name = self.ai_panel.selected_provider().to_string();
-                    let model = self.ai_panel.selected_model().to_string();
-                    
-                    if model.is_empty() {
-                        tracing::error!("No model selected");
-                        self.ai_panel.set_response("Error: No model selected".to_string());
-                        return;
-                    }
-                    
-                    let engine_clone = engine.clone();
-                    let ctx_clone = ctx.clone();
-                    
-                    // Create channel for receiving AI response
-                    let (tx, rx) = mpsc::unbounded_channel();
-                    self.ai_receiver = Some(rx);
-                    
-                    // Create chat request
-                    let request = ChatRequest::new(model)
-                        .with_user_message(context);
-                    
-                    self.runtime.spawn(async move {
-                        match engine_clone.chat_completion_with_provider(&provider_name, request).await {
-                            Ok(response) => {
-                                tracing::info!("Received AI response: {} chars", response.content.len());
-                                let _ = tx.send(AiMessage::Response(response.content));
-                                ctx_clone.request_repaint();
-                            }
-                            Err(e) => {
-                                tracing::error!("AI request failed: {}", e);
-                                let _ = tx.send(AiMessage::Error(format!("AI request failed: {}", e)));
-                                ctx_clone.request_repaint();
-                            }
-                        }
-                    });
-                } else {
-                    tracing::warn!("No AI engine available");
-                    self.ai_panel.set_response("Error: AI engine not initialized".to_string());
-                }
-                
-                ctx.request_repaint();
+        }
+    }
+
+    /// Re-run watched blocks whose interval has elapsed since their last run
+    /// finished. Only one command runs at a time, so a due block waits for
+    /// whatever is currently running.
+    fn run_due_watches(&mut self, ctx: &Context) {
+        let block_manager = &self.block_manager;
+        self.watched_blocks.retain(|id, _| block_manager.get_block(id).is_some());
+
+        let now = chrono::Utc::now();
+        let mut next_wakeup: Option<Duration> = None;
+        let mut due: Option<Uuid> = None;
+
+        for (id, interval) in &self.watched_blocks {
+            let Some(block) = self.block_manager.get_block(id) else {
+                continue;
+            };
+            let Some(completed_at) = block.metadata.completed_at else {
+                continue;
+            };
+            let elapsed = (now - completed_at).to_std().unwrap_or_default();
+            if elapsed >= *interval {
+                due = due.or(Some(*id));
+            } else {
+                let remaining = *interval - elapsed;
+                next_wakeup = Some(next_wakeup.map_or(remaining, |d| d.min(remaining)));
+            }
+        }
+
+        if let Some(wakeup) = next_wakeup {
+            ctx.request_repaint_after(wakeup);
+        }
+
+        if self.current_block_id.is_some() {
+            if due.is_some() {
+                ctx.request_repaint_after(Duration::from_millis(250));
+            }
+            return;
+        }
+
+        if let Some(block_id) = due {
+            if let Some(block) = self.block_manager.get_block_mut(&block_id) {
+                block.restart_execution();
+                let command = block.command.clone();
+                self.spawn_command(block_id, command, ctx);
             }
         }
     }
-}
 
-enum OutputMessage {
-    Output(String),
-    Exit(i32),
-}
+    /// Remember whether a finished block failed with a transient error, and
+    /// schedule the next automatic retry if it is being retried with backoff
+    fn note_transient_failure(&mut self, block_id: Uuid) {
+        let transient = self.block_manager.get_block(&block_id).is_some_and(|block| {
+            block.exit_code.is_some_and(|code| code != 0) && retry::is_transient_failure(&block.output)
+        });
+        if !transient {
+            self.transient_failures.remove(&block_id);
+            self.retries.remove(&block_id);
+            return;
+        }
+        self.transient_failures.insert(block_id);
 
-enum AiMessage {
-    Response(String),
-    StreamChunk(String),
-    Error(String),
-    ModelsLoaded(Vec<String>),
-    CommandGenerated(String), // Generated shell command from natural language
-}
+        let Some(scheduled) = self.retries.get_mut(&block_id) else {
+            return;
+        };
+        if scheduled.attempt >= self.config.retry.max_attempts {
+            self.retries.remove(&block_id);
+            return;
+        }
+        scheduled.attempt += 1;
+        let initial = Duration::from_secs(self.config.retry.initial_delay_secs);
+        scheduled.due = Some(Instant::now() + retry::backoff_delay(initial, scheduled.attempt - 1));
+    }
 
-impl eframe::App for ImmateriumApp {
-    fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
-        // Auto-save session periodically
-        self.auto_save();
-        
-        // Poll output receiver for new output
-        let mut should_clear_receiver = false;
-        if let Some(rx) = &mut self.output_receiver {
-            while let Ok(msg) = rx.try_recv() {
-                match msg {
-                    OutputMessage::Output(text) => {
-                        if let Some(block_id) = self.current_block_id {
-                            if let Some(block) = self.block_manager.get_block_mut(&block_id) {
-                                block.append_output(text);
-                                self.save_needed = true; // Mark for save when output changes
-                            }
+    /// What the block's retry chip shows, if it has one
+    fn retry_status(&self, block: &Block) -> Option<RetryStatus> {
+        let max_attempts = self.config.retry.max_attempts;
+        match self.retries.get(&block.id) {
+            Some(ScheduledRetry { attempt, due: Some(due) }) => Some(RetryStatus::Scheduled {
+                attempt: *attempt,
+                max_attempts,
+                remaining: due.saturating_duration_since(Instant::now()),
+            }),
+            Some(ScheduledRetry { due: None, .. }) => None,
+            None if self.transient_failures.contains(&block.id) && !block.is_running() => {
+                Some(RetryStatus::Available { max_attempts })
+            }
+            None => None,
+        }
+    }
+
+    /// Run a finished block's command again in place
+    fn retry_block(&mut self, block_id: Uuid, ctx: &Context) {
+        if self.current_block_id.is_some() {
+            self.toasts.push(t!("status-command-running"));
+            return;
+        }
+        let Some(block) = self.block_manager.get_block_mut(&block_id) else {
+            return;
+        };
+        block.restart_execution();
+        let command = block.command.clone();
+        self.transient_failures.remove(&block_id);
+        self.spawn_command(block_id, command, ctx);
+    }
+
+    /// Ask the AI why a block failed, from its exit code and the end of its output
+    fn ask_ai_about_exit_code(&mut self, block: &Block, ctx: &Context) {
+        let Some(code) = block.exit_code else {
+            return;
+        };
+        let lines: Vec<&str> = block.output.lines().collect();
+        let tail = lines[lines.len().saturating_sub(EXIT_EXPLANATION_LINES)..].join("\n");
+        let mut prompt = format!("The command `{}` exited with status {}.", block.command, code);
+        if let Some(explanation) = crate::shell::exit_code::explain_exit_code(code) {
+            prompt.push_str(&format!(" That usually means: {}.", explanation));
+        }
+        if !tail.trim().is_empty() {
+            prompt.push_str(&format!("\n\nThe end of its output:\n```\n{}\n```", tail));
+        }
+        prompt.push_str("\n\nWhy did it fail, and how can I fix it?");
+
+        if !self.ai_panel.is_open() {
+            self.ai_panel.toggle_sidebar();
+        }
+        self.send_ai_prompt(prompt, AiOrigin::for_block(AiFeature::Diagnosis, block.id), ctx);
+    }
+
+    /// Start the automatic retry that is due, once nothing else is running
+    fn run_due_retries(&mut self, ctx: &Context) {
+        let block_manager = &self.block_manager;
+        self.retries.retain(|id, _| block_manager.get_block(id).is_some());
+
+        let now = Instant::now();
+        let mut due = None;
+        for (id, scheduled) in &self.retries {
+            match scheduled.due {
+                Some(at) if at <= now => due = due.or(Some(*id)),
+                // Repaint every second so the countdown moves
+                Some(at) => ctx.request_repaint_after((at - now).min(Duration::from_secs(1))),
+                None => {}
+            }
+        }
+
+        let Some(block_id) = due else {
+            return;
+        };
+        if self.current_block_id.is_some() {
+            ctx.request_repaint_after(Duration::from_millis(250));
+            return;
+        }
+        if let Some(scheduled) = self.retries.get_mut(&block_id) {
+            scheduled.due = None;
+        }
+        self.retry_block(block_id, ctx);
+    }
+
+    /// Refresh git status for the session's working directory in the background
+    fn refresh_git_status(&mut self, ctx: &Context) {
+        self.refresh_git_panel(ctx);
+        let working_dir = self.session.working_directory.clone();
+        let ctx_clone = ctx.clone();
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.git_receiver = Some(rx);
+
+        self.runtime.spawn(async move {
+            let status = tokio::task::spawn_blocking(move || GitStatus::detect(&working_dir))
+                .await
+                .unwrap_or(None);
+            let _ = tx.send(status);
+            ctx_clone.request_repaint();
+        });
+    }
+
+    /// Reload the git panel's list of changes in the background, if it is shown
+    fn refresh_git_panel(&mut self, ctx: &Context) {
+        if !self.config.appearance.git_panel {
+            return;
+        }
+        self.git_panel.start_loading();
+        let working_dir = self.session.working_directory.clone();
+        let tx = self.git_panel_sender.clone();
+        let ctx = ctx.clone();
+        self.runtime.spawn_blocking(move || {
+            let status = GitStatus::repo_root(&working_dir)
+                .and_then(|root| git::changed_files(&root).map(|changes| (root, changes)));
+            let _ = tx.send(GitPanelUpdate::Changes(status));
+            ctx.request_repaint();
+        });
+    }
+
+    /// Load the diff of a file selected in the git panel in the background
+    fn load_git_diff(&mut self, change: FileChange, staged: bool, ctx: &Context) {
+        let Some(root) = self.git_panel.root().map(Path::to_path_buf) else {
+            return;
+        };
+        self.git_panel.select(&change.path, staged);
+        let tx = self.git_panel_sender.clone();
+        let ctx = ctx.clone();
+        self.runtime.spawn_blocking(move || {
+            let diff = git::file_diff(&root, &change, staged).unwrap_or_default();
+            let _ = tx.send(GitPanelUpdate::Diff(change.path, staged, diff));
+            ctx.request_repaint();
+        });
+    }
+
+    /// Load shell aliases in the background (requires sourcing ~/.bashrc)
+    fn load_shell_aliases(&mut self, ctx: &Context) {
+        let shell = self.config.general.default_shell.clone();
+        let ctx_clone = ctx.clone();
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.alias_receiver = Some(rx);
+
+        self.runtime.spawn(async move {
+            let aliases = tokio::task::spawn_blocking(move || expansion::load_aliases(&shell))
+                .await
+                .unwrap_or_default();
+            let _ = tx.send(aliases);
+            ctx_clone.request_repaint();
+        });
+    }
+
+    /// What the input will actually run after alias and variable expansion,
+    /// or None if expansion changes nothing
+    fn expansion_preview(&self) -> Option<String> {
+        let input = self.command_input.trim();
+        if input.is_empty()
+            || self.config.ai.operation_mode == crate::config::OperationMode::AiPromptOnly
+        {
+            return None;
+        }
+
+        let environment = self.command_environment();
+        let expanded = expansion::expand_preview(input, &self.shell_aliases, |name| {
+            environment
+                .get(name)
+                .cloned()
+                .or_else(|| std::env::var(name).ok())
+        });
+
+        (expanded != input).then_some(expanded)
+    }
+
+    /// Look for virtualenvs and pinned Node versions around the working directory
+    fn refresh_project_runtimes(&mut self) {
+        self.project_runtimes = ProjectRuntimes::detect(&self.session.working_directory);
+    }
+
+    /// Re-read kubeconfig (honouring KUBECONFIG from the session environment)
+    fn refresh_kube_context(&mut self) {
+        if !self.config.kubernetes.enabled {
+            self.kube_config = None;
+            return;
+        }
+
+        let kubeconfig_env = self.command_environment()
+            .get("KUBECONFIG")
+            .cloned()
+            .or_else(|| std::env::var("KUBECONFIG").ok());
+        self.kube_config = KubeConfig::default_path(kubeconfig_env.as_deref())
+            .filter(|path| path.exists())
+            .and_then(|path| match KubeConfig::load(&path) {
+                Ok(config) => Some(config),
+                Err(e) => {
+                    tracing::warn!("{:#}", e);
+                    None
+                }
+            });
+    }
+
+    /// Whether there are changes auto-save has not written yet
+    fn has_unsaved_changes(&self) -> bool {
+        self.save_needed || self.output_unsaved_since.is_some()
+    }
+
+    /// Clear the unsaved state, returning whether there was any
+    fn take_unsaved_changes(&mut self) -> bool {
+        let unsaved = self.has_unsaved_changes();
+        self.save_needed = false;
+        self.output_unsaved_since = None;
+        unsaved
+    }
+
+    fn auto_save(&mut self) {
+        // Output streaming into the running block is written at most once per
+        // OUTPUT_SAVE_DELAY; any other change, or the command finishing, saves it
+        let output_due = self.output_unsaved_since.is_some_and(|since| {
+            self.current_block_id.is_none() || since.elapsed() >= OUTPUT_SAVE_DELAY
+        });
+
+        // Check if enough time has elapsed since last save
+        let save_interval = Duration::from_secs(self.config.general.auto_save_interval);
+        if self.last_save.elapsed() < save_interval || !(self.save_needed || output_due) {
+            return;
+        }
+
+        if let Some(ref session_manager) = self.session_manager {
+            let session_id = self.session.id;
+            let blocks = self.block_manager.get_blocks().to_vec();
+            
+            let session_manager = session_manager.clone();
+            let pending_writes = self.pending_writes.clone();
+            let save_timings = self.save_timings.clone();
+            // Everything journaled so far is in this save
+            let checkpoint = self.journal.as_mut().map(|j| (j.dir().to_path_buf(), j.checkpoint()));
+            pending_writes.fetch_add(blocks.len(), Ordering::Relaxed);
+            self.runtime.spawn(async move {
+                let started = Instant::now();
+                match session_manager.save_blocks(&session_id, &blocks).await {
+                    Ok(()) => {
+                        let took = started.elapsed();
+                        tracing::debug!("Auto-saved session {} ({} blocks in {:?})", session_id, blocks.len(), took);
+                        if let Ok(mut timings) = save_timings.lock() {
+                            timings.record(took, blocks.len());
                         }
-                    }
-                    OutputMessage::Exit(code) => {
-                        if let Some(block_id) = self.current_block_id {
-                            if let Some(block) = self.block_manager.get_block_mut(&block_id) {
-                                block.complete_execution(code);
-                                self.save_needed = true; // Save when command completes
+                        if let Some((dir, segment)) = checkpoint {
+                            if let Err(e) = journal::discard_through(&dir, segment) {
+                                tracing::warn!("Failed to trim journal: {:#}", e);
                             }
                         }
-                        self.current_block_id = None;
-                        should_clear_receiver = true;
                     }
+                    Err(e) => tracing::error!("Failed to save session: {:#}", e),
                 }
-            }
+                pending_writes.fetch_sub(blocks.len(), Ordering::Relaxed);
+            });
+            
+            self.last_save = Instant::now();
+            self.take_unsaved_changes();
         }
-        if should_clear_receiver {
+    }
+
+    /// Wind down before the window closes: stop (or let go of) the running
+    /// command, write everything auto-save hasn't yet, and close the database
+    fn shut_down(&mut self) {
+        if let Some(block_id) = self.current_block_id.take() {
+            // Output that arrived since the last frame still belongs in the block
+            let mut output = String::new();
+            if let Some(rx) = &mut self.output_receiver {
+                while let Ok(msg) = rx.try_recv() {
+                    match msg {
+                        OutputMessage::Input(input) => self.pty_input = Some(input),
+                        OutputMessage::Output(text) => output.push_str(&text),
+                        _ => {}
+                    }
+                }
+            }
+            let note = if self.config.general.detach_on_exit {
+                "Left running when Immaterium closed"
+            } else {
+                if let Some(input) = &self.pty_input {
+                    let _ = input.send(PtyInput::Terminate);
+                }
+                "Terminated when Immaterium closed"
+            };
+            tracing::info!("{} command in block {}", note, block_id);
+            if let Some(block) = self.block_manager.get_block_mut(&block_id) {
+                if self.terminal_view.is_none() {
+                    block.append_output(output);
+                }
+                block.append_output(format!("\n[{}]\n", note));
+                block.cancel_execution();
+                self.save_needed = true;
+            }
             self.output_receiver = None;
+            self.pty_input = None;
         }
-        
-        // Poll AI receiver for AI responses
-        if let Some(rx) = &mut self.ai_receiver {
-            while let Ok(msg) = rx.try_recv() {
-                match msg {
-                    AiMessage::Response(content) => {
-                        self.ai_panel.set_response(content.clone());
-                        self.ai_panel.add_assistant_message(content);
-                    }
-                    AiMessage::StreamChunk(chunk) => {
-                        self.ai_panel.append_response(chunk);
-                    }
-                    AiMessage::Error(err) => {
-                        self.ai_panel.set_response(format!("Error: {}", err));
-                        self.ai_panel.stop_streaming();
-                        self.is_generating_command = false;
-                    }
-                    AiMessage::ModelsLoaded(models) => {
-                        self.ai_panel.set_available_models(models);
-                        // Save selected model to config
-                        self.config.ai.selected_model = Some(self.ai_panel.selected_model().to_string());
-                        if let Err(e) = self.config.save() {
-                            tracing::error!("Failed to save config: {}", e);
-                        }
-                    }
-                    AiMessage::CommandGenerated(command) => {
-                        // Create a pending approval block instead of showing modal
-                        let block = Block::new_pending_approval(
-                            self.original_nl_input.clone(),
-                            command,
-                            self.session.working_directory.clone(),
-                        );
-                        self.block_manager.add_block(block);
-                        self.is_generating_command = false;
-                        self.original_nl_input.clear();
-                    }
+
+        let Some(session_manager) = self.session_manager.clone() else {
+            return;
+        };
+        // Let auto-saves already handed off finish first, so they can't
+        // overwrite the final save with older blocks
+        let waiting = Instant::now();
+        while self.pending_writes.load(Ordering::Relaxed) > 0 && waiting.elapsed() < SHUTDOWN_SAVE_WAIT {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let saved = if self.take_unsaved_changes() {
+            let blocks = self.block_manager.get_blocks().to_vec();
+            match self.runtime.block_on(session_manager.save_blocks(&self.session.id, &blocks)) {
+                Ok(()) => {
+                    tracing::info!("Saved session {} on exit", self.session.id);
+                    true
+                }
+                Err(e) => {
+                    tracing::error!("Failed to save session on exit: {:#}", e);
+                    false
                 }
             }
+        } else {
+            true
+        };
+        // Nothing is left for the journal to recover unless that save failed
+        if let (true, Some(journal)) = (saved, &mut self.journal) {
+            let segment = journal.checkpoint();
+            if let Err(e) = journal::discard_through(journal.dir(), segment) {
+                tracing::warn!("Failed to clear journal: {:#}", e);
+            }
         }
-        
-        // Top menu bar
-        TopBottomPanel::top("menu_bar").show(ctx, |ui| {
-            egui::menu::bar(ui, |ui| {
-                ui.menu_button("File", |ui| {
-                    if ui.button("New Session").clicked() {
-                        self.show_new_session_dialog = true;
-                        ui.close_menu();
-                    }
-                    if ui.button("Open Session...").clicked() {
-                        self.load_available_sessions();
-                        self.show_session_list = true;
-                        ui.close_menu();
-                    }
-                    if ui.button("Save Session").clicked() {
-                        self.save_needed = true;
-                        self.auto_save();
-                        ui.close_menu();
-                    }
-                    ui.separator();
-                    if ui.button("Export...").clicked() {
-                        self.show_export_dialog = true;
-                        ui.close_menu();
-                    }
-                    ui.separator();
-                    if ui.button("Settings").clicked() {
-                        tracing::info!("Settings clicked");
-                        ui.close_menu();
-                    }
-                    ui.separator();
-                    if ui.button("Quit").clicked() {
-                        ctx.send_viewport_cmd(ViewportCommand::Close);
-                    }
-                });
+        self.runtime.block_on(session_manager.close());
+    }
 
-                ui.menu_button("Edit", |ui| {
-                    if ui.button("Copy").clicked() {
-                        ui.close_menu();
-                    }
-                    if ui.button("Paste").clicked() {
-                        ui.close_menu();
-                    }
-                });
+    /// Last-ditch save while a panic unwinds: write the blocks to the
+    /// database, or to a JSON file next to the crash report if that fails
+    fn emergency_save(&mut self) {
+        let blocks = self.block_manager.get_blocks().to_vec();
+        let session_id = self.session.id;
+        let saved = self.session_manager.as_ref().map(|session_manager| {
+            self.runtime.block_on(async {
+                tokio::time::timeout(SHUTDOWN_SAVE_WAIT, session_manager.save_blocks(&session_id, &blocks)).await
+            })
+        });
+        let note = match saved {
+            Some(Ok(Ok(()))) => format!("Session {} was saved to the database.", session_id),
+            failed => {
+                if let Some(Ok(Err(e))) = failed {
+                    tracing::error!("Emergency save failed: {:#}", e);
+                }
+                match crash_dir().map(|dir| crash::dump_blocks(&dir, &session_id, &blocks)) {
+                    Some(Ok(path)) => format!("Session {} could not be saved; its blocks were written to {}", session_id, path.display()),
+                    Some(Err(e)) => format!("Session {} could not be saved or written to a file: {:#}", session_id, e),
+                    None => format!("Session {} could not be saved.", session_id),
+                }
+            }
+        };
+        tracing::error!("{}", note);
+        crash::add_to_report(&note);
+    }
 
-                ui.menu_button("View", |ui| {
-                    if ui.button("🎨 Change Theme...").clicked() {
-                        self.show_theme_selector = true;
-                        ui.close_menu();
-                    }
-                    ui.separator();
-                    if ui.button("Split Horizontal").clicked() {
-                        tracing::info!("Split horizontal clicked");
-                        ui.close_menu();
-                    }
-                    if ui.button("Split Vertical").clicked() {
-                        tracing::info!("Split vertical clicked");
-                        ui.close_menu();
-                    }
-                    ui.separator();
-                    if ui.button("Zoom In").clicked() {
-                        ui.close_menu();
-                    }
-                    if ui.button("Zoom Out").clicked() {
-                        ui.close_menu();
-                    }
-                });
+    /// Refresh the session list in the background
+    fn load_available_sessions(&mut self, ctx: &Context) {
+        if let Some(ref session_manager) = self.session_manager {
+            let session_manager = session_manager.clone();
+            let tx = self.session_sender.clone();
+            let ctx = ctx.clone();
+            self.session_list.start();
+            self.runtime.spawn(async move {
+                let result = session_manager.list_sessions().await.map_err(|e| format!("{:#}", e));
+                let _ = tx.send(SessionMessage::Listed(result));
+                ctx.request_repaint();
+            });
+        }
+    }
 
-                ui.menu_button("AI", |ui| {
-                    if ui.button("Toggle AI Panel").clicked() {
-                        self.ai_panel.toggle_sidebar();
-                        ui.close_menu();
-                    }
-                    
-                    ui.separator();
-                    ui.label("Operation Mode:");
-                    
-                    use crate::config::OperationMode;
-                    
-                    if ui.selectable_label(
-                        self.config.ai.operation_mode == OperationMode::TerminalOnly,
-                        "🖥️ Terminal Only"
-                    ).on_hover_text("Always execute as shell commands").clicked() {
-                        self.config.ai.operation_mode = OperationMode::TerminalOnly;
-                        ui.close_menu();
-                    }
-                    
-                    if ui.selectable_label(
-                        self.config.ai.operation_mode == OperationMode::AiPromptOnly,
-                        "🤖 AI Prompt Only"
-                    ).on_hover_text("Always convert to commands using AI").clicked() {
-                        self.config.ai.operation_mode = OperationMode::AiPromptOnly;
-                        ui.close_menu();
-                    }
-                    
-                    if ui.selectable_label(
-                        self.config.ai.operation_mode == OperationMode::Hybrid,
-                        "🔀 Hybrid (Auto-detect)"
-                    ).on_hover_text("Automatically detect NL vs commands").clicked() {
-                        self.config.ai.operation_mode = OperationMode::Hybrid;
-                        ui.close_menu();
-                    }
-                    
-                    ui.separator();
-                    
-                    ui.label("Provider:");
-                    for (name, provider) in &self.config.ai.providers {
-                        if provider.enabled {
-                            let is_selected = name == &self.config.ai.default_provider;
-                            if ui.selectable_label(is_selected, name).clicked() {
-                                tracing::info!("Switched AI provider to: {}", name);
-                                ui.close_menu();
-                            }
-                        }
-                    }
-                });
+    /// One session entry in the Open Session dialog
+    fn show_session_row(
+        &mut self,
+        session_info: &SessionInfo,
+        folders: &[String],
+        moves: &mut Vec<(Uuid, Option<String>)>,
+        ui: &mut egui::Ui,
+        ctx: &Context,
+    ) {
+        ui.horizontal(|ui| {
+            let handle_id = egui::Id::new(("session_drag", session_info.id));
+            ui.dnd_drag_source(handle_id, session_info.id, |ui| {
+                ui.label(RichText::new("⠿").color(Color32::from_rgb(120, 120, 120)));
+            });
 
-                ui.menu_button("Help", |ui| {
-                    if ui.button("Documentation").clicked() {
-                        ui.close_menu();
-                    }
-                    if ui.button("Keyboard Shortcuts").clicked() {
-                        ui.close_menu();
-                    }
-                    ui.separator();
-                    if ui.button("About").clicked() {
+            if let Some(color) = session_info.color {
+                ui.label(RichText::new("●").color(session_color(color)));
+            }
+
+            let is_current = session_info.id == self.session.id;
+            let label = if is_current {
+                t!("open-session-current", name = session_info.name.clone())
+            } else if session_info.is_active {
+                format!("● {}", session_info.name)
+            } else {
+                session_info.name.clone()
+            };
+
+            let response = ui.selectable_label(is_current, label);
+            if response.clicked() && !is_current {
+                self.switch_to_session(session_info.id, ctx);
+                self.show_session_list = false;
+            }
+            if let Some(subtitle) = session_subtitle(session_info, &self.available_sessions) {
+                ui.label(RichText::new(subtitle).small().weak());
+            }
+            if let Some(ref description) = session_info.description {
+                ui.label(RichText::new(description).italics().weak());
+            }
+            response.context_menu(|ui| {
+                ui.label(RichText::new(t!("open-session-move-to")).weak());
+                for folder in folders {
+                    if session_info.folder.as_ref() != Some(folder) && ui.button(format!("📁 {}", folder)).clicked() {
+                        moves.push((session_info.id, Some(folder.clone())));
                         ui.close_menu();
                     }
-                });
+                }
+                if session_info.folder.is_some() && ui.button(t!("open-session-ungrouped")).clicked() {
+                    moves.push((session_info.id, None));
+                    ui.close_menu();
+                }
+            });
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.label(t!(
+                    "open-session-updated",
+                    time = session_info.updated_at.format("%Y-%m-%d %H:%M").to_string()
+                ));
             });
         });
+    }
 
-        // Main terminal area
-        CentralPanel::default().show(ctx, |ui| {
-            // Handle right-click anywhere in the panel
-            let panel_response = ui.interact(
+    fn move_session_to_folder(&mut self, session_id: Uuid, folder: Option<String>) {
+        if session_id == self.session.id {
+            self.session.folder = folder.clone();
+        }
+
+        if let Some(ref session_manager) = self.session_manager {
+            let session_manager = session_manager.clone();
+            if let Err(e) = self.runtime.block_on(async {
+                session_manager.set_session_folder(&session_id, folder.as_deref()).await
+            }) {
+                tracing::error!("Failed to move session: {}", e);
+            }
+        }
+    }
+
+    /// Load the database key when encryption is on (or blocks are still
+    /// encrypted from before) and bring stored blocks in line with the setting
+    async fn configure_encryption(sm: SessionManager, storage: &StorageConfig) -> SessionManager {
+        let encrypt = storage.encrypt_history;
+        if !encrypt && !sm.has_encrypted_blocks().await.unwrap_or(false) {
+            return sm;
+        }
+
+        let sm = match FieldCipher::from_keyring(encrypt) {
+            Ok(Some(cipher)) => sm.with_cipher(cipher, encrypt),
+            Ok(None) => {
+                tracing::error!("Database has encrypted blocks but the OS keyring has no key for them");
+                return sm;
+            }
+            Err(e) => {
+                tracing::error!("Database encryption unavailable: {:#}", e);
+                return sm;
+            }
+        };
+
+        if let Err(e) = sm.migrate_encryption().await {
+            tracing::error!("Failed to update block encryption: {:#}", e);
+        }
+        sm
+    }
+
+    /// Measure the database in the background; arrives as `SessionMessage::DatabaseSize`
+    fn refresh_database_size(&mut self, ctx: &Context) {
+        let Some(session_manager) = self.session_manager.clone() else {
+            self.database_size = None;
+            return;
+        };
+        let tx = self.session_sender.clone();
+        let ctx = ctx.clone();
+        self.runtime.spawn(async move {
+            let _ = tx.send(SessionMessage::DatabaseSize(session_manager.database_size().await.ok()));
+            ctx.request_repaint();
+        });
+    }
+
+    /// Apply the retention limits now, in the background. The open session is
+    /// saved first and reloaded afterwards so removed blocks don't get written
+    /// back. The result arrives as `SessionMessage::Retained`.
+    fn apply_retention_now(&mut self, ctx: &Context) {
+        let Some(session_manager) = self.session_manager.clone() else {
+            return;
+        };
+        let storage = self.config.storage.clone();
+        let session_id = self.session.id;
+        let blocks = self.block_manager.get_blocks().to_vec();
+        let tx = self.session_sender.clone();
+        let ctx = ctx.clone();
+
+        self.storage_busy = true;
+        self.storage_status = Some(t!("settings-applying-retention"));
+        self.runtime.spawn(async move {
+            let result = async {
+                session_manager.save_blocks(&session_id, &blocks).await?;
+                let removed = session_manager.apply_retention(
+                    storage.max_block_age_days,
+                    storage.max_blocks_per_session,
+                    retention_archive(&storage).as_deref(),
+                ).await?;
+                let session = session_manager.load_session(&session_id).await?;
+                let size = session_manager.database_size().await.ok();
+                anyhow::Ok(Retained { removed, blocks: session.blocks, size })
+            }
+            .await;
+            let _ = tx.send(SessionMessage::Retained(session_id, result.map_err(|e| format!("{:#}", e))));
+            ctx.request_repaint();
+        });
+    }
+
+    /// VACUUM the database in the background; the sizes before and after
+    /// arrive as `SessionMessage::Compacted`
+    fn compact_database(&mut self, ctx: &Context) {
+        let Some(session_manager) = self.session_manager.clone() else {
+            return;
+        };
+        let tx = self.session_sender.clone();
+        let ctx = ctx.clone();
+
+        self.storage_busy = true;
+        self.storage_status = Some(t!("settings-compacting"));
+        self.runtime.spawn(async move {
+            let before = session_manager.database_size().await.ok();
+            let result = match session_manager.compact_database().await {
+                Ok(()) => Ok((before, session_manager.database_size().await.ok())),
+                Err(e) => Err(format!("{:#}", e)),
+            };
+            let _ = tx.send(SessionMessage::Compacted(result));
+            ctx.request_repaint();
+        });
+    }
+
+    /// Snapshot the database in the background once the backup interval has passed
+    fn run_due_backup(&mut self) {
+        let hours = self.config.storage.backup_interval_hours;
+        let (Some(session_manager), Some(dir)) = (self.session_manager.clone(), backup_dir()) else {
+            return;
+        };
+        let interval = Duration::from_secs(u64::from(hours) * 3600);
+        let due = self.last_backup
+            .is_none_or(|t| t.elapsed().unwrap_or_default() >= interval);
+        if hours == 0 || !due {
+            return;
+        }
+
+        self.last_backup = Some(SystemTime::now());
+        let keep = self.config.storage.backups_to_keep as usize;
+        self.runtime.spawn(async move {
+            if let Err(e) = session_manager.backup_database(&dir, keep).await {
+                tracing::error!("Database backup failed: {:#}", e);
+            }
+        });
+    }
+
+    fn refresh_backups(&mut self) {
+        self.backups = backup_dir()
+            .and_then(|dir| backup::list_backups(&dir).ok())
+            .unwrap_or_default();
+    }
+
+    /// Snapshot the database in the background; the result arrives as
+    /// `SessionMessage::BackedUp`
+    fn back_up_now(&mut self, ctx: &Context) {
+        let (Some(session_manager), Some(dir)) = (self.session_manager.clone(), backup_dir()) else {
+            return;
+        };
+        let keep = self.config.storage.backups_to_keep as usize;
+        let tx = self.session_sender.clone();
+        let ctx = ctx.clone();
+
+        self.backup_busy = true;
+        self.backup_status = Some("Backing up...".to_string());
+        self.runtime.spawn(async move {
+            let result = session_manager.backup_database(&dir, keep).await.map_err(|e| format!("{:#}", e));
+            let _ = tx.send(SessionMessage::BackedUp(result));
+            ctx.request_repaint();
+        });
+    }
+
+    /// Swap the database for a backup and reopen it in the background. The
+    /// session manager is away until `SessionMessage::Restored` brings back
+    /// the reopened one, so nothing writes to the database meanwhile.
+    fn restore_from_backup(&mut self, backup_path: PathBuf, ctx: &Context) {
+        let Some(session_manager) = self.session_manager.take() else {
+            return;
+        };
+        let storage = self.config.storage.clone();
+        let session = self.session.clone();
+        let tx = self.session_sender.clone();
+        let ctx = ctx.clone();
+
+        self.backup_busy = true;
+        self.backup_status = Some("Restoring...".to_string());
+        self.runtime.spawn(async move {
+            session_manager.close().await;
+            let restored = backup::restore_backup(&backup_path, &database_path())
+                .await
+                .map_err(|e| format!("{:#}", e));
+            // Reopen whichever database is now in place, restored or not
+            let reopened = async {
+                let sm = SessionManager::new(Database::new(database_path()).await?).await?;
+                let sm = Self::configure_encryption(sm, &storage).await;
+                let active = match sm.get_active_session().await {
+                    Ok(Some(active)) => Some(active.id),
+                    // Keep the open session in a restore that has none
+                    _ => {
+                        sm.create_session(&session).await?;
+                        None
+                    }
+                };
+                anyhow::Ok((sm, active))
+            }
+            .await
+            .map_err(|e| format!("{:#}", e));
+            let _ = tx.send(SessionMessage::Restored(Box::new(RestoreOutcome { backup_path, restored, reopened })));
+            ctx.request_repaint();
+        });
+    }
+
+    fn finish_restore(&mut self, outcome: RestoreOutcome, ctx: &Context) {
+        self.backup_busy = false;
+        let (session_manager, active) = match outcome.reopened {
+            Ok(reopened) => reopened,
+            Err(e) => {
+                tracing::error!("Failed to reopen database: {}", e);
+                self.backup_status = Some(format!("Failed to reopen database: {}", e));
+                return;
+            }
+        };
+        self.session_manager = Some(session_manager);
+        if let Err(e) = outcome.restored {
+            self.backup_status = Some(format!("Restore failed: {}", e));
+            return;
+        }
+
+        // Blocks in memory belong to the replaced database
+        self.take_unsaved_changes();
+        match active {
+            Some(session_id) => self.switch_to_session(session_id, ctx),
+            None => self.save_needed = true,
+        }
+        self.backup_status = Some(format!(
+            "Restored {}; the previous database was kept as {}.pre-restore",
+            outcome.backup_path.file_name().unwrap_or_default().to_string_lossy(),
+            database_path().display()
+        ));
+        self.refresh_database_size(ctx);
+    }
+
+    /// Write every session, and the settings if asked, to `archive_path` in
+    /// the background; the result arrives as `SessionMessage::Exported`
+    fn export_all_data(&mut self, ctx: &Context) {
+        let Some(session_manager) = self.session_manager.clone() else {
+            return;
+        };
+        // Get the open session's latest blocks into the database first
+        let blocks = self.block_manager.get_blocks().to_vec();
+        let settings = if self.archive_include_settings {
+            Config::config_path().ok().and_then(|path| std::fs::read_to_string(path).ok())
+        } else {
+            None
+        };
+        let session_id = self.session.id;
+        let path = crate::utils::expand_tilde(self.archive_path.trim());
+        let passphrase = Some(self.archive_passphrase.clone()).filter(|p| !p.is_empty());
+        let tx = self.session_sender.clone();
+        let ctx = ctx.clone();
+
+        self.archive_busy = true;
+        self.archive_status = Some("Exporting...".to_string());
+        self.runtime.spawn(async move {
+            let result = async {
+                session_manager.save_blocks(&session_id, &blocks).await?;
+                let archive = DataArchive::collect(&session_manager, settings).await?;
+                archive.to_file(&path, passphrase.as_deref())?;
+                anyhow::Ok((archive.sessions.len(), path))
+            }
+            .await;
+            let _ = tx.send(SessionMessage::Exported(result.map_err(|e| format!("{:#}", e))));
+            ctx.request_repaint();
+        });
+    }
+
+    /// Restore sessions (and settings, if asked) from `archive_path` in the
+    /// background; `finish_data_import` takes it from there
+    fn import_all_data(&mut self, ctx: &Context) {
+        let Some(session_manager) = self.session_manager.clone() else {
+            return;
+        };
+        let path = crate::utils::expand_tilde(self.archive_path.trim());
+        let passphrase = self.archive_passphrase.clone();
+        let include_settings = self.archive_include_settings;
+        let blocks = self.block_manager.get_blocks().to_vec();
+        let session_id = self.session.id;
+        let mode = self.archive_restore_mode;
+        let tx = self.session_sender.clone();
+        let ctx = ctx.clone();
+
+        self.archive_busy = true;
+        self.archive_status = Some("Importing...".to_string());
+        self.runtime.spawn(async move {
+            let result = async {
+                let archive = DataArchive::from_file(&path, &passphrase)?;
+                session_manager.save_blocks(&session_id, &blocks).await?;
+                let report = archive.restore(&session_manager, mode).await?;
+                let sessions = session_manager.list_sessions().await.unwrap_or_default();
+                let settings = archive.settings.filter(|_| include_settings);
+                anyhow::Ok(Box::new(DataImport { report, settings, sessions }))
+            }
+            .await;
+            let _ = tx.send(SessionMessage::Imported(result.map_err(|e| format!("{:#}", e))));
+            ctx.request_repaint();
+        });
+    }
+
+    /// Report an archive import, load its settings, then reopen the open
+    /// session (or the active one), since it may have changed
+    fn finish_data_import(&mut self, import: DataImport, ctx: &Context) {
+        let mut status = format!("Imported sessions: {}", import.report.summary());
+        if let Some(settings) = &import.settings {
+            match self.restore_settings(settings) {
+                Ok(()) => status.push_str("; settings restored"),
+                Err(e) => status.push_str(&format!("; settings not restored: {:#}", e)),
+            }
+        }
+        self.archive_status = Some(status);
+
+        // Blocks in memory may belong to a session that was replaced or removed
+        self.take_unsaved_changes();
+        let sessions = &import.sessions;
+        let reopen = sessions
+            .iter()
+            .find(|s| s.id == self.session.id)
+            .or_else(|| sessions.iter().find(|s| s.is_active))
+            .or(sessions.first());
+        match reopen {
+            Some(session) => self.switch_to_session(session.id, ctx),
+            None => {
+                let session = Session::new("default".to_string(), self.session.working_directory.clone());
+                self.load_session_in_background(session.id, Some(session), ctx);
+            }
+        }
+    }
+
+    /// Replace config.toml with settings from an archive and load them
+    fn restore_settings(&mut self, settings: &str) -> anyhow::Result<()> {
+        toml::from_str::<Config>(settings).map_err(|e| anyhow::anyhow!("The archived settings can't be read: {}", e))?;
+        let path = Config::config_path()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, settings).map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+        self.config = Config::load_from_file(&path)?;
+        Ok(())
+    }
+
+    /// Send a request to a plugin in the background; `tag` wraps the result
+    /// so the reply can be routed when it arrives
+    fn run_plugin<F>(&self, plugin: Plugin, request: PluginRequest, tag: F, ctx: &Context)
+    where
+        F: FnOnce(Result<PluginResponse, String>) -> PluginReply + Send + 'static,
+    {
+        let tx = self.plugin_sender.clone();
+        let ctx_clone = ctx.clone();
+        self.runtime.spawn(async move {
+            let result = plugins::invoke(&plugin, &request).await;
+            let _ = tx.send(tag(result.map_err(|e| format!("{:#}", e))));
+            ctx_clone.request_repaint();
+        });
+    }
+
+    fn run_plugin_action(&mut self, plugin: Plugin, action: String, block_id: Uuid, ctx: &Context) {
+        let Some(block) = self.block_manager.get_block(&block_id) else {
+            return;
+        };
+        let request = PluginRequest::Action { action, block: block.into() };
+        self.plugin_status = Some(format!("🧩 {}...", plugin.name()));
+        self.run_plugin(plugin, request, PluginReply::Action, ctx);
+    }
+
+    fn run_block_action(&mut self, action: BlockActionConfig, block_id: Uuid, ctx: &Context) {
+        let Some(block) = self.block_manager.get_block(&block_id) else {
+            return;
+        };
+        let input = ActionInput::from(block);
+        let tx = self.action_sender.clone();
+        let ctx = ctx.clone();
+        self.action_status = Some(format!("⚡ {}...", action.label));
+        self.runtime.spawn(async move {
+            let output = shell::run_block_action(&action, &input).await.map_err(|e| e.to_string());
+            let _ = tx.send(BlockActionReply { action, cwd: input.cwd, output });
+            ctx.request_repaint();
+        });
+    }
+
+    fn handle_block_action_reply(&mut self, reply: BlockActionReply, ctx: &Context) {
+        let label = &reply.action.label;
+        let output = match reply.output {
+            Ok(output) => output,
+            Err(e) => {
+                self.action_status = Some(format!("⚡ {}", e));
+                return;
+            }
+        };
+        self.action_status = match reply.action.result {
+            BlockActionResult::Status => output.lines().next().map(|line| format!("⚡ {}", line)),
+            BlockActionResult::Clipboard => {
+                ctx.output_mut(|o| o.copied_text = output);
+                Some(format!("⚡ {}: copied to clipboard", label))
+            }
+            BlockActionResult::Insert => {
+                self.command_input = output.trim_end().to_string();
+                None
+            }
+            BlockActionResult::Block => {
+                let mut block = Block::new(reply.action.command.clone(), reply.cwd);
+                block.start_execution();
+                block.append_output(output);
+                block.complete_execution(0);
+                self.block_manager.add_block(block);
+                self.save_needed = true;
+                None
+            }
+            BlockActionResult::None => None,
+        };
+    }
+
+    fn refresh_plugin_panel(&mut self, ctx: &Context) {
+        let Some(view) = &self.plugin_panel else {
+            return;
+        };
+        let request = PluginRequest::Panel {
+            panel: view.panel.id.clone(),
+            session: SessionPayload::new(&self.session, self.block_manager.get_blocks()),
+        };
+        let panel_id = view.panel.id.clone();
+        self.run_plugin(view.plugin.clone(), request, move |result| PluginReply::Panel(panel_id, result), ctx);
+    }
+
+    /// Apply the script's `transform_output` and `on_block_complete` hooks to a finished block
+    fn run_completion_hooks(&mut self, block_id: Uuid) {
+        let (Some(scripts), Some(block)) = (&self.scripts, self.block_manager.get_block_mut(&block_id)) else {
+            return;
+        };
+        if let Some(output) = scripts.transform_output(&block.command, &block.output.to_string()) {
+            block.output.clear();
+            block.output.push_str(&output);
+        }
+        scripts.on_block_complete(block).apply(block);
+        self.save_needed = true;
+    }
+
+    fn reload_scripts(&mut self) {
+        match &mut self.scripts {
+            Some(scripts) => {
+                self.script_status = Some(match scripts.reload() {
+                    Ok(()) => script_summary(scripts),
+                    Err(e) => format!("📜 {}", e),
+                });
+            }
+            None => (self.scripts, self.script_status) = load_scripts(&self.config.scripting),
+        }
+    }
+
+    /// Ask a plugin renderer for the output of a finished block, if one handles its command
+    fn render_with_plugin(&mut self, block_id: Uuid, ctx: &Context) {
+        let (Some(host), Some(block)) = (&self.plugin_host, self.block_manager.get_block(&block_id)) else {
+            return;
+        };
+        let Some((plugin, renderer)) = host.renderer_for(&block.command) else {
+            return;
+        };
+        let request = PluginRequest::Render { renderer: renderer.id.clone(), block: block.into() };
+        self.run_plugin(plugin.clone(), request, move |result| PluginReply::Render(block_id, result), ctx);
+    }
+
+    fn handle_plugin_reply(&mut self, reply: PluginReply, ctx: &Context) {
+        match reply {
+            PluginReply::Action(Ok(response)) => {
+                if let Some(text) = response.copy {
+                    ctx.output_mut(|o| o.copied_text = text);
+                }
+                if let Some(command) = response.insert_command {
+                    self.command_input = command;
+                }
+                self.plugin_status = response.message.or(response.text).map(|m| format!("🧩 {}", m));
+            }
+            PluginReply::Action(Err(e)) => self.plugin_status = Some(format!("🧩 {}", e)),
+            PluginReply::Panel(panel_id, result) => {
+                if let Some(view) = self.plugin_panel.as_mut().filter(|v| v.panel.id == panel_id) {
+                    match result {
+                        Ok(response) => {
+                            view.text = response.text.or(response.message);
+                            view.error = None;
+                        }
+                        Err(e) => view.error = Some(e),
+                    }
+                }
+            }
+            PluginReply::Render(block_id, Ok(response)) => {
+                if let Some(text) = response.text {
+                    self.plugin_renders.insert(block_id, text);
+                }
+            }
+            PluginReply::Render(_, Err(e)) => tracing::warn!("Plugin renderer failed: {}", e),
+        }
+    }
+
+    /// Compare `block_id` against the last earlier run of the same command
+    /// that succeeded, or failing that the block just before it
+    fn open_environment_diff(&mut self, block_id: Uuid) {
+        let blocks: Vec<&Block> = self.block_manager.get_blocks()
+            .iter()
+            .filter(|b| !b.is_divider && !b.is_calculation && b.metadata.started_at.is_some())
+            .collect();
+        let Some(index) = blocks.iter().position(|b| b.id == block_id) else {
+            return;
+        };
+        let earlier = &blocks[..index];
+        let before = earlier.iter()
+            .rev()
+            .find(|b| b.command == blocks[index].command && b.exit_code == Some(0))
+            .or_else(|| earlier.last())
+            .map(|b| b.id)
+            .unwrap_or(block_id);
+        self.environment_diff = Some((before, block_id));
+    }
+
+    /// Render a block (or the whole session) as Markdown with secrets
+    /// redacted, and open it for review before sharing
+    fn open_share_dialog(&mut self, block_id: Option<Uuid>) {
+        let mut session = self.session.clone();
+        session.blocks = match block_id {
+            Some(id) => self.block_manager.get_block(&id).cloned().into_iter().collect(),
+            None => self.block_manager.get_blocks().to_vec(),
+        };
+        let title = match session.blocks.first() {
+            Some(block) if block_id.is_some() => block.command.clone(),
+            _ => format!("Session: {}", session.name),
+        };
+        let slug: String = session.name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+            .collect();
+
+        let redaction = share::redact(&ExportedSession::new(session).to_markdown());
+        self.share_draft = Some(ShareDraft {
+            title,
+            file_name: format!("{}.md", slug.trim_matches('-')),
+            content: redaction.text,
+            redactions: redaction.count,
+            target: 0,
+            status: None,
+        });
+    }
+
+    fn upload_share(&mut self, target: ShareTarget, ctx: &Context) {
+        let Some(draft) = &mut self.share_draft else {
+            return;
+        };
+        if self.config.general.offline {
+            draft.status = Some(t!("share-offline"));
+            return;
+        }
+        let (file_name, title, content) = (draft.file_name.clone(), draft.title.clone(), draft.content.clone());
+        draft.status = Some(t!("share-uploading", target = target.label()));
+
+        let ctx_clone = ctx.clone();
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.share_receiver = Some(rx);
+        self.runtime.spawn(async move {
+            let result = share::upload(&target, &file_name, &title, &content).await;
+            let _ = tx.send(result.map_err(|e| format!("{:#}", e)));
+            ctx_clone.request_repaint();
+        });
+    }
+
+    /// Offline mode holds back sync with anything but a directory
+    fn sync_blocked_offline(&self) -> bool {
+        self.config.general.offline
+            && self.config.sync.backend.as_ref().is_some_and(|backend| !matches!(backend, SyncBackendConfig::Directory { .. }))
+    }
+
+    /// Sync sessions with the configured backend in the background
+    fn start_sync(&mut self, ctx: &Context) {
+        let (Some(session_manager), Some(backend)) =
+            (self.session_manager.clone(), self.config.sync.backend.as_ref())
+        else {
+            return;
+        };
+        if self.sync_receiver.is_some() || self.sync_blocked_offline() {
+            return;
+        }
+        let backend = sync::backend_from_config(backend);
+        let passphrase = self.config.sync.passphrase.clone();
+        self.last_sync = Some(Instant::now());
+
+        // Unsaved blocks are written first so the current session syncs as shown
+        let session_id = self.session.id;
+        let blocks = self.block_manager.get_blocks().to_vec();
+        let save_first = self.take_unsaved_changes();
+
+        let ctx_clone = ctx.clone();
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.sync_receiver = Some(rx);
+        self.sync_status = Some("⇅ Syncing...".to_string());
+
+        self.runtime.spawn(async move {
+            let result = async {
+                if save_first {
+                    session_manager.save_blocks(&session_id, &blocks).await?;
+                }
+                sync::sync_sessions(&session_manager, backend.as_ref(), passphrase.as_deref()).await
+            }
+            .await;
+            let _ = tx.send(result.map_err(|e| format!("{:#}", e)));
+            ctx_clone.request_repaint();
+        });
+    }
+
+    fn finish_sync(&mut self, result: Result<SyncReport, String>, ctx: &Context) {
+        match result {
+            Ok(report) => {
+                self.sync_status = Some(format!("⇅ {}", report.summary()));
+                // Show a pulled copy of the current session unless it is busy
+                let idle = self.current_block_id.is_none() && !self.has_unsaved_changes();
+                if idle && report.pulled.contains(&self.session.id) {
+                    self.switch_to_session(self.session.id, ctx);
+                }
+            }
+            Err(e) => {
+                tracing::error!("Session sync failed: {}", e);
+                self.sync_status = Some(format!("⇅ Sync failed: {}", e));
+            }
+        }
+    }
+
+    /// Load a session in the background and switch to it once it arrives
+    fn switch_to_session(&mut self, session_id: Uuid, ctx: &Context) {
+        self.load_session_in_background(session_id, None, ctx);
+    }
+
+    /// Create `new_session` (if given) with its blocks, load `session_id` and
+    /// mark it active, all off the UI thread. The new session's name is made
+    /// unique against the stored sessions first. The result arrives as
+    /// `SessionMessage::Loaded`.
+    fn load_session_in_background(&mut self, session_id: Uuid, new_session: Option<Session>, ctx: &Context) {
+        let Some(session_manager) = self.session_manager.clone() else {
+            return;
+        };
+        let tx = self.session_sender.clone();
+        let ctx = ctx.clone();
+        self.loading_session = Some(session_id);
+        self.runtime.spawn(async move {
+            let result = async {
+                if let Some(mut session) = new_session {
+                    let sessions = session_manager.list_sessions().await?;
+                    session.name = unique_session_name(&session.name, &sessions);
+                    session_manager.create_session(&session).await?;
+                    for (index, block) in session.blocks.iter().enumerate() {
+                        session_manager.save_block(&session.id, block, index as i32).await?;
+                    }
+                }
+                let session = session_manager.load_session(&session_id).await?;
+                session_manager.set_active_session(&session_id).await?;
+                anyhow::Ok(Box::new(session))
+            }
+            .await;
+            let _ = tx.send(SessionMessage::Loaded(session_id, result.map_err(|e| format!("{:#}", e))));
+            ctx.request_repaint();
+        });
+    }
+
+    fn handle_session_message(&mut self, message: SessionMessage, ctx: &Context) {
+        match message {
+            SessionMessage::Listed(result) => {
+                if let Some(sessions) = self.session_list.finish(result) {
+                    self.broadcast_targets.retain(|id| sessions.iter().any(|s| s.id == *id));
+                    self.available_sessions = sessions;
+                }
+            }
+            SessionMessage::DatabaseSize(size) => self.database_size = size,
+            SessionMessage::BackedUp(result) => {
+                self.backup_busy = false;
+                self.backup_status = Some(match result {
+                    Ok(path) => {
+                        self.last_backup = Some(SystemTime::now());
+                        format!("Backed up to {}", abbreviate_home(&path))
+                    }
+                    Err(e) => format!("Backup failed: {}", e),
+                });
+                self.refresh_backups();
+            }
+            SessionMessage::Restored(outcome) => self.finish_restore(*outcome, ctx),
+            SessionMessage::Exported(result) => {
+                self.archive_busy = false;
+                self.archive_status = Some(match result {
+                    Ok((count, path)) => format!("Exported {} sessions to {}", count, abbreviate_home(&path)),
+                    Err(e) => format!("Export failed: {}", e),
+                });
+            }
+            SessionMessage::Imported(result) => {
+                self.archive_busy = false;
+                match result {
+                    Ok(import) => self.finish_data_import(*import, ctx),
+                    Err(e) => self.archive_status = Some(format!("Import failed: {}", e)),
+                }
+            }
+            SessionMessage::Retained(session_id, result) => {
+                self.storage_busy = false;
+                match result {
+                    Ok(retained) => {
+                        // Unless another session was opened meanwhile
+                        if session_id == self.session.id {
+                            self.block_manager = BlockManager::new();
+                            for block in retained.blocks {
+                                self.block_manager.add_block(block);
+                            }
+                            self.take_unsaved_changes();
+                        }
+                        self.database_size = retained.size;
+                        self.storage_status = Some(t!("settings-blocks-removed", count = retained.removed));
+                    }
+                    Err(e) => self.storage_status = Some(t!("settings-retention-failed", error = e)),
+                }
+            }
+            SessionMessage::Compacted(result) => {
+                self.storage_busy = false;
+                self.storage_status = Some(match result {
+                    Ok((Some(before), Some(after))) => {
+                        self.database_size = Some(after);
+                        t!("settings-compacted", before = format_size(before), after = format_size(after))
+                    }
+                    Ok(_) => t!("settings-database-compacted"),
+                    Err(e) => t!("settings-compaction-failed", error = e),
+                });
+            }
+            SessionMessage::BroadcastBlock(session_id, block) => {
+                if let Some(journal) = &mut self.journal {
+                    journal.record_block(session_id, &block);
+                }
+            }
+            SessionMessage::Broadcast(session_id, result) => {
+                self.broadcast_pending = self.broadcast_pending.saturating_sub(1);
+                let name = self.available_sessions
+                    .iter()
+                    .find(|s| s.id == session_id)
+                    .map(|s| s.name.clone())
+                    .unwrap_or_else(|| session_id.to_string());
+                match result {
+                    Ok(0) => {}
+                    Ok(code) => self.broadcast_failed.push(t!("broadcast-exit", session = name, code = code.to_string())),
+                    Err(e) => {
+                        tracing::error!("Broadcast to {} failed: {}", name, e);
+                        self.broadcast_failed.push(format!("{} ({})", name, e));
+                    }
+                }
+            }
+            // A later switch replaced this one
+            SessionMessage::Loaded(session_id, _) if self.loading_session != Some(session_id) => {}
+            SessionMessage::Loaded(_, Ok(loaded_session)) => {
+                self.loading_session = None;
+                if self.importing_session.take() == Some(loaded_session.id) {
+                    tracing::info!("Imported session {}", loaded_session.name);
+                    self.show_import_dialog = false;
+                }
+                // Save current session first
+                self.auto_save();
+
+                // Switch to new session
+                self.session = *loaded_session;
+                self.block_manager = BlockManager::new();
+                for block in &self.session.blocks {
+                    self.block_manager.add_block(block.clone());
+                }
+                self.watched_blocks.clear();
+                self.transient_failures.clear();
+                self.retries.clear();
+                self.diagnostic_scans.clear();
+                self.section_scans.clear();
+                self.output_highlights.clear();
+                self.redacted_outputs.clear();
+                self.scheduler.clear();
+                self.command_queue.clear();
+
+                self.load_command_history();
+                self.refresh_git_status(ctx);
+                self.refresh_kube_context();
+                self.refresh_project_runtimes();
+                self.open_workspace(ctx);
+                self.load_available_sessions(ctx);
+                tracing::info!("Switched to session: {}", self.session.name);
+            }
+            SessionMessage::Loaded(session_id, Err(e)) => {
+                self.loading_session = None;
+                if self.importing_session.take() == Some(session_id) {
+                    self.import_status = Some(t!("import-failed", error = e));
+                } else {
+                    tracing::error!("Failed to load session: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Most recent distinct `ssh <alias> ...` commands from history, newest first
+    fn recent_ssh_commands(&self, alias: &str, limit: usize) -> Vec<String> {
+        let prefix = format!("ssh {}", alias);
+        let mut commands: Vec<String> = Vec::new();
+        for command in self.command_history.entries().iter().rev() {
+            let is_for_host = command == &prefix
+                || command.strip_prefix(&prefix).map(|rest| rest.starts_with(' ')).unwrap_or(false);
+            if is_for_host && !commands.contains(command) {
+                commands.push(command.clone());
+                if commands.len() == limit {
+                    break;
+                }
+            }
+        }
+        commands
+    }
+
+    /// Run the session's automatic setup, or hold it for a trust decision
+    /// when the directory hasn't been trusted
+    fn open_workspace(&mut self, ctx: &Context) {
+        let working_dir = self.session.working_directory.clone();
+        self.untrusted_workspace = None;
+        if self.config.general.trusts(&working_dir) || self.workspace_setup().is_empty() {
+            self.run_workspace_setup(ctx);
+            return;
+        }
+        tracing::info!("Holding automatic setup for untrusted {}", working_dir.display());
+        self.startup_queue.clear();
+        self.direnv_environment.clear();
+        self.direnv_blocked = None;
+        self.direnv_receiver = None;
+        self.direnv_dir = Some(working_dir.clone());
+        self.untrusted_workspace = Some(working_dir);
+    }
+
+    fn run_workspace_setup(&mut self, ctx: &Context) {
+        self.reload_env_file();
+        self.refresh_direnv(ctx);
+        self.queue_startup_commands();
+    }
+
+    /// What opening the session runs unasked, one line each
+    fn workspace_setup(&self) -> Vec<String> {
+        let mut setup: Vec<String> = self.session.startup_commands
+            .iter()
+            .filter(|c| !c.trim().is_empty())
+            .map(|c| format!("▶ {}", c))
+            .collect();
+        if let Some(path) = &self.session.env_file {
+            setup.push(t!("trust-load-env", path = abbreviate_home(path)));
+        }
+        if self.config.general.direnv {
+            if let Some(envrc) = direnv::find_envrc(&self.session.working_directory) {
+                setup.push(t!("trust-direnv", path = abbreviate_home(&envrc)));
+            }
+        }
+        setup
+    }
+
+    /// Settle the trust prompt: remember the directory if trusted, and run the
+    /// setup unless it was declined
+    fn decide_workspace_trust(&mut self, trust: bool, run: bool, ctx: &Context) {
+        let Some(dir) = self.untrusted_workspace.take() else {
+            return;
+        };
+        if trust {
+            self.config.general.trusted_directories.push(dir);
+            if let Err(e) = self.config.save() {
+                tracing::error!("Failed to save config: {}", e);
+            }
+        }
+        if run {
+            self.run_workspace_setup(ctx);
+        }
+    }
+
+    /// Schedule the session's startup commands to run as blocks
+    fn queue_startup_commands(&mut self) {
+        self.startup_queue = self.session.startup_commands
+            .iter()
+            .filter(|c| !c.trim().is_empty())
+            .cloned()
+            .collect();
+    }
+
+    /// Start the next queued startup command once nothing else is running
+    fn run_next_startup_command(&mut self, ctx: &Context) {
+        if self.current_block_id.is_some() || self.output_receiver.is_some() {
+            return;
+        }
+
+        if let Some(command) = self.startup_queue.pop_front() {
+            self.execute_shell_command(command, ctx);
+            if let Some(block) = self.block_manager.get_last_block_mut() {
+                block.is_startup = true;
+            }
+        }
+    }
+
+    /// Queue scheduled commands that are due and run queued commands one at a time
+    fn run_scheduled_commands(&mut self, ctx: &Context) {
+        let now = chrono::Local::now().naive_local();
+        for command in self.scheduler.take_due(now) {
+            tracing::info!("Running scheduled command: {}", command);
+            self.command_queue.push_back(command);
+        }
+        if let Some(wait) = self.scheduler.next_due_in(now) {
+            ctx.request_repaint_after(wait);
+        }
+
+        if self.current_block_id.is_some() || self.output_receiver.is_some() {
+            return;
+        }
+        if let Some(command) = self.command_queue.pop_front() {
+            self.execute_shell_command(command, ctx);
+        }
+    }
+
+    /// Validate and persist the triggers being edited. Returns false (leaving
+    /// the dialog open with an error) if any pattern is not a valid regex.
+    fn save_triggers(&mut self) -> bool {
+        let triggers: Vec<Trigger> = self.triggers_draft
+            .iter()
+            .filter(|t| !t.pattern.trim().is_empty())
+            .cloned()
+            .collect();
+        if let Some(error) = triggers.iter().find_map(|t| t.validate().err()) {
+            self.triggers_error = Some(format!("{:#}", error));
+            return false;
+        }
+        self.triggers_error = None;
+        self.session.triggers = triggers.clone();
+
+        if let Some(ref session_manager) = self.session_manager {
+            let session_manager = session_manager.clone();
+            let session_id = self.session.id;
+            if let Err(e) = self.runtime.block_on(async {
+                session_manager.set_triggers(&session_id, &triggers).await
+            }) {
+                tracing::error!("Failed to save triggers: {}", e);
+            }
+        }
+        true
+    }
+
+    fn save_session_details(&mut self) {
+        let description = self.description_draft.trim();
+        self.session.description = (!description.is_empty()).then(|| description.to_string());
+        self.session.color = self.color_draft;
+
+        if let Some(ref session_manager) = self.session_manager {
+            let session_manager = session_manager.clone();
+            let session_id = self.session.id;
+            let description = self.session.description.clone();
+            let color = self.session.color;
+            if let Err(e) = self.runtime.block_on(async {
+                session_manager.set_session_details(&session_id, description.as_deref(), color).await
+            }) {
+                tracing::error!("Failed to save session details: {}", e);
+            }
+        }
+        if let Some(info) = self.available_sessions.iter_mut().find(|s| s.id == self.session.id) {
+            info.description = self.session.description.clone();
+            info.color = self.session.color;
+        }
+    }
+
+    fn save_startup_commands(&mut self) {
+        let commands: Vec<String> = self.startup_commands_draft
+            .lines()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty())
+            .collect();
+        self.session.startup_commands = commands.clone();
+
+        if let Some(ref session_manager) = self.session_manager {
+            let session_manager = session_manager.clone();
+            let session_id = self.session.id;
+            if let Err(e) = self.runtime.block_on(async {
+                session_manager.set_startup_commands(&session_id, &commands).await
+            }) {
+                tracing::error!("Failed to save startup commands: {}", e);
+            }
+        }
+    }
+
+    /// Load a .env file into the session environment and persist it
+    fn load_env_file(&mut self, path: PathBuf) {
+        match crate::utils::dotenv::load(&path) {
+            Ok(vars) => {
+                tracing::info!("Loaded {} variables from {}", vars.len(), path.display());
+                self.env_file_modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+                self.session.apply_env_file(path, vars);
+                self.env_file_error = None;
+                self.env_file_changed = false;
+                self.persist_environment();
+            }
+            Err(e) => {
+                tracing::warn!("{:#}", e);
+                self.env_file_error = Some(format!("{:#}", e));
+            }
+        }
+    }
+
+    fn unload_env_file(&mut self) {
+        self.session.clear_env_file();
+        self.env_file_modified = None;
+        self.env_file_changed = false;
+        self.env_file_error = None;
+        self.persist_environment();
+    }
+
+    /// Re-apply the session's .env file so it reflects the file's current contents
+    fn reload_env_file(&mut self) {
+        self.env_file_modified = None;
+        self.env_file_changed = false;
+        if let Some(path) = self.session.env_file.clone() {
+            self.load_env_file(path);
+        }
+    }
+
+    /// Notice edits to the loaded .env file (checked every couple of seconds)
+    fn check_env_file_changed(&mut self) {
+        if self.env_file_changed || self.last_env_file_check.elapsed() < Duration::from_secs(2) {
+            return;
+        }
+        self.last_env_file_check = Instant::now();
+
+        if let Some(ref path) = self.session.env_file {
+            let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+            if modified != self.env_file_modified {
+                self.env_file_changed = true;
+            }
+        }
+    }
+
+    /// Environment injected into commands: direnv exports, overridden by the
+    /// session's own variables
+    fn command_environment(&self) -> HashMap<String, String> {
+        let mut environment = self.direnv_environment.clone();
+        environment.extend(self.session.environment.clone());
+        environment
+    }
+
+    /// Re-evaluate direnv for the session's working directory in the background
+    fn refresh_direnv(&mut self, ctx: &Context) {
+        let working_dir = self.session.working_directory.clone();
+        self.direnv_dir = Some(working_dir.clone());
+        self.direnv_blocked = None;
+
+        if !self.config.general.direnv {
+            self.direnv_environment.clear();
+            return;
+        }
+
+        let ctx_clone = ctx.clone();
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.direnv_receiver = Some(rx);
+
+        self.runtime.spawn(async move {
+            let status = tokio::task::spawn_blocking(move || {
+                direnv::export(&working_dir).unwrap_or_else(|e| {
+                    tracing::warn!("direnv: {:#}", e);
+                    DirenvStatus::NotFound
+                })
+            })
+            .await
+            .unwrap_or(DirenvStatus::NotFound);
+            let _ = tx.send(status);
+            ctx_clone.request_repaint();
+        });
+    }
+
+    /// Trust the blocked .envrc and apply it
+    fn allow_direnv(&mut self, ctx: &Context) {
+        if let Some(envrc) = self.direnv_blocked.take() {
+            match direnv::allow(&envrc) {
+                Ok(()) => self.refresh_direnv(ctx),
+                Err(e) => tracing::error!("{:#}", e),
+            }
+        }
+    }
+
+    fn persist_environment(&mut self) {
+        if let Some(ref session_manager) = self.session_manager {
+            let session_manager = session_manager.clone();
+            let session = self.session.clone();
+            if let Err(e) = self.runtime.block_on(async {
+                session_manager.update_environment(&session).await
+            }) {
+                tracing::error!("Failed to save session environment: {}", e);
+            }
+        }
+    }
+
+    fn create_new_session(&mut self, name: String, ctx: &Context) {
+        let working_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+        // The dialog checks against the list it loaded; the background task
+        // checks again in case it went stale
+        let new_session = Session::new(name, working_dir);
+        self.load_session_in_background(new_session.id, Some(new_session), ctx);
+    }
+
+    /// Add an imported session alongside the existing ones and switch to it.
+    /// It gets fresh ids so importing a session twice never overwrites anything,
+    /// and a numbered name if one of the same name exists.
+    fn import_session(&mut self, mut session: Session, ctx: &Context) {
+        session.id = Uuid::new_v4();
+        for block in &mut session.blocks {
+            block.id = Uuid::new_v4();
+        }
+        self.import_status = None;
+        self.importing_session = Some(session.id);
+        self.load_session_in_background(session.id, Some(session), ctx);
+    }
+
+    fn handle_ai_action(&mut self, action: AiAction, ctx: &Context) {
+        self.poll_ai_engine(true);
+        match action {
+            AiAction::ProviderChanged(provider) => {
+                tracing::info!("AI provider changed to: {}", provider);
+                self.ai_panel.set_selected_provider(provider.clone());
+                // Request model list load
+                self.handle_ai_action(AiAction::LoadModels, ctx);
+            }
+            AiAction::ModelChanged(model) => {
+                tracing::info!("AI model changed to: {}", model);
+                self.ai_panel.set_selected_model(model.clone());
+                self.config.ai.selected_model = Some(model);
+                if let Err(e) = self.config.save() {
+                    tracing::error!("Failed to save config: {}", e);
+                }
+                self.warm_up_model();
+            }
+            AiAction::OverflowChanged(strategy) => {
+                self.config.ai.context_overflow = strategy;
+                if let Err(e) = self.config.save() {
+                    tracing::error!("Failed to save config: {}", e);
+                }
+            }
+            AiAction::LoadModels => {
+                tracing::info!("Loading AI models...");
+                
+                if let Some(engine) = &self.ai_engine {
+                    let provider_name = self.ai_panel.selected_provider().to_string();
+                    
+                    if let Some(provider) = engine.get_provider(&provider_name) {
+                        let provider_clone = provider.clone();
+                        let engine_clone = engine.clone();
+                        let ctx_clone = ctx.clone();
+                        
+                        let ai_request = self.ai_requests.start(AiOrigin::new(AiFeature::ModelList), &provider_name);
+                        
+                        self.runtime.spawn(async move {
+                            let _slot = ai_request.slot().await;
+                            match provider_clone.list_models().await {
+                                Ok(models) => {
+                                    tracing::info!("Loaded {} models from {}", models.len(), provider_name);
+                                    ai_request.answer(AiMessage::ModelsLoaded(models));
+                                    ctx_clone.request_repaint();
+                                }
+                                Err(e) => {
+                                    tracing::error!("Failed to load models: {}", e);
+                                    engine_clone.record_error(&provider_name, &e);
+                                    ai_request.answer(AiMessage::Error(format!("Failed to load models: {}", e)));
+                                    ctx_clone.request_repaint();
+                                }
+                            }
+                        });
+                    }
+                } else {
+                    tracing::warn!("No AI engine available");
+                    // Set some default models for offline use
+                    let models = vec![
+                        "qwen2.5-coder:3b".to_string(),
+                        "qwen2.5-coder:7b".to_string(),
+                    ];
+                    self.ai_panel.set_available_models(models);
+                }
+            }
+            AiAction::SendPrompt(prompt) => {
+                self.send_ai_prompt(prompt, AiOrigin::new(AiFeature::Chat), ctx);
+            }
+        }
+    }
+
+    /// Whether an answer for `feature` goes in the panel's response box: panel
+    /// prompts always do, a diagnosis only while no panel prompt is waiting
+    fn owns_ai_response(&self, feature: AiFeature) -> bool {
+        feature == AiFeature::Chat || !self.ai_requests.is_running(AiFeature::Chat)
+    }
+
+    /// Send a prompt to the AI panel's conversation, tagged with the feature
+    /// that asked; only panel prompts replace an answer still on its way
+    fn send_ai_prompt(&mut self, prompt: String, origin: AiOrigin, ctx: &Context) {
+        self.poll_ai_engine(true);
+        tracing::info!("Sending prompt to AI: {}", prompt);
+        
+        // Add to conversation
+        self.ai_panel.add_user_message(prompt.clone(), origin.feature);
+        if self.owns_ai_response(origin.feature) {
+            self.ai_panel.start_streaming();
+        }
+        
+        // Build context from recent blocks if enabled
+        let blocks: Vec<Block> = self.block_manager.get_blocks()
+            .iter()
+            .cloned()
+            .collect();
+        
+        let context = if self.ai_panel.include_context {
+            build_history_context(&blocks, &prompt, self.ai_panel.context_blocks, self.ai_panel.context_overflow)
+        } else {
+            BuiltContext::plain(prompt.clone())
+        };
+        let overflow_requests = context.overflow_requests(&prompt);
+        let attachments = self.ai_panel.attachment_context();
+        let with_attachments = move |context: String| match &attachments {
+            Some(files) => format!("{}\n\n{}", files, context),
+            None => context,
+        };
+        
+        // Send to AI engine
+        if let Some(engine) = &self.ai_engine {
+            let provider_name = self.ai_panel.selected_provider().to_string();
+            let model = engine.model_for(&provider_name, origin.feature, self.ai_panel.selected_model());
+            
+            if model.is_empty() {
+                tracing::error!("No model selected");
+                self.ai_panel.set_response("Error: No model selected".to_string());
+                return;
+            }
+            
+            let engine_clone = engine.clone();
+            let ctx_clone = ctx.clone();
+            
+            let audit = self.start_ai_audit(
+                AiRequestKind::Chat,
+                &provider_name,
+                &model,
+                &with_attachments(context.context.clone()),
+            );
+            let overflow_requests: Vec<_> = overflow_requests
+                .into_iter()
+                .map(|prompt| {
+                    let audit = self.start_ai_audit(AiRequestKind::Condense, &provider_name, &model, &prompt);
+                    (prompt, audit)
+                })
+                .collect();
+            let ai_request = self.ai_requests.start(origin, &provider_name);
+            
+            self.runtime.spawn(async move {
+                let _slot = ai_request.slot().await;
+
+                // Condense the history that didn't fit, for the strategies that send it
+                let mut answers = Vec::new();
+                for (overflow_prompt, overflow_audit) in overflow_requests {
+                    let request = ChatRequest::new(model.clone()).with_user_message(overflow_prompt);
+                    let started = Instant::now();
+                    let result = engine_clone.chat_completion_with_provider(&provider_name, request).await;
+                    record_ai_audit(overflow_audit, &result, started.elapsed()).await;
+                    match result {
+                        Ok(response) => answers.push(response.content),
+                        Err(e) => tracing::warn!("Could not condense earlier history: {}", e),
+                    }
+                }
+
+                // Create chat request
+                let prompt = with_attachments(context.with_condensed_history(&answers));
+                let audit = audit.map(|(manager, entry)| (manager, entry.with_prompt(&prompt)));
+                let request = ChatRequest::new(model).with_user_message(prompt);
+                let started = Instant::now();
+                let result = engine_clone.chat_completion_with_provider(&provider_name, request).await;
+                record_ai_audit(audit, &result, started.elapsed()).await;
+                match result {
+                    Ok(response) => {
+                        tracing::info!("Received AI response: {} chars", response.content.len());
+                        ai_request.answer(AiMessage::Response(response.content, response.timing));
+                        ctx_clone.request_repaint();
+                    }
+                    Err(e) => {
+                        tracing::error!("AI request failed: {}", e);
+                        ai_request.answer(AiMessage::Error(format!("AI request failed: {}", e)));
+                        ctx_clone.request_repaint();
+                    }
+                }
+            });
+        } else {
+            tracing::warn!("No AI engine available");
+            self.ai_panel.set_response("Error: AI engine not initialized".to_string());
+        }
+        
+        ctx.request_repaint();
+    }
+}
+
+/// Whether a provider runs on this machine; Ollama without a URL talks to localhost
+fn provider_is_local(name: &str, provider: &AiProviderConfig) -> bool {
+    match provider.base_url.as_deref() {
+        Some(url) => is_local_url(url),
+        None => name == "ollama",
+    }
+}
+
+/// Interpretation of command input forced by a prefix
+#[derive(Debug, PartialEq)]
+enum ForcedInput<'a> {
+    Ai(&'a str),
+    Shell(&'a str),
+}
+
+/// Whether `input` starts with the AI or shell prefix, and the rest of it.
+/// The longer prefix wins when one starts with the other.
+fn forced_input<'a>(input: &'a str, ai_prefix: &str, shell_prefix: &str) -> Option<ForcedInput<'a>> {
+    let mut prefixes = [(ai_prefix, true), (shell_prefix, false)];
+    prefixes.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+    prefixes.into_iter().filter(|(prefix, _)| !prefix.is_empty()).find_map(|(prefix, ai)| {
+        let rest = input.strip_prefix(prefix)?.trim();
+        Some(if ai { ForcedInput::Ai(rest) } else { ForcedInput::Shell(rest) })
+    })
+}
+
+/// Placeholder of the command input, mentioning the prefixes that are set
+fn input_hint(ai_prefix: &str, shell_prefix: &str) -> String {
+    let mut hints = Vec::new();
+    if !ai_prefix.is_empty() {
+        hints.push(format!("{}… asks AI", ai_prefix));
+    }
+    if !shell_prefix.is_empty() {
+        hints.push(format!("{}… runs in shell", shell_prefix));
+    }
+    if hints.is_empty() {
+        "Enter a command or natural language request...".to_string()
+    } else {
+        format!("Enter a command or natural language request ({})", hints.join(", "))
+    }
+}
+
+/// Instructions for command generation, asking for up to `candidates`
+/// alternatives when more than one is wanted
+fn command_system_prompt(candidates: usize) -> String {
+    let reply = if candidates > 1 {
+        format!(
+            "Reply ONLY with up to {} alternative shell commands, one per line, best first, \
+             no explanations, no numbering, no markdown, no code blocks. \
+             Only offer alternatives that meaningfully differ, e.g. in the tools they use.",
+            candidates
+        )
+    } else {
+        "Reply ONLY with the shell command, no explanations, no markdown, no code blocks.".to_string()
+    };
+    format!(
+        "You are a helpful shell command generator. Convert natural language requests into valid commands \
+         for the shell and operating system described in the request, using the package manager and tools that system has. \
+         {} If the request is ambiguous, choose the most common interpretation.",
+        reply
+    )
+}
+
+/// How a natural-language request is put to the model, for the request
+/// itself and for the configured examples
+fn command_request(nl_input: &str) -> String {
+    format!("Convert this request to a shell command: {}", nl_input)
+}
+
+/// Command generation request up to the user's prompt: the instructions,
+/// the configured sampling and the configured examples
+fn command_chat_request(ai: &AiConfig, model: String, system_prompt: String) -> ChatRequest {
+    let mut request = ChatRequest::new(model)
+        .with_system_message(system_prompt)
+        .with_temperature(ai.command_sampling_temperature());
+    if let Some(seed) = ai.command_seed {
+        request = request.with_seed(seed);
+    }
+    for example in &ai.command_examples {
+        request = request
+            .with_user_message(command_request(&example.request))
+            .with_assistant_message(example.command.clone());
+    }
+    request
+}
+
+/// Finish an audit entry with the outcome of its request and record it,
+/// returning its id
+async fn record_ai_audit(
+    audit: Option<(SessionManager, AiAuditEntry)>,
+    result: &Result<ChatResponse, AiError>,
+    latency: Duration,
+) -> Option<Uuid> {
+    let (session_manager, entry) = audit?;
+    let entry = match result {
+        Ok(response) => {
+            let tokens = response.usage.as_ref().map(|u| (u.prompt_tokens, u.completion_tokens));
+            entry.succeeded(&response.content, tokens, latency)
+        }
+        Err(e) => entry.failed(e.to_string(), latency),
+    };
+    match session_manager.record_ai_request(&entry).await {
+        Ok(()) => Some(entry.id),
+        Err(e) => {
+            tracing::warn!("Failed to update AI audit log: {:#}", e);
+            None
+        }
+    }
+}
+
+/// Run a broadcast command to completion in a session that isn't open, with
+/// the hooks and the session's triggers, and append the finished block to
+/// it. It is stopped when it outlives the job's timeout or `cancel` changes.
+async fn run_in_session(
+    session_manager: &SessionManager,
+    session_id: Uuid,
+    job: BroadcastJob,
+    mut cancel: tokio::sync::watch::Receiver<u64>,
+    journal: &mpsc::UnboundedSender<SessionMessage>,
+) -> anyhow::Result<i32> {
+    let session = session_manager.load_session(&session_id).await?;
+    let cwd = session.working_directory.clone();
+    let mut executor = ShellExecutor::new(job.shell)?;
+    executor.set_environment(session.environment.clone());
+    if cwd.is_dir() {
+        executor.set_working_directory(cwd.clone());
+    }
+
+    let mut block = Block::new(job.command.clone(), cwd.clone());
+    block.start_execution();
+    let (results, refused) = job.hooks.run_pre(&job.command, &cwd).await;
+    block.metadata.hooks.extend(results);
+    if let Some(reason) = refused {
+        block.append_output(format!("Refused by hook: {}\n", reason));
+        block.cancel_execution();
+        session_manager.append_block(&session_id, &block).await?;
+        anyhow::bail!("refused by hook: {}", reason);
+    }
+    let _ = journal.send(SessionMessage::BroadcastBlock(session_id, Box::new(block.clone())));
+
+    let started = Instant::now();
+    let mut matcher = TriggerMatcher::new(&session.triggers);
+    let (mut rx, input) = executor.execute_with_input(job.command.clone()).await?;
+    let deadline = async {
+        match job.timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(deadline);
+    let mut exit_code = -1;
+    let mut stopped = None;
+    loop {
+        tokio::select! {
+            line = rx.recv() => match line {
+                Some(OutputLine::Stdout(s) | OutputLine::Stderr(s)) => {
+                    let hits = matcher.feed(&s);
+                    block.append_output(s);
+                    for hit in hits {
+                        apply_background_trigger(&mut block, hit);
+                    }
+                }
+                Some(OutputLine::WorkingDirectory(_)) => {}
+                Some(OutputLine::Exit(code)) => {
+                    exit_code = code;
+                    break;
+                }
+                None => break,
+            },
+            _ = &mut deadline => {
+                stopped = Some("timed out");
+                break;
+            }
+            _ = cancel.changed() => {
+                stopped = Some("stopped");
+                break;
+            }
+        }
+    }
+
+    if let Some(reason) = stopped {
+        let _ = input.send(PtyInput::Terminate);
+        block.append_output(format!("\n[Broadcast {}]\n", reason));
+        block.cancel_execution();
+    } else {
+        for hit in matcher.finish() {
+            apply_background_trigger(&mut block, hit);
+        }
+        block.complete_execution(exit_code);
+        block.metadata.hooks.extend(job.hooks.run_post(&job.command, &cwd, exit_code, started.elapsed()).await);
+    }
+    let _ = journal.send(SessionMessage::BroadcastBlock(session_id, Box::new(block.clone())));
+    session_manager.append_block(&session_id, &block).await?;
+    match stopped {
+        Some(reason) => anyhow::bail!("{}", reason),
+        None => Ok(exit_code),
+    }
+}
+
+/// Apply a trigger that fired in a session that isn't open. Its commands
+/// aren't run, since there is no session open to run them in.
+fn apply_background_trigger(block: &mut Block, hit: TriggerHit) {
+    match hit.action {
+        TriggerAction::Notify => {
+            crate::utils::notify::send(&t!("trigger-notification", command = block.command.clone()), &hit.line)
+        }
+        TriggerAction::HighlightLine => block.highlighted_lines.push(hit.line_index),
+        TriggerAction::MarkBlock => block.is_pinned = true,
+        TriggerAction::RunCommand(command) => {
+            tracing::info!("Not running trigger command in a background session: {}", command)
+        }
+    }
+}
+
+/// Display color of the color tag a session was given
+fn session_color(color: SessionColor) -> Color32 {
+    let [r, g, b] = color.rgb();
+    Color32::from_rgb(r, g, b)
+}
+
+/// A calculator mistake in the user's language
+fn calc_error_message(error: &calc::CalcError) -> String {
+    use calc::CalcError;
+    match error {
+        CalcError::Empty => t!("calc-error-empty"),
+        CalcError::InvalidNumber(number) => t!("calc-error-invalid-number", number = number.as_str()),
+        CalcError::Unexpected(token) => t!("calc-error-unexpected", token = token.as_str()),
+        CalcError::Expected { expected, found: None } => t!("calc-error-expected", expected = expected.as_str()),
+        CalcError::Expected { expected, found: Some(found) } => {
+            t!("calc-error-expected-found", expected = expected.as_str(), found = found.as_str())
+        }
+        CalcError::EndsEarly => t!("calc-error-ends-early"),
+        CalcError::UnknownName(name) => t!("calc-error-unknown-name", name = name.as_str()),
+        CalcError::UnknownFunction(name) => t!("calc-error-unknown-function", name = name.as_str()),
+        CalcError::OneArgument(name) => t!("calc-error-one-argument", name = name.as_str()),
+        CalcError::NeedsArguments(name) => t!("calc-error-needs-arguments", name = name.as_str()),
+        CalcError::DivisionByZero => t!("calc-error-division-by-zero"),
+        CalcError::NotFinite => t!("calc-error-not-finite"),
+    }
+}
+
+/// Where crash reports and emergency session dumps go
+fn crash_dir() -> Option<PathBuf> {
+    Config::data_dir().ok().map(|dir| dir.join("crashes"))
+}
+
+/// Where block changes are journaled between saves
+fn journal_dir() -> Option<PathBuf> {
+    Config::data_dir().ok().map(|dir| dir.join("journal"))
+}
+
+/// Journal for this run. Off when history is encrypted, since the journal
+/// would keep commands and output on disk in plain text.
+fn open_journal(storage: &StorageConfig) -> Option<Journal> {
+    if storage.encrypt_history {
+        return None;
+    }
+    match Journal::open(&journal_dir()?) {
+        Ok(journal) => Some(journal),
+        Err(e) => {
+            tracing::warn!("Crash recovery journal unavailable: {:#}", e);
+            None
+        }
+    }
+}
+
+/// The session database, in the working directory
+fn database_path() -> PathBuf {
+    PathBuf::from("immaterium.db")
+}
+
+/// The configured Rhai script, and a status line if it failed to load
+fn load_scripts(config: &ScriptingConfig) -> (Option<ScriptEngine>, Option<String>) {
+    if !config.enabled {
+        return (None, None);
+    }
+    let path = match &config.path {
+        Some(path) => Some(crate::utils::expand_tilde(path)),
+        None => ScriptEngine::default_path(),
+    };
+    let Some(path) = path else {
+        return (None, None);
+    };
+    match ScriptEngine::load(path) {
+        Ok(scripts) => (Some(scripts), None),
+        Err(e) => {
+            tracing::warn!("Failed to load script: {}", e);
+            (None, Some(format!("📜 {}", e)))
+        }
+    }
+}
+
+fn script_summary(scripts: &ScriptEngine) -> String {
+    let hooks = scripts.hooks();
+    if hooks.is_empty() {
+        format!("📜 No hooks in {}", abbreviate_home(scripts.path()))
+    } else {
+        format!("📜 Hooks: {}", hooks.join(", "))
+    }
+}
+
+/// Where database backups go
+fn backup_dir() -> Option<PathBuf> {
+    Config::data_dir().ok().map(|dir| dir.join("backups"))
+}
+
+/// Monthly archive file for blocks removed by retention, if archiving is on
+fn retention_archive(storage: &StorageConfig) -> Option<PathBuf> {
+    if !storage.archive_removed_blocks {
+        return None;
+    }
+    let month = chrono::Local::now().format("%Y-%m");
+    Config::data_dir()
+        .ok()
+        .map(|dir| dir.join("archive").join(format!("blocks-{}.jsonl", month)))
+}
+
+pub(super) fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Hand output to the terminal view while a full-screen program runs,
+/// returning the part that belongs in the block
+fn route_terminal_output(
+    view: &mut Option<TerminalView>,
+    pty_input: Option<&std::sync::mpsc::Sender<PtyInput>>,
+    block_id: Uuid,
+    text: String,
+) -> String {
+    if let Some(terminal) = view {
+        terminal.process(&text);
+        if !terminal.is_active() {
+            *view = None;
+        }
+        return String::new();
+    }
+
+    let (Some(start), Some(input)) = (terminal_view::find_alternate_screen_enter(&text), pty_input) else {
+        return text;
+    };
+    let mut terminal = TerminalView::new(block_id, input.clone());
+    terminal.process(&text[start..]);
+    if terminal.is_active() {
+        *view = Some(terminal);
+    }
+    text[..start].to_string()
+}
+
+enum OutputMessage {
+    Input(std::sync::mpsc::Sender<PtyInput>),
+    Output(String),
+    Trigger(TriggerHit),
+    WorkingDirectory(PathBuf),
+    Hooks(Vec<HookResult>),
+    /// A pre hook refused the command, which did not run
+    Refused(String),
+    Exit(i32),
+}
+
+/// Progress of the background refresh of the session list
+#[derive(Default)]
+struct SessionListState {
+    loading: bool,
+    /// Why the last refresh failed; the previous list stays shown
+    error: Option<String>,
+}
+
+impl SessionListState {
+    fn start(&mut self) {
+        self.loading = true;
+    }
+
+    /// Settle a refresh, returning the sessions to show if it succeeded
+    fn finish(&mut self, result: Result<Vec<SessionInfo>, String>) -> Option<Vec<SessionInfo>> {
+        self.loading = false;
+        match result {
+            Ok(sessions) => {
+                self.error = None;
+                Some(sessions)
+            }
+            Err(e) => {
+                tracing::error!("Failed to list sessions: {}", e);
+                self.error = Some(e);
+                None
+            }
+        }
+    }
+}
+
+/// Result of session database work done in the background
+enum SessionMessage {
+    Listed(Result<Vec<SessionInfo>, String>),
+    Loaded(Uuid, Result<Box<Session>, String>),
+    DatabaseSize(Option<u64>),
+    /// Retention was applied while this session was open
+    Retained(Uuid, Result<Retained, String>),
+    /// The database was compacted, with its size before and after
+    Compacted(Result<(Option<u64>, Option<u64>), String>),
+    /// A backup was written to this path
+    BackedUp(Result<PathBuf, String>),
+    Restored(Box<RestoreOutcome>),
+    /// All data was exported: how many sessions, and where to
+    Exported(Result<(usize, PathBuf), String>),
+    Imported(Result<Box<DataImport>, String>),
+    /// A broadcast command finished in another session, with its exit code
+    Broadcast(Uuid, Result<i32, String>),
+    /// A broadcast command's block in another session, to journal
+    BroadcastBlock(Uuid, Box<Block>),
+}
+
+/// Outcome of applying retention from the settings dialog
+struct Retained {
+    removed: usize,
+    /// What is left of the open session
+    blocks: Vec<Block>,
+    size: Option<u64>,
+}
+
+/// Outcome of restoring a backup: whether the swap worked, and the database
+/// reopened afterwards with its active session (None when the open session
+/// was added to it instead)
+struct RestoreOutcome {
+    backup_path: PathBuf,
+    restored: Result<(), String>,
+    reopened: Result<(SessionManager, Option<Uuid>), String>,
+}
+
+/// A data archive restored into the database, with the settings to load
+/// (if asked for) and the sessions now stored
+struct DataImport {
+    report: RestoreReport,
+    settings: Option<String>,
+    sessions: Vec<SessionInfo>,
+}
+
+/// A typed command to run in other sessions
+#[derive(Clone)]
+struct BroadcastJob {
+    shell: String,
+    command: String,
+    hooks: HookRunner,
+    /// Stop the command if it is still running after this long
+    timeout: Option<Duration>,
+}
+
+/// Finished user-defined block action and its stdout
+struct BlockActionReply {
+    action: BlockActionConfig,
+    cwd: PathBuf,
+    output: Result<String, String>,
+}
+
+/// Background results for the git panel
+enum GitPanelUpdate {
+    /// Repository root and its changes, or `None` outside a repository
+    Changes(Option<(PathBuf, Vec<FileChange>)>),
+    /// Path, whether staged, and its diff
+    Diff(String, bool, String),
+}
+
+/// Reply from a plugin process, tagged with what asked for it
+enum PluginReply {
+    Action(Result<PluginResponse, String>),
+    Panel(String, Result<PluginResponse, String>),
+    Render(Uuid, Result<PluginResponse, String>),
+}
+
+/// A plugin side panel and its latest contents
+struct PluginPanelView {
+    plugin: Plugin,
+    panel: PluginPanel,
+    text: Option<String>,
+    error: Option<String>,
+}
+
+/// Automatic retry of a block that failed with a transient error
+struct ScheduledRetry {
+    attempt: u32,
+    /// When the retry runs; None while it is running
+    due: Option<Instant>,
+}
+
+/// A block or session export waiting to be reviewed and shared
+struct ShareDraft {
+    title: String,
+    file_name: String,
+    content: String,
+    redactions: usize,
+    target: usize,
+    status: Option<String>,
+}
+
+enum AiMessage {
+    Response(String, Option<ResponseTiming>),
+    StreamChunk(String),
+    Error(String),
+    ModelsLoaded(Vec<String>),
+    CommandGenerated(String, Vec<String>, AiProvenance, Option<Uuid>), // The natural-language request, the shell commands generated for it (best first), where they came from, and their audit entry
+    Explanation(Uuid, String, CommandExplanation), // The suggestion's block, the explained command and what it does
+}
+
+impl eframe::App for ImmateriumApp {
+    fn update(&mut self, ctx: &Context, frame: &mut eframe::Frame) {
+        // A panic here ends the app; save the session on the way out
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| self.update_frame(ctx, frame)));
+        if let Err(panic) = result {
+            self.emergency_save();
+            std::panic::resume_unwind(panic);
+        }
+    }
+
+    fn raw_input_hook(&mut self, ctx: &Context, raw_input: &mut egui::RawInput) {
+        // The terminal view would pass the copy on to its program as Ctrl+C
+        let target = if self.terminal_view.is_some() {
+            CopyOnSelect::Off
+        } else {
+            self.config.general.copy_on_select
+        };
+        self.selection_copy.prepare_input(ctx, raw_input, target);
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        // Save window state
+        if let Ok(config_json) = serde_json::to_string(&self.config) {
+            storage.set_string("config", config_json);
+        }
+        if self.config.window != self.saved_window {
+            match self.config.save() {
+                Ok(()) => self.saved_window = self.config.window.clone(),
+                Err(e) => tracing::warn!("Failed to save window layout: {}", e),
+            }
+        }
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.shut_down();
+    }
+}
+
+impl ImmateriumApp {
+    fn update_frame(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
+        let frame_start = Instant::now();
+        if ctx.input(|i| i.key_pressed(egui::Key::F12)) {
+            self.show_debug_overlay = !self.show_debug_overlay;
+        }
+        self.dispatch_shortcuts(ctx);
+        self.selection_copy.update(ctx, self.config.general.copy_on_select);
+        self.handle_middle_click_paste(ctx);
+        if self.show_debug_overlay {
+            self.collect_debug_stats();
+        }
+
+        // Auto-save session periodically
+        self.auto_save();
+        self.handle_dropped_files(ctx);
+        self.handle_global_hotkey(ctx);
+        self.track_window_layout(ctx);
+        
+        // Poll output receiver for new output
+        let mut should_clear_receiver = false;
+        let mut trigger_hits = Vec::new();
+        let mut new_working_directory = None;
+        let mut finished_block = None;
+        let mut started_broadcast = None;
+        if let Some(rx) = &mut self.output_receiver {
+            while let Ok(msg) = rx.try_recv() {
+                match msg {
+                    OutputMessage::Input(input) => {
+                        self.pty_input = Some(input);
+                        if self.broadcast_after_start.is_some() && self.broadcast_after_start == self.current_block_id {
+                            started_broadcast = self.broadcast_after_start.take();
+                        }
+                    }
+                    OutputMessage::Output(text) => {
+                        if let Some(block_id) = self.current_block_id {
+                            // Full-screen programs render in the terminal view, not the block
+                            let text = route_terminal_output(&mut self.terminal_view, self.pty_input.as_ref(), block_id, text);
+                            if let Some(block) = self.block_manager.get_block_mut(&block_id) {
+                                if !text.is_empty() {
+                                    if let Some(journal) = &mut self.journal {
+                                        journal.record_output(self.session.id, block_id, &text);
+                                    }
+                                    block.append_output(text);
+                                    // Coalesced with later output; see auto_save
+                                    self.output_unsaved_since.get_or_insert_with(Instant::now);
+                                }
+                            }
+                        }
+                    }
+                    OutputMessage::Trigger(hit) => {
+                        if let Some(block_id) = self.current_block_id {
+                            trigger_hits.push((block_id, hit));
+                        }
+                    }
+                    OutputMessage::WorkingDirectory(path) => new_working_directory = Some(path),
+                    OutputMessage::Hooks(results) => {
+                        if let Some(block) = self.current_block_id.and_then(|id| self.block_manager.get_block_mut(&id)) {
+                            block.metadata.hooks.extend(results);
+                        }
+                    }
+                    OutputMessage::Refused(reason) => {
+                        self.broadcast_after_start = None;
+                        if let Some(block) = self.current_block_id.and_then(|id| self.block_manager.get_block_mut(&id)) {
+                            block.append_output(format!("Refused by hook: {}\n", reason));
+                            block.cancel_execution();
+                            self.save_needed = true;
+                            if let Some(journal) = &mut self.journal {
+                                journal.record_block(self.session.id, block);
+                            }
+                        }
+                        self.current_block_id = None;
+                        self.pty_input = None;
+                        should_clear_receiver = true;
+                    }
+                    OutputMessage::Exit(code) => {
+                        if let Some(block_id) = self.current_block_id {
+                            if let Some(block) = self.block_manager.get_block_mut(&block_id) {
+                                block.complete_execution(code);
+                                self.save_needed = true; // Save when command completes
+                                if let Some(journal) = &mut self.journal {
+                                    journal.record_block(self.session.id, block);
+                                }
+                            }
+                            finished_block = Some(block_id);
+                        }
+                        self.current_block_id = None;
+                        self.pty_input = None;
+                        self.terminal_view = None;
+                        self.broadcast_after_start = None;
+                        should_clear_receiver = true;
+                    }
+                }
+            }
+        }
+        if let Some(command) = started_broadcast.and_then(|id| self.block_manager.get_block(&id)).map(|b| b.command.clone()) {
+            self.broadcast_command(&command, ctx);
+        }
+        if let Some(journal) = &mut self.journal {
+            journal.flush();
+        }
+        for (block_id, hit) in trigger_hits {
+            self.handle_trigger_hit(block_id, hit);
+        }
+        if let Some(path) = new_working_directory {
+            self.set_working_directory(path);
+        }
+        if let Some(block_id) = finished_block {
+            self.note_transient_failure(block_id);
+            self.file_browser.refresh();
+            self.run_completion_hooks(block_id);
+            self.render_with_plugin(block_id, ctx);
+            self.refresh_plugin_panel(ctx);
+        }
+        if should_clear_receiver {
+            self.output_receiver = None;
+            self.refresh_git_status(ctx);
+            self.refresh_kube_context();
+            self.refresh_project_runtimes();
+        }
+
+        while let Ok(update) = self.git_panel_receiver.try_recv() {
+            match update {
+                GitPanelUpdate::Changes(status) => self.git_panel.set_changes(status),
+                GitPanelUpdate::Diff(path, staged, diff) => {
+                    self.git_panel.set_diff(&path, staged, &diff, self.config.appearance.font_size)
+                }
+            }
+        }
+        while let Ok((block_id, command, findings)) = self.findings_receiver.try_recv() {
+            if let Some(entry) = self.command_findings.get_mut(&(block_id, command)) {
+                *entry = Some(findings);
+            }
+        }
+        while let Ok((command, findings)) = self.typed_lint_receiver.try_recv() {
+            self.finish_typed_lint(command, findings, ctx);
+        }
+        while let Ok(reply) = self.plugin_receiver.try_recv() {
+            self.handle_plugin_reply(reply, ctx);
+        }
+        while let Ok(reply) = self.action_receiver.try_recv() {
+            self.handle_block_action_reply(reply, ctx);
+        }
+        while let Ok((name, bytes)) = self.attach_receiver.try_recv() {
+            self.finish_attachment(name, bytes);
+        }
+        while let Ok(message) = self.session_receiver.try_recv() {
+            self.handle_session_message(message, ctx);
+        }
+
+        self.poll_ai_engine(false);
+        self.refresh_ai_health(ctx);
+
+        // Poll share upload; the link goes to the clipboard
+        if let Some(rx) = &mut self.share_receiver {
+            if let Ok(result) = rx.try_recv() {
+                self.share_receiver = None;
+                let status = match result {
+                    Ok(url) => {
+                        ctx.output_mut(|o| o.copied_text = url.clone());
+                        t!("share-link-copied", url = url)
+                    }
+                    Err(e) => t!("share-failed", error = e),
+                };
+                if let Some(draft) = &mut self.share_draft {
+                    draft.status = Some(status);
+                }
+            }
+        }
+
+        // Poll session sync, and start one when the interval has passed
+        if let Some(rx) = &mut self.sync_receiver {
+            if let Ok(result) = rx.try_recv() {
+                self.sync_receiver = None;
+                self.finish_sync(result, ctx);
+            }
+        }
+        let sync_interval = Duration::from_secs(self.config.sync.interval_minutes * 60);
+        if self.config.sync.interval_minutes > 0
+            && self.last_sync.is_none_or(|t| t.elapsed() >= sync_interval)
+        {
+            self.start_sync(ctx);
+        }
+
+        if let Some(rx) = &mut self.bookmark_receiver {
+            if let Ok(bookmarks) = rx.try_recv() {
+                self.directory_bookmarks = bookmarks;
+                self.bookmark_receiver = None;
+            }
+        }
+
+        // Poll git status receiver
+        if let Some(rx) = &mut self.git_receiver {
+            if let Ok(status) = rx.try_recv() {
+                self.git_status = status;
+                self.git_receiver = None;
+            }
+        }
+
+        self.run_next_startup_command(ctx);
+        self.run_due_watches(ctx);
+        self.run_due_retries(ctx);
+        self.run_scheduled_commands(ctx);
+        self.run_due_backup();
+        self.check_env_file_changed();
+
+        // Follow working directory changes with direnv, like a shell hook
+        if self.direnv_dir.as_ref() != Some(&self.session.working_directory) && self.untrusted_workspace.is_none() {
+            self.refresh_direnv(ctx);
+        }
+        if let Some(rx) = &mut self.direnv_receiver {
+            if let Ok(status) = rx.try_recv() {
+                match status {
+                    DirenvStatus::Applied(vars) => {
+                        tracing::info!("direnv: applied {} variables", vars.len());
+                        self.direnv_environment = vars;
+                    }
+                    DirenvStatus::Blocked(envrc) => {
+                        self.direnv_environment.clear();
+                        self.direnv_blocked = Some(envrc);
+                    }
+                    DirenvStatus::NotFound => self.direnv_environment.clear(),
+                }
+                self.direnv_receiver = None;
+            }
+        }
+
+        // Poll shell alias receiver
+        if let Some(rx) = &mut self.alias_receiver {
+            if let Ok(aliases) = rx.try_recv() {
+                self.shell_aliases = aliases;
+                self.alias_receiver = None;
+            }
+        }
+        
+        // Answers from AI requests, routed by what asked for them
+        let mut to_explain = Vec::new();
+        while let Some((origin, msg)) = self.ai_requests.try_recv() {
+            match msg {
+                AiMessage::Response(content, timing) => {
+                    if self.owns_ai_response(origin.feature) {
+                        self.ai_panel.set_response(content.clone());
+                        self.ai_panel.set_response_timing(timing);
+                    }
+                    self.ai_panel.add_assistant_message(content, origin.feature);
+                }
+                AiMessage::StreamChunk(chunk) => {
+                    self.ai_panel.append_response(chunk);
+                }
+                AiMessage::Error(err) => match origin.feature {
+                    AiFeature::Chat | AiFeature::Diagnosis => {
+                        if self.owns_ai_response(origin.feature) {
+                            self.ai_panel.set_response(format!("Error: {}", err));
+                        }
+                        self.ai_panel.add_assistant_message(format!("Error: {}", err), origin.feature);
+                    }
+                    AiFeature::CommandGeneration | AiFeature::FixIt | AiFeature::ModelList => {
+                        self.toasts.push(format!("🤖 {}", err));
+                    }
+                    AiFeature::Explanation => {}
+                },
+                AiMessage::ModelsLoaded(models) => {
+                    self.ai_panel.set_available_models(models);
+                    // Save selected model to config
+                    self.config.ai.selected_model = Some(self.ai_panel.selected_model().to_string());
+                    if let Err(e) = self.config.save() {
+                        tracing::error!("Failed to save config: {}", e);
+                    }
+                }
+                AiMessage::CommandGenerated(request, mut commands, provenance, audit_id) => {
+                    // Create a pending approval block instead of showing modal
+                    let block = Block::new_pending_approval(
+                        request,
+                        commands.first().cloned().unwrap_or_default(),
+                        self.session.working_directory.clone(),
+                    )
+                    .with_provenance(provenance);
+                    if commands.len() > 1 {
+                        self.command_candidates.insert(block.id, std::mem::take(&mut commands));
+                    }
+                    to_explain.push((block.id, block.command.clone()));
+                    if let Some(audit_id) = audit_id {
+                        self.ai_audit_commands.insert(block.id, audit_id);
+                    }
+                    self.focus_approval = Some(block.id);
+                    self.block_manager.add_block(block);
+                }
+                AiMessage::Explanation(block_id, command, explanation) => {
+                    // Dropped if the suggestion was dealt with in the meantime
+                    let key = (block_id, command);
+                    if let Some(entry) = self.command_explanations.get_mut(&key) {
+                        *entry = Some(explanation);
+                    }
+                }
+            }
+        }
+        for (block_id, command) in to_explain {
+            self.validate_command(block_id, command.clone(), ctx);
+            self.explain_command(block_id, command, ctx);
+        }
+
+        // Notices stay off the window while a block is captured from it
+        match &mut self.block_capture {
+            Some(capture) => {
+                if capture.show(ctx) {
+                    self.block_capture = None;
+                }
+            }
+            None => self.toasts.show(ctx),
+        }
+
+        // Presenting takes the whole window; commands keep running underneath
+        if self.presentation.active {
+            let blocks: Vec<Block> = self
+                .presentation_blocks()
+                .iter()
+                .map(|block| self.redact_for_display(block).into_owned())
+                .collect();
+            self.presentation.show(ctx, &blocks, self.config.appearance.font_size);
+            return;
+        }
+
+        // Top menu bar
+        TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button(t!("menu-file"), |ui| {
+                    if ui.button(t!("menu-new-session")).clicked() {
+                        self.load_available_sessions(ctx);
+                        self.show_new_session_dialog = true;
+                        ui.close_menu();
+                    }
+                    if ui.button(t!("menu-open-session")).clicked() {
+                        self.load_available_sessions(ctx);
+                        self.show_session_list = true;
+                        ui.close_menu();
+                    }
+                    ui.menu_button(t!("menu-recent-sessions"), |ui| {
+                        // Listed newest first; refreshed whenever a session is opened
+                        let recent: Vec<(Uuid, String)> = self
+                            .available_sessions
+                            .iter()
+                            .filter(|s| s.id != self.session.id)
+                            .take(RECENT_SESSIONS)
+                            .map(|s| (s.id, s.name.clone()))
+                            .collect();
+                        if recent.is_empty() {
+                            ui.label(RichText::new(t!("menu-no-recent-sessions")).weak());
+                        }
+                        for (session_id, name) in recent {
+                            if ui.button(name).clicked() {
+                                self.switch_to_session(session_id, ctx);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    if ui.button(t!("menu-save-session")).clicked() {
+                        self.save_needed = true;
+                        self.auto_save();
+                        ui.close_menu();
+                    }
+                    if ui.button(t!("menu-session-settings")).clicked() {
+                        self.description_draft = self.session.description.clone().unwrap_or_default();
+                        self.color_draft = self.session.color;
+                        self.startup_commands_draft = self.session.startup_commands.join("\n");
+                        self.triggers_draft = self.session.triggers.clone();
+                        self.triggers_error = None;
+                        self.env_file_draft = self.session.env_file
+                            .as_ref()
+                            .map(|p| p.display().to_string())
+                            .unwrap_or_default();
+                        self.reveal_env_values = false;
+                        self.show_session_settings = true;
+                        ui.close_menu();
+                    }
+                    if ui.button(t!("menu-broadcast")).clicked() {
+                        self.load_available_sessions(ctx);
+                        self.show_broadcast = true;
+                        ui.close_menu();
+                    }
+                    if ui.button(t!("menu-ssh-hosts")).clicked() {
+                        self.ssh_hosts = ssh_config::load_user_hosts();
+                        self.ssh_query.clear();
+                        self.show_ssh_picker = true;
+                        ui.close_menu();
+                    }
+                    let jump = egui::Button::new(t!("menu-quick-jump")).shortcut_text(&self.config.keybindings.quick_jump);
+                    if ui.add(jump).clicked() {
+                        self.open_quick_jump(ctx);
+                        ui.close_menu();
+                    }
+                    if ui.button(t!("menu-workflows")).clicked() {
+                        self.workflow_store = WorkflowStore::open_default();
+                        self.workflow_query.clear();
+                        self.workflow_import_status = None;
+                        self.show_workflows = true;
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button(t!("menu-share-session")).clicked() {
+                        self.open_share_dialog(None);
+                        ui.close_menu();
+                    }
+                    if ui.button(t!("menu-export")).clicked() {
+                        self.show_export_dialog = true;
+                        ui.close_menu();
+                    }
+                    if ui.button(t!("menu-import-session")).clicked() {
+                        self.import_status = None;
+                        self.show_import_dialog = true;
+                        ui.close_menu();
+                    }
+                    if ui.button(t!("menu-restore-backup")).clicked() {
+                        self.refresh_backups();
+                        self.restore_candidate = None;
+                        self.backup_status = None;
+                        self.show_restore_dialog = true;
+                        ui.close_menu();
+                    }
+                    if ui.button(t!("menu-data-archive")).clicked() {
+                        if self.archive_path.is_empty() {
+                            self.archive_path = format!("~/immaterium-{}.archive", chrono::Local::now().format("%Y%m%d"));
+                        }
+                        self.archive_status = None;
+                        self.show_data_archive = true;
+                        ui.close_menu();
+                    }
+                    let can_sync = self.config.sync.backend.is_some() && self.sync_receiver.is_none() && !self.sync_blocked_offline();
+                    let sync_hint = if self.sync_blocked_offline() { t!("menu-sync-offline") } else { t!("menu-sync-unconfigured") };
+                    if ui.add_enabled(can_sync, egui::Button::new(t!("menu-sync-sessions")))
+                        .on_disabled_hover_text(sync_hint)
+                        .clicked()
+                    {
+                        self.start_sync(ctx);
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button(t!("menu-settings")).clicked() {
+                        self.storage_status = None;
+                        self.refresh_database_size(ctx);
+                        self.show_settings = true;
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button(t!("menu-quit")).clicked() {
+                        ctx.send_viewport_cmd(ViewportCommand::Close);
+                    }
+                });
+
+                ui.menu_button(t!("menu-edit"), |ui| {
+                    if ui.button(t!("menu-copy")).clicked() {
+                        ui.close_menu();
+                    }
+                    if ui.button(t!("menu-paste")).clicked() {
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button(t!("menu-insert-divider")).clicked() {
+                        self.divider_title.clear();
+                        self.show_divider_dialog = true;
+                        ui.close_menu();
+                    }
+                    if ui.button(t!("menu-clear-blocks")).clicked() {
+                        self.confirm_clear_blocks = true;
+                        ui.close_menu();
+                    }
+                });
+
+                ui.menu_button(t!("menu-view"), |ui| {
+                    if ui.button(t!("menu-change-theme")).clicked() {
+                        self.show_theme_selector = true;
+                        ui.close_menu();
+                    }
+                    if ui.button(t!("menu-scheduled-commands")).clicked() {
+                        self.show_scheduler = true;
+                        ui.close_menu();
+                    }
+                    let history = egui::Button::new(t!("menu-history")).shortcut_text(&self.config.keybindings.history);
+                    if ui.add(history).clicked() {
+                        self.history_view.open = true;
+                        ui.close_menu();
+                    }
+                    if ui.checkbox(&mut self.show_debug_overlay, t!("menu-debug-overlay")).clicked() {
+                        ui.close_menu();
+                    }
+                    if ui.checkbox(&mut self.config.appearance.file_browser, t!("menu-file-browser")).clicked() {
+                        if let Err(e) = self.config.save() {
+                            tracing::error!("Failed to save config: {}", e);
+                        }
+                        ui.close_menu();
+                    }
+                    if ui.button(t!("menu-presentation")).clicked() {
+                        self.start_presentation();
+                        ui.close_menu();
+                    }
+                    if ui.checkbox(&mut self.config.general.offline, t!("menu-offline"))
+                        .on_hover_text(t!("menu-offline-hint"))
+                        .clicked()
+                    {
+                        if let Err(e) = self.config.save() {
+                            tracing::error!("Failed to save config: {}", e);
+                        }
+                        self.apply_offline_mode(ctx);
+                        ui.close_menu();
+                    }
+                    if ui.checkbox(&mut self.config.redaction.enabled, t!("menu-redact-secrets"))
+                        .on_hover_text(t!("menu-redact-secrets-hint"))
+                        .clicked()
+                    {
+                        if let Err(e) = self.config.save() {
+                            tracing::error!("Failed to save config: {}", e);
+                        }
+                        self.update_redactor();
+                        ui.close_menu();
+                    }
+                    if ui.checkbox(&mut self.config.appearance.git_panel, t!("menu-git-panel")).clicked() {
+                        if let Err(e) = self.config.save() {
+                            tracing::error!("Failed to save config: {}", e);
+                        }
+                        self.refresh_git_panel(ctx);
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button(t!("menu-split-horizontal")).clicked() {
+                        tracing::info!("Split horizontal clicked");
+                        ui.close_menu();
+                    }
+                    if ui.button(t!("menu-split-vertical")).clicked() {
+                        tracing::info!("Split vertical clicked");
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button(t!("menu-zoom-in")).clicked() {
+                        ui.close_menu();
+                    }
+                    if ui.button(t!("menu-zoom-out")).clicked() {
+                        ui.close_menu();
+                    }
+                });
+
+                ui.menu_button(t!("menu-ai"), |ui| {
+                    if ui.button(t!("menu-toggle-ai-panel")).clicked() {
+                        self.ai_panel.toggle_sidebar();
+                        ui.close_menu();
+                    }
+                    if ui.button(t!("menu-ai-diagnostics")).clicked() {
+                        self.show_ai_diagnostics = true;
+                        ui.close_menu();
+                    }
+                    if ui.button(t!("menu-ai-audit")).clicked() {
+                        self.ai_audit_view.open();
+                        self.load_ai_audit(ctx);
+                        ui.close_menu();
+                    }
+                    
+                    ui.separator();
+                    ui.label(t!("menu-operation-mode"));
+                    
+                    use crate::config::OperationMode;
+                    
+                    if ui.selectable_label(
+                        self.config.ai.operation_mode == OperationMode::TerminalOnly,
+                        t!("menu-mode-terminal")
+                    ).on_hover_text(t!("menu-mode-terminal-hint")).clicked() {
+                        self.set_operation_mode(OperationMode::TerminalOnly);
+                        ui.close_menu();
+                    }
+                    
+                    if ui.selectable_label(
+                        self.config.ai.operation_mode == OperationMode::AiPromptOnly,
+                        t!("menu-mode-ai")
+                    ).on_hover_text(t!("menu-mode-ai-hint")).clicked() {
+                        self.set_operation_mode(OperationMode::AiPromptOnly);
+                        ui.close_menu();
+                    }
+                    
+                    if ui.selectable_label(
+                        self.config.ai.operation_mode == OperationMode::Hybrid,
+                        t!("menu-mode-hybrid")
+                    ).on_hover_text(t!("menu-mode-hybrid-hint")).clicked() {
+                        self.set_operation_mode(OperationMode::Hybrid);
+                        ui.close_menu();
+                    }
+                    
+                    ui.separator();
+                    
+                    ui.label(t!("menu-provider"));
+                    for (name, provider) in &self.config.ai.providers {
+                        if provider.enabled {
+                            let is_selected = name == &self.config.ai.default_provider;
+                            if ui.selectable_label(is_selected, name).clicked() {
+                                tracing::info!("Switched AI provider to: {}", name);
+                                ui.close_menu();
+                            }
+                        }
+                    }
+                });
+
+                ui.menu_button(t!("menu-plugins"), |ui| {
+                    let panels: Vec<(Plugin, PluginPanel)> = self.plugin_host
+                        .iter()
+                        .flat_map(|host| host.panels())
+                        .map(|(plugin, panel)| (plugin.clone(), panel.clone()))
+                        .collect();
+                    if panels.is_empty() {
+                        ui.label(RichText::new(t!("menu-no-plugin-panels")).weak());
+                    }
+                    for (plugin, panel) in panels {
+                        let open = self.plugin_panel.as_ref().is_some_and(|v| {
+                            v.plugin.name() == plugin.name() && v.panel.id == panel.id
+                        });
+                        if ui.selectable_label(open, &panel.title).on_hover_text(plugin.name()).clicked() {
+                            if open {
+                                self.plugin_panel = None;
+                            } else {
+                                self.plugin_panel = Some(PluginPanelView { plugin, panel, text: None, error: None });
+                                self.refresh_plugin_panel(ctx);
+                            }
+                            ui.close_menu();
+                        }
+                    }
+                    ui.separator();
+                    if ui.button(t!("menu-reload-plugins")).clicked() {
+                        if let Some(host) = &mut self.plugin_host {
+                            host.reload();
+                            self.plugin_status = Some(t!("status-plugins-loaded", count = host.plugins().len()));
+                        }
+                        self.plugin_panel = None;
+                        ui.close_menu();
+                    }
+                    if let Some(host) = &self.plugin_host {
+                        ui.label(RichText::new(abbreviate_home(host.dir())).weak())
+                            .on_hover_text(t!("menu-plugin-dir-hint"));
+                    }
+                    ui.separator();
+                    if ui.add_enabled(self.config.scripting.enabled, egui::Button::new(t!("menu-reload-scripts"))).clicked() {
+                        self.reload_scripts();
+                        ui.close_menu();
+                    }
+                });
+
+                ui.menu_button(t!("menu-help"), |ui| {
+                    if ui.button(t!("menu-documentation")).clicked() {
+                        ui.close_menu();
+                    }
+                    let shortcuts = egui::Button::new(t!("menu-keyboard-shortcuts")).shortcut_text(&self.config.keybindings.shortcuts);
+                    if ui.add(shortcuts).clicked() {
+                        self.shortcuts_query.clear();
+                        self.show_shortcuts = true;
+                        ui.close_menu();
+                    }
+                    if ui.button(t!("menu-logs")).clicked() {
+                        self.log_viewer.open = true;
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button(t!("menu-about")).clicked() {
+                        ui.close_menu();
+                    }
+                });
+            });
+        });
+
+        // Plugin side panel
+        let mut close_plugin_panel = false;
+        let mut refresh_plugin_panel = false;
+        if let Some(view) = &self.plugin_panel {
+            let panel = egui::SidePanel::right("plugin_panel")
+                .resizable(true)
+                .default_width(self.panel_width("plugin_panel", 320.0))
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new(&view.panel.title).strong())
+                            .on_hover_text(view.plugin.name());
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            close_plugin_panel = icon_button(ui, "✕", &t!("button-close-panel")).clicked();
+                            refresh_plugin_panel = icon_button(ui, "⟳", &t!("button-refresh")).clicked();
+                        });
+                    });
+                    ui.separator();
+                    ScrollArea::vertical().show(ui, |ui| {
+                        if let Some(error) = &view.error {
+                            ui.label(RichText::new(error).color(Color32::from_rgb(220, 60, 80)));
+                        } else if let Some(text) = &view.text {
+                            ui.label(RichText::new(text).monospace());
+                        } else {
+                            ui.spinner();
+                        }
+                    });
+                });
+            self.config.window.panel_widths.insert("plugin_panel".to_string(), panel.response.rect.width());
+        }
+        if close_plugin_panel {
+            self.plugin_panel = None;
+        } else if refresh_plugin_panel {
+            self.refresh_plugin_panel(ctx);
+        }
+
+        // File browser side panel
+        if self.config.appearance.file_browser {
+            let font_size = self.config.appearance.font_size;
+            self.file_browser.set_root(&self.session.working_directory);
+            let panel = egui::SidePanel::left("file_browser")
+                .resizable(true)
+                .default_width(self.panel_width("file_browser", 260.0))
+                .show(ctx, |ui| self.file_browser.show(ui, font_size));
+            self.config.window.panel_widths.insert("file_browser".to_string(), panel.response.rect.width());
+            if let Some(action) = panel.inner {
+                self.handle_file_browser_action(action, ctx);
+            }
+        }
+
+        // Git side panel
+        if self.config.appearance.git_panel {
+            let font_size = self.config.appearance.font_size;
+            let panel = egui::SidePanel::left("git_panel")
+                .resizable(true)
+                .default_width(self.panel_width("git_panel", 300.0))
+                .show(ctx, |ui| self.git_panel.show(ui, font_size));
+            self.config.window.panel_widths.insert("git_panel".to_string(), panel.response.rect.width());
+            match panel.inner {
+                Some(GitPanelAction::Refresh) => self.refresh_git_panel(ctx),
+                Some(GitPanelAction::ShowDiff(change, staged)) => self.load_git_diff(change, staged, ctx),
+                Some(GitPanelAction::Insert(command)) => {
+                    self.command_input = command;
+                    ctx.memory_mut(|m| m.request_focus(egui::Id::new("command_input")));
+                }
+                Some(GitPanelAction::Close) => {
+                    self.config.appearance.git_panel = false;
+                    if let Err(e) = self.config.save() {
+                        tracing::error!("Failed to save config: {}", e);
+                    }
+                }
+                None => {}
+            }
+        }
+
+        // Main terminal area
+        CentralPanel::default().show(ctx, |ui| {
+            // Handle right-click anywhere in the panel
+            let panel_response = ui.interact(
                 ui.max_rect(),
                 egui::Id::new("main_panel_interaction"),
                 egui::Sense::click()
@@ -922,13 +5255,77 @@ impl eframe::App for ImmateriumApp {
                 self.context_menu_block = None; // No specific block
                 self.context_menu_pos = ctx.input(|i| i.pointer.hover_pos());
                 self.context_menu_opened_at = Some(Instant::now());
+                self.focus_context_menu = true;
             }
             
             // Blocks area (takes remaining space)
             // Reserve space for AI panel + command input
-            let input_area_height = 100.0;
-            let available_height = ui.available_height() - input_area_height;
+            let expansion_preview = self.expansion_preview();
+            let mut input_area_height = if self.config.prompt.segments.is_empty() { 100.0 } else { 124.0 };
+            if expansion_preview.is_some() {
+                input_area_height += 18.0;
+            }
+            let command_suggestion = self.command_suggestion();
+            if command_suggestion.is_some() {
+                input_area_height += 18.0;
+            }
+            let command_environment = self.command_environment();
+            let runtime_warning =
+                runtime_env::runtime_warning(
+                    &self.command_input,
+                    &self.project_runtimes,
+                    &command_environment,
+                    &self.session.working_directory,
+                );
+            if runtime_warning.is_some() {
+                input_area_height += 18.0;
+            }
+            if self.direnv_blocked.is_some() {
+                input_area_height += 28.0;
+            }
             
+            // Quick filter chips
+            if self.block_manager.count() > 0 {
+                ui.horizontal(|ui| {
+                    for filter in BlockFilter::ALL {
+                        let count = self.block_manager.get_blocks()
+                            .iter()
+                            .filter(|b| filter.matches(b))
+                            .count();
+                        let active = self.block_filters.contains(&filter);
+                        let chip = ui.selectable_label(
+                            active,
+                            RichText::new(format!("{} {}", filter.label(), count))
+                                .size(self.config.appearance.font_size - 3.0),
+                        );
+                        if chip.clicked() {
+                            if active {
+                                self.block_filters.remove(&filter);
+                            } else {
+                                self.block_filters.insert(filter);
+                            }
+                        }
+                    }
+
+                    if !self.block_filters.is_empty() && ui.small_button("✕ Clear").clicked() {
+                        self.block_filters.clear();
+                    }
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let label = if self.config.appearance.log_view { "▤ Blocks" } else { "☰ Log view" };
+                        if ui.small_button(label).on_hover_text("Toggle one-line-per-block view").clicked() {
+                            self.config.appearance.log_view = !self.config.appearance.log_view;
+                            self.expanded_log_rows.clear();
+                            if let Err(e) = self.config.save() {
+                                tracing::error!("Failed to save config: {}", e);
+                            }
+                        }
+                    });
+                });
+                ui.add_space(4.0);
+            }
+
+            let available_height = ui.available_height() - input_area_height;
             ScrollArea::vertical()
                 .id_source("blocks_scroll_area")
                 .auto_shrink([false; 2])
@@ -936,345 +5333,2085 @@ impl eframe::App for ImmateriumApp {
                 .max_height(available_height)
                 .scroll_bar_visibility(egui::scroll_area::ScrollBarVisibility::AlwaysHidden)
                 .show(ui, |ui| {
+                    // A full-screen program owns the view until it leaves the alternate screen
+                    if let Some(terminal) = &mut self.terminal_view {
+                        terminal.show(ui, available_height, self.config.appearance.font_size);
+                        return;
+                    }
+
                     if self.block_manager.count() == 0 {
                         ui.add_space(20.0);
                         ui.label(
-                            RichText::new(format!("~/dev  {}", self.session.working_directory.file_name()
-                                .and_then(|n| n.to_str())
-                                .unwrap_or("")))
+                            RichText::new(format!("~/dev  {}", self.session.working_directory.file_name()
+                                .and_then(|n| n.to_str())
+                                .unwrap_or("")))
+                                .color(Color32::from_rgb(120, 120, 120))
+                                .size(13.0)
+                        );
+                        ui.add_space(8.0);
+                    } else {
+                        // Display blocks matching any active filter (newest at bottom)
+                        let blocks_to_display: Vec<_> = self.block_manager.get_blocks()
+                            .iter()
+                            .filter(|b| {
+                                self.block_filters.is_empty()
+                                    || self.block_filters.iter().any(|f| f.matches(b))
+                            })
+                            .map(|b| b.clone())
+                            .collect();
+
+                        self.debug_stats.blocks_rendered = blocks_to_display.len();
+                        if blocks_to_display.is_empty() {
+                            ui.add_space(20.0);
+                            ui.label(
+                                RichText::new("No blocks match the active filters")
+                                    .color(Color32::from_rgb(120, 120, 120))
+                                    .size(13.0)
+                            );
+                        }
+                        
+                        let viewport_top = ui.clip_rect().top();
+                        let mut sticky_header = None;
+
+                        for block in blocks_to_display {
+                            let shown = self.redact_for_display(&block);
+                            let focus_approval = self.focus_approval == Some(block.id);
+                            if focus_approval {
+                                self.focus_approval = None;
+                            }
+                            if self.config.appearance.highlight_diagnostics {
+                                self.diagnostic_scans.entry(block.id).or_default().update(&block.output);
+                            }
+                            let diagnostics = self
+                                .diagnostic_scans
+                                .get(&block.id)
+                                .filter(|_| self.config.appearance.highlight_diagnostics)
+                                .map_or(&[][..], DiagnosticScan::diagnostics);
+                            if self.config.appearance.output_sections {
+                                self.section_scans.entry(block.id).or_default().update(&block.output);
+                            }
+                            let sections = self
+                                .section_scans
+                                .get(&block.id)
+                                .filter(|_| self.config.appearance.output_sections)
+                                .map_or(&[][..], SectionScan::sections);
+                            if self.config.appearance.highlight_output && !block.is_running() {
+                                self.output_highlights.entry(block.id).or_default().update(&block.command, &block.output);
+                            }
+                            let syntax_colors = self
+                                .output_highlights
+                                .get(&block.id)
+                                .filter(|_| self.config.appearance.highlight_output)
+                                .map_or(&[][..], OutputHighlight::lines);
+                            let widget = BlockWidget::new(&shown, self.config.appearance.font_size)
+                                .watching(self.watched_blocks.get(&block.id).copied())
+                                .rendered(self.plugin_renders.get(&block.id).map(String::as_str))
+                                .candidates(self.command_candidates.get(&block.id).map(Vec::as_slice))
+                                .explanation(
+                                    self.command_explanations
+                                        .get(&(block.id, block.command.clone()))
+                                        .and_then(Option::as_ref),
+                                )
+                                .findings(
+                                    self.command_findings
+                                        .get(&(block.id, block.command.clone()))
+                                        .and_then(Option::as_deref),
+                                )
+                                .focus_approval(focus_approval)
+                                .patterns(self.config.appearance.state_patterns)
+                                .retry(self.retry_status(&block))
+                                .diagnostics(diagnostics, &self.theme_loader.current().colors)
+                                .sections(sections)
+                                .syntax_colors(syntax_colors);
+                            // Pending approvals always show in full so they can be acted on
+                            let as_row = self.config.appearance.log_view
+                                && block.state != crate::core::BlockState::PendingApproval
+                                && !self.expanded_log_rows.contains(&block.id);
+                            let block_response = if as_row {
+                                widget.show_compact(ui)
+                            } else {
+                                widget.show(ui)
+                            };
+
+                            // Block whose header scrolled away while its body is still on screen
+                            if let Some(rect) = block_response.rect.filter(|_| !block.is_divider) {
+                                if rect.top() < viewport_top
+                                    && rect.bottom() > viewport_top + block_response.header_height * 2.0
+                                {
+                                    sticky_header = Some((shown.clone().into_owned(), rect, block_response.header_height));
+                                }
+                            }
+                            
+                            if block_response.selected {
+                                self.block_manager.select_block(block.id);
+                            }
+
+                            if self.scroll_to_block == Some(block.id) {
+                                if let Some(rect) = block_response.rect {
+                                    ui.scroll_to_rect(rect, Some(egui::Align::Center));
+                                }
+                                self.scroll_to_block = None;
+                            }
+                            
+                            if block_response.toggle_collapsed {
+                                if self.config.appearance.log_view {
+                                    // In log view, collapsing returns the block to a single row
+                                    if !self.expanded_log_rows.remove(&block.id) {
+                                        self.expanded_log_rows.insert(block.id);
+                                    }
+                                } else {
+                                    self.block_manager.toggle_block_collapsed(&block.id);
+                                }
+                            }
+                            
+                            if block_response.show_context_menu {
+                                self.context_menu_block = Some(block.id);
+                                self.context_menu_pos = block_response.context_menu_pos
+                                    .or_else(|| ctx.input(|i| i.pointer.hover_pos()));
+                                self.context_menu_opened_at = Some(Instant::now());
+                                self.focus_context_menu = true;
+                            }
+                            
+                            if block_response.stop_watch {
+                                self.watched_blocks.remove(&block.id);
+                            }
+
+                            if block_response.retry {
+                                self.retry_block(block.id, ctx);
+                            }
+                            if block_response.retry_with_backoff {
+                                self.retries.insert(block.id, ScheduledRetry { attempt: 1, due: Some(Instant::now()) });
+                            }
+                            if block_response.cancel_retry {
+                                self.retries.remove(&block.id);
+                            }
+
+                            if block_response.explain_exit_code {
+                                self.ask_ai_about_exit_code(&block, ctx);
+                            }
+
+                            if block_response.remove_block {
+                                self.delete_blocks(vec![block.id]);
+                            }
+
+                            if let Some(command) = &block_response.select_candidate {
+                                if let Some(pending) = self.block_manager.get_block_mut(&block.id) {
+                                    pending.command = command.clone();
+                                }
+                                self.validate_command(block.id, command.clone(), ctx);
+                                self.explain_command(block.id, command.clone(), ctx);
+                            }
+
+                            if block_response.approve_command {
+                                // Execute the AI-suggested command, remembering the request behind it
+                                let command = block.command.clone();
+                                self.block_manager.remove_block(&block.id);
+                                self.record_command_use(&command);
+                                let started = self.execute_shell_command(command, ctx);
+                                // A typed command held back by the linter is broadcast like any typed one
+                                if block.original_input.is_none() && block.metadata.ai_provenance.is_none() {
+                                    self.broadcast_after_start = started;
+                                }
+                                if let Some(executed) = self.block_manager.get_last_block_mut() {
+                                    executed.original_input = block.original_input.clone();
+                                    executed.metadata.ai_provenance = block.metadata.ai_provenance.clone();
+                                    let executed_id = executed.id;
+                                    self.audit_command_executed(block.id, executed_id);
+                                }
+                            }
+                            
+                            if block_response.reject_command {
+                                // Remove the pending block
+                                self.block_manager.remove_block(&block.id);
+                            }
+                            
+                            if block_response.edit_command {
+                                // Put command in input for editing
+                                self.command_input = block.command.clone();
+                                self.block_manager.remove_block(&block.id);
+                            }
+                            
+                            if block_response.regenerate_command {
+                                // Regenerate command from original NL input
+                                if let Some(nl_input) = block.original_input.clone() {
+                                    self.block_manager.remove_block(&block.id);
+                                    self.convert_natural_language_to_command(nl_input, ctx);
+                                }
+                            }
+
+                            // Keyboard focus returns to the input once a suggestion is handled
+                            if block_response.approve_command
+                                || block_response.reject_command
+                                || block_response.edit_command
+                                || block_response.regenerate_command
+                            {
+                                self.ai_audit_commands.remove(&block.id);
+                                self.command_candidates.remove(&block.id);
+                                self.command_explanations.retain(|(id, _), _| *id != block.id);
+                                self.command_findings.retain(|(id, _), _| *id != block.id);
+                                ctx.memory_mut(|m| m.request_focus(egui::Id::new("command_input")));
+                            }
+                            
+                            if as_row {
+                                ui.add_space(2.0);
+                                continue;
+                            }
+
+                            // Thin separator line between blocks
+                            ui.add_space(8.0);
+                            ui.painter().hline(
+                                ui.available_rect_before_wrap().x_range(),
+                                ui.cursor().top(),
+                                egui::Stroke::new(1.0, Color32::from_rgb(50, 50, 50))
+                            );
+                            ui.add_space(8.0);
+                        }
+
+                        // Keep the command of a tall block visible while reading its output
+                        if let Some((block, rect, header_height)) = sticky_header {
+                            BlockWidget::new(&block, self.config.appearance.font_size)
+                                .patterns(self.config.appearance.state_patterns)
+                                .paint_sticky_header(ui, rect, header_height);
+                        }
+                    }
+                });
+            
+            ui.add_space(4.0);
+            
+            // AI Panel (compact mode above command input)
+            let providers = self.usable_providers();
+            
+            let ai_panel = ui.scope(|ui| self.ai_panel.show_compact(ui, &providers));
+            self.ai_panel_rect = Some(ai_panel.response.rect);
+            if let Some(action) = ai_panel.inner {
+                self.handle_ai_action(action, ctx);
+            }
+            
+            ui.add_space(4.0);
+            
+            // Command input area - Warp style (minimal, no heavy border)
+            egui::Frame::none()
+                .fill(egui::Color32::from_rgba_premultiplied(30, 30, 35, 50))
+                .stroke(egui::Stroke::NONE)
+                .inner_margin(egui::Margin {
+                    left: 12.0,
+                    right: 12.0,
+                    top: 10.0,
+                    bottom: 10.0,
+                })
+                .show(ui, |ui| {
+                    // Prompt segments (cwd, git branch, last exit code, ...)
+                    let last_exit_code = self.block_manager.get_blocks()
+                        .iter()
+                        .rev()
+                        .find_map(|b| b.exit_code);
+                    PromptBar::new(
+                        &self.config.prompt.segments,
+                        self.theme_loader.current(),
+                        &self.session.working_directory,
+                        &command_environment,
+                        self.config.appearance.font_size,
+                    )
+                    .git_status(self.git_status.as_ref())
+                    .last_exit_code(last_exit_code)
+                    .runtimes(&self.project_runtimes)
+                    .show(ui);
+                    if self.config.prompt.segments.contains(&crate::config::PromptSegment::Time) {
+                        ctx.request_repaint_after(Duration::from_secs(1));
+                    }
+
+                    // direnv found an .envrc that still needs to be trusted
+                    if let Some(envrc) = self.direnv_blocked.clone() {
+                        ui.horizontal(|ui| {
+                            ui.label(
+                                RichText::new(format!("🔒 direnv: {} is not allowed", abbreviate_home(&envrc)))
+                                    .color(Color32::from_rgb(255, 165, 0))
+                                    .size(self.config.appearance.font_size - 2.0)
+                            );
+                            if ui.small_button("Allow").on_hover_text("Run `direnv allow` and apply it").clicked() {
+                                self.allow_direnv(ctx);
+                            }
+                            if ui.small_button("Ignore").clicked() {
+                                self.direnv_blocked = None;
+                            }
+                        });
+                    }
+
+                    let input_id = egui::Id::new("command_input");
+                    if self.config.general.vim_mode && ctx.memory(|m| m.has_focus(input_id)) {
+                        self.handle_vim_input(ctx, input_id);
+                    }
+
+                    ui.horizontal(|ui| {
+                        if self.config.general.vim_mode {
+                            let color = match self.vim.mode() {
+                                VimMode::Normal => Color32::from_rgb(100, 180, 255),
+                                VimMode::Insert => Color32::from_rgb(80, 200, 120),
+                            };
+                            ui.label(
+                                RichText::new(self.vim.mode().label())
+                                    .color(color)
+                                    .strong()
+                                    .size(self.config.appearance.font_size - 3.0)
+                            );
+                        }
+
+                        self.show_mode_badge(ui);
+
+                        let insert_path = t!("path-picker-hint", key = self.config.keybindings.insert_path.clone());
+                        if icon_button(ui, "📂", &insert_path).clicked() {
+                            self.path_picker.open_at(&self.session.working_directory);
+                        }
+
+                        ui.label(
+                            egui::RichText::new("›")
+                                .color(egui::Color32::from_rgb(100, 180, 255))
+                                .size(self.config.appearance.font_size + 2.0)
+                        );
+                        
+                        ui.add_space(4.0);
+
+                        // Colorize the command live, matching block headers
+                        let font_id = egui::FontId::monospace(self.config.appearance.font_size);
+                        let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+                            let mut job = SyntaxHighlighter::new().command_layout_job(
+                                text,
+                                font_id.clone(),
+                                Color32::from_rgb(220, 220, 220),
+                            );
+                            job.wrap.max_width = wrap_width;
+                            ui.fonts(|f| f.layout_job(job))
+                        };
+
+                        let hint = input_hint(&self.config.ai.ai_prefix, &self.config.ai.shell_prefix);
+                        let response = ui.add(
+                            egui::TextEdit::singleline(&mut self.command_input)
+                                .id(input_id)
+                                .desired_width(f32::INFINITY)
+                                .hint_text(hint)
+                                .font(egui::FontId::monospace(self.config.appearance.font_size))
+                                .layouter(&mut layouter)
+                                // Tab accepts the suggestion instead of moving focus
+                                .lock_focus(command_suggestion.is_some()),
+                        );
+                        
+                        // Handle Enter key
+                        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                            self.execute_command(ctx);
+                            response.request_focus();
+                        }
+                        
+                        // Handle Up/Down arrows for history navigation, like a shell:
+                        // only when the input is empty or already recalling history
+                        if response.has_focus() {
+                            let navigating = self.command_history.is_navigating();
+                            let mut recalled = false;
+                            if ui.input(|i| i.key_pressed(egui::Key::ArrowUp))
+                                && (navigating || self.command_input.is_empty())
+                            {
+                                self.history_previous();
+                                recalled = true;
+                            }
+                            if ui.input(|i| i.key_pressed(egui::Key::ArrowDown)) && navigating {
+                                self.history_next();
+                                recalled = true;
+                            }
+                            if let Some(suggestion) = &command_suggestion {
+                                if ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Tab)) {
+                                    self.command_input = suggestion.command.clone();
+                                    recalled = true;
+                                }
+                            }
+
+                            // Keep the cursor at the end of the recalled command
+                            if recalled {
+                                if let Some(mut state) = egui::TextEdit::load_state(ctx, response.id) {
+                                    let end = egui::text::CCursor::new(self.command_input.chars().count());
+                                    state.cursor.set_char_range(Some(egui::text::CCursorRange::one(end)));
+                                    state.store(ctx, response.id);
+                                }
+                            }
+                        }
+                        
+                        // Show status on the right
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if self.current_block_id.is_some() {
+                                ui.spinner();
+                                ui.label(
+                                    egui::RichText::new("Running...")
+                                        .color(egui::Color32::from_rgb(150, 150, 150))
+                                );
+                            }
+                        });
+                    });
+
+                    // Alias / variable expansion preview
+                    if let Some(expanded) = &expansion_preview {
+                        ui.label(
+                            RichText::new(format!("will run: {}", expanded))
                                 .color(Color32::from_rgb(120, 120, 120))
-                                .size(13.0)
+                                .monospace()
+                                .size(self.config.appearance.font_size - 2.0)
                         );
-                        ui.add_space(8.0);
+                    }
+
+                    // Most used command starting with the input
+                    if let Some(suggestion) = &command_suggestion {
+                        ui.label(
+                            RichText::new(format!("→ {}   (Tab to accept · used {}×)", suggestion.command, suggestion.count))
+                                .color(Color32::from_rgb(120, 120, 120))
+                                .monospace()
+                                .size(self.config.appearance.font_size - 2.0)
+                        );
+                    }
+
+                    // The command needs an environment that isn't active
+                    if let Some(warning) = &runtime_warning {
+                        ui.label(
+                            RichText::new(format!("⚠ {}", warning))
+                                .color(Color32::from_rgb(255, 165, 0))
+                                .size(self.config.appearance.font_size - 2.0)
+                        );
+                    }
+                });
+            
+            // Context menu (popup that closes when clicking away)
+            if self.context_menu_pos.is_some() {
+                if let Some(menu_pos) = self.context_menu_pos {
+                    let popup_id = egui::Id::new("context_menu");
+                    
+                    // Show popup at saved position (not following cursor)
+                    egui::Area::new(popup_id)
+                        .order(egui::Order::Foreground)
+                        .fixed_pos(menu_pos)
+                        .show(ctx, |ui| {
+                        let frame = egui::Frame::popup(ui.style());
+                        let frame_response = frame.show(ui, |ui| {
+                            ui.set_min_width(180.0);
+                            ui.spacing_mut().button_padding = egui::vec2(8.0, 4.0);
+                            
+                            // Show different menu options depending on whether a block is selected
+                            if let Some(block_id) = self.context_menu_block {
+                                // Block-specific menu
+                                let copy_command = ui.button(t!("block-copy-command"));
+                                if std::mem::take(&mut self.focus_context_menu) {
+                                    copy_command.request_focus();
+                                }
+                                if copy_command.clicked() {
+                                    if let Some(cmd) = self.block_manager.copy_block_command(&block_id) {
+                                        ui.output_mut(|o| o.copied_text = self.redact_copy(cmd));
+                                    }
+                                    self.context_menu_block = None;
+                                    self.context_menu_pos = None;
+                                    self.context_menu_opened_at = None;
+                                }
+                                
+                                if ui.button(t!("block-copy-output")).clicked() {
+                                    if let Some(output) = self.block_manager.copy_block_output(&block_id) {
+                                        ui.output_mut(|o| o.copied_text = self.redact_copy(output));
+                                    }
+                                    self.context_menu_block = None;
+                                    self.context_menu_pos = None;
+                                    self.context_menu_opened_at = None;
+                                }
+                                
+                                if ui.button(t!("block-copy-both")).clicked() {
+                                    if let Some(full) = self.block_manager.copy_block_full(&block_id) {
+                                        ui.output_mut(|o| o.copied_text = self.redact_copy(full));
+                                    }
+                                    self.context_menu_block = None;
+                                    self.context_menu_pos = None;
+                                    self.context_menu_opened_at = None;
+                                }
+
+                                if ui.button(t!("block-copy-image")).clicked() {
+                                    if let Some(block) = self.block_manager.get_block(&block_id).cloned() {
+                                        let shown = self.redact_for_display(&block).into_owned();
+                                        let colors = self
+                                            .output_highlights
+                                            .get(&block_id)
+                                            .filter(|_| self.config.appearance.highlight_output)
+                                            .map_or_else(Vec::new, |highlight| highlight.lines().to_vec());
+                                        self.block_capture =
+                                            Some(BlockCapture::new(shown, colors, self.config.appearance.font_size, self.toasts.sender()));
+                                    }
+                                    self.context_menu_block = None;
+                                    self.context_menu_pos = None;
+                                    self.context_menu_opened_at = None;
+                                }
+                                
+                                ui.separator();
+                                
+                                if ui.button(t!("block-edit-rerun")).clicked() {
+                                    if let Some(block) = self.block_manager.get_block(&block_id) {
+                                        self.command_input = block.command.clone();
+                                    }
+                                    self.context_menu_block = None;
+                                    self.context_menu_pos = None;
+                                    self.context_menu_opened_at = None;
+                                }
+                                
+                                let failed = self.block_manager.get_block(&block_id)
+                                    .is_some_and(|b| b.state == crate::core::BlockState::Failed);
+                                if failed && self.ai_engine.is_some() && ui.button(t!("block-fix-with-ai")).clicked() {
+                                    self.fix_failed_command(block_id, ctx);
+                                    self.context_menu_block = None;
+                                    self.context_menu_pos = None;
+                                    self.context_menu_opened_at = None;
+                                }
+
+                                let pinned = self.block_manager.get_block(&block_id)
+                                    .map(|b| b.is_pinned)
+                                    .unwrap_or(false);
+                                if ui.button(if pinned { "📌 Unpin" } else { "📌 Pin" }).clicked() {
+                                    if let Some(block) = self.block_manager.get_block_mut(&block_id) {
+                                        block.toggle_pinned();
+                                        self.save_needed = true;
+                                    }
+                                    self.context_menu_block = None;
+                                    self.context_menu_pos = None;
+                                    self.context_menu_opened_at = None;
+                                }
+
+                                let in_ai_context = self.block_manager.get_block(&block_id)
+                                    .map(|b| b.in_ai_context)
+                                    .unwrap_or(false);
+                                let label = if in_ai_context {
+                                    t!("block-remove-from-ai-context")
+                                } else {
+                                    t!("block-add-to-ai-context")
+                                };
+                                if ui.button(label).clicked() {
+                                    if let Some(block) = self.block_manager.get_block_mut(&block_id) {
+                                        block.toggle_ai_context();
+                                        self.save_needed = true;
+                                    }
+                                    self.context_menu_block = None;
+                                    self.context_menu_pos = None;
+                                    self.context_menu_opened_at = None;
+                                }
+
+                                let can_watch = self.block_manager.get_block(&block_id)
+                                    .map(|b| b.state != crate::core::BlockState::PendingApproval)
+                                    .unwrap_or(false);
+                                if self.watched_blocks.contains_key(&block_id) {
+                                    if ui.button(t!("block-stop-watching")).clicked() {
+                                        self.watched_blocks.remove(&block_id);
+                                        self.context_menu_block = None;
+                                        self.context_menu_pos = None;
+                                        self.context_menu_opened_at = None;
+                                    }
+                                } else if can_watch {
+                                    ui.horizontal(|ui| {
+                                        let clicked = ui.button(t!("block-rerun-every")).clicked();
+                                        ui.add(
+                                            egui::DragValue::new(&mut self.watch_interval_secs)
+                                                .range(1..=3600)
+                                                .suffix(" s"),
+                                        );
+                                        if clicked {
+                                            self.watched_blocks.insert(
+                                                block_id,
+                                                Duration::from_secs(self.watch_interval_secs),
+                                            );
+                                            self.context_menu_block = None;
+                                            self.context_menu_pos = None;
+                                            self.context_menu_opened_at = None;
+                                        }
+                                    });
+                                }
+
+                                let actions: Vec<(Plugin, String, String)> = self.plugin_host
+                                    .iter()
+                                    .flat_map(|host| host.actions())
+                                    .map(|(plugin, action)| (plugin.clone(), action.id.clone(), action.label.clone()))
+                                    .collect();
+                                for (plugin, action, label) in actions {
+                                    if ui.button(format!("🧩 {}", label)).on_hover_text(plugin.name()).clicked() {
+                                        self.run_plugin_action(plugin, action, block_id, ctx);
+                                        self.context_menu_block = None;
+                                        self.context_menu_pos = None;
+                                        self.context_menu_opened_at = None;
+                                    }
+                                }
+
+                                for action in self.config.block_actions.clone() {
+                                    if ui.button(format!("⚡ {}", action.label)).on_hover_text(&action.command).clicked() {
+                                        self.run_block_action(action, block_id, ctx);
+                                        self.context_menu_block = None;
+                                        self.context_menu_pos = None;
+                                        self.context_menu_opened_at = None;
+                                    }
+                                }
+
+                                if ui.button(t!("block-diff-environment")).clicked() {
+                                    self.open_environment_diff(block_id);
+                                    self.context_menu_block = None;
+                                    self.context_menu_pos = None;
+                                    self.context_menu_opened_at = None;
+                                }
+
+                                if ui.button(t!("block-share")).clicked() {
+                                    self.open_share_dialog(Some(block_id));
+                                    self.context_menu_block = None;
+                                    self.context_menu_pos = None;
+                                    self.context_menu_opened_at = None;
+                                }
+
+                                if ui.button(t!("block-delete")).clicked() {
+                                    self.delete_blocks(vec![block_id]);
+                                    self.context_menu_block = None;
+                                    self.context_menu_pos = None;
+                                    self.context_menu_opened_at = None;
+                                }
+                            } else {
+                                // General menu (no block selected)
+                                let open_session = ui.button(t!("context-open-session"));
+                                if std::mem::take(&mut self.focus_context_menu) {
+                                    open_session.request_focus();
+                                }
+                                if open_session.clicked() {
+                                    self.show_session_list = true;
+                                    self.context_menu_pos = None;
+                                    self.context_menu_opened_at = None;
+                                }
+                                
+                                if ui.button(t!("context-change-theme")).clicked() {
+                                    self.show_theme_selector = !self.show_theme_selector;
+                                    self.context_menu_pos = None;
+                                    self.context_menu_opened_at = None;
+                                }
+                                
+                                ui.separator();
+                                
+                                if ui.button(t!("context-export-session")).clicked() {
+                                    self.show_export_dialog = true;
+                                    self.context_menu_pos = None;
+                                    self.context_menu_opened_at = None;
+                                }
+                                
+                                if ui.button(t!("context-clear-blocks")).clicked() {
+                                    self.confirm_clear_blocks = true;
+                                    self.context_menu_pos = None;
+                                    self.context_menu_opened_at = None;
+                                }
+                            }
+                        });
+                        
+                        // Close menu if clicking outside
+                        // Check if menu has been open for at least 100ms to prevent closing on opening click
+                        let menu_open_duration = self.context_menu_opened_at
+                            .map(|t| t.elapsed().as_millis())
+                            .unwrap_or(0);
+                        
+                        if menu_open_duration > 100 {
+                            // Check for any click outside the menu
+                            if ui.input(|i| i.pointer.any_click()) && !frame_response.response.contains_pointer() {
+                                self.context_menu_block = None;
+                                self.context_menu_pos = None;
+                                self.context_menu_opened_at = None;
+                            }
+                            
+                            // Also close on Escape key
+                            if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                                self.context_menu_block = None;
+                                self.context_menu_pos = None;
+                                self.context_menu_opened_at = None;
+                            }
+                        }
+                    });
+                }
+            }
+        });
+
+        // Status bar
+        let mut status_action = None;
+        TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if let Some(color) = self.session.color {
+                    ui.label(RichText::new("●").color(session_color(color)));
+                }
+                let session_label = ui.label(t!("status-session", name = self.session.name.clone()));
+                if let Some(ref description) = self.session.description {
+                    session_label.on_hover_text(description);
+                }
+                if self.loading_session.is_some() {
+                    ui.spinner().on_hover_text("Loading session...");
+                }
+                ui.separator();
+                ui.label(format!("📁 {}", abbreviate_home(&self.session.working_directory)))
+                    .on_hover_text(self.session.working_directory.display().to_string());
+                if !self.direnv_environment.is_empty() {
+                    ui.separator();
+                    let mut keys: Vec<_> = self.direnv_environment.keys().cloned().collect();
+                    keys.sort();
+                    ui.label(format!("▲ direnv ({})", keys.len()))
+                        .on_hover_text(keys.join("\n"));
+                }
+                if let Some(env_file) = self.session.env_file.clone() {
+                    ui.separator();
+                    if self.env_file_changed {
+                        let reapply = ui.button(
+                            RichText::new(t!("status-env-file-changed")).color(Color32::from_rgb(255, 165, 0))
+                        );
+                        if reapply.on_hover_text(t!("status-env-file-reapply")).clicked() {
+                            self.reload_env_file();
+                        }
+                    } else {
+                        ui.label(format!("🔑 .env ({})", self.session.env_file_keys.len()))
+                            .on_hover_text(env_file.display().to_string());
+                    }
+                }
+                let scheduled = self.scheduler.upcoming().len();
+                if scheduled > 0 {
+                    ui.separator();
+                    let next = self.scheduler.upcoming()[0].next_run.format("%H:%M").to_string();
+                    let response = ui.add(
+                        egui::Label::new(format!("⏰ {}", scheduled)).sense(egui::Sense::click())
+                    );
+                    if response.on_hover_text(t!("schedule-next-run", time = next)).clicked() {
+                        self.show_scheduler = true;
+                    }
+                }
+                if !self.broadcast_targets.is_empty() || self.broadcast_pending > 0 {
+                    ui.separator();
+                    let (text, color) = if self.broadcast_pending > 0 {
+                        (t!("broadcast-running", count = self.broadcast_pending), Color32::from_rgb(100, 150, 255))
+                    } else if !self.broadcast_failed.is_empty() {
+                        (t!("broadcast-failed", count = self.broadcast_failed.len()), Color32::from_rgb(220, 60, 80))
+                    } else {
+                        (format!("📡 {}", self.broadcast_targets.len()), Color32::from_rgb(255, 165, 0))
+                    };
+                    let hover = if self.broadcast_failed.is_empty() {
+                        t!("broadcast-targets", count = self.broadcast_targets.len())
+                    } else {
+                        format!("{}\n{}", t!("broadcast-failed-in"), self.broadcast_failed.join("\n"))
+                    };
+                    let response = ui.add(egui::Label::new(RichText::new(text).color(color)).sense(egui::Sense::click()));
+                    if response.on_hover_text(hover).clicked() {
+                        self.show_broadcast = true;
+                    }
+                }
+                if let Some(ref status) = self.plugin_status {
+                    ui.separator();
+                    ui.label(status);
+                }
+                if let Some(ref status) = self.script_status {
+                    ui.separator();
+                    ui.label(status);
+                }
+                if let Some(ref status) = self.action_status {
+                    ui.separator();
+                    ui.label(status);
+                }
+                if let Some(ref status) = self.sync_status {
+                    ui.separator();
+                    ui.label(status).on_hover_text("Session sync (↑ pushed, ↓ pulled)");
+                }
+                if self.config.general.offline {
+                    ui.separator();
+                    ui.label(RichText::new(t!("menu-offline")).color(Color32::from_rgb(255, 165, 0)))
+                        .on_hover_text(t!("menu-offline-hint"));
+                }
+                if let Some(ref git) = self.git_status {
+                    ui.separator();
+                    let color = if git.is_dirty {
+                        Color32::from_rgb(255, 165, 0)
                     } else {
-                        // Display all blocks (newest at bottom)
-                        let blocks_to_display: Vec<_> = self.block_manager.get_blocks()
+                        Color32::from_rgb(80, 200, 120)
+                    };
+                    ui.label(RichText::new(format!("⎇ {}", git.summary())).color(color))
+                        .on_hover_text("Git branch (* = uncommitted changes, ↑ ahead, ↓ behind)");
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.label(format!("v{}", env!("CARGO_PKG_VERSION")));
+                    ui.separator();
+                    ui.label(t!("status-blocks", count = self.block_manager.count()));
+                    ui.separator();
+
+                    let running = self.ai_requests.running();
+                    if !running.is_empty() {
+                        let lines: Vec<String> = running
                             .iter()
-                            .map(|b| b.clone())
+                            .map(|request| {
+                                let state = if request.is_active() { "" } else { ", waiting" };
+                                format!(
+                                    "{} · {} ({}s{})",
+                                    request.origin.feature.label(),
+                                    request.provider,
+                                    request.started.elapsed().as_secs(),
+                                    state
+                                )
+                            })
                             .collect();
-                        
-                        for block in blocks_to_display {
-                            let widget = BlockWidget::new(&block, self.config.appearance.font_size);
-                            let block_response = widget.show(ui);
-                            
-                            if block_response.selected {
-                                self.block_manager.select_block(block.id);
+                        ui.label(format!("⏳ {}", running.len()))
+                            .on_hover_text(format!("AI requests in flight:\n{}", lines.join("\n")));
+                        ui.separator();
+                        ctx.request_repaint_after(Duration::from_secs(1));
+                    }
+
+                    // AI provider/model (click to switch)
+                    let model = self.ai_panel.selected_model();
+                    let ai_label = if model.is_empty() {
+                        format!("🤖 {}", self.ai_panel.selected_provider())
+                    } else {
+                        format!("🤖 {}/{}", self.ai_panel.selected_provider(), model)
+                    };
+                    ui.menu_button(ai_label, |ui| {
+                        ui.label(t!("menu-provider"));
+                        for name in self.usable_providers() {
+                            let is_selected = name == self.ai_panel.selected_provider();
+                            if ui.selectable_label(is_selected, name.as_str()).clicked() && !is_selected {
+                                status_action = Some(AiAction::ProviderChanged(name));
+                                ui.close_menu();
                             }
-                            
-                            if block_response.toggle_collapsed {
-                                self.block_manager.toggle_block_collapsed(&block.id);
+                        }
+
+                        ui.separator();
+                        ui.label(t!("menu-model"));
+                        if self.ai_panel.available_models().is_empty() {
+                            if ui.button(t!("menu-load-models")).clicked() {
+                                status_action = Some(AiAction::LoadModels);
+                                ui.close_menu();
                             }
-                            
-                            if block_response.show_context_menu {
-                                self.context_menu_block = Some(block.id);
-                                self.context_menu_pos = ctx.input(|i| i.pointer.hover_pos());
-                                self.context_menu_opened_at = Some(Instant::now());
+                        } else {
+                            let models = self.ai_panel.available_models().to_vec();
+                            for model in models {
+                                let is_selected = model == self.ai_panel.selected_model();
+                                if ui.selectable_label(is_selected, &model).clicked() {
+                                    if !is_selected {
+                                        status_action = Some(AiAction::ModelChanged(model));
+                                    }
+                                    ui.close_menu();
+                                }
                             }
-                            
-                            if block_response.approve_command {
-                                // Execute the AI-suggested command
-                                let command = block.command.clone();
-                                self.block_manager.remove_block(&block.id);
-                                self.execute_shell_command(command, ctx);
+                        }
+                    });
+                    ui.separator();
+
+                    // Kubernetes context (click to switch)
+                    if let Some(kube) = self.kube_config.clone() {
+                        if let Some(current) = kube.current() {
+                            let is_production = current.is_production(&self.config.kubernetes.production_patterns);
+                            let text = format!("☸ {}/{}", current.name, current.namespace_or_default());
+                            let label = if is_production {
+                                RichText::new(text)
+                                    .color(Color32::WHITE)
+                                    .background_color(Color32::from_rgb(180, 40, 50))
+                            } else {
+                                RichText::new(text)
+                            };
+                            let mut switch_to: Option<String> = None;
+                            ui.menu_button(label, |ui| {
+                                if is_production {
+                                    ui.colored_label(Color32::from_rgb(220, 60, 80), "⚠ Production cluster");
+                                    ui.separator();
+                                }
+                                ui.label("Switch context:");
+                                for context in &kube.contexts {
+                                    let is_current = context.name == current.name;
+                                    if ui.selectable_label(is_current, &context.name).clicked() && !is_current {
+                                        switch_to = Some(context.name.clone());
+                                        ui.close_menu();
+                                    }
+                                }
+                            });
+                            if let Some(name) = switch_to {
+                                // Runs as a normal block so the switch is visible in the session
+                                if self.current_block_id.is_some() {
+                                    tracing::warn!("Command already running, not switching kube context");
+                                } else {
+                                    self.execute_shell_command(format!("kubectl config use-context {}", name), ctx);
+                                }
                             }
-                            
-                            if block_response.reject_command {
-                                // Remove the pending block
-                                self.block_manager.remove_block(&block.id);
+                            ui.separator();
+                        }
+                    }
+
+                    // Shell backend
+                    let shell_name = std::path::Path::new(&self.config.general.default_shell)
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .unwrap_or(&self.config.general.default_shell)
+                        .to_string();
+                    ui.label(format!("🐚 {}", shell_name))
+                        .on_hover_text(self.config.general.default_shell.clone());
+                });
+            });
+        });
+        if let Some(action) = status_action {
+            self.handle_ai_action(action, ctx);
+        }
+
+        // Session list dialog
+        if self.show_session_list {
+            egui::Window::new(t!("dialog-open-session"))
+                .id(egui::Id::new("open_session_window"))
+                .collapsible(false)
+                .resizable(true)
+                .default_width(500.0)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(t!("open-session-hint"));
+                        if self.session_list.loading {
+                            ui.spinner();
+                        }
+                    });
+                    if let Some(error) = &self.session_list.error {
+                        ui.colored_label(Color32::from_rgb(220, 60, 80), t!("open-session-list-failed", error = error.clone()));
+                    }
+                    ui.separator();
+
+                    let mut groups = group_sessions_by_folder(&self.available_sessions);
+                    // Folders created in this dialog that don't contain sessions yet
+                    for folder in &self.pending_session_folders {
+                        if !groups.iter().any(|(f, _)| f.as_deref() == Some(folder.as_str())) {
+                            groups.push((Some(folder.clone()), Vec::new()));
+                        }
+                    }
+                    let folder_names: Vec<String> = groups.iter().filter_map(|(f, _)| f.clone()).collect();
+                    let mut moves: Vec<(Uuid, Option<String>)> = Vec::new();
+
+                    ScrollArea::vertical()
+                        .max_height(300.0)
+                        .show(ui, |ui| {
+                            for (folder, sessions) in &groups {
+                                let (_, dropped) = ui.dnd_drop_zone::<Uuid, _>(egui::Frame::none(), |ui| {
+                                    ui.set_min_width(ui.available_width());
+                                    let title = match folder {
+                                        Some(name) => format!("📁 {} ({})", name, sessions.len()),
+                                        None => t!("open-session-ungrouped-count", count = sessions.len()),
+                                    };
+                                    egui::CollapsingHeader::new(title)
+                                        .id_source(("session_folder", folder.clone()))
+                                        .default_open(true)
+                                        .show(ui, |ui| {
+                                            for session_info in sessions {
+                                                self.show_session_row(session_info, &folder_names, &mut moves, ui, ctx);
+                                            }
+                                        });
+                                });
+                                if let Some(session_id) = dropped {
+                                    moves.push((*session_id, folder.clone()));
+                                }
                             }
-                            
-                            if block_response.edit_command {
-                                // Put command in input for editing
-                                self.command_input = block.command.clone();
-                                self.block_manager.remove_block(&block.id);
+                        });
+
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        let response = ui.add(
+                            egui::TextEdit::singleline(&mut self.new_folder_name)
+                                .hint_text(t!("open-session-new-folder"))
+                                .desired_width(160.0),
+                        );
+                        let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                        if (ui.button(t!("open-session-add-folder")).clicked() || submitted) && !self.new_folder_name.trim().is_empty() {
+                            let name = self.new_folder_name.trim().to_string();
+                            if !self.pending_session_folders.contains(&name) {
+                                self.pending_session_folders.push(name);
                             }
-                            
-                            if block_response.regenerate_command {
-                                // Regenerate command from original NL input
-                                if let Some(nl_input) = block.original_input.clone() {
-                                    self.block_manager.remove_block(&block.id);
-                                    self.convert_natural_language_to_command(nl_input, ctx);
+                            self.new_folder_name.clear();
+                        }
+                    });
+
+                    if !moves.is_empty() {
+                        for (session_id, folder) in moves {
+                            self.move_session_to_folder(session_id, folder);
+                        }
+                        self.load_available_sessions(ctx);
+                    }
+
+                    ui.separator();
+                    if ui.button(t!("button-close")).clicked() {
+                        self.show_session_list = false;
+                    }
+                });
+        }
+
+        // Broadcast targets
+        if self.show_broadcast {
+            let mut open = true;
+            egui::Window::new(t!("dialog-broadcast"))
+                .id(egui::Id::new("broadcast_window"))
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(true)
+                .default_width(400.0)
+                .show(ctx, |ui| {
+                    ui.label(
+                        RichText::new(t!("broadcast-hint"))
+                            .weak()
+                    );
+                    ui.separator();
+
+                    let others: Vec<SessionInfo> = self.available_sessions
+                        .iter()
+                        .filter(|s| s.id != self.session.id)
+                        .cloned()
+                        .collect();
+                    if others.is_empty() {
+                        ui.label(RichText::new(t!("menu-no-recent-sessions")).weak());
+                    }
+                    ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        for info in &others {
+                            ui.horizontal(|ui| {
+                                let mut checked = self.broadcast_targets.contains(&info.id);
+                                if ui.checkbox(&mut checked, &info.name).changed() {
+                                    if checked {
+                                        self.broadcast_targets.insert(info.id);
+                                    } else {
+                                        self.broadcast_targets.remove(&info.id);
+                                    }
+                                }
+                                if let Some(color) = info.color {
+                                    ui.label(RichText::new("●").color(session_color(color)));
+                                }
+                                ui.label(RichText::new(abbreviate_home(&info.working_directory)).small().weak());
+                            });
+                        }
+                    });
+
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        if ui.button(t!("button-select-all")).clicked() {
+                            self.broadcast_targets.extend(others.iter().map(|s| s.id));
+                        }
+                        if ui.button(t!("button-select-none")).clicked() {
+                            self.broadcast_targets.clear();
+                        }
+                        if self.broadcast_pending > 0 && ui.button(t!("button-stop-broadcasts")).clicked() {
+                            self.broadcast_cancel.send_modify(|generation| *generation += 1);
+                        }
+                    });
+                });
+            if !open {
+                self.show_broadcast = false;
+            }
+        }
+
+        // SSH host picker
+        if self.show_ssh_picker {
+            let mut open = true;
+            egui::Window::new(t!("dialog-ssh-hosts"))
+                .id(egui::Id::new("ssh_hosts_window"))
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(true)
+                .default_width(450.0)
+                .show(ctx, |ui| {
+                    let search = ui.add(
+                        egui::TextEdit::singleline(&mut self.ssh_query)
+                            .hint_text("Search hosts...")
+                            .desired_width(f32::INFINITY),
+                    );
+                    if ctx.memory(|m| m.focused().is_none()) {
+                        search.request_focus();
+                    }
+                    ui.separator();
+
+                    let hosts: Vec<SshHost> = self.ssh_hosts
+                        .iter()
+                        .filter(|h| self.ssh_query.is_empty() || h.matches(&self.ssh_query))
+                        .cloned()
+                        .collect();
+
+                    if self.ssh_hosts.is_empty() {
+                        ui.label(RichText::new("No hosts found in ~/.ssh/config").weak());
+                    }
+
+                    let mut insert: Option<String> = None;
+                    ScrollArea::vertical().max_height(350.0).show(ui, |ui| {
+                        for host in &hosts {
+                            ui.horizontal(|ui| {
+                                if ui.button(RichText::new(&host.alias).monospace().strong()).clicked() {
+                                    insert = Some(host.ssh_command());
                                 }
+                                ui.label(RichText::new(host.target()).weak());
+                            });
+
+                            // Recent commands run against this host
+                            let recent = self.recent_ssh_commands(&host.alias, 5);
+                            for command in recent {
+                                ui.horizontal(|ui| {
+                                    ui.add_space(16.0);
+                                    if ui.small_button(RichText::new(&command).monospace()).clicked() {
+                                        insert = Some(command.clone());
+                                    }
+                                });
                             }
-                            
-                            // Thin separator line between blocks
-                            ui.add_space(8.0);
-                            ui.painter().hline(
-                                ui.available_rect_before_wrap().x_range(),
-                                ui.cursor().top(),
-                                egui::Stroke::new(1.0, Color32::from_rgb(50, 50, 50))
-                            );
-                            ui.add_space(8.0);
                         }
+                    });
+
+                    // Enter picks the first match
+                    if search.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        if let Some(host) = hosts.first() {
+                            insert = Some(host.ssh_command());
+                        }
+                    }
+
+                    if let Some(command) = insert {
+                        self.command_input = command;
+                        self.show_ssh_picker = false;
+                    }
+                });
+            if !open {
+                self.show_ssh_picker = false;
+            }
+        }
+
+        // Directory quick-jump
+        if self.show_quick_jump {
+            let mut open = true;
+            let mut jump: Option<PathBuf> = None;
+            let mut pin: Option<(PathBuf, bool)> = None;
+            let mut forget: Option<PathBuf> = None;
+            egui::Window::new(t!("dialog-quick-jump"))
+                .id(egui::Id::new("quick_jump_window"))
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(true)
+                .default_width(500.0)
+                .show(ctx, |ui| {
+                    let ranked: Vec<DirectoryBookmark> =
+                        bookmarks::rank(&self.directory_bookmarks, &self.quick_jump_query, chrono::Utc::now())
+                            .into_iter()
+                            .cloned()
+                            .collect();
+                    // Arrow keys move the selection rather than the cursor
+                    ui.input_mut(|i| {
+                        if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown) {
+                            self.quick_jump_selected += 1;
+                        }
+                        if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp) {
+                            self.quick_jump_selected = self.quick_jump_selected.saturating_sub(1);
+                        }
+                    });
+                    self.quick_jump_selected = self.quick_jump_selected.min(ranked.len().saturating_sub(1));
+
+                    let search = ui.add(
+                        egui::TextEdit::singleline(&mut self.quick_jump_query)
+                            .hint_text("Type part of a path...")
+                            .desired_width(f32::INFINITY),
+                    );
+                    if search.changed() {
+                        self.quick_jump_selected = 0;
+                    }
+                    if ctx.memory(|m| m.focused().is_none()) {
+                        search.request_focus();
+                    }
+
+                    let current = self.session.working_directory.clone();
+                    let current_pinned = self.directory_bookmarks.iter().any(|b| b.path == current && b.pinned);
+                    if !current_pinned && ui.small_button("📌 Pin current directory").clicked() {
+                        pin = Some((current, true));
+                    }
+                    ui.separator();
+
+                    if self.bookmark_receiver.is_some() {
+                        ui.spinner();
+                    } else if self.directory_bookmarks.is_empty() {
+                        ui.label(RichText::new("Directories you cd into show up here").weak());
+                    }
+
+                    ScrollArea::vertical().max_height(350.0).show(ui, |ui| {
+                        for (i, bookmark) in ranked.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                let pin_icon = if bookmark.pinned { "📌" } else { "📍" };
+                                let pin_hint = if bookmark.pinned { "Unpin" } else { "Pin" };
+                                if icon_button(ui, pin_icon, pin_hint).clicked() {
+                                    pin = Some((bookmark.path.clone(), !bookmark.pinned));
+                                }
+                                let label = ui.selectable_label(
+                                    i == self.quick_jump_selected,
+                                    RichText::new(abbreviate_home(&bookmark.path)).monospace(),
+                                );
+                                if i == self.quick_jump_selected {
+                                    label.scroll_to_me(None);
+                                }
+                                if label.clicked() {
+                                    jump = Some(bookmark.path.clone());
+                                }
+                                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                    if !bookmark.pinned && icon_button(ui, "✕", "Forget").clicked() {
+                                        forget = Some(bookmark.path.clone());
+                                    }
+                                    if bookmark.visits > 0 {
+                                        ui.label(RichText::new(format!("{}×", bookmark.visits)).weak());
+                                    }
+                                });
+                            });
+                        }
+                    });
+
+                    // Enter jumps to the selected directory
+                    if search.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        if let Some(bookmark) = ranked.get(self.quick_jump_selected) {
+                            jump = Some(bookmark.path.clone());
+                        }
+                    }
+                });
+            if let Some((path, pinned)) = pin {
+                self.set_directory_pinned(&path, pinned);
+            }
+            if let Some(path) = forget {
+                self.forget_directory(&path);
+            }
+            if let Some(dir) = jump {
+                self.change_directory(&dir, ctx);
+                self.show_quick_jump = false;
+            }
+            if !open {
+                self.show_quick_jump = false;
+            }
+        }
+
+        // Session command history
+        if let Some(path) = self.path_picker.show(ctx) {
+            self.insert_at_cursor(ctx, &insertable_path(&path, &self.session.working_directory));
+        }
+        if let Some(block_id) = self.history_view.show(ctx, self.block_manager.get_blocks(), self.config.appearance.font_size) {
+            self.show_block(block_id);
+        }
+
+        // Divider heading
+        if self.show_divider_dialog {
+            let mut open = true;
+            egui::Window::new(t!("dialog-insert-divider"))
+                .id(egui::Id::new("insert_divider_window"))
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(false)
+                .default_width(360.0)
+                .show(ctx, |ui| {
+                    ui.label(t!("divider-prompt"));
+                    let title = ui.add(
+                        egui::TextEdit::singleline(&mut self.divider_title)
+                            .hint_text(t!("divider-heading-hint"))
+                            .desired_width(f32::INFINITY),
+                    );
+                    if ctx.memory(|m| m.focused().is_none()) {
+                        title.request_focus();
+                    }
+                    let submitted = title.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    ui.horizontal(|ui| {
+                        let can_insert = !self.divider_title.trim().is_empty();
+                        if (ui.add_enabled(can_insert, egui::Button::new(t!("divider-insert"))).clicked() || submitted) && can_insert {
+                            let title = std::mem::take(&mut self.divider_title);
+                            self.insert_divider(&title);
+                            self.show_divider_dialog = false;
+                        }
+                        if ui.button(t!("button-cancel")).clicked() {
+                            self.show_divider_dialog = false;
+                        }
+                    });
+                });
+            if !open {
+                self.show_divider_dialog = false;
+            }
+        }
+
+        // Confirm clearing the session's blocks
+        if self.confirm_clear_blocks {
+            let mut open = true;
+            let count = self.block_manager.get_blocks()
+                .iter()
+                .filter(|b| Some(b.id) != self.current_block_id)
+                .count();
+            egui::Window::new(t!("dialog-clear-blocks"))
+                .id(egui::Id::new("clear_blocks_window"))
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(t!("clear-blocks-confirm", count = count, session = self.session.name.clone()));
+                    if self.current_block_id.is_some() {
+                        ui.label(RichText::new(t!("clear-blocks-running-kept")).weak());
+                    }
+                    ui.add_space(8.0);
+                    ui.horizontal(|ui| {
+                        let delete = egui::Button::new(RichText::new(t!("clear-blocks-delete")).color(Color32::from_rgb(230, 80, 80)));
+                        if ui.add_enabled(count > 0, delete).clicked() {
+                            let ids = self.block_manager.get_blocks().iter().map(|b| b.id).collect();
+                            self.delete_blocks(ids);
+                            self.confirm_clear_blocks = false;
+                        }
+                        if ui.button(t!("button-cancel")).clicked() {
+                            self.confirm_clear_blocks = false;
+                        }
+                    });
+                });
+            if !open {
+                self.confirm_clear_blocks = false;
+            }
+        }
+
+        // Confirmation for a dangerous typed command
+        if let Some((command, pattern)) = self.confirm_dangerous.clone() {
+            let mut open = true;
+            let mut decision = None;
+            egui::Window::new(t!("dialog-confirm-command"))
+                .id(egui::Id::new("confirm_command_window"))
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label(t!("confirm-matches", pattern = pattern.clone()));
+                    ui.add_space(4.0);
+                    ui.label(RichText::new(&command).monospace().strong());
+                    ui.add_space(8.0);
+                    ui.horizontal(|ui| {
+                        let run = ui.button(RichText::new(t!("button-run")).color(Color32::from_rgb(230, 80, 80)));
+                        if run.clicked() {
+                            decision = Some(Some(false));
+                        }
+                        if ui.button(t!("button-run-always")).on_hover_text(t!("confirm-remember-hint")).clicked() {
+                            decision = Some(Some(true));
+                        }
+                        if ui.button(t!("button-cancel")).clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                            decision = Some(None);
+                        }
+                    });
+                });
+            if !open {
+                decision = Some(None);
+            }
+            match decision {
+                Some(Some(remember)) => {
+                    self.confirm_dangerous = None;
+                    if remember {
+                        self.config.dangerous_commands.allowed.push(command.trim().to_string());
+                        if let Err(e) = self.config.save() {
+                            tracing::error!("Failed to save config: {}", e);
+                        }
+                    }
+                    self.run_typed_command(command, ctx);
+                }
+                Some(None) => {
+                    // Hand the command back for editing
+                    self.confirm_dangerous = None;
+                    self.command_input = command;
+                }
+                None => {}
+            }
+        }
+
+        // Automatic setup held back until the directory is trusted
+        if let Some(dir) = self.untrusted_workspace.clone() {
+            let setup = self.workspace_setup();
+            let mut open = true;
+            let mut decision = None;
+            egui::Window::new(t!("dialog-trust-workspace"))
+                .id(egui::Id::new("trust_workspace_window"))
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label(t!("trust-runs-setup"));
+                    ui.label(RichText::new(abbreviate_home(&dir)).monospace().strong());
+                    ui.add_space(4.0);
+                    for line in &setup {
+                        ui.label(RichText::new(line).monospace());
                     }
+                    ui.add_space(4.0);
+                    ui.label(RichText::new(t!("trust-only-known")).weak());
+                    ui.add_space(8.0);
+                    ui.horizontal(|ui| {
+                        if ui.button(t!("button-trust-folder")).on_hover_text(t!("trust-folder-hint")).clicked() {
+                            decision = Some((true, true));
+                        }
+                        if ui.button(t!("button-run-once")).clicked() {
+                            decision = Some((false, true));
+                        }
+                        if ui.button(t!("button-dont-run")).clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                            decision = Some((false, false));
+                        }
+                    });
                 });
-            
-            ui.add_space(4.0);
-            
-            // AI Panel (compact mode above command input)
-            let providers: Vec<String> = self.config.ai.providers
-                .keys()
-                .filter(|k| self.config.ai.providers[*k].enabled)
-                .cloned()
-                .collect();
-            
-            if let Some(action) = self.ai_panel.show_compact(ui, &providers) {
-                self.handle_ai_action(action, ctx);
+            if !open {
+                decision = Some((false, false));
             }
-            
-            ui.add_space(4.0);
-            
-            // Command input area - Warp style (minimal, no heavy border)
-            egui::Frame::none()
-                .fill(egui::Color32::from_rgba_premultiplied(30, 30, 35, 50))
-                .stroke(egui::Stroke::NONE)
-                .inner_margin(egui::Margin {
-                    left: 12.0,
-                    right: 12.0,
-                    top: 10.0,
-                    bottom: 10.0,
-                })
-                .show(ui, |ui| {
+            if let Some((trust, run)) = decision {
+                self.decide_workspace_trust(trust, run, ctx);
+            }
+        }
+
+        // What changed in the environment between two blocks
+        if let Some((before_id, after_id)) = self.environment_diff {
+            let mut open = true;
+            let mut selected = (before_id, after_id);
+            let blocks: Vec<(Uuid, String)> = self.block_manager.get_blocks()
+                .iter()
+                .filter(|b| !b.is_divider && !b.is_calculation && b.metadata.started_at.is_some())
+                .map(|b| (b.id, format!("{}  {}", b.timestamp.with_timezone(&chrono::Local).format("%H:%M:%S"), b.command)))
+                .collect();
+            let label = |id: Uuid| blocks.iter()
+                .find(|(b, _)| *b == id)
+                .map(|(_, l)| l.clone())
+                .unwrap_or_default();
+            egui::Window::new(t!("dialog-environment-diff"))
+                .id(egui::Id::new("environment_diff_window"))
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(true)
+                .default_width(560.0)
+                .show(ctx, |ui| {
+                    egui::Grid::new("environment_diff_blocks").num_columns(2).show(ui, |ui| {
+                        let sides = [
+                            ("environment_diff_before", t!("env-diff-before"), &mut selected.0),
+                            ("environment_diff_after", t!("env-diff-after"), &mut selected.1),
+                        ];
+                        for (id_source, name, id) in sides {
+                            ui.label(name);
+                            egui::ComboBox::from_id_source(id_source)
+                                .width(440.0)
+                                .selected_text(label(*id))
+                                .show_ui(ui, |ui| {
+                                    for (block, text) in blocks.iter().rev() {
+                                        ui.selectable_value(id, *block, text);
+                                    }
+                                });
+                            ui.end_row();
+                        }
+                    });
+                    ui.separator();
+
+                    let (Some(before), Some(after)) = (
+                        self.block_manager.get_block(&selected.0),
+                        self.block_manager.get_block(&selected.1),
+                    ) else {
+                        return;
+                    };
+                    let outcome = |b: &Block| match b.exit_code {
+                        Some(0) => RichText::new(t!("env-diff-succeeded")).color(Color32::from_rgb(100, 200, 100)),
+                        Some(code) => RichText::new(t!("env-diff-failed", code = code.to_string()))
+                            .color(Color32::from_rgb(230, 80, 80)),
+                        None => RichText::new(t!("env-diff-no-exit-code")).weak(),
+                    };
                     ui.horizontal(|ui| {
-                        ui.label(
-                            egui::RichText::new("›")
-                                .color(egui::Color32::from_rgb(100, 180, 255))
-                                .size(self.config.appearance.font_size + 2.0)
-                        );
-                        
+                        ui.label(outcome(before));
+                        ui.label("→");
+                        ui.label(outcome(after));
+                    });
+
+                    let (directory, changes) = environment_diff(before, after);
+                    if let Some((old, new)) = &directory {
+                        ui.label(RichText::new(t!("env-diff-working-directory")).strong());
+                        ui.label(RichText::new(format!("- {}", old.display())).monospace().color(Color32::from_rgb(230, 80, 80)));
+                        ui.label(RichText::new(format!("+ {}", new.display())).monospace().color(Color32::from_rgb(100, 200, 100)));
                         ui.add_space(4.0);
-                        let response = ui.add(
-                            egui::TextEdit::singleline(&mut self.command_input)
-                                .desired_width(f32::INFINITY)
-                                .hint_text("Enter a command or natural language request...")
-                                .font(egui::FontId::monospace(self.config.appearance.font_size)),
-                        );
-                        
-                        // Handle Enter key
-                        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                            self.execute_command(ctx);
-                            response.request_focus();
-                        }
-                        
-                        // Handle Up/Down arrows for history navigation
-                        if response.has_focus() {
-                            if ui.input(|i| i.key_pressed(egui::Key::ArrowUp)) {
-                                self.history_previous();
+                    }
+                    if before.metadata.environment.is_empty() || after.metadata.environment.is_empty() {
+                        ui.label(RichText::new(t!("env-diff-no-snapshot")).weak());
+                    }
+                    if directory.is_none() && changes.is_empty() {
+                        ui.label(t!("env-diff-no-differences"));
+                        return;
+                    }
+                    egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                        for change in &changes {
+                            ui.label(RichText::new(&change.name).strong().monospace());
+                            if let Some(value) = &change.before {
+                                ui.label(RichText::new(format!("- {}", value)).monospace().color(Color32::from_rgb(230, 80, 80)));
                             }
-                            if ui.input(|i| i.key_pressed(egui::Key::ArrowDown)) {
-                                self.history_next();
+                            if let Some(value) = &change.after {
+                                ui.label(RichText::new(format!("+ {}", value)).monospace().color(Color32::from_rgb(100, 200, 100)));
                             }
                         }
-                        
-                        // Show status on the right
-                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                            if self.current_block_id.is_some() {
-                                ui.spinner();
-                                ui.label(
-                                    egui::RichText::new("Running...")
-                                        .color(egui::Color32::from_rgb(150, 150, 150))
-                                );
-                            } else {
-                                // Show hint about operation mode
-                                let mode_hint = match self.config.ai.operation_mode {
-                                    crate::config::OperationMode::TerminalOnly => "Terminal mode",
-                                    crate::config::OperationMode::AiPromptOnly => "AI mode",
-                                    crate::config::OperationMode::Hybrid => "Hybrid mode",
-                                };
-                                ui.label(
-                                    egui::RichText::new(mode_hint)
-                                        .color(egui::Color32::from_rgb(100, 100, 100))
-                                        .size(self.config.appearance.font_size - 2.0)
-                                );
-                            }
-                        });
                     });
                 });
-            
-            // Context menu (popup that closes when clicking away)
-            if self.context_menu_pos.is_some() {
-                if let Some(menu_pos) = self.context_menu_pos {
-                    let popup_id = egui::Id::new("context_menu");
-                    
-                    // Show popup at saved position (not following cursor)
-                    egui::Area::new(popup_id)
-                        .order(egui::Order::Foreground)
-                        .fixed_pos(menu_pos)
-                        .show(ctx, |ui| {
-                        let frame = egui::Frame::popup(ui.style());
-                        let frame_response = frame.show(ui, |ui| {
-                            ui.set_min_width(180.0);
-                            ui.spacing_mut().button_padding = egui::vec2(8.0, 4.0);
-                            
-                            // Show different menu options depending on whether a block is selected
-                            if let Some(block_id) = self.context_menu_block {
-                                // Block-specific menu
-                                if ui.button("📋 Copy Command").clicked() {
-                                    if let Some(cmd) = self.block_manager.copy_block_command(&block_id) {
-                                        ui.output_mut(|o| o.copied_text = cmd);
-                                    }
-                                    self.context_menu_block = None;
-                                    self.context_menu_pos = None;
-                                    self.context_menu_opened_at = None;
-                                }
-                                
-                                if ui.button("📄 Copy Output").clicked() {
-                                    if let Some(output) = self.block_manager.copy_block_output(&block_id) {
-                                        ui.output_mut(|o| o.copied_text = output);
-                                    }
-                                    self.context_menu_block = None;
-                                    self.context_menu_pos = None;
-                                    self.context_menu_opened_at = None;
-                                }
-                                
-                                if ui.button("📑 Copy Both").clicked() {
-                                    if let Some(full) = self.block_manager.copy_block_full(&block_id) {
-                                        ui.output_mut(|o| o.copied_text = full);
-                                    }
-                                    self.context_menu_block = None;
-                                    self.context_menu_pos = None;
-                                    self.context_menu_opened_at = None;
+            self.environment_diff = open.then_some(selected);
+        }
+
+        // Keyboard shortcut reference
+        if self.show_shortcuts {
+            let mut open = true;
+            egui::Window::new(t!("dialog-keyboard-shortcuts"))
+                .id(egui::Id::new("keyboard_shortcuts_window"))
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(true)
+                .default_width(520.0)
+                .show(ctx, |ui| {
+                    let search = ui.add(
+                        egui::TextEdit::singleline(&mut self.shortcuts_query)
+                            .hint_text("Search shortcuts...")
+                            .desired_width(f32::INFINITY),
+                    );
+                    if ctx.memory(|m| m.focused().is_none()) {
+                        search.request_focus();
+                    }
+                    ui.separator();
+
+                    let query = self.shortcuts_query.clone();
+                    let bindings = &self.config.keybindings;
+                    ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                        egui::Grid::new("shortcuts_grid").num_columns(3).striped(true).spacing([16.0, 6.0]).show(ui, |ui| {
+                            for action in KeyAction::ALL {
+                                let binding = action.binding(bindings);
+                                let shortcut = parse_shortcut(binding);
+                                // Shown the platform's way, e.g. ⌘ on macOS, but found either way
+                                let keys = shortcut.map_or_else(|| binding.to_string(), |s| ctx.format_shortcut(&s));
+                                if !keybindings::shortcut_matches(&query, &format!("{} {}", keys, binding), action.description(), action.config_key()) {
+                                    continue;
                                 }
-                                
-                                ui.separator();
-                                
-                                if ui.button("✏️ Edit & Re-run").clicked() {
-                                    if let Some(block) = self.block_manager.get_block(&block_id) {
-                                        self.command_input = block.command.clone();
-                                    }
-                                    self.context_menu_block = None;
-                                    self.context_menu_pos = None;
-                                    self.context_menu_opened_at = None;
+                                if shortcut.is_some() {
+                                    ui.label(RichText::new(keys).monospace().strong());
+                                } else {
+                                    ui.label(RichText::new(keys).monospace().strong().color(Color32::from_rgb(230, 80, 80)))
+                                        .on_hover_text("Not a shortcut this build understands");
                                 }
-                                
-                                if ui.button("🗑️ Delete Block").clicked() {
-                                    self.block_manager.remove_block(&block_id);
-                                    self.context_menu_block = None;
-                                    self.context_menu_pos = None;
-                                    self.context_menu_opened_at = None;
+                                let mut description = RichText::new(action.description());
+                                if !action.is_available() {
+                                    description = description.weak();
                                 }
-                            } else {
-                                // General menu (no block selected)
-                                if ui.button("📂 Open Session").clicked() {
-                                    self.show_session_list = true;
-                                    self.context_menu_pos = None;
-                                    self.context_menu_opened_at = None;
+                                let description = ui.label(description);
+                                if !action.is_available() {
+                                    description.on_hover_text("Not available yet");
                                 }
-                                
-                                if ui.button("🎨 Change Theme").clicked() {
-                                    self.show_theme_selector = !self.show_theme_selector;
-                                    self.context_menu_pos = None;
-                                    self.context_menu_opened_at = None;
+                                let mut config_key = RichText::new(action.config_key()).monospace().weak();
+                                if action.is_customized(bindings) {
+                                    config_key = config_key.italics();
                                 }
-                                
-                                ui.separator();
-                                
-                                if ui.button("📤 Export Session").clicked() {
-                                    self.show_export_dialog = true;
-                                    self.context_menu_pos = None;
-                                    self.context_menu_opened_at = None;
+                                let config_key = ui.label(config_key);
+                                if action.is_customized(bindings) {
+                                    config_key.on_hover_text("Changed from the default in [keybindings]");
                                 }
-                                
-                                if ui.button("🗑️ Clear All Blocks").clicked() {
-                                    // Clear all blocks
-                                    let block_ids: Vec<_> = self.block_manager.get_blocks()
-                                        .iter()
-                                        .map(|b| b.id)
-                                        .collect();
-                                    for id in block_ids {
-                                        self.block_manager.remove_block(&id);
-                                    }
-                                    self.context_menu_pos = None;
-                                    self.context_menu_opened_at = None;
+                                ui.end_row();
+                            }
+                            for (keys, description) in keybindings::FIXED_SHORTCUTS {
+                                if !keybindings::shortcut_matches(&query, keys, description, "") {
+                                    continue;
                                 }
+                                ui.label(RichText::new(*keys).monospace().strong());
+                                ui.label(*description);
+                                ui.label("");
+                                ui.end_row();
                             }
                         });
-                        
-                        // Close menu if clicking outside
-                        // Check if menu has been open for at least 100ms to prevent closing on opening click
-                        let menu_open_duration = self.context_menu_opened_at
-                            .map(|t| t.elapsed().as_millis())
-                            .unwrap_or(0);
-                        
-                        if menu_open_duration > 100 {
-                            // Check for any click outside the menu
-                            if ui.input(|i| i.pointer.any_click()) && !frame_response.response.contains_pointer() {
-                                self.context_menu_block = None;
-                                self.context_menu_pos = None;
-                                self.context_menu_opened_at = None;
+                    });
+                    ui.separator();
+                    ui.label(RichText::new("Change shortcuts in the [keybindings] section of the config file").weak());
+                });
+            if !open {
+                self.show_shortcuts = false;
+            }
+        }
+
+        // Scheduled commands panel
+        if self.show_scheduler {
+            let mut open = true;
+            egui::Window::new(t!("dialog-scheduled-commands"))
+                .id(egui::Id::new("scheduled_commands_window"))
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(true)
+                .default_width(520.0)
+                .show(ctx, |ui| {
+                    egui::Grid::new("schedule_form").num_columns(2).show(ui, |ui| {
+                        ui.label(t!("schedule-command"));
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.schedule_command_draft)
+                                .font(egui::TextStyle::Monospace)
+                                .desired_width(380.0),
+                        );
+                        ui.end_row();
+                        ui.label(t!("schedule-when"));
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.schedule_spec_draft)
+                                .hint_text("every 5m | at 14:30 | at 2026-01-31 09:00 | */15 9-17 * * 1-5")
+                                .desired_width(380.0),
+                        );
+                        ui.end_row();
+                    });
+
+                    ui.horizontal(|ui| {
+                        if ui.button(t!("schedule-add")).clicked() {
+                            let now = chrono::Local::now().naive_local();
+                            match self.scheduler.add(&self.schedule_command_draft, &self.schedule_spec_draft, now) {
+                                Ok(_) => {
+                                    self.schedule_command_draft.clear();
+                                    self.schedule_spec_draft.clear();
+                                    self.schedule_error = None;
+                                }
+                                Err(e) => self.schedule_error = Some(format!("{:#}", e)),
+                            }
+                        }
+                        if let Some(ref error) = self.schedule_error {
+                            ui.label(RichText::new(error).color(Color32::from_rgb(220, 60, 80)));
+                        }
+                    });
+
+                    ui.separator();
+                    ui.label(RichText::new(t!("schedule-upcoming")).strong());
+
+                    let mut remove = None;
+                    if self.scheduler.upcoming().is_empty() {
+                        ui.label(RichText::new(t!("schedule-empty")).weak());
+                    }
+                    egui::Grid::new("schedule_list").num_columns(4).striped(true).show(ui, |ui| {
+                        for job in self.scheduler.upcoming() {
+                            ui.label(job.next_run.format("%Y-%m-%d %H:%M:%S").to_string());
+                            ui.label(RichText::new(&job.spec).weak());
+                            ui.label(RichText::new(&job.command).monospace());
+                            if icon_button(ui, "🗑", &t!("button-unschedule")).clicked() {
+                                remove = Some(job.id);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    if let Some(id) = remove {
+                        self.scheduler.remove(&id);
+                    }
+                });
+            if !open {
+                self.show_scheduler = false;
+            }
+        }
+
+        // Workflow picker and Warp importer
+        if self.show_workflows {
+            let mut open = true;
+            egui::Window::new(t!("dialog-workflows"))
+                .id(egui::Id::new("workflows_window"))
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(true)
+                .default_width(500.0)
+                .show(ctx, |ui| {
+                    let search = ui.add(
+                        egui::TextEdit::singleline(&mut self.workflow_query)
+                            .hint_text(t!("workflows-search-hint"))
+                            .desired_width(f32::INFINITY),
+                    );
+                    if ctx.memory(|m| m.focused().is_none()) {
+                        search.request_focus();
+                    }
+                    ui.separator();
+
+                    let query = self.workflow_query.to_lowercase();
+                    let matching: Vec<workflows::Workflow> = self.workflow_store
+                        .as_ref()
+                        .map(|store| store.workflows())
+                        .unwrap_or_default()
+                        .iter()
+                        .filter(|w| {
+                            query.is_empty()
+                                || w.name.to_lowercase().contains(&query)
+                                || w.command.to_lowercase().contains(&query)
+                                || w.tags.iter().any(|t| t.to_lowercase().contains(&query))
+                        })
+                        .cloned()
+                        .collect();
+
+                    if matching.is_empty() {
+                        ui.label(RichText::new(t!("workflows-empty")).weak());
+                    }
+
+                    let mut insert: Option<String> = None;
+                    ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        for workflow in &matching {
+                            ui.horizontal(|ui| {
+                                let response = ui.button(RichText::new(&workflow.name).strong());
+                                if let Some(ref description) = workflow.description {
+                                    response.clone().on_hover_text(description);
+                                }
+                                if response.clicked() {
+                                    insert = Some(workflow.render(&[]));
+                                }
+                                ui.label(RichText::new(&workflow.command).monospace().weak());
+                            });
+                        }
+                    });
+
+                    // Enter picks the first match
+                    if search.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                        if let Some(workflow) = matching.first() {
+                            insert = Some(workflow.render(&[]));
+                        }
+                    }
+
+                    ui.separator();
+                    ui.label(t!("workflows-import-prompt"));
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.workflow_import_path)
+                                .hint_text("~/warp/workflows/specs")
+                                .desired_width(360.0),
+                        );
+                        if ui.button(t!("workflows-import")).clicked() {
+                            let path = crate::utils::expand_tilde(self.workflow_import_path.trim());
+                            let result = workflows::import_path(&path).and_then(|imported| {
+                                match self.workflow_store.as_mut() {
+                                    Some(store) => store.add(&imported),
+                                    None => anyhow::bail!(t!("workflows-no-config-dir")),
+                                }
+                            });
+                            self.workflow_import_status = Some(match result {
+                                Ok(count) => t!("workflows-imported", count = count),
+                                Err(e) => t!("workflows-import-failed", error = format!("{:#}", e)),
+                            });
+                        }
+                    });
+                    if let Some(ref status) = self.workflow_import_status {
+                        ui.label(RichText::new(status).weak());
+                    }
+
+                    if let Some(command) = insert {
+                        // Placeholders without defaults stay in the input to be filled in
+                        self.command_input = command;
+                        self.show_workflows = false;
+                    }
+                });
+            if !open {
+                self.show_workflows = false;
+            }
+        }
+
+        // Application settings dialog
+        if self.show_settings {
+            let mut open = true;
+            egui::Window::new(t!("dialog-settings"))
+                .id(egui::Id::new("settings_window"))
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(false)
+                .default_width(420.0)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new(t!("settings-language")).strong());
+                        let current = crate::i18n::LANGUAGES
+                            .iter()
+                            .find(|(code, _, _)| *code == crate::i18n::language())
+                            .map_or(crate::i18n::language(), |(_, name, _)| *name);
+                        egui::ComboBox::from_id_source("language")
+                            .selected_text(current)
+                            .show_ui(ui, |ui| {
+                                for (code, name, _) in crate::i18n::LANGUAGES {
+                                    if ui.selectable_label(*code == crate::i18n::language(), *name).clicked() {
+                                        crate::i18n::set_language(code);
+                                        self.config.general.language = code.to_string();
+                                    }
+                                }
+                            });
+                    });
+                    ui.checkbox(&mut self.config.appearance.state_patterns, t!("settings-state-patterns"))
+                        .on_hover_text(t!("settings-state-patterns-hint"));
+                    ui.checkbox(&mut self.config.appearance.highlight_diagnostics, t!("settings-highlight-diagnostics"))
+                        .on_hover_text(t!("settings-highlight-diagnostics-hint"));
+                    ui.checkbox(&mut self.config.appearance.output_sections, t!("settings-output-sections"))
+                        .on_hover_text(t!("settings-output-sections-hint"));
+                    ui.checkbox(&mut self.config.appearance.highlight_output, t!("settings-highlight-output"))
+                        .on_hover_text(t!("settings-highlight-output-hint"));
+                    ui.checkbox(&mut self.config.general.workspace_trust, t!("settings-workspace-trust"))
+                        .on_hover_text(t!("settings-workspace-trust-hint"));
+                    if self.config.general.workspace_trust && !self.config.general.trusted_directories.is_empty() {
+                        ui.collapsing(t!("settings-trusted-directories"), |ui| {
+                            let mut untrust = None;
+                            for (index, dir) in self.config.general.trusted_directories.iter().enumerate() {
+                                ui.horizontal(|ui| {
+                                    if ui.small_button("✖").on_hover_text("Stop trusting").clicked() {
+                                        untrust = Some(index);
+                                    }
+                                    ui.label(RichText::new(abbreviate_home(dir)).monospace());
+                                });
                             }
-                            
-                            // Also close on Escape key
-                            if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
-                                self.context_menu_block = None;
-                                self.context_menu_pos = None;
-                                self.context_menu_opened_at = None;
+                            if let Some(index) = untrust {
+                                self.config.general.trusted_directories.remove(index);
                             }
+                        });
+                    }
+                    ui.separator();
+
+                    ui.label(RichText::new(t!("settings-storage")).strong());
+                    egui::Grid::new("storage_settings")
+                        .num_columns(2)
+                        .spacing([12.0, 6.0])
+                        .show(ui, |ui| {
+                            ui.label(t!("settings-max-block-age"));
+                            ui.add(
+                                egui::DragValue::new(&mut self.config.storage.max_block_age_days)
+                                    .range(0..=3650)
+                                    .suffix(t!("settings-days")),
+                            )
+                            .on_hover_text(t!("settings-max-block-age-hint"));
+                            ui.end_row();
+
+                            ui.label(t!("settings-max-blocks"));
+                            ui.add(
+                                egui::DragValue::new(&mut self.config.storage.max_blocks_per_session)
+                                    .range(0..=100_000),
+                            )
+                            .on_hover_text(t!("settings-no-limit"));
+                            ui.end_row();
+
+                            ui.label(t!("settings-backup-interval"));
+                            ui.add(
+                                egui::DragValue::new(&mut self.config.storage.backup_interval_hours)
+                                    .range(0..=720)
+                                    .suffix(t!("settings-hours")),
+                            )
+                            .on_hover_text(t!("settings-backup-disabled"));
+                            ui.end_row();
+
+                            ui.label(t!("settings-backups-kept"));
+                            ui.add(
+                                egui::DragValue::new(&mut self.config.storage.backups_to_keep)
+                                    .range(1..=100),
+                            );
+                            ui.end_row();
+                        });
+                    ui.checkbox(
+                        &mut self.config.storage.archive_removed_blocks,
+                        t!("settings-archive"),
+                    );
+                    ui.label(
+                        RichText::new(t!("settings-retention-note"))
+                            .weak(),
+                    );
+
+                    ui.add_space(4.0);
+                    ui.checkbox(
+                        &mut self.config.storage.encrypt_history,
+                        t!("settings-encrypt"),
+                    )
+                    .on_hover_text(t!("settings-encrypt-hint"));
+                    ui.checkbox(
+                        &mut self.config.storage.snapshot_environment,
+                        t!("settings-environment-snapshot"),
+                    )
+                    .on_hover_text(t!(
+                        "settings-environment-snapshot-hint",
+                        variables = self.config.storage.snapshot_variables.join(", ")
+                    ));
+                    let encrypting = self.session_manager.as_ref().is_some_and(|sm| sm.encrypts());
+                    if encrypting {
+                        ui.label(RichText::new(t!("settings-encrypted")).weak());
+                    } else if self.config.storage.encrypt_history {
+                        ui.label(
+                            RichText::new(t!("settings-after-restart"))
+                                .weak(),
+                        );
+                    }
+
+                    ui.add_space(4.0);
+                    ui.label(t!(
+                        "settings-database-size",
+                        size = self.database_size.map(format_size).unwrap_or_else(|| t!("settings-unknown"))
+                    ));
+
+                    ui.horizontal(|ui| {
+                        if ui.button(t!("button-save")).clicked() {
+                            self.storage_status = Some(match self.config.save() {
+                                Ok(()) => t!("settings-saved").to_string(),
+                                Err(e) => t!("settings-save-failed", error = e.to_string()),
+                            });
+                        }
+                        let idle = self.current_block_id.is_none() && !self.storage_busy;
+                        if ui.add_enabled(idle, egui::Button::new(t!("settings-apply-now")))
+                            .on_disabled_hover_text(t!("settings-wait-for-command"))
+                            .clicked()
+                        {
+                            self.apply_retention_now(ctx);
+                        }
+                        if ui.add_enabled(!self.storage_busy, egui::Button::new(t!("settings-compact")))
+                            .on_hover_text(t!("settings-compact-hint"))
+                            .clicked()
+                        {
+                            self.compact_database(ctx);
                         }
                     });
-                }
-            }
-        });
 
-        // Status bar
-        TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                ui.label(format!("Session: {}", self.session.name));
-                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                    ui.label(format!("v{}", env!("CARGO_PKG_VERSION")));
-                    ui.separator();
-                    ui.label(format!("{} blocks", self.block_manager.count()));
+                    if let Some(status) = &self.storage_status {
+                        ui.label(RichText::new(status).weak());
+                    }
                 });
-            });
-        });
+            if !open {
+                self.show_settings = false;
+            }
+        }
 
-        // Session list dialog
-        if self.show_session_list {
-            egui::Window::new("📂 Open Session")
+        // Session settings dialog
+        if self.show_session_settings {
+            let mut open = true;
+            egui::Window::new(format!("⚙ Session Settings: {}", self.session.name))
+                .id(egui::Id::new("session_settings_window"))
+                .open(&mut open)
                 .collapsible(false)
                 .resizable(true)
                 .default_width(500.0)
                 .show(ctx, |ui| {
-                    ui.label("Select a session to open:");
+                    ui.label(RichText::new(t!("session-description")).strong());
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.description_draft)
+                            .hint_text(t!("session-description-hint"))
+                            .desired_width(f32::INFINITY),
+                    );
+                    ui.horizontal(|ui| {
+                        ui.label(t!("session-color"));
+                        if ui.selectable_label(self.color_draft.is_none(), t!("session-no-color")).clicked() {
+                            self.color_draft = None;
+                        }
+                        for color in SessionColor::ALL {
+                            let selected = self.color_draft == Some(color);
+                            let swatch = RichText::new(if selected { "◉" } else { "●" })
+                                .color(session_color(color));
+                            if ui.selectable_label(selected, swatch).on_hover_text(t!(&format!("session-color-{}", color.name()))).clicked() {
+                                self.color_draft = Some(color);
+                            }
+                        }
+                    });
+
                     ui.separator();
-                    
-                    ScrollArea::vertical()
-                        .max_height(300.0)
-                        .show(ui, |ui| {
-                            for session_info in &self.available_sessions.clone() {
-                                ui.horizontal(|ui| {
-                                    let is_current = session_info.id == self.session.id;
-                                    let label = if is_current {
-                                        format!("▶ {} (current)", session_info.name)
-                                    } else if session_info.is_active {
-                                        format!("● {}", session_info.name)
-                                    } else {
-                                        session_info.name.clone()
-                                    };
-                                    
-                                    if ui.selectable_label(is_current, label).clicked() && !is_current {
-                                        self.switch_to_session(session_info.id);
-                                        self.show_session_list = false;
+                    ui.label(RichText::new("Startup commands").strong());
+                    ui.label(
+                        RichText::new("Run in order as blocks whenever this session is opened, one per line.")
+                            .weak()
+                    );
+                    ui.add(
+                        egui::TextEdit::multiline(&mut self.startup_commands_draft)
+                            .font(egui::TextStyle::Monospace)
+                            .hint_text("kubectl config use-context staging")
+                            .desired_rows(4)
+                            .desired_width(f32::INFINITY),
+                    );
+
+                    ui.separator();
+                    ui.label(RichText::new(t!("session-env-file")).strong());
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.env_file_draft)
+                                .hint_text("/path/to/project/.env")
+                                .desired_width(300.0),
+                        );
+                        if ui.button(t!("session-env-file-load")).clicked() && !self.env_file_draft.trim().is_empty() {
+                            let path = PathBuf::from(self.env_file_draft.trim());
+                            let path = if path.is_relative() {
+                                self.session.working_directory.join(path)
+                            } else {
+                                path
+                            };
+                            self.load_env_file(path);
+                        }
+                        if self.session.env_file.is_some() && ui.button(t!("session-env-file-unload")).clicked() {
+                            self.unload_env_file();
+                        }
+                    });
+                    if let Some(ref error) = self.env_file_error {
+                        ui.colored_label(Color32::from_rgb(220, 60, 80), error);
+                    }
+
+                    if !self.session.environment.is_empty() {
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new(t!("session-environment")).strong());
+                            ui.checkbox(&mut self.reveal_env_values, t!("session-show-values"));
+                        });
+                        let mut vars: Vec<_> = self.session.environment.iter().collect();
+                        vars.sort();
+                        ScrollArea::vertical()
+                            .id_source("session_env_vars")
+                            .max_height(150.0)
+                            .show(ui, |ui| {
+                                egui::Grid::new("session_env_grid").striped(true).show(ui, |ui| {
+                                    for (key, value) in vars {
+                                        ui.monospace(key);
+                                        let shown = if self.reveal_env_values {
+                                            value.clone()
+                                        } else {
+                                            crate::utils::dotenv::mask(value)
+                                        };
+                                        ui.monospace(shown);
+                                        ui.end_row();
                                     }
-                                    
-                                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                                        ui.label(format!("Updated: {}", 
-                                            session_info.updated_at.format("%Y-%m-%d %H:%M")));
-                                    });
                                 });
-                                ui.separator();
-                            }
-                        });
-                    
+                            });
+                    }
+
                     ui.separator();
-                    if ui.button("❌ Close").clicked() {
-                        self.show_session_list = false;
+                    ui.label(RichText::new(t!("trigger-title")).strong());
+                    ui.label(
+                        RichText::new(t!("trigger-hint"))
+                            .weak()
+                    );
+                    let mut remove = None;
+                    egui::Grid::new("session_triggers_grid").num_columns(5).show(ui, |ui| {
+                        for (i, trigger) in self.triggers_draft.iter_mut().enumerate() {
+                            ui.checkbox(&mut trigger.enabled, "");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut trigger.pattern)
+                                    .font(egui::TextStyle::Monospace)
+                                    .hint_text(t!("trigger-pattern-hint"))
+                                    .desired_width(160.0),
+                            );
+                            egui::ComboBox::from_id_source(("trigger_action", i))
+                                .selected_text(trigger.action.label())
+                                .show_ui(ui, |ui| {
+                                    let actions = [
+                                        TriggerAction::Notify,
+                                        TriggerAction::HighlightLine,
+                                        TriggerAction::RunCommand(String::new()),
+                                        TriggerAction::MarkBlock,
+                                    ];
+                                    for action in actions {
+                                        let selected = std::mem::discriminant(&trigger.action)
+                                            == std::mem::discriminant(&action);
+                                        if ui.selectable_label(selected, action.label()).clicked() && !selected {
+                                            trigger.action = action;
+                                        }
+                                    }
+                                });
+                            if let TriggerAction::RunCommand(ref mut command) = trigger.action {
+                                ui.add(
+                                    egui::TextEdit::singleline(command)
+                                        .font(egui::TextStyle::Monospace)
+                                        .hint_text(t!("trigger-command-hint"))
+                                        .desired_width(140.0),
+                                );
+                            } else {
+                                ui.label("");
+                            }
+                            if icon_button(ui, "🗑", &t!("button-remove-trigger")).clicked() {
+                                remove = Some(i);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    if let Some(i) = remove {
+                        self.triggers_draft.remove(i);
+                    }
+                    if ui.button(t!("trigger-add")).clicked() {
+                        self.triggers_draft.push(Trigger::new("", TriggerAction::Notify));
                     }
+                    if let Some(ref error) = self.triggers_error {
+                        ui.colored_label(Color32::from_rgb(220, 60, 80), error);
+                    }
+
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        if ui.button("💾 Save").clicked() && self.save_triggers() {
+                            self.save_session_details();
+                            self.save_startup_commands();
+                            self.show_session_settings = false;
+                        }
+                        if ui.button("▶ Save & Run Now").clicked() && self.save_triggers() {
+                            self.save_session_details();
+                            self.save_startup_commands();
+                            self.queue_startup_commands();
+                            self.show_session_settings = false;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.show_session_settings = false;
+                        }
+                    });
                 });
+            if !open {
+                self.show_session_settings = false;
+            }
         }
 
         // Generating command indicator (small corner indicator)
-        if self.is_generating_command {
+        if self.ai_requests.is_running(AiFeature::CommandGeneration) {
             egui::Area::new(egui::Id::new("generating_indicator"))
                 .anchor(egui::Align2::RIGHT_BOTTOM, [-10.0, -10.0])
                 .show(ctx, |ui| {
@@ -1286,10 +7423,12 @@ impl eframe::App for ImmateriumApp {
                         .show(ui, |ui| {
                             ui.horizontal(|ui| {
                                 ui.spinner();
-                                ui.label(
-                                    egui::RichText::new("🤖 Generating command...")
-                                        .color(egui::Color32::from_rgb(255, 165, 0))
-                                );
+                                let text = if self.ai_panel.waiting() > 0 {
+                                    t!("status-waiting-rate-limit")
+                                } else {
+                                    t!("status-generating-command")
+                                };
+                                ui.label(egui::RichText::new(text).color(egui::Color32::from_rgb(255, 165, 0)));
                             });
                         });
                 });
@@ -1297,39 +7436,50 @@ impl eframe::App for ImmateriumApp {
 
         // New session dialog
         if self.show_new_session_dialog {
-            egui::Window::new("✨ New Session")
+            egui::Window::new(t!("dialog-new-session"))
+                .id(egui::Id::new("new_session_window"))
                 .collapsible(false)
                 .resizable(false)
                 .show(ctx, |ui| {
-                    ui.label("Enter a name for the new session:");
+                    ui.label(t!("new-session-prompt"));
                     
                     let response = ui.add(
                         egui::TextEdit::singleline(&mut self.new_session_name)
-                            .hint_text("Session name...")
+                            .hint_text(t!("new-session-name-hint"))
                             .desired_width(300.0)
                     );
+
+                    let name = self.new_session_name.trim().to_string();
+                    let taken = session_name_taken(&name, &self.available_sessions, None);
+                    if taken {
+                        let suggestion = unique_session_name(&name, &self.available_sessions);
+                        ui.horizontal(|ui| {
+                            ui.label(
+                                RichText::new(t!("new-session-name-taken", name = name.clone()))
+                                    .color(Color32::from_rgb(255, 165, 0)),
+                            );
+                            if ui.small_button(t!("new-session-use-name", name = suggestion.clone())).clicked() {
+                                self.new_session_name = suggestion;
+                            }
+                        });
+                    }
+                    let valid = !name.is_empty() && !taken;
                     
-                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                        if !self.new_session_name.trim().is_empty() {
-                            let name = self.new_session_name.trim().to_string();
-                            self.create_new_session(name);
-                            self.new_session_name.clear();
-                            self.show_new_session_dialog = false;
-                        }
+                    if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) && valid {
+                        self.create_new_session(name.clone(), ctx);
+                        self.new_session_name.clear();
+                        self.show_new_session_dialog = false;
                     }
                     
                     ui.separator();
                     ui.horizontal(|ui| {
-                        if ui.button("✅ Create").clicked() {
-                            if !self.new_session_name.trim().is_empty() {
-                                let name = self.new_session_name.trim().to_string();
-                                self.create_new_session(name);
-                                self.new_session_name.clear();
-                                self.show_new_session_dialog = false;
-                            }
+                        if ui.add_enabled(valid, egui::Button::new(t!("new-session-create"))).clicked() {
+                            self.create_new_session(name.clone(), ctx);
+                            self.new_session_name.clear();
+                            self.show_new_session_dialog = false;
                         }
                         
-                        if ui.button("❌ Cancel").clicked() {
+                        if ui.button(t!("button-cancel-dialog")).clicked() {
                             self.new_session_name.clear();
                             self.show_new_session_dialog = false;
                         }
@@ -1339,17 +7489,18 @@ impl eframe::App for ImmateriumApp {
 
         // Export dialog
         if self.show_export_dialog {
-            egui::Window::new("📤 Export Session")
+            egui::Window::new(t!("dialog-export-session"))
+                .id(egui::Id::new("export_session_window"))
                 .collapsible(false)
                 .resizable(false)
                 .show(ctx, |ui| {
-                    ui.label(format!("Export session: {}", self.session.name));
+                    ui.label(t!("export-session-name", name = self.session.name.clone()));
                     ui.separator();
                     
-                    ui.label("Choose export format:");
+                    ui.label(t!("export-choose-format"));
                     ui.add_space(10.0);
                     
-                    if ui.button("📄 Export as JSON").clicked() {
+                    if ui.button(t!("export-json")).clicked() {
                         let filename = format!("{}.json", self.session.name.replace(' ', "_"));
                         let exported = ExportedSession::new(self.session.clone());
                         match exported.to_json_file(&filename) {
@@ -1359,7 +7510,7 @@ impl eframe::App for ImmateriumApp {
                         self.show_export_dialog = false;
                     }
                     
-                    if ui.button("📝 Export as Markdown").clicked() {
+                    if ui.button(t!("export-markdown")).clicked() {
                         let filename = format!("{}.md", self.session.name.replace(' ', "_"));
                         let exported = ExportedSession::new(self.session.clone());
                         match exported.to_markdown_file(&filename) {
@@ -1369,7 +7520,7 @@ impl eframe::App for ImmateriumApp {
                         self.show_export_dialog = false;
                     }
                     
-                    if ui.button("📋 Export as Text").clicked() {
+                    if ui.button(t!("export-text")).clicked() {
                         let filename = format!("{}.txt", self.session.name.replace(' ', "_"));
                         let exported = ExportedSession::new(self.session.clone());
                         match exported.to_text_file(&filename) {
@@ -1380,15 +7531,370 @@ impl eframe::App for ImmateriumApp {
                     }
                     
                     ui.separator();
-                    if ui.button("❌ Cancel").clicked() {
+                    ui.label(t!("export-encrypted-intro"));
+                    egui::Grid::new("export_passphrase").num_columns(2).show(ui, |ui| {
+                        ui.label(t!("export-passphrase"));
+                        ui.add(egui::TextEdit::singleline(&mut self.export_passphrase).password(true));
+                        ui.end_row();
+                        ui.label(t!("export-passphrase-confirm"));
+                        ui.add(egui::TextEdit::singleline(&mut self.export_passphrase_confirm).password(true));
+                        ui.end_row();
+                    });
+                    let matches = self.export_passphrase == self.export_passphrase_confirm;
+                    if !matches && !self.export_passphrase_confirm.is_empty() {
+                        ui.label(RichText::new(t!("export-passphrase-mismatch")).color(Color32::from_rgb(220, 80, 80)));
+                    }
+                    let ready = matches && !self.export_passphrase.is_empty();
+                    if ui.add_enabled(ready, egui::Button::new(t!("export-encrypted"))).clicked() {
+                        let filename = format!("{}.session.enc", self.session.name.replace(' ', "_"));
+                        let exported = ExportedSession::new(self.session.clone());
+                        match exported.to_encrypted_file(&filename, &self.export_passphrase) {
+                            Ok(_) => tracing::info!("Exported encrypted session to {}", filename),
+                            Err(e) => tracing::error!("Failed to export: {}", e),
+                        }
+                        self.show_export_dialog = false;
+                    }
+
+                    ui.separator();
+                    if ui.button(t!("button-cancel-dialog")).clicked() {
                         self.show_export_dialog = false;
                     }
                 });
+            if !self.show_export_dialog {
+                self.export_passphrase.clear();
+                self.export_passphrase_confirm.clear();
+            }
+        }
+
+        // Import dialog
+        if self.show_import_dialog {
+            let mut open = true;
+            egui::Window::new(t!("dialog-import-session"))
+                .id(egui::Id::new("import_session_window"))
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(t!("import-intro"));
+                    egui::Grid::new("import_session").num_columns(2).show(ui, |ui| {
+                        ui.label(t!("import-file"));
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.import_path)
+                                .hint_text("~/Downloads/prod.session.enc")
+                                .desired_width(300.0),
+                        );
+                        ui.end_row();
+                        ui.label(t!("import-passphrase"));
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.import_passphrase)
+                                .password(true)
+                                .hint_text(t!("import-passphrase-hint")),
+                        );
+                        ui.end_row();
+                    });
+
+                    if ui.add_enabled(!self.import_path.trim().is_empty(), egui::Button::new(t!("import-button"))).clicked() {
+                        let path = crate::utils::expand_tilde(self.import_path.trim());
+                        match ExportedSession::from_file(&path, &self.import_passphrase) {
+                            Ok(exported) => {
+                                self.import_passphrase.clear();
+                                self.import_session(exported.session, ctx);
+                            }
+                            Err(e) => self.import_status = Some(t!("import-failed", error = format!("{:#}", e))),
+                        }
+                    }
+                    if let Some(status) = &self.import_status {
+                        ui.label(RichText::new(status).weak());
+                    }
+                });
+            if !open {
+                self.show_import_dialog = false;
+                self.import_passphrase.clear();
+            }
+        }
+
+        // Share dialog: review the redacted export, then upload
+        if self.share_draft.is_some() {
+            let targets = ShareTarget::available(&self.config.share);
+            let uploading = self.share_receiver.is_some();
+            let offline = self.config.general.offline;
+            let mut open = true;
+            let mut upload = None;
+            egui::Window::new(t!("dialog-share"))
+                .id(egui::Id::new("share_window"))
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(true)
+                .default_width(560.0)
+                .show(ctx, |ui| {
+                    let Some(draft) = &mut self.share_draft else {
+                        return;
+                    };
+                    ui.label(RichText::new(&draft.title).strong());
+                    let note = match draft.redactions {
+                        0 => t!("share-no-secrets"),
+                        n => t!("share-secrets-replaced", count = n, marker = share::redact::REDACTED),
+                    };
+                    ui.label(RichText::new(note).color(Color32::from_rgb(255, 165, 0)));
+
+                    ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                        ui.add(
+                            egui::TextEdit::multiline(&mut draft.content)
+                                .font(egui::TextStyle::Monospace)
+                                .desired_width(f32::INFINITY),
+                        );
+                    });
+
+                    if targets.is_empty() {
+                        ui.label(
+                            RichText::new(t!("share-not-configured"))
+                                .weak(),
+                        );
+                    } else {
+                        ui.horizontal(|ui| {
+                            draft.target = draft.target.min(targets.len() - 1);
+                            egui::ComboBox::from_id_source("share_target")
+                                .selected_text(targets[draft.target].label())
+                                .show_ui(ui, |ui| {
+                                    for (index, target) in targets.iter().enumerate() {
+                                        ui.selectable_value(&mut draft.target, index, target.label());
+                                    }
+                                });
+                            if ui.add_enabled(!uploading && !offline, egui::Button::new(t!("button-upload")))
+                                .on_disabled_hover_text(t!("menu-sync-offline"))
+                                .clicked()
+                            {
+                                upload = Some(targets[draft.target].clone());
+                            }
+                        });
+                    }
+                    if let Some(status) = &draft.status {
+                        ui.label(RichText::new(status).weak());
+                    }
+                });
+            if let Some(target) = upload {
+                self.upload_share(target, ctx);
+            }
+            if !open {
+                self.share_draft = None;
+            }
+        }
+
+        self.log_viewer.show(ctx);
+
+        if let Some(rx) = &mut self.ai_audit_receiver {
+            if let Ok(entries) = rx.try_recv() {
+                self.ai_audit_view.set_entries(entries);
+                self.ai_audit_receiver = None;
+            }
+        }
+        if self.ai_audit_view.show(ctx, self.config.ai.audit.enabled) {
+            self.load_ai_audit(ctx);
+        }
+
+        if self.show_ai_diagnostics {
+            let action = ai_diagnostics::show(ctx, &mut self.show_ai_diagnostics, self.ai_engine.as_deref());
+            if let (Some(action), Some(engine)) = (action, self.ai_engine.clone()) {
+                let ctx_clone = ctx.clone();
+                self.runtime.spawn(async move {
+                    match action {
+                        DiagnosticsAction::Check(name) => engine.check_provider(&name).await,
+                        DiagnosticsAction::CheckAll => engine.refresh_health().await,
+                    }
+                    ctx_clone.request_repaint();
+                });
+            }
+        }
+
+        // Report of a crash in an earlier run, shown once
+        if let Some(report) = self.crash_reports.first().cloned() {
+            let mut dismissed = false;
+            egui::Window::new(t!("dialog-crash-report"))
+                .id(egui::Id::new("crash_report_window"))
+                .collapsible(false)
+                .default_width(560.0)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label(t!("crash-report-intro"));
+                    ui.label(RichText::new(report.path.display().to_string()).monospace().weak());
+                    ui.add_space(4.0);
+                    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                        ui.label(RichText::new(&report.contents).monospace().size(11.0));
+                    });
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        if ui.button(t!("crash-report-copy")).clicked() {
+                            ctx.output_mut(|o| o.copied_text = report.contents.clone());
+                        }
+                        if ui.button(t!("crash-report-dismiss")).clicked() {
+                            dismissed = true;
+                        }
+                        if self.crash_reports.len() > 1 {
+                            ui.label(RichText::new(t!("crash-report-more", count = self.crash_reports.len() - 1)).weak());
+                        }
+                    });
+                });
+            if dismissed {
+                if let Err(e) = crash::mark_seen(&report) {
+                    tracing::warn!("{:#}", e);
+                }
+                self.crash_reports.remove(0);
+            }
+        }
+
+        // Restore from backup dialog
+        if self.show_restore_dialog {
+            let mut open = true;
+            egui::Window::new(t!("dialog-restore-backup"))
+                .id(egui::Id::new("restore_backup_window"))
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(false)
+                .default_width(420.0)
+                .show(ctx, |ui| {
+                    if self.backups.is_empty() {
+                        ui.label(RichText::new("No backups yet.").weak());
+                    }
+                    egui::Grid::new("backups").num_columns(3).striped(true).show(ui, |ui| {
+                        for backup in &self.backups {
+                            ui.label(backup.created.format("%Y-%m-%d %H:%M").to_string())
+                                .on_hover_text(backup.path.display().to_string());
+                            ui.label(format_size(backup.size));
+                            let selected = self.restore_candidate.as_ref() == Some(&backup.path);
+                            if ui.selectable_label(selected, "Restore...").clicked() {
+                                self.restore_candidate = Some(backup.path.clone());
+                            }
+                            ui.end_row();
+                        }
+                    });
+
+                    if let Some(candidate) = self.restore_candidate.clone() {
+                        ui.separator();
+                        ui.label(
+                            RichText::new("Replace the database with this backup? Changes made since it was taken are set aside.")
+                                .color(Color32::from_rgb(255, 165, 0)),
+                        );
+                        ui.horizontal(|ui| {
+                            let idle = self.current_block_id.is_none() && !self.backup_busy;
+                            if ui.add_enabled(idle, egui::Button::new("Restore"))
+                                .on_disabled_hover_text("Wait for the running command to finish")
+                                .clicked()
+                            {
+                                self.restore_candidate = None;
+                                self.restore_from_backup(candidate, ctx);
+                            }
+                            if ui.button("Cancel").clicked() {
+                                self.restore_candidate = None;
+                            }
+                        });
+                    }
+
+                    ui.separator();
+                    if ui.add_enabled(!self.backup_busy, egui::Button::new("💾 Back Up Now")).clicked() {
+                        self.back_up_now(ctx);
+                    }
+                    if let Some(status) = &self.backup_status {
+                        ui.label(RichText::new(status).weak());
+                    }
+                });
+            if !open {
+                self.show_restore_dialog = false;
+            }
+        }
+
+        // Export or import everything, for moving to another machine
+        if self.show_data_archive {
+            let mut open = true;
+            egui::Window::new(t!("dialog-data-archive"))
+                .id(egui::Id::new("data_archive_window"))
+                .open(&mut open)
+                .collapsible(false)
+                .resizable(false)
+                .default_width(440.0)
+                .show(ctx, |ui| {
+                    ui.label("All sessions with their blocks, and optionally the settings, in one file.");
+                    egui::Grid::new("data_archive").num_columns(2).show(ui, |ui| {
+                        ui.label("File");
+                        ui.add(egui::TextEdit::singleline(&mut self.archive_path).desired_width(300.0));
+                        ui.end_row();
+                        ui.label("Passphrase");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.archive_passphrase)
+                                .password(true)
+                                .hint_text("optional; encrypts the archive"),
+                        );
+                        ui.end_row();
+                        ui.label("Confirm");
+                        ui.add(egui::TextEdit::singleline(&mut self.archive_passphrase_confirm).password(true))
+                            .on_hover_text("Only needed when exporting");
+                        ui.end_row();
+                    });
+                    ui.checkbox(&mut self.archive_include_settings, "Include settings (config.toml)")
+                        .on_hover_text("The settings may hold API keys, so they are only exported encrypted");
+
+                    ui.separator();
+                    let matches = self.archive_passphrase == self.archive_passphrase_confirm;
+                    if !matches && !self.archive_passphrase_confirm.is_empty() {
+                        ui.label(RichText::new("Passphrases don't match").color(Color32::from_rgb(220, 80, 80)));
+                    }
+                    let encrypts_history = self.session_manager.as_ref().is_some_and(|m| m.encrypts());
+                    let needs_passphrase = self.archive_include_settings || encrypts_history;
+                    let has_passphrase = !self.archive_passphrase.is_empty() || !needs_passphrase;
+                    if !has_passphrase {
+                        ui.label(
+                            RichText::new("A passphrase is needed to export settings or encrypted history")
+                                .color(Color32::from_rgb(255, 165, 0)),
+                        );
+                    }
+                    let has_path = !self.archive_path.trim().is_empty();
+                    if ui
+                        .add_enabled(
+                            has_path && matches && has_passphrase && !self.archive_busy,
+                            egui::Button::new("📤 Export All Data"),
+                        )
+                        .clicked()
+                    {
+                        self.export_all_data(ctx);
+                    }
+
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("Import:");
+                        ui.radio_value(&mut self.archive_restore_mode, RestoreMode::Merge, "Merge")
+                            .on_hover_text("Keep the sessions here; add the archived ones and take whichever copy changed last");
+                        ui.radio_value(&mut self.archive_restore_mode, RestoreMode::Replace, "Replace")
+                            .on_hover_text("Delete the sessions here first");
+                    });
+                    if self.archive_restore_mode == RestoreMode::Replace {
+                        ui.label(
+                            RichText::new("Sessions not in the archive will be deleted.")
+                                .color(Color32::from_rgb(255, 165, 0)),
+                        );
+                    }
+                    let idle = self.current_block_id.is_none() && !self.archive_busy;
+                    if ui.add_enabled(has_path && idle, egui::Button::new("📥 Import All Data"))
+                        .on_disabled_hover_text("Needs a file, and no command running")
+                        .clicked()
+                    {
+                        self.import_all_data(ctx);
+                    }
+
+                    if let Some(status) = &self.archive_status {
+                        ui.separator();
+                        ui.label(RichText::new(status).weak());
+                    }
+                });
+            if !open {
+                self.show_data_archive = false;
+                self.archive_passphrase.clear();
+                self.archive_passphrase_confirm.clear();
+            }
         }
 
         // Theme selector dialog
         if self.show_theme_selector {
-            egui::Window::new("🎨 Select Theme")
+            egui::Window::new(t!("dialog-select-theme"))
+                .id(egui::Id::new("select_theme_window"))
                 .collapsible(false)
                 .resizable(false)
                 .show(ctx, |ui| {
@@ -1405,6 +7911,8 @@ impl eframe::App for ImmateriumApp {
                                 tracing::error!("Failed to switch theme: {}", e);
                             } else {
                                 self.theme_loader.apply_to_egui(ctx);
+                                // Output was highlighted with the old theme's colors
+                                self.output_highlights.clear();
                                 tracing::info!("Switched to theme: {}", theme_name);
                             }
                             self.show_theme_selector = false;
@@ -1417,12 +7925,14 @@ impl eframe::App for ImmateriumApp {
                     }
                 });
         }
-    }
 
-    fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        // Save window state
-        if let Ok(config_json) = serde_json::to_string(&self.config) {
-            storage.set_string("config", config_json);
+        crate::ui::accessibility::paint_focus_ring(ctx);
+
+        if self.show_debug_overlay {
+            self.debug_stats.show(ctx);
+            self.debug_stats.record_frame(frame_start.elapsed());
+            // Keep the numbers moving while the overlay is open
+            ctx.request_repaint_after(Duration::from_millis(250));
         }
     }
 }
@@ -1430,6 +7940,63 @@ impl eframe::App for ImmateriumApp {
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn test_forced_input() {
+        assert_eq!(forced_input("# list big files", "#", "!"), Some(ForcedInput::Ai("list big files")));
+        assert_eq!(forced_input("!install ripgrep", "#", "!"), Some(ForcedInput::Shell("install ripgrep")));
+        assert_eq!(forced_input("check disk", "#", "!"), None);
+        // Disabled prefixes
+        assert_eq!(forced_input("# list big files", "", "!"), None);
+        // The longer of two overlapping prefixes wins
+        assert_eq!(forced_input("?? why", "??", "?"), Some(ForcedInput::Ai("why")));
+        assert_eq!(forced_input("? ls", "??", "?"), Some(ForcedInput::Shell("ls")));
+
+        assert_eq!(input_hint("", ""), "Enter a command or natural language request...");
+        assert_eq!(input_hint("#", ""), "Enter a command or natural language request (#… asks AI)");
+    }
+
+    #[test]
+    fn test_command_chat_request() {
+        use crate::ai::MessageRole;
+        let mut ai = AiConfig {
+            command_temperature: 0.4,
+            command_seed: Some(7),
+            command_examples: vec![crate::config::CommandExample {
+                request: "deploy to staging".to_string(),
+                command: "make deploy ENV=staging".to_string(),
+            }],
+            ..Default::default()
+        };
+        let request = command_chat_request(&ai, "codellama".to_string(), command_system_prompt(1));
+        assert_eq!(request.model, "codellama");
+        assert_eq!(request.temperature, Some(0.4));
+        assert_eq!(request.seed, Some(7));
+        let roles: Vec<_> = request.messages.iter().map(|m| m.role.clone()).collect();
+        assert_eq!(roles, [MessageRole::System, MessageRole::User, MessageRole::Assistant]);
+        assert_eq!(request.messages[1].content, command_request("deploy to staging"));
+
+        ai.command_temperature = 9.0;
+        ai.command_seed = None;
+        let request = command_chat_request(&ai, "codellama".to_string(), command_system_prompt(1));
+        assert_eq!(request.temperature, Some(2.0));
+        assert_eq!(request.seed, None);
+    }
+
+    #[test]
+    fn test_session_list_refresh() {
+        let mut list = SessionListState::default();
+        list.start();
+        assert!(list.loading);
+        assert!(list.finish(Err("database is locked".to_string())).is_none());
+        assert!(!list.loading);
+        assert_eq!(list.error.as_deref(), Some("database is locked"));
+
+        list.start();
+        assert_eq!(list.finish(Ok(Vec::new())).map(|sessions| sessions.len()), Some(0));
+        assert!(!list.loading);
+        assert!(list.error.is_none());
+    }
     
     mod context_menu {
         use std::time::{Duration, Instant};
@@ -0,0 +1,172 @@
+use crate::core::Block;
+use crate::syntax::LineColors;
+use crate::ui::toast::ToastSender;
+use crate::ui::BlockWidget;
+use egui::{ColorImage, Context, Event, Rect, ViewportCommand};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Width of the captured card in points
+const CARD_WIDTH: f32 = 760.0;
+
+/// Give up when the window system hasn't delivered the screenshot by then
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// "Copy as image" for a block: the card is painted on its own layer over
+/// the window, the window is screenshotted and the card cut out of it, so
+/// the picture doesn't depend on where the block is scrolled to. A card
+/// taller than the window is cut off at its edge, which the notice says.
+pub struct BlockCapture {
+    block: Block,
+    colors: Vec<LineColors>,
+    font_size: f32,
+    frames: u32,
+    rect: Option<Rect>,
+    truncated: bool,
+    started: Instant,
+    toasts: ToastSender,
+}
+
+impl BlockCapture {
+    pub fn new(mut block: Block, colors: Vec<LineColors>, font_size: f32, toasts: ToastSender) -> Self {
+        block.is_selected = false;
+        block.is_collapsed = false;
+        Self {
+            block,
+            colors,
+            font_size,
+            frames: 0,
+            rect: None,
+            truncated: false,
+            started: Instant::now(),
+            toasts,
+        }
+    }
+
+    /// Paint the card and take the screenshot; call every frame until it
+    /// returns true. The outcome is posted as a notice.
+    pub fn show(&mut self, ctx: &Context) -> bool {
+        let screenshot = ctx.input(|i| {
+            i.events.iter().find_map(|event| match event {
+                Event::Screenshot { image, .. } => Some(image.clone()),
+                _ => None,
+            })
+        });
+        if let (Some(image), Some(rect)) = (screenshot, self.rect) {
+            let card = image.region(&rect, Some(ctx.pixels_per_point()));
+            deliver(card, self.block.id, self.truncated, self.toasts.clone(), ctx.clone());
+            return true;
+        }
+        if self.started.elapsed() > CAPTURE_TIMEOUT {
+            let _ = self.toasts.send("🖼 Could not copy block as image: the window screenshot never arrived".to_string());
+            return true;
+        }
+
+        let origin = ctx.screen_rect().min + egui::vec2(16.0, 16.0);
+        let area = egui::Area::new(egui::Id::new(("block_capture", self.block.id)))
+            .order(egui::Order::Tooltip)
+            .fixed_pos(origin)
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::window(ui.style()).shadow(egui::epaint::Shadow::NONE).show(ui, |ui| {
+                    ui.set_width(CARD_WIDTH.min(ctx.screen_rect().width() - 64.0));
+                    BlockWidget::new(&self.block, self.font_size).syntax_colors(&self.colors).show(ui);
+                });
+            });
+
+        // A new area is laid out invisibly on its first frame
+        self.frames += 1;
+        if self.frames == 2 {
+            let (rect, truncated) = visible_part(area.response.rect, ctx.screen_rect());
+            self.rect = Some(rect);
+            self.truncated = truncated;
+            ctx.send_viewport_cmd(ViewportCommand::Screenshot);
+        }
+        ctx.request_repaint();
+        false
+    }
+}
+
+/// The part of the card inside the window, and whether any of it was cut off
+fn visible_part(card: Rect, screen: Rect) -> (Rect, bool) {
+    let visible = card.intersect(screen);
+    (visible, visible != card)
+}
+
+/// Put the card on the clipboard, or save it as a PNG off the UI thread
+/// when that fails
+fn deliver(card: ColorImage, block_id: Uuid, truncated: bool, toasts: ToastSender, ctx: Context) {
+    let note = if truncated { " (cut off at the window edge)" } else { "" };
+    if crate::ui::selection::copy_image(&card) {
+        let _ = toasts.send(format!("🖼 Copied block as image{}", note));
+        return;
+    }
+    std::thread::spawn(move || {
+        let status = match save_png(&card, block_id) {
+            Ok(path) => format!("🖼 Saved block image to {}{}", path.display(), note),
+            Err(e) => format!("🖼 Could not copy block as image: {}", e),
+        };
+        let _ = toasts.send(status);
+        ctx.request_repaint();
+    });
+}
+
+fn save_png(card: &ColorImage, block_id: Uuid) -> anyhow::Result<PathBuf> {
+    let dir = crate::config::Config::data_dir()?.join("images");
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("block-{}.png", block_id));
+    let [width, height] = card.size;
+    image::save_buffer(&path, card.as_raw(), width as u32, height as u32, image::ExtendedColorType::Rgba8)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{pos2, RawInput};
+    use std::path::PathBuf;
+    use std::sync::mpsc::{channel, Receiver};
+
+    fn capture() -> (BlockCapture, Receiver<String>) {
+        let block = Block::new("echo hi".to_string(), PathBuf::from("/tmp"));
+        let (toasts, notices) = channel();
+        (BlockCapture::new(block, Vec::new(), 14.0, toasts), notices)
+    }
+
+    #[test]
+    fn test_visible_part() {
+        let screen = Rect::from_min_max(pos2(0.0, 0.0), pos2(800.0, 600.0));
+        let small = Rect::from_min_max(pos2(16.0, 16.0), pos2(400.0, 300.0));
+        assert_eq!(visible_part(small, screen), (small, false));
+
+        let tall = Rect::from_min_max(pos2(16.0, 16.0), pos2(400.0, 2000.0));
+        let (rect, truncated) = visible_part(tall, screen);
+        assert!(truncated);
+        assert_eq!(rect.max.y, 600.0);
+    }
+
+    #[test]
+    fn test_waits_for_screenshot() {
+        let ctx = Context::default();
+        let (mut capture, notices) = capture();
+        for _ in 0..3 {
+            let mut done = true;
+            let _ = ctx.run(RawInput::default(), |ctx| done = capture.show(ctx));
+            assert!(!done);
+        }
+        assert!(capture.rect.is_some());
+        assert!(notices.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_gives_up_without_screenshot() {
+        let ctx = Context::default();
+        let (mut capture, notices) = capture();
+        capture.started = Instant::now() - CAPTURE_TIMEOUT - Duration::from_secs(1);
+        let mut done = false;
+        let _ = ctx.run(RawInput::default(), |ctx| done = capture.show(ctx));
+        assert!(done);
+        assert!(notices.try_recv().unwrap().contains("never arrived"));
+    }
+}
@@ -1,28 +1,145 @@
-use crate::core::{Block, BlockState};
+use crate::ai::CommandExplanation;
+use crate::core::block::format_duration;
+use crate::core::{Block, BlockState, HookPhase, HookResult, LineSeverity, OutputDiagnostic, OutputSection};
+use crate::shell::lint::{self, Finding, Severity};
+use crate::shell::exit_code::explain_exit_code;
+use crate::shell::osc;
+use crate::syntax::{LineColors, SyntaxHighlighter};
+use crate::t;
+use crate::theme::ColorScheme;
+use crate::ui::accessibility::{self, icon_button};
 use egui::{Color32, RichText, Ui};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::time::Duration;
 
 pub struct BlockWidget<'a> {
     block: &'a Block,
     font_size: f32,
+    watch_interval: Option<Duration>,
+    rendered_output: Option<&'a str>,
+    focus_approval: bool,
+    patterns: bool,
+    candidates: Option<&'a [String]>,
+    explanation: Option<&'a CommandExplanation>,
+    findings: Option<&'a [Finding]>,
+    retry: Option<RetryStatus>,
+    diagnostics: &'a [OutputDiagnostic],
+    sections: &'a [OutputSection],
+    syntax_colors: &'a [LineColors],
+    error_color: Color32,
+    warning_color: Color32,
+}
+
+/// Retry offered on a block that failed with a transient error
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RetryStatus {
+    Available { max_attempts: u32 },
+    /// An automatic retry is waiting for its backoff delay
+    Scheduled { attempt: u32, max_attempts: u32, remaining: Duration },
 }
 
 impl<'a> BlockWidget<'a> {
     pub fn new(block: &'a Block, font_size: f32) -> Self {
-        Self { block, font_size }
+        Self {
+            block,
+            font_size,
+            watch_interval: None,
+            rendered_output: None,
+            focus_approval: false,
+            patterns: false,
+            candidates: None,
+            explanation: None,
+            findings: None,
+            retry: None,
+            diagnostics: &[],
+            sections: &[],
+            syntax_colors: &[],
+            error_color: Color32::from_rgb(230, 80, 80),
+            warning_color: Color32::from_rgb(255, 165, 0),
+        }
+    }
+
+    /// Mark the block as being re-run on an interval (watch mode)
+    pub fn watching(mut self, interval: Option<Duration>) -> Self {
+        self.watch_interval = interval;
+        self
+    }
+
+    /// Show output as rendered by a plugin instead of the raw output
+    pub fn rendered(mut self, output: Option<&'a str>) -> Self {
+        self.rendered_output = output;
+        self
+    }
+
+    /// Move keyboard focus to the Execute button of a suggested command
+    pub fn focus_approval(mut self, focus: bool) -> Self {
+        self.focus_approval = focus;
+        self
+    }
+
+    /// Tell states apart by accent bar pattern instead of color
+    pub fn patterns(mut self, patterns: bool) -> Self {
+        self.patterns = patterns;
+        self
+    }
+
+    /// Alternative commands to offer on a suggested command
+    pub fn candidates(mut self, candidates: Option<&'a [String]>) -> Self {
+        self.candidates = candidates;
+        self
+    }
+
+    /// What the suggested command does, shown under the request behind it
+    pub fn explanation(mut self, explanation: Option<&'a CommandExplanation>) -> Self {
+        self.explanation = explanation;
+        self
+    }
+
+    /// Problems found checking the suggested command
+    pub fn findings(mut self, findings: Option<&'a [Finding]>) -> Self {
+        self.findings = findings;
+        self
+    }
+
+    /// Offer to re-run a command that failed with a transient error
+    pub fn retry(mut self, retry: Option<RetryStatus>) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Color the output's error and warning lines with the theme's colors,
+    /// and mark them beside the scrollbar
+    pub fn diagnostics(mut self, diagnostics: &'a [OutputDiagnostic], colors: &ColorScheme) -> Self {
+        self.diagnostics = diagnostics;
+        self.error_color = colors.ansi_red.to_egui();
+        self.warning_color = colors.ansi_yellow.to_egui();
+        self
+    }
+
+    /// Show the phases of a recognized build tool as collapsible sections
+    pub fn sections(mut self, sections: &'a [OutputSection]) -> Self {
+        self.sections = sections;
+        self
+    }
+
+    /// Color the output's lines by the language it was detected as
+    pub fn syntax_colors(mut self, colors: &'a [LineColors]) -> Self {
+        self.syntax_colors = colors;
+        self
     }
 
     pub fn show(self, ui: &mut Ui) -> BlockResponse {
+        if self.block.is_divider {
+            return self.show_divider(ui);
+        }
+        if self.block.is_calculation {
+            return self.show_calculation(ui);
+        }
         let mut response = BlockResponse::default();
 
         // Subtle left border color based on state
-        let block_color = match self.block.state {
-            BlockState::PendingApproval => Color32::from_rgb(255, 165, 0), // Orange
-            BlockState::Running => Color32::from_rgb(100, 149, 237), // Blue
-            BlockState::Completed => Color32::from_rgb(80, 200, 120), // Green
-            BlockState::Failed => Color32::from_rgb(220, 60, 80), // Red
-            BlockState::Editing => Color32::from_rgb(150, 150, 150), // Gray
-            BlockState::Cancelled => Color32::from_rgb(180, 140, 60), // Muted orange
-        };
+        let block_color = self.state_color();
 
         let bg_color = if self.block.is_selected {
             Color32::from_rgba_premultiplied(50, 50, 70, 15)
@@ -46,52 +163,122 @@ impl<'a> BlockWidget<'a> {
                         egui::vec2(3.0, ui.available_height()),
                         egui::Sense::hover()
                     );
-                    ui.painter().rect_filled(rect, 0.0, block_color);
+                    paint_state_bar(ui.painter(), rect, &self.block.state, block_color, self.patterns);
                     ui.add_space(8.0);
                     
                     ui.vertical(|ui| {
                         // Header with command and metadata
-                        ui.horizontal(|ui| {
+                        let header = ui.horizontal(|ui| {
                             // Collapse/expand button (subtle)
-                            let collapse_icon = if self.block.is_collapsed { "›" } else { "⌄" };
-                            if ui.small_button(collapse_icon).clicked() {
+                            let (collapse_icon, collapse_label) = if self.block.is_collapsed {
+                                ("›", t!("block-expand"))
+                            } else {
+                                ("⌄", t!("block-collapse"))
+                            };
+                            if icon_button(ui, collapse_icon, &collapse_label).clicked() {
                                 response.toggle_collapsed = true;
                             }
 
+                            if self.block.state == BlockState::Running {
+                                ui.add(egui::Spinner::new().size(self.font_size - 2.0));
+                            } else {
+                                ui.label(
+                                    RichText::new(accessibility::state_icon(&self.block.state))
+                                        .color(block_color)
+                                        .size(self.font_size - 2.0),
+                                );
+                            }
+
+                            if self.block.is_pinned {
+                                ui.label(RichText::new("📌").size(self.font_size - 3.0));
+                            }
+                            if self.block.in_ai_context {
+                                ui.label(RichText::new("🤖").size(self.font_size - 3.0))
+                                    .on_hover_text(t!("block-always-in-ai-context"));
+                            }
+                            if self.block.is_startup {
+                                startup_badge(ui, self.font_size);
+                            }
+                            for tag in &self.block.tags {
+                                tag_badge(ui, self.font_size, tag);
+                            }
+                            if !self.block.metadata.hooks.is_empty() {
+                                hooks_badge(ui, self.font_size, &self.block.metadata.hooks);
+                            }
+                            if !self.block.metadata.environment.is_empty() {
+                                environment_badge(ui, self.font_size, &self.block.metadata.environment);
+                            }
+                            if let Some(interval) = self.watch_interval {
+                                watch_badge(ui, self.font_size, interval);
+                                if icon_button(ui, "⏹", &t!("block-stop-watching-hint")).clicked() {
+                                    response.stop_watch = true;
+                                }
+                            }
+
                             // Command (no $ prefix for cleaner look)
-                            ui.label(
-                                RichText::new(self.block.get_display_command())
-                                    .font(egui::FontId::monospace(self.font_size))
-                                    .color(Color32::from_rgb(220, 220, 220)),
-                            );
+                            ui.label(SyntaxHighlighter::new().command_layout_job(
+                                &self.block.get_display_command(),
+                                egui::FontId::monospace(self.font_size),
+                                Color32::from_rgb(220, 220, 220),
+                            ));
 
                             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                                 // Context menu button (subtle)
-                                if ui.small_button("⋯").clicked() {
+                                if icon_button(ui, "⋯", &t!("block-actions")).clicked() {
                                     response.show_context_menu = true;
                                 }
 
-                                // Duration (more subtle)
-                                if !self.block.format_duration().is_empty() {
-                                    ui.label(
-                                        RichText::new(self.block.format_duration())
-                                            .color(Color32::from_rgb(120, 120, 120))
-                                            .size(self.font_size - 3.0),
-                                    );
+                                if let Some(duration) = self.block.metadata.duration {
+                                    duration_badge(ui, self.font_size, duration);
                                 }
 
                                 // Exit code (only show if non-zero)
                                 if let Some(code) = self.block.exit_code {
                                     if code != 0 {
+                                        let label = RichText::new(format!("exit {}", code))
+                                            .color(Color32::from_rgb(220, 60, 80))
+                                            .size(self.font_size - 2.0);
+                                        if exit_code_label(ui, label, code).clicked() {
+                                            response.explain_exit_code = true;
+                                        }
+                                    }
+                                }
+
+                                match self.retry {
+                                    Some(RetryStatus::Available { max_attempts }) => {
+                                        if retry_chip(ui, self.font_size, &format!("⏱ ×{}", max_attempts))
+                                            .on_hover_text(t!("block-retry-backoff-hint", count = max_attempts))
+                                            .clicked()
+                                        {
+                                            response.retry_with_backoff = true;
+                                        }
+                                        if retry_chip(ui, self.font_size, &t!("block-retry"))
+                                            .on_hover_text(t!("block-retry-hint"))
+                                            .clicked()
+                                        {
+                                            response.retry = true;
+                                        }
+                                    }
+                                    Some(RetryStatus::Scheduled { attempt, max_attempts, remaining }) => {
+                                        if icon_button(ui, "✕", &t!("block-stop-retrying")).clicked() {
+                                            response.cancel_retry = true;
+                                        }
                                         ui.label(
-                                            RichText::new(format!("exit {}", code))
-                                                .color(Color32::from_rgb(220, 60, 80))
-                                                .size(self.font_size - 2.0),
+                                            RichText::new(t!(
+                                                "block-retry-scheduled",
+                                                attempt = attempt.to_string(),
+                                                max = max_attempts.to_string(),
+                                                seconds = (remaining.as_secs() + 1).to_string()
+                                            ))
+                                            .color(Color32::from_rgb(240, 180, 90))
+                                            .size(self.font_size - 3.0),
                                         );
                                     }
+                                    None => {}
                                 }
                             });
                         });
+                        response.header_height = header.response.rect.height();
 
                         // For PendingApproval blocks, show the original NL input and approval buttons
                         if self.block.state == BlockState::PendingApproval {
@@ -104,42 +291,116 @@ impl<'a> BlockWidget<'a> {
                                         .size(self.font_size - 1.0),
                                 );
                             }
+                            if let Some(explanation) = self.explanation {
+                                explanation_label(ui, explanation, self.font_size);
+                            }
+                            for finding in self.findings.unwrap_or_default() {
+                                finding_label(ui, finding, self.font_size);
+                            }
+                            if let Some(provenance) = &self.block.metadata.ai_provenance {
+                                ui.label(
+                                    RichText::new(format!("via {} / {}", provenance.provider, provenance.model))
+                                        .color(Color32::from_rgb(110, 110, 110))
+                                        .size(self.font_size - 2.0),
+                                )
+                                .on_hover_ui(|ui| {
+                                    ui.label(RichText::new("Prompt sent").strong());
+                                    ui.label(RichText::new(&provenance.prompt).monospace());
+                                });
+                            }
                             
+                            if let Some(candidates) = self.candidates.filter(|c| c.len() > 1) {
+                                ui.add_space(4.0);
+                                for candidate in candidates {
+                                    let chosen = *candidate == self.block.command;
+                                    let label = RichText::new(candidate).monospace().size(self.font_size - 1.0);
+                                    if ui.radio(chosen, label).clicked() && !chosen {
+                                        response.select_candidate = Some(candidate.clone());
+                                    }
+                                }
+                            }
+
                             ui.add_space(6.0);
+                            // Shortcuts only apply while no text field is being typed in
+                            let shortcut = |key| !ui.ctx().wants_keyboard_input() && ui.input(|i| i.key_pressed(key));
+                            let (edit_key, regenerate_key, cancel_key) =
+                                (shortcut(egui::Key::E), shortcut(egui::Key::R), shortcut(egui::Key::Escape));
                             ui.horizontal(|ui| {
-                                ui.small_button(RichText::new("✓ Execute (Enter)").color(Color32::from_rgb(80, 200, 120)))
-                                    .clicked().then(|| response.approve_command = true);
+                                let execute = if lint::has_errors(self.findings.unwrap_or_default()) {
+                                    ui.small_button(RichText::new("⚠ Run anyway (Enter)").color(Color32::from_rgb(255, 165, 0)))
+                                } else {
+                                    ui.small_button(
+                                        RichText::new(t!("block-execute-suggestion")).color(Color32::from_rgb(80, 200, 120)),
+                                    )
+                                };
+                                let command = &self.block.command;
+                                execute.widget_info(|| {
+                                    egui::WidgetInfo::labeled(
+                                        egui::WidgetType::Button,
+                                        true,
+                                        t!("block-execute-suggestion-hint", command = command.clone()),
+                                    )
+                                });
+                                if self.focus_approval {
+                                    execute.request_focus();
+                                }
+                                if execute.clicked() {
+                                    response.approve_command = true;
+                                }
                                 
-                                if ui.small_button("✎ Edit").clicked() || ui.input(|i| i.key_pressed(egui::Key::E)) {
+                                if ui.small_button(t!("block-edit-suggestion")).clicked() || edit_key {
                                     response.edit_command = true;
                                 }
                                 
-                                if ui.small_button("↻ Regenerate").clicked() || ui.input(|i| i.key_pressed(egui::Key::R)) {
+                                // Held-back typed commands have no request to regenerate from
+                                if self.block.original_input.is_some()
+                                    && (ui.small_button(t!("block-regenerate-suggestion")).clicked() || regenerate_key)
+                                {
                                     response.regenerate_command = true;
                                 }
                                 
-                                if ui.small_button("✕ Cancel").clicked() || ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                                if ui.small_button(t!("block-cancel-suggestion")).clicked() || cancel_key {
                                     response.reject_command = true;
                                 }
                             });
                         }
 
+                        // Progress of a running command that rewrites its line (curl, pip...)
+                        if let Some(progress) = self.block.progress() {
+                            ui.add_space(4.0);
+                            ui.add(
+                                egui::ProgressBar::new(progress)
+                                    .desired_width(ui.available_width().min(400.0))
+                                    .show_percentage(),
+                            );
+                        }
+
                         // Output (if not collapsed)
                         if !self.block.is_collapsed && !self.block.output.is_empty() {
                             ui.add_space(4.0);
                             
-                            egui::ScrollArea::vertical()
+                            let jump_id = ui.id().with(("diagnostic_jump", self.block.id));
+                            let mut scroll = egui::ScrollArea::vertical()
                                 .id_source(format!("block_output_{}", self.block.id))
-                                .max_height(400.0)
-                                .show(ui, |ui| {
-                                    ui.add(
-                                        egui::Label::new(
-                                            RichText::new(&self.block.output)
-                                                .font(egui::FontId::monospace(self.font_size))
-                                                .color(Color32::from_rgb(200, 200, 200)),
-                                        )
-                                    );
-                                });
+                                .max_height(400.0);
+                            if let Some(offset) = ui.data_mut(|d| d.remove_temp::<f32>(jump_id)) {
+                                scroll = scroll.vertical_scroll_offset(offset);
+                            }
+                            let output = scroll.show(ui, |ui| {
+                                if let Some(rendered) = self.rendered_output {
+                                    ui.label(RichText::new(rendered).monospace().size(self.font_size))
+                                        .on_hover_text("Rendered by a plugin");
+                                } else if self.block.output.chunks().any(osc::has_hyperlink) {
+                                    self.show_linked_output(ui);
+                                } else if self.sections.len() > 1 {
+                                    self.show_sectioned_output(ui);
+                                } else {
+                                    ui.add(egui::Label::new(self.output_job()));
+                                }
+                            });
+                            if self.rendered_output.is_none() {
+                                self.diagnostic_map(ui, output.inner_rect, output.content_size.y, jump_id);
+                            }
                         }
 
                         // Metadata footer (only if expanded and completed)
@@ -183,8 +444,688 @@ impl<'a> BlockWidget<'a> {
             response.show_context_menu = true;
         }
 
+        let menu_pos = frame_response.response.rect.left_top() + egui::vec2(12.0, response.header_height);
+        self.handle_focus(ui, &interact_response, menu_pos, &mut response);
+
+        response.rect = Some(frame_response.response.rect);
+        response
+    }
+
+    /// A heading with a rule, separating phases of work; the same in both views
+    fn show_divider(self, ui: &mut Ui) -> BlockResponse {
+        let mut response = BlockResponse::default();
+        ui.add_space(8.0);
+        let row = ui.horizontal(|ui| {
+            ui.label(
+                RichText::new(&self.block.command)
+                    .strong()
+                    .size(self.font_size + 1.0)
+                    .color(Color32::from_rgb(180, 190, 220)),
+            );
+            ui.label(
+                RichText::new(self.block.timestamp.format("%H:%M").to_string())
+                    .size(self.font_size - 3.0)
+                    .color(Color32::from_rgb(110, 110, 110)),
+            );
+            let rect = ui.available_rect_before_wrap();
+            ui.painter().hline(
+                rect.left() + 4.0..=rect.right(),
+                rect.center().y,
+                egui::Stroke::new(1.0, Color32::from_rgb(80, 80, 100)),
+            );
+        });
+        ui.add_space(4.0);
+
+        let interact = ui.interact(row.response.rect, ui.id().with(self.block.id), egui::Sense::click());
+        interact.context_menu(|ui| {
+            if ui.button(t!("block-remove-divider")).clicked() {
+                response.remove_block = true;
+                ui.close_menu();
+            }
+        });
+        interact.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Label, true, t!("a11y-divider", title = self.block.command.clone())));
+        response.rect = Some(row.response.rect);
+        response
+    }
+
+    /// An expression and its result on one line; the same in both views
+    fn show_calculation(self, ui: &mut Ui) -> BlockResponse {
+        let mut response = BlockResponse::default();
+        let result = self.block.output.to_string();
+        let row = ui.horizontal(|ui| {
+            ui.label(RichText::new("🧮").size(self.font_size - 2.0));
+            ui.label(
+                RichText::new(&self.block.command)
+                    .monospace()
+                    .size(self.font_size)
+                    .color(Color32::from_rgb(170, 170, 170)),
+            );
+            ui.label(
+                RichText::new(format!("= {}", result))
+                    .monospace()
+                    .strong()
+                    .size(self.font_size)
+                    .color(Color32::from_rgb(120, 200, 170)),
+            );
+        });
+
+        let interact = ui
+            .interact(row.response.rect, ui.id().with(self.block.id), egui::Sense::click())
+            .on_hover_text(t!("block-copy-result-hint"));
+        if interact.clicked() {
+            ui.output_mut(|o| o.copied_text = result.clone());
+        }
+        interact.context_menu(|ui| {
+            if ui.button(t!("block-copy-result")).clicked() {
+                ui.output_mut(|o| o.copied_text = result.clone());
+                ui.close_menu();
+            }
+            if ui.button(t!("block-remove-calculation")).clicked() {
+                response.remove_block = true;
+                ui.close_menu();
+            }
+        });
+        interact.widget_info(|| {
+            egui::WidgetInfo::labeled(egui::WidgetType::Label, true, format!("{} = {}", self.block.command, result))
+        });
+        response.rect = Some(row.response.rect);
+        response
+    }
+
+    /// Name the block for screen readers and open its context menu at
+    /// `menu_pos` on Shift+F10 while it has keyboard focus
+    fn handle_focus(&self, ui: &Ui, interact: &egui::Response, menu_pos: egui::Pos2, response: &mut BlockResponse) {
+        interact.widget_info(|| {
+            egui::WidgetInfo::labeled(egui::WidgetType::Other, true, accessibility::block_description(self.block))
+        });
+        if interact.has_focus() && ui.input(|i| i.modifiers.shift && i.key_pressed(egui::Key::F10)) {
+            response.show_context_menu = true;
+            response.context_menu_pos = Some(menu_pos);
+        }
+    }
+
+    /// Single-row rendering for the log view: status icon, command, duration
+    /// and exit code. Clicking the row asks to expand it.
+    pub fn show_compact(self, ui: &mut Ui) -> BlockResponse {
+        if self.block.is_divider {
+            return self.show_divider(ui);
+        }
+        if self.block.is_calculation {
+            return self.show_calculation(ui);
+        }
+        let mut response = BlockResponse::default();
+
+        let row = ui.horizontal(|ui| {
+            ui.set_min_height(self.font_size + 6.0);
+            if self.block.state == BlockState::Running {
+                ui.add(egui::Spinner::new().size(self.font_size));
+            } else {
+                ui.label(
+                    RichText::new(accessibility::state_icon(&self.block.state))
+                        .color(self.state_color())
+                        .size(self.font_size),
+                );
+            }
+            ui.add_space(4.0);
+
+            ui.label(
+                RichText::new(self.block.timestamp.format("%H:%M:%S").to_string())
+                    .size(self.font_size - 3.0)
+                    .color(Color32::from_rgb(110, 110, 110)),
+            );
+            ui.add_space(4.0);
+
+            if self.block.is_pinned {
+                ui.label(RichText::new("📌").size(self.font_size - 3.0));
+            }
+            if self.block.in_ai_context {
+                ui.label(RichText::new("🤖").size(self.font_size - 3.0))
+                    .on_hover_text(t!("block-always-in-ai-context"));
+            }
+            if self.block.is_startup {
+                startup_badge(ui, self.font_size);
+            }
+            for tag in &self.block.tags {
+                tag_badge(ui, self.font_size, tag);
+            }
+            if let Some(interval) = self.watch_interval {
+                watch_badge(ui, self.font_size, interval);
+            }
+
+            let mut job = SyntaxHighlighter::new().command_layout_job(
+                &self.block.get_display_command(),
+                egui::FontId::monospace(self.font_size),
+                Color32::from_rgb(220, 220, 220),
+            );
+            job.wrap.max_width = (ui.available_width() - 120.0).max(40.0);
+            job.wrap.max_rows = 1;
+            ui.add(egui::Label::new(job).selectable(false));
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if let Some(progress) = self.block.progress() {
+                    ui.label(
+                        RichText::new(format!("{:.0}%", progress * 100.0))
+                            .color(self.state_color())
+                            .size(self.font_size - 3.0),
+                    );
+                }
+
+                if let Some(code) = self.block.exit_code {
+                    let color = if code == 0 {
+                        Color32::from_rgb(110, 110, 110)
+                    } else {
+                        Color32::from_rgb(220, 60, 80)
+                    };
+                    let label = RichText::new(format!("exit {}", code))
+                        .color(color)
+                        .size(self.font_size - 3.0);
+                    if exit_code_label(ui, label, code).clicked() {
+                        response.explain_exit_code = true;
+                    }
+                }
+
+                if let Some(duration) = self.block.metadata.duration {
+                    duration_badge(ui, self.font_size, duration);
+                }
+            });
+        });
+
+        let row_rect = row.response.rect;
+        let interact_response = ui.interact(
+            row_rect,
+            ui.id().with(("log_row", self.block.id)),
+            egui::Sense::click(),
+        );
+
+        if interact_response.hovered() || self.block.is_selected {
+            ui.painter().rect_filled(
+                row_rect,
+                2.0,
+                Color32::from_rgba_premultiplied(50, 50, 70, 15),
+            );
+        }
+
+        if interact_response.clicked() {
+            response.selected = true;
+            response.toggle_collapsed = true;
+        }
+
+        if interact_response.secondary_clicked() {
+            response.show_context_menu = true;
+        }
+
+        self.handle_focus(ui, &interact_response, row_rect.left_bottom(), &mut response);
+
+        response.rect = Some(row_rect);
+        response.header_height = row_rect.height();
         response
     }
+
+    /// Output containing OSC 8 hyperlinks: lines with links are laid out span by
+    /// span so the link text is clickable; runs of other lines stay one label
+    fn show_linked_output(&self, ui: &mut Ui) {
+        let font = egui::FontId::monospace(self.font_size);
+        let text_color = Color32::from_rgb(200, 200, 200);
+        let highlight = Color32::from_rgb(90, 75, 20);
+        let mut plain_run = String::new();
+
+        let flush = |ui: &mut Ui, run: &mut String| {
+            if !run.is_empty() {
+                let text = run.trim_end_matches(['\r', '\n']);
+                ui.label(RichText::new(text).font(font.clone()).color(text_color));
+                run.clear();
+            }
+        };
+
+        for (index, line) in self.block.output.lines().enumerate() {
+            let highlighted = self.block.highlighted_lines.contains(&index);
+            let severity = self.severity_at(index);
+            if !highlighted && !osc::has_hyperlink(line) && severity.is_none() {
+                plain_run.push_str(line);
+                continue;
+            }
+            let line_color = severity.map_or(text_color, |s| self.severity_color(s));
+
+            flush(ui, &mut plain_run);
+            ui.horizontal_wrapped(|ui| {
+                ui.spacing_mut().item_spacing.x = 0.0;
+                for span in osc::split_hyperlinks(line.trim_end_matches(['\r', '\n'])) {
+                    let mut text = RichText::new(span.text).font(font.clone());
+                    if highlighted {
+                        text = text.background_color(highlight);
+                    }
+                    match span.url {
+                        Some(url) => {
+                            ui.hyperlink_to(text, url).on_hover_text(url);
+                        }
+                        None => {
+                            ui.label(text.color(line_color));
+                        }
+                    }
+                }
+            });
+        }
+        flush(ui, &mut plain_run);
+    }
+
+    /// Output laid out straight from the stored chunks, with the lines matched
+    /// by highlight triggers marked
+    fn output_job(&self) -> egui::text::LayoutJob {
+        let mut job = egui::text::LayoutJob::default();
+        let plain = egui::TextFormat {
+            font_id: egui::FontId::monospace(self.font_size),
+            color: Color32::from_rgb(200, 200, 200),
+            ..Default::default()
+        };
+
+        if self.block.highlighted_lines.is_empty() && self.diagnostics.is_empty() && self.syntax_colors.is_empty() {
+            for chunk in self.block.output.chunks() {
+                job.append(chunk, 0.0, plain.clone());
+            }
+            return job;
+        }
+
+        let lines: Vec<&str> = self.block.output.lines().collect();
+        self.lines_job(&lines, 0..lines.len(), false)
+    }
+
+    /// Some lines of the output, with highlighted, error and warning lines
+    /// marked; `trim_last` drops the final line break so no blank row follows
+    fn lines_job(&self, lines: &[&str], range: Range<usize>, trim_last: bool) -> egui::text::LayoutJob {
+        let mut job = egui::text::LayoutJob::default();
+        let plain = egui::TextFormat {
+            font_id: egui::FontId::monospace(self.font_size),
+            color: Color32::from_rgb(200, 200, 200),
+            ..Default::default()
+        };
+        let highlighted = egui::TextFormat {
+            background: Color32::from_rgb(90, 75, 20),
+            color: Color32::from_rgb(255, 230, 150),
+            ..plain.clone()
+        };
+
+        let last = range.end.saturating_sub(1);
+        for (index, line) in lines[range.clone()].iter().enumerate().map(|(i, line)| (range.start + i, *line)) {
+            let line = if trim_last && index == last {
+                line.trim_end_matches(['\n', '\r'])
+            } else {
+                line
+            };
+            let format = if self.block.highlighted_lines.contains(&index) {
+                highlighted.clone()
+            } else if let Some(severity) = self.severity_at(index) {
+                egui::TextFormat {
+                    color: self.severity_color(severity),
+                    ..plain.clone()
+                }
+            } else if let Some(colors) = self.syntax_colors.get(index).filter(|c| !c.is_empty()) {
+                // A trimmed last line is shorter than the one highlighted
+                for (range, color) in colors {
+                    if let Some(text) = line.get(range.start.min(line.len())..range.end.min(line.len())) {
+                        job.append(text, 0.0, egui::TextFormat { color: *color, ..plain.clone() });
+                    }
+                }
+                continue;
+            } else {
+                plain.clone()
+            };
+            job.append(line, 0.0, format);
+        }
+        job
+    }
+
+    /// Output of a recognized build tool, with each phase under a heading
+    /// that collapses it. Phases with errors, and the one still running,
+    /// start open.
+    fn show_sectioned_output(&self, ui: &mut Ui) {
+        let lines: Vec<&str> = self.block.output.lines().collect();
+        ui.spacing_mut().item_spacing.y = 0.0;
+        let mut shown = 0;
+        for section in self.sections {
+            if section.start >= lines.len() {
+                break;
+            }
+            let end = section.end.unwrap_or(lines.len()).min(lines.len());
+            if shown < section.start {
+                ui.add(egui::Label::new(self.lines_job(&lines, shown..section.start, true)));
+            }
+            shown = end;
+
+            let body = section.start + 1..end;
+            if body.is_empty() {
+                ui.add(egui::Label::new(self.lines_job(&lines, section.start..end, true)));
+                continue;
+            }
+            let worst = self
+                .diagnostics
+                .iter()
+                .filter(|d| (section.start..end).contains(&d.line))
+                .map(|d| d.severity)
+                .min_by_key(|severity| *severity != LineSeverity::Error);
+            let running = section.end.is_none() && self.block.is_running();
+            let heading = RichText::new(format!("{}  ({} lines)", section.title, body.len()))
+                .monospace()
+                .size(self.font_size - 1.0)
+                .color(worst.map_or(Color32::from_rgb(160, 160, 170), |s| self.severity_color(s)));
+            egui::CollapsingHeader::new(heading)
+                .id_source(("output_section", self.block.id, section.start))
+                .default_open(worst == Some(LineSeverity::Error) || running)
+                .show(ui, |ui| {
+                    ui.add(egui::Label::new(self.lines_job(&lines, body, true)));
+                });
+        }
+        if shown < lines.len() {
+            ui.add(egui::Label::new(self.lines_job(&lines, shown..lines.len(), true)));
+        }
+    }
+
+    fn severity_at(&self, line: usize) -> Option<LineSeverity> {
+        self.diagnostics
+            .binary_search_by_key(&line, |d| d.line)
+            .ok()
+            .map(|i| self.diagnostics[i].severity)
+    }
+
+    fn severity_color(&self, severity: LineSeverity) -> Color32 {
+        match severity {
+            LineSeverity::Error => self.error_color,
+            LineSeverity::Warning => self.warning_color,
+        }
+    }
+
+    /// Marks beside the output's scrollbar where errors and warnings are, for
+    /// long output; clicking near one scrolls to it
+    fn diagnostic_map(&self, ui: &mut Ui, viewport: egui::Rect, content_height: f32, jump_id: egui::Id) {
+        if self.diagnostics.is_empty() || content_height <= viewport.height() {
+            return;
+        }
+        let total_lines = self.block.output.lines().count().max(1) as f32;
+        let strip = egui::Rect::from_min_max(
+            egui::pos2(viewport.right() - 4.0, viewport.top()),
+            viewport.right_bottom(),
+        );
+        let painter = ui.painter_at(strip);
+        for diagnostic in self.diagnostics {
+            let y = strip.top() + strip.height() * diagnostic.line as f32 / total_lines;
+            painter.rect_filled(
+                egui::Rect::from_min_size(egui::pos2(strip.left(), y), egui::vec2(strip.width(), 2.0)),
+                0.0,
+                self.severity_color(diagnostic.severity),
+            );
+        }
+
+        let errors = self.diagnostics.iter().filter(|d| d.severity == LineSeverity::Error).count();
+        let warnings = self.diagnostics.len() - errors;
+        let map = ui
+            .interact(strip, jump_id.with("map"), egui::Sense::click())
+            .on_hover_cursor(egui::CursorIcon::PointingHand)
+            .on_hover_text(format!("{} errors, {} warnings; click a mark to jump to it", errors, warnings));
+        if let Some(pos) = map.interact_pointer_pos().filter(|_| map.clicked()) {
+            let clicked_line = (pos.y - strip.top()) / strip.height() * total_lines;
+            let nearest = self
+                .diagnostics
+                .iter()
+                .min_by(|a, b| {
+                    (a.line as f32 - clicked_line).abs().total_cmp(&(b.line as f32 - clicked_line).abs())
+                })
+                .expect("diagnostics is not empty");
+            // Put the line a third of the way down the view
+            let line_top = content_height * nearest.line as f32 / total_lines;
+            ui.data_mut(|d| d.insert_temp(jump_id, (line_top - viewport.height() / 3.0).max(0.0)));
+            ui.ctx().request_repaint();
+        }
+    }
+
+    /// Accent color for the block's state; a single neutral color when
+    /// patterns replace colors
+    fn state_color(&self) -> Color32 {
+        if self.patterns {
+            return Color32::from_rgb(190, 190, 190);
+        }
+        match self.block.state {
+            BlockState::PendingApproval => Color32::from_rgb(255, 165, 0), // Orange
+            BlockState::Running => Color32::from_rgb(100, 149, 237), // Blue
+            BlockState::Completed => Color32::from_rgb(80, 200, 120), // Green
+            BlockState::Failed => Color32::from_rgb(220, 60, 80), // Red
+            BlockState::Editing => Color32::from_rgb(150, 150, 150), // Gray
+            BlockState::Cancelled => Color32::from_rgb(180, 140, 60), // Muted orange
+        }
+    }
+
+    /// Paint the command line pinned to the top of the visible area, for a block
+    /// whose own header has scrolled out of view
+    pub fn paint_sticky_header(&self, ui: &Ui, block_rect: egui::Rect, header_height: f32) {
+        let top = ui.clip_rect().top();
+        let rect = egui::Rect::from_min_max(
+            egui::pos2(block_rect.left(), top),
+            egui::pos2(block_rect.right(), top + header_height + 8.0),
+        );
+
+        let painter = ui.painter();
+        painter.rect_filled(rect, 0.0, ui.visuals().panel_fill);
+        paint_state_bar(
+            painter,
+            egui::Rect::from_min_size(rect.min, egui::vec2(3.0, rect.height())),
+            &self.block.state,
+            self.state_color(),
+            self.patterns,
+        );
+        painter.hline(
+            rect.x_range(),
+            rect.bottom(),
+            egui::Stroke::new(1.0, Color32::from_rgb(60, 60, 60)),
+        );
+
+        let mut job = SyntaxHighlighter::new().command_layout_job(
+            &self.block.get_display_command(),
+            egui::FontId::monospace(self.font_size),
+            Color32::from_rgb(220, 220, 220),
+        );
+        job.wrap.max_width = rect.width() - 20.0;
+        job.wrap.max_rows = 1;
+        let galley = ui.fonts(|f| f.layout_job(job));
+        let text_pos = egui::pos2(rect.left() + 11.0, rect.center().y - galley.size().y / 2.0);
+        painter.galley(text_pos, galley, Color32::from_rgb(220, 220, 220));
+    }
+}
+
+/// Left accent bar of a block. With `patterns` each state gets its own
+/// pattern: solid when completed, dashed while running, dotted while awaiting
+/// approval, zigzag when failed, hollow when cancelled and a hairline when editing.
+fn paint_state_bar(painter: &egui::Painter, rect: egui::Rect, state: &BlockState, color: Color32, patterns: bool) {
+    if !patterns {
+        painter.rect_filled(rect, 0.0, color);
+        return;
+    }
+    match state {
+        BlockState::Completed => {
+            painter.rect_filled(rect, 0.0, color);
+        }
+        BlockState::Running => {
+            let mut y = rect.top();
+            while y < rect.bottom() {
+                let dash = egui::Rect::from_x_y_ranges(rect.x_range(), y..=(y + 5.0).min(rect.bottom()));
+                painter.rect_filled(dash, 0.0, color);
+                y += 8.0;
+            }
+        }
+        BlockState::PendingApproval => {
+            let mut y = rect.top() + 2.0;
+            while y < rect.bottom() {
+                painter.circle_filled(egui::pos2(rect.center().x, y), 1.5, color);
+                y += 6.0;
+            }
+        }
+        BlockState::Failed => {
+            let mut points = Vec::new();
+            let mut y = rect.top();
+            while y <= rect.bottom() {
+                let x = if points.len() % 2 == 0 { rect.left() } else { rect.right() };
+                points.push(egui::pos2(x, y));
+                y += 4.0;
+            }
+            painter.add(egui::Shape::line(points, egui::Stroke::new(1.5, color)));
+        }
+        BlockState::Cancelled => {
+            painter.rect_stroke(rect, 0.0, egui::Stroke::new(1.0, color));
+        }
+        BlockState::Editing => {
+            painter.vline(rect.center().x, rect.y_range(), egui::Stroke::new(1.0, color));
+        }
+    }
+}
+
+/// Small pill marking blocks run automatically when the session opened
+fn startup_badge(ui: &mut Ui, font_size: f32) {
+    egui::Frame::none()
+        .fill(Color32::from_rgb(45, 55, 75))
+        .rounding(3.0)
+        .inner_margin(egui::Margin::symmetric(4.0, 0.0))
+        .show(ui, |ui| {
+            ui.label(
+                RichText::new("startup")
+                    .color(Color32::from_rgb(140, 170, 220))
+                    .size(font_size - 4.0),
+            );
+        });
+}
+
+/// Hook icon, red if any hook failed; hovering lists each hook's result
+fn hooks_badge(ui: &mut Ui, font_size: f32, hooks: &[HookResult]) {
+    let failed = hooks.iter().any(|h| !h.ok);
+    let color = if failed { Color32::from_rgb(220, 100, 100) } else { Color32::from_rgb(110, 110, 110) };
+    let details: Vec<String> = hooks
+        .iter()
+        .map(|h| {
+            let phase = if h.phase == HookPhase::Pre { "before" } else { "after" };
+            let status = if h.ok { "✓" } else { "✗" };
+            match &h.message {
+                Some(message) => format!("{} {} ({}): {}", status, h.hook, phase, message),
+                None => format!("{} {} ({})", status, h.hook, phase),
+            }
+        })
+        .collect();
+    ui.label(RichText::new("🪝").color(color).size(font_size - 3.0))
+        .on_hover_text(details.join("\n"));
+}
+
+/// Globe icon; hovering lists the environment variables recorded when the
+/// command started
+fn environment_badge(ui: &mut Ui, font_size: f32, environment: &HashMap<String, String>) {
+    let mut vars: Vec<_> = environment.iter().collect();
+    vars.sort();
+    let details: Vec<String> = vars.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+    ui.label(RichText::new("🌐").color(Color32::from_rgb(110, 110, 110)).size(font_size - 3.0))
+        .on_hover_text(format!("Environment when started:\n{}", details.join("\n")));
+}
+
+/// Pill for a tag added to the block (e.g. by a script)
+fn tag_badge(ui: &mut Ui, font_size: f32, tag: &str) {
+    egui::Frame::none()
+        .fill(Color32::from_rgb(60, 50, 70))
+        .rounding(3.0)
+        .inner_margin(egui::Margin::symmetric(4.0, 0.0))
+        .show(ui, |ui| {
+            ui.label(
+                RichText::new(format!("#{}", tag))
+                    .color(Color32::from_rgb(200, 160, 220))
+                    .size(font_size - 4.0),
+            );
+        });
+}
+
+/// One problem bash -n or shellcheck found, with where it is in the command
+fn finding_label(ui: &mut Ui, finding: &Finding, font_size: f32) {
+    let (icon, color) = match finding.severity {
+        Severity::Error => ("✖", Color32::from_rgb(230, 80, 80)),
+        Severity::Warning => ("⚠", Color32::from_rgb(255, 165, 0)),
+        Severity::Info => ("ℹ", Color32::from_rgb(100, 180, 255)),
+        Severity::Style => ("•", Color32::from_rgb(150, 150, 150)),
+    };
+    ui.horizontal_wrapped(|ui| {
+        ui.label(RichText::new(format!("{} {}", icon, finding.location())).monospace().color(color).size(font_size - 2.0));
+        ui.label(RichText::new(&finding.message).color(color).size(font_size - 2.0));
+    });
+}
+
+/// What a suggested command does, then one line per flag it uses
+fn explanation_label(ui: &mut Ui, explanation: &CommandExplanation, font_size: f32) {
+    let color = Color32::from_rgb(170, 170, 170);
+    if !explanation.summary.is_empty() {
+        ui.label(RichText::new(format!("ℹ {}", explanation.summary)).color(color).size(font_size - 1.0));
+    }
+    for (flag, meaning) in &explanation.flags {
+        ui.horizontal_wrapped(|ui| {
+            ui.add_space(12.0);
+            ui.label(RichText::new(flag).monospace().color(Color32::from_rgb(150, 190, 230)).size(font_size - 2.0));
+            ui.label(RichText::new(meaning).color(color).size(font_size - 2.0));
+        });
+    }
+}
+
+/// Commands taking at least this long get an amber duration badge
+const SLOW_COMMAND: Duration = Duration::from_secs(10);
+/// And at least this long a red one
+const VERY_SLOW_COMMAND: Duration = Duration::from_secs(60);
+
+/// Pill with how long a command took, colored so slow steps stand out
+pub(crate) fn duration_badge(ui: &mut Ui, font_size: f32, duration: Duration) {
+    let (fill, color) = if duration >= VERY_SLOW_COMMAND {
+        (Color32::from_rgb(75, 40, 45), Color32::from_rgb(235, 120, 120))
+    } else if duration >= SLOW_COMMAND {
+        (Color32::from_rgb(70, 55, 30), Color32::from_rgb(240, 180, 90))
+    } else {
+        (Color32::from_rgb(45, 45, 50), Color32::from_rgb(140, 140, 140))
+    };
+    egui::Frame::none()
+        .fill(fill)
+        .rounding(3.0)
+        .inner_margin(egui::Margin::symmetric(4.0, 0.0))
+        .show(ui, |ui| {
+            ui.label(RichText::new(format_duration(duration)).color(color).size(font_size - 4.0));
+        })
+        .response
+        .on_hover_text(format!("Took {:.2}s", duration.as_secs_f64()));
+}
+
+/// `exit N` label; hovering explains what the status usually means, and
+/// clicking a failure's label asks the AI for more
+fn exit_code_label(ui: &mut Ui, text: RichText, code: i32) -> egui::Response {
+    if code == 0 {
+        return ui.label(text);
+    }
+    let hint = match explain_exit_code(code) {
+        Some(explanation) => format!("{}\n\nClick to ask the AI for more", explanation),
+        None => "Click to ask the AI what went wrong".to_string(),
+    };
+    ui.add(egui::Label::new(text).sense(egui::Sense::click()))
+        .on_hover_cursor(egui::CursorIcon::PointingHand)
+        .on_hover_text(hint)
+}
+
+/// Small amber button offering to re-run a failed command
+fn retry_chip(ui: &mut Ui, font_size: f32, text: &str) -> egui::Response {
+    ui.add(
+        egui::Button::new(RichText::new(text).color(Color32::from_rgb(240, 180, 90)).size(font_size - 3.0))
+            .fill(Color32::from_rgb(70, 55, 30))
+            .rounding(3.0)
+            .small(),
+    )
+}
+
+/// Pill showing the re-run interval of a watched block
+fn watch_badge(ui: &mut Ui, font_size: f32, interval: Duration) {
+    egui::Frame::none()
+        .fill(Color32::from_rgb(40, 60, 55))
+        .rounding(3.0)
+        .inner_margin(egui::Margin::symmetric(4.0, 0.0))
+        .show(ui, |ui| {
+            ui.label(
+                RichText::new(format!("⟳ every {}s", interval.as_secs()))
+                    .color(Color32::from_rgb(120, 200, 170))
+                    .size(font_size - 4.0),
+            );
+        });
 }
 
 #[derive(Default)]
@@ -196,4 +1137,20 @@ pub struct BlockResponse {
     pub reject_command: bool,
     pub edit_command: bool,
     pub regenerate_command: bool,
+    /// Alternative picked to replace the suggested command
+    pub select_candidate: Option<String>,
+    pub stop_watch: bool,
+    /// Delete a divider or calculation, which have no other way to go
+    pub remove_block: bool,
+    /// Re-run a command that failed with a transient error, now or with backoff
+    pub retry: bool,
+    pub retry_with_backoff: bool,
+    pub cancel_retry: bool,
+    /// Ask the AI why the command failed, from its exit code and output
+    pub explain_exit_code: bool,
+    /// Where to open the context menu when it was opened from the keyboard
+    pub context_menu_pos: Option<egui::Pos2>,
+    /// Screen rect of the whole block and height of its command header
+    pub rect: Option<egui::Rect>,
+    pub header_height: f32,
 }
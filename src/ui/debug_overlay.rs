@@ -0,0 +1,161 @@
+use egui::{Color32, Context, RichText};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Frames kept for the frame time average and peak
+const FRAME_WINDOW: usize = 120;
+
+/// Numbers shown in the debug overlay, gathered while a frame is built
+#[derive(Default)]
+pub struct DebugStats {
+    frame_times: VecDeque<Duration>,
+    pub blocks_rendered: usize,
+    pub blocks_total: usize,
+    /// Blocks handed to the database but not yet written
+    pub pending_writes: usize,
+    /// Changes not yet handed to the database
+    pub save_needed: bool,
+    /// Messages waiting in each background channel when the frame started
+    pub backlogs: Vec<(&'static str, usize)>,
+    pub output_bytes: usize,
+    pub save_timings: SaveTimings,
+}
+
+/// How long auto-saves take, recorded by the task doing the save
+#[derive(Debug, Clone, Default)]
+pub struct SaveTimings {
+    pub saves: u64,
+    pub last: Duration,
+    pub max: Duration,
+    total: Duration,
+    /// Blocks written by the last save
+    pub last_blocks: usize,
+}
+
+impl SaveTimings {
+    pub fn record(&mut self, took: Duration, blocks: usize) {
+        self.saves += 1;
+        self.last = took;
+        self.max = self.max.max(took);
+        self.total += took;
+        self.last_blocks = blocks;
+    }
+
+    pub fn average(&self) -> Duration {
+        if self.saves == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.saves as u32
+        }
+    }
+}
+
+impl DebugStats {
+    pub fn record_frame(&mut self, took: Duration) {
+        if self.frame_times.len() == FRAME_WINDOW {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(took);
+    }
+
+    /// Average and slowest of the recent frames
+    pub fn frame_time(&self) -> (Duration, Duration) {
+        if self.frame_times.is_empty() {
+            return (Duration::ZERO, Duration::ZERO);
+        }
+        let total: Duration = self.frame_times.iter().sum();
+        let max = self.frame_times.iter().max().copied().unwrap_or_default();
+        (total / self.frame_times.len() as u32, max)
+    }
+
+    /// Semi-transparent panel in the top right corner of the window
+    pub fn show(&self, ctx: &Context) {
+        let (average, max) = self.frame_time();
+        let slow = Color32::from_rgb(255, 165, 0);
+        let normal = Color32::from_rgb(200, 200, 200);
+        egui::Area::new(egui::Id::new("debug_overlay"))
+            .anchor(egui::Align2::RIGHT_TOP, [-10.0, 40.0])
+            .order(egui::Order::Foreground)
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::none()
+                    .fill(Color32::from_rgba_premultiplied(20, 20, 20, 220))
+                    .inner_margin(8.0)
+                    .rounding(4.0)
+                    .show(ui, |ui| {
+                        let line = |ui: &mut egui::Ui, text: String, color: Color32| {
+                            ui.label(RichText::new(text).monospace().size(11.0).color(color));
+                        };
+                        // Anything over a 60 Hz frame budget is worth a look
+                        let budget = Duration::from_micros(16_667);
+                        line(
+                            ui,
+                            format!("frame  {:>6.2} ms avg  {:>6.2} ms max", ms(average), ms(max)),
+                            if max > budget { slow } else { normal },
+                        );
+                        line(ui, format!("blocks {} rendered / {} total", self.blocks_rendered, self.blocks_total), normal);
+                        line(ui, format!("output {}", super::app::format_size(self.output_bytes as u64)), normal);
+                        let unsaved = if self.save_needed { " (+ unsaved changes)" } else { "" };
+                        line(ui, format!("db     {} pending writes{}", self.pending_writes, unsaved), normal);
+                        let saves = &self.save_timings;
+                        if saves.saves > 0 {
+                            line(
+                                ui,
+                                format!(
+                                    "save   {:>6.2} ms last ({} blocks)  {:.2} ms avg  {:.2} ms max",
+                                    ms(saves.last),
+                                    saves.last_blocks,
+                                    ms(saves.average()),
+                                    ms(saves.max)
+                                ),
+                                if saves.last > budget { slow } else { normal },
+                            );
+                        }
+                        for (channel, backlog) in &self.backlogs {
+                            line(
+                                ui,
+                                format!("chan   {:<8} {}", channel, backlog),
+                                if *backlog > 0 { slow } else { normal },
+                            );
+                        }
+                    });
+            });
+    }
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_time_window() {
+        let mut stats = DebugStats::default();
+        assert_eq!(stats.frame_time(), (Duration::ZERO, Duration::ZERO));
+
+        stats.record_frame(Duration::from_millis(40));
+        for _ in 0..FRAME_WINDOW {
+            stats.record_frame(Duration::from_millis(2));
+        }
+        // The slow frame has left the window
+        assert_eq!(stats.frame_time(), (Duration::from_millis(2), Duration::from_millis(2)));
+
+        stats.record_frame(Duration::from_millis(122));
+        let (average, max) = stats.frame_time();
+        assert_eq!(max, Duration::from_millis(122));
+        assert!(average > Duration::from_millis(2));
+    }
+
+    #[test]
+    fn test_save_timings() {
+        let mut timings = SaveTimings::default();
+        assert_eq!(timings.average(), Duration::ZERO);
+        timings.record(Duration::from_millis(30), 200);
+        timings.record(Duration::from_millis(10), 201);
+        assert_eq!(timings.average(), Duration::from_millis(20));
+        assert_eq!((timings.last, timings.max, timings.last_blocks), (Duration::from_millis(10), Duration::from_millis(30), 201));
+    }
+}
@@ -0,0 +1,251 @@
+use crate::t;
+use crate::ui::accessibility::icon_button;
+use egui::{CollapsingHeader, Color32, RichText, ScrollArea, Ui};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// Entries shown per directory; the rest are summarized
+const MAX_ENTRIES: usize = 500;
+
+/// What the file browser asks the app to do with a path
+pub enum FileBrowserAction {
+    Cd(PathBuf),
+    Insert(PathBuf),
+    Open(PathBuf),
+    Attach(PathBuf),
+    Close,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Entry {
+    pub(crate) name: String,
+    pub(crate) path: PathBuf,
+    pub(crate) is_dir: bool,
+}
+
+/// Directory listing, or why it couldn't be read
+pub(crate) type Listing = Result<(Vec<Entry>, usize), String>;
+
+/// A listing read in the background, with the generation it was read for
+type ListingReply = (u64, PathBuf, Listing);
+
+/// Left sidebar with a file tree rooted at the session's working directory.
+/// Directories are read in the background when first expanded and kept
+/// until refreshed.
+pub struct FileBrowser {
+    root: PathBuf,
+    /// `None` while the directory is being read
+    listings: HashMap<PathBuf, Option<Listing>>,
+    show_hidden: bool,
+    /// Bumped when cached listings are dropped, so reads still under way
+    /// don't bring them back
+    generation: u64,
+    sender: Sender<ListingReply>,
+    receiver: Receiver<ListingReply>,
+}
+
+impl Default for FileBrowser {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            root: PathBuf::new(),
+            listings: HashMap::new(),
+            show_hidden: false,
+            generation: 0,
+            sender,
+            receiver,
+        }
+    }
+}
+
+impl FileBrowser {
+    /// Root the tree at `dir`, starting over if it moved
+    pub fn set_root(&mut self, dir: &Path) {
+        if self.root != dir {
+            self.root = dir.to_path_buf();
+            self.refresh();
+        }
+    }
+
+    /// Forget cached listings, e.g. after a command may have changed files
+    pub fn refresh(&mut self) {
+        self.listings.clear();
+        self.generation += 1;
+    }
+
+    /// Take listings finished in the background
+    fn receive_listings(&mut self) {
+        while let Ok((generation, dir, listing)) = self.receiver.try_recv() {
+            if generation == self.generation {
+                self.listings.insert(dir, Some(listing));
+            }
+        }
+    }
+
+    /// Read `dir` off the UI thread
+    fn start_listing(&mut self, ui: &Ui, dir: &Path) {
+        self.listings.insert(dir.to_path_buf(), None);
+        let (generation, dir, show_hidden) = (self.generation, dir.to_path_buf(), self.show_hidden);
+        let sender = self.sender.clone();
+        let ctx = ui.ctx().clone();
+        std::thread::spawn(move || {
+            let listing = list_dir(&dir, show_hidden);
+            let _ = sender.send((generation, dir, listing));
+            ctx.request_repaint();
+        });
+    }
+
+    pub fn show(&mut self, ui: &mut Ui, font_size: f32) -> Option<FileBrowserAction> {
+        self.receive_listings();
+        let mut action = None;
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(t!("files-title")).strong());
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if icon_button(ui, "✕", &t!("button-close-panel")).clicked() {
+                    action = Some(FileBrowserAction::Close);
+                }
+                if icon_button(ui, "⟳", &t!("button-refresh")).clicked() {
+                    self.refresh();
+                }
+                if ui.toggle_value(&mut self.show_hidden, ".*").on_hover_text(t!("files-show-hidden")).changed() {
+                    self.refresh();
+                }
+            });
+        });
+        ui.label(
+            RichText::new(self.root.display().to_string())
+                .monospace()
+                .color(Color32::from_rgb(150, 150, 150))
+                .size(font_size - 3.0),
+        );
+        ui.separator();
+
+        let root = self.root.clone();
+        ScrollArea::vertical().id_source("file_browser").auto_shrink([false, false]).show(ui, |ui| {
+            self.show_dir(ui, &root, font_size, &mut action);
+        });
+        action
+    }
+
+    fn show_dir(&mut self, ui: &mut Ui, dir: &Path, font_size: f32, action: &mut Option<FileBrowserAction>) {
+        let listing = match self.listings.get(dir) {
+            Some(Some(listing)) => listing.clone(),
+            Some(None) => {
+                ui.spinner();
+                return;
+            }
+            None => {
+                self.start_listing(ui, dir);
+                ui.spinner();
+                return;
+            }
+        };
+        let (entries, hidden) = match listing {
+            Ok(listing) => listing,
+            Err(e) => {
+                ui.label(RichText::new(e).color(Color32::from_rgb(230, 80, 80)).size(font_size - 2.0));
+                return;
+            }
+        };
+
+        for entry in &entries {
+            if entry.is_dir {
+                let header = CollapsingHeader::new(RichText::new(format!("📁 {}", entry.name)).size(font_size - 2.0))
+                    .id_source(&entry.path)
+                    .show(ui, |ui| self.show_dir(ui, &entry.path, font_size, action));
+                header.header_response.context_menu(|ui| entry_menu(ui, entry, action));
+            } else {
+                let label = ui
+                    .selectable_label(false, RichText::new(format!("📄 {}", entry.name)).size(font_size - 2.0))
+                    .on_hover_text(t!("files-file-hint"));
+                if label.clicked() {
+                    *action = Some(FileBrowserAction::Insert(entry.path.clone()));
+                }
+                label.context_menu(|ui| entry_menu(ui, entry, action));
+            }
+        }
+        if hidden > 0 {
+            ui.label(RichText::new(t!("files-more", count = hidden)).weak().size(font_size - 3.0));
+        }
+    }
+}
+
+fn entry_menu(ui: &mut Ui, entry: &Entry, action: &mut Option<FileBrowserAction>) {
+    let path = entry.path.clone();
+    if entry.is_dir && ui.button(t!("files-cd-here")).clicked() {
+        *action = Some(FileBrowserAction::Cd(path.clone()));
+        ui.close_menu();
+    }
+    if ui.button(t!("files-insert-path")).clicked() {
+        *action = Some(FileBrowserAction::Insert(path.clone()));
+        ui.close_menu();
+    }
+    if ui.button(t!("files-open")).clicked() {
+        *action = Some(FileBrowserAction::Open(path.clone()));
+        ui.close_menu();
+    }
+    if !entry.is_dir && ui.button(t!("files-attach")).clicked() {
+        *action = Some(FileBrowserAction::Attach(path));
+        ui.close_menu();
+    }
+}
+
+/// Directories first, then files, each by name; at most MAX_ENTRIES of them,
+/// with the count of the rest
+pub(crate) fn list_dir(dir: &Path, show_hidden: bool) -> Listing {
+    let read = std::fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let mut entries: Vec<Entry> = read
+        .filter_map(Result::ok)
+        .map(|e| Entry {
+            name: e.file_name().to_string_lossy().into_owned(),
+            is_dir: e.path().is_dir(),
+            path: e.path(),
+        })
+        .filter(|e| show_hidden || !e.name.starts_with('.'))
+        .collect();
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase())));
+    let rest = entries.len().saturating_sub(MAX_ENTRIES);
+    entries.truncate(MAX_ENTRIES);
+    Ok((entries, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_list_dir() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("b.txt"), "").unwrap();
+        std::fs::write(dir.path().join("A.txt"), "").unwrap();
+        std::fs::write(dir.path().join(".env"), "").unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+
+        let (entries, rest) = list_dir(dir.path(), false).unwrap();
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["src", "A.txt", "b.txt"]);
+        assert_eq!(rest, 0);
+        assert!(entries[0].is_dir);
+
+        let (entries, _) = list_dir(dir.path(), true).unwrap();
+        assert_eq!(entries.len(), 4);
+        assert!(list_dir(&dir.path().join("missing"), false).is_err());
+    }
+
+    #[test]
+    fn test_stale_listings_are_dropped() {
+        let mut browser = FileBrowser::default();
+        let dir = PathBuf::from("/src");
+        browser.sender.send((browser.generation, dir.clone(), Ok((Vec::new(), 0)))).unwrap();
+        browser.receive_listings();
+        assert!(matches!(browser.listings.get(&dir), Some(Some(Ok(_)))));
+
+        // A read that finishes after a refresh doesn't bring the old listing back
+        browser.sender.send((browser.generation, dir.clone(), Ok((Vec::new(), 0)))).unwrap();
+        browser.refresh();
+        browser.receive_listings();
+        assert!(browser.listings.is_empty());
+    }
+}
@@ -0,0 +1,195 @@
+use crate::syntax::SyntaxHighlighter;
+use crate::t;
+use crate::ui::accessibility::icon_button;
+use crate::utils::git::FileChange;
+use crate::utils::shell_escape;
+use egui::text::LayoutJob;
+use egui::{Color32, RichText, ScrollArea, Ui};
+use std::path::{Path, PathBuf};
+
+/// What the git panel asks the app to do
+pub enum GitPanelAction {
+    Refresh,
+    /// Load the diff of a file, staged or not
+    ShowDiff(FileChange, bool),
+    /// Put a git command in the command input
+    Insert(String),
+    Close,
+}
+
+/// Left sidebar with the uncommitted changes of the session's repository and
+/// the diff of the selected file. Buttons only fill in git commands; nothing
+/// runs until the command is submitted.
+#[derive(Default)]
+pub struct GitPanel {
+    root: Option<PathBuf>,
+    changes: Vec<FileChange>,
+    loading: bool,
+    /// Selected path and whether its staged diff is shown
+    selected: Option<(String, bool)>,
+    diff: Option<LayoutJob>,
+}
+
+impl GitPanel {
+    /// Changes are about to be reloaded
+    pub fn start_loading(&mut self) {
+        self.loading = true;
+    }
+
+    /// The repository's changes, or `None` outside a repository
+    pub fn set_changes(&mut self, status: Option<(PathBuf, Vec<FileChange>)>) {
+        self.loading = false;
+        let (root, changes) = status.unzip();
+        if root != self.root {
+            self.selected = None;
+            self.diff = None;
+        }
+        self.root = root;
+        self.changes = changes.unwrap_or_default();
+    }
+
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
+    /// Highlight a loaded diff, unless another file was selected meanwhile
+    pub fn set_diff(&mut self, path: &str, staged: bool, diff: &str, font_size: f32) {
+        if self.selected.as_ref() != Some(&(path.to_string(), staged)) {
+            return;
+        }
+        let font_id = egui::FontId::monospace(font_size - 2.0);
+        self.diff = Some(SyntaxHighlighter::new().diff_layout_job(diff, path, font_id, Color32::from_rgb(200, 200, 200)));
+    }
+
+    pub fn show(&mut self, ui: &mut Ui, font_size: f32) -> Option<GitPanelAction> {
+        let mut action = None;
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("Git").strong());
+            if self.loading {
+                ui.spinner();
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if icon_button(ui, "✕", &t!("button-close-panel")).clicked() {
+                    action = Some(GitPanelAction::Close);
+                }
+                if icon_button(ui, "⟳", &t!("button-refresh")).clicked() {
+                    action = Some(GitPanelAction::Refresh);
+                }
+            });
+        });
+        ui.separator();
+
+        if self.root.is_none() {
+            ui.label(RichText::new("Not inside a git repository").weak());
+            return action;
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            for (label, command) in [("Stage all", "git add -A"), ("Commit", "git commit -m \"\""), ("Push", "git push"), ("Pull", "git pull")] {
+                if ui.small_button(label).on_hover_text(command).clicked() {
+                    action = Some(GitPanelAction::Insert(command.to_string()));
+                }
+            }
+        });
+        ui.add_space(4.0);
+
+        let staged: Vec<&FileChange> = self.changes.iter().filter(|c| c.staged.is_some()).collect();
+        let unstaged: Vec<&FileChange> = self.changes.iter().filter(|c| c.unstaged.is_some()).collect();
+        ScrollArea::vertical().id_source("git_changes").max_height(ui.available_height() * 0.4).show(ui, |ui| {
+            if self.changes.is_empty() {
+                ui.label(RichText::new("Nothing to commit, working tree clean").weak());
+            }
+            if !staged.is_empty() {
+                ui.label(RichText::new(format!("Staged ({})", staged.len())).strong().size(font_size - 2.0));
+                for change in &staged {
+                    if let Some(a) = self.change_row(ui, change, true, font_size) {
+                        action = Some(a);
+                    }
+                }
+            }
+            if !unstaged.is_empty() {
+                ui.label(RichText::new(format!("Changes ({})", unstaged.len())).strong().size(font_size - 2.0));
+                for change in &unstaged {
+                    if let Some(a) = self.change_row(ui, change, false, font_size) {
+                        action = Some(a);
+                    }
+                }
+            }
+        });
+
+        if let Some(job) = &self.diff {
+            ui.separator();
+            ScrollArea::both().id_source("git_diff").auto_shrink([false, false]).show(ui, |ui| {
+                ui.add(egui::Label::new(job.clone()).extend());
+            });
+        }
+        action
+    }
+
+    fn change_row(&self, ui: &mut Ui, change: &FileChange, staged: bool, font_size: f32) -> Option<GitPanelAction> {
+        let mut action = None;
+        let code = if staged { change.staged } else { change.unstaged }.unwrap_or(' ');
+        let path = shell_escape(&change.path);
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(code.to_string()).monospace().color(status_color(code)).size(font_size - 2.0));
+            let selected = self.selected.as_ref() == Some(&(change.path.clone(), staged));
+            if ui
+                .selectable_label(selected, RichText::new(&change.path).size(font_size - 2.0))
+                .on_hover_text("Show diff")
+                .clicked()
+            {
+                action = Some(GitPanelAction::ShowDiff(change.clone(), staged));
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if staged {
+                    if icon_button(ui, "−", "Unstage").clicked() {
+                        action = Some(GitPanelAction::Insert(format!("git restore --staged -- {}", path)));
+                    }
+                } else {
+                    if !change.is_untracked() && icon_button(ui, "↶", "Discard changes").clicked() {
+                        action = Some(GitPanelAction::Insert(format!("git restore -- {}", path)));
+                    }
+                    if icon_button(ui, "+", "Stage").clicked() {
+                        action = Some(GitPanelAction::Insert(format!("git add -- {}", path)));
+                    }
+                }
+            });
+        });
+        action
+    }
+
+    /// Remember which file's diff is about to load
+    pub fn select(&mut self, path: &str, staged: bool) {
+        self.selected = Some((path.to_string(), staged));
+        self.diff = None;
+    }
+}
+
+fn status_color(code: char) -> Color32 {
+    match code {
+        'A' | '?' => Color32::from_rgb(80, 200, 120),
+        'D' => Color32::from_rgb(230, 80, 80),
+        'R' | 'C' => Color32::from_rgb(100, 180, 255),
+        _ => Color32::from_rgb(255, 165, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_diff_is_ignored() {
+        let mut panel = GitPanel::default();
+        panel.set_changes(Some((PathBuf::from("/repo"), Vec::new())));
+        panel.select("a.rs", false);
+        panel.set_diff("b.rs", false, "+x\n", 14.0);
+        assert!(panel.diff.is_none());
+        panel.set_diff("a.rs", false, "+x\n", 14.0);
+        assert!(panel.diff.is_some());
+
+        // Another repository drops the selection
+        panel.set_changes(Some((PathBuf::from("/other"), Vec::new())));
+        assert!(panel.selected.is_none() && panel.diff.is_none());
+    }
+}
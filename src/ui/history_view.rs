@@ -0,0 +1,217 @@
+use crate::core::block::format_duration;
+use crate::core::{Block, BlockState};
+use crate::t;
+use crate::ui::block_widget::duration_badge;
+use egui::{Color32, Context, RichText, ScrollArea, TextEdit};
+use std::cmp::Ordering;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Column the history table is sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryColumn {
+    Time,
+    Duration,
+    ExitCode,
+    Command,
+}
+
+impl HistoryColumn {
+    const ALL: [HistoryColumn; 4] = [
+        HistoryColumn::Time,
+        HistoryColumn::Duration,
+        HistoryColumn::ExitCode,
+        HistoryColumn::Command,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            HistoryColumn::Time => "Time",
+            HistoryColumn::Duration => "Duration",
+            HistoryColumn::ExitCode => "Exit",
+            HistoryColumn::Command => "Command",
+        }
+    }
+
+    fn compare(&self, a: &Block, b: &Block) -> Ordering {
+        match self {
+            HistoryColumn::Time => a.timestamp.cmp(&b.timestamp),
+            HistoryColumn::Duration => a.metadata.duration.cmp(&b.metadata.duration),
+            HistoryColumn::ExitCode => a.exit_code.cmp(&b.exit_code),
+            HistoryColumn::Command => a.command.to_lowercase().cmp(&b.command.to_lowercase()),
+        }
+    }
+}
+
+/// View > History: every command of the session in a table that sorts by
+/// time, duration, exit code or command, for finding the slow or failing
+/// step of a long run. Clicking a row jumps to its block.
+pub struct HistoryView {
+    pub open: bool,
+    sort: HistoryColumn,
+    ascending: bool,
+    filter: String,
+}
+
+impl Default for HistoryView {
+    fn default() -> Self {
+        Self {
+            open: false,
+            sort: HistoryColumn::Time,
+            ascending: false,
+            filter: String::new(),
+        }
+    }
+}
+
+impl HistoryView {
+    /// Returns the block the user asked to jump to
+    pub fn show(&mut self, ctx: &Context, blocks: &[Block], font_size: f32) -> Option<Uuid> {
+        if !self.open {
+            return None;
+        }
+        let mut jump = None;
+        let mut open = true;
+        egui::Window::new(t!("dialog-history"))
+            .id(egui::Id::new("history_window"))
+            .open(&mut open)
+            .default_size([720.0, 440.0])
+            .show(ctx, |ui| {
+                let rows = sorted_commands(blocks, self.sort, self.ascending, &self.filter);
+                ui.horizontal(|ui| {
+                    ui.add(TextEdit::singleline(&mut self.filter).hint_text("Filter commands").desired_width(220.0));
+                    ui.label(RichText::new(summary(&rows)).weak());
+                });
+                ui.separator();
+
+                ScrollArea::vertical().id_source("history_rows").auto_shrink([false, false]).show(ui, |ui| {
+                    egui::Grid::new("history").num_columns(4).striped(true).spacing([16.0, 4.0]).show(ui, |ui| {
+                        for column in HistoryColumn::ALL {
+                            let arrow = match (self.sort == column, self.ascending) {
+                                (false, _) => "",
+                                (true, true) => " ⏶",
+                                (true, false) => " ⏷",
+                            };
+                            let heading = RichText::new(format!("{}{}", column.label(), arrow)).strong();
+                            if ui.selectable_label(self.sort == column, heading).clicked() {
+                                if self.sort == column {
+                                    self.ascending = !self.ascending;
+                                } else {
+                                    // Slowest, failing and newest first are what people look for
+                                    self.sort = column;
+                                    self.ascending = column == HistoryColumn::Command;
+                                }
+                            }
+                        }
+                        ui.end_row();
+
+                        for block in &rows {
+                            let time = block.timestamp.with_timezone(&chrono::Local).format("%H:%M:%S").to_string();
+                            if ui.selectable_label(false, time).on_hover_text("Show block").clicked() {
+                                jump = Some(block.id);
+                            }
+                            match block.metadata.duration {
+                                Some(duration) => duration_badge(ui, font_size, duration),
+                                None => {
+                                    ui.label("");
+                                }
+                            }
+                            exit_label(ui, block);
+                            let command = block.command.lines().next().unwrap_or_default();
+                            if ui
+                                .selectable_label(false, RichText::new(command).monospace())
+                                .on_hover_text(&block.command)
+                                .clicked()
+                            {
+                                jump = Some(block.id);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        self.open = open;
+        if jump.is_some() {
+            self.open = false;
+        }
+        jump
+    }
+}
+
+fn exit_label(ui: &mut egui::Ui, block: &Block) {
+    match (block.exit_code, &block.state) {
+        (Some(0), _) => ui.label(RichText::new("0").color(Color32::from_rgb(80, 200, 120))),
+        (Some(code), _) => ui.label(RichText::new(code.to_string()).color(Color32::from_rgb(230, 80, 80))),
+        (None, BlockState::Running) => ui.spinner(),
+        (None, _) => ui.label(RichText::new("—").weak()),
+    };
+}
+
+/// The session's commands matching `filter`, without dividers or suggestions
+/// still waiting for approval
+fn sorted_commands<'a>(blocks: &'a [Block], column: HistoryColumn, ascending: bool, filter: &str) -> Vec<&'a Block> {
+    let filter = filter.trim().to_lowercase();
+    let mut rows: Vec<&Block> = blocks
+        .iter()
+        .filter(|b| !b.is_divider && !b.is_calculation && b.state != BlockState::PendingApproval)
+        .filter(|b| filter.is_empty() || b.command.to_lowercase().contains(&filter))
+        .collect();
+    rows.sort_by(|a, b| {
+        let order = column.compare(a, b);
+        if ascending {
+            order
+        } else {
+            order.reverse()
+        }
+    });
+    rows
+}
+
+/// `12 commands · 2 failed · 4m 10s total`
+fn summary(rows: &[&Block]) -> String {
+    let failed = rows.iter().filter(|b| b.exit_code.is_some_and(|code| code != 0)).count();
+    let total: Duration = rows.iter().filter_map(|b| b.metadata.duration).sum();
+    let mut summary = format!("{} commands", rows.len());
+    if failed > 0 {
+        summary.push_str(&format!(" · {} failed", failed));
+    }
+    summary.push_str(&format!(" · {} total", format_duration(total)));
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn finished(command: &str, secs: u64, exit_code: i32) -> Block {
+        let mut block = Block::new(command.to_string(), PathBuf::from("/tmp"));
+        block.complete_execution(exit_code);
+        block.metadata.duration = Some(Duration::from_secs(secs));
+        block
+    }
+
+    #[test]
+    fn test_sorted_commands() {
+        let blocks = vec![
+            finished("cargo build", 40, 0),
+            Block::divider("Tests".to_string(), PathBuf::from("/tmp")),
+            finished("cargo test", 95, 101),
+            finished("ls", 0, 0),
+        ];
+        let commands = |rows: Vec<&Block>| rows.iter().map(|b| b.command.clone()).collect::<Vec<_>>();
+
+        assert_eq!(
+            commands(sorted_commands(&blocks, HistoryColumn::Duration, false, "")),
+            ["cargo test", "cargo build", "ls"]
+        );
+        assert_eq!(commands(sorted_commands(&blocks, HistoryColumn::ExitCode, false, ""))[0], "cargo test");
+        assert_eq!(
+            commands(sorted_commands(&blocks, HistoryColumn::Command, true, "cargo")),
+            ["cargo build", "cargo test"]
+        );
+
+        let rows = sorted_commands(&blocks, HistoryColumn::Time, true, "");
+        assert_eq!(summary(&rows), "3 commands · 1 failed · 2m 15s total");
+    }
+}
@@ -0,0 +1,110 @@
+use anyhow::{anyhow, Result};
+use egui::{Context, Pos2, Rect, Vec2};
+use global_hotkey::hotkey::HotKey;
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long the slide-down animation takes
+const SLIDE_DURATION: Duration = Duration::from_millis(180);
+
+/// A system-wide shortcut, registered for as long as this value lives
+pub struct GlobalHotkey {
+    _manager: GlobalHotKeyManager,
+    pressed: Arc<AtomicBool>,
+}
+
+impl GlobalHotkey {
+    /// Register `shortcut` (e.g. `ctrl+backquote` or `super+F12`). Presses
+    /// wake the UI even while another application has focus.
+    pub fn register(shortcut: &str, ctx: &Context) -> Result<Self> {
+        let hotkey: HotKey = shortcut
+            .parse()
+            .map_err(|e| anyhow!("Invalid hotkey '{}': {}", shortcut, e))?;
+        let manager = GlobalHotKeyManager::new().map_err(|e| anyhow!("Global hotkeys unavailable: {}", e))?;
+        manager
+            .register(hotkey)
+            .map_err(|e| anyhow!("Failed to register hotkey '{}': {}", shortcut, e))?;
+
+        let pressed = Arc::new(AtomicBool::new(false));
+        let flag = pressed.clone();
+        let ctx = ctx.clone();
+        let id = hotkey.id();
+        GlobalHotKeyEvent::set_event_handler(Some(move |event: GlobalHotKeyEvent| {
+            if event.id() == id && event.state() == HotKeyState::Pressed {
+                flag.store(true, Ordering::SeqCst);
+                ctx.request_repaint();
+            }
+        }));
+
+        tracing::info!("Registered global hotkey {}", shortcut);
+        Ok(Self { _manager: manager, pressed })
+    }
+
+    /// Whether the hotkey was pressed since the last call
+    pub fn take_pressed(&self) -> bool {
+        self.pressed.swap(false, Ordering::SeqCst)
+    }
+}
+
+/// Window sliding down from the top edge of the screen
+pub struct SlideDown {
+    started: Instant,
+    target: Rect,
+}
+
+impl SlideDown {
+    /// Full-width strip `height_fraction` of the monitor tall, at the top of the
+    /// monitor the window is on. egui only reports the monitor's size, so its
+    /// origin is inferred assuming monitors of that size sit side by side.
+    pub fn start(ctx: &Context, height_fraction: f32) -> Option<Self> {
+        let (monitor, outer) = ctx.input(|i| (i.viewport().monitor_size, i.viewport().outer_rect));
+        let monitor = monitor?;
+        let center_x = outer.map_or(0.0, |r| r.center().x);
+        let left = (center_x / monitor.x).floor().max(0.0) * monitor.x;
+        let size = Vec2::new(monitor.x, monitor.y * height_fraction.clamp(0.2, 1.0));
+        Some(Self {
+            started: Instant::now(),
+            target: Rect::from_min_size(Pos2::new(left, 0.0), size),
+        })
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.target.size()
+    }
+
+    /// Window position for this frame, and whether the animation has finished
+    pub fn position(&self) -> (Pos2, bool) {
+        let t = self.started.elapsed().as_secs_f32() / SLIDE_DURATION.as_secs_f32();
+        (slide_position(self.target, t), t >= 1.0)
+    }
+}
+
+/// Top-left corner at progress `t` (0..1), easing out as it reaches `target`
+fn slide_position(target: Rect, t: f32) -> Pos2 {
+    let t = t.clamp(0.0, 1.0);
+    let eased = 1.0 - (1.0 - t).powi(3);
+    Pos2::new(target.min.x, target.min.y - target.height() * (1.0 - eased))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slide_position() {
+        let target = Rect::from_min_size(Pos2::new(1920.0, 0.0), Vec2::new(1920.0, 500.0));
+        assert_eq!(slide_position(target, 0.0), Pos2::new(1920.0, -500.0));
+        assert_eq!(slide_position(target, 1.0), target.min);
+        assert_eq!(slide_position(target, 3.0), target.min);
+        assert!(slide_position(target, 0.5).y > -250.0);
+    }
+
+    #[test]
+    fn test_parse_shortcut() {
+        assert!("ctrl+backquote".parse::<HotKey>().is_ok());
+        assert!("super+F12".parse::<HotKey>().is_ok());
+        assert!("ctrl+nonsense".parse::<HotKey>().is_err());
+    }
+}
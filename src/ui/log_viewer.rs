@@ -0,0 +1,109 @@
+use crate::t;
+use crate::utils::logging::{self, LogLine};
+use egui::{Color32, Context, RichText, ScrollArea, TextEdit};
+use tracing::Level;
+
+const LEVELS: [Level; 5] = [Level::ERROR, Level::WARN, Level::INFO, Level::DEBUG, Level::TRACE];
+
+/// Help > Logs: the app's recent log lines, filtered by level and text
+pub struct LogViewer {
+    pub open: bool,
+    /// Least severe level shown
+    min_level: Level,
+    filter: String,
+}
+
+impl Default for LogViewer {
+    fn default() -> Self {
+        Self {
+            open: false,
+            min_level: Level::INFO,
+            filter: String::new(),
+        }
+    }
+}
+
+impl LogViewer {
+    pub fn show(&mut self, ctx: &Context) {
+        if !self.open {
+            return;
+        }
+        let lines: Vec<LogLine> =
+            logging::with_recent_lines(|lines| lines.iter().filter(|line| self.matches(line)).cloned().collect());
+
+        let mut open = true;
+        egui::Window::new(t!("dialog-logs"))
+            .id(egui::Id::new("logs_window"))
+            .open(&mut open)
+            .default_size([760.0, 420.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Level:");
+                    egui::ComboBox::from_id_source("log_level")
+                        .selected_text(self.min_level.as_str())
+                        .show_ui(ui, |ui| {
+                            for level in LEVELS {
+                                ui.selectable_value(&mut self.min_level, level, level.as_str());
+                            }
+                        });
+                    ui.add(TextEdit::singleline(&mut self.filter).hint_text("Filter").desired_width(200.0));
+                    if ui.button("📋 Copy").on_hover_text("Copy the lines shown").clicked() {
+                        let text = lines.iter().map(|line| line.text.as_str()).collect::<Vec<_>>().join("\n");
+                        ctx.output_mut(|o| o.copied_text = text);
+                    }
+                });
+                match logging::log_path() {
+                    Some(path) => ui.label(RichText::new(path.display().to_string()).weak().small()),
+                    None => ui.label(RichText::new("The log file could not be opened; logs only go to stderr").weak().small()),
+                };
+                ui.separator();
+
+                let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+                ScrollArea::both()
+                    .auto_shrink([false, false])
+                    .stick_to_bottom(true)
+                    .show_rows(ui, row_height, lines.len(), |ui, rows| {
+                        for line in &lines[rows] {
+                            ui.label(RichText::new(&line.text).monospace().color(level_color(line.level)));
+                        }
+                    });
+            });
+        self.open = open;
+    }
+
+    fn matches(&self, line: &LogLine) -> bool {
+        // More verbose levels compare greater
+        line.level <= self.min_level
+            && (self.filter.is_empty() || line.text.to_lowercase().contains(&self.filter.to_lowercase()))
+    }
+}
+
+fn level_color(level: Level) -> Color32 {
+    match level {
+        Level::ERROR => Color32::from_rgb(230, 80, 80),
+        Level::WARN => Color32::from_rgb(255, 165, 0),
+        Level::INFO => Color32::from_rgb(200, 200, 200),
+        _ => Color32::GRAY,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_and_text_filter() {
+        let line = |level, text: &str| LogLine { level, text: text.to_string() };
+        let mut viewer = LogViewer {
+            min_level: Level::WARN,
+            ..Default::default()
+        };
+        assert!(viewer.matches(&line(Level::ERROR, "save failed")));
+        assert!(viewer.matches(&line(Level::WARN, "slow frame")));
+        assert!(!viewer.matches(&line(Level::INFO, "started")));
+
+        viewer.filter = "SAVE".to_string();
+        assert!(viewer.matches(&line(Level::ERROR, "save failed")));
+        assert!(!viewer.matches(&line(Level::WARN, "slow frame")));
+    }
+}
@@ -1,7 +1,27 @@
+pub mod accessibility;
+pub mod ai_audit_view;
+pub mod ai_diagnostics;
 pub mod ai_panel;
 pub mod app;
+pub mod block_image;
 pub mod block_widget;
+pub mod debug_overlay;
+pub mod file_browser;
+pub mod git_panel;
+pub mod history_view;
+pub mod hotkey;
+pub mod log_viewer;
+pub mod path_picker;
+pub mod presentation;
+pub mod prompt_bar;
+pub mod selection;
+pub mod terminal_view;
+pub mod toast;
+pub mod vim;
 
 pub use ai_panel::{AiAction, AiPanel, AiPanelMode};
 pub use app::ImmateriumApp;
 pub use block_widget::BlockWidget;
+pub use prompt_bar::PromptBar;
+pub use terminal_view::TerminalView;
+pub use vim::{VimMode, VimState};
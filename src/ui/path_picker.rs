@@ -0,0 +1,134 @@
+use crate::t;
+use crate::ui::file_browser::{list_dir, Entry, Listing};
+use egui::{Color32, Context, RichText, ScrollArea, TextEdit};
+use std::path::{Path, PathBuf};
+
+/// Popup for browsing to a file or directory and inserting its path at the
+/// command input cursor, opened from the 📂 button by the input or the
+/// `insert_path` keybinding
+#[derive(Default)]
+pub struct PathPicker {
+    pub open: bool,
+    dir: PathBuf,
+    listing: Option<Listing>,
+    query: String,
+    show_hidden: bool,
+}
+
+impl PathPicker {
+    /// Open the picker in `dir`
+    pub fn open_at(&mut self, dir: &Path) {
+        self.open = true;
+        self.navigate(dir.to_path_buf());
+    }
+
+    fn navigate(&mut self, dir: PathBuf) {
+        self.listing = Some(list_dir(&dir, self.show_hidden));
+        self.dir = dir;
+        self.query.clear();
+    }
+
+    /// Returns the path the user picked
+    pub fn show(&mut self, ctx: &Context) -> Option<PathBuf> {
+        if !self.open {
+            return None;
+        }
+        let mut picked = None;
+        let mut enter_dir = None;
+        let mut open = true;
+        egui::Window::new(t!("dialog-insert-path"))
+            .id(egui::Id::new("insert_path_window"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(450.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if let Some(parent) = self.dir.parent() {
+                        if ui.button("⬆").on_hover_text(t!("path-picker-parent")).clicked() {
+                            enter_dir = Some(parent.to_path_buf());
+                        }
+                    }
+                    ui.label(RichText::new(self.dir.display().to_string()).monospace());
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.toggle_value(&mut self.show_hidden, ".*").on_hover_text(t!("files-show-hidden")).changed() {
+                            enter_dir = Some(self.dir.clone());
+                        }
+                    });
+                });
+                let search = ui.add(
+                    TextEdit::singleline(&mut self.query)
+                        .hint_text(t!("path-picker-filter"))
+                        .desired_width(f32::INFINITY),
+                );
+                if ctx.memory(|m| m.focused().is_none()) {
+                    search.request_focus();
+                }
+                ui.separator();
+
+                let entries = match &self.listing {
+                    Some(Ok((entries, _))) => filter_entries(entries, &self.query),
+                    Some(Err(e)) => {
+                        ui.colored_label(Color32::from_rgb(230, 80, 80), e);
+                        Vec::new()
+                    }
+                    None => Vec::new(),
+                };
+
+                ScrollArea::vertical().max_height(350.0).show(ui, |ui| {
+                    for entry in &entries {
+                        ui.horizontal(|ui| {
+                            if entry.is_dir {
+                                if ui.selectable_label(false, format!("📁 {}", entry.name)).clicked() {
+                                    enter_dir = Some(entry.path.clone());
+                                }
+                                if ui.small_button(t!("path-picker-insert")).clicked() {
+                                    picked = Some(entry.path.clone());
+                                }
+                            } else if ui.selectable_label(false, format!("📄 {}", entry.name)).clicked() {
+                                picked = Some(entry.path.clone());
+                            }
+                        });
+                    }
+                    if let Some(Ok((_, hidden))) = &self.listing {
+                        if *hidden > 0 {
+                            ui.label(RichText::new(t!("files-more", count = *hidden)).weak());
+                        }
+                    }
+                });
+
+                // Enter opens the first matching directory or picks the first file
+                if search.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    match entries.first() {
+                        Some(entry) if entry.is_dir => enter_dir = Some(entry.path.clone()),
+                        Some(entry) => picked = Some(entry.path.clone()),
+                        None => {}
+                    }
+                    search.request_focus();
+                }
+
+                ui.separator();
+                if ui.button(t!("path-picker-insert-directory")).clicked() {
+                    picked = Some(self.dir.clone());
+                }
+            });
+
+        if let Some(dir) = enter_dir {
+            self.navigate(dir);
+        }
+        if !open || picked.is_some() {
+            self.open = false;
+        }
+        picked
+    }
+}
+
+/// Entries whose name contains the query, ignoring case
+fn filter_entries(entries: &[Entry], query: &str) -> Vec<Entry> {
+    let query = query.trim().to_lowercase();
+    entries
+        .iter()
+        .filter(|e| query.is_empty() || e.name.to_lowercase().contains(&query))
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,114 @@
+use crate::core::Block;
+use crate::syntax::SyntaxHighlighter;
+use crate::t;
+use egui::{Align, Context, Key, Layout, Modifiers, RichText, ScrollArea};
+
+/// How much larger than the configured font size text is shown
+const SCALE: f32 = 1.6;
+
+/// Read-only walk through the session's blocks for talks and teaching: the
+/// input, menus and block metadata are hidden and one command with its
+/// output fills the window, stepped through with the arrow keys
+#[derive(Debug, Default)]
+pub struct Presentation {
+    pub active: bool,
+    index: usize,
+}
+
+impl Presentation {
+    /// Start presenting at the block at `index`
+    pub fn start(&mut self, index: usize) {
+        self.active = true;
+        self.index = index;
+    }
+
+    pub fn show(&mut self, ctx: &Context, blocks: &[Block], font_size: f32) {
+        self.handle_keys(ctx, blocks.len());
+        let font_size = font_size * SCALE;
+
+        egui::CentralPanel::default()
+            .frame(egui::Frame::central_panel(&ctx.style()).inner_margin(48.0))
+            .show(ctx, |ui| {
+                ui.with_layout(Layout::bottom_up(Align::Min), |ui| {
+                    ui.horizontal(|ui| {
+                        let position = if blocks.is_empty() {
+                            t!("presentation-empty")
+                        } else {
+                            format!("{} / {}", self.index + 1, blocks.len())
+                        };
+                        ui.label(RichText::new(position).size(font_size * 0.6).weak());
+                        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            ui.label(RichText::new(t!("presentation-hint")).size(font_size * 0.6).weak());
+                        });
+                    });
+                    ui.separator();
+
+                    ui.with_layout(Layout::top_down(Align::Min), |ui| {
+                        if let Some(block) = blocks.get(self.index) {
+                            show_slide(ui, block, font_size);
+                        }
+                    });
+                });
+            });
+    }
+
+    fn handle_keys(&mut self, ctx: &Context, count: usize) {
+        let pressed = |keys: &[Key]| ctx.input_mut(|i| keys.iter().any(|key| i.consume_key(Modifiers::NONE, *key)));
+        if pressed(&[Key::Escape]) {
+            self.active = false;
+        }
+        let last = count.saturating_sub(1);
+        if pressed(&[Key::ArrowRight, Key::ArrowDown, Key::PageDown, Key::Space, Key::Enter]) {
+            self.index = (self.index + 1).min(last);
+        }
+        if pressed(&[Key::ArrowLeft, Key::ArrowUp, Key::PageUp, Key::Backspace]) {
+            self.index = self.index.saturating_sub(1);
+        }
+        if pressed(&[Key::Home]) {
+            self.index = 0;
+        }
+        if pressed(&[Key::End]) {
+            self.index = last;
+        }
+        self.index = self.index.min(last);
+    }
+}
+
+/// One block: a divider as a title, a calculation as its equation, a
+/// command as a prompt line over its output
+fn show_slide(ui: &mut egui::Ui, block: &Block, font_size: f32) {
+    if block.is_divider {
+        ui.add_space(ui.available_height() / 3.0);
+        ui.vertical_centered(|ui| ui.heading(RichText::new(&block.command).size(font_size * 1.5).strong()));
+        return;
+    }
+    if block.is_calculation {
+        ui.label(RichText::new(format!("{} = {}", block.command, block.output)).monospace().size(font_size));
+        return;
+    }
+
+    ui.horizontal_wrapped(|ui| {
+        ui.label(RichText::new("$").monospace().size(font_size).color(ui.visuals().hyperlink_color));
+        let job = SyntaxHighlighter::new().command_layout_job(
+            &block.command,
+            egui::FontId::monospace(font_size),
+            ui.visuals().text_color(),
+        );
+        ui.label(job);
+    });
+    if let Some(input) = &block.original_input {
+        ui.label(RichText::new(format!("# {}", input)).monospace().size(font_size * 0.7).weak());
+    }
+    ui.add_space(font_size * 0.5);
+
+    let output = block.output.to_string();
+    let output = output.trim_end();
+    if !output.is_empty() {
+        ScrollArea::vertical()
+            .id_source(("presentation_output", block.id))
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                ui.label(RichText::new(output).monospace().size(font_size * 0.8));
+            });
+    }
+}
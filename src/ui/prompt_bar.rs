@@ -0,0 +1,129 @@
+use crate::config::PromptSegment;
+use crate::theme::Theme;
+use crate::utils::runtime_env::{active_node_version, active_venv, node_version_matches};
+use crate::utils::{abbreviate_home, GitStatus, ProjectRuntimes};
+use egui::{Color32, RichText, Ui};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Warp-style prompt row rendered above the command input
+pub struct PromptBar<'a> {
+    segments: &'a [PromptSegment],
+    theme: &'a Theme,
+    working_directory: &'a Path,
+    git_status: Option<&'a GitStatus>,
+    last_exit_code: Option<i32>,
+    environment: &'a HashMap<String, String>,
+    runtimes: Option<&'a ProjectRuntimes>,
+    font_size: f32,
+}
+
+impl<'a> PromptBar<'a> {
+    pub fn new(
+        segments: &'a [PromptSegment],
+        theme: &'a Theme,
+        working_directory: &'a Path,
+        environment: &'a HashMap<String, String>,
+        font_size: f32,
+    ) -> Self {
+        Self {
+            segments,
+            theme,
+            working_directory,
+            git_status: None,
+            last_exit_code: None,
+            environment,
+            runtimes: None,
+            font_size,
+        }
+    }
+
+    pub fn git_status(mut self, git_status: Option<&'a GitStatus>) -> Self {
+        self.git_status = git_status;
+        self
+    }
+
+    pub fn last_exit_code(mut self, code: Option<i32>) -> Self {
+        self.last_exit_code = code;
+        self
+    }
+
+    /// What the project in the working directory expects to run with
+    pub fn runtimes(mut self, runtimes: &'a ProjectRuntimes) -> Self {
+        self.runtimes = Some(runtimes);
+        self
+    }
+
+    /// Resolve a segment to its text and color, or None if it has nothing to show
+    fn segment(&self, segment: PromptSegment) -> Option<(String, Color32)> {
+        let colors = &self.theme.colors;
+        let syntax = &self.theme.syntax;
+
+        match segment {
+            PromptSegment::Cwd => Some((
+                abbreviate_home(self.working_directory),
+                syntax.function.to_egui(),
+            )),
+            PromptSegment::GitBranch => self.git_status.map(|git| {
+                let text = if git.is_dirty {
+                    format!("⎇ {}*", git.branch)
+                } else {
+                    format!("⎇ {}", git.branch)
+                };
+                (text, syntax.keyword.to_egui())
+            }),
+            PromptSegment::ExitCode => self.last_exit_code.map(|code| {
+                if code == 0 {
+                    ("✓".to_string(), colors.block_success.to_egui())
+                } else {
+                    (format!("✗ {}", code), colors.block_error.to_egui())
+                }
+            }),
+            PromptSegment::Time => Some((
+                chrono::Local::now().format("%H:%M:%S").to_string(),
+                colors.text_secondary.to_egui(),
+            )),
+            PromptSegment::Venv => match active_venv(self.environment) {
+                Some(name) => Some((format!("🐍 {}", name), syntax.type_name.to_egui())),
+                // The project has a virtualenv that isn't active
+                None => self.runtimes?.python_venv.as_ref().map(|venv| {
+                    let name = venv.file_name().unwrap_or_default().to_string_lossy();
+                    (format!("🐍 {} (inactive)", name), colors.ansi_yellow.to_egui())
+                }),
+            },
+            PromptSegment::Node => {
+                let pinned = self.runtimes.and_then(|r| r.node_version.as_deref());
+                match (active_node_version(self.environment), pinned) {
+                    (Some(active), Some(pinned)) if !node_version_matches(pinned, &active) => {
+                        Some((format!("⬢ {} (project: {})", active, pinned), colors.ansi_yellow.to_egui()))
+                    }
+                    (Some(active), _) => Some((format!("⬢ {}", active), syntax.string.to_egui())),
+                    (None, Some(pinned)) => Some((format!("⬢ {}", pinned), colors.text_secondary.to_egui())),
+                    (None, None) => None,
+                }
+            }
+        }
+    }
+
+    /// Returns true if anything was drawn
+    pub fn show(self, ui: &mut Ui) -> bool {
+        let parts: Vec<_> = self
+            .segments
+            .iter()
+            .filter_map(|s| self.segment(*s))
+            .collect();
+
+        if parts.is_empty() {
+            return false;
+        }
+
+        ui.horizontal(|ui| {
+            ui.spacing_mut().item_spacing.x = 10.0;
+            for (text, color) in parts {
+                ui.label(RichText::new(text).color(color).size(self.font_size - 2.0));
+            }
+        });
+
+        true
+    }
+}
@@ -0,0 +1,124 @@
+use crate::config::CopyOnSelect;
+use egui::text_selection::LabelSelectionState;
+use egui::{Context, Id, RawInput};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Target of the copy made this frame, read by the end-of-frame hook
+const COPYING: &str = "copy_on_select";
+
+/// Terminal-style copy-on-select for output: when a drag that selected text
+/// ends, egui is asked to copy the selection the next frame, and the copied
+/// text is moved to the primary selection if that's where it should go
+#[derive(Debug, Default)]
+pub struct SelectionCopy {
+    dragging: bool,
+    pending: bool,
+}
+
+impl SelectionCopy {
+    /// Register the hook that redirects the copy; once per context
+    pub fn install(ctx: &Context) {
+        ctx.on_end_frame(COPYING, Arc::new(redirect_copy));
+    }
+
+    /// Notice a selection drag ending; call every frame
+    pub fn update(&mut self, ctx: &Context, target: CopyOnSelect) {
+        if target == CopyOnSelect::Off {
+            self.dragging = false;
+            return;
+        }
+        let (dragging, released) = ctx.input(|i| (i.pointer.is_decidedly_dragging(), i.pointer.primary_released()));
+        self.dragging |= dragging;
+        if released && std::mem::take(&mut self.dragging) && LabelSelectionState::load(ctx).has_selection() {
+            self.pending = true;
+            ctx.request_repaint();
+        }
+    }
+
+    /// Add the copy request to the next frame's input; `Off` drops it
+    pub fn prepare_input(&mut self, ctx: &Context, raw_input: &mut RawInput, target: CopyOnSelect) {
+        if std::mem::take(&mut self.pending) && target != CopyOnSelect::Off {
+            raw_input.events.push(egui::Event::Copy);
+            ctx.data_mut(|d| d.insert_temp(Id::new(COPYING), target));
+        }
+    }
+}
+
+/// egui puts copied text on the clipboard; a copy-on-select meant for the
+/// primary selection is taken back out and set there instead
+fn redirect_copy(ctx: &Context) {
+    let Some(target) = ctx.data_mut(|d| d.remove_temp::<CopyOnSelect>(Id::new(COPYING))) else {
+        return;
+    };
+    if target != CopyOnSelect::Primary {
+        return;
+    }
+    let text = ctx.output_mut(|o| std::mem::take(&mut o.copied_text));
+    if !text.is_empty() {
+        set_primary(text);
+    }
+}
+
+/// The clipboard is kept open: on X11 what it holds is lost when it's dropped
+fn with_clipboard<T>(f: impl FnOnce(&mut arboard::Clipboard) -> Result<T, arboard::Error>) -> Option<T> {
+    static CLIPBOARD: OnceLock<Mutex<Option<arboard::Clipboard>>> = OnceLock::new();
+    let mut clipboard = CLIPBOARD.get_or_init(|| Mutex::new(None)).lock().ok()?;
+    if clipboard.is_none() {
+        *clipboard = arboard::Clipboard::new()
+            .map_err(|e| tracing::warn!("Clipboard unavailable: {}", e))
+            .ok();
+    }
+    f(clipboard.as_mut()?)
+        .map_err(|e| tracing::debug!("Clipboard access failed: {}", e))
+        .ok()
+}
+
+#[cfg(all(unix, not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))))]
+fn set_primary(text: String) {
+    use arboard::{LinuxClipboardKind, SetExtLinux};
+    with_clipboard(|c| c.set().clipboard(LinuxClipboardKind::Primary).text(text));
+}
+
+#[cfg(not(all(unix, not(any(target_os = "macos", target_os = "android", target_os = "emscripten")))))]
+fn set_primary(_text: String) {}
+
+/// What a middle-click pastes: the primary selection where there is one,
+/// otherwise the clipboard
+#[cfg(all(unix, not(any(target_os = "macos", target_os = "android", target_os = "emscripten"))))]
+pub fn paste_text() -> Option<String> {
+    use arboard::{GetExtLinux, LinuxClipboardKind};
+    let primary = with_clipboard(|c| c.get().clipboard(LinuxClipboardKind::Primary).text());
+    primary_or(primary, || with_clipboard(|c| c.get_text()))
+}
+
+#[cfg(not(all(unix, not(any(target_os = "macos", target_os = "android", target_os = "emscripten")))))]
+pub fn paste_text() -> Option<String> {
+    with_clipboard(|c| c.get_text())
+}
+
+/// The primary selection unless it's empty, else the clipboard
+#[cfg_attr(not(all(unix, not(any(target_os = "macos", target_os = "android", target_os = "emscripten")))), allow(dead_code))]
+fn primary_or(primary: Option<String>, clipboard: impl FnOnce() -> Option<String>) -> Option<String> {
+    primary.filter(|text| !text.is_empty()).or_else(clipboard)
+}
+
+/// Put an image on the clipboard; false when there's no clipboard to hold it
+pub fn copy_image(image: &egui::ColorImage) -> bool {
+    let [width, height] = image.size;
+    let data = arboard::ImageData { width, height, bytes: image.as_raw().into() };
+    with_clipboard(|c| c.set_image(data)).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_primary_falls_back_to_clipboard() {
+        let clipboard = || Some("from clipboard".to_string());
+        assert_eq!(primary_or(Some("selected".to_string()), clipboard).as_deref(), Some("selected"));
+        assert_eq!(primary_or(Some(String::new()), clipboard).as_deref(), Some("from clipboard"));
+        assert_eq!(primary_or(None, clipboard).as_deref(), Some("from clipboard"));
+        assert_eq!(primary_or(None, || None), None);
+    }
+}
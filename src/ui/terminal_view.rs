@@ -0,0 +1,303 @@
+use crate::shell::PtyInput;
+use egui::{Color32, Event, Key, Modifiers, Ui};
+use std::sync::mpsc::Sender;
+use uuid::Uuid;
+
+const DEFAULT_FG: Color32 = Color32::from_rgb(200, 200, 200);
+const DEFAULT_BG: Color32 = Color32::from_rgb(20, 20, 24);
+
+/// Escape sequences that switch a terminal to the alternate screen
+const ALTERNATE_SCREEN_ENTER: [&str; 3] = ["\x1b[?1049h", "\x1b[?1047h", "\x1b[?47h"];
+
+/// Position where `text` switches to the alternate screen (vim, htop, less...)
+pub fn find_alternate_screen_enter(text: &str) -> Option<usize> {
+    ALTERNATE_SCREEN_ENTER
+        .iter()
+        .filter_map(|sequence| text.find(sequence))
+        .min()
+}
+
+/// Full terminal emulator view for a block running a full-screen program.
+/// Output is rendered as a character grid and keyboard input goes straight to
+/// the program until it leaves the alternate screen.
+pub struct TerminalView {
+    pub block_id: Uuid,
+    parser: vt100::Parser,
+    input: Sender<PtyInput>,
+    size: (u16, u16),
+}
+
+impl TerminalView {
+    pub fn new(block_id: Uuid, input: Sender<PtyInput>) -> Self {
+        Self {
+            block_id,
+            parser: vt100::Parser::new(24, 80, 0),
+            input,
+            size: (24, 80),
+        }
+    }
+
+    pub fn process(&mut self, text: &str) {
+        self.parser.process(text.as_bytes());
+    }
+
+    /// Whether the program is still on the alternate screen
+    pub fn is_active(&self) -> bool {
+        self.parser.screen().alternate_screen()
+    }
+
+    pub fn title(&self) -> &str {
+        self.parser.screen().title()
+    }
+
+    /// Render the grid into `height` pixels of the available width, resizing
+    /// the terminal to fit, and forward keyboard input to the program
+    pub fn show(&mut self, ui: &mut Ui, height: f32, font_size: f32) {
+        let font = egui::FontId::monospace(font_size);
+        let (char_width, row_height) = ui.fonts(|f| (f.glyph_width(&font, 'M'), f.row_height(&font)));
+        let width = ui.available_width();
+
+        let cols = ((width / char_width).floor() as u16).max(10);
+        let rows = ((height / row_height).floor() as u16).max(5);
+        if (rows, cols) != self.size {
+            self.size = (rows, cols);
+            self.parser.set_size(rows, cols);
+            let _ = self.input.send(PtyInput::Resize { rows, cols });
+        }
+
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(width, height), egui::Sense::click());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, DEFAULT_BG);
+
+        let screen = self.parser.screen();
+        for row in 0..rows {
+            let y = rect.top() + row as f32 * row_height;
+            let mut job = egui::text::LayoutJob::default();
+
+            for col in 0..cols {
+                let Some(cell) = screen.cell(row, col) else {
+                    continue;
+                };
+                if cell.is_wide_continuation() {
+                    continue;
+                }
+
+                let (mut fg, mut bg) = (
+                    terminal_color(cell.fgcolor(), DEFAULT_FG),
+                    terminal_color(cell.bgcolor(), DEFAULT_BG),
+                );
+                if cell.inverse() {
+                    std::mem::swap(&mut fg, &mut bg);
+                }
+                if bg != DEFAULT_BG {
+                    let cells = if cell.is_wide() { 2.0 } else { 1.0 };
+                    painter.rect_filled(
+                        egui::Rect::from_min_size(
+                            egui::pos2(rect.left() + col as f32 * char_width, y),
+                            egui::vec2(char_width * cells, row_height),
+                        ),
+                        0.0,
+                        bg,
+                    );
+                }
+
+                let contents = cell.contents();
+                let text = if contents.is_empty() { " " } else { contents.as_str() };
+                job.append(
+                    text,
+                    0.0,
+                    egui::TextFormat {
+                        font_id: font.clone(),
+                        color: if cell.bold() { fg.gamma_multiply(1.2) } else { fg },
+                        underline: if cell.underline() {
+                            egui::Stroke::new(1.0, fg)
+                        } else {
+                            egui::Stroke::NONE
+                        },
+                        italics: cell.italic(),
+                        ..Default::default()
+                    },
+                );
+            }
+
+            let galley = ui.fonts(|f| f.layout_job(job));
+            painter.galley(egui::pos2(rect.left(), y), galley, DEFAULT_FG);
+        }
+
+        if !screen.hide_cursor() {
+            let (cursor_row, cursor_col) = screen.cursor_position();
+            painter.rect_filled(
+                egui::Rect::from_min_size(
+                    egui::pos2(
+                        rect.left() + cursor_col as f32 * char_width,
+                        rect.top() + cursor_row as f32 * row_height,
+                    ),
+                    egui::vec2(char_width, row_height),
+                ),
+                0.0,
+                DEFAULT_FG.gamma_multiply(0.6),
+            );
+        }
+
+        self.forward_input(ui);
+    }
+
+    /// Take keyboard events away from the rest of the UI and send them to the program
+    fn forward_input(&self, ui: &Ui) {
+        let application_cursor = self.parser.screen().application_cursor();
+        let bracketed_paste = self.parser.screen().bracketed_paste();
+        let mut bytes = Vec::new();
+
+        ui.ctx().input_mut(|input| {
+            input.events.retain(|event| {
+                match event {
+                    Event::Text(text) => bytes.extend_from_slice(text.as_bytes()),
+                    Event::Paste(text) => {
+                        if bracketed_paste {
+                            bytes.extend_from_slice(b"\x1b[200~");
+                            bytes.extend_from_slice(text.as_bytes());
+                            bytes.extend_from_slice(b"\x1b[201~");
+                        } else {
+                            bytes.extend_from_slice(text.as_bytes());
+                        }
+                    }
+                    // egui turns Ctrl+C / Ctrl+X into clipboard events
+                    Event::Copy => bytes.push(0x03),
+                    Event::Cut => bytes.push(0x18),
+                    Event::Key { key, pressed: true, modifiers, .. } => {
+                        match key_to_bytes(*key, *modifiers, application_cursor) {
+                            Some(sequence) => bytes.extend_from_slice(&sequence),
+                            None => return true,
+                        }
+                    }
+                    Event::Key { .. } => {}
+                    _ => return true,
+                }
+                false
+            });
+        });
+
+        if !bytes.is_empty() {
+            let _ = self.input.send(PtyInput::Bytes(bytes));
+        }
+    }
+}
+
+/// Bytes a terminal sends for a key press. Printable keys without Ctrl return
+/// None because they also arrive as text events.
+pub fn key_to_bytes(key: Key, modifiers: Modifiers, application_cursor: bool) -> Option<Vec<u8>> {
+    let arrow = |code: u8| {
+        if application_cursor {
+            vec![0x1b, b'O', code]
+        } else {
+            vec![0x1b, b'[', code]
+        }
+    };
+
+    let bytes = match key {
+        Key::Enter => vec![b'\r'],
+        Key::Tab if modifiers.shift => b"\x1b[Z".to_vec(),
+        Key::Tab => vec![b'\t'],
+        Key::Backspace => vec![0x7f],
+        Key::Escape => vec![0x1b],
+        Key::ArrowUp => arrow(b'A'),
+        Key::ArrowDown => arrow(b'B'),
+        Key::ArrowRight => arrow(b'C'),
+        Key::ArrowLeft => arrow(b'D'),
+        Key::Home => b"\x1b[H".to_vec(),
+        Key::End => b"\x1b[F".to_vec(),
+        Key::Insert => b"\x1b[2~".to_vec(),
+        Key::Delete => b"\x1b[3~".to_vec(),
+        Key::PageUp => b"\x1b[5~".to_vec(),
+        Key::PageDown => b"\x1b[6~".to_vec(),
+        Key::F1 => b"\x1bOP".to_vec(),
+        Key::F2 => b"\x1bOQ".to_vec(),
+        Key::F3 => b"\x1bOR".to_vec(),
+        Key::F4 => b"\x1bOS".to_vec(),
+        Key::F5 => b"\x1b[15~".to_vec(),
+        Key::F6 => b"\x1b[17~".to_vec(),
+        Key::F7 => b"\x1b[18~".to_vec(),
+        Key::F8 => b"\x1b[19~".to_vec(),
+        Key::F9 => b"\x1b[20~".to_vec(),
+        Key::F10 => b"\x1b[21~".to_vec(),
+        _ if modifiers.ctrl => {
+            // Ctrl+letter sends the matching control character (Ctrl+A = 0x01)
+            let name = key.name();
+            let letter = name.chars().next().filter(|c| name.len() == 1 && c.is_ascii_alphabetic())?;
+            vec![letter.to_ascii_uppercase() as u8 & 0x1f]
+        }
+        _ => return None,
+    };
+    Some(bytes)
+}
+
+/// Map a terminal color to egui, using the xterm 256-color palette
+fn terminal_color(color: vt100::Color, default: Color32) -> Color32 {
+    const ANSI: [(u8, u8, u8); 16] = [
+        (0, 0, 0),
+        (205, 49, 49),
+        (13, 188, 121),
+        (229, 229, 16),
+        (36, 114, 200),
+        (188, 63, 188),
+        (17, 168, 205),
+        (229, 229, 229),
+        (102, 102, 102),
+        (241, 76, 76),
+        (35, 209, 139),
+        (245, 245, 67),
+        (59, 142, 234),
+        (214, 112, 214),
+        (41, 184, 219),
+        (255, 255, 255),
+    ];
+
+    match color {
+        vt100::Color::Default => default,
+        vt100::Color::Rgb(r, g, b) => Color32::from_rgb(r, g, b),
+        vt100::Color::Idx(i) if i < 16 => {
+            let (r, g, b) = ANSI[i as usize];
+            Color32::from_rgb(r, g, b)
+        }
+        vt100::Color::Idx(i) if i < 232 => {
+            // 6x6x6 color cube
+            let i = i - 16;
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            Color32::from_rgb(level(i / 36), level((i / 6) % 6), level(i % 6))
+        }
+        vt100::Color::Idx(i) => {
+            let gray = 8 + (i - 232) * 10;
+            Color32::from_rgb(gray, gray, gray)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_alternate_screen_enter() {
+        assert_eq!(find_alternate_screen_enter("plain output\n"), None);
+        assert_eq!(find_alternate_screen_enter("ab\x1b[?1049h\x1b[H"), Some(2));
+        assert_eq!(find_alternate_screen_enter("\x1b[?47h"), Some(0));
+    }
+
+    #[test]
+    fn test_key_to_bytes() {
+        let none = Modifiers::NONE;
+        assert_eq!(key_to_bytes(Key::Enter, none, false), Some(b"\r".to_vec()));
+        assert_eq!(key_to_bytes(Key::ArrowUp, none, false), Some(b"\x1b[A".to_vec()));
+        assert_eq!(key_to_bytes(Key::ArrowUp, none, true), Some(b"\x1bOA".to_vec()));
+        assert_eq!(key_to_bytes(Key::D, Modifiers::CTRL, false), Some(vec![0x04]));
+        // Plain letters arrive as text events instead
+        assert_eq!(key_to_bytes(Key::D, none, false), None);
+    }
+
+    #[test]
+    fn test_terminal_color_palette() {
+        assert_eq!(terminal_color(vt100::Color::Default, DEFAULT_FG), DEFAULT_FG);
+        assert_eq!(terminal_color(vt100::Color::Idx(196), DEFAULT_FG), Color32::from_rgb(255, 0, 0));
+        assert_eq!(terminal_color(vt100::Color::Idx(232), DEFAULT_FG), Color32::from_rgb(8, 8, 8));
+    }
+}
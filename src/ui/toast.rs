@@ -0,0 +1,93 @@
+use egui::{Align2, Context, Frame, Id, Order};
+use std::collections::VecDeque;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+/// How long a notice stays up
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+/// Older notices are dropped beyond this many
+const MAX_TOASTS: usize = 4;
+
+/// Posts notices from another thread or task
+pub type ToastSender = Sender<String>;
+
+/// Short notices stacked in the bottom-right corner, so the results of
+/// unrelated actions don't overwrite each other. Background work posts
+/// through a [`ToastSender`].
+pub struct Toasts {
+    shown: VecDeque<(String, Instant)>,
+    sender: Sender<String>,
+    receiver: Receiver<String>,
+}
+
+impl Default for Toasts {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        Self { shown: VecDeque::new(), sender, receiver }
+    }
+}
+
+impl Toasts {
+    pub fn push(&mut self, text: impl Into<String>) {
+        self.push_at(text.into(), Instant::now());
+    }
+
+    pub fn sender(&self) -> ToastSender {
+        self.sender.clone()
+    }
+
+    fn push_at(&mut self, text: String, now: Instant) {
+        self.shown.push_back((text, now));
+        while self.shown.len() > MAX_TOASTS {
+            self.shown.pop_front();
+        }
+    }
+
+    /// Take posted notices and drop the ones that have been up long enough
+    fn update(&mut self, now: Instant) {
+        while let Ok(text) = self.receiver.try_recv() {
+            self.push_at(text, now);
+        }
+        self.shown.retain(|(_, shown_at)| now.duration_since(*shown_at) < TOAST_DURATION);
+    }
+
+    pub fn show(&mut self, ctx: &Context) {
+        self.update(Instant::now());
+        let Some((_, oldest)) = self.shown.front() else {
+            return;
+        };
+        ctx.request_repaint_after(TOAST_DURATION.saturating_sub(oldest.elapsed()));
+        egui::Area::new(Id::new("toasts"))
+            .order(Order::Foreground)
+            .anchor(Align2::RIGHT_BOTTOM, [-12.0, -36.0])
+            .interactable(false)
+            .show(ctx, |ui| {
+                for (text, _) in &self.shown {
+                    Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.label(text);
+                    });
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toasts_expire_and_stay_few() {
+        let mut toasts = Toasts::default();
+        let start = Instant::now();
+        for i in 0..6 {
+            toasts.push_at(format!("notice {}", i), start);
+        }
+        toasts.sender().send("from a task".to_string()).unwrap();
+        toasts.update(start);
+        let texts: Vec<_> = toasts.shown.iter().map(|(text, _)| text.as_str()).collect();
+        assert_eq!(texts, ["notice 3", "notice 4", "notice 5", "from a task"]);
+
+        toasts.update(start + TOAST_DURATION);
+        assert!(toasts.shown.is_empty());
+    }
+}
@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+
+/// Register used when no `"x` prefix is given
+const UNNAMED_REGISTER: char = '"';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VimMode {
+    Normal,
+    Insert,
+}
+
+impl VimMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            VimMode::Normal => "NORMAL",
+            VimMode::Insert => "INSERT",
+        }
+    }
+}
+
+/// Modal editing state for a single-line input.
+/// Cursor positions are char indices into the text.
+#[derive(Debug, Clone)]
+pub struct VimState {
+    mode: VimMode,
+    registers: HashMap<char, String>,
+    pending_operator: Option<char>,
+    awaiting_register: bool,
+    active_register: Option<char>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CharClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+fn class_of(c: char) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Whitespace
+    } else if c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
+
+fn byte_index(text: &str, char_index: usize) -> usize {
+    text.char_indices()
+        .nth(char_index)
+        .map(|(i, _)| i)
+        .unwrap_or(text.len())
+}
+
+impl VimState {
+    pub fn new() -> Self {
+        Self {
+            mode: VimMode::Insert,
+            registers: HashMap::new(),
+            pending_operator: None,
+            awaiting_register: false,
+            active_register: None,
+        }
+    }
+
+    pub fn mode(&self) -> VimMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: VimMode) {
+        self.mode = mode;
+        self.pending_operator = None;
+        self.awaiting_register = false;
+        self.active_register = None;
+    }
+
+    pub fn register(&self, name: char) -> Option<&str> {
+        self.registers.get(&name).map(|s| s.as_str())
+    }
+
+    /// Leave insert mode (Escape); the cursor steps back onto the last inserted char
+    pub fn enter_normal(&mut self, cursor: &mut usize) {
+        self.set_mode(VimMode::Normal);
+        *cursor = cursor.saturating_sub(1);
+    }
+
+    /// Handle a typed character while in normal mode
+    pub fn handle_normal_key(&mut self, key: char, text: &mut String, cursor: &mut usize) {
+        let len = text.chars().count();
+
+        if self.awaiting_register {
+            self.awaiting_register = false;
+            if key.is_ascii_alphanumeric() {
+                self.active_register = Some(key.to_ascii_lowercase());
+            }
+            return;
+        }
+
+        if let Some(op) = self.pending_operator.take() {
+            self.apply_operator(op, key, text, cursor);
+            return;
+        }
+
+        match key {
+            '"' => self.awaiting_register = true,
+            // Motions
+            'h' => *cursor = cursor.saturating_sub(1),
+            'l' => *cursor = (*cursor + 1).min(len.saturating_sub(1)),
+            '0' => *cursor = 0,
+            '^' => *cursor = Self::first_non_blank(text),
+            '$' => *cursor = len.saturating_sub(1),
+            'w' | 'b' | 'e' => {
+                *cursor = Self::motion(key, text, *cursor).min(len.saturating_sub(1));
+            }
+            // Entering insert mode
+            'i' => self.set_mode(VimMode::Insert),
+            'a' => {
+                *cursor = (*cursor + 1).min(len);
+                self.set_mode(VimMode::Insert);
+            }
+            'I' => {
+                *cursor = Self::first_non_blank(text);
+                self.set_mode(VimMode::Insert);
+            }
+            'A' => {
+                *cursor = len;
+                self.set_mode(VimMode::Insert);
+            }
+            // Editing
+            'x' => {
+                if *cursor < len {
+                    self.delete_range(text, *cursor, *cursor + 1, true);
+                    *cursor = (*cursor).min(text.chars().count().saturating_sub(1));
+                }
+                self.active_register = None;
+            }
+            'X' => {
+                if *cursor > 0 {
+                    self.delete_range(text, *cursor - 1, *cursor, true);
+                    *cursor -= 1;
+                }
+                self.active_register = None;
+            }
+            's' => {
+                if *cursor < len {
+                    self.delete_range(text, *cursor, *cursor + 1, true);
+                }
+                self.set_mode(VimMode::Insert);
+            }
+            'D' | 'C' => {
+                self.delete_range(text, *cursor, len, true);
+                if key == 'C' {
+                    self.set_mode(VimMode::Insert);
+                } else {
+                    *cursor = cursor.saturating_sub(1).min(text.chars().count().saturating_sub(1));
+                    self.active_register = None;
+                }
+            }
+            'S' => {
+                self.delete_range(text, 0, len, true);
+                *cursor = 0;
+                self.set_mode(VimMode::Insert);
+            }
+            'p' | 'P' => {
+                let register = self.active_register.take().unwrap_or(UNNAMED_REGISTER);
+                if let Some(content) = self.registers.get(&register).cloned() {
+                    let at = if key == 'p' && len > 0 { *cursor + 1 } else { *cursor };
+                    let at = at.min(len);
+                    text.insert_str(byte_index(text, at), &content);
+                    *cursor = at + content.chars().count().saturating_sub(1);
+                }
+            }
+            'd' | 'c' | 'y' => self.pending_operator = Some(key),
+            _ => {}
+        }
+    }
+
+    /// Apply an operator (d/c/y) followed by a motion or a doubled operator
+    fn apply_operator(&mut self, op: char, motion: char, text: &mut String, cursor: &mut usize) {
+        let len = text.chars().count();
+
+        let (start, end) = if motion == op {
+            // dd / cc / yy operate on the whole line
+            (0, len)
+        } else {
+            // cw behaves like ce in vim
+            let motion = if op == 'c' && motion == 'w' { 'e' } else { motion };
+            let target = match motion {
+                'w' | 'b' | 'e' => Self::motion(motion, text, *cursor),
+                '0' => 0,
+                '^' => Self::first_non_blank(text),
+                '$' => len,
+                'h' => cursor.saturating_sub(1),
+                'l' => (*cursor + 1).min(len),
+                _ => {
+                    self.active_register = None;
+                    return;
+                }
+            };
+            let inclusive = motion == 'e';
+            if target >= *cursor {
+                (*cursor, (target + usize::from(inclusive)).min(len))
+            } else {
+                (target, *cursor)
+            }
+        };
+
+        match op {
+            'y' => {
+                let yanked: String = text.chars().skip(start).take(end - start).collect();
+                self.store_register(yanked);
+                *cursor = start.min(len.saturating_sub(1));
+            }
+            _ => {
+                self.delete_range(text, start, end, false);
+                *cursor = start;
+                if op == 'c' {
+                    self.set_mode(VimMode::Insert);
+                } else {
+                    *cursor = start.min(text.chars().count().saturating_sub(1));
+                }
+            }
+        }
+        self.active_register = None;
+    }
+
+    /// Remove chars in [start, end) storing them in the active register
+    fn delete_range(&mut self, text: &mut String, start: usize, end: usize, keep_register: bool) {
+        if start >= end {
+            return;
+        }
+        let (from, to) = (byte_index(text, start), byte_index(text, end));
+        let removed: String = text.drain(from..to).collect();
+        self.store_register(removed);
+        if !keep_register {
+            self.active_register = None;
+        }
+    }
+
+    fn store_register(&mut self, content: String) {
+        if let Some(name) = self.active_register {
+            self.registers.insert(name, content.clone());
+        }
+        self.registers.insert(UNNAMED_REGISTER, content);
+    }
+
+    fn first_non_blank(text: &str) -> usize {
+        text.chars().position(|c| !c.is_whitespace()).unwrap_or(0)
+    }
+
+    /// Compute the target of a word motion
+    fn motion(key: char, text: &str, cursor: usize) -> usize {
+        let chars: Vec<char> = text.chars().collect();
+        let len = chars.len();
+        let mut pos = cursor;
+
+        match key {
+            'w' => {
+                if pos < len {
+                    let class = class_of(chars[pos]);
+                    if class != CharClass::Whitespace {
+                        while pos < len && class_of(chars[pos]) == class {
+                            pos += 1;
+                        }
+                    }
+                }
+                while pos < len && class_of(chars[pos]) == CharClass::Whitespace {
+                    pos += 1;
+                }
+                pos
+            }
+            'b' => {
+                if pos == 0 {
+                    return 0;
+                }
+                pos -= 1;
+                while pos > 0 && class_of(chars[pos]) == CharClass::Whitespace {
+                    pos -= 1;
+                }
+                let class = class_of(chars[pos]);
+                while pos > 0 && class_of(chars[pos - 1]) == class {
+                    pos -= 1;
+                }
+                pos
+            }
+            'e' => {
+                if pos + 1 >= len {
+                    return len.saturating_sub(1);
+                }
+                pos += 1;
+                while pos + 1 < len && class_of(chars[pos]) == CharClass::Whitespace {
+                    pos += 1;
+                }
+                let class = class_of(chars[pos]);
+                while pos + 1 < len && class_of(chars[pos + 1]) == class {
+                    pos += 1;
+                }
+                pos
+            }
+            _ => cursor,
+        }
+    }
+}
+
+impl Default for VimState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normal(text: &str, cursor: usize) -> (VimState, String, usize) {
+        let mut vim = VimState::new();
+        vim.set_mode(VimMode::Normal);
+        (vim, text.to_string(), cursor)
+    }
+
+    fn keys(vim: &mut VimState, text: &mut String, cursor: &mut usize, input: &str) {
+        for c in input.chars() {
+            vim.handle_normal_key(c, text, cursor);
+        }
+    }
+
+    #[test]
+    fn test_word_motions() {
+        let (mut vim, mut text, mut cursor) = normal("git commit -m msg", 0);
+        keys(&mut vim, &mut text, &mut cursor, "w");
+        assert_eq!(cursor, 4);
+        keys(&mut vim, &mut text, &mut cursor, "e");
+        assert_eq!(cursor, 9);
+        keys(&mut vim, &mut text, &mut cursor, "b");
+        assert_eq!(cursor, 4);
+        keys(&mut vim, &mut text, &mut cursor, "$");
+        assert_eq!(cursor, 16);
+        keys(&mut vim, &mut text, &mut cursor, "0");
+        assert_eq!(cursor, 0);
+    }
+
+    #[test]
+    fn test_delete_word_and_paste() {
+        let (mut vim, mut text, mut cursor) = normal("ls -la /tmp", 0);
+        keys(&mut vim, &mut text, &mut cursor, "dw");
+        assert_eq!(text, "-la /tmp");
+        assert_eq!(vim.register('"'), Some("ls "));
+        keys(&mut vim, &mut text, &mut cursor, "$p");
+        assert_eq!(text, "-la /tmpls ");
+    }
+
+    #[test]
+    fn test_change_word_enters_insert() {
+        let (mut vim, mut text, mut cursor) = normal("echo hello world", 5);
+        keys(&mut vim, &mut text, &mut cursor, "cw");
+        assert_eq!(text, "echo  world");
+        assert_eq!(cursor, 5);
+        assert_eq!(vim.mode(), VimMode::Insert);
+    }
+
+    #[test]
+    fn test_named_registers() {
+        let (mut vim, mut text, mut cursor) = normal("make build", 0);
+        keys(&mut vim, &mut text, &mut cursor, "\"ayw");
+        assert_eq!(vim.register('a'), Some("make "));
+        keys(&mut vim, &mut text, &mut cursor, "x");
+        assert_eq!(text, "ake build");
+        // Unnamed register was overwritten, 'a' was not
+        assert_eq!(vim.register('"'), Some("m"));
+        keys(&mut vim, &mut text, &mut cursor, "\"aP");
+        assert_eq!(text, "make ake build");
+    }
+
+    #[test]
+    fn test_dd_and_append() {
+        let (mut vim, mut text, mut cursor) = normal("rm -rf /", 3);
+        keys(&mut vim, &mut text, &mut cursor, "dd");
+        assert!(text.is_empty());
+        assert_eq!(cursor, 0);
+
+        let (mut vim, mut text, mut cursor) = normal("ls", 0);
+        keys(&mut vim, &mut text, &mut cursor, "A");
+        assert_eq!(cursor, 2);
+        assert_eq!(vim.mode(), VimMode::Insert);
+    }
+
+    #[test]
+    fn test_escape_moves_cursor_back() {
+        let mut vim = VimState::new();
+        let mut cursor = 4;
+        vim.enter_normal(&mut cursor);
+        assert_eq!(vim.mode(), VimMode::Normal);
+        assert_eq!(cursor, 3);
+    }
+}
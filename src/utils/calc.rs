@@ -0,0 +1,330 @@
+/// Evaluate an arithmetic expression typed after the calculator prefix:
+/// numbers (`1.5`, `2e3`, `0xff`, `0b101`, `1_000`), `+ - * / %`, `^` or
+/// `**` for powers, parentheses, the constants `pi` and `e`, and
+/// `sqrt abs round floor ceil ln log2 log10 min max`
+pub fn evaluate(expr: &str) -> Result<f64, CalcError> {
+    let tokens = tokenize(expr)?;
+    if tokens.is_empty() {
+        return Err(CalcError::Empty);
+    }
+    let mut parser = Parser { tokens, pos: 0 };
+    let value = parser.expression()?;
+    if let Some(token) = parser.tokens.get(parser.pos) {
+        return Err(CalcError::Unexpected(token.to_string()));
+    }
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(CalcError::NotFinite)
+    }
+}
+
+/// Why an expression could not be evaluated; the UI turns it into a
+/// message. Tokens and names are kept as typed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcError {
+    /// Only whitespace after the prefix
+    Empty,
+    InvalidNumber(String),
+    Unexpected(String),
+    Expected { expected: String, found: Option<String> },
+    EndsEarly,
+    UnknownName(String),
+    UnknownFunction(String),
+    /// A one-argument function called with a different number
+    OneArgument(String),
+    /// `min()` or `max()` without arguments
+    NeedsArguments(String),
+    DivisionByZero,
+    NotFinite,
+}
+
+/// Whole numbers without a fraction, everything else with up to ten decimals
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+    let text = format!("{:.10}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+    Open,
+    Close,
+    Comma,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "{}", format_number(*n)),
+            Token::Ident(name) => write!(f, "{}", name),
+            Token::Op(op) => write!(f, "{}", op),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+            Token::Comma => write!(f, ","),
+        }
+    }
+}
+
+fn tokenize(expr: &str) -> Result<Vec<Token>, CalcError> {
+    let chars: Vec<char> = expr.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '0'..='9' | '.' => {
+                let start = i;
+                let radix = match (c, chars.get(i + 1)) {
+                    ('0', Some('x' | 'X')) => 16,
+                    ('0', Some('b' | 'B')) => 2,
+                    ('0', Some('o' | 'O')) => 8,
+                    _ => 10,
+                };
+                if radix != 10 {
+                    i += 2;
+                    let digits_start = i;
+                    while i < chars.len() && (chars[i].is_digit(radix) || chars[i] == '_') {
+                        i += 1;
+                    }
+                    let digits: String = chars[digits_start..i].iter().filter(|c| **c != '_').collect();
+                    let value = u64::from_str_radix(&digits, radix)
+                        .map_err(|_| CalcError::InvalidNumber(chars[start..i].iter().collect()))?;
+                    tokens.push(Token::Number(value as f64));
+                    continue;
+                }
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.' || chars[i] == '_') {
+                    i += 1;
+                }
+                // Exponent, e.g. 2e3 or 1.5E-2
+                if i < chars.len() && matches!(chars[i], 'e' | 'E') {
+                    let mut j = i + 1;
+                    if j < chars.len() && matches!(chars[j], '+' | '-') {
+                        j += 1;
+                    }
+                    if j < chars.len() && chars[j].is_ascii_digit() {
+                        i = j;
+                        while i < chars.len() && chars[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                    }
+                }
+                let text: String = chars[start..i].iter().filter(|c| **c != '_').collect();
+                let value = text.parse::<f64>().map_err(|_| CalcError::InvalidNumber(text))?;
+                tokens.push(Token::Number(value));
+            }
+            c if c.is_alphabetic() => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect::<String>().to_lowercase()));
+            }
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                tokens.push(Token::Op('^'));
+                i += 2;
+            }
+            '+' | '-' | '*' | '/' | '%' | '^' => {
+                tokens.push(Token::Op(c));
+                i += 1;
+            }
+            '(' => {
+                tokens.push(Token::Open);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::Close);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            other => return Err(CalcError::Unexpected(other.to_string())),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over the tokens; `^` binds tighter than unary minus,
+/// so `-2^2` is -4
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), CalcError> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            found => Err(CalcError::Expected {
+                expected: expected.to_string(),
+                found: found.map(|token| token.to_string()),
+            }),
+        }
+    }
+
+    fn expression(&mut self) -> Result<f64, CalcError> {
+        let mut value = self.term()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.pos += 1;
+            let rhs = self.term()?;
+            value = if op == '+' { value + rhs } else { value - rhs };
+        }
+        Ok(value)
+    }
+
+    fn term(&mut self) -> Result<f64, CalcError> {
+        let mut value = self.unary()?;
+        while let Some(Token::Op(op @ ('*' | '/' | '%'))) = self.peek().cloned() {
+            self.pos += 1;
+            let rhs = self.unary()?;
+            value = match op {
+                '*' => value * rhs,
+                _ if rhs == 0.0 => return Err(CalcError::DivisionByZero),
+                '/' => value / rhs,
+                _ => value % rhs,
+            };
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<f64, CalcError> {
+        match self.peek() {
+            Some(Token::Op('-')) => {
+                self.pos += 1;
+                Ok(-self.unary()?)
+            }
+            Some(Token::Op('+')) => {
+                self.pos += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> Result<f64, CalcError> {
+        let base = self.primary()?;
+        if let Some(Token::Op('^')) = self.peek() {
+            self.pos += 1;
+            let exponent = self.unary()?;
+            return Ok(base.powf(exponent));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<f64, CalcError> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(n),
+            Some(Token::Open) => {
+                let value = self.expression()?;
+                self.expect(Token::Close)?;
+                Ok(value)
+            }
+            Some(Token::Ident(name)) => {
+                if self.peek() != Some(&Token::Open) {
+                    return match name.as_str() {
+                        "pi" => Ok(std::f64::consts::PI),
+                        "e" => Ok(std::f64::consts::E),
+                        _ => Err(CalcError::UnknownName(name)),
+                    };
+                }
+                self.pos += 1;
+                let mut args = vec![self.expression()?];
+                while self.peek() == Some(&Token::Comma) {
+                    self.pos += 1;
+                    args.push(self.expression()?);
+                }
+                self.expect(Token::Close)?;
+                call(&name, &args)
+            }
+            Some(token) => Err(CalcError::Unexpected(token.to_string())),
+            None => Err(CalcError::EndsEarly),
+        }
+    }
+}
+
+fn call(name: &str, args: &[f64]) -> Result<f64, CalcError> {
+    let one = |f: fn(f64) -> f64| match args {
+        [x] => Ok(f(*x)),
+        _ => Err(CalcError::OneArgument(name.to_string())),
+    };
+    match name {
+        "sqrt" => one(f64::sqrt),
+        "abs" => one(f64::abs),
+        "round" => one(f64::round),
+        "floor" => one(f64::floor),
+        "ceil" => one(f64::ceil),
+        "ln" => one(f64::ln),
+        "log2" => one(f64::log2),
+        "log10" | "log" => one(f64::log10),
+        "min" => args.iter().copied().reduce(f64::min).ok_or_else(|| CalcError::NeedsArguments(name.to_string())),
+        "max" => args.iter().copied().reduce(f64::max).ok_or_else(|| CalcError::NeedsArguments(name.to_string())),
+        _ => Err(CalcError::UnknownFunction(name.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(evaluate("4096*3/1024").unwrap(), 12.0);
+        assert_eq!(evaluate("2 + 3 * 4").unwrap(), 14.0);
+        assert_eq!(evaluate("(2 + 3) * 4").unwrap(), 20.0);
+        assert_eq!(evaluate("2^10").unwrap(), 1024.0);
+        assert_eq!(evaluate("2**3**2").unwrap(), 512.0);
+        assert_eq!(evaluate("-2^2").unwrap(), -4.0);
+        assert_eq!(evaluate("17 % 5").unwrap(), 2.0);
+        assert_eq!(evaluate("1_000 * 1.5e3").unwrap(), 1_500_000.0);
+        assert_eq!(evaluate("0xff + 0b101 + 0o10").unwrap(), 268.0);
+        assert_eq!(evaluate("max(3, sqrt(16), 2)").unwrap(), 4.0);
+        assert!((evaluate("2 * pi").unwrap() - std::f64::consts::TAU).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(evaluate("1 / 0").unwrap_err(), CalcError::DivisionByZero);
+        assert_eq!(evaluate("").unwrap_err(), CalcError::Empty);
+        assert_eq!(evaluate("2 +").unwrap_err(), CalcError::EndsEarly);
+        assert_eq!(
+            evaluate("(1 + 2").unwrap_err(),
+            CalcError::Expected { expected: ")".to_string(), found: None }
+        );
+        assert_eq!(
+            evaluate("(1 + 2,").unwrap_err(),
+            CalcError::Expected { expected: ")".to_string(), found: Some(",".to_string()) }
+        );
+        assert_eq!(evaluate("1 2").unwrap_err(), CalcError::Unexpected("2".to_string()));
+        assert_eq!(evaluate("1 $").unwrap_err(), CalcError::Unexpected("$".to_string()));
+        assert_eq!(evaluate("ls -la").unwrap_err(), CalcError::UnknownName("ls".to_string()));
+        assert_eq!(evaluate("foo(1)").unwrap_err(), CalcError::UnknownFunction("foo".to_string()));
+        assert_eq!(evaluate("sqrt(1, 2)").unwrap_err(), CalcError::OneArgument("sqrt".to_string()));
+        assert_eq!(evaluate("sqrt(-1)").unwrap_err(), CalcError::NotFinite);
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(12.0), "12");
+        assert_eq!(format_number(-3.0), "-3");
+        assert_eq!(format_number(0.1 + 0.2), "0.3");
+        assert_eq!(format_number(1.0 / 3.0), "0.3333333333");
+    }
+}
@@ -0,0 +1,107 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Outcome of evaluating direnv for a directory
+#[derive(Debug, Clone, PartialEq)]
+pub enum DirenvStatus {
+    /// No .envrc applies to the directory
+    NotFound,
+    /// An .envrc exists but has not been allowed yet
+    Blocked(PathBuf),
+    /// Variables exported by the allowed .envrc
+    Applied(HashMap<String, String>),
+}
+
+/// Find the .envrc that applies to `dir` (direnv searches parent directories too)
+pub fn find_envrc(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(".envrc"))
+        .find(|p| p.is_file())
+}
+
+/// Evaluate direnv in `dir`, like a shell hook would on entering it
+pub fn export(dir: &Path) -> Result<DirenvStatus> {
+    let Some(envrc) = find_envrc(dir) else {
+        return Ok(DirenvStatus::NotFound);
+    };
+
+    let output = Command::new("direnv")
+        .args(["export", "json"])
+        .current_dir(dir)
+        .output()
+        .context("Failed to run direnv (is it installed?)")?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.contains("is blocked") {
+        return Ok(DirenvStatus::Blocked(envrc));
+    }
+    if !output.status.success() {
+        bail!("direnv export failed: {}", stderr.trim());
+    }
+
+    Ok(DirenvStatus::Applied(parse_export(&String::from_utf8_lossy(&output.stdout))))
+}
+
+/// Mark an .envrc as trusted (`direnv allow`)
+pub fn allow(envrc: &Path) -> Result<()> {
+    let status = Command::new("direnv")
+        .arg("allow")
+        .arg(envrc)
+        .status()
+        .context("Failed to run direnv allow")?;
+
+    if !status.success() {
+        bail!("direnv allow failed for {}", envrc.display());
+    }
+    Ok(())
+}
+
+/// Parse `direnv export json`. Unset variables (null) and direnv's own
+/// bookkeeping variables are skipped.
+pub fn parse_export(json: &str) -> HashMap<String, String> {
+    if json.trim().is_empty() {
+        return HashMap::new();
+    }
+
+    let values: HashMap<String, Option<String>> = match serde_json::from_str(json) {
+        Ok(values) => values,
+        Err(e) => {
+            tracing::warn!("Failed to parse direnv output: {}", e);
+            return HashMap::new();
+        }
+    };
+
+    values
+        .into_iter()
+        .filter(|(key, _)| !key.starts_with("DIRENV_"))
+        .filter_map(|(key, value)| value.map(|v| (key, v)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_export() {
+        let json = r#"{"AWS_PROFILE":"dev","OLD_VAR":null,"DIRENV_DIR":"-/src/app","DIRENV_DIFF":"abc"}"#;
+        let vars = parse_export(json);
+        assert_eq!(vars.len(), 1);
+        assert_eq!(vars.get("AWS_PROFILE").map(String::as_str), Some("dev"));
+        assert!(parse_export("").is_empty());
+    }
+
+    #[test]
+    fn test_find_envrc_in_parent() {
+        let root = tempdir().unwrap();
+        let nested = root.path().join("src/module");
+        std::fs::create_dir_all(&nested).unwrap();
+        assert_eq!(find_envrc(&nested).filter(|p| p.starts_with(root.path())), None);
+
+        std::fs::write(root.path().join(".envrc"), "export FOO=bar\n").unwrap();
+        assert_eq!(find_envrc(&nested), Some(root.path().join(".envrc")));
+    }
+}
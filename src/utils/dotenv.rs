@@ -0,0 +1,107 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Read and parse a .env file
+pub fn load(path: &Path) -> Result<Vec<(String, String)>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(parse(&content))
+}
+
+/// Parse .env content: `KEY=value` lines with optional `export ` prefix,
+/// `#` comments and single/double quoted values. Invalid lines are skipped.
+pub fn parse(content: &str) -> Vec<(String, String)> {
+    let mut vars = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+
+        let key = key.trim();
+        let is_valid_key = key
+            .chars()
+            .next()
+            .map(|c| c.is_ascii_alphabetic() || c == '_')
+            .unwrap_or(false)
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_valid_key {
+            continue;
+        }
+
+        vars.push((key.to_string(), parse_value(value.trim())));
+    }
+
+    vars
+}
+
+fn parse_value(value: &str) -> String {
+    if let Some(inner) = value.strip_prefix('"').and_then(|v| v.rfind('"').map(|end| &v[..end])) {
+        return inner
+            .replace("\\n", "\n")
+            .replace("\\\"", "\"")
+            .replace("\\\\", "\\");
+    }
+
+    if let Some(inner) = value.strip_prefix('\'').and_then(|v| v.rfind('\'').map(|end| &v[..end])) {
+        return inner.to_string();
+    }
+
+    // Unquoted: strip trailing inline comments
+    match value.find(" #") {
+        Some(pos) => value[..pos].trim_end().to_string(),
+        None => value.to_string(),
+    }
+}
+
+/// Mask a value for display, keeping a short hint of its length
+pub fn mask(value: &str) -> String {
+    if value.is_empty() {
+        String::new()
+    } else {
+        "•".repeat(value.chars().count().clamp(4, 12))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dotenv() {
+        let content = r#"
+# Database
+DATABASE_URL=postgres://localhost/dev
+export API_KEY="abc\"123"
+SINGLE='keep $literal'
+DEBUG=true # inline comment
+1INVALID=x
+not a var
+EMPTY=
+"#;
+        let vars = parse(content);
+        assert_eq!(
+            vars,
+            vec![
+                ("DATABASE_URL".to_string(), "postgres://localhost/dev".to_string()),
+                ("API_KEY".to_string(), "abc\"123".to_string()),
+                ("SINGLE".to_string(), "keep $literal".to_string()),
+                ("DEBUG".to_string(), "true".to_string()),
+                ("EMPTY".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mask() {
+        assert_eq!(mask(""), "");
+        assert_eq!(mask("ab"), "••••");
+        assert_eq!(mask("a-very-long-secret-token"), "••••••••••••");
+    }
+}
@@ -0,0 +1,215 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Snapshot of a git repository's state for display in the UI
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GitStatus {
+    pub branch: String,
+    pub is_dirty: bool,
+    pub ahead: u32,
+    pub behind: u32,
+}
+
+impl GitStatus {
+    /// Query git for the status of the repository containing `dir`.
+    /// Returns None if `dir` is not inside a git work tree or git is unavailable.
+    pub fn detect(dir: &Path) -> Option<Self> {
+        let output = Command::new("git")
+            .args(["status", "--porcelain=v2", "--branch"])
+            .current_dir(dir)
+            .output()
+            .ok()?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        Some(Self::parse_porcelain(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// Top-level directory of the repository containing `dir`
+    pub fn repo_root(dir: &Path) -> Option<PathBuf> {
+        let output = Command::new("git")
+            .args(["rev-parse", "--show-toplevel"])
+            .current_dir(dir)
+            .output()
+            .ok()?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        let root = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (!root.is_empty()).then(|| PathBuf::from(root))
+    }
+
+    /// Parse the output of `git status --porcelain=v2 --branch`
+    pub fn parse_porcelain(output: &str) -> Self {
+        let mut status = Self::default();
+        let mut oid = String::new();
+
+        for line in output.lines() {
+            if let Some(head) = line.strip_prefix("# branch.head ") {
+                status.branch = head.to_string();
+            } else if let Some(commit) = line.strip_prefix("# branch.oid ") {
+                oid = commit.to_string();
+            } else if let Some(ab) = line.strip_prefix("# branch.ab ") {
+                for part in ab.split_whitespace() {
+                    if let Some(n) = part.strip_prefix('+') {
+                        status.ahead = n.parse().unwrap_or(0);
+                    } else if let Some(n) = part.strip_prefix('-') {
+                        status.behind = n.parse().unwrap_or(0);
+                    }
+                }
+            } else if !line.starts_with('#') && !line.is_empty() {
+                status.is_dirty = true;
+            }
+        }
+
+        // Detached HEAD: show the short commit hash instead
+        if status.branch == "(detached)" && oid.len() >= 7 {
+            status.branch = oid[..7].to_string();
+        }
+
+        status
+    }
+
+    /// Format for the status bar, e.g. "main* ↑2 ↓1"
+    pub fn summary(&self) -> String {
+        let mut text = self.branch.clone();
+        if self.is_dirty {
+            text.push('*');
+        }
+        if self.ahead > 0 {
+            text.push_str(&format!(" ↑{}", self.ahead));
+        }
+        if self.behind > 0 {
+            text.push_str(&format!(" ↓{}", self.behind));
+        }
+        text
+    }
+}
+
+/// A path with uncommitted changes, with its `git status` codes
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileChange {
+    pub path: String,
+    /// Change in the index, e.g. `M` or `A`; `None` when nothing is staged
+    pub staged: Option<char>,
+    /// Change in the work tree not yet staged; `?` for untracked files
+    pub unstaged: Option<char>,
+}
+
+impl FileChange {
+    pub fn is_untracked(&self) -> bool {
+        self.unstaged == Some('?')
+    }
+}
+
+/// Uncommitted changes in the repository containing `dir`
+pub fn changed_files(dir: &Path) -> Option<Vec<FileChange>> {
+    let output = Command::new("git")
+        .args(["status", "--porcelain=v1", "-z", "--untracked-files=all"])
+        .current_dir(dir)
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(parse_changes(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse `git status --porcelain=v1 -z`: `XY path` entries separated by NUL,
+/// where renames and copies are followed by an entry with the old path
+pub fn parse_changes(output: &str) -> Vec<FileChange> {
+    let code = |c: char| (c != ' ').then_some(c);
+    let mut changes = Vec::new();
+    let mut entries = output.split('\0').filter(|e| !e.is_empty());
+    while let Some(entry) = entries.next() {
+        let mut chars = entry.chars();
+        let (Some(x), Some(y)) = (chars.next(), chars.next()) else {
+            continue;
+        };
+        let Some(path) = entry.get(3..) else {
+            continue;
+        };
+        if matches!(x, 'R' | 'C') {
+            entries.next();
+        }
+        changes.push(if x == '?' {
+            FileChange { path: path.to_string(), staged: None, unstaged: Some('?') }
+        } else {
+            FileChange { path: path.to_string(), staged: code(x), unstaged: code(y) }
+        });
+    }
+    changes
+}
+
+/// Diff of one file, staged or in the work tree, relative to the repository
+/// root `root`. Untracked files are shown as entirely added.
+pub fn file_diff(root: &Path, change: &FileChange, staged: bool) -> Option<String> {
+    let mut command = Command::new("git");
+    command.current_dir(root).arg("diff").arg("--no-color");
+    if change.is_untracked() {
+        command.args(["--no-index", "--", "/dev/null", &change.path]);
+    } else if staged {
+        command.args(["--cached", "--", &change.path]);
+    } else {
+        command.args(["--", &change.path]);
+    }
+    // `--no-index` exits with 1 when the files differ, which they always do
+    let output = command.output().ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_clean_branch() {
+        let output = "# branch.oid 1234567890abcdef\n# branch.head main\n# branch.upstream origin/main\n# branch.ab +0 -0\n";
+        let status = GitStatus::parse_porcelain(output);
+        assert_eq!(status.branch, "main");
+        assert!(!status.is_dirty);
+        assert_eq!(status.summary(), "main");
+    }
+
+    #[test]
+    fn test_parse_dirty_ahead_behind() {
+        let output = "# branch.oid 1234567890abcdef\n# branch.head feature\n# branch.ab +2 -1\n1 .M N... 100644 100644 100644 abc def src/main.rs\n? new_file.txt\n";
+        let status = GitStatus::parse_porcelain(output);
+        assert_eq!(status.branch, "feature");
+        assert!(status.is_dirty);
+        assert_eq!(status.ahead, 2);
+        assert_eq!(status.behind, 1);
+        assert_eq!(status.summary(), "feature* ↑2 ↓1");
+    }
+
+    #[test]
+    fn test_parse_changes() {
+        let output = "M  src/main.rs\0 M README.md\0R  new name.rs\0old name.rs\0?? notes.txt\0MM Cargo.toml\0";
+        let changes = parse_changes(output);
+        let summary: Vec<_> = changes.iter().map(|c| (c.path.as_str(), c.staged, c.unstaged)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("src/main.rs", Some('M'), None),
+                ("README.md", None, Some('M')),
+                ("new name.rs", Some('R'), None),
+                ("notes.txt", None, Some('?')),
+                ("Cargo.toml", Some('M'), Some('M')),
+            ]
+        );
+        assert!(changes[3].is_untracked());
+    }
+
+    #[test]
+    fn test_parse_detached_head() {
+        let output = "# branch.oid 1234567890abcdef\n# branch.head (detached)\n";
+        let status = GitStatus::parse_porcelain(output);
+        assert_eq!(status.branch, "1234567");
+    }
+}
@@ -1,2 +1,200 @@
-// Placeholder for keybindings utilities
-// Will be implemented in Milestone 11
+use crate::config::KeybindingsConfig;
+use egui::{Key, KeyboardShortcut, Modifiers};
+
+/// Something a `[keybindings]` shortcut does. The dispatcher and the
+/// shortcut reference both go through `ALL`, so a new binding shows up in
+/// the reference as soon as it is handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    NewBlock,
+    AiSuggest,
+    Search,
+    History,
+    SplitHorizontal,
+    SplitVertical,
+    ClosePane,
+    Settings,
+    CycleOperationMode,
+    QuickJump,
+    InsertPath,
+    Presentation,
+    Shortcuts,
+}
+
+impl KeyAction {
+    pub const ALL: [KeyAction; 13] = [
+        KeyAction::NewBlock,
+        KeyAction::AiSuggest,
+        KeyAction::CycleOperationMode,
+        KeyAction::QuickJump,
+        KeyAction::InsertPath,
+        KeyAction::Search,
+        KeyAction::History,
+        KeyAction::SplitHorizontal,
+        KeyAction::SplitVertical,
+        KeyAction::ClosePane,
+        KeyAction::Settings,
+        KeyAction::Presentation,
+        KeyAction::Shortcuts,
+    ];
+
+    /// Its name in the `[keybindings]` config
+    pub fn config_key(&self) -> &'static str {
+        match self {
+            KeyAction::NewBlock => "new_block",
+            KeyAction::AiSuggest => "ai_suggest",
+            KeyAction::Search => "search",
+            KeyAction::History => "history",
+            KeyAction::SplitHorizontal => "split_horizontal",
+            KeyAction::SplitVertical => "split_vertical",
+            KeyAction::ClosePane => "close_pane",
+            KeyAction::Settings => "settings",
+            KeyAction::CycleOperationMode => "cycle_operation_mode",
+            KeyAction::QuickJump => "quick_jump",
+            KeyAction::InsertPath => "insert_path",
+            KeyAction::Presentation => "presentation",
+            KeyAction::Shortcuts => "shortcuts",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            KeyAction::NewBlock => "Run the command input",
+            KeyAction::AiSuggest => "Turn the input into a command with AI",
+            KeyAction::Search => "Search blocks",
+            KeyAction::History => "Show the session's command history",
+            KeyAction::SplitHorizontal => "Split pane horizontally",
+            KeyAction::SplitVertical => "Split pane vertically",
+            KeyAction::ClosePane => "Close pane",
+            KeyAction::Settings => "Open settings",
+            KeyAction::CycleOperationMode => "Cycle Terminal → AI → Hybrid mode",
+            KeyAction::QuickJump => "Jump to a directory",
+            KeyAction::InsertPath => "Pick a file and insert its quoted path",
+            KeyAction::Presentation => "Present the session's blocks one at a time",
+            KeyAction::Shortcuts => "Show this shortcut reference",
+        }
+    }
+
+    /// Whether the dispatcher does anything with it yet. Shortcuts that
+    /// aren't handled are left for widgets to use.
+    pub fn is_available(&self) -> bool {
+        !matches!(
+            self,
+            KeyAction::Search | KeyAction::SplitHorizontal | KeyAction::SplitVertical | KeyAction::ClosePane
+        )
+    }
+
+    /// Whether it acts on the command input, and so only fires while the
+    /// input has focus; elsewhere the keys belong to the focused widget
+    pub fn needs_input_focus(&self) -> bool {
+        matches!(self, KeyAction::NewBlock | KeyAction::AiSuggest)
+    }
+
+    /// The shortcut it is bound to, as written in the config
+    pub fn binding<'a>(&self, config: &'a KeybindingsConfig) -> &'a str {
+        match self {
+            KeyAction::NewBlock => &config.new_block,
+            KeyAction::AiSuggest => &config.ai_suggest,
+            KeyAction::Search => &config.search,
+            KeyAction::History => &config.history,
+            KeyAction::SplitHorizontal => &config.split_horizontal,
+            KeyAction::SplitVertical => &config.split_vertical,
+            KeyAction::ClosePane => &config.close_pane,
+            KeyAction::Settings => &config.settings,
+            KeyAction::CycleOperationMode => &config.cycle_operation_mode,
+            KeyAction::QuickJump => &config.quick_jump,
+            KeyAction::InsertPath => &config.insert_path,
+            KeyAction::Presentation => &config.presentation,
+            KeyAction::Shortcuts => &config.shortcuts,
+        }
+    }
+
+    /// Whether the user bound it to something other than the default
+    pub fn is_customized(&self, config: &KeybindingsConfig) -> bool {
+        let defaults = KeybindingsConfig::default();
+        let (binding, default) = (self.binding(config), self.binding(&defaults));
+        match (parse_shortcut(binding), parse_shortcut(default)) {
+            (Some(shortcut), Some(default_shortcut)) => shortcut != default_shortcut,
+            _ => binding != default,
+        }
+    }
+}
+
+/// Keys with a fixed meaning, listed in the shortcut reference after the
+/// configurable ones
+pub const FIXED_SHORTCUTS: &[(&str, &str)] = &[
+    ("Enter", "Run the command input"),
+    ("Up / Down", "Step through command history"),
+    ("Esc", "Leave insert mode (vim mode)"),
+    ("F12", "Toggle the debug overlay"),
+];
+
+/// Whether a shortcut reference row matches a search, by what it does, its
+/// keys or its config name
+pub fn shortcut_matches(query: &str, keys: &str, description: &str, config_key: &str) -> bool {
+    let query = query.trim().to_lowercase();
+    query.is_empty()
+        || [keys, description, config_key].iter().any(|field| field.to_lowercase().contains(&query))
+}
+
+/// Parse a shortcut as written in the `[keybindings]` config, e.g. `Ctrl+Shift+M`
+/// or `Ctrl+,`. `Ctrl` means Cmd on macOS. Returns `None` for anything egui
+/// doesn't know.
+pub fn parse_shortcut(text: &str) -> Option<KeyboardShortcut> {
+    let mut modifiers = Modifiers::NONE;
+    // Split from the right so a trailing `+` can itself be the key
+    let (mods, last) = match text.trim().rsplit_once('+') {
+        Some((mods, "")) => (mods.strip_suffix('+').unwrap_or(mods), "+"),
+        Some((mods, last)) => (mods, last),
+        None => ("", text.trim()),
+    };
+    for part in mods.split('+').map(str::trim).filter(|p| !p.is_empty()) {
+        modifiers = modifiers
+            | match part.to_lowercase().as_str() {
+                "ctrl" | "control" | "cmd" | "command" => Modifiers::COMMAND,
+                "shift" => Modifiers::SHIFT,
+                "alt" | "option" => Modifiers::ALT,
+                _ => return None,
+            };
+    }
+    Key::from_name(last.trim()).map(|key| KeyboardShortcut::new(modifiers, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_shortcut() {
+        assert_eq!(
+            parse_shortcut("Ctrl+Shift+M"),
+            Some(KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::M))
+        );
+        assert_eq!(parse_shortcut("ctrl+,"), Some(KeyboardShortcut::new(Modifiers::COMMAND, Key::Comma)));
+        assert_eq!(parse_shortcut("F12"), Some(KeyboardShortcut::new(Modifiers::NONE, Key::F12)));
+        assert_eq!(parse_shortcut("Ctrl+Nonsense"), None);
+        assert_eq!(parse_shortcut("Hyper+M"), None);
+    }
+
+    #[test]
+    fn test_key_actions() {
+        let mut config = KeybindingsConfig::default();
+        // Every default binding parses, so the dispatcher can handle it
+        for action in KeyAction::ALL {
+            assert!(parse_shortcut(action.binding(&config)).is_some(), "{}", action.config_key());
+            assert!(!action.is_customized(&config));
+        }
+
+        assert!(KeyAction::NewBlock.needs_input_focus());
+        assert!(!KeyAction::Settings.needs_input_focus());
+
+        config.quick_jump = "ctrl+g".to_string();
+        assert!(!KeyAction::QuickJump.is_customized(&config));
+        config.quick_jump = "Alt+J".to_string();
+        assert!(KeyAction::QuickJump.is_customized(&config));
+
+        assert!(shortcut_matches("jump", "Ctrl+G", "Jump to a directory", "quick_jump"));
+        assert!(shortcut_matches("ctrl+g", "Ctrl+G", "Jump to a directory", "quick_jump"));
+        assert!(!shortcut_matches("split", "Ctrl+G", "Jump to a directory", "quick_jump"));
+    }
+}
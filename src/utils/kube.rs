@@ -0,0 +1,143 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// A context entry from kubeconfig
+#[derive(Debug, Clone, PartialEq)]
+pub struct KubeContext {
+    pub name: String,
+    pub cluster: String,
+    pub namespace: Option<String>,
+}
+
+impl KubeContext {
+    /// Namespace kubectl will use for this context
+    pub fn namespace_or_default(&self) -> &str {
+        self.namespace.as_deref().unwrap_or("default")
+    }
+
+    /// Whether the context or its cluster matches any of the (case-insensitive) patterns
+    pub fn is_production(&self, patterns: &[String]) -> bool {
+        let name = self.name.to_lowercase();
+        let cluster = self.cluster.to_lowercase();
+        patterns.iter().any(|p| {
+            let p = p.to_lowercase();
+            !p.is_empty() && (name.contains(&p) || cluster.contains(&p))
+        })
+    }
+}
+
+/// The parts of a kubeconfig needed for display and switching
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KubeConfig {
+    pub current_context: Option<String>,
+    pub contexts: Vec<KubeContext>,
+}
+
+#[derive(Deserialize)]
+struct RawConfig {
+    #[serde(rename = "current-context", default)]
+    current_context: Option<String>,
+    #[serde(default)]
+    contexts: Vec<RawNamedContext>,
+}
+
+#[derive(Deserialize)]
+struct RawNamedContext {
+    name: String,
+    context: RawContext,
+}
+
+#[derive(Deserialize)]
+struct RawContext {
+    #[serde(default)]
+    cluster: String,
+    #[serde(default)]
+    namespace: Option<String>,
+}
+
+impl KubeConfig {
+    /// Path kubectl would read: the first entry of $KUBECONFIG, else ~/.kube/config
+    pub fn default_path(kubeconfig_env: Option<&str>) -> Option<PathBuf> {
+        if let Some(first) = kubeconfig_env.and_then(|v| v.split(':').find(|p| !p.is_empty())) {
+            return Some(PathBuf::from(first));
+        }
+        directories::BaseDirs::new().map(|dirs| dirs.home_dir().join(".kube").join("config"))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(yaml: &str) -> Result<Self> {
+        let raw: RawConfig = serde_yaml::from_str(yaml).context("Invalid kubeconfig")?;
+        Ok(Self {
+            current_context: raw.current_context.filter(|c| !c.is_empty()),
+            contexts: raw
+                .contexts
+                .into_iter()
+                .map(|c| KubeContext {
+                    name: c.name,
+                    cluster: c.context.cluster,
+                    namespace: c.context.namespace.filter(|n| !n.is_empty()),
+                })
+                .collect(),
+        })
+    }
+
+    pub fn current(&self) -> Option<&KubeContext> {
+        let name = self.current_context.as_ref()?;
+        self.contexts.iter().find(|c| &c.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+apiVersion: v1
+kind: Config
+current-context: prod-eu
+clusters:
+  - name: eks-prod-eu
+    cluster:
+      server: https://example.com
+contexts:
+  - name: prod-eu
+    context:
+      cluster: eks-prod-eu
+      namespace: payments
+  - name: minikube
+    context:
+      cluster: minikube
+"#;
+
+    #[test]
+    fn test_parse_kubeconfig() {
+        let config = KubeConfig::parse(CONFIG).unwrap();
+        assert_eq!(config.contexts.len(), 2);
+
+        let current = config.current().unwrap();
+        assert_eq!(current.cluster, "eks-prod-eu");
+        assert_eq!(current.namespace_or_default(), "payments");
+        assert_eq!(config.contexts[1].namespace_or_default(), "default");
+    }
+
+    #[test]
+    fn test_production_patterns() {
+        let config = KubeConfig::parse(CONFIG).unwrap();
+        let patterns = vec!["PROD".to_string()];
+        assert!(config.contexts[0].is_production(&patterns));
+        assert!(!config.contexts[1].is_production(&patterns));
+        assert!(!config.contexts[0].is_production(&[String::new()]));
+    }
+
+    #[test]
+    fn test_default_path_uses_first_kubeconfig_entry() {
+        let path = KubeConfig::default_path(Some("/a/config:/b/config"));
+        assert_eq!(path, Some(PathBuf::from("/a/config")));
+    }
+}
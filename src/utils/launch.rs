@@ -0,0 +1,11 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Open a file or directory with the desktop's default application
+/// (`open` on macOS, `xdg-open` elsewhere). Failures are only logged.
+pub fn open_with_default(path: &Path) {
+    let opener = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+    if let Err(e) = Command::new(opener).arg(path).spawn() {
+        tracing::warn!("Failed to open {}: {}", path.display(), e);
+    }
+}
@@ -0,0 +1,189 @@
+use anyhow::{Context, Result};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::Level;
+
+const LOG_NAME: &str = "immaterium";
+/// Size at which the log is rolled over to a numbered file
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+/// Rolled-over logs kept next to the current one
+const ROLLED_LOGS_KEPT: usize = 3;
+/// Lines kept in memory for the log viewer
+const RECENT_LINES: usize = 5000;
+
+/// The log file set up by `init`, for the in-app viewer
+static LOG_FILE: OnceLock<LogFile> = OnceLock::new();
+
+/// A line written to the log
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub level: Level,
+    pub text: String,
+}
+
+/// Log writer for tracing: appends to a file in the data directory, rolling
+/// it over once it grows past MAX_LOG_BYTES, and keeps the newest lines in
+/// memory for the log viewer
+#[derive(Clone)]
+pub struct LogFile {
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+    recent: VecDeque<LogLine>,
+}
+
+/// Open the log in `dir` and make it available to `recent_lines` and `log_path`
+pub fn init(dir: &Path) -> Result<LogFile> {
+    let log = LogFile::open(dir)?;
+    let _ = LOG_FILE.set(log.clone());
+    Ok(log)
+}
+
+/// Path of the current log file, if logging to a file
+pub fn log_path() -> Option<PathBuf> {
+    LOG_FILE.get().map(|log| log_file_path(&log.inner.lock().unwrap().dir, 0))
+}
+
+/// Run `f` over the lines kept in memory, oldest first
+pub fn with_recent_lines<T>(f: impl FnOnce(&VecDeque<LogLine>) -> T) -> T {
+    match LOG_FILE.get() {
+        Some(log) => f(&log.inner.lock().unwrap().recent),
+        None => f(&VecDeque::new()),
+    }
+}
+
+impl LogFile {
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create log directory {}", dir.display()))?;
+        let path = log_file_path(dir, 0);
+        let file = open_append(&path)?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self {
+            inner: Arc::new(Mutex::new(Inner {
+                dir: dir.to_path_buf(),
+                file,
+                size,
+                recent: VecDeque::new(),
+            })),
+        })
+    }
+}
+
+impl Inner {
+    /// Shift immaterium.log to immaterium.1.log, .1 to .2 and so on, dropping the oldest
+    fn roll_over(&mut self) -> io::Result<()> {
+        for n in (1..ROLLED_LOGS_KEPT).rev() {
+            let from = log_file_path(&self.dir, n);
+            if from.exists() {
+                fs::rename(&from, log_file_path(&self.dir, n + 1))?;
+            }
+        }
+        fs::rename(log_file_path(&self.dir, 0), log_file_path(&self.dir, 1))?;
+        self.file = open_append(&log_file_path(&self.dir, 0)).map_err(io::Error::other)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn remember(&mut self, text: &str) {
+        for line in text.lines().filter(|line| !line.is_empty()) {
+            if self.recent.len() == RECENT_LINES {
+                self.recent.pop_front();
+            }
+            // Continuation lines of a multi-line message keep the level of its first line
+            let level = line_level(line)
+                .or_else(|| self.recent.back().map(|last| last.level))
+                .unwrap_or(Level::INFO);
+            self.recent.push_back(LogLine {
+                level,
+                text: line.to_string(),
+            });
+        }
+    }
+}
+
+impl Write for LogFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut inner = self.inner.lock().map_err(|_| io::Error::other("log lock poisoned"))?;
+        if inner.size + buf.len() as u64 > MAX_LOG_BYTES && inner.size > 0 {
+            inner.roll_over()?;
+        }
+        inner.file.write_all(buf)?;
+        inner.size += buf.len() as u64;
+        inner.remember(&String::from_utf8_lossy(buf));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.lock().map_err(|_| io::Error::other("log lock poisoned"))?.file.flush()
+    }
+}
+
+fn open_append(path: &Path) -> Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open log file {}", path.display()))
+}
+
+/// `immaterium.log` for the current log, `immaterium.<n>.log` for rolled-over ones
+fn log_file_path(dir: &Path, n: usize) -> PathBuf {
+    if n == 0 {
+        dir.join(format!("{}.log", LOG_NAME))
+    } else {
+        dir.join(format!("{}.{}.log", LOG_NAME, n))
+    }
+}
+
+/// Level of a line in tracing's default format: `<timestamp>  INFO target: message`
+fn line_level(line: &str) -> Option<Level> {
+    let mut words = line.split_whitespace();
+    words.next()?;
+    words.next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_lines_are_kept_with_their_level() {
+        let dir = tempdir().unwrap();
+        let mut log = LogFile::open(dir.path()).unwrap();
+        log.write_all(b"2026-01-01T00:00:00.000000Z  WARN immaterium::ui: disk almost full\n").unwrap();
+        log.write_all(b"2026-01-01T00:00:01.000000Z ERROR immaterium::core: save failed\ncaused by: locked\n").unwrap();
+
+        let recent = &log.inner.lock().unwrap().recent;
+        let levels: Vec<_> = recent.iter().map(|l| l.level).collect();
+        assert_eq!(levels, [Level::WARN, Level::ERROR, Level::ERROR]);
+        assert_eq!(recent[2].text, "caused by: locked");
+        let written = fs::read_to_string(log_file_path(dir.path(), 0)).unwrap();
+        assert_eq!(written.lines().count(), 3);
+    }
+
+    #[test]
+    fn test_roll_over() {
+        let dir = tempdir().unwrap();
+        let mut log = LogFile::open(dir.path()).unwrap();
+        let line = vec![b'x'; 1024 * 1024];
+        for _ in 0..6 {
+            log.write_all(&line).unwrap();
+        }
+        assert!(log_file_path(dir.path(), 1).exists());
+        assert!(fs::metadata(log_file_path(dir.path(), 0)).unwrap().len() <= MAX_LOG_BYTES);
+
+        for _ in 0..(ROLLED_LOGS_KEPT + 1) * 5 {
+            log.write_all(&line).unwrap();
+        }
+        assert!(log_file_path(dir.path(), ROLLED_LOGS_KEPT).exists());
+        assert!(!log_file_path(dir.path(), ROLLED_LOGS_KEPT + 1).exists());
+    }
+}
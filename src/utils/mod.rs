@@ -1,4 +1,19 @@
 // Utility modules
 
+pub mod calc;
+pub mod direnv;
+pub mod dotenv;
+pub mod git;
+pub mod kube;
+pub mod launch;
+pub mod logging;
+pub mod notify;
+pub mod path;
+pub mod runtime_env;
+pub mod ssh_config;
 pub mod syntax;
 pub mod keybindings;
+
+pub use git::GitStatus;
+pub use path::{abbreviate_home, expand_tilde, insertable_path, shell_escape};
+pub use runtime_env::ProjectRuntimes;
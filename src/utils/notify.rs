@@ -0,0 +1,26 @@
+use std::process::Command;
+
+/// Show a desktop notification using the platform's command-line notifier
+/// (notify-send on Linux, osascript on macOS). Failures are only logged.
+pub fn send(title: &str, body: &str) {
+    let result = if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification {} with title {}",
+            applescript_string(body),
+            applescript_string(title)
+        );
+        Command::new("osascript").args(["-e", &script]).spawn()
+    } else {
+        Command::new("notify-send")
+            .args(["--app-name=Immaterium", title, body])
+            .spawn()
+    };
+
+    if let Err(e) = result {
+        tracing::warn!("Failed to show notification '{}': {}", title, e);
+    }
+}
+
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
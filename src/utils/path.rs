@@ -0,0 +1,90 @@
+use std::path::{Path, PathBuf};
+
+/// Render a path for display, replacing the home directory prefix with `~`
+pub fn abbreviate_home(path: &Path) -> String {
+    if let Some(home) = directories::BaseDirs::new().map(|d| d.home_dir().to_path_buf()) {
+        if let Ok(rest) = path.strip_prefix(&home) {
+            if rest.as_os_str().is_empty() {
+                return "~".to_string();
+            }
+            return format!("~/{}", rest.display());
+        }
+    }
+    path.display().to_string()
+}
+
+/// Expand a leading `~` in user-entered paths to the home directory
+pub fn expand_tilde(path: &str) -> PathBuf {
+    let home = directories::BaseDirs::new().map(|d| d.home_dir().to_path_buf());
+    match (path, home) {
+        ("~", Some(home)) => home,
+        (p, Some(home)) if p.starts_with("~/") => home.join(&p[2..]),
+        (p, _) => PathBuf::from(p),
+    }
+}
+
+/// Quote a path for pasting into a shell command line; paths of only safe
+/// characters are left as they are
+pub fn shell_escape(path: &str) -> String {
+    let safe = !path.is_empty()
+        && path.chars().all(|c| c.is_ascii_alphanumeric() || "_-./+,:@%=".contains(c));
+    if safe {
+        path.to_string()
+    } else {
+        format!("'{}'", path.replace('\'', "'\\''"))
+    }
+}
+
+/// Text for a path inserted into the command line: relative to `cwd` when it
+/// is inside it, then shell-escaped. Relative paths that start with `-` get
+/// a `./` so they aren't read as options.
+pub fn insertable_path(path: &Path, cwd: &Path) -> String {
+    let shown = match path.strip_prefix(cwd) {
+        Ok(rest) if rest.as_os_str().is_empty() => ".".to_string(),
+        Ok(rest) if rest.to_string_lossy().starts_with('-') => format!("./{}", rest.to_string_lossy()),
+        Ok(rest) => rest.to_string_lossy().into_owned(),
+        Err(_) => path.to_string_lossy().into_owned(),
+    };
+    shell_escape(&shown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_abbreviate_home() {
+        let home = directories::BaseDirs::new().unwrap().home_dir().to_path_buf();
+        assert_eq!(abbreviate_home(&home), "~");
+        assert_eq!(abbreviate_home(&home.join("dev/project")), "~/dev/project");
+    }
+
+    #[test]
+    fn test_expand_tilde() {
+        let home = directories::BaseDirs::new().unwrap().home_dir().to_path_buf();
+        assert_eq!(expand_tilde("~/dev"), home.join("dev"));
+        assert_eq!(expand_tilde("/tmp/x"), PathBuf::from("/tmp/x"));
+    }
+
+    #[test]
+    fn test_non_home_path_unchanged() {
+        assert_eq!(abbreviate_home(Path::new("/definitely/not/home")), "/definitely/not/home");
+    }
+
+    #[test]
+    fn test_shell_escape() {
+        assert_eq!(shell_escape("/tmp/build.log"), "/tmp/build.log");
+        assert_eq!(shell_escape("/tmp/my file.txt"), "'/tmp/my file.txt'");
+        assert_eq!(shell_escape("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn test_insertable_path() {
+        let cwd = Path::new("/home/me/project");
+        assert_eq!(insertable_path(Path::new("/home/me/project/src/main.rs"), cwd), "src/main.rs");
+        assert_eq!(insertable_path(Path::new("/home/me/project"), cwd), ".");
+        assert_eq!(insertable_path(Path::new("/home/me/project/-rf"), cwd), "./-rf");
+        assert_eq!(insertable_path(Path::new("/home/me/project/Über Café.md"), cwd), "'Über Café.md'");
+        assert_eq!(insertable_path(Path::new("/tmp/it's here"), cwd), "'/tmp/it'\\''s here'");
+    }
+}
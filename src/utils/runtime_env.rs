@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Directories checked for a project-local Python virtualenv
+const VENV_DIRS: [&str; 3] = [".venv", "venv", "env"];
+
+/// Commands that should run inside the project's virtualenv
+const PYTHON_COMMANDS: [&str; 5] = ["pip", "pip3", "python", "python3", "pytest"];
+
+/// Commands that should run with the project's Node version
+const NODE_COMMANDS: [&str; 5] = ["node", "npm", "npx", "yarn", "pnpm"];
+
+/// What the project around a directory expects to run with, from files in
+/// it or its parents
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProjectRuntimes {
+    /// A virtualenv inside the project, e.g. `.venv`
+    pub python_venv: Option<PathBuf>,
+    /// Node version pinned by `.nvmrc`, `.node-version` or asdf's `.tool-versions`
+    pub node_version: Option<String>,
+    /// The file that pinned `node_version`
+    pub node_version_file: Option<PathBuf>,
+}
+
+impl ProjectRuntimes {
+    /// Look in `dir` and its parents; the nearest marker of each kind wins
+    pub fn detect(dir: &Path) -> Self {
+        let mut runtimes = Self::default();
+        for dir in dir.ancestors() {
+            if runtimes.python_venv.is_none() {
+                runtimes.python_venv = VENV_DIRS
+                    .iter()
+                    .map(|name| dir.join(name))
+                    .find(|venv| venv.join("pyvenv.cfg").is_file());
+            }
+            if runtimes.node_version.is_none() {
+                if let Some((version, file)) = pinned_node_version(dir) {
+                    runtimes.node_version = Some(version);
+                    runtimes.node_version_file = Some(file);
+                }
+            }
+            if runtimes.python_venv.is_some() && runtimes.node_version.is_some() {
+                break;
+            }
+        }
+        runtimes
+    }
+}
+
+fn pinned_node_version(dir: &Path) -> Option<(String, PathBuf)> {
+    for name in [".nvmrc", ".node-version"] {
+        let file = dir.join(name);
+        if let Ok(contents) = std::fs::read_to_string(&file) {
+            if let Some(version) = contents.lines().map(str::trim).find(|l| !l.is_empty() && !l.starts_with('#')) {
+                return Some((version.to_string(), file));
+            }
+        }
+    }
+    let file = dir.join(".tool-versions");
+    let contents = std::fs::read_to_string(&file).ok()?;
+    let version = contents.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        (parts.next()? == "nodejs").then(|| parts.next().map(str::to_string))?
+    })?;
+    Some((version, file))
+}
+
+/// Look up a variable the way a command will see it: the app's own
+/// environment first, then the process environment
+fn lookup(environment: &HashMap<String, String>, key: &str) -> Option<String> {
+    environment
+        .get(key)
+        .cloned()
+        .or_else(|| std::env::var(key).ok())
+        .filter(|v| !v.is_empty())
+}
+
+/// Name of the active Python virtualenv or conda environment, if any.
+/// Session environment takes precedence over the process environment.
+pub fn active_venv(environment: &HashMap<String, String>) -> Option<String> {
+    if let Some(venv) = lookup(environment, "VIRTUAL_ENV") {
+        return Path::new(&venv)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(|n| n.to_string());
+    }
+
+    lookup(environment, "CONDA_DEFAULT_ENV")
+}
+
+/// Node version selected by nvm or asdf, if it can be told from the environment
+pub fn active_node_version(environment: &HashMap<String, String>) -> Option<String> {
+    if let Some(version) = lookup(environment, "ASDF_NODEJS_VERSION") {
+        return Some(version);
+    }
+    // nvm puts the selected version's bin directory, .../node/v18.17.0/bin, in NVM_BIN
+    let bin = lookup(environment, "NVM_BIN")?;
+    Path::new(&bin)
+        .parent()?
+        .file_name()?
+        .to_str()
+        .map(str::to_string)
+}
+
+/// Whether `active` satisfies a pinned version: `18` and `v18.17` both
+/// accept `v18.17.0`. Aliases like `lts/*` can't be checked and always pass.
+pub fn node_version_matches(pinned: &str, active: &str) -> bool {
+    let pinned = pinned.trim_start_matches('v');
+    let active = active.trim_start_matches('v');
+    if !pinned.starts_with(|c: char| c.is_ascii_digit()) {
+        return true;
+    }
+    active == pinned || active.starts_with(&format!("{}.", pinned))
+}
+
+/// Whether `program`, given with a path and resolved against
+/// `working_directory`, lives inside `venv`, e.g. `.venv/bin/pip`
+fn runs_from(program: &str, venv: &Path, working_directory: &Path) -> bool {
+    if !program.contains('/') {
+        return false;
+    }
+    let path = working_directory.join(program);
+    let path = path.canonicalize().unwrap_or(path);
+    let venv = venv.canonicalize().unwrap_or_else(|_| venv.to_path_buf());
+    path.starts_with(venv)
+}
+
+/// Why `command`, run in `working_directory`, would run without the
+/// environment the project expects, if it would
+pub fn runtime_warning(
+    command: &str,
+    project: &ProjectRuntimes,
+    environment: &HashMap<String, String>,
+    working_directory: &Path,
+) -> Option<String> {
+    let path = command.split_whitespace().next()?;
+    let program = path.rsplit('/').next().unwrap_or(path);
+
+    if PYTHON_COMMANDS.contains(&program) {
+        let venv = project.python_venv.as_ref()?;
+        // The venv's own interpreter runs in it whether or not it is activated
+        if runs_from(path, venv, working_directory) {
+            return None;
+        }
+        let active = lookup(environment, "VIRTUAL_ENV");
+        if active.as_deref().map(Path::new) != Some(venv.as_path()) {
+            let name = venv.file_name().and_then(|n| n.to_str()).unwrap_or(".venv");
+            return Some(match active {
+                Some(other) => format!("{} runs in {}, not the project's {}", program, other, name),
+                None => format!("{} runs outside the project's virtualenv ({}); activate it first", program, name),
+            });
+        }
+    }
+
+    if NODE_COMMANDS.contains(&program) {
+        let pinned = project.node_version.as_deref()?;
+        let active = active_node_version(environment)?;
+        if !node_version_matches(pinned, &active) {
+            return Some(format!("{} runs with Node {}, but the project pins {}", program, active, pinned));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_detect_project_runtimes() {
+        let root = tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join(".venv")).unwrap();
+        std::fs::write(root.path().join(".venv/pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+        std::fs::write(root.path().join(".tool-versions"), "python 3.11.4\nnodejs 18.17.0\n").unwrap();
+        let sub = root.path().join("web");
+        std::fs::create_dir_all(&sub).unwrap();
+        std::fs::write(sub.join(".nvmrc"), "v20\n").unwrap();
+
+        let runtimes = ProjectRuntimes::detect(&sub);
+        assert_eq!(runtimes.python_venv, Some(root.path().join(".venv")));
+        assert_eq!(runtimes.node_version.as_deref(), Some("v20"));
+
+        let runtimes = ProjectRuntimes::detect(root.path());
+        assert_eq!(runtimes.node_version.as_deref(), Some("18.17.0"));
+    }
+
+    #[test]
+    fn test_runtime_warning() {
+        let project = ProjectRuntimes {
+            python_venv: Some(PathBuf::from("/work/app/.venv")),
+            node_version: Some("18".to_string()),
+            node_version_file: None,
+        };
+        let mut environment = HashMap::from([
+            ("VIRTUAL_ENV".to_string(), "/work/app/.venv".to_string()),
+            ("NVM_BIN".to_string(), "/home/me/.nvm/versions/node/v18.17.0/bin".to_string()),
+        ]);
+        let cwd = Path::new("/work/app");
+        assert_eq!(runtime_warning("pip install -r requirements.txt", &project, &environment, cwd), None);
+        assert_eq!(runtime_warning("npm test", &project, &environment, cwd), None);
+
+        environment.insert("VIRTUAL_ENV".to_string(), "/other/.venv".to_string());
+        environment.insert("NVM_BIN".to_string(), "/home/me/.nvm/versions/node/v20.1.0/bin".to_string());
+        assert!(runtime_warning("pip install flask", &project, &environment, cwd).is_some());
+        assert!(runtime_warning("npm install", &project, &environment, cwd).unwrap().contains("v20.1.0"));
+        assert_eq!(runtime_warning("ls", &project, &environment, cwd), None);
+
+        assert!(node_version_matches("lts/*", "v20.1.0"));
+        assert!(!node_version_matches("18.1", "v18.17.0"));
+    }
+
+    #[test]
+    fn test_venv_interpreter_by_path() {
+        let root = tempdir().unwrap();
+        let venv = root.path().join(".venv");
+        std::fs::create_dir_all(venv.join("bin")).unwrap();
+        std::fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+        std::fs::write(venv.join("bin/pip"), "").unwrap();
+        let project = ProjectRuntimes::detect(root.path());
+        let environment = HashMap::new();
+
+        // Not activated, but run straight from the project's virtualenv
+        assert_eq!(runtime_warning(".venv/bin/pip install flask", &project, &environment, root.path()), None);
+        let absolute = format!("{} install flask", venv.join("bin/pip").display());
+        assert_eq!(runtime_warning(&absolute, &project, &environment, root.path()), None);
+        assert_eq!(runtime_warning("./.venv/bin/pip list", &project, &environment, root.path()), None);
+
+        assert!(runtime_warning("pip install flask", &project, &environment, root.path()).is_some());
+        assert!(runtime_warning("/usr/bin/pip install flask", &project, &environment, root.path()).is_some());
+    }
+}
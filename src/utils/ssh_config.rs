@@ -0,0 +1,161 @@
+use std::path::Path;
+
+/// A concrete host alias from ~/.ssh/config
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SshHost {
+    pub alias: String,
+    pub hostname: Option<String>,
+    pub user: Option<String>,
+    pub port: Option<u16>,
+}
+
+impl SshHost {
+    /// Command that connects to the host
+    pub fn ssh_command(&self) -> String {
+        format!("ssh {}", self.alias)
+    }
+
+    /// "user@hostname:port" style description, omitting unknown parts
+    pub fn target(&self) -> String {
+        let mut target = String::new();
+        if let Some(ref user) = self.user {
+            target.push_str(user);
+            target.push('@');
+        }
+        target.push_str(self.hostname.as_deref().unwrap_or(&self.alias));
+        if let Some(port) = self.port {
+            target.push_str(&format!(":{}", port));
+        }
+        target
+    }
+
+    /// Case-insensitive match against alias and hostname
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.alias.to_lowercase().contains(&query)
+            || self
+                .hostname
+                .as_ref()
+                .map(|h| h.to_lowercase().contains(&query))
+                .unwrap_or(false)
+    }
+}
+
+/// Load hosts from the user's ~/.ssh/config (empty if missing)
+pub fn load_user_hosts() -> Vec<SshHost> {
+    directories::BaseDirs::new()
+        .map(|dirs| dirs.home_dir().join(".ssh").join("config"))
+        .map(|path| load(&path))
+        .unwrap_or_default()
+}
+
+pub fn load(path: &Path) -> Vec<SshHost> {
+    std::fs::read_to_string(path)
+        .map(|content| parse(&content))
+        .unwrap_or_default()
+}
+
+/// Parse ssh_config host blocks. Wildcard patterns (`*`, `?`, `!`) are not
+/// connectable aliases and are skipped; `Match` blocks are ignored.
+pub fn parse(content: &str) -> Vec<SshHost> {
+    let mut hosts: Vec<SshHost> = Vec::new();
+    // Indices into `hosts` that the current block's options apply to
+    let mut current: Vec<usize> = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (keyword, value) = match line.split_once(|c: char| c.is_whitespace() || c == '=') {
+            Some((k, v)) => (k.to_lowercase(), v.trim_start_matches(|c: char| c.is_whitespace() || c == '=').trim()),
+            None => continue,
+        };
+
+        match keyword.as_str() {
+            "host" => {
+                current.clear();
+                for pattern in value.split_whitespace() {
+                    if pattern.contains(['*', '?', '!']) {
+                        continue;
+                    }
+                    let index = match hosts.iter().position(|h| h.alias == pattern) {
+                        Some(index) => index,
+                        None => {
+                            hosts.push(SshHost {
+                                alias: pattern.to_string(),
+                                ..Default::default()
+                            });
+                            hosts.len() - 1
+                        }
+                    };
+                    current.push(index);
+                }
+            }
+            "match" => current.clear(),
+            // First obtained value wins, as in ssh itself
+            "hostname" => {
+                for &i in &current {
+                    hosts[i].hostname.get_or_insert_with(|| value.to_string());
+                }
+            }
+            "user" => {
+                for &i in &current {
+                    hosts[i].user.get_or_insert_with(|| value.to_string());
+                }
+            }
+            "port" => {
+                if let Ok(port) = value.parse() {
+                    for &i in &current {
+                        hosts[i].port.get_or_insert(port);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    hosts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ssh_config() {
+        let content = "\
+Host *
+    ServerAliveInterval 60
+
+# Work machines
+Host bastion prod-*
+    HostName bastion.example.com
+    User deploy
+    Port 2222
+
+Host dev devbox
+    HostName=10.0.0.5
+";
+        let hosts = parse(content);
+        assert_eq!(hosts.len(), 3);
+        assert_eq!(hosts[0].alias, "bastion");
+        assert_eq!(hosts[0].target(), "deploy@bastion.example.com:2222");
+        assert_eq!(hosts[1].hostname.as_deref(), Some("10.0.0.5"));
+        assert_eq!(hosts[2].alias, "devbox");
+        assert_eq!(hosts[2].ssh_command(), "ssh devbox");
+    }
+
+    #[test]
+    fn test_host_matches() {
+        let host = SshHost {
+            alias: "Bastion".to_string(),
+            hostname: Some("jump.example.com".to_string()),
+            ..Default::default()
+        };
+        assert!(host.matches("bast"));
+        assert!(host.matches("example"));
+        assert!(!host.matches("prod"));
+    }
+}
@@ -0,0 +1,95 @@
+// Reusable command workflows with {{argument}} placeholders
+// Stored as YAML (Warp's workflow format) in the config directory
+
+pub mod store;
+pub mod warp;
+
+pub use store::WorkflowStore;
+pub use warp::{import_path, parse_warp_yaml};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Workflow {
+    pub name: String,
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<WorkflowArgument>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowArgument {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_value: Option<String>,
+}
+
+impl Workflow {
+    /// Substitute `{{argument}}` placeholders. Arguments without a value fall
+    /// back to their default; if there is none the placeholder is left in place.
+    pub fn render(&self, values: &[(String, String)]) -> String {
+        let mut command = self.command.clone();
+        for argument in &self.arguments {
+            let value = values
+                .iter()
+                .find(|(name, _)| name == &argument.name)
+                .map(|(_, v)| v.clone())
+                .or_else(|| argument.default_value.clone());
+            if let Some(value) = value {
+                command = command.replace(&format!("{{{{{}}}}}", argument.name), &value);
+            }
+        }
+        command
+    }
+
+    /// Filename-safe identifier derived from the name
+    pub fn slug(&self) -> String {
+        let slug: String = self
+            .name
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let slug = slug.trim_matches('_').to_string();
+        if slug.is_empty() {
+            "workflow".to_string()
+        } else {
+            slug
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_uses_values_then_defaults() {
+        let workflow = Workflow {
+            name: "Tail logs".to_string(),
+            command: "kubectl logs -n {{namespace}} {{pod}} --tail={{lines}}".to_string(),
+            description: None,
+            tags: Vec::new(),
+            arguments: vec![
+                WorkflowArgument { name: "namespace".to_string(), description: None, default_value: Some("default".to_string()) },
+                WorkflowArgument { name: "pod".to_string(), description: None, default_value: None },
+                WorkflowArgument { name: "lines".to_string(), description: None, default_value: Some("100".to_string()) },
+            ],
+            source_url: None,
+        };
+
+        assert_eq!(
+            workflow.render(&[("lines".to_string(), "20".to_string())]),
+            "kubectl logs -n default {{pod}} --tail=20"
+        );
+        assert_eq!(workflow.slug(), "tail_logs");
+    }
+}
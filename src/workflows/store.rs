@@ -0,0 +1,90 @@
+use super::{parse_warp_yaml, Workflow};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Workflows saved as one YAML file each in a directory
+pub struct WorkflowStore {
+    dir: PathBuf,
+    workflows: Vec<Workflow>,
+}
+
+impl WorkflowStore {
+    /// Store in the config directory (`<config>/workflows`)
+    pub fn open_default() -> Option<Self> {
+        let dirs = directories::ProjectDirs::from("com", "immaterium", "immaterium")?;
+        Some(Self::open(dirs.config_dir().join("workflows")))
+    }
+
+    pub fn open(dir: PathBuf) -> Self {
+        let mut store = Self {
+            dir,
+            workflows: Vec::new(),
+        };
+        store.reload();
+        store
+    }
+
+    pub fn workflows(&self) -> &[Workflow] {
+        &self.workflows
+    }
+
+    /// Re-read all workflows from disk, sorted by name
+    pub fn reload(&mut self) {
+        self.workflows.clear();
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return;
+        };
+
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some("yaml") {
+                continue;
+            }
+            match std::fs::read_to_string(&path).map_err(anyhow::Error::from).and_then(|c| parse_warp_yaml(&c)) {
+                Ok(workflows) => self.workflows.extend(workflows),
+                Err(e) => tracing::warn!("Failed to load workflow {:?}: {}", path, e),
+            }
+        }
+        self.workflows.sort_by_key(|w| w.name.to_lowercase());
+    }
+
+    /// Save workflows into the store, returning how many were written.
+    /// Workflows with the same name replace the existing file.
+    pub fn add(&mut self, workflows: &[Workflow]) -> Result<usize> {
+        std::fs::create_dir_all(&self.dir).context("Failed to create workflows directory")?;
+
+        for workflow in workflows {
+            let yaml = serde_yaml::to_string(workflow).context("Failed to serialize workflow")?;
+            let path = self.path_for(workflow);
+            std::fs::write(&path, yaml).with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+
+        self.reload();
+        Ok(workflows.len())
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path_for(&self, workflow: &Workflow) -> PathBuf {
+        self.dir.join(format!("{}.yaml", workflow.slug()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_and_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = WorkflowStore::open(dir.path().join("workflows"));
+        assert!(store.workflows().is_empty());
+
+        let workflows = parse_warp_yaml("name: List ports\ncommand: ss -tlnp\n").unwrap();
+        assert_eq!(store.add(&workflows).unwrap(), 1);
+
+        let reopened = WorkflowStore::open(dir.path().join("workflows"));
+        assert_eq!(reopened.workflows(), workflows.as_slice());
+    }
+}
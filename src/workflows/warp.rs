@@ -0,0 +1,95 @@
+use super::Workflow;
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Parse a Warp workflow YAML document. Warp files hold a single workflow,
+/// but lists of workflows are accepted too.
+pub fn parse_warp_yaml(yaml: &str) -> Result<Vec<Workflow>> {
+    let value: serde_yaml::Value = serde_yaml::from_str(yaml).context("Invalid YAML")?;
+    let workflows = match value {
+        serde_yaml::Value::Sequence(_) => serde_yaml::from_value::<Vec<Workflow>>(value),
+        _ => serde_yaml::from_value::<Workflow>(value).map(|w| vec![w]),
+    }
+    .context("Not a Warp workflow")?;
+
+    Ok(workflows
+        .into_iter()
+        .filter(|w| !w.command.trim().is_empty())
+        .collect())
+}
+
+/// Import workflows from a Warp workflow file or a directory of them
+/// (searched recursively). Files that fail to parse are skipped with a warning.
+pub fn import_path(path: &Path) -> Result<Vec<Workflow>> {
+    if path.is_file() {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        return parse_warp_yaml(&content).with_context(|| format!("Failed to import {}", path.display()));
+    }
+
+    let mut workflows = Vec::new();
+    for entry in std::fs::read_dir(path).with_context(|| format!("Failed to read {}", path.display()))? {
+        let entry_path = entry?.path();
+        let is_yaml = matches!(
+            entry_path.extension().and_then(|e| e.to_str()),
+            Some("yaml") | Some("yml")
+        );
+
+        if entry_path.is_dir() {
+            workflows.extend(import_path(&entry_path)?);
+        } else if is_yaml {
+            match import_path(&entry_path) {
+                Ok(imported) => workflows.extend(imported),
+                Err(e) => tracing::warn!("{:#}", e),
+            }
+        }
+    }
+
+    Ok(workflows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_warp_workflow() {
+        let yaml = r#"
+---
+name: Uncompress a tar file
+command: "tar -xvf {{tar_file}} -C {{directory}}"
+tags:
+  - tar
+description: Extracts a tar archive into a directory
+arguments:
+  - name: tar_file
+    description: The archive to extract
+    default_value: ~
+  - name: directory
+    description: Target directory
+    default_value: "."
+source_url: "https://example.com"
+author: someone
+shells: []
+"#;
+        let workflows = parse_warp_yaml(yaml).unwrap();
+        assert_eq!(workflows.len(), 1);
+        let workflow = &workflows[0];
+        assert_eq!(workflow.arguments.len(), 2);
+        assert_eq!(workflow.arguments[0].default_value, None);
+        assert_eq!(workflow.render(&[]), "tar -xvf {{tar_file}} -C .");
+    }
+
+    #[test]
+    fn test_import_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("git")).unwrap();
+        std::fs::write(dir.path().join("git/undo.yaml"), "name: Undo commit\ncommand: git reset HEAD~1\n").unwrap();
+        std::fs::write(dir.path().join("broken.yml"), "name: [").unwrap();
+        std::fs::write(dir.path().join("README.md"), "# not a workflow").unwrap();
+
+        let workflows = import_path(dir.path()).unwrap();
+        assert_eq!(workflows.len(), 1);
+        assert_eq!(workflows[0].command, "git reset HEAD~1");
+    }
+}
@@ -17,7 +17,7 @@ fn create_sample_blocks() -> Vec<Block> {
             id: Uuid::new_v4(),
             timestamp: Utc::now(),
             command: "ls -la".to_string(),
-            output: "total 24\ndrwxr-xr-x  3 user user 4096 Oct 29 10:30 .\ndrwxr-xr-x 10 user user 4096 Oct 29 09:15 ..\n-rw-r--r--  1 user user  220 Oct 29 09:15 .bashrc\n-rw-r--r--  1 user user  807 Oct 29 09:15 .profile".to_string(),
+            output: "total 24\ndrwxr-xr-x  3 user user 4096 Oct 29 10:30 .\ndrwxr-xr-x 10 user user 4096 Oct 29 09:15 ..\n-rw-r--r--  1 user user  220 Oct 29 09:15 .bashrc\n-rw-r--r--  1 user user  807 Oct 29 09:15 .profile".into(),
             exit_code: Some(0),
             state: BlockState::Completed,
             metadata: BlockMetadata {
@@ -26,15 +26,26 @@ fn create_sample_blocks() -> Vec<Block> {
                 environment: HashMap::new(),
                 started_at: Some(Utc::now()),
                 completed_at: Some(Utc::now()),
+                hooks: Vec::new(),
+                ai_provenance: None,
             },
             is_collapsed: false,
             is_selected: false,
+            original_input: None,
+            is_pinned: false,
+            is_startup: false,
+            highlighted_lines: Vec::new(),
+            tags: Vec::new(),
+            is_divider: false,
+            is_calculation: false,
+            in_ai_context: false,
+            pending_carriage_return: false,
         },
         Block {
             id: Uuid::new_v4(),
             timestamp: Utc::now(),
             command: "cat .bashrc".to_string(),
-            output: "# ~/.bashrc: executed by bash(1) for non-login shells.\n\n# If not running interactively, don't do anything\ncase $- in\n    *i*) ;;\n      *) return;;\nesac\n\n# enable color support\nif [ -x /usr/bin/dircolors ]; then\n    test -r ~/.dircolors && eval \"$(dircolors -b ~/.dircolors)\" || eval \"$(dircolors -b)\"\n    alias ls='ls --color=auto'\nfi".to_string(),
+            output: "# ~/.bashrc: executed by bash(1) for non-login shells.\n\n# If not running interactively, don't do anything\ncase $- in\n    *i*) ;;\n      *) return;;\nesac\n\n# enable color support\nif [ -x /usr/bin/dircolors ]; then\n    test -r ~/.dircolors && eval \"$(dircolors -b ~/.dircolors)\" || eval \"$(dircolors -b)\"\n    alias ls='ls --color=auto'\nfi".into(),
             exit_code: Some(0),
             state: BlockState::Completed,
             metadata: BlockMetadata {
@@ -43,15 +54,26 @@ fn create_sample_blocks() -> Vec<Block> {
                 environment: HashMap::new(),
                 started_at: Some(Utc::now()),
                 completed_at: Some(Utc::now()),
+                hooks: Vec::new(),
+                ai_provenance: None,
             },
             is_collapsed: false,
             is_selected: false,
+            original_input: None,
+            is_pinned: false,
+            is_startup: false,
+            highlighted_lines: Vec::new(),
+            tags: Vec::new(),
+            is_divider: false,
+            is_calculation: false,
+            in_ai_context: false,
+            pending_carriage_return: false,
         },
         Block {
             id: Uuid::new_v4(),
             timestamp: Utc::now(),
             command: "grep -n 'alias' .bashrc".to_string(),
-            output: "11:    alias ls='ls --color=auto'\n".to_string(),
+            output: "11:    alias ls='ls --color=auto'\n".into(),
             exit_code: Some(0),
             state: BlockState::Completed,
             metadata: BlockMetadata {
@@ -60,9 +82,20 @@ fn create_sample_blocks() -> Vec<Block> {
                 environment: HashMap::new(),
                 started_at: Some(Utc::now()),
                 completed_at: Some(Utc::now()),
+                hooks: Vec::new(),
+                ai_provenance: None,
             },
             is_collapsed: false,
             is_selected: false,
+            original_input: None,
+            is_pinned: false,
+            is_startup: false,
+            highlighted_lines: Vec::new(),
+            tags: Vec::new(),
+            is_divider: false,
+            is_calculation: false,
+            in_ai_context: false,
+            pending_carriage_return: false,
         },
     ]
 }
@@ -203,7 +236,7 @@ async fn test_token_budget_management() {
             id: Uuid::new_v4(),
             timestamp: Utc::now(),
             command: format!("echo 'Command number {}'", i),
-            output: format!("Command number {}\n", i),
+            output: format!("Command number {}\n", i).into(),
             exit_code: Some(0),
             state: BlockState::Completed,
             metadata: BlockMetadata {
@@ -212,9 +245,20 @@ async fn test_token_budget_management() {
                 environment: HashMap::new(),
                 started_at: Some(Utc::now()),
                 completed_at: Some(Utc::now()),
+                hooks: Vec::new(),
+                ai_provenance: None,
             },
             is_collapsed: false,
             is_selected: false,
+            original_input: None,
+            is_pinned: false,
+            is_startup: false,
+            highlighted_lines: Vec::new(),
+            tags: Vec::new(),
+            is_divider: false,
+            is_calculation: false,
+            in_ai_context: false,
+            pending_carriage_return: false,
         });
     }
 